@@ -0,0 +1,204 @@
+//! Benchmarks of every step of the decoding pipeline separately, and of complete decodes
+//!
+//! Run with `cargo bench --bench stages`, these work on the stable toolchain. Every step runs on the output of the
+//! previous steps for the same image, computed once up front, so a regression shows up in the step that caused it.
+//!
+//! The error correction of a version 40 code runs with `--features encode,test-util`, add `parallel` to compare the
+//! correction of its blocks side by side with correcting them one after the other.
+
+use bardecoder::decode::{Decode, QRDecoder};
+use bardecoder::detect::{Detect, LineScan, Location};
+use bardecoder::extract::{Extract, QRExtractor};
+use bardecoder::prepare::{BlockedMean, Prepare};
+use bardecoder::util::qr::{QRData, QRLocation};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use image::{DynamicImage, GrayImage};
+
+const IMAGES: [&str; 4] = [
+    "version1_example.jpg",
+    "version3_example2.jpg",
+    "needs_alignment.jpg",
+    "multiple_codes.png",
+];
+
+fn open(name: &str) -> DynamicImage {
+    image::open(format!("tests/images/{name}")).expect("Failed to open benchmark image")
+}
+
+fn prepared(img: &DynamicImage) -> GrayImage {
+    BlockedMean::new(5, 7).prepare(img)
+}
+
+fn locations(prepared: &GrayImage) -> Vec<QRLocation> {
+    LineScan::new()
+        .detect(prepared)
+        .into_iter()
+        .map(|location| match location {
+            Location::QR(qrloc) => qrloc,
+        })
+        .collect()
+}
+
+fn extracted(prepared: &GrayImage) -> Vec<QRData> {
+    locations(prepared)
+        .into_iter()
+        .filter_map(|qrloc| QRExtractor::new().extract(prepared, qrloc).ok())
+        .collect()
+}
+
+fn prepare(c: &mut Criterion) {
+    let mut group = c.benchmark_group("prepare");
+    let blocked_mean = BlockedMean::new(5, 7);
+
+    for name in &IMAGES {
+        let img = open(name);
+        group.bench_function(*name, |b| b.iter(|| blocked_mean.prepare(&img)));
+    }
+
+    group.finish();
+}
+
+fn detect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("detect");
+    let line_scan = LineScan::new();
+
+    for name in &IMAGES {
+        let prepared = prepared(&open(name));
+        group.bench_function(*name, |b| b.iter(|| line_scan.detect(&prepared)));
+    }
+
+    group.finish();
+}
+
+fn extract(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extract");
+    let extractor = QRExtractor::new();
+
+    for name in &IMAGES {
+        let prepared = prepared(&open(name));
+        let locations = locations(&prepared);
+
+        // QRLocation is consumed by the extractor, so every iteration gets a fresh copy of the locations
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                || locations.clone(),
+                |locations| {
+                    for qrloc in locations {
+                        let _ = extractor.extract(&prepared, qrloc);
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// Error correction and decoding of the data, from the extracted modules
+fn correct(c: &mut Criterion) {
+    let mut group = c.benchmark_group("correct");
+    let decoder = QRDecoder::new();
+
+    for name in &IMAGES {
+        let extracted = extracted(&prepared(&open(name)));
+
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                || extracted.clone(),
+                |extracted| {
+                    for data in extracted {
+                        let _ = decoder.decode(Ok(data));
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// Correcting many errors is much slower than checking a block without errors, which the test images mostly have
+#[cfg(feature = "test-util")]
+fn correct_damaged(c: &mut Criterion) {
+    use bardecoder::util::fault;
+
+    let mut group = c.benchmark_group("correct_damaged");
+    let decoder = QRDecoder::new();
+
+    for name in &IMAGES {
+        let mut extracted = extracted(&prepared(&open(name)));
+        for data in &mut extracted {
+            fault::flip_modules(data, 8 * data.version as usize, 1).expect("Extracted code has a valid version");
+        }
+
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                || extracted.clone(),
+                |extracted| {
+                    for data in extracted {
+                        let _ = decoder.decode(Ok(data));
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(not(feature = "test-util"))]
+fn correct_damaged(_: &mut Criterion) {}
+
+// The 81 blocks of a version 40 code at the high level, corrected side by side with `--features parallel` and one
+// after the other without, to compare the two. Without flipped modules the correction is the 30 syndromes of every
+// block, which all come out zero
+#[cfg(all(feature = "encode", feature = "test-util"))]
+fn correct_version40(c: &mut Criterion) {
+    use bardecoder::encode::QREncoder;
+    use bardecoder::util::fault;
+    use bardecoder::util::qr::ECLevel;
+
+    let mut group = c.benchmark_group("correct_version40");
+    let decoder = QRDecoder::new();
+
+    let message = "version 40 ".repeat(100);
+    let mut encoder = QREncoder::new();
+    encoder.version(40).ec_level(ECLevel::HIGH);
+    let code = encoder.encode(&message).expect("Message fits in version 40").to_qr_data();
+
+    for flipped in [0, 500] {
+        let mut damaged = code.clone();
+        fault::flip_modules(&mut damaged, flipped, 1).expect("Version 40 is valid");
+
+        group.bench_function(format!("{flipped} modules flipped"), |b| {
+            b.iter_batched(|| damaged.clone(), |data| decoder.decode(Ok(data)), BatchSize::SmallInput)
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(not(all(feature = "encode", feature = "test-util")))]
+fn correct_version40(_: &mut Criterion) {}
+
+fn end_to_end(c: &mut Criterion) {
+    let mut group = c.benchmark_group("end_to_end");
+    let decoder = bardecoder::default_decoder();
+    let decoder_with_info = bardecoder::default_decoder_with_info();
+
+    for name in &IMAGES {
+        let img = open(name);
+        group.bench_function(*name, |b| b.iter(|| decoder.decode(&img)));
+        group.bench_function(format!("{name} with info"), |b| b.iter(|| decoder_with_info.decode(&img)));
+    }
+
+    group.finish();
+}
+
+criterion_group!(stages, prepare, detect, extract, correct, correct_damaged, correct_version40, end_to_end);
+criterion_main!(stages);
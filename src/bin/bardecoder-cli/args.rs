@@ -0,0 +1,246 @@
+use std::fmt;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: bardecoder-cli [OPTIONS] [FILE|GLOB|-]...
+
+Decode QR codes in the provided images. With no inputs, or when an input is '-',
+the image is read from stdin.
+
+Options:
+    --with-info            Also print version, error correction level and error count
+    --roi <X,Y,W,H>        Only decode inside the given region of interest, in pixels
+    --symbology <NAME>     Symbology to look for. Only 'qr' is supported
+    --try-harder           Retry with alternative settings when nothing decodes
+    --format <FORMAT>      Output format: text (default), raw (payloads only), json, ndjson or csv
+    -h, --help             Print this help
+    -V, --version          Print version information";
+
+/// Fully parsed command line
+#[derive(Debug)]
+pub enum Command {
+    Run(Args),
+    Help,
+    Version,
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub inputs: Vec<Input>,
+    pub with_info: bool,
+    pub roi: Option<Roi>,
+    pub symbology: Symbology,
+    pub try_harder: bool,
+    pub format: Format,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Stdin => write!(f, "<stdin>"),
+            Input::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Region of interest in pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Roi {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Symbology {
+    QR,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    Text,
+    Raw,
+    Json,
+    Ndjson,
+    Csv,
+}
+
+#[derive(Debug)]
+pub struct ArgsError(pub String);
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Command, ArgsError> {
+    let mut parsed = Args {
+        inputs: vec![],
+        with_info: false,
+        roi: None,
+        symbology: Symbology::QR,
+        try_harder: false,
+        format: Format::Text,
+    };
+
+    while let Some(arg) = args.next() {
+        // Allow both `--flag value` and `--flag=value`
+        let (flag, inline_value) = match arg.find('=') {
+            Some(pos) if arg.starts_with("--") => (arg[..pos].to_string(), Some(arg[pos + 1..].to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| ArgsError(format!("Missing value for {name}")))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--with-info" => parsed.with_info = true,
+            "--try-harder" => parsed.try_harder = true,
+            "--roi" => parsed.roi = Some(parse_roi(&value("--roi")?)?),
+            "--symbology" => parsed.symbology = parse_symbology(&value("--symbology")?)?,
+            "--format" => parsed.format = parse_format(&value("--format")?)?,
+            "-" => parsed.inputs.push(Input::Stdin),
+            _ if flag.starts_with('-') => {
+                return Err(ArgsError(format!("Unknown option {flag}")));
+            }
+            _ => parsed.inputs.extend(expand(&arg)?),
+        }
+    }
+
+    if parsed.inputs.is_empty() {
+        parsed.inputs.push(Input::Stdin);
+    }
+
+    Ok(Command::Run(parsed))
+}
+
+fn parse_roi(roi: &str) -> Result<Roi, ArgsError> {
+    let parts = roi
+        .split(',')
+        .map(|p| p.trim().parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|_| ArgsError(format!("Invalid region of interest {roi:?}, expected X,Y,W,H")))?;
+
+    match parts.as_slice() {
+        [x, y, width, height] if *width > 0 && *height > 0 => Ok(Roi {
+            x: *x,
+            y: *y,
+            width: *width,
+            height: *height,
+        }),
+        _ => Err(ArgsError(format!(
+            "Invalid region of interest {roi:?}, expected X,Y,W,H with non-zero width and height"
+        ))),
+    }
+}
+
+fn parse_symbology(symbology: &str) -> Result<Symbology, ArgsError> {
+    match symbology.to_ascii_lowercase().as_str() {
+        "qr" | "qrcode" => Ok(Symbology::QR),
+        _ => Err(ArgsError(format!("Unsupported symbology {symbology:?}, only 'qr' is supported"))),
+    }
+}
+
+fn parse_format(format: &str) -> Result<Format, ArgsError> {
+    match format.to_ascii_lowercase().as_str() {
+        "text" => Ok(Format::Text),
+        "raw" => Ok(Format::Raw),
+        "json" => Ok(Format::Json),
+        "ndjson" => Ok(Format::Ndjson),
+        "csv" => Ok(Format::Csv),
+        _ => Err(ArgsError(format!(
+            "Unknown format {format:?}, expected text, raw, json, ndjson or csv"
+        ))),
+    }
+}
+
+// Expand glob patterns ourselves so they also work on shells that don't do it for us
+fn expand(arg: &str) -> Result<Vec<Input>, ArgsError> {
+    if !arg.contains(['*', '?', '[']) {
+        return Ok(vec![Input::File(PathBuf::from(arg))]);
+    }
+
+    let paths = glob::glob(arg).map_err(|e| ArgsError(format!("Invalid pattern {arg:?}: {e}")))?;
+
+    let mut inputs = vec![];
+    for path in paths {
+        let path = path.map_err(|e| ArgsError(format!("Unable to read {arg:?}: {e}")))?;
+        if path.is_file() {
+            inputs.push(Input::File(path));
+        }
+    }
+
+    if inputs.is_empty() {
+        return Err(ArgsError(format!("No files match {arg:?}")));
+    }
+
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(args: &[&str]) -> Args {
+        match parse(args.iter().map(|a| a.to_string())) {
+            Ok(Command::Run(args)) => args,
+            other => panic!("Expected Command::Run, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_defaults_to_stdin() {
+        let args = run(&[]);
+        assert_eq!(args.inputs, vec![Input::Stdin]);
+        assert_eq!(args.format, Format::Text);
+        assert!(!args.with_info);
+        assert!(!args.try_harder);
+    }
+
+    #[test]
+    fn test_flags_and_values() {
+        let args = run(&["--with-info", "--roi=1,2,30,40", "--symbology", "QR", "--try-harder", "--format", "NDJSON", "a.png"]);
+        assert!(args.with_info);
+        assert_eq!(args.format, Format::Ndjson);
+        assert!(args.try_harder);
+        assert_eq!(args.symbology, Symbology::QR);
+        assert_eq!(
+            args.roi,
+            Some(Roi {
+                x: 1,
+                y: 2,
+                width: 30,
+                height: 40
+            })
+        );
+        assert_eq!(args.inputs, vec![Input::File(PathBuf::from("a.png"))]);
+    }
+
+    #[test]
+    fn test_glob_expansion() {
+        let args = run(&["tests/images/version1_example*.jpg"]);
+        assert!(args.inputs.len() >= 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(parse(["--roi", "1,2,3"].iter().map(|a| a.to_string())).is_err());
+        assert!(parse(["--symbology", "ean13"].iter().map(|a| a.to_string())).is_err());
+        assert!(parse(["--bogus"].iter().map(|a| a.to_string())).is_err());
+        assert!(parse(["--format"].iter().map(|a| a.to_string())).is_err());
+    }
+}
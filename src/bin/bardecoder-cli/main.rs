@@ -0,0 +1,187 @@
+//! Command line interface to decode QR codes in image files
+//!
+//! Build with `cargo build --features cli`; run with `--help` for usage.
+
+mod args;
+#[path = "../shared/json.rs"]
+mod json;
+mod output;
+
+use std::io::Read;
+use std::process::exit;
+use std::time::Instant;
+
+use bardecoder::decode::{Decode, QRDecoderWithInfo};
+use bardecoder::detect::{Detect, LineScan, Location};
+use bardecoder::extract::{Extract, QRExtractor};
+use bardecoder::prepare::{BlockedMean, Prepare};
+
+use image::{DynamicImage, GenericImageView, ImageFormat};
+
+use crate::args::{Command, Input, Roi, USAGE};
+use crate::output::{Decoded, Printer};
+
+/// Alternative BlockedMean parameters to try when `--try-harder` is set and the default finds nothing
+const TRY_HARDER_BLOCKS: [(u32, u32); 3] = [(7, 9), (3, 5), (9, 11)];
+
+fn main() {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            println!("{USAGE}");
+            return;
+        }
+        Ok(Command::Version) => {
+            println!("bardecoder-cli {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(e) => {
+            eprintln!("bardecoder-cli: {e}\n\n{USAGE}");
+            exit(2);
+        }
+    };
+
+    let mut decoded_any = false;
+    let mut failed_input = false;
+
+    let mut printer = Printer::new(args.format, args.with_info);
+    printer.begin();
+
+    for input in &args.inputs {
+        let img = match load(input, args.roi) {
+            Ok(img) => img,
+            Err(e) => {
+                eprintln!("{input}: {e}");
+                failed_input = true;
+                continue;
+            }
+        };
+
+        let results = decode(&img, args.try_harder);
+
+        if results.is_empty() {
+            eprintln!("{input}: no QR codes found");
+        }
+
+        for decoded in &results {
+            decoded_any |= decoded.result.is_ok();
+            printer.record(input, decoded);
+        }
+    }
+
+    printer.end();
+
+    if failed_input {
+        exit(2);
+    }
+
+    if !decoded_any {
+        exit(1);
+    }
+}
+
+fn load(input: &Input, roi: Option<Roi>) -> Result<DynamicImage, String> {
+    // Files are read in full, rather than with image::open, so the EXIF data can be read from the same bytes
+    let (bytes, format) = match input {
+        Input::Stdin => {
+            let mut bytes = vec![];
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|e| format!("Unable to read stdin: {e}"))?;
+            (bytes, None)
+        }
+        Input::File(path) => (
+            std::fs::read(path).map_err(|e| format!("Unable to open image: {e}"))?,
+            ImageFormat::from_path(path).ok(),
+        ),
+    };
+
+    // These need a decoder outside of the image crate, see bardecoder::prepare::ImageLoader
+    if let Some(format) = bardecoder::prepare::HeifFormat::from_bytes(&bytes) {
+        return Err(format!("Unable to open image: {format:?} images are not supported"));
+    }
+
+    let img = match format {
+        Some(format) => image::load_from_memory_with_format(&bytes, format),
+        None => image::load_from_memory(&bytes),
+    }
+    .map_err(|e| format!("Unable to open image: {e}"))?;
+
+    // Photos are turned the way they are displayed before the region of interest is applied
+    #[cfg(feature = "exif")]
+    let img = match bardecoder::prepare::Orientation::from_bytes(&bytes) {
+        Some(orientation) => orientation.apply(img),
+        None => img,
+    };
+
+    match roi {
+        Some(roi) => {
+            if roi.x >= img.width() || roi.y >= img.height() {
+                return Err(format!(
+                    "Region of interest starts outside of the {width}x{height} image",
+                    width = img.width(),
+                    height = img.height()
+                ));
+            }
+
+            Ok(img.crop_imm(roi.x, roi.y, roi.width, roi.height))
+        }
+        None => Ok(img),
+    }
+}
+
+fn decode(img: &DynamicImage, try_harder: bool) -> Vec<Decoded> {
+    let results = run(img, &BlockedMean::new(5, 7));
+
+    if !try_harder || results.iter().any(|r| r.result.is_ok()) {
+        return results;
+    }
+
+    // Different block sizes cope better with different module sizes and lighting
+    for (block_size, block_mean_size) in TRY_HARDER_BLOCKS.iter() {
+        let retry = run(img, &BlockedMean::new(*block_size, *block_mean_size));
+        if retry.iter().any(|r| r.result.is_ok()) {
+            return retry;
+        }
+    }
+
+    // Finally try light-on-dark codes
+    let mut inverted = img.clone();
+    inverted.invert();
+
+    let retry = run(&inverted, &BlockedMean::new(5, 7));
+    if retry.iter().any(|r| r.result.is_ok()) {
+        return retry;
+    }
+
+    results
+}
+
+// Run the default pipeline by hand, so the location and timing of every code is known
+fn run(img: &DynamicImage, prepare: &BlockedMean) -> Vec<Decoded> {
+    let start = Instant::now();
+    let prepared = prepare.prepare(img);
+    let locations = LineScan::new().detect(&prepared);
+    let detect_time = start.elapsed();
+
+    let extractor = QRExtractor::new();
+    let decoder = QRDecoderWithInfo::new();
+
+    locations
+        .into_iter()
+        .map(|location| match location {
+            Location::QR(loc) => {
+                let start = Instant::now();
+                let corners = loc.corners();
+                let result = decoder.decode(extractor.extract(&prepared, loc));
+
+                Decoded {
+                    result,
+                    corners,
+                    detect_time,
+                    decode_time: start.elapsed(),
+                }
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use bardecoder::util::Point;
+use bardecoder::{QRError, QRInfo};
+
+use crate::args::{Format, Input};
+use crate::json::json_string;
+
+/// A single located code, successfully decoded or not
+pub struct Decoded {
+    pub result: Result<(String, QRInfo), QRError>,
+    pub corners: [Point; 4],
+    /// Time spent preparing the image and detecting all codes in it
+    pub detect_time: Duration,
+    /// Time spent extracting and decoding this code
+    pub decode_time: Duration,
+}
+
+const CSV_HEADER: &str = "file,status,payload,error,version,ec_level,errors,total_data,\
+top_left_x,top_left_y,top_right_x,top_right_y,bottom_right_x,bottom_right_y,bottom_left_x,bottom_left_y,\
+detect_ms,decode_ms";
+
+/// Writes decoded codes to stdout in the requested format
+pub struct Printer {
+    format: Format,
+    with_info: bool,
+    records: usize,
+}
+
+impl Printer {
+    pub fn new(format: Format, with_info: bool) -> Printer {
+        Printer {
+            format,
+            with_info,
+            records: 0,
+        }
+    }
+
+    pub fn begin(&self) {
+        match self.format {
+            Format::Json => println!("["),
+            Format::Csv => println!("{CSV_HEADER}"),
+            _ => {}
+        }
+    }
+
+    pub fn record(&mut self, input: &Input, decoded: &Decoded) {
+        match self.format {
+            Format::Text => self.text(input, decoded),
+            Format::Raw => {
+                if let Ok((data, _)) = &decoded.result {
+                    println!("{data}");
+                }
+            }
+            Format::Json => {
+                if self.records > 0 {
+                    println!(",");
+                }
+                print!("  {}", json(input, decoded));
+            }
+            Format::Ndjson => println!("{}", json(input, decoded)),
+            Format::Csv => println!("{}", csv(input, decoded)),
+        }
+
+        self.records += 1;
+    }
+
+    pub fn end(&self) {
+        if let Format::Json = self.format {
+            if self.records > 0 {
+                println!();
+            }
+            println!("]");
+        }
+    }
+
+    fn text(&self, input: &Input, decoded: &Decoded) {
+        match &decoded.result {
+            Ok((data, info)) if self.with_info => println!(
+                "{input}: {data} [version {version}, ec level {ec_level:?}, {errors} bit errors]",
+                version = info.version,
+                ec_level = info.ec_level,
+                errors = info.errors
+            ),
+            Ok((data, _)) => println!("{input}: {data}"),
+            Err(e) => eprintln!("{input}: {e}"),
+        }
+    }
+}
+
+fn json(input: &Input, decoded: &Decoded) -> String {
+    let corners = decoded
+        .corners
+        .iter()
+        .map(|p| format!("[{x:.1},{y:.1}]", x = p.x, y = p.y))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let fields = match &decoded.result {
+        Ok((data, info)) => format!(
+            "\"status\":\"ok\",\"payload\":{payload},\"version\":{version},\"ec_level\":\"{ec_level:?}\",\
+             \"errors\":{errors},\"total_data\":{total_data}",
+            payload = json_string(data),
+            version = info.version,
+            ec_level = info.ec_level,
+            errors = info.errors,
+            total_data = info.total_data
+        ),
+        Err(e) => format!(
+            "\"status\":\"error\",\"error\":{error},\"error_code\":{code}",
+            error = json_string(&e.msg),
+            code = e.code().value()
+        ),
+    };
+
+    format!(
+        "{{\"file\":{file},{fields},\"corners\":[{corners}],\"detect_ms\":{detect:.3},\"decode_ms\":{decode:.3}}}",
+        file = json_string(&input.to_string()),
+        detect = millis(decoded.detect_time),
+        decode = millis(decoded.decode_time)
+    )
+}
+
+fn csv(input: &Input, decoded: &Decoded) -> String {
+    let mut row = vec![csv_field(&input.to_string())];
+
+    match &decoded.result {
+        Ok((data, info)) => {
+            row.push(String::from("ok"));
+            row.push(csv_field(data));
+            row.push(String::new());
+            row.push(info.version.to_string());
+            row.push(format!("{:?}", info.ec_level));
+            row.push(info.errors.to_string());
+            row.push(info.total_data.to_string());
+        }
+        Err(e) => {
+            row.push(String::from("error"));
+            row.push(String::new());
+            row.push(csv_field(&e.msg));
+            row.extend(vec![String::new(); 4]);
+        }
+    }
+
+    for corner in &decoded.corners {
+        row.push(format!("{:.1}", corner.x));
+        row.push(format!("{:.1}", corner.y));
+    }
+
+    row.push(format!("{:.3}", millis(decoded.detect_time)));
+    row.push(format!("{:.3}", millis(decoded.decode_time)));
+
+    row.join(",")
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_csv_row_matches_header() {
+        let decoded = Decoded {
+            result: Err(QRError::new("broken, badly")),
+            corners: [Point { x: 0.0, y: 0.0 }; 4],
+            detect_time: Duration::from_millis(2),
+            decode_time: Duration::from_millis(1),
+        };
+
+        let row = csv(&Input::Stdin, &decoded);
+        // The quoted error message contains one extra comma
+        assert_eq!(row.matches(',').count(), CSV_HEADER.matches(',').count() + 1);
+        assert!(row.starts_with("<stdin>,error,,\"broken, badly\","));
+    }
+
+    #[test]
+    fn test_json_error_code() {
+        use std::convert::TryFrom;
+
+        let decoded = Decoded {
+            result: Err(bardecoder::ECLevel::try_from(4).unwrap_err()),
+            corners: [Point { x: 0.0, y: 0.0 }; 4],
+            detect_time: Duration::from_millis(2),
+            decode_time: Duration::from_millis(1),
+        };
+
+        let json = json(&Input::Stdin, &decoded);
+        assert!(json.contains("\"error\":\"Invalid error correction level: 100\",\"error_code\":300,"), "{}", json);
+    }
+}
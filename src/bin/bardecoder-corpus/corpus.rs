@@ -0,0 +1,280 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Extensions of the files that are decoded, in lower case
+const IMAGE_EXTENSIONS: [&str; 10] = ["png", "jpg", "jpeg", "gif", "bmp", "webp", "tif", "tiff", "pgm", "pnm"];
+
+/// Appended to the file name of an image for the file with its expected results, as in `label.png.expected`
+pub const SIDECAR_EXTENSION: &str = "expected";
+
+/// An image in the corpus
+#[derive(Debug, PartialEq)]
+pub struct Sample {
+    pub image: PathBuf,
+    /// Content of every code in the image, in any order. `None` if the image has no sidecar file
+    pub expected: Option<Vec<String>>,
+}
+
+/// What decoding one image gave
+#[derive(Debug)]
+pub struct Outcome {
+    pub image: PathBuf,
+    pub expected: Vec<String>,
+    pub decoded: Vec<String>,
+    /// Why the image could not be read, which fails it
+    pub load_error: Option<String>,
+    /// Codes that were detected but did not decode
+    pub errors: Vec<String>,
+    pub time: Duration,
+}
+
+impl Outcome {
+    /// Every expected code decoded and nothing else, regardless of order
+    pub fn passed(&self) -> bool {
+        if self.load_error.is_some() {
+            return false;
+        }
+
+        let mut expected = self.expected.clone();
+        let mut decoded = self.decoded.clone();
+        expected.sort();
+        decoded.sort();
+
+        expected == decoded
+    }
+
+    // Expected codes that were decoded, counting duplicates once per occurrence
+    fn found(&self) -> usize {
+        let mut decoded = self.decoded.clone();
+
+        self.expected
+            .iter()
+            .filter(|expected| match decoded.iter().position(|d| d == *expected) {
+                Some(index) => {
+                    decoded.swap_remove(index);
+                    true
+                }
+                None => false,
+            })
+            .count()
+    }
+}
+
+/// Every image below the directory, in file name order, or the path itself if it is a file
+pub fn walk(path: &Path) -> io::Result<Vec<Sample>> {
+    let mut samples = vec![];
+
+    if path.is_file() {
+        samples.push(sample(path.to_path_buf())?);
+        return Ok(samples);
+    }
+
+    let mut entries = fs::read_dir(path)?.map(|entry| entry.map(|e| e.path())).collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            samples.extend(walk(&entry)?);
+        } else if is_image(&entry) {
+            samples.push(sample(entry)?);
+        }
+    }
+
+    Ok(samples)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+fn sample(image: PathBuf) -> io::Result<Sample> {
+    let mut sidecar = image.clone().into_os_string();
+    sidecar.push(".");
+    sidecar.push(SIDECAR_EXTENSION);
+
+    let expected = match fs::read_to_string(&sidecar) {
+        Ok(text) => Some(parse_expected(&text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    Ok(Sample { image, expected })
+}
+
+/// The contents of a sidecar file: one code per line, with `\n` for a line break and `\\` for a backslash
+///
+/// Blank lines are ignored, so an empty file means the image has no codes.
+pub fn parse_expected(text: &str) -> Vec<String> {
+    text.lines().filter(|line| !line.trim().is_empty()).map(unescape).collect()
+}
+
+fn unescape(line: &str) -> String {
+    let mut content = String::with_capacity(line.len());
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                content.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                content.push('\\');
+                chars.next();
+            }
+            _ => content.push(c),
+        }
+    }
+
+    content
+}
+
+/// Aggregate results of a run over the corpus
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub images: usize,
+    pub passed: usize,
+    pub skipped: usize,
+    pub codes: usize,
+    pub codes_found: usize,
+    pub total_time: Duration,
+    pub median_time: Duration,
+    pub p95_time: Duration,
+    pub max_time: Duration,
+}
+
+impl Summary {
+    pub fn new(outcomes: &[Outcome], skipped: usize) -> Summary {
+        let mut times: Vec<Duration> = outcomes.iter().map(|outcome| outcome.time).collect();
+        times.sort();
+
+        let percentile = |p: usize| match times.len() {
+            0 => Duration::default(),
+            len => times[(len - 1) * p / 100],
+        };
+
+        Summary {
+            images: outcomes.len(),
+            passed: outcomes.iter().filter(|outcome| outcome.passed()).count(),
+            skipped,
+            codes: outcomes.iter().map(|outcome| outcome.expected.len()).sum(),
+            codes_found: outcomes.iter().map(Outcome::found).sum(),
+            total_time: times.iter().sum(),
+            median_time: percentile(50),
+            p95_time: percentile(95),
+            max_time: times.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate = |part: usize, total: usize| match total {
+            0 => 100.0,
+            _ => 100.0 * part as f64 / total as f64,
+        };
+        let mean = match self.images {
+            0 => Duration::default(),
+            images => self.total_time / images as u32,
+        };
+
+        writeln!(
+            f,
+            "images: {} passed, {} failed, {} without a sidecar file skipped",
+            self.passed,
+            self.images - self.passed,
+            self.skipped
+        )?;
+        writeln!(f, "image decode rate: {:.1}%", rate(self.passed, self.images))?;
+        writeln!(f, "code decode rate: {:.1}% ({} of {})", rate(self.codes_found, self.codes), self.codes_found, self.codes)?;
+        write!(
+            f,
+            "decode time: {:.1?} total, {:.1?} mean, {:.1?} median, {:.1?} p95, {:.1?} max",
+            self.total_time, mean, self.median_time, self.p95_time, self.max_time
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(expected: &[&str], decoded: &[&str], millis: u64) -> Outcome {
+        Outcome {
+            image: PathBuf::from("a.png"),
+            expected: expected.iter().map(|e| e.to_string()).collect(),
+            decoded: decoded.iter().map(|d| d.to_string()).collect(),
+            load_error: None,
+            errors: vec![],
+            time: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn test_parse_expected() {
+        assert_eq!(parse_expected("Ver1\nhttp://example.com/\n"), vec!["Ver1", "http://example.com/"]);
+        assert_eq!(parse_expected("BEGIN:VCARD\\nEND:VCARD\n\\\\n"), vec!["BEGIN:VCARD\nEND:VCARD", "\\n"]);
+        assert!(parse_expected("\n  \n").is_empty());
+    }
+
+    #[test]
+    fn test_passed() {
+        assert!(outcome(&["a", "b"], &["b", "a"], 0).passed());
+        assert!(outcome(&[], &[], 0).passed());
+        assert!(!outcome(&["a", "a"], &["a"], 0).passed());
+        assert!(!outcome(&["a"], &["a", "b"], 0).passed());
+
+        let mut unreadable = outcome(&[], &[], 0);
+        unreadable.load_error = Some(String::from("Unable to open image"));
+        assert!(!unreadable.passed());
+    }
+
+    #[test]
+    fn test_summary() {
+        let outcomes = [
+            outcome(&["a", "b"], &["a"], 30),
+            outcome(&["c"], &["c"], 10),
+            outcome(&[], &[], 20),
+        ];
+        let summary = Summary::new(&outcomes, 4);
+
+        assert_eq!((summary.images, summary.passed, summary.skipped), (3, 2, 4));
+        assert_eq!((summary.codes, summary.codes_found), (3, 2));
+        assert_eq!(summary.total_time, Duration::from_millis(60));
+        assert_eq!(summary.median_time, Duration::from_millis(20));
+        assert_eq!(summary.max_time, Duration::from_millis(30));
+        assert!(summary.to_string().contains("image decode rate: 66.7%"));
+    }
+
+    #[test]
+    fn test_walk() {
+        let dir = std::env::temp_dir().join(format!("bardecoder_corpus_{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("b.PNG"), b"").unwrap();
+        fs::write(dir.join("b.PNG.expected"), "Ver1\n").unwrap();
+        fs::write(dir.join("notes.txt"), b"").unwrap();
+        fs::write(dir.join("nested/a.jpg"), b"").unwrap();
+
+        let samples = walk(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            samples,
+            vec![
+                Sample {
+                    image: dir.join("b.PNG"),
+                    expected: Some(vec![String::from("Ver1")]),
+                },
+                Sample {
+                    image: dir.join("nested/a.jpg"),
+                    expected: None,
+                },
+            ]
+        );
+    }
+}
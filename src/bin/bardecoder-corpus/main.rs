@@ -0,0 +1,153 @@
+//! Regression runner over a corpus of images with their expected results
+//!
+//! Build with `cargo build --release --features corpus`; run with `--help` for usage. Every image below the
+//! directories is decoded with the default decoder and compared with the sidecar file next to it, so the effect of a
+//! change on robustness can be measured against a corpus that does not have to be part of the repository.
+
+mod corpus;
+
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Instant;
+
+use bardecoder::Decoder;
+
+use image::{DynamicImage, GrayImage};
+
+use crate::corpus::{Outcome, Sample, Summary, SIDECAR_EXTENSION};
+
+const USAGE: &str = "\
+Usage: bardecoder-corpus [OPTIONS] <DIR>...
+
+Decode every image below the directories and compare the results with the
+sidecar file next to each image, named after it with '.expected' appended, as
+in 'label.png.expected'. The sidecar has the content of every code in the
+image on its own line, in any order, with '\\n' for a line break and '\\\\' for a
+backslash. An empty sidecar means the image has no codes. Images without one
+are skipped.
+
+Exits with 1 if any image failed.
+
+Options:
+    --failures-only        Only list the images that failed
+    -h, --help             Print this help
+    -V, --version          Print version information";
+
+fn main() {
+    let mut dirs = vec![];
+    let mut failures_only = false;
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            "-V" | "--version" => {
+                println!("bardecoder-corpus {}", env!("CARGO_PKG_VERSION"));
+                return;
+            }
+            "--failures-only" => failures_only = true,
+            _ if arg.starts_with('-') => usage_error(&format!("Unknown option {arg}")),
+            _ => dirs.push(PathBuf::from(arg)),
+        }
+    }
+
+    if dirs.is_empty() {
+        usage_error("No corpus directory provided");
+    }
+
+    let mut samples = vec![];
+    for dir in &dirs {
+        match corpus::walk(dir) {
+            Ok(found) => samples.extend(found),
+            Err(e) => {
+                eprintln!("bardecoder-corpus: unable to read {}: {e}", dir.display());
+                exit(2);
+            }
+        }
+    }
+
+    let decoder = bardecoder::default_decoder();
+    let mut outcomes = vec![];
+    let mut skipped = 0;
+
+    for Sample { image, expected } in samples {
+        let expected = match expected {
+            Some(expected) => expected,
+            None => {
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let outcome = run(&decoder, image, expected);
+        if outcome.passed() {
+            if !failures_only {
+                println!("PASS {} ({:.1?})", outcome.image.display(), outcome.time);
+            }
+        } else {
+            println!(
+                "FAIL {} ({:.1?}): expected {:?}, decoded {:?}",
+                outcome.image.display(),
+                outcome.time,
+                outcome.expected,
+                outcome.decoded
+            );
+            for error in outcome.load_error.iter().chain(&outcome.errors) {
+                println!("     {error}");
+            }
+        }
+
+        outcomes.push(outcome);
+    }
+
+    let summary = Summary::new(&outcomes, skipped);
+    println!("\n{summary}");
+
+    if outcomes.is_empty() {
+        eprintln!("bardecoder-corpus: no images with a .{SIDECAR_EXTENSION} sidecar file found");
+    }
+
+    if summary.passed < summary.images {
+        exit(1);
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("bardecoder-corpus: {message}\n\n{USAGE}");
+    exit(2);
+}
+
+// Only decoding is timed, not reading the image
+fn run(decoder: &Decoder<DynamicImage, GrayImage, String>, image: PathBuf, expected: Vec<String>) -> Outcome {
+    let mut outcome = Outcome {
+        image,
+        expected,
+        decoded: vec![],
+        load_error: None,
+        errors: vec![],
+        time: Default::default(),
+    };
+
+    let img = match image::open(&outcome.image) {
+        Ok(img) => img,
+        Err(e) => {
+            outcome.load_error = Some(format!("Unable to open image: {e}"));
+            return outcome;
+        }
+    };
+
+    let start = Instant::now();
+    let results = decoder.decode(&img);
+    outcome.time = start.elapsed();
+
+    for result in results {
+        match result {
+            Ok(content) => outcome.decoded.push(content),
+            Err(e) => outcome.errors.push(e.to_string()),
+        }
+    }
+
+    outcome
+}
@@ -0,0 +1,215 @@
+use std::io::{self, BufRead, Read, Write};
+
+/// Longest request line or header accepted, in bytes
+const MAX_LINE: usize = 8 * 1024;
+/// Most headers accepted in a request
+const MAX_HEADERS: usize = 64;
+
+/// The parts of a request the service looks at
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    /// The path, without the query string
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+/// A response with a JSON body
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn json(status: u16, body: String) -> Response {
+        Response { status, body }
+    }
+
+    /// A response with only an error message, as `{"error":"..."}`
+    pub fn error(status: u16, message: &str) -> Response {
+        Response::json(status, format!("{{\"error\":{}}}", crate::json::json_string(message)))
+    }
+
+    /// Write the response, after which the connection is closed
+    pub fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(
+            out,
+            "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {length}\r\n\
+             Connection: close\r\n\r\n{body}",
+            status = self.status,
+            reason = reason(self.status),
+            length = self.body.len(),
+            body = self.body
+        )?;
+        out.flush()
+    }
+}
+
+/// Read a request, with a body of at most `max_body` bytes
+///
+/// A client that sends `Expect: 100-continue` is told to go ahead through `out` before the body is read. The error is
+/// the response to send instead, if there is any point in sending one.
+pub fn read_request(input: &mut impl BufRead, out: &mut impl Write, max_body: usize) -> Result<Request, Response> {
+    let request_line = read_line(input)?;
+    let mut parts = request_line.split(' ');
+    let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/1.") => (method, target),
+        _ => return Err(Response::error(400, "Malformed request line")),
+    };
+
+    let mut content_length = None;
+    let mut expect_continue = false;
+
+    for _ in 0..MAX_HEADERS {
+        let line = read_line(input)?;
+        if line.is_empty() {
+            let path = target.split('?').next().unwrap_or_default();
+            let body = read_body(input, out, content_length, expect_continue, max_body)?;
+
+            return Ok(Request {
+                method: method.to_string(),
+                path: path.to_string(),
+                body,
+            });
+        }
+
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| Response::error(400, "Malformed header"))?;
+        let value = value.trim();
+
+        if name.eq_ignore_ascii_case("content-length") {
+            let length = value
+                .parse::<usize>()
+                .map_err(|_| Response::error(400, "Invalid Content-Length"))?;
+            content_length = Some(length);
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(Response::error(411, "Chunked bodies are not supported, send a Content-Length"));
+        } else if name.eq_ignore_ascii_case("expect") && value.eq_ignore_ascii_case("100-continue") {
+            expect_continue = true;
+        }
+    }
+
+    Err(Response::error(431, "Too many headers"))
+}
+
+fn read_body(
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+    content_length: Option<usize>,
+    expect_continue: bool,
+    max_body: usize,
+) -> Result<Vec<u8>, Response> {
+    let length = content_length.unwrap_or(0);
+    if length > max_body {
+        return Err(Response::error(
+            413,
+            &format!("Body of {length} bytes is larger than the maximum of {max_body}"),
+        ));
+    }
+
+    if expect_continue && length > 0 {
+        out.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .and_then(|()| out.flush())
+            .map_err(|_| Response::error(400, "Connection closed"))?;
+    }
+
+    let mut body = vec![0; length];
+    input
+        .read_exact(&mut body)
+        .map_err(|_| Response::error(400, "Body is shorter than its Content-Length"))?;
+
+    Ok(body)
+}
+
+// A line without its line ending
+fn read_line(input: &mut impl BufRead) -> Result<String, Response> {
+    let mut line = vec![];
+    input
+        .take(MAX_LINE as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .map_err(|_| Response::error(400, "Unable to read request"))?;
+
+    if line.len() > MAX_LINE {
+        return Err(Response::error(431, "Request line or header too long"));
+    }
+    if line.pop() != Some(b'\n') {
+        return Err(Response::error(400, "Incomplete request"));
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    String::from_utf8(line).map_err(|_| Response::error(400, "Request line or header is not UTF-8"))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(request: &[u8], max_body: usize) -> (Result<Request, Response>, Vec<u8>) {
+        let mut out = vec![];
+        let result = read_request(&mut &request[..], &mut out, max_body);
+        (result, out)
+    }
+
+    #[test]
+    fn test_read_request() {
+        let (request, out) = read(b"POST /decode?x=1 HTTP/1.1\r\nHost: a\r\ncontent-length: 5\r\n\r\nhello", 10);
+        let request = request.unwrap();
+
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/decode"));
+        assert_eq!(request.body, b"hello");
+        assert!(out.is_empty());
+
+        let (request, _) = read(b"GET / HTTP/1.0\n\n", 10);
+        assert!(request.unwrap().body.is_empty());
+    }
+
+    #[test]
+    fn test_expect_continue() {
+        let (request, out) = read(b"POST /decode HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 2\r\n\r\nhi", 10);
+
+        assert_eq!(request.unwrap().body, b"hi");
+        assert_eq!(out, b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+
+    #[test]
+    fn test_read_request_errors() {
+        let status = |request: &[u8]| read(request, 10).0.unwrap_err().status;
+
+        assert_eq!(status(b"POST /decode\r\n\r\n"), 400);
+        assert_eq!(status(b"POST /decode HTTP/1.1\r\nContent-Length: 11\r\n\r\n"), 413);
+        assert_eq!(status(b"POST /decode HTTP/1.1\r\nContent-Length: 4\r\n\r\nab"), 400);
+        assert_eq!(status(b"POST /decode HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"), 411);
+        assert_eq!(status(b"POST /decode HTTP/1.1\r\nContent-Length: 4"), 400);
+        assert_eq!(status(&[b"GET /".as_ref(), &[b'a'; MAX_LINE], b" HTTP/1.1\r\n\r\n"].concat()), 431);
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut out = vec![];
+        Response::error(404, "Not \"here\"").write_to(&mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(out.contains("Content-Length: 24\r\n"));
+        assert!(out.ends_with("\r\n\r\n{\"error\":\"Not \\\"here\\\"\"}"));
+    }
+}
@@ -0,0 +1,155 @@
+//! HTTP service that decodes the QR codes in posted images
+//!
+//! Build with `cargo build --release --features serve`; run with `--help` for usage. Every request is handled on its
+//! own thread, with a single decoder shared between them.
+
+mod http;
+#[path = "../shared/json.rs"]
+mod json;
+mod service;
+
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream};
+use std::process::exit;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::service::Service;
+
+const USAGE: &str = "\
+Usage: bardecoder-serve [OPTIONS]
+
+Listen for HTTP requests and decode the QR codes in the image posted to
+/decode, in any format the image crate supports:
+
+    curl --data-binary @label.png http://127.0.0.1:8080/decode
+
+The response is a JSON object with a 'codes' array in reading order. Every
+code has a 'status' of 'ok' with its 'payload', 'version', 'ec_level',
+'mask', 'errors' and 'total_data', or 'error' with an 'error' message and
+the stable numeric 'error_code' of bardecoder::ErrorCode, and its four
+'corners' with the 'extract_ms' and 'decode_ms' it took. The object
+also has the 'prepare_ms' and 'detect_ms' of the whole image. Requests that
+cannot be decoded get a 4xx status with an 'error' message.
+
+Options:
+    --listen <ADDR>        Address to listen on [default: 127.0.0.1:8080]
+    --max-body <BYTES>     Largest body accepted [default: 20000000]
+    --max-pixels <PIXELS>  Largest image accepted [default: 50000000]
+    -h, --help             Print this help
+    -V, --version          Print version information";
+
+/// Time a client gets to send its request, or read the response
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+fn main() {
+    let mut listen = String::from("127.0.0.1:8080");
+    let mut max_body = 20_000_000;
+    let mut max_pixels = 50_000_000;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            "-V" | "--version" => {
+                println!("bardecoder-serve {}", env!("CARGO_PKG_VERSION"));
+                return;
+            }
+            "--listen" => listen = value(&arg, args.next()),
+            "--max-body" => max_body = number(&arg, args.next()),
+            "--max-pixels" => max_pixels = number(&arg, args.next()),
+            _ => usage_error(&format!("Unknown option {arg}")),
+        }
+    }
+
+    let listener = match TcpListener::bind(&listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("bardecoder-serve: unable to listen on {listen}: {e}");
+            exit(2);
+        }
+    };
+    eprintln!("bardecoder-serve: listening on {listen}");
+
+    let service = Arc::new(Service::new(max_pixels));
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let service = Arc::clone(&service);
+                std::thread::spawn(move || handle(stream, &service, max_body));
+            }
+            Err(e) => eprintln!("bardecoder-serve: unable to accept connection: {e}"),
+        }
+    }
+}
+
+// Answer a single request and close the connection
+fn handle(stream: TcpStream, service: &Service, max_body: usize) {
+    // A client that stops sending does not hold on to its thread for good
+    if stream.set_read_timeout(Some(TIMEOUT)).is_err() || stream.set_write_timeout(Some(TIMEOUT)).is_err() {
+        return;
+    }
+
+    let mut out = match stream.try_clone() {
+        Ok(out) => out,
+        Err(_) => return,
+    };
+
+    let response = match http::read_request(&mut BufReader::new(stream), &mut out, max_body) {
+        Ok(request) => service.respond(&request),
+        Err(response) => response,
+    };
+
+    // The client may be gone already, there is no one to report that to
+    let _ = response.write_to(&mut out);
+}
+
+fn value(option: &str, next: Option<String>) -> String {
+    next.unwrap_or_else(|| usage_error(&format!("Missing value for {option}")))
+}
+
+fn number<T: FromStr>(option: &str, next: Option<String>) -> T {
+    let text = value(option, next);
+    text.parse()
+        .unwrap_or_else(|_| usage_error(&format!("Invalid value for {option}: {text}")))
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("bardecoder-serve: {message}\n\n{USAGE}");
+    exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_handle() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            handle(stream, &Service::new(u64::MAX), 1_000_000);
+        });
+
+        let image = std::fs::read("tests/images/version1_example.jpg").unwrap();
+        let mut client = TcpStream::connect(address).unwrap();
+        write!(client, "POST /decode HTTP/1.1\r\nContent-Length: {}\r\n\r\n", image.len()).unwrap();
+        client.write_all(&image).unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        server.join().unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\"payload\":\"01234567\""));
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use bardecoder::util::qr::ErrorCode;
+use bardecoder::util::trace::CodeTrace;
+use bardecoder::{Decoder, QRError, QRInfo};
+
+use image::{DynamicImage, GrayImage};
+
+use crate::http::{Request, Response};
+use crate::json::json_string;
+
+/// Decodes the images posted to `/decode`
+pub struct Service {
+    decoder: Decoder<DynamicImage, GrayImage, (String, QRInfo)>,
+}
+
+impl Service {
+    pub fn new(max_pixels: u64) -> Service {
+        let mut builder = bardecoder::default_builder_with_info();
+        builder.max_pixels(max_pixels);
+
+        Service {
+            decoder: builder.build().expect("The default builder has every component"),
+        }
+    }
+
+    pub fn respond(&self, request: &Request) -> Response {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/decode") => self.decode(&request.body),
+            (_, "/decode") => Response::error(405, "Only POST is supported, with the image as the body"),
+            (_, path) => Response::error(404, &format!("No such endpoint: {path}")),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Response {
+        let img = match self.load(bytes) {
+            Ok(img) => img,
+            Err(response) => return response,
+        };
+
+        let (results, trace) = self.decoder.decode_with_trace(&img);
+
+        // Every result has a trace, except the errors for codes dropped by the limits of the decoder
+        let codes: Vec<String> = results
+            .iter()
+            .enumerate()
+            .map(|(i, result)| code_json(result, trace.codes.get(i)))
+            .collect();
+
+        Response::json(
+            200,
+            format!(
+                "{{\"codes\":[{codes}],\"prepare_ms\":{prepare:.3},\"detect_ms\":{detect:.3}}}",
+                codes = codes.join(","),
+                prepare = millis(trace.prepare_time),
+                detect = millis(trace.detect_time)
+            ),
+        )
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<DynamicImage, Response> {
+        if bytes.is_empty() {
+            return Err(Response::error(400, "No image in the body"));
+        }
+
+        // These need a decoder outside of the image crate, see bardecoder::prepare::ImageLoader
+        if let Some(format) = bardecoder::prepare::HeifFormat::from_bytes(bytes) {
+            return Err(Response::error(415, &format!("{format:?} images are not supported")));
+        }
+
+        // The dimensions are in the header, so a huge image is refused before its pixels are decoded
+        self.decoder.load_bytes(bytes).map_err(|e| match e.code() {
+            ErrorCode::ResourceLimit => Response::error(413, &e.msg),
+            _ => Response::error(422, &e.msg),
+        })
+    }
+}
+
+fn code_json(result: &Result<(String, QRInfo), QRError>, trace: Option<&CodeTrace>) -> String {
+    let fields = match result {
+        Ok((payload, info)) => format!(
+            "\"status\":\"ok\",\"payload\":{payload},\"version\":{version},\"ec_level\":\"{ec_level:?}\",\
+             \"mask\":{mask},\"errors\":{errors},\"total_data\":{total_data}",
+            payload = json_string(payload),
+            version = info.version,
+            ec_level = info.ec_level,
+            mask = info.mask,
+            errors = info.errors,
+            total_data = info.total_data
+        ),
+        Err(e) => format!(
+            "\"status\":\"error\",\"error\":{error},\"error_code\":{code}",
+            error = json_string(&e.msg),
+            code = e.code().value()
+        ),
+    };
+
+    let location = match trace {
+        Some(trace) => {
+            let corners: Vec<String> = trace
+                .corners
+                .iter()
+                .map(|p| format!("[{x:.1},{y:.1}]", x = p.x, y = p.y))
+                .collect();
+
+            format!(
+                ",\"corners\":[{corners}],\"extract_ms\":{extract:.3},\"decode_ms\":{decode:.3}",
+                corners = corners.join(","),
+                extract = millis(trace.extract_time),
+                decode = millis(trace.decode_time)
+            )
+        }
+        None => String::new(),
+    };
+
+    format!("{{{fields}{location}}}")
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(path: &str, body: Vec<u8>) -> Request {
+        Request {
+            method: String::from("POST"),
+            path: String::from(path),
+            body,
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let bytes = std::fs::read("tests/images/version1_example.jpg").unwrap();
+        let response = Service::new(u64::MAX).respond(&post("/decode", bytes));
+
+        assert_eq!(response.status, 200);
+        assert!(
+            response.body.starts_with("{\"codes\":[{\"status\":\"ok\",\"payload\":\"01234567\",\"version\":1,"),
+            "{}",
+            response.body
+        );
+        assert!(response.body.contains(",\"corners\":[["));
+        assert!(response.body.contains("\"detect_ms\":"));
+    }
+
+    #[test]
+    fn test_decode_nothing_found() {
+        let mut bytes = vec![];
+        DynamicImage::new_luma8(40, 40)
+            .write_to(&mut bytes, image::ImageOutputFormat::Png)
+            .unwrap();
+        let response = Service::new(u64::MAX).respond(&post("/decode", bytes));
+
+        assert_eq!(response.status, 200);
+        assert!(response.body.starts_with("{\"codes\":[],\"prepare_ms\":"));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let bytes = std::fs::read("tests/images/version1_example.jpg").unwrap();
+
+        assert_eq!(Service::new(1000).respond(&post("/decode", bytes)).status, 413);
+        assert_eq!(Service::new(u64::MAX).respond(&post("/decode", b"not an image".to_vec())).status, 422);
+        assert_eq!(Service::new(u64::MAX).respond(&post("/decode", vec![])).status, 400);
+        let heic = b"\0\0\0\x10ftypheic\0\0\0\0".to_vec();
+        assert_eq!(Service::new(u64::MAX).respond(&post("/decode", heic)).status, 415);
+        assert_eq!(Service::new(u64::MAX).respond(&post("/other", vec![])).status, 404);
+
+        let get = Request {
+            method: String::from("GET"),
+            ..post("/decode", vec![])
+        };
+        assert_eq!(Service::new(u64::MAX).respond(&get).status, 405);
+    }
+}
@@ -0,0 +1,266 @@
+//! Scan QR codes from a live camera feed
+//!
+//! [`CameraScanner`] grabs frames from a [`FrameSource`] until a code has been decoded confidently, or the timeout
+//! expires. With the `nokhwa` feature enabled, [`NokhwaCamera`] provides a [`FrameSource`] for the native camera
+//! stack of the platform.
+//!
+//! # Example
+//! ```ignore
+//! use bardecoder::camera::{CameraScanner, NokhwaCamera};
+//! use std::time::Duration;
+//!
+//! let camera = NokhwaCamera::open(0).unwrap();
+//! let mut scanner = CameraScanner::new(camera);
+//! let item = scanner.scan(Duration::from_secs(30)).unwrap();
+//! println!("{}", item.data);
+//! ```
+//!
+//! [`CameraScanner`]: struct.CameraScanner.html
+//! [`FrameSource`]: trait.FrameSource.html
+//! [`NokhwaCamera`]: struct.NokhwaCamera.html
+
+#[cfg(feature = "nokhwa")]
+mod nokhwa;
+
+#[cfg(feature = "nokhwa")]
+pub use self::nokhwa::NokhwaCamera;
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use image::{DynamicImage, GrayImage};
+
+use crate::decoder::{default_decoder_with_info, Decoder};
+use crate::util::qr::QRInfo;
+use crate::video::{VideoDecoder, VideoResult};
+
+/// Frames between full detections while the codes in view are tracked, a third of a second at 30 frames per second
+const DETECT_EVERY: usize = 10;
+
+/// Error type for camera scanning
+#[derive(Debug, thiserror::Error)]
+pub enum CameraError {
+    /// The camera could not be opened or did not deliver a frame
+    #[error("Camera error: {0}")]
+    Camera(String),
+    /// No code was decoded confidently before the timeout expired
+    #[error("No QR code found within {0:?}")]
+    Timeout(Duration),
+}
+
+/// Source of frames for a [`CameraScanner`]
+///
+/// Implement this for any camera library not supported out of the box
+///
+/// [`CameraScanner`]: struct.CameraScanner.html
+pub trait FrameSource {
+    /// Grab the next frame, blocking until one is available
+    fn next_frame(&mut self) -> Result<DynamicImage, CameraError>;
+}
+
+/// A code decoded by a [`CameraScanner`]
+///
+/// [`CameraScanner`]: struct.CameraScanner.html
+#[derive(Debug)]
+pub struct DecodedItem {
+    /// The decoded content
+    pub data: String,
+    /// Information about the decoded code
+    pub info: QRInfo,
+    /// Number of frames grabbed before the code was decoded confidently
+    pub frames: usize,
+    /// Time spent scanning
+    pub elapsed: Duration,
+}
+
+/// Decode frames from a [`FrameSource`] until a code is found
+///
+/// A single decode in a noisy camera frame can be wrong, so a code is only returned once the same content has been
+/// decoded from several frames. This is 2 frames by default, see [`confirmations`].
+///
+/// The frames are decoded by a [`VideoDecoder`] with [`track`], so once a code has been found, the following frames
+/// decode it at the same location without running detection, which makes confirming it cheap.
+///
+/// [`FrameSource`]: trait.FrameSource.html
+/// [`confirmations`]: #method.confirmations
+/// [`VideoDecoder`]: ../video/struct.VideoDecoder.html
+/// [`track`]: ../video/struct.VideoDecoder.html#method.track
+pub struct CameraScanner<S> {
+    source: S,
+    video: VideoDecoder,
+    confirmations: usize,
+}
+
+impl<S: FrameSource> CameraScanner<S> {
+    /// Create a scanner for the provided source, using the default decoder
+    pub fn new(source: S) -> CameraScanner<S> {
+        CameraScanner::with_decoder(source, default_decoder_with_info())
+    }
+
+    /// Create a scanner for the provided source, using a custom decoder
+    pub fn with_decoder(
+        source: S,
+        decoder: Decoder<DynamicImage, GrayImage, (String, QRInfo)>,
+    ) -> CameraScanner<S> {
+        let mut video = VideoDecoder::with_decoder(decoder);
+        // Every sighting of a code counts, not only the first
+        video.track(DETECT_EVERY).repeat_after(Duration::ZERO);
+
+        CameraScanner {
+            source,
+            video,
+            confirmations: 2,
+        }
+    }
+
+    /// Set the number of frames the same content needs to be decoded from before it is returned
+    ///
+    /// Several codes with the same content in one frame count as one. A value of 0 is treated as 1
+    pub fn confirmations(&mut self, confirmations: usize) -> &mut CameraScanner<S> {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+
+    /// Grab and decode frames until a code has been decoded confidently
+    ///
+    /// The timeout is checked before every frame is grabbed. A [`FrameSource::next_frame`] that blocks, on a camera
+    /// that stopped delivering frames for example, is not interrupted, so the scan can take longer than `timeout`
+    /// by as long as that call does. Sources that can wait forever should give up with `CameraError::Camera` after a
+    /// timeout of their own.
+    ///
+    /// [`FrameSource::next_frame`]: trait.FrameSource.html#tymethod.next_frame
+    ///
+    /// # Errors
+    ///
+    /// - `CameraError::Camera` - the source failed to deliver a frame
+    /// - `CameraError::Timeout` - nothing was decoded confidently within `timeout`
+    pub fn scan(&mut self, timeout: Duration) -> Result<DecodedItem, CameraError> {
+        let start = Instant::now();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut frames = 0;
+
+        // Codes are tracked from one frame to the next within a scan only
+        self.video.reset();
+
+        while start.elapsed() < timeout {
+            let frame = self.source.next_frame()?;
+            frames += 1;
+
+            let mut in_frame = HashSet::new();
+
+            for VideoResult { data, info, .. } in self.video.push(start.elapsed(), &frame) {
+                if !in_frame.insert(data.clone()) {
+                    continue;
+                }
+
+                let count = seen.entry(data.clone()).or_insert(0);
+                *count += 1;
+
+                if *count >= self.confirmations {
+                    return Ok(DecodedItem {
+                        data,
+                        info,
+                        frames,
+                        elapsed: start.elapsed(),
+                    });
+                }
+            }
+        }
+
+        Err(CameraError::Timeout(timeout))
+    }
+
+    /// Access the underlying frame source
+    pub fn source(&mut self) -> &mut S {
+        &mut self.source
+    }
+
+    /// Stop scanning and return the underlying frame source
+    pub fn into_source(self) -> S {
+        self.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Frames {
+        frames: Vec<DynamicImage>,
+        grabbed: usize,
+    }
+
+    impl FrameSource for Frames {
+        fn next_frame(&mut self) -> Result<DynamicImage, CameraError> {
+            let frame = self
+                .frames
+                .get(self.grabbed)
+                .cloned()
+                .ok_or_else(|| CameraError::Camera(String::from("End of stream")))?;
+            self.grabbed += 1;
+            Ok(frame)
+        }
+    }
+
+    fn frames(frames: Vec<DynamicImage>) -> Frames {
+        Frames { frames, grabbed: 0 }
+    }
+
+    #[test]
+    fn test_scan_needs_confirmation() {
+        let blank = DynamicImage::new_luma8(100, 100);
+        let code = image::open("tests/images/version1_example.jpg").unwrap();
+
+        let mut scanner = CameraScanner::new(frames(vec![blank, code.clone(), code]));
+        let item = scanner.scan(Duration::from_secs(60)).unwrap();
+
+        assert_eq!(item.data, "01234567");
+        assert_eq!(item.frames, 3);
+        assert_eq!(scanner.into_source().grabbed, 3);
+    }
+
+    #[test]
+    fn test_scan_single_confirmation() {
+        let code = image::open("tests/images/version1_example.jpg").unwrap();
+
+        let mut scanner = CameraScanner::new(frames(vec![code]));
+        scanner.confirmations(0);
+        let item = scanner.scan(Duration::from_secs(60)).unwrap();
+
+        assert_eq!(item.frames, 1);
+    }
+
+    #[test]
+    fn test_scan_counts_frames() {
+        let code = image::open("tests/images/version1_example.jpg").unwrap().to_luma8();
+
+        // The same code twice in one frame is not confirmed by it
+        let mut side_by_side = GrayImage::new(code.width() * 2, code.height());
+        image::imageops::replace(&mut side_by_side, &code, 0, 0);
+        image::imageops::replace(&mut side_by_side, &code, code.width(), 0);
+        let twice = DynamicImage::ImageLuma8(side_by_side);
+        assert_eq!(default_decoder_with_info().decode(&twice).len(), 2);
+
+        let mut scanner = CameraScanner::new(frames(vec![twice.clone()]));
+        assert!(matches!(scanner.scan(Duration::from_secs(60)), Err(CameraError::Camera(_))));
+
+        let mut scanner = CameraScanner::new(frames(vec![twice.clone(), twice]));
+        assert_eq!(scanner.scan(Duration::from_secs(60)).unwrap().frames, 2);
+    }
+
+    #[test]
+    fn test_scan_errors() {
+        let blank = DynamicImage::new_luma8(100, 100);
+
+        let mut scanner = CameraScanner::new(frames(vec![blank]));
+        match scanner.scan(Duration::from_secs(60)) {
+            Err(CameraError::Camera(_)) => {}
+            other => panic!("Expected camera error, got {:?}", other),
+        }
+
+        match scanner.scan(Duration::from_secs(0)) {
+            Err(CameraError::Timeout(_)) => {}
+            other => panic!("Expected timeout, got {:?}", other),
+        }
+    }
+}
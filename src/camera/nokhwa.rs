@@ -0,0 +1,57 @@
+use super::{CameraError, FrameSource};
+
+use ::nokhwa::pixel_format::RgbFormat;
+use ::nokhwa::utils::{CameraIndex, RequestedFormat, RequestedFormatType};
+use ::nokhwa::{Camera, NokhwaError};
+
+use image::{DynamicImage, RgbImage};
+
+/// [`FrameSource`] for the platform's native camera stack, using the [`nokhwa`] crate
+///
+/// [`FrameSource`]: trait.FrameSource.html
+/// [`nokhwa`]: https://crates.io/crates/nokhwa
+pub struct NokhwaCamera {
+    camera: Camera,
+}
+
+impl NokhwaCamera {
+    /// Open the camera with the provided index at its highest frame rate and start streaming
+    pub fn open(index: u32) -> Result<NokhwaCamera, CameraError> {
+        let format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
+        let camera = Camera::new(CameraIndex::Index(index), format)?;
+
+        NokhwaCamera::from_camera(camera)
+    }
+
+    /// Use an already configured camera, starting its stream if needed
+    pub fn from_camera(mut camera: Camera) -> Result<NokhwaCamera, CameraError> {
+        if !camera.is_stream_open() {
+            camera.open_stream()?;
+        }
+
+        Ok(NokhwaCamera { camera })
+    }
+
+    /// Access the underlying camera
+    pub fn camera(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+}
+
+impl FrameSource for NokhwaCamera {
+    fn next_frame(&mut self) -> Result<DynamicImage, CameraError> {
+        let rgb = self.camera.frame()?.decode_image::<RgbFormat>()?;
+
+        // nokhwa may depend on a different version of the image crate, so go through the raw buffer
+        let (width, height) = (rgb.width(), rgb.height());
+        RgbImage::from_raw(width, height, rgb.into_raw())
+            .map(DynamicImage::ImageRgb8)
+            .ok_or_else(|| CameraError::Camera(String::from("Frame buffer does not match its resolution")))
+    }
+}
+
+impl From<NokhwaError> for CameraError {
+    fn from(error: NokhwaError) -> Self {
+        CameraError::Camera(error.to_string())
+    }
+}
@@ -0,0 +1,140 @@
+//! GS1 Digital Link URLs, which carry GS1 element strings in a web address
+//!
+//! The path holds a primary key, like `/01/<GTIN>`, optionally followed by key qualifiers like `/10/<batch>` and
+//! `/21/<serial>`. Other attributes are in the query, with the AI as parameter name. Anything before the primary key
+//! is free, so any domain and path prefix can be used, for example `https://example.com/products/01/<GTIN>`.
+
+use super::gs1::known_element;
+use super::uri::percent_decode;
+use super::{strip_prefix_ignore_case, Gs1Element, Gs1Error, ParsedContent};
+
+// AIs which identify the item, only these can start the GS1 part of the path
+const PRIMARY_KEYS: &[&str] = &[
+    "00", "01", "253", "255", "401", "402", "414", "417", "8003", "8004", "8006", "8010", "8013", "8017", "8018",
+];
+
+/// A GS1 Digital Link URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigitalLink {
+    /// The complete URL, as written in the code
+    pub url: String,
+    /// The primary key first, then the key qualifiers from the path and the attributes from the query
+    pub elements: Vec<Gs1Element>,
+}
+
+/// Parse a GS1 Digital Link URL into its element strings
+///
+/// GTINs with less than 14 digits are padded with zeros, the same as they would be in a barcode.
+///
+/// # Errors
+///
+/// Returns `Gs1Error::NotDigitalLink` if there is no primary key in the path, and other `Gs1Error`s for invalid values
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::content::parse_digital_link;
+///
+/// let link = parse_digital_link("https://id.gs1.org/01/9506000134352/10/AB-123?17=260131").unwrap();
+/// let elements: Vec<(&str, &str)> = link.elements.iter().map(|e| (e.ai.as_str(), e.value.as_str())).collect();
+///
+/// assert_eq!(elements, vec![("01", "09506000134352"), ("10", "AB-123"), ("17", "260131")]);
+/// ```
+pub fn parse_digital_link(url: &str) -> Result<DigitalLink, Gs1Error> {
+    let rest = strip_prefix_ignore_case(url, "https://")
+        .or_else(|| strip_prefix_ignore_case(url, "http://"))
+        .ok_or(Gs1Error::NotDigitalLink)?;
+
+    let rest = rest.split('#').next().unwrap_or("");
+    let (path, query) = match rest.find('?') {
+        Some(question) => (&rest[..question], &rest[question + 1..]),
+        None => (rest, ""),
+    };
+
+    // The first segment is the domain
+    let segments: Vec<&str> = path.split('/').skip(1).filter(|s| !s.is_empty()).collect();
+
+    // The GS1 part of the path ends the path, a primary key followed by pairs of AIs and values
+    let start = (0..segments.len())
+        .find(|start| {
+            (segments.len() - start).is_multiple_of(2)
+                && PRIMARY_KEYS.contains(&segments[*start])
+                && segments[*start..].chunks(2).all(|pair| known_element(pair[0], String::new()).is_some())
+        })
+        .ok_or(Gs1Error::NotDigitalLink)?;
+
+    let mut elements = vec![];
+    for pair in segments[start..].chunks(2) {
+        elements.push(digital_link_element(pair[0], &percent_decode(pair[1]))?);
+    }
+
+    // Query parameters that are not AIs are for the resolver, not part of the item
+    for (key, value) in query.split('&').filter_map(|pair| {
+        let mut parts = pair.splitn(2, '=');
+        Some((parts.next()?, parts.next()?))
+    }) {
+        if key.chars().all(|c| c.is_ascii_digit()) {
+            if let Some(element) = known_element(key, percent_decode(value)) {
+                elements.push(element?);
+            }
+        }
+    }
+
+    Ok(DigitalLink {
+        url: String::from(url),
+        elements,
+    })
+}
+
+pub(super) fn parse(content: &str) -> Option<ParsedContent> {
+    parse_digital_link(content).ok().map(ParsedContent::Gs1DigitalLink)
+}
+
+fn digital_link_element(ai: &str, value: &str) -> Result<Gs1Element, Gs1Error> {
+    // GTIN-8, GTIN-12 and GTIN-13 are written without leading zeros
+    let value = match (ai, value.len()) {
+        ("01", 8) | ("01", 12) | ("01", 13) => format!("{:0>14}", value),
+        _ => String::from(value),
+    };
+
+    known_element(ai, value).unwrap_or(Err(Gs1Error::NotDigitalLink))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(link: &DigitalLink) -> Vec<(&str, &str)> {
+        link.elements.iter().map(|e| (e.ai.as_str(), e.value.as_str())).collect()
+    }
+
+    #[test]
+    fn test_parse_digital_link() {
+        let url = "HTTPS://example.com/shop/01/09506000134352/21/A%2F1?3103=000750&linkType=gs1:pip#x";
+        let link = parse_digital_link(url).unwrap();
+        assert_eq!(pairs(&link), vec![("01", "09506000134352"), ("21", "A/1"), ("3103", "000750")]);
+
+        let link = parse_digital_link("https://example.com/00/106141411234567897").unwrap();
+        assert_eq!(pairs(&link), vec![("00", "106141411234567897")]);
+        assert_eq!(link.elements[0].title, "SSCC");
+
+        let link = parse_digital_link("http://example.com/01/12345670").unwrap();
+        assert_eq!(pairs(&link), vec![("01", "00000012345670")]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_digital_link("https://example.com/products/123"), Err(Gs1Error::NotDigitalLink));
+        assert_eq!(parse_digital_link("https://example.com/10/ABC"), Err(Gs1Error::NotDigitalLink));
+        assert_eq!(parse_digital_link("https://example.com/01/09506000134352/21"), Err(Gs1Error::NotDigitalLink));
+        assert_eq!(parse_digital_link("ftp://example.com/01/09506000134352"), Err(Gs1Error::NotDigitalLink));
+        assert_eq!(
+            parse_digital_link("https://example.com/01/09506000134353"),
+            Err(Gs1Error::CheckDigit(String::from("01")))
+        );
+        assert_eq!(
+            parse_digital_link("https://example.com/01/09506000134352?17=2601"),
+            Err(Gs1Error::InvalidLength(String::from("17")))
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! EPC codes (also known as GiroCode), for SEPA credit transfers
+//!
+//! Defined in EPC069-12, every field is on its own line: service tag `BCD`, version, character set, identification
+//! `SCT`, BIC, name, IBAN, amount, purpose, structured reference, unstructured remittance and information for the
+//! user. Trailing empty fields may be left out.
+
+use super::{non_empty, ParsedContent};
+
+/// A SEPA credit transfer, from an EPC code
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpcTransfer {
+    /// Version of the format, 1 or 2
+    pub version: u8,
+    /// Character set of the code, from 1 (UTF-8) to 8 (ISO 8859-15)
+    pub character_set: u8,
+    /// BIC of the bank of the beneficiary, which is optional since version 2
+    pub bic: Option<String>,
+    /// Name of the beneficiary
+    pub name: String,
+    /// IBAN of the beneficiary, without spaces
+    pub iban: String,
+    /// Amount in euro cents, missing if the user should fill it in
+    pub amount: Option<u64>,
+    /// Purpose code of four letters, for example `CHAR` for a charity payment
+    pub purpose: Option<String>,
+    /// Structured creditor reference, for example `RF18539007547034`
+    pub reference: Option<String>,
+    /// Unstructured remittance information, used when there is no structured reference
+    pub remittance: Option<String>,
+    /// Information for the user, not part of the transfer
+    pub information: Option<String>,
+}
+
+/// Errors in EPC codes
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum EpcError {
+    /// The service tag `BCD` or the identification `SCT` is missing
+    #[error("Not an EPC code")]
+    NotEpc,
+    /// Only versions `001` and `002` exist
+    #[error("Unsupported EPC version {0}")]
+    UnsupportedVersion(String),
+    /// The character set is not a number from 1 to 8
+    #[error("Invalid EPC character set {0}")]
+    InvalidCharacterSet(String),
+    /// The BIC is malformed, or missing in a version 1 code
+    #[error("Invalid BIC {0:?}")]
+    InvalidBic(String),
+    /// The IBAN is malformed or its check digits are wrong
+    #[error("Invalid IBAN {0:?}")]
+    InvalidIban(String),
+    /// The amount is not in euros, has more than two decimals or is out of range
+    #[error("Invalid amount {0:?}")]
+    InvalidAmount(String),
+    /// The purpose code is not four letters
+    #[error("Invalid purpose {0:?}")]
+    InvalidPurpose(String),
+    /// The name of the beneficiary is missing
+    #[error("Missing name of the beneficiary")]
+    MissingName,
+    /// A code has either a structured reference or unstructured remittance information, not both
+    #[error("Both a structured reference and unstructured remittance information")]
+    ReferenceAndRemittance,
+    /// The field is longer than allowed
+    #[error("EPC field {0} is too long")]
+    TooLong(&'static str),
+}
+
+/// Parse an EPC code, validating every field
+///
+/// # Errors
+///
+/// Returns an `EpcError` if the content is not an EPC code, or any of its fields is invalid
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::content::parse_epc;
+///
+/// let transfer = parse_epc("BCD\n002\n1\nSCT\n\nRed Cross\nBE72 0000 0000 1616\nEUR12.5").unwrap();
+///
+/// assert_eq!(transfer.iban, "BE72000000001616");
+/// assert_eq!(transfer.amount, Some(1250));
+/// assert_eq!(transfer.bic, None);
+/// ```
+pub fn parse_epc(content: &str) -> Result<EpcTransfer, EpcError> {
+    let lines: Vec<&str> = content.lines().map(str::trim).collect();
+    let line = |index: usize| lines.get(index).copied().unwrap_or("");
+
+    if line(0) != "BCD" || line(3) != "SCT" || lines.len() > 12 {
+        return Err(EpcError::NotEpc);
+    }
+
+    let version = match line(1) {
+        "001" => 1,
+        "002" => 2,
+        other => return Err(EpcError::UnsupportedVersion(String::from(other))),
+    };
+
+    let character_set = match line(2).parse() {
+        Ok(set @ 1..=8) => set,
+        _ => return Err(EpcError::InvalidCharacterSet(String::from(line(2)))),
+    };
+
+    let bic = match line(4) {
+        "" if version == 2 => None,
+        bic if valid_bic(bic) => Some(String::from(bic)),
+        bic => return Err(EpcError::InvalidBic(String::from(bic))),
+    };
+
+    let name = non_empty(String::from(max_length(line(5), 70, "name")?)).ok_or(EpcError::MissingName)?;
+
+    let iban = line(6).replace(' ', "");
+    if !valid_iban(&iban) {
+        return Err(EpcError::InvalidIban(String::from(line(6))));
+    }
+
+    let amount = match line(7) {
+        "" => None,
+        amount => Some(cents(amount).ok_or_else(|| EpcError::InvalidAmount(String::from(amount)))?),
+    };
+
+    let purpose = match line(8) {
+        "" => None,
+        purpose if purpose.len() == 4 && purpose.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Some(String::from(purpose))
+        }
+        purpose => return Err(EpcError::InvalidPurpose(String::from(purpose))),
+    };
+
+    let reference = non_empty(String::from(max_length(line(9), 35, "reference")?));
+    let remittance = non_empty(String::from(max_length(line(10), 140, "remittance")?));
+
+    if reference.is_some() && remittance.is_some() {
+        return Err(EpcError::ReferenceAndRemittance);
+    }
+
+    Ok(EpcTransfer {
+        version,
+        character_set,
+        bic,
+        name,
+        iban,
+        amount,
+        purpose,
+        reference,
+        remittance,
+        information: non_empty(String::from(max_length(line(11), 70, "information")?)),
+    })
+}
+
+pub(super) fn parse(content: &str) -> Option<ParsedContent> {
+    parse_epc(content).ok().map(ParsedContent::SepaTransfer)
+}
+
+fn max_length<'a>(value: &'a str, max: usize, field: &'static str) -> Result<&'a str, EpcError> {
+    if value.chars().count() > max {
+        Err(EpcError::TooLong(field))
+    } else {
+        Ok(value)
+    }
+}
+
+// Bank code, country code, location code and optionally a branch code
+fn valid_bic(bic: &str) -> bool {
+    let bytes = bic.as_bytes();
+
+    (bytes.len() == 8 || bytes.len() == 11)
+        && bytes[..6].iter().all(u8::is_ascii_uppercase)
+        && bytes[6..].iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+}
+
+// ISO 13616: move the country code and check digits to the end, replace letters by 10 to 35, then mod 97 is 1
+fn valid_iban(iban: &str) -> bool {
+    let bytes = iban.as_bytes();
+
+    if bytes.len() < 15
+        || bytes.len() > 34
+        || !bytes[..2].iter().all(u8::is_ascii_uppercase)
+        || !bytes[2..4].iter().all(u8::is_ascii_digit)
+        || !bytes.iter().all(u8::is_ascii_alphanumeric)
+    {
+        return false;
+    }
+
+    let remainder = bytes[4..].iter().chain(&bytes[..4]).fold(0u32, |remainder, b| {
+        match char::from(*b).to_digit(36) {
+            Some(value) if value >= 10 => (remainder * 100 + value) % 97,
+            Some(value) => (remainder * 10 + value) % 97,
+            None => remainder,
+        }
+    });
+
+    remainder == 1
+}
+
+// EUR followed by 0.01 to 999999999.99, with at most two decimals
+fn cents(amount: &str) -> Option<u64> {
+    let amount = amount.strip_prefix("EUR")?;
+    let (euros, decimals) = match amount.find('.') {
+        Some(dot) => (&amount[..dot], &amount[dot + 1..]),
+        None => (amount, ""),
+    };
+
+    if euros.is_empty()
+        || euros.len() > 9
+        || decimals.len() > 2
+        || !euros.chars().chain(decimals.chars()).all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let cents = euros.parse::<u64>().ok()? * 100 + format!("{:0<2}", decimals).parse::<u64>().ok()?;
+
+    Some(cents).filter(|c| *c > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "BCD\n001\n1\nSCT\nBPOTBEB1\nRed Cross\nBE72000000001616\nEUR1\nCHAR\n\nUrgency fund\nThank you";
+
+    #[test]
+    fn test_parse_epc() {
+        assert_eq!(
+            parse_epc(CODE),
+            Ok(EpcTransfer {
+                version: 1,
+                character_set: 1,
+                bic: Some(String::from("BPOTBEB1")),
+                name: String::from("Red Cross"),
+                iban: String::from("BE72000000001616"),
+                amount: Some(100),
+                purpose: Some(String::from("CHAR")),
+                reference: None,
+                remittance: Some(String::from("Urgency fund")),
+                information: Some(String::from("Thank you")),
+            })
+        );
+
+        let crlf = "BCD\r\n002\r\n2\r\nSCT\r\n\r\nShop\r\nDE89 3704 0044 0532 0130 00\r\n\r\n\r\nRF18539007547034";
+        let transfer = parse_epc(crlf).unwrap();
+        assert_eq!(transfer.amount, None);
+        assert_eq!(transfer.reference, Some(String::from("RF18539007547034")));
+    }
+
+    #[test]
+    fn test_errors() {
+        let with = |index: usize, value: &str| {
+            let mut lines: Vec<&str> = CODE.lines().collect();
+            lines[index] = value;
+            parse_epc(&lines.join("\n"))
+        };
+
+        assert_eq!(with(3, "SCX"), Err(EpcError::NotEpc));
+        assert_eq!(with(1, "003"), Err(EpcError::UnsupportedVersion(String::from("003"))));
+        assert_eq!(with(2, "9"), Err(EpcError::InvalidCharacterSet(String::from("9"))));
+        assert_eq!(with(4, ""), Err(EpcError::InvalidBic(String::new())));
+        assert_eq!(with(4, "BPOTBE"), Err(EpcError::InvalidBic(String::from("BPOTBE"))));
+        assert_eq!(with(5, ""), Err(EpcError::MissingName));
+        assert_eq!(with(6, "BE73000000001616"), Err(EpcError::InvalidIban(String::from("BE73000000001616"))));
+        assert_eq!(with(7, "USD1.00"), Err(EpcError::InvalidAmount(String::from("USD1.00"))));
+        assert_eq!(with(7, "EUR1.001"), Err(EpcError::InvalidAmount(String::from("EUR1.001"))));
+        assert_eq!(with(7, "EUR0"), Err(EpcError::InvalidAmount(String::from("EUR0"))));
+        assert_eq!(with(8, "CHARITY"), Err(EpcError::InvalidPurpose(String::from("CHARITY"))));
+        assert_eq!(with(9, "RF18539007547034"), Err(EpcError::ReferenceAndRemittance));
+        assert_eq!(with(11, &"x".repeat(71)), Err(EpcError::TooLong("information")));
+    }
+
+    #[test]
+    fn test_cents() {
+        assert_eq!(cents("EUR12.5"), Some(1250));
+        assert_eq!(cents("EUR999999999.99"), Some(99_999_999_999));
+        assert_eq!(cents("EUR1000000000"), None);
+        assert_eq!(cents("EUR.50"), None);
+    }
+}
@@ -0,0 +1,392 @@
+//! GS1 element strings, as used on trade items and logistic units
+//!
+//! An element string is an Application Identifier (AI) followed by its value. AIs with a predefined length are
+//! simply concatenated, values of variable length are terminated by a group separator (ASCII 29) unless they are the
+//! last one. Codes are recognised by their symbology identifier (`]Q3` for QR, `]C1` for GS1-128, `]d2` for
+//! DataMatrix, `]e0` for DataBar, `]J1` for DotCode), or written in human readable form, with the AIs in brackets.
+
+use super::ParsedContent;
+
+const GS: char = '\u{1D}';
+
+const SYMBOLOGY_IDENTIFIERS: &[&str] = &["]Q3", "]C1", "]d2", "]e0", "]J1"];
+
+/// An Application Identifier with its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gs1Element {
+    /// The Application Identifier, for example `01`
+    pub ai: String,
+    /// The GS1 data title of the AI, for example `GTIN`
+    pub title: &'static str,
+    /// The value, without separators
+    pub value: String,
+}
+
+impl Gs1Element {
+    /// Number of decimals in the value, for the measure and amount AIs (31nn to 36nn and 39nn) where it is encoded
+    /// in the last digit of the AI
+    pub fn decimals(&self) -> Option<u32> {
+        match self.ai.as_bytes() {
+            [b'3', b'1'..=b'6' | b'9', _, d] => char::from(*d).to_digit(10),
+            _ => None,
+        }
+    }
+}
+
+/// Errors in GS1 element strings
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Gs1Error {
+    /// There are no element strings at all
+    #[error("No GS1 element strings")]
+    Empty,
+    /// The characters at this position are not a known Application Identifier
+    #[error("Unknown GS1 Application Identifier at position {0}")]
+    UnknownAi(usize),
+    /// The value is too long or too short for the AI
+    #[error("Invalid length for the value of GS1 AI ({0})")]
+    InvalidLength(String),
+    /// The value should only contain digits
+    #[error("Value of GS1 AI ({0}) is not numeric")]
+    NotNumeric(String),
+    /// The check digit of the GTIN, SSCC, GLN or other key does not match
+    #[error("Invalid check digit for GS1 AI ({0})")]
+    CheckDigit(String),
+    /// The URL has no primary key, followed by pairs of AIs and values, in its path
+    #[error("Not a GS1 Digital Link URL")]
+    NotDigitalLink,
+}
+
+#[derive(Copy, Clone)]
+enum Data {
+    /// Predefined length, all digits
+    Fixed(usize),
+    /// Variable length up to the maximum, digits only if numeric
+    Variable { max: usize, numeric: bool },
+}
+
+use self::Data::{Fixed, Variable};
+
+const fn x(max: usize) -> Data {
+    Variable { max, numeric: false }
+}
+
+const fn n(max: usize) -> Data {
+    Variable { max, numeric: true }
+}
+
+// AI prefix, length of the AI, data title, data, whether the value ends in a GS1 check digit.
+// The first matching prefix is used, so more specific prefixes come first.
+const AIS: &[(&str, usize, &str, Data, bool)] = &[
+    ("00", 2, "SSCC", Fixed(18), true),
+    ("01", 2, "GTIN", Fixed(14), true),
+    ("02", 2, "CONTENT", Fixed(14), true),
+    ("10", 2, "BATCH/LOT", x(20), false),
+    ("11", 2, "PROD DATE", Fixed(6), false),
+    ("12", 2, "DUE DATE", Fixed(6), false),
+    ("13", 2, "PACK DATE", Fixed(6), false),
+    ("15", 2, "BEST BEFORE or BEST BY", Fixed(6), false),
+    ("16", 2, "SELL BY", Fixed(6), false),
+    ("17", 2, "USE BY or EXPIRY", Fixed(6), false),
+    ("20", 2, "VARIANT", Fixed(2), false),
+    ("21", 2, "SERIAL", x(20), false),
+    ("22", 2, "CPV", x(20), false),
+    ("235", 3, "TPX", x(28), false),
+    ("240", 3, "ADDITIONAL ID", x(30), false),
+    ("241", 3, "CUST. PART No.", x(30), false),
+    ("242", 3, "MTO VARIANT", n(6), false),
+    ("243", 3, "PCN", x(20), false),
+    ("250", 3, "SECONDARY SERIAL", x(30), false),
+    ("251", 3, "REF. TO SOURCE", x(30), false),
+    ("253", 3, "GDTI", x(30), false),
+    ("254", 3, "GLN EXTENSION COMPONENT", x(20), false),
+    ("255", 3, "GCN", n(25), false),
+    ("30", 2, "VAR. COUNT", n(8), false),
+    ("310", 4, "NET WEIGHT (kg)", Fixed(6), false),
+    ("311", 4, "LENGTH (m)", Fixed(6), false),
+    ("312", 4, "WIDTH (m)", Fixed(6), false),
+    ("313", 4, "HEIGHT (m)", Fixed(6), false),
+    ("314", 4, "AREA (m²)", Fixed(6), false),
+    ("315", 4, "NET VOLUME (l)", Fixed(6), false),
+    ("316", 4, "NET VOLUME (m³)", Fixed(6), false),
+    ("320", 4, "NET WEIGHT (lb)", Fixed(6), false),
+    ("330", 4, "GROSS WEIGHT (kg)", Fixed(6), false),
+    ("31", 4, "MEASURE", Fixed(6), false),
+    ("32", 4, "MEASURE", Fixed(6), false),
+    ("33", 4, "MEASURE", Fixed(6), false),
+    ("34", 4, "MEASURE", Fixed(6), false),
+    ("35", 4, "MEASURE", Fixed(6), false),
+    ("36", 4, "MEASURE", Fixed(6), false),
+    ("37", 2, "COUNT", n(8), false),
+    ("390", 4, "AMOUNT", n(15), false),
+    ("391", 4, "AMOUNT", n(18), false),
+    ("392", 4, "PRICE", n(15), false),
+    ("393", 4, "PRICE", n(18), false),
+    ("394", 4, "PRCNT OFF", Fixed(4), false),
+    ("395", 4, "PRICE/UoM", Fixed(6), false),
+    ("400", 3, "ORDER NUMBER", x(30), false),
+    ("401", 3, "GINC", x(30), false),
+    ("402", 3, "GSIN", Fixed(17), true),
+    ("403", 3, "ROUTE", x(30), false),
+    ("410", 3, "SHIP TO LOC", Fixed(13), true),
+    ("411", 3, "BILL TO", Fixed(13), true),
+    ("412", 3, "PURCHASE FROM", Fixed(13), true),
+    ("413", 3, "SHIP FOR LOC", Fixed(13), true),
+    ("414", 3, "LOC No.", Fixed(13), true),
+    ("415", 3, "PAY TO", Fixed(13), true),
+    ("416", 3, "PROD/SERV LOC", Fixed(13), true),
+    ("417", 3, "PARTY", Fixed(13), true),
+    ("420", 3, "SHIP TO POST", x(20), false),
+    ("421", 3, "SHIP TO POST", x(12), false),
+    ("422", 3, "ORIGIN", Fixed(3), false),
+    ("423", 3, "COUNTRY - INITIAL PROCESS", n(15), false),
+    ("424", 3, "COUNTRY - PROCESS", Fixed(3), false),
+    ("425", 3, "COUNTRY - DISASSEMBLY", n(15), false),
+    ("426", 3, "COUNTRY - FULL PROCESS", Fixed(3), false),
+    ("427", 3, "ORIGIN SUBDIVISION", x(3), false),
+    ("7003", 4, "EXPIRY TIME", Fixed(10), false),
+    ("8003", 4, "GRAI", x(30), false),
+    ("8004", 4, "GIAI", x(30), false),
+    ("8005", 4, "PRICE PER UNIT", Fixed(6), false),
+    ("8006", 4, "ITIP", Fixed(18), false),
+    ("8007", 4, "IBAN", x(34), false),
+    ("8008", 4, "PROD TIME", n(12), false),
+    ("8010", 4, "CPID", x(30), false),
+    ("8013", 4, "GMN", x(25), false),
+    ("8017", 4, "GSRN - PROVIDER", Fixed(18), true),
+    ("8018", 4, "GSRN - RECIPIENT", Fixed(18), true),
+    ("8020", 4, "REF No.", x(25), false),
+    ("8200", 4, "PRODUCT URL", x(70), false),
+    ("90", 2, "INTERNAL", x(30), false),
+    ("91", 2, "INTERNAL", x(90), false),
+    ("92", 2, "INTERNAL", x(90), false),
+    ("93", 2, "INTERNAL", x(90), false),
+    ("94", 2, "INTERNAL", x(90), false),
+    ("95", 2, "INTERNAL", x(90), false),
+    ("96", 2, "INTERNAL", x(90), false),
+    ("97", 2, "INTERNAL", x(90), false),
+    ("98", 2, "INTERNAL", x(90), false),
+    ("99", 2, "INTERNAL", x(90), false),
+];
+
+type Entry = (&'static str, usize, &'static str, Data, bool);
+
+/// Split GS1 element strings into their AIs and values
+///
+/// Accepts the raw data, with or without symbology identifier, where variable length values are terminated by a
+/// group separator (ASCII 29), as well as the human readable form with the AIs in brackets, like
+/// `(01)09506000134352(10)AB-123`. Values are checked against the length and character set of their AI, and the
+/// check digits of keys like the GTIN and SSCC are verified.
+///
+/// # Errors
+///
+/// Returns a `Gs1Error` for unknown AIs and invalid values
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::content::parse_gs1;
+///
+/// let elements = parse_gs1("]Q30109506000134352172601311021AB-123").unwrap();
+///
+/// assert_eq!(elements[0].title, "GTIN");
+/// assert_eq!(elements[1].value, "260131");
+/// assert_eq!(elements[2].value, "21AB-123");
+/// ```
+pub fn parse_gs1(input: &str) -> Result<Vec<Gs1Element>, Gs1Error> {
+    let input = SYMBOLOGY_IDENTIFIERS
+        .iter()
+        .find_map(|id| input.strip_prefix(id))
+        .unwrap_or(input);
+
+    let elements = if input.starts_with('(') {
+        bracketed(input)?
+    } else {
+        raw(input)?
+    };
+
+    if elements.is_empty() {
+        return Err(Gs1Error::Empty);
+    }
+
+    Ok(elements)
+}
+
+pub(super) fn parse(content: &str) -> Option<ParsedContent> {
+    let recognised = content.starts_with('(') || SYMBOLOGY_IDENTIFIERS.iter().any(|id| content.starts_with(id));
+
+    if recognised {
+        parse_gs1(content).ok().map(ParsedContent::Gs1)
+    } else {
+        None
+    }
+}
+
+fn raw(input: &str) -> Result<Vec<Gs1Element>, Gs1Error> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut elements = vec![];
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        if chars[pos] == GS {
+            pos += 1;
+            continue;
+        }
+
+        let entry = lookup(&chars[pos..]).ok_or(Gs1Error::UnknownAi(pos))?;
+        let ai: String = chars[pos..pos + entry.1].iter().collect();
+        pos += entry.1;
+
+        let end = match entry.3 {
+            Fixed(length) => pos + length,
+            Variable { .. } => chars[pos..].iter().position(|c| *c == GS).map_or(chars.len(), |p| pos + p),
+        };
+
+        if end > chars.len() || chars[pos..end].contains(&GS) {
+            return Err(Gs1Error::InvalidLength(ai));
+        }
+
+        let value: String = chars[pos..end].iter().collect();
+        pos = end;
+
+        elements.push(element(entry, ai, value)?);
+    }
+
+    Ok(elements)
+}
+
+fn bracketed(input: &str) -> Result<Vec<Gs1Element>, Gs1Error> {
+    let mut elements = vec![];
+    let mut rest = input;
+    let mut pos = 0;
+
+    while !rest.is_empty() {
+        let close = match (rest.strip_prefix('('), rest.find(')')) {
+            (Some(_), Some(close)) => close,
+            _ => return Err(Gs1Error::UnknownAi(pos)),
+        };
+
+        let ai = &rest[1..close];
+        let value_end = rest[close..].find('(').map_or(rest.len(), |p| close + p);
+        let value = &rest[close + 1..value_end];
+
+        elements.push(known_element(ai, String::from(value)).ok_or(Gs1Error::UnknownAi(pos))??);
+
+        pos += value_end;
+        rest = &rest[value_end..];
+    }
+
+    Ok(elements)
+}
+
+/// The element for a complete AI, None if the AI is unknown
+pub(super) fn known_element(ai: &str, value: String) -> Option<Result<Gs1Element, Gs1Error>> {
+    match lookup(&ai.chars().collect::<Vec<_>>()) {
+        Some(entry) if entry.1 == ai.len() => Some(element(entry, String::from(ai), value)),
+        _ => None,
+    }
+}
+
+// The AI at the start of the input, the digits after the prefix are not checked against the table
+fn lookup(input: &[char]) -> Option<Entry> {
+    AIS.iter().copied().find(|(prefix, length, ..)| {
+        input.len() >= *length
+            && input[..*length].iter().all(|c| c.is_ascii_digit())
+            && prefix.chars().zip(input).all(|(p, c)| p == *c)
+    })
+}
+
+fn element(entry: Entry, ai: String, value: String) -> Result<Gs1Element, Gs1Error> {
+    let (_, _, title, data, check_digit) = entry;
+
+    let (valid_length, numeric) = match data {
+        Fixed(length) => (value.chars().count() == length, true),
+        Variable { max, numeric } => (!value.is_empty() && value.chars().count() <= max, numeric),
+    };
+
+    if !valid_length {
+        return Err(Gs1Error::InvalidLength(ai));
+    }
+
+    // Dates and the like have a predefined length, and are always numeric
+    if numeric && !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(Gs1Error::NotNumeric(ai));
+    }
+
+    if check_digit && !valid_check_digit(&value) {
+        return Err(Gs1Error::CheckDigit(ai));
+    }
+
+    Ok(Gs1Element { ai, title, value })
+}
+
+// GS1 mod 10: from the right, excluding the check digit itself, digits are weighted 3, 1, 3, 1, ...
+fn valid_check_digit(digits: &str) -> bool {
+    let digits: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+
+    match digits.split_last() {
+        Some((check, rest)) => {
+            let sum: u32 = rest
+                .iter()
+                .rev()
+                .enumerate()
+                .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+                .sum();
+
+            (10 - sum % 10) % 10 == *check
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(elements: &[Gs1Element]) -> Vec<(&str, &str)> {
+        elements.iter().map(|e| (e.ai.as_str(), e.value.as_str())).collect()
+    }
+
+    #[test]
+    fn test_raw() {
+        let elements = parse_gs1("]C1010950600013435210AB-123\u{1D}3103000750\u{1D}17260131").unwrap();
+
+        assert_eq!(
+            pairs(&elements),
+            vec![("01", "09506000134352"), ("10", "AB-123"), ("3103", "000750"), ("17", "260131")]
+        );
+        assert_eq!(elements[2].title, "NET WEIGHT (kg)");
+        assert_eq!(elements[2].decimals(), Some(3));
+        assert_eq!(elements[0].decimals(), None);
+    }
+
+    #[test]
+    fn test_bracketed() {
+        let elements = parse_gs1("(00)106141411234567897(3922)1299(21)A-1").unwrap();
+        assert_eq!(
+            pairs(&elements),
+            vec![("00", "106141411234567897"), ("3922", "1299"), ("21", "A-1")]
+        );
+        assert_eq!(elements[1].decimals(), Some(2));
+
+        assert_eq!(parse_gs1("(01)09506000134352(19)1"), Err(Gs1Error::UnknownAi(18)));
+        assert_eq!(parse_gs1("(310)000750"), Err(Gs1Error::UnknownAi(0)));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse_gs1("0109506000134353"), Err(Gs1Error::CheckDigit(String::from("01"))));
+        assert_eq!(parse_gs1("01095060001343"), Err(Gs1Error::InvalidLength(String::from("01"))));
+        assert_eq!(parse_gs1("1126013A"), Err(Gs1Error::NotNumeric(String::from("11"))));
+        assert_eq!(parse_gs1("0109506000134352\u{1D}19"), Err(Gs1Error::UnknownAi(17)));
+        assert_eq!(parse_gs1("10\u{1D}21X"), Err(Gs1Error::InvalidLength(String::from("10"))));
+        assert_eq!(parse_gs1("]Q3"), Err(Gs1Error::Empty));
+    }
+
+    #[test]
+    fn test_check_digit() {
+        assert!(valid_check_digit("09506000134352"));
+        assert!(valid_check_digit("106141411234567897"));
+        assert!(valid_check_digit("5412345000013"));
+        assert!(!valid_check_digit("5412345000014"));
+    }
+}
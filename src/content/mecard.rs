@@ -0,0 +1,190 @@
+//! `KEY:value;` formats: `MECARD:` and `MATMSG:`, and the field syntax shared with `WIFI:`
+//!
+//! Values escape `\`, `;`, `,`, `:` and `"` with a backslash
+
+use super::{non_empty, strip_prefix_ignore_case, Contact, Email, ParsedContent};
+
+pub(super) fn parse(content: &str) -> Option<ParsedContent> {
+    if let Some(body) = strip_prefix_ignore_case(content, "MECARD:") {
+        parse_mecard(&fields(body))
+    } else if let Some(body) = strip_prefix_ignore_case(content, "MATMSG:") {
+        parse_matmsg(&fields(body))
+    } else {
+        None
+    }
+}
+
+/// A field with an upper case key and its value, still escaped
+pub(super) struct Field {
+    pub(super) key: String,
+    pub(super) raw: String,
+}
+
+fn parse_mecard(fields: &[Field]) -> Option<ParsedContent> {
+    // Names are written as "last,first", addresses as "po box,extended,street,city,region,postal code,country"
+    let name = first_raw(fields, "N").map(|n| {
+        let mut parts = split_unescaped(n, ',');
+        parts.reverse();
+        join_non_empty(&parts, " ")
+    });
+    let address = first_raw(fields, "ADR").map(|a| join_non_empty(&split_unescaped(a, ','), ", "));
+
+    Some(ParsedContent::Contact(Contact {
+        name: name.and_then(non_empty),
+        phones: all(fields, "TEL"),
+        emails: all(fields, "EMAIL"),
+        organization: first(fields, "ORG").and_then(non_empty),
+        title: first(fields, "TITLE").and_then(non_empty),
+        address: address.and_then(non_empty),
+        urls: all(fields, "URL"),
+        note: first(fields, "NOTE").and_then(non_empty),
+    }))
+}
+
+fn parse_matmsg(fields: &[Field]) -> Option<ParsedContent> {
+    Some(ParsedContent::Email(Email {
+        to: vec![first(fields, "TO")?],
+        subject: first(fields, "SUB").and_then(non_empty),
+        body: first(fields, "BODY").and_then(non_empty),
+        ..Email::default()
+    }))
+}
+
+/// Split into fields at every unescaped `;`. Only the first unescaped `:` separates key and value
+pub(super) fn fields(body: &str) -> Vec<Field> {
+    let mut fields = vec![];
+    let mut key: Option<String> = None;
+    let mut current = String::new();
+    let mut chars = body.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push(c);
+                current.extend(chars.next());
+            }
+            ':' if key.is_none() => key = Some(std::mem::take(&mut current)),
+            ';' => {
+                if let Some(key) = key.take() {
+                    fields.push(Field {
+                        key: key.to_ascii_uppercase(),
+                        raw: std::mem::take(&mut current),
+                    });
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if let Some(key) = key {
+        fields.push(Field {
+            key: key.to_ascii_uppercase(),
+            raw: current,
+        });
+    }
+
+    fields
+}
+
+/// The raw value of the first field with the provided key
+pub(super) fn first_raw<'a>(fields: &'a [Field], key: &str) -> Option<&'a str> {
+    fields.iter().find(|f| f.key == key).map(|f| f.raw.as_str())
+}
+
+/// The unescaped value of the first field with the provided key
+pub(super) fn first(fields: &[Field], key: &str) -> Option<String> {
+    first_raw(fields, key).map(unescape)
+}
+
+fn all(fields: &[Field], key: &str) -> Vec<String> {
+    fields
+        .iter()
+        .filter(|f| f.key == key && !f.raw.is_empty())
+        .map(|f| unescape(&f.raw))
+        .collect()
+}
+
+// Unescaped parts of a raw value, separated by unescaped `separator`s
+fn split_unescaped(raw: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => parts.last_mut().unwrap().extend(chars.next()),
+            _ if c == separator => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    parts
+}
+
+fn join_non_empty(parts: &[String], separator: &str) -> String {
+    let parts: Vec<&str> = parts.iter().map(|p| p.trim()).filter(|p| !p.is_empty()).collect();
+    parts.join(separator)
+}
+
+/// Remove the backslashes from escaped characters
+pub(super) fn unescape(raw: &str) -> String {
+    let mut unescaped = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+/// Escape all special characters, the reverse of `unescape`
+pub(super) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if let '\\' | ';' | ',' | ':' | '"' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields() {
+        let fields = fields("S:a\\;b;p:c:d;URL:http\\://x;;");
+        let values: Vec<(&str, String)> = fields.iter().map(|f| (f.key.as_str(), unescape(&f.raw))).collect();
+
+        assert_eq!(
+            values,
+            vec![
+                ("S", String::from("a;b")),
+                ("P", String::from("c:d")),
+                ("URL", String::from("http://x")),
+            ]
+        );
+        assert_eq!(first(&super::fields("N:no terminator"), "N"), Some(String::from("no terminator")));
+    }
+
+    #[test]
+    fn test_split_unescaped() {
+        assert_eq!(split_unescaped("Doe\\, Jr.,Jane", ','), vec!["Doe, Jr.", "Jane"]);
+        assert_eq!(join_non_empty(&split_unescaped(",,Main Street 1,Amsterdam,,,NL", ','), ", "), "Main Street 1, Amsterdam, NL");
+    }
+
+    #[test]
+    fn test_escape() {
+        let value = "a\\b;c,d:e\"f";
+        assert_eq!(escape(value), "a\\\\b\\;c\\,d\\:e\\\"f");
+        assert_eq!(unescape(&escape(value)), value);
+    }
+}
@@ -0,0 +1,548 @@
+//! Parse decoded content into typed payloads
+//!
+//! QR Codes are mostly used to carry a handful of well known formats: URLs, WiFi credentials, contact cards,
+//! calendar events, payment requests and so on. [`ParsedContent::parse`] recognises them so applications can act on
+//! the content without writing their own parsers.
+//!
+//! # Example
+//! ```
+//! # extern crate bardecoder;
+//! use bardecoder::content::{ParsedContent, WiFi, WiFiSecurity};
+//!
+//! let parsed = ParsedContent::parse("WIFI:T:WPA;S:My Network;P:secret;;");
+//!
+//! assert_eq!(
+//!     parsed,
+//!     ParsedContent::WiFi(WiFi {
+//!         ssid: String::from("My Network"),
+//!         password: Some(String::from("secret")),
+//!         security: WiFiSecurity::Wpa,
+//!         hidden: false,
+//!     })
+//! );
+//! ```
+//!
+//! [`ParsedContent::parse`]: enum.ParsedContent.html#method.parse
+
+mod digital_link;
+mod epc;
+mod gs1;
+mod mecard;
+mod otp;
+mod uri;
+mod vcard;
+mod wifi;
+
+pub use self::digital_link::{parse_digital_link, DigitalLink};
+pub use self::epc::{parse_epc, EpcError, EpcTransfer};
+pub use self::gs1::{parse_gs1, Gs1Element, Gs1Error};
+pub use self::otp::{Otp, OtpAlgorithm, OtpKind};
+
+/// Decoded content, parsed by type
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedContent {
+    /// A web address, starting with `http://` or `https://`
+    Url(String),
+    /// Credentials to join a wireless network
+    WiFi(WiFi),
+    /// A contact card, in vCard or MECARD format
+    Contact(Contact),
+    /// A location on earth
+    Geo(Geo),
+    /// An email to send, from a `mailto:` URI or MATMSG
+    Email(Email),
+    /// A phone number to call
+    Phone(Phone),
+    /// A text message to send
+    Sms(Sms),
+    /// An event from an iCalendar `VEVENT`
+    CalendarEvent(CalendarEvent),
+    /// A payment request from a URI, such as a Bitcoin address
+    Payment(Payment),
+    /// A SEPA credit transfer, from an EPC code (also known as GiroCode)
+    SepaTransfer(EpcTransfer),
+    /// A one-time password account, from an `otpauth://` URI
+    Otp(Otp),
+    /// One-time password accounts exported from an authenticator app, from an `otpauth-migration://` URI
+    OtpMigration(Vec<Otp>),
+    /// GS1 element strings, with a symbology identifier such as `]Q3` or in human readable form
+    Gs1(Vec<Gs1Element>),
+    /// A GS1 Digital Link URL, with its element strings
+    Gs1DigitalLink(DigitalLink),
+    /// Anything that is not recognised, unchanged
+    Text(String),
+}
+
+/// Credentials to join a wireless network
+///
+/// Use [`to_payload`] to go the other way and create the content for a QR Code
+///
+/// [`to_payload`]: #method.to_payload
+#[derive(Debug, Clone, PartialEq)]
+pub struct WiFi {
+    /// Network name
+    pub ssid: String,
+    /// Password, if the network has one
+    pub password: Option<String>,
+    /// Authentication type
+    pub security: WiFiSecurity,
+    /// Whether the network does not broadcast its name
+    pub hidden: bool,
+}
+
+/// Authentication type of a wireless network
+#[derive(Debug, Clone, PartialEq)]
+pub enum WiFiSecurity {
+    /// Open network
+    None,
+    /// WEP
+    Wep,
+    /// WPA, WPA2 or WPA3
+    Wpa,
+    /// Any other authentication type, as written in the code
+    Other(String),
+}
+
+/// A contact card
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Contact {
+    /// Full name
+    pub name: Option<String>,
+    /// Phone numbers
+    pub phones: Vec<String>,
+    /// Email addresses
+    pub emails: Vec<String>,
+    /// Company or organization
+    pub organization: Option<String>,
+    /// Job title
+    pub title: Option<String>,
+    /// Postal address, with its parts separated by commas
+    pub address: Option<String>,
+    /// Websites
+    pub urls: Vec<String>,
+    /// Free text note
+    pub note: Option<String>,
+}
+
+/// A location on earth, from a `geo:` URI
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geo {
+    /// Latitude in degrees
+    pub latitude: f64,
+    /// Longitude in degrees
+    pub longitude: f64,
+    /// Altitude in meters
+    pub altitude: Option<f64>,
+    /// Uncertainty of the location in meters
+    pub uncertainty: Option<f64>,
+    /// Search query, for example the name of the place
+    pub query: Option<String>,
+}
+
+/// An email to send
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Email {
+    /// Recipient addresses
+    pub to: Vec<String>,
+    /// Addresses to send a copy to
+    pub cc: Vec<String>,
+    /// Addresses to send a blind copy to
+    pub bcc: Vec<String>,
+    /// Subject line
+    pub subject: Option<String>,
+    /// Message body
+    pub body: Option<String>,
+}
+
+/// A phone number to call, from a `tel:` URI
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phone {
+    /// The number, as written in the code
+    pub number: String,
+    /// Extension to dial after the call connects
+    pub extension: Option<String>,
+}
+
+/// A text message to send
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sms {
+    /// Recipient phone numbers
+    pub numbers: Vec<String>,
+    /// Message text
+    pub message: Option<String>,
+}
+
+/// An event from an iCalendar `VEVENT`
+///
+/// Start and end are kept as written, for example `20240101T120000Z`, as they can be in any time zone
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CalendarEvent {
+    /// Title of the event
+    pub summary: Option<String>,
+    /// Start date or date-time
+    pub start: Option<String>,
+    /// End date or date-time
+    pub end: Option<String>,
+    /// Where the event takes place
+    pub location: Option<String>,
+    /// Longer description
+    pub description: Option<String>,
+}
+
+/// A payment request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payment {
+    /// Payment method, the URI scheme, for example `bitcoin`
+    pub scheme: String,
+    /// Where to send the money to, for example a wallet address
+    pub address: String,
+    /// Amount, as written in the code
+    pub amount: Option<String>,
+    /// Name of the receiver
+    pub label: Option<String>,
+    /// Message for the receiver
+    pub message: Option<String>,
+}
+
+impl ParsedContent {
+    /// Parse decoded content
+    ///
+    /// Content that is not recognised, or is malformed, is returned as [`ParsedContent::Text`]
+    ///
+    /// [`ParsedContent::Text`]: #variant.Text
+    pub fn parse(content: &str) -> ParsedContent {
+        let trimmed = content.trim();
+
+        otp::parse(trimmed)
+            .or_else(|| digital_link::parse(trimmed))
+            .or_else(|| uri::parse(trimmed))
+            .or_else(|| wifi::parse(trimmed))
+            .or_else(|| mecard::parse(trimmed))
+            .or_else(|| vcard::parse(trimmed))
+            .or_else(|| epc::parse(trimmed))
+            .or_else(|| gs1::parse(trimmed))
+            .unwrap_or_else(|| ParsedContent::Text(String::from(content)))
+    }
+}
+
+// Split off a case insensitive prefix
+fn strip_prefix_ignore_case<'a>(content: &'a str, prefix: &str) -> Option<&'a str> {
+    match content.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&content[prefix.len()..]),
+        _ => None,
+    }
+}
+
+// None for empty values, which codes are full of
+fn non_empty(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        assert_eq!(
+            ParsedContent::parse("https://example.com/path?a=b"),
+            ParsedContent::Url(String::from("https://example.com/path?a=b"))
+        );
+        assert_eq!(
+            ParsedContent::parse("HTTP://EXAMPLE.COM"),
+            ParsedContent::Url(String::from("HTTP://EXAMPLE.COM"))
+        );
+        assert_eq!(
+            ParsedContent::parse("http://not a url"),
+            ParsedContent::Text(String::from("http://not a url"))
+        );
+    }
+
+    #[test]
+    fn test_wifi() {
+        assert_eq!(
+            ParsedContent::parse("WIFI:S:\"cafe\\;bar\";T:nopass;H:true;;"),
+            ParsedContent::WiFi(WiFi {
+                ssid: String::from("cafe;bar"),
+                password: None,
+                security: WiFiSecurity::None,
+                hidden: true,
+            })
+        );
+        assert_eq!(
+            ParsedContent::parse("wifi:T:WPA2-EAP;S:home;P:pass:word;;"),
+            ParsedContent::WiFi(WiFi {
+                ssid: String::from("home"),
+                password: Some(String::from("pass:word")),
+                security: WiFiSecurity::Other(String::from("WPA2-EAP")),
+                hidden: false,
+            })
+        );
+        assert_eq!(
+            ParsedContent::parse("WIFI:T:WPA;P:no ssid;;"),
+            ParsedContent::Text(String::from("WIFI:T:WPA;P:no ssid;;"))
+        );
+    }
+
+    #[test]
+    fn test_contact() {
+        assert_eq!(
+            ParsedContent::parse("MECARD:N:Doe,Jane;TEL:+3112345678;TEL:+3187654321;EMAIL:jane@example.com;;"),
+            ParsedContent::Contact(Contact {
+                name: Some(String::from("Jane Doe")),
+                phones: vec![String::from("+3112345678"), String::from("+3187654321")],
+                emails: vec![String::from("jane@example.com")],
+                ..Contact::default()
+            })
+        );
+
+        let vcard = "BEGIN:VCARD\r\nVERSION:3.0\r\nN:Doe;Jane;;;\r\nFN:Jane Doe\r\nORG:Example Inc.\r\n\
+                     TITLE:Engineer\r\nTEL;TYPE=CELL:+3112345678\r\nEMAIL;TYPE=INTERNET:jane@example.com\r\n\
+                     ADR;TYPE=WORK:;;Main Street 1;Amsterdam;;1000 AA;Netherlands\r\nURL:https://example.com\r\n\
+                     NOTE:Line one\\nline two\r\nEND:VCARD";
+        assert_eq!(
+            ParsedContent::parse(vcard),
+            ParsedContent::Contact(Contact {
+                name: Some(String::from("Jane Doe")),
+                phones: vec![String::from("+3112345678")],
+                emails: vec![String::from("jane@example.com")],
+                organization: Some(String::from("Example Inc.")),
+                title: Some(String::from("Engineer")),
+                address: Some(String::from("Main Street 1, Amsterdam, 1000 AA, Netherlands")),
+                urls: vec![String::from("https://example.com")],
+                note: Some(String::from("Line one\nline two")),
+            })
+        );
+
+        // vCard 2.1 as exported by phones, and vCard 4.0 with URI values
+        let vcard = "BEGIN:VCARD\nVERSION:2.1\nN;CHARSET=UTF-8;ENCODING=QUOTED-PRINTABLE:M=C3=BCller;J=C3=BCrgen\n\
+                     TEL;CELL:+491701234567\nEND:VCARD";
+        assert_eq!(
+            ParsedContent::parse(vcard),
+            ParsedContent::Contact(Contact {
+                name: Some(String::from("Jürgen Müller")),
+                phones: vec![String::from("+491701234567")],
+                ..Contact::default()
+            })
+        );
+
+        let vcard = "BEGIN:VCARD\nVERSION:4.0\nFN:Jane Doe\nTEL;VALUE=uri;TYPE=cell:tel:+3112345678\n\
+                     EMAIL:mailto:jane@example.com\nEND:VCARD";
+        assert_eq!(
+            ParsedContent::parse(vcard),
+            ParsedContent::Contact(Contact {
+                name: Some(String::from("Jane Doe")),
+                phones: vec![String::from("+3112345678")],
+                emails: vec![String::from("jane@example.com")],
+                ..Contact::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_geo() {
+        assert_eq!(
+            ParsedContent::parse("geo:52.3676,4.9041,12?q=Amsterdam%20Centraal"),
+            ParsedContent::Geo(Geo {
+                latitude: 52.3676,
+                longitude: 4.9041,
+                altitude: Some(12.0),
+                uncertainty: None,
+                query: Some(String::from("Amsterdam Centraal")),
+            })
+        );
+        assert_eq!(
+            ParsedContent::parse("GEO:-33.8688,151.2093;crs=wgs84;u=35"),
+            ParsedContent::Geo(Geo {
+                latitude: -33.8688,
+                longitude: 151.2093,
+                altitude: None,
+                uncertainty: Some(35.0),
+                query: None,
+            })
+        );
+        assert_eq!(ParsedContent::parse("geo:north"), ParsedContent::Text(String::from("geo:north")));
+        assert_eq!(ParsedContent::parse("geo:91,0"), ParsedContent::Text(String::from("geo:91,0")));
+        assert_eq!(ParsedContent::parse("geo:1,2;crs=moon"), ParsedContent::Text(String::from("geo:1,2;crs=moon")));
+    }
+
+    #[test]
+    fn test_email() {
+        let expected = ParsedContent::Email(Email {
+            to: vec![String::from("info@example.com")],
+            subject: Some(String::from("Hello there")),
+            body: Some(String::from("1+1=2")),
+            ..Email::default()
+        });
+
+        assert_eq!(ParsedContent::parse("mailto:info@example.com?subject=Hello%20there&body=1+1%3D2"), expected);
+        assert_eq!(ParsedContent::parse("MATMSG:TO:info@example.com;SUB:Hello there;BODY:1+1=2;;"), expected);
+
+        let mailto = "mailto:a@example.com,b@example.com?To=c@example.com\
+                      &cc=d@example.com,%20e@example.com&bcc=f@example.com&x-mailer=1";
+        assert_eq!(
+            ParsedContent::parse(mailto),
+            ParsedContent::Email(Email {
+                to: vec![
+                    String::from("a@example.com"),
+                    String::from("b@example.com"),
+                    String::from("c@example.com"),
+                ],
+                cc: vec![String::from("d@example.com"), String::from("e@example.com")],
+                bcc: vec![String::from("f@example.com")],
+                ..Email::default()
+            })
+        );
+        assert_eq!(
+            ParsedContent::parse("mailto:?to=a@example.com"),
+            ParsedContent::Email(Email {
+                to: vec![String::from("a@example.com")],
+                ..Email::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_phone_and_sms() {
+        assert_eq!(
+            ParsedContent::parse("tel:+31-20-1234567"),
+            ParsedContent::Phone(Phone {
+                number: String::from("+31-20-1234567"),
+                extension: None,
+            })
+        );
+        assert_eq!(
+            ParsedContent::parse("TEL:+1-201-555-0123;ext=1234;phone-context=example.com"),
+            ParsedContent::Phone(Phone {
+                number: String::from("+1-201-555-0123"),
+                extension: Some(String::from("1234")),
+            })
+        );
+        assert_eq!(ParsedContent::parse("tel:;ext=1"), ParsedContent::Text(String::from("tel:;ext=1")));
+
+        let expected = ParsedContent::Sms(Sms {
+            numbers: vec![String::from("+3112345678")],
+            message: Some(String::from("Hi: there")),
+        });
+        assert_eq!(ParsedContent::parse("SMSTO:+3112345678:Hi: there"), expected);
+        assert_eq!(ParsedContent::parse("sms:+3112345678?body=Hi%3A%20there"), expected);
+        assert_eq!(
+            ParsedContent::parse("sms:+3112345678,+3187654321"),
+            ParsedContent::Sms(Sms {
+                numbers: vec![String::from("+3112345678"), String::from("+3187654321")],
+                message: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_calendar_event() {
+        let event = "BEGIN:VCALENDAR\nVERSION:2.0\nBEGIN:VEVENT\nSUMMARY:Release party\nDTSTART:20240101T200000Z\n\
+                     DTEND;TZID=Europe/Amsterdam:20240102T020000\nLOCATION:Office\\, 2nd floor\n\
+                     DESCRIPTION:Bring\n  snacks\nEND:VEVENT\nEND:VCALENDAR";
+
+        assert_eq!(
+            ParsedContent::parse(event),
+            ParsedContent::CalendarEvent(CalendarEvent {
+                summary: Some(String::from("Release party")),
+                start: Some(String::from("20240101T200000Z")),
+                end: Some(String::from("20240102T020000")),
+                location: Some(String::from("Office, 2nd floor")),
+                description: Some(String::from("Bring snacks")),
+            })
+        );
+    }
+
+    #[test]
+    fn test_payment() {
+        assert_eq!(
+            ParsedContent::parse("bitcoin:1BoatSLRHtKNngkdXEeobR76b53LETtpyT?amount=0.01&label=Coffee%20Shop"),
+            ParsedContent::Payment(Payment {
+                scheme: String::from("bitcoin"),
+                address: String::from("1BoatSLRHtKNngkdXEeobR76b53LETtpyT"),
+                amount: Some(String::from("0.01")),
+                label: Some(String::from("Coffee Shop")),
+                message: None,
+            })
+        );
+
+        let epc = "BCD\n002\n1\nSCT\nBPOTBEB1\nRed Cross\nBE72000000001616\nEUR12.50\n\n\nDonation";
+        assert_eq!(
+            ParsedContent::parse(epc),
+            ParsedContent::SepaTransfer(EpcTransfer {
+                version: 2,
+                character_set: 1,
+                bic: Some(String::from("BPOTBEB1")),
+                name: String::from("Red Cross"),
+                iban: String::from("BE72000000001616"),
+                amount: Some(1250),
+                purpose: None,
+                reference: None,
+                remittance: Some(String::from("Donation")),
+                information: None,
+            })
+        );
+
+        // Invalid check digits in the IBAN
+        let epc = "BCD\n002\n1\nSCT\n\nRed Cross\nBE72000000001617";
+        assert_eq!(ParsedContent::parse(epc), ParsedContent::Text(String::from(epc)));
+    }
+
+    #[test]
+    fn test_otp() {
+        assert_eq!(
+            ParsedContent::parse("otpauth://totp/ACME%20Co:john@example.com?secret=JBSWY3DPEHPK3PXP&issuer=ACME%20Co"),
+            ParsedContent::Otp(Otp {
+                kind: OtpKind::Totp { period: 30 },
+                issuer: Some(String::from("ACME Co")),
+                account: String::from("john@example.com"),
+                secret: b"Hello!\xDE\xAD\xBE\xEF".to_vec(),
+                algorithm: OtpAlgorithm::Sha1,
+                digits: 6,
+            })
+        );
+
+        let missing_secret = "otpauth://totp/john@example.com";
+        assert_eq!(ParsedContent::parse(missing_secret), ParsedContent::Text(String::from(missing_secret)));
+    }
+
+    #[test]
+    fn test_gs1() {
+        match ParsedContent::parse("]Q30109506000134352172601311021AB-123") {
+            ParsedContent::Gs1(elements) => {
+                let ais: Vec<&str> = elements.iter().map(|e| e.ai.as_str()).collect();
+                assert_eq!(ais, vec!["01", "17", "10"]);
+            }
+            other => panic!("Expected GS1 content, got {:?}", other),
+        }
+
+        match ParsedContent::parse("https://id.gs1.org/01/09506000134352/10/AB-123") {
+            ParsedContent::Gs1DigitalLink(link) => {
+                assert_eq!(link.url, "https://id.gs1.org/01/09506000134352/10/AB-123");
+                assert_eq!(link.elements.len(), 2);
+            }
+            other => panic!("Expected a GS1 Digital Link, got {:?}", other),
+        }
+
+        // Without symbology identifier or brackets it could be anything
+        assert_eq!(
+            ParsedContent::parse("0109506000134352"),
+            ParsedContent::Text(String::from("0109506000134352"))
+        );
+        assert_eq!(
+            ParsedContent::parse("(01)09506000134353"),
+            ParsedContent::Text(String::from("(01)09506000134353"))
+        );
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(ParsedContent::parse("01234567"), ParsedContent::Text(String::from("01234567")));
+        assert_eq!(ParsedContent::parse(""), ParsedContent::Text(String::new()));
+        assert_eq!(ParsedContent::parse("note: call me"), ParsedContent::Text(String::from("note: call me")));
+    }
+}
@@ -0,0 +1,357 @@
+//! One-time password accounts for authenticator apps
+//!
+//! `otpauth://totp/<issuer>:<account>?secret=<base32>&issuer=...&algorithm=...&digits=...&period=...` as defined by
+//! the Google Authenticator key URI format, and `otpauth-migration://offline?data=<base64>` exports, which hold a
+//! protobuf encoded batch of accounts.
+
+use std::convert::TryFrom;
+
+use super::uri::{percent_decode, query_value, split_query};
+use super::vcard::base64;
+use super::{strip_prefix_ignore_case, ParsedContent};
+
+/// A one-time password account
+#[derive(Debug, Clone, PartialEq)]
+pub struct Otp {
+    /// Time or counter based
+    pub kind: OtpKind,
+    /// Provider of the account, for example `Example Inc`
+    pub issuer: Option<String>,
+    /// Name of the account, usually a user name or email address
+    pub account: String,
+    /// Shared secret
+    pub secret: Vec<u8>,
+    /// Hash algorithm of the HMAC
+    pub algorithm: OtpAlgorithm,
+    /// Length of the generated passwords
+    pub digits: u32,
+}
+
+/// Whether passwords are generated from the time or from a counter
+#[derive(Debug, Clone, PartialEq)]
+pub enum OtpKind {
+    /// TOTP, a new password every `period` seconds
+    Totp {
+        /// Seconds a password is valid
+        period: u32,
+    },
+    /// HOTP, a new password every time the counter is incremented
+    Hotp {
+        /// Initial value of the counter
+        counter: u64,
+    },
+}
+
+/// Hash algorithm of a one-time password
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OtpAlgorithm {
+    /// SHA-1, the default
+    Sha1,
+    /// SHA-256
+    Sha256,
+    /// SHA-512
+    Sha512,
+    /// MD5, only found in migration exports
+    Md5,
+}
+
+impl Otp {
+    /// The secret as unpadded base32, the way authenticator apps show it for manual entry
+    pub fn secret_base32(&self) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+        let mut encoded = String::with_capacity(self.secret.len().div_ceil(5) * 8);
+        let mut buffer = 0u32;
+        let mut bits = 0;
+
+        for byte in &self.secret {
+            buffer = (buffer << 8) | u32::from(*byte);
+            bits += 8;
+
+            while bits >= 5 {
+                bits -= 5;
+                encoded.push(char::from(ALPHABET[((buffer >> bits) & 0x1F) as usize]));
+            }
+        }
+
+        if bits > 0 {
+            encoded.push(char::from(ALPHABET[((buffer << (5 - bits)) & 0x1F) as usize]));
+        }
+
+        encoded
+    }
+}
+
+pub(super) fn parse(content: &str) -> Option<ParsedContent> {
+    if let Some(rest) = strip_prefix_ignore_case(content, "otpauth://") {
+        parse_otpauth(rest).map(ParsedContent::Otp)
+    } else if let Some(rest) = strip_prefix_ignore_case(content, "otpauth-migration://") {
+        parse_migration(rest).map(ParsedContent::OtpMigration)
+    } else {
+        None
+    }
+}
+
+fn parse_otpauth(rest: &str) -> Option<Otp> {
+    let (path, query) = split_query(rest);
+    let slash = path.find('/')?;
+    let label = percent_decode(&path[slash + 1..]);
+
+    let parameter = |name| query_value(query, name);
+    let number = |name, default| match parameter(name) {
+        Some(value) => value.parse().ok(),
+        None => Some(default),
+    };
+
+    let kind = match path[..slash].to_ascii_lowercase().as_str() {
+        "totp" => OtpKind::Totp {
+            period: number("period", 30).filter(|p| *p > 0)?,
+        },
+        "hotp" => OtpKind::Hotp {
+            counter: parameter("counter")?.parse().ok()?,
+        },
+        _ => return None,
+    };
+
+    let algorithm = match parameter("algorithm") {
+        None => OtpAlgorithm::Sha1,
+        Some(algorithm) => match algorithm.to_ascii_uppercase().as_str() {
+            "SHA1" => OtpAlgorithm::Sha1,
+            "SHA256" => OtpAlgorithm::Sha256,
+            "SHA512" => OtpAlgorithm::Sha512,
+            _ => return None,
+        },
+    };
+
+    // The label is "issuer:account", the issuer parameter takes precedence over its prefix
+    let (prefix, account) = match label.find(':') {
+        Some(colon) => (Some(label[..colon].trim()), label[colon + 1..].trim()),
+        None => (None, label.trim()),
+    };
+    let issuer = parameter("issuer").or_else(|| prefix.filter(|p| !p.is_empty()).map(String::from));
+
+    let secret = base32(&parameter("secret")?)?;
+    if secret.is_empty() {
+        return None;
+    }
+
+    Some(Otp {
+        kind,
+        issuer,
+        account: String::from(account),
+        secret,
+        algorithm,
+        digits: number("digits", 6).filter(|d| (6..=8).contains(d))?,
+    })
+}
+
+// The data parameter is a MigrationPayload message, with its accounts in field 1
+fn parse_migration(rest: &str) -> Option<Vec<Otp>> {
+    let (_, query) = split_query(rest);
+    let data = base64(&query_value(query, "data")?)?;
+
+    let mut accounts = vec![];
+    for (field, value) in protobuf(&data)? {
+        if let (1, Value::Bytes(parameters)) = (field, value) {
+            accounts.push(parse_parameters(parameters)?);
+        }
+    }
+
+    if accounts.is_empty() {
+        None
+    } else {
+        Some(accounts)
+    }
+}
+
+// OtpParameters: secret, name, issuer, algorithm, digits, type and counter
+fn parse_parameters(message: &[u8]) -> Option<Otp> {
+    let mut otp = Otp {
+        kind: OtpKind::Totp { period: 30 },
+        issuer: None,
+        account: String::new(),
+        secret: vec![],
+        algorithm: OtpAlgorithm::Sha1,
+        digits: 6,
+    };
+    let mut hotp = false;
+    let mut counter = 0;
+
+    for (field, value) in protobuf(message)? {
+        match (field, value) {
+            (1, Value::Bytes(secret)) => otp.secret = secret.to_vec(),
+            (2, Value::Bytes(name)) => otp.account = String::from_utf8_lossy(name).into_owned(),
+            (3, Value::Bytes(issuer)) if !issuer.is_empty() => {
+                otp.issuer = Some(String::from_utf8_lossy(issuer).into_owned())
+            }
+            (4, Value::Varint(algorithm)) => {
+                otp.algorithm = match algorithm {
+                    2 => OtpAlgorithm::Sha256,
+                    3 => OtpAlgorithm::Sha512,
+                    4 => OtpAlgorithm::Md5,
+                    _ => OtpAlgorithm::Sha1,
+                }
+            }
+            (5, Value::Varint(digits)) => otp.digits = if digits == 2 { 8 } else { 6 },
+            (6, Value::Varint(kind)) => hotp = kind == 1,
+            (7, Value::Varint(value)) => counter = value,
+            _ => {}
+        }
+    }
+
+    if hotp {
+        otp.kind = OtpKind::Hotp { counter };
+    }
+
+    // The name can still hold the issuer as a prefix
+    if let Some(colon) = otp.account.find(':') {
+        if otp.issuer.is_none() {
+            otp.issuer = Some(String::from(otp.account[..colon].trim()));
+        }
+        otp.account = String::from(otp.account[colon + 1..].trim());
+    }
+
+    if otp.secret.is_empty() {
+        None
+    } else {
+        Some(otp)
+    }
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+// The fields of a protobuf message, only varints and length delimited values are used by the migration format
+fn protobuf(mut message: &[u8]) -> Option<Vec<(u64, Value<'_>)>> {
+    let mut fields = vec![];
+
+    while !message.is_empty() {
+        let key = varint(&mut message)?;
+
+        let value = match key & 0x7 {
+            0 => Value::Varint(varint(&mut message)?),
+            2 => {
+                let length = usize::try_from(varint(&mut message)?).ok()?;
+                let bytes = message.get(..length)?;
+                message = &message[length..];
+
+                Value::Bytes(bytes)
+            }
+            _ => return None,
+        };
+
+        fields.push((key >> 3, value));
+    }
+
+    Some(fields)
+}
+
+fn varint(message: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = message.split_first()?;
+        *message = rest;
+        value |= u64::from(byte & 0x7F) << shift;
+
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+// RFC 4648 base32, case insensitive, ignoring padding and spaces
+fn base32(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in encoded.bytes().filter(|c| *c != b'=' && *c != b' ') {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+
+        buffer = (buffer << 5) | u32::from(value);
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_otpauth() {
+        let otp = parse_otpauth(
+            "totp/Example%20Inc:alice@example.com?secret=JBSWY3DPEHPK3PXP&algorithm=sha256&digits=8&period=60",
+        )
+        .unwrap();
+
+        assert_eq!(
+            otp,
+            Otp {
+                kind: OtpKind::Totp { period: 60 },
+                issuer: Some(String::from("Example Inc")),
+                account: String::from("alice@example.com"),
+                secret: b"Hello!\xDE\xAD\xBE\xEF".to_vec(),
+                algorithm: OtpAlgorithm::Sha256,
+                digits: 8,
+            }
+        );
+        assert_eq!(otp.secret_base32(), "JBSWY3DPEHPK3PXP");
+
+        let otp = parse_otpauth("hotp/bob?secret=jbswy3dp&issuer=Other&counter=5").unwrap();
+        assert_eq!(otp.kind, OtpKind::Hotp { counter: 5 });
+        assert_eq!(otp.issuer, Some(String::from("Other")));
+        assert_eq!(otp.digits, 6);
+
+        assert_eq!(parse_otpauth("hotp/bob?secret=JBSWY3DP"), None);
+        assert_eq!(parse_otpauth("totp/bob?secret=JBSWY3D1"), None);
+        assert_eq!(parse_otpauth("totp/bob?secret=JBSWY3DP&digits=12"), None);
+        assert_eq!(parse_otpauth("totp/bob?secret=JBSWY3DP&algorithm=MD4"), None);
+        assert_eq!(parse_otpauth("totp/bob"), None);
+    }
+
+    #[test]
+    fn test_migration() {
+        // Two accounts: a TOTP with issuer and SHA-512, and an 8 digit HOTP with the issuer in its name
+        let payload = [
+            &[0x0A, 0x1A, 0x0A, 0x02, 0x12, 0x34, 0x12, 0x05][..],
+            b"alice",
+            &[0x1A, 0x07],
+            b"Example",
+            &[0x20, 0x03, 0x28, 0x01, 0x30, 0x02],
+            &[0x0A, 0x15, 0x0A, 0x01, 0xFF, 0x12, 0x09],
+            b"Other:bob",
+            &[0x28, 0x02, 0x30, 0x01, 0x38, 0xAC, 0x02, 0x10, 0x01],
+        ]
+        .concat();
+        let data = "ChoKAhI0EgVhbGljZRoHRXhhbXBsZSADKAEwAgoVCgH/EglPdGhlcjpib2IoAjABOKwCEAE=";
+        assert_eq!(base64(data), Some(payload));
+
+        let accounts = parse_migration(&format!("offline?data={}", data.replace('/', "%2F"))).unwrap();
+
+        assert_eq!(accounts.len(), 2);
+        assert_eq!(accounts[0].account, "alice");
+        assert_eq!(accounts[0].issuer, Some(String::from("Example")));
+        assert_eq!(accounts[0].algorithm, OtpAlgorithm::Sha512);
+        assert_eq!(accounts[0].kind, OtpKind::Totp { period: 30 });
+        assert_eq!(accounts[1].account, "bob");
+        assert_eq!(accounts[1].issuer, Some(String::from("Other")));
+        assert_eq!(accounts[1].digits, 8);
+        assert_eq!(accounts[1].kind, OtpKind::Hotp { counter: 300 });
+        assert_eq!(accounts[1].secret, vec![0xFF]);
+    }
+}
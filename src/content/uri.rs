@@ -0,0 +1,226 @@
+//! URIs: `http(s):`, `mailto:` (RFC 6068), `tel:` (RFC 3966), `sms:` (RFC 5724), `geo:` (RFC 5870) and
+//! cryptocurrency payment requests (BIP 21)
+
+use super::{non_empty, Email, Geo, ParsedContent, Payment, Phone, Sms};
+
+const PAYMENT_SCHEMES: &[&str] = &["bitcoin", "bitcoincash", "litecoin", "dogecoin", "dash", "zcash"];
+
+pub(super) fn parse(content: &str) -> Option<ParsedContent> {
+    let colon = content.find(':')?;
+    let scheme = content[..colon].to_ascii_lowercase();
+    let rest = &content[colon + 1..];
+
+    match scheme.as_str() {
+        "http" | "https" if rest.starts_with("//") && !content.contains(char::is_whitespace) => {
+            Some(ParsedContent::Url(String::from(content)))
+        }
+        "mailto" => {
+            // Recipients can be in the path as well as in a "to" header, every header can hold a list
+            let (to, query) = split_query(rest);
+            let mut recipients = addresses(&percent_decode(to));
+            recipients.extend(query_values(query, "to").iter().flat_map(|to| addresses(to)));
+
+            Some(ParsedContent::Email(Email {
+                to: recipients,
+                cc: query_values(query, "cc").iter().flat_map(|cc| addresses(cc)).collect(),
+                bcc: query_values(query, "bcc").iter().flat_map(|bcc| addresses(bcc)).collect(),
+                subject: query_value(query, "subject"),
+                body: query_value(query, "body"),
+            }))
+        }
+        "tel" => parse_tel(rest),
+        "sms" | "mms" => {
+            let (numbers, query) = split_query(rest);
+            let numbers = addresses(&percent_decode(numbers));
+
+            if numbers.is_empty() {
+                return None;
+            }
+
+            Some(ParsedContent::Sms(Sms {
+                numbers,
+                message: query_value(query, "body"),
+            }))
+        }
+        // SMSTO:number:message, the message itself may contain colons
+        "smsto" | "mmsto" => {
+            let mut parts = rest.splitn(2, ':');
+
+            Some(ParsedContent::Sms(Sms {
+                numbers: vec![String::from(parts.next()?)],
+                message: parts.next().map(String::from).and_then(non_empty),
+            }))
+        }
+        "geo" => parse_geo(rest),
+        _ if PAYMENT_SCHEMES.contains(&scheme.as_str()) => {
+            let (address, query) = split_query(rest);
+
+            Some(ParsedContent::Payment(Payment {
+                scheme,
+                address: percent_decode(address.trim_start_matches("//")),
+                amount: query_value(query, "amount"),
+                label: query_value(query, "label"),
+                message: query_value(query, "message"),
+            }))
+        }
+        _ => None,
+    }
+}
+
+// tel:number[;ext=extension][;other parameters]
+fn parse_tel(rest: &str) -> Option<ParsedContent> {
+    let mut parts = rest.split(';');
+    let number = percent_decode(parts.next()?.trim());
+
+    if number.is_empty() {
+        return None;
+    }
+
+    let extension = parts
+        .find_map(|param| strip_parameter(param, "ext"))
+        .filter(|ext| !ext.is_empty())
+        .map(String::from);
+
+    Some(ParsedContent::Phone(Phone { number, extension }))
+}
+
+// geo:lat,lon[,alt][;crs=wgs84][;u=uncertainty][?q=query]
+fn parse_geo(rest: &str) -> Option<ParsedContent> {
+    let (path, query) = split_query(rest);
+    let mut params = path.split(';');
+    let coordinates = params.next()?;
+
+    let mut uncertainty = None;
+    for param in params {
+        if let Some(crs) = strip_parameter(param, "crs") {
+            // Coordinates in any other reference system can't be given as a latitude and longitude
+            if !crs.eq_ignore_ascii_case("wgs84") {
+                return None;
+            }
+        } else if let Some(u) = strip_parameter(param, "u") {
+            uncertainty = Some(u.parse::<f64>().ok().filter(|u| *u >= 0.0)?);
+        }
+    }
+
+    let mut parts = coordinates.split(',').map(|c| c.trim().parse::<f64>());
+    let latitude = parts.next()?.ok()?;
+    let longitude = parts.next()?.ok()?;
+    let altitude = match parts.next() {
+        Some(altitude) => Some(altitude.ok()?),
+        None => None,
+    };
+
+    if parts.next().is_some() || !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return None;
+    }
+
+    Some(ParsedContent::Geo(Geo {
+        latitude,
+        longitude,
+        altitude,
+        uncertainty,
+        query: query_value(query, "q"),
+    }))
+}
+
+pub(super) fn split_query(rest: &str) -> (&str, &str) {
+    match rest.find('?') {
+        Some(question) => (&rest[..question], &rest[question + 1..]),
+        None => (rest, ""),
+    }
+}
+
+// The value of the first parameter with the provided name, case insensitive
+pub(super) fn query_value(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            Some((parts.next()?, parts.next()?))
+        })
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| percent_decode(value))
+        .and_then(non_empty)
+}
+
+// All values of the parameters with the provided name, case insensitive
+fn query_values(query: &str, name: &str) -> Vec<String> {
+    query
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            Some((parts.next()?, parts.next()?))
+        })
+        .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| percent_decode(value))
+        .collect()
+}
+
+// A comma separated list of addresses or phone numbers
+fn addresses(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// The value of a "name=value" parameter, if it has the provided name
+fn strip_parameter<'a>(param: &'a str, name: &str) -> Option<&'a str> {
+    let mut parts = param.splitn(2, '=');
+    let key = parts.next()?.trim();
+
+    if key.eq_ignore_ascii_case(name) {
+        parts.next().map(str::trim)
+    } else {
+        None
+    }
+}
+
+// Decode %XX escapes. A '+' stays a '+', as it does in mailto and BIP 21 URIs
+pub(super) fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match (bytes[i], bytes.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) => std::str::from_utf8(hex).ok().and_then(|h| u8::from_str_radix(h, 16).ok()),
+            _ => None,
+        };
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b+c"), "a b+c");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn test_query_values() {
+        assert_eq!(query_values("cc=a&CC=b%2Cc&bcc=d", "cc"), vec!["a", "b,c"]);
+        assert_eq!(addresses(" a@example.com,,b@example.com "), vec!["a@example.com", "b@example.com"]);
+        assert_eq!(strip_parameter("EXT=12", "ext"), Some("12"));
+        assert_eq!(strip_parameter("extension=12", "ext"), None);
+    }
+}
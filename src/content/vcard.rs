@@ -0,0 +1,292 @@
+//! Line based formats: vCard contacts and iCalendar events
+//!
+//! Supports vCard 2.1, 3.0 and 4.0: folded lines, backslash escapes and, mostly found in 2.1 cards exported by
+//! phones, `QUOTED-PRINTABLE` and `BASE64` encoded values in UTF-8 or Latin-1 character sets.
+
+use super::{non_empty, strip_prefix_ignore_case, CalendarEvent, Contact, ParsedContent};
+
+pub(super) fn parse(content: &str) -> Option<ParsedContent> {
+    if strip_prefix_ignore_case(content, "BEGIN:VCARD").is_some() {
+        Some(parse_vcard(&properties(content)))
+    } else if strip_prefix_ignore_case(content, "BEGIN:VEVENT").is_some()
+        || strip_prefix_ignore_case(content, "BEGIN:VCALENDAR").is_some()
+    {
+        parse_vevent(&properties(content))
+    } else {
+        None
+    }
+}
+
+// A property line, split into its upper case name and its decoded, but still escaped, value
+struct Property {
+    name: String,
+    value: String,
+}
+
+fn parse_vcard(properties: &[Property]) -> ParsedContent {
+    // FN is the display name, N is "last;first;middle;prefix;suffix"
+    let name = first(properties, "FN").map(unescape).and_then(non_empty).or_else(|| {
+        let n = first(properties, "N")?;
+        let parts = components(n);
+        let ordered = [parts.get(3), parts.get(1), parts.get(2), parts.first(), parts.get(4)];
+        let name: Vec<&str> = ordered.iter().flatten().map(|p| p.as_str()).filter(|p| !p.is_empty()).collect();
+
+        non_empty(name.join(" "))
+    });
+
+    // vCard 4.0 allows URIs for phone numbers and email addresses
+    let strip = |scheme: &'static str| {
+        move |value: String| match strip_prefix_ignore_case(&value, scheme) {
+            Some(stripped) => String::from(stripped),
+            None => value,
+        }
+    };
+
+    ParsedContent::Contact(Contact {
+        name,
+        phones: all(properties, "TEL").into_iter().map(strip("tel:")).collect(),
+        emails: all(properties, "EMAIL").into_iter().map(strip("mailto:")).collect(),
+        organization: first(properties, "ORG").and_then(|o| {
+            let parts: Vec<String> = components(o).into_iter().filter(|p| !p.is_empty()).collect();
+            non_empty(parts.join(", "))
+        }),
+        title: first(properties, "TITLE").map(unescape).and_then(non_empty),
+        address: first(properties, "ADR").and_then(|a| {
+            let parts: Vec<String> = components(a).into_iter().filter(|p| !p.is_empty()).collect();
+            non_empty(parts.join(", "))
+        }),
+        urls: all(properties, "URL"),
+        note: first(properties, "NOTE").map(unescape).and_then(non_empty),
+    })
+}
+
+fn parse_vevent(properties: &[Property]) -> Option<ParsedContent> {
+    // Only the first event of a calendar, everything before it belongs to the calendar itself
+    let start = properties
+        .iter()
+        .position(|p| p.name == "BEGIN" && p.value.eq_ignore_ascii_case("VEVENT"))?;
+    let event = &properties[start..];
+    let field = |name| first(event, name).map(unescape).and_then(non_empty);
+
+    Some(ParsedContent::CalendarEvent(CalendarEvent {
+        summary: field("SUMMARY"),
+        start: field("DTSTART"),
+        end: field("DTEND"),
+        location: field("LOCATION"),
+        description: field("DESCRIPTION"),
+    }))
+}
+
+// Unfold continuation lines and split every line into a property
+fn properties(content: &str) -> Vec<Property> {
+    let mut lines: Vec<String> = vec![];
+
+    for line in content.lines() {
+        match lines.last_mut() {
+            // Folded line, continued after a space or tab
+            Some(last) if line.starts_with(' ') || line.starts_with('\t') => last.push_str(&line[1..]),
+            // Quoted printable soft line break, the next line continues the value without any indentation
+            Some(last) if last.ends_with('=') && is_quoted_printable(last) => {
+                last.pop();
+                last.push_str(line);
+            }
+            _ => lines.push(String::from(line)),
+        }
+    }
+
+    lines.iter().filter_map(|line| property(line)).collect()
+}
+
+fn property(line: &str) -> Option<Property> {
+    let colon = line.find(':')?;
+    let mut params = line[..colon].split(';');
+    let name = params.next()?.to_ascii_uppercase();
+
+    // Property names can be grouped, for example item1.EMAIL
+    let name = match name.rfind('.') {
+        Some(dot) => String::from(&name[dot + 1..]),
+        None => name,
+    };
+
+    let mut encoding = None;
+    let mut charset = None;
+
+    // vCard 2.1 also allows parameters without a name, such as TEL;CELL or NOTE;QUOTED-PRINTABLE
+    for param in params {
+        let (key, value) = match param.find('=') {
+            Some(equals) => (&param[..equals], &param[equals + 1..]),
+            None => ("", param),
+        };
+        let value = value.trim_matches('"').to_ascii_uppercase();
+
+        match key.to_ascii_uppercase().as_str() {
+            "ENCODING" => encoding = Some(value),
+            "CHARSET" => charset = Some(value),
+            "" if value == "QUOTED-PRINTABLE" || value == "BASE64" => encoding = Some(value),
+            _ => {}
+        }
+    }
+
+    let raw = &line[colon + 1..];
+    let bytes = match encoding.as_deref() {
+        Some("QUOTED-PRINTABLE") => quoted_printable(raw),
+        Some("B") | Some("BASE64") => base64(raw)?,
+        _ => return Some(Property {
+            name,
+            value: String::from(raw),
+        }),
+    };
+
+    Some(Property {
+        name,
+        value: decode_charset(&bytes, charset.as_deref()),
+    })
+}
+
+fn is_quoted_printable(line: &str) -> bool {
+    match line.find(':') {
+        Some(colon) => line[..colon].to_ascii_uppercase().contains("QUOTED-PRINTABLE"),
+        None => false,
+    }
+}
+
+fn quoted_printable(raw: &str) -> Vec<u8> {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match (bytes[i], bytes.get(i + 1..i + 3)) {
+            (b'=', Some(hex)) => std::str::from_utf8(hex).ok().and_then(|h| u8::from_str_radix(h, 16).ok()),
+            _ => None,
+        };
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    decoded
+}
+
+pub(super) fn base64(raw: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(raw.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in raw.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+// UTF-8 unless the charset says otherwise. Latin-1 maps every byte to the code point with the same value
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    match charset {
+        Some("ISO-8859-1") | Some("LATIN1") | Some("US-ASCII") => bytes.iter().map(|b| char::from(*b)).collect(),
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+fn first<'a>(properties: &'a [Property], name: &str) -> Option<&'a str> {
+    properties.iter().find(|p| p.name == name).map(|p| p.value.as_str())
+}
+
+fn all(properties: &[Property], name: &str) -> Vec<String> {
+    properties
+        .iter()
+        .filter(|p| p.name == name)
+        .map(|p| unescape(&p.value))
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+// Unescaped parts of a structured value, separated by unescaped ';'
+fn components(value: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => parts.last_mut().unwrap().push('\n'),
+                Some(c) => parts.last_mut().unwrap().push(c),
+                None => {}
+            },
+            ';' => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    parts.into_iter().map(|p| String::from(p.trim())).collect()
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => {}
+            },
+            _ => unescaped.push(c),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_properties() {
+        let props = properties("BEGIN:VCARD\r\nitem1.EMAIL;TYPE=INTERNET:a@b.c\r\nNOTE:long\r\n  line\r\nEND:VCARD");
+        let names: Vec<&str> = props.iter().map(|p| p.name.as_str()).collect();
+
+        assert_eq!(names, vec!["BEGIN", "EMAIL", "NOTE", "END"]);
+        assert_eq!(props[2].value, "long line");
+        assert_eq!(components("a\\;b;;c"), vec!["a;b", "", "c"]);
+    }
+
+    #[test]
+    fn test_encodings() {
+        let props = properties(
+            "N;CHARSET=UTF-8;ENCODING=QUOTED-PRINTABLE:M=C3=BCller;J=\r\n=C3=BCrgen\r\n\
+             ORG;CHARSET=ISO-8859-1;QUOTED-PRINTABLE:Caf=E9\r\n\
+             NOTE;ENCODING=b:aOlsbG8=\r\n\
+             TITLE;ENCODING=BASE64:not base64!",
+        );
+        let values: Vec<(&str, &str)> = props.iter().map(|p| (p.name.as_str(), p.value.as_str())).collect();
+
+        assert_eq!(values, vec![("N", "Müller;Jürgen"), ("ORG", "Café"), ("NOTE", "h\u{FFFD}llo")]);
+        assert_eq!(base64("SGVsbG8sIHdvcmxk"), Some(b"Hello, world".to_vec()));
+    }
+}
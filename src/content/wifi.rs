@@ -0,0 +1,116 @@
+//! `WIFI:` network configuration, as written by Android, iOS and most generators
+//!
+//! `WIFI:T:<type>;S:<ssid>;P:<password>;H:<hidden>;;` with the fields in any order. The SSID and password may be
+//! surrounded by double quotes, and special characters are escaped the same as in MECARD
+
+use super::mecard::{escape, fields, first, first_raw, unescape};
+use super::{non_empty, strip_prefix_ignore_case, ParsedContent, WiFi, WiFiSecurity};
+
+pub(super) fn parse(content: &str) -> Option<ParsedContent> {
+    let fields = fields(strip_prefix_ignore_case(content, "WIFI:")?);
+
+    let ssid = unquote(first_raw(&fields, "S")?);
+    if ssid.is_empty() {
+        return None;
+    }
+
+    let security = match first(&fields, "T") {
+        None => WiFiSecurity::None,
+        Some(t) => WiFiSecurity::from_type(&t),
+    };
+
+    Some(ParsedContent::WiFi(WiFi {
+        ssid,
+        password: first_raw(&fields, "P").map(unquote).and_then(non_empty),
+        security,
+        hidden: first(&fields, "H").is_some_and(|h| h.eq_ignore_ascii_case("true")),
+    }))
+}
+
+impl WiFi {
+    /// Write the configuration as a `WIFI:` payload, to encode it in a QR Code
+    ///
+    /// All special characters are escaped, so parsing the payload again results in the same configuration.
+    pub fn to_payload(&self) -> String {
+        let mut payload = String::from("WIFI:");
+
+        match &self.security {
+            WiFiSecurity::None => payload.push_str("T:nopass;"),
+            WiFiSecurity::Wep => payload.push_str("T:WEP;"),
+            WiFiSecurity::Wpa => payload.push_str("T:WPA;"),
+            WiFiSecurity::Other(t) => payload.push_str(&format!("T:{};", escape(t))),
+        }
+
+        payload.push_str(&format!("S:{};", escape(&self.ssid)));
+
+        if let Some(password) = &self.password {
+            payload.push_str(&format!("P:{};", escape(password)));
+        }
+
+        if self.hidden {
+            payload.push_str("H:true;");
+        }
+
+        payload.push(';');
+        payload
+    }
+}
+
+impl WiFiSecurity {
+    fn from_type(t: &str) -> WiFiSecurity {
+        match t.to_ascii_uppercase().as_str() {
+            "" | "NOPASS" => WiFiSecurity::None,
+            "WEP" => WiFiSecurity::Wep,
+            "WPA" | "WPA2" | "WPA3" | "SAE" => WiFiSecurity::Wpa,
+            _ => WiFiSecurity::Other(String::from(t)),
+        }
+    }
+}
+
+// Strip surrounding double quotes, but only if they were not escaped, then unescape
+fn unquote(raw: &str) -> String {
+    let inner = raw.strip_prefix('"').and_then(|r| r.strip_suffix('"'));
+
+    match inner {
+        // An odd number of backslashes before the closing quote means it is escaped itself
+        Some(inner) if inner.chars().rev().take_while(|c| *c == '\\').count() % 2 == 0 => unescape(inner),
+        _ => unescape(raw),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unquote() {
+        assert_eq!(unquote("\"home\""), "home");
+        assert_eq!(unquote("\\\"home\\\""), "\"home\"");
+        assert_eq!(unquote("\"back\\\\\""), "back\\");
+        assert_eq!(unquote("\""), "\"");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for ssid in &["plain", "a;b:c,d\\e", "\"quoted\"", "trailing\\", "\u{1F4F6} caf\u{E9}"] {
+            for security in &[
+                WiFiSecurity::None,
+                WiFiSecurity::Wep,
+                WiFiSecurity::Wpa,
+                WiFiSecurity::Other(String::from("WPA2-EAP")),
+            ] {
+                let wifi = WiFi {
+                    ssid: String::from(*ssid),
+                    password: match security {
+                        WiFiSecurity::None => None,
+                        _ => Some(format!("{}:pass;", ssid)),
+                    },
+                    security: security.clone(),
+                    hidden: *security == WiFiSecurity::Wep,
+                };
+
+                assert_eq!(ParsedContent::parse(&wifi.to_payload()), ParsedContent::WiFi(wifi));
+            }
+        }
+    }
+}
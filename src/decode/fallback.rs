@@ -0,0 +1,132 @@
+use super::Decode;
+
+use crate::util::metrics;
+use crate::util::qr::{ErrorCode, QRError};
+
+/// Run decoders in order until one of them succeeds
+///
+/// For example a strict decoder first, then a lenient one and then one that recovers what it can from a damaged code.
+/// Every decoder gets the same extracted data; the result of the first one that succeeds is returned. When they all
+/// fail, the error has the message of every attempt, in order, so it shows what was tried, and the
+/// [`code`](../util/qr/struct.QRError.html#method.code) of the first.
+///
+/// [`DecoderBuilder::fallback_decode`] sets one up from the decode implementations of the builder.
+///
+/// [`DecoderBuilder::fallback_decode`]: ../struct.DecoderBuilder.html#method.fallback_decode
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::{Decode, Fallback, QRDecoder};
+/// use bardecoder::util::qr::QRData;
+///
+/// let decode = Fallback::new(vec![Box::new(QRDecoder::new()), Box::new(QRDecoder::new())]);
+///
+/// let error = decode.decode(Ok(QRData::new(vec![255; 21 * 21], 1))).unwrap_err();
+/// assert!(error.msg.starts_with("All 2 decoders failed: 1) "));
+/// ```
+pub struct Fallback<DATA, RESULT> {
+    decoders: Vec<Box<dyn Decode<DATA, RESULT, QRError> + Send + Sync>>,
+}
+
+impl<DATA, RESULT> Fallback<DATA, RESULT> {
+    /// Construct a new Fallback, trying the decoders in the order provided
+    pub fn new(decoders: Vec<Box<dyn Decode<DATA, RESULT, QRError> + Send + Sync>>) -> Fallback<DATA, RESULT> {
+        Fallback { decoders }
+    }
+}
+
+impl<DATA: Clone, RESULT> Decode<DATA, RESULT, QRError> for Fallback<DATA, RESULT> {
+    fn decode(&self, data: Result<DATA, QRError>) -> Result<RESULT, QRError> {
+        let data = data?;
+
+        // A single decoder keeps its own error
+        if let [decoder] = self.decoders.as_slice() {
+            return decoder.decode(Ok(data));
+        }
+
+        let (mut attempts, mut code) = (vec![], ErrorCode::Other);
+        for (i, decoder) in self.decoders.iter().enumerate() {
+            if i > 0 {
+                metrics::count(|metrics| metrics.retries += 1);
+            }
+
+            match decoder.decode(Ok(data.clone())) {
+                Ok(result) => return Ok(result),
+                Err(e) => {
+                    if i == 0 {
+                        code = e.code();
+                    }
+                    attempts.push(format!("{}) {}", i + 1, e.msg));
+                }
+            }
+        }
+
+        let msg = format!("All {} decoders failed: {}", self.decoders.len(), attempts.join("; "));
+        Err(QRError::with_code(code, msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Succeeds with its result for data of at least `from`
+    struct Threshold {
+        from: u8,
+        result: &'static str,
+    }
+
+    impl Decode<u8, &'static str, QRError> for Threshold {
+        fn decode(&self, data: Result<u8, QRError>) -> Result<&'static str, QRError> {
+            match data? {
+                data if data >= self.from => Ok(self.result),
+                data => Err(QRError::new(format!("{data} is below {}", self.from))),
+            }
+        }
+    }
+
+    fn chain(thresholds: &[(u8, &'static str)]) -> Fallback<u8, &'static str> {
+        Fallback::new(
+            thresholds
+                .iter()
+                .map(|&(from, result)| {
+                    Box::new(Threshold { from, result }) as Box<dyn Decode<u8, &'static str, QRError> + Send + Sync>
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_fallback() {
+        let decode = chain(&[(10, "strict"), (5, "lenient"), (0, "partial")]);
+
+        assert_eq!(decode.decode(Ok(12)), Ok("strict"));
+        assert_eq!(decode.decode(Ok(7)), Ok("lenient"));
+        assert_eq!(decode.decode(Ok(2)), Ok("partial"));
+
+        let error = QRError::new("extract failed");
+        assert_eq!(decode.decode(Err(error.clone())), Err(error));
+    }
+
+    #[test]
+    fn test_fallback_errors() {
+        let decode = chain(&[(10, "strict"), (5, "lenient")]);
+        assert_eq!(
+            decode.decode(Ok(2)).unwrap_err().msg,
+            "All 2 decoders failed: 1) 2 is below 10; 2) 2 is below 5"
+        );
+
+        // The code is that of the first attempt
+        let corrupted = |_: Result<u8, QRError>| -> Result<&'static str, QRError> {
+            Err(QRError::format("Format information corrupted"))
+        };
+        let decode: Fallback<u8, &'static str> =
+            Fallback::new(vec![Box::new(corrupted), Box::new(Threshold { from: 5, result: "lenient" })]);
+        assert_eq!(decode.decode(Ok(2)).unwrap_err().code(), ErrorCode::Format);
+        assert_eq!(chain(&[(10, "strict"), (5, "lenient")]).decode(Ok(2)).unwrap_err().code(), ErrorCode::Other);
+
+        let single = chain(&[(10, "strict")]);
+        assert_eq!(single.decode(Ok(2)).unwrap_err().msg, "2 is below 10");
+    }
+}
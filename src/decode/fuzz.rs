@@ -0,0 +1,172 @@
+//! Entry points into the steps of decoding a QR Code, for fuzzing
+//!
+//! Every function builds the input of one step from arbitrary bytes and runs that step on it. The same bytes always
+//! give the same input, so a crash found by a fuzzer can be replayed. None of them may panic: whatever the bytes,
+//! invalid input must be an error. The `cargo-fuzz` targets in the `fuzz` directory of the repository call these.
+//!
+//! Where a step needs a version, error correction level or mask pattern, it is taken from the first bytes, always
+//! within range so the fuzzer spends its time on the modules and codewords instead. Extraction is the exception, its
+//! location is taken as it is.
+
+use super::qr::{block_info, blocks as qr_blocks, correct as qr_correct, data as qr_data, format as qr_format};
+use super::{BytePolicy, StandardMask};
+use super::{Decode, QRDecoderWithInfo};
+use crate::extract::{Extract, QRExtractor};
+use crate::util::qr::{ECLevel, QRData, QRError, QRInfo, QRLocation};
+use crate::util::{LumaImage, Point};
+
+/// Read the format information, from the modules of a code
+///
+/// The first byte is the version, the bits of the other bytes are the modules row by row, padded with light modules.
+pub fn format(input: &[u8]) -> Result<(ECLevel, u8), QRError> {
+    qr_format::read_format(&modules(input))
+}
+
+/// De-interleave the codewords into blocks, from the modules of a code
+///
+/// The first byte is the error correction level, in the lowest 2 bits, and mask pattern, in the next 3 bits. The rest
+/// is the same as for [`format`](fn.format.html).
+pub fn blocks(input: &[u8]) -> Result<Vec<Vec<u8>>, QRError> {
+    let (first, rest) = split(input);
+
+    qr_blocks::blocks(&modules(rest), &level(first), &StandardMask::new(first >> 2 & 7)?)
+}
+
+/// Correct the errors in a block of codewords
+///
+/// The first byte is the version, the second the error correction level, the third picks the block. The block is
+/// filled with the other bytes, padded with zeros.
+pub fn correct(input: &[u8]) -> Result<Vec<u8>, QRError> {
+    let (version, rest) = split(input);
+    let (ec_level, rest) = split(rest);
+    let (pick, rest) = split(rest);
+
+    let groups = block_info(version_of(version), &level(ec_level))?;
+    let group = &groups[usize::from(pick) % groups.len()];
+    let block = rest.iter().copied().chain(std::iter::repeat(0)).take(usize::from(group.total_per)).collect();
+
+    qr_correct::correct(block, group)
+}
+
+/// Parse the segments of the data codewords
+///
+/// The first byte is the version, the other bytes are the data codewords.
+pub fn data(input: &[u8]) -> Result<String, QRError> {
+    let (version, rest) = split(input);
+
+    qr_data::data(rest.to_vec(), version_of(version), BytePolicy::default())
+}
+
+/// Run all steps of [`QRDecoderWithInfo`](../struct.QRDecoderWithInfo.html), from the modules of a code
+///
+/// The input is the same as for [`format`](fn.format.html).
+pub fn decode(input: &[u8]) -> Result<(String, QRInfo), QRError> {
+    QRDecoderWithInfo::new().decode(Ok(modules(input)))
+}
+
+/// Extract the modules of a code from an image, at any location
+///
+/// The first 28 bytes are the x and y of the top left, top right and bottom left finder patterns, then the module size,
+/// as little endian `f32`, so they can be anything including NaN and infinity. The next byte is the version, as it is.
+/// The bits of the other bytes are the pixels of an image of 64 by 64, row by row, padded with light pixels.
+pub fn extract(input: &[u8]) -> Result<QRData, QRError> {
+    let mut floats = [0.0; 7];
+    let mut rest = input;
+    for float in floats.iter_mut() {
+        let (bytes, after) = rest.split_at(rest.len().min(4));
+        let mut le = [0; 4];
+        le[..bytes.len()].copy_from_slice(bytes);
+
+        *float = f64::from(f32::from_le_bytes(le));
+        rest = after;
+    }
+    let (version, rest) = split(rest);
+
+    let point = |i: usize| Point {
+        x: floats[i],
+        y: floats[i + 1],
+    };
+    let loc = QRLocation {
+        top_left: point(0),
+        top_right: point(2),
+        bottom_left: point(4),
+        module_size: floats[6],
+        version: u32::from(version),
+    };
+
+    let dark = |i: u32| rest.get(i as usize / 8).is_some_and(|byte| byte >> (7 - i % 8) & 1 == 1);
+    let image = LumaImage::from_fn(64, 64, |x, y| if dark(y * 64 + x) { 0 } else { 255 });
+
+    QRExtractor::new().extract(&image, loc)
+}
+
+fn split(input: &[u8]) -> (u8, &[u8]) {
+    match input.split_first() {
+        Some((first, rest)) => (*first, rest),
+        None => (0, &[]),
+    }
+}
+
+fn version_of(byte: u8) -> u32 {
+    u32::from(byte % 40) + 1
+}
+
+fn level(byte: u8) -> ECLevel {
+    match byte & 3 {
+        0 => ECLevel::LOW,
+        1 => ECLevel::MEDIUM,
+        2 => ECLevel::QUARTILE,
+        _ => ECLevel::HIGH,
+    }
+}
+
+fn modules(input: &[u8]) -> QRData {
+    let (version, rest) = split(input);
+    let version = version_of(version);
+    let side = 4 * version + 17;
+
+    let bits = rest.iter().flat_map(|byte| (0..8).rev().map(move |bit| byte >> bit & 1 == 1));
+    let data = bits
+        .chain(std::iter::repeat(false))
+        .take((side * side) as usize)
+        .map(|dark| if dark { 0 } else { 255 })
+        .collect();
+
+    QRData::new(data, version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A few hundred random inputs through every entry point, a smoke test of what the fuzz targets do at length
+    #[test]
+    fn test_random_input_does_not_panic() {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..300 {
+            let len = (next() % 600) as usize;
+            let input: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+
+            let _ = format(&input);
+            let _ = blocks(&input);
+            let _ = correct(&input);
+            let _ = data(&input);
+            let _ = decode(&input);
+            let _ = extract(&input);
+        }
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert_eq!(blocks(&[]).unwrap().len(), 1);
+        assert!(data(&[]).unwrap().is_empty());
+        assert_eq!(correct(&[]).unwrap(), vec![0; 26]);
+    }
+}
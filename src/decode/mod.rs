@@ -2,9 +2,23 @@
 
 use std::error::Error;
 
-mod qr;
+mod fallback;
+pub(crate) mod qr;
 
+#[cfg(any(test, feature = "fuzz"))]
+pub mod fuzz;
+
+pub use self::fallback::Fallback;
+pub use self::qr::append::{join_structured_append, StructuredAppendError};
+pub use self::qr::assemble::{FromQRDecode, QRDecoded, QRDecoderInto};
+pub use self::qr::data::BytePolicy;
 pub use self::qr::decoder::{QRDecoder, QRDecoderWithInfo};
+pub use self::qr::mask::{QRMask, StandardMask};
+pub use self::qr::orientation::{decode_any_orientation, MatrixOrientation};
+pub use self::qr::origin::{text_origins, TextOrigin};
+pub use self::qr::stream::QRStreamDecoder;
+
+pub(crate) use self::qr::corrected_modules;
 
 /// Decode extracted data into a resulting String
 ///
@@ -32,6 +46,18 @@ pub use self::qr::decoder::{QRDecoder, QRDecoderWithInfo};
 /// }
 /// ```
 ///
+/// A function or closure with the signature of [`decode`](#tymethod.decode) is a `Decode` as well, for example to
+/// post-process what another decoder returns:
+/// ```
+/// # extern crate bardecoder;
+/// # use bardecoder::util::qr::{QRData, QRError};
+/// use bardecoder::decode::{Decode, QRDecoder};
+///
+/// let qr = QRDecoder::new();
+/// let trimmed = move |data: Result<QRData, QRError>| qr.decode(data).map(|text| text.trim().to_string());
+/// let decode: Box<dyn Decode<QRData, String, QRError> + Send + Sync> = Box::new(trimmed);
+/// ```
+///
 /// with the corresponding impl Extract being the Example [`here`]
 ///
 /// [`Extract`]: ../extract/trait.Extract.html
@@ -43,4 +69,25 @@ where
 {
     /// Does the actual decoding
     fn decode(&self, data: Result<DATA, ERROR>) -> Result<RESULT, ERROR>;
+
+    /// Whether the data decodes to a result with exactly the expected content, `None` if only decoding it can tell
+    ///
+    /// [`Decoder::contains`] asks this first, so a decoder can compare while it decodes, stopping at the first
+    /// difference without building the result. [`QRDecoder`] and [`QRDecoderWithInfo`] do. The default is `None`,
+    /// after which the data is decoded and the content of the result compared.
+    ///
+    /// [`Decoder::contains`]: ../struct.Decoder.html#method.contains
+    fn matches(&self, _data: &Result<DATA, ERROR>, _expected: &str) -> Option<bool> {
+        None
+    }
+}
+
+impl<DATA, RESULT, ERROR, F> Decode<DATA, RESULT, ERROR> for F
+where
+    ERROR: Error,
+    F: Fn(Result<DATA, ERROR>) -> Result<RESULT, ERROR>,
+{
+    fn decode(&self, data: Result<DATA, ERROR>) -> Result<RESULT, ERROR> {
+        self(data)
+    }
 }
@@ -0,0 +1,175 @@
+use crate::util::qr::{QRInfo, StructuredAppend};
+
+/// Why the codes of a structured append sequence could not be joined, see [`join_structured_append`]
+///
+/// [`join_structured_append`]: fn.join_structured_append.html
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum StructuredAppendError {
+    /// There were no codes to join
+    #[error("No codes to join")]
+    Empty,
+    /// The code at this position of the input is not part of a structured append sequence
+    #[error("Code {0} is not part of a structured append sequence")]
+    NotAppended(usize),
+    /// The code at this position of the input has another number of codes or parity than the first one, so it
+    /// belongs to another sequence
+    #[error("Code {0} belongs to another structured append sequence")]
+    OtherSequence(usize),
+    /// Two codes with different contents claim the same place in the sequence
+    #[error("Two different codes at index {0} of the sequence")]
+    Conflict(u8),
+    /// The code for this index of the sequence is missing
+    #[error("Code {index} of the {total} codes of the sequence is missing")]
+    Missing {
+        /// Index of the missing code, from 0
+        index: u8,
+        /// Number of codes in the sequence
+        total: u8,
+    },
+    /// The parity of the data of all the codes does not match the parity they were encoded with, so one of them is
+    /// from another message with the same number of codes and parity
+    #[error("Parity of the joined data is {actual:#04x}, the codes were encoded with {expected:#04x}")]
+    Parity {
+        /// Parity in the structured append headers
+        expected: u8,
+        /// Parity of the data of the codes
+        actual: u8,
+    },
+}
+
+/// Join the contents of the codes of a structured append sequence into the whole message
+///
+/// The codes can be in any order, as decoded from one image or collected from several, and a code that was decoded
+/// more than once only counts once. Before joining, every code is checked to be part of the same sequence, every
+/// position of the sequence to be there, and the parity of all the data to match that of the message, which catches
+/// a code of another message that happens to have the same number of codes and parity in its header.
+///
+/// # Errors
+///
+/// Returns the [`StructuredAppendError`] of the first check that fails
+///
+/// [`StructuredAppendError`]: enum.StructuredAppendError.html
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::join_structured_append;
+/// use bardecoder::util::LumaImage;
+///
+/// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+/// let decoder = bardecoder::default_luma_decoder_with_info();
+/// let parts: Vec<_> = decoder.decode(&source).into_iter().flatten().collect();
+///
+/// match join_structured_append(&parts) {
+///     Ok(message) => println!("{}", message),
+///     Err(e) => println!("Not a whole message: {}", e),
+/// }
+/// ```
+pub fn join_structured_append(parts: &[(String, QRInfo)]) -> Result<String, StructuredAppendError> {
+    let mut headers: Vec<(StructuredAppend, &str)> = vec![];
+
+    for (i, (content, info)) in parts.iter().enumerate() {
+        let header = info.structured_append.ok_or(StructuredAppendError::NotAppended(i))?;
+
+        let sequence = (header.total, header.parity);
+        let other = headers.first().is_some_and(|(first, _)| (first.total, first.parity) != sequence);
+        if other || header.index >= header.total {
+            return Err(StructuredAppendError::OtherSequence(i));
+        }
+
+        match headers.iter().find(|(seen, _)| seen.index == header.index) {
+            Some((_, seen)) if *seen != content => return Err(StructuredAppendError::Conflict(header.index)),
+            Some(_) => {}
+            None => headers.push((header, content)),
+        }
+    }
+
+    let total = headers.first().ok_or(StructuredAppendError::Empty)?.0.total;
+    if let Some(index) = (0..total).find(|index| headers.iter().all(|(header, _)| header.index != *index)) {
+        return Err(StructuredAppendError::Missing { index, total });
+    }
+    headers.sort_by_key(|(header, _)| header.index);
+
+    let expected = headers[0].0.parity;
+    let actual = headers.iter().fold(0, |parity, (header, _)| parity ^ header.data_parity);
+    if actual != expected {
+        return Err(StructuredAppendError::Parity { expected, actual });
+    }
+
+    Ok(headers.into_iter().map(|(_, content)| content).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::qr::ECLevel;
+
+    // A code of a sequence of `total` with `parity`, holding `content` as bytes
+    fn part(content: &str, index: u8, total: u8, parity: u8) -> (String, QRInfo) {
+        let structured_append = StructuredAppend {
+            index,
+            total,
+            parity,
+            data_parity: content.bytes().fold(0, |parity, byte| parity ^ byte),
+        };
+
+        (
+            String::from(content),
+            QRInfo {
+                version: 1,
+                ec_level: ECLevel::LOW,
+                mask: 0,
+                total_data: 208,
+                errors: 0,
+                damaged_modules: vec![],
+                print_growth: 0.0,
+                raw_data: None,
+                physical_size: None,
+                structured_append: Some(structured_append),
+            },
+        )
+    }
+
+    #[test]
+    fn test_join_structured_append() {
+        let parity = b"Hello, world".iter().fold(0, |parity, byte| parity ^ byte);
+        let parts = [part("world", 2, 3, parity), part("Hello", 0, 3, parity), part(", ", 1, 3, parity)];
+
+        assert_eq!(join_structured_append(&parts), Ok(String::from("Hello, world")));
+
+        // The same code twice counts once
+        let twice = [parts[0].clone(), parts[1].clone(), parts[0].clone(), parts[2].clone()];
+        assert_eq!(join_structured_append(&twice), Ok(String::from("Hello, world")));
+    }
+
+    #[test]
+    fn test_join_structured_append_errors() {
+        let parity = b"Hello, world".iter().fold(0, |parity, byte| parity ^ byte);
+        let hello = part("Hello", 0, 3, parity);
+        let world = part("world", 2, 3, parity);
+
+        assert_eq!(join_structured_append(&[]), Err(StructuredAppendError::Empty));
+
+        let mut single = hello.clone();
+        single.1.structured_append = None;
+        assert_eq!(join_structured_append(&[world.clone(), single]), Err(StructuredAppendError::NotAppended(1)));
+
+        let other = part(", ", 1, 4, parity);
+        assert_eq!(join_structured_append(&[hello.clone(), other]), Err(StructuredAppendError::OtherSequence(1)));
+
+        let conflict = part("Howdy", 0, 3, parity);
+        assert_eq!(join_structured_append(&[hello.clone(), conflict]), Err(StructuredAppendError::Conflict(0)));
+
+        let missing = Err(StructuredAppendError::Missing { index: 1, total: 3 });
+        assert_eq!(join_structured_append(&[hello.clone(), world.clone()]), missing);
+
+        // A part of another message with the same header
+        let mixed = [hello, part(". ", 1, 3, parity), world];
+        let mismatch = Err(StructuredAppendError::Parity {
+            expected: parity,
+            actual: parity ^ b',' ^ b' ' ^ b'.' ^ b' ',
+        });
+        assert_eq!(join_structured_append(&mixed), mismatch);
+    }
+}
@@ -0,0 +1,249 @@
+use std::marker::PhantomData;
+
+use super::super::Decode;
+use super::data::BytePolicy;
+use super::decoder::{first_decoded, formats};
+use super::mask::StandardMask;
+
+use crate::util::qr::{ECLevel, QRData, QRError, StructuredAppend};
+
+/// A result type that is built straight from the error corrected data of a QR Code, see [`QRDecoderInto`]
+///
+/// [`QRDecoderInto`]: struct.QRDecoderInto.html
+pub trait FromQRDecode: Sized {
+    /// Build the result from the data codewords and what else is known about the code
+    ///
+    /// An error fails the code the same way as data that does not parse: the next format is tried, if there is one.
+    fn from_qr_decode(decoded: QRDecoded) -> Result<Self, QRError>;
+}
+
+/// The message, the same as [`QRDecoder`](struct.QRDecoder.html) decodes
+impl FromQRDecode for String {
+    fn from_qr_decode(decoded: QRDecoded) -> Result<String, QRError> {
+        decoded.text()
+    }
+}
+
+/// The data codewords of a QR Code after error correction, before they are parsed, see [`FromQRDecode`]
+///
+/// [`FromQRDecode`]: trait.FromQRDecode.html
+#[derive(Debug, Clone)]
+pub struct QRDecoded {
+    codewords: Vec<u8>,
+    version: u32,
+    ec_level: ECLevel,
+    mask: u8,
+    errors: u32,
+    byte_policy: BytePolicy,
+}
+
+impl QRDecoded {
+    /// The data codewords, in order, with the error correction codewords left out
+    pub fn codewords(&self) -> &[u8] {
+        &self.codewords
+    }
+
+    /// The data codewords, without copying them
+    pub fn into_codewords(self) -> Vec<u8> {
+        self.codewords
+    }
+
+    /// Version of the QR Code, 1 being the smallest, 40 the largest
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Error correction level the code decoded with
+    pub fn ec_level(&self) -> &ECLevel {
+        &self.ec_level
+    }
+
+    /// Mask pattern the code decoded with, from 0 to 7
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Number of bits error correction fixed, the same as [`QRInfo::errors`]
+    ///
+    /// [`QRInfo::errors`]: ../util/qr/struct.QRInfo.html#structfield.errors
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+
+    /// Parse the codewords into the message, with the byte policy of the decoder
+    pub fn text(self) -> Result<String, QRError> {
+        super::data::data(self.codewords, self.version, self.byte_policy)
+    }
+
+    /// Parse the codewords into the message, also with the structured append header of the code, if it has one
+    pub fn text_with_append(self) -> Result<(String, Option<StructuredAppend>), QRError> {
+        super::data::data_with_append(self.codewords, self.version, self.byte_policy)
+    }
+}
+
+/// Decode a QR Code into any type that implements [`FromQRDecode`]
+///
+/// The code is corrected the same way as by [`QRDecoder`], after which the result is built from the corrected data
+/// codewords by [`FromQRDecode::from_qr_decode`]. A high-throughput pipeline then gets its own type directly, without
+/// the `String` and `QRInfo` it would otherwise turn into it, or parses only the part of the payload it needs.
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::{FromQRDecode, QRDecoded, QRDecoderInto};
+/// use bardecoder::detect::LineScan;
+/// use bardecoder::extract::QRExtractor;
+/// use bardecoder::prepare::BlockedMean;
+/// use bardecoder::util::qr::QRError;
+/// use bardecoder::util::LumaImage;
+/// use bardecoder::DecoderBuilder;
+///
+/// // The payload of a proprietary encoding, without parsing it as text
+/// struct Payload {
+///     version: u32,
+///     codewords: Vec<u8>,
+/// }
+///
+/// impl FromQRDecode for Payload {
+///     fn from_qr_decode(decoded: QRDecoded) -> Result<Payload, QRError> {
+///         Ok(Payload {
+///             version: decoded.version(),
+///             codewords: decoded.into_codewords(),
+///         })
+///     }
+/// }
+///
+/// let mut db: DecoderBuilder<LumaImage, LumaImage, Payload> = DecoderBuilder::new();
+/// db.prepare(Box::new(BlockedMean::new(5, 7)));
+/// db.detect(Box::new(LineScan::new()));
+/// db.qr(Box::new(QRExtractor::new()), Box::new(QRDecoderInto::<Payload>::new()));
+///
+/// let decoder = db.build().unwrap();
+/// ```
+///
+/// [`FromQRDecode`]: trait.FromQRDecode.html
+/// [`FromQRDecode::from_qr_decode`]: trait.FromQRDecode.html#tymethod.from_qr_decode
+/// [`QRDecoder`]: struct.QRDecoder.html
+pub struct QRDecoderInto<T> {
+    forced_format: Option<(ECLevel, u8)>,
+    byte_policy: BytePolicy,
+    result: PhantomData<fn() -> T>,
+}
+
+impl<T: FromQRDecode> QRDecoderInto<T> {
+    /// Construct a new QRDecoderInto
+    pub fn new() -> QRDecoderInto<T> {
+        QRDecoderInto {
+            forced_format: None,
+            byte_policy: BytePolicy::default(),
+            result: PhantomData,
+        }
+    }
+
+    /// Construct a QRDecoderInto that ignores the format information, see
+    /// [`QRDecoder::with_forced_format`](struct.QRDecoder.html#method.with_forced_format)
+    pub fn with_forced_format(ec_level: ECLevel, mask: u8) -> QRDecoderInto<T> {
+        QRDecoderInto {
+            forced_format: Some((ec_level, mask)),
+            byte_policy: BytePolicy::default(),
+            result: PhantomData,
+        }
+    }
+
+    /// Parse eight bit segments with this policy in [`QRDecoded::text`], see
+    /// [`QRDecoder::with_byte_policy`](struct.QRDecoder.html#method.with_byte_policy)
+    ///
+    /// [`QRDecoded::text`]: struct.QRDecoded.html#method.text
+    pub fn with_byte_policy(mut self, policy: BytePolicy) -> QRDecoderInto<T> {
+        self.byte_policy = policy;
+        self
+    }
+}
+
+impl<T: FromQRDecode> Decode<QRData, T, QRError> for QRDecoderInto<T> {
+    fn decode(&self, data: Result<QRData, QRError>) -> Result<T, QRError> {
+        let qr_data = data?;
+
+        first_decoded(formats(&self.forced_format, &qr_data)?, |format| {
+            decode_format_into(&qr_data, format, self.byte_policy)
+        })
+    }
+}
+
+fn decode_format_into<T: FromQRDecode>(
+    qr_data: &QRData,
+    format: (ECLevel, u8),
+    byte_policy: BytePolicy,
+) -> Result<T, QRError> {
+    let blocks = super::blocks::blocks(qr_data, &format.0, &StandardMask::new(format.1)?)?;
+    let block_info = super::block_info(qr_data.version, &format.0)?;
+
+    let corrected = super::correct::correct_blocks(blocks, block_info, super::correct::correct_with_error_count)?;
+
+    let mut codewords = Vec::with_capacity(block_info.iter().map(|bi| bi.data_per as usize).sum());
+    let mut errors = 0;
+
+    for ((corrected, error_count), bi) in corrected.iter().zip(block_info) {
+        codewords.extend(corrected.iter().take(bi.data_per as usize));
+        errors += error_count;
+    }
+
+    T::from_qr_decode(QRDecoded {
+        codewords,
+        version: qr_data.version,
+        ec_level: format.0,
+        mask: format.1,
+        errors,
+        byte_policy,
+    })
+}
+
+#[cfg(all(test, feature = "encode"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_into() {
+        use super::super::decoder::QRDecoderWithInfo;
+        use crate::encode::QREncoder;
+        use crate::util::fault;
+        use crate::util::qr::QRInfo;
+
+        struct Assembled {
+            decoded: QRDecoded,
+        }
+
+        impl FromQRDecode for Assembled {
+            fn from_qr_decode(decoded: QRDecoded) -> Result<Assembled, QRError> {
+                Ok(Assembled { decoded })
+            }
+        }
+
+        let mut data = QREncoder::new().ec_level(ECLevel::QUARTILE).encode("assembled").unwrap().to_qr_data();
+        fault::flip_modules(&mut data, 6, 3).unwrap();
+
+        let decoder: QRDecoderInto<String> = QRDecoderInto::new();
+        assert_eq!(decoder.decode(Ok(data.clone())), Ok(String::from("assembled")));
+
+        let with_info = QRDecoderWithInfo::new().with_raw_data(true);
+        let (_, info): (String, QRInfo) = with_info.decode(Ok(data.clone())).unwrap();
+
+        let assembled = QRDecoderInto::<Assembled>::new().decode(Ok(data)).unwrap().decoded;
+        assert_eq!(assembled.codewords(), info.raw_data.as_deref().unwrap());
+        assert_eq!((assembled.version(), assembled.ec_level(), assembled.mask()), (1, &ECLevel::QUARTILE, info.mask));
+        assert_eq!(assembled.errors(), info.errors);
+        assert_eq!(assembled.text(), Ok(String::from("assembled")));
+
+        // A result that can not be built fails the code
+        struct Never {}
+
+        impl FromQRDecode for Never {
+            fn from_qr_decode(_: QRDecoded) -> Result<Never, QRError> {
+                Err(QRError::new("never"))
+            }
+        }
+
+        let data = QREncoder::new().encode("never").unwrap().to_qr_data();
+        assert_eq!(QRDecoderInto::<Never>::new().decode(Ok(data)).err().unwrap().msg, "never");
+    }
+}
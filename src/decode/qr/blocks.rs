@@ -1,46 +1,33 @@
+use std::sync::OnceLock;
+
 use super::block_info;
-use super::{BlockInfo, ECLevel, QRMask};
+use super::mask::QRMask;
+use super::{BlockInfo, ECLevel};
 
 use crate::util::qr::{QRData, QRError};
+use crate::util::trace::{self, TraceEvent};
 
-#[allow(clippy::borrowed_box)] // QRMask is a trait, unsure how to solve
-pub fn blocks(data: &QRData, level: &ECLevel, mask: &Box<QRMask>) -> Result<Vec<Vec<u8>>, QRError> {
+pub fn blocks(data: &QRData, level: &ECLevel, mask: &dyn QRMask) -> Result<Vec<Vec<u8>>, QRError> {
     let bi = block_info(data.version, level)?;
-    let mut codewords = Codewords::new(bi);
-    let mut x = data.side - 1;
-    let loc = alignment_location(data.version)?;
 
-    loop {
-        let y_range = y_range(x, data.side);
-
-        for y in y_range {
-            if is_data(data, &loc, x, y) {
-                codewords.add_bit(mask(data, x, y));
-            }
+    trace::record(|| TraceEvent::Blocks {
+        sizes: bi.iter().map(|b| (b.total_per, b.data_per)).collect(),
+    });
 
-            if is_data(data, &loc, x - 1, y) {
-                codewords.add_bit(mask(data, x - 1, y));
-            }
-        }
-
-        if x == 1 {
-            break;
-        }
+    let mut codewords = Codewords::new(bi);
 
-        x -= 2;
-        if x == 6 {
-            // skip timing pattern
-            x = 5;
-        }
+    for &(x, y) in data_modules(data.version)? {
+        codewords.add_bit(mask.unmask(data, x, y));
     }
 
-    let bi = block_info(data.version, level)?;
     let blocks = codewords.blocks();
 
     if blocks.len() != bi.len() {
-        return Err(QRError {
-            msg: format!("Expected {expected} blocks but found {found}", expected = bi.len(), found = blocks.len()),
-        });
+        return Err(QRError::invalid_input(format!(
+            "Expected {expected} blocks but found {found}",
+            expected = bi.len(),
+            found = blocks.len()
+        )));
     }
 
     for (i, block) in blocks.iter().enumerate() {
@@ -49,30 +36,86 @@ pub fn blocks(data: &QRData, level: &ECLevel, mask: &Box<QRMask>) -> Result<Vec<
 
     for i in 0..blocks.len() {
         if bi[i].total_per as usize != blocks[i].len() {
-            return Err(QRError {
-                msg: format!(
-                    "Expected {expected} codewords in block {block} but found {found}",
-                    expected = bi[i].total_per,
-                    block = i,
-                    found = blocks[i].len()
-                ),
-            });
+            return Err(QRError::invalid_input(format!(
+                "Expected {expected} codewords in block {block} but found {found}",
+                expected = bi[i].total_per,
+                block = i,
+                found = blocks[i].len()
+            )));
         }
     }
 
     Ok(blocks)
 }
 
-fn y_range(x: u32, side: u32) -> Box<dyn Iterator<Item = u32>> {
-    let x = if x < 6 { x + 1 } else { x };
-    if (i64::from(x) - i64::from(side) + 1) % 4 == 0 {
-        Box::new((0..side).rev())
-    } else {
-        Box::new(0..side)
+/// Coordinates of the eight modules of a codeword, most significant bit first
+pub type CodewordModules = Vec<(u32, u32)>;
+
+/// The modules of every codeword, per block
+pub fn codeword_modules(data: &QRData, level: &ECLevel) -> Result<Vec<Vec<CodewordModules>>, QRError> {
+    let bi = block_info(data.version, level)?;
+    let mut blocks = Blocks::new(bi);
+
+    for codeword in data_modules(data.version)?.chunks_exact(8) {
+        blocks.push(codeword.to_vec());
+    }
+
+    Ok(blocks.blocks)
+}
+
+// Coordinates of all modules holding data or error correction bits, in the order they are read
+//
+// The order only depends on the version, so it is computed once per version and shared by every decode after that
+pub fn data_modules(version: u32) -> Result<&'static [(u32, u32)], QRError> {
+    static ORDERS: [OnceLock<Vec<(u32, u32)>>; 40] = [const { OnceLock::new() }; 40];
+
+    let loc = alignment_location(version)?;
+    let order = ORDERS[version as usize - 1].get_or_init(|| module_order(version, &loc));
+
+    Ok(order)
+}
+
+fn module_order(version: u32, loc: &AlignmentLocation) -> Vec<(u32, u32)> {
+    let side = 4 * version + 17;
+
+    // Most modules hold data for all but the smallest versions, so this is close and never grows
+    let mut modules = Vec::with_capacity((side * side) as usize);
+    let mut x = side - 1;
+
+    loop {
+        // Pairs of columns are read upwards and downwards in turn, starting upwards at the right
+        let column = if x < 6 { x + 1 } else { x };
+        let upwards = (side - 1 - column).is_multiple_of(4);
+
+        for i in 0..side {
+            let y = if upwards { side - 1 - i } else { i };
+
+            if is_data(version, loc, x, y) {
+                modules.push((x, y));
+            }
+
+            if is_data(version, loc, x - 1, y) {
+                modules.push((x - 1, y));
+            }
+        }
+
+        if x == 1 {
+            break;
+        }
+
+        x -= 2;
+        if x == 6 {
+            // skip timing pattern
+            x = 5;
+        }
     }
+
+    modules
 }
 
-fn is_data(data: &QRData, loc: &AlignmentLocation, x: u32, y: u32) -> bool {
+fn is_data(version: u32, loc: &AlignmentLocation, x: u32, y: u32) -> bool {
+    let side = 4 * version + 17;
+
     // timing patterns
     if x == 6 || y == 6 {
         return false;
@@ -84,30 +127,30 @@ fn is_data(data: &QRData, loc: &AlignmentLocation, x: u32, y: u32) -> bool {
     }
 
     // top right locator pattern
-    if x > data.side - 9 && y < 9 {
+    if x > side - 9 && y < 9 {
         return false;
     }
 
     // bottom left locator pattern
-    if x < 9 && y > data.side - 9 {
+    if x < 9 && y > side - 9 {
         return false;
     }
 
     // top right version info
-    if data.version >= 7 && x > data.side - 12 && y < 6 {
+    if version >= 7 && x > side - 12 && y < 6 {
         return false;
     }
 
     // buttom left version info
-    if data.version >= 7 && y > data.side - 12 && x < 6 {
+    if version >= 7 && y > side - 12 && x < 6 {
         return false;
     }
 
-    if x == data.side - 9 && y < 9 {
+    if x == side - 9 && y < 9 {
         return true;
     }
 
-    if y == data.side - 9 && x < 9 {
+    if y == side - 9 && x < 9 {
         return true;
     }
 
@@ -135,7 +178,7 @@ fn is_data(data: &QRData, loc: &AlignmentLocation, x: u32, y: u32) -> bool {
 /// The fix explicitly expresses this intent: `coord >= 4 && coord <= 8`
 fn is_alignment_coord(loc: &AlignmentLocation, coord: u32) -> bool {
     // Check if coordinate falls within finder pattern regions (coordinates 4-8)
-    if coord >= 4 && coord <= 8 {
+    if (4..=8).contains(&coord) {
         return true;
     }
 
@@ -150,6 +193,26 @@ fn is_alignment_coord(loc: &AlignmentLocation, coord: u32) -> bool {
     false
 }
 
+/// Row and column coordinates of the centers of the alignment patterns, in modules
+///
+/// Computed once per version, the same as the order of the data modules.
+#[cfg(feature = "encode")]
+pub fn alignment_positions(version: u32) -> Result<&'static [u32], QRError> {
+    static POSITIONS: [OnceLock<Vec<u32>>; 40] = [const { OnceLock::new() }; 40];
+
+    let loc = alignment_location(version)?;
+    let positions = POSITIONS[version as usize - 1].get_or_init(|| {
+        if version == 1 {
+            return vec![];
+        }
+
+        let last = 4 * version + 10;
+        std::iter::once(6).chain((loc.start..=last).step_by(loc.step as usize)).collect()
+    });
+
+    Ok(positions)
+}
+
 fn alignment_location(version: u32) -> Result<AlignmentLocation, QRError> {
     match version {
         // no alignment patterns for version 1 but this saves some exception paths
@@ -166,18 +229,21 @@ fn alignment_location(version: u32) -> Result<AlignmentLocation, QRError> {
         7 => Ok(AlignmentLocation::new(22, 16)),
         8 => Ok(AlignmentLocation::new(24, 18)),
         9 | 14 => Ok(AlignmentLocation::new(26, 20)),
-        10 => Ok(AlignmentLocation::new(28, 22)),
+        10 | 21 => Ok(AlignmentLocation::new(28, 22)),
         11 | 17 => Ok(AlignmentLocation::new(30, 24)),
-        12 | 25 => Ok(AlignmentLocation::new(32, 26)),
-        13 | 20 => Ok(AlignmentLocation::new(34, 28)),
+        12 | 25 | 38 => Ok(AlignmentLocation::new(32, 26)),
+        13 | 20 | 27 | 34 => Ok(AlignmentLocation::new(34, 28)),
         15 => Ok(AlignmentLocation::new(26, 22)),
-        16 => Ok(AlignmentLocation::new(26, 24)),
-        18 => Ok(AlignmentLocation::new(30, 26)),
-        19 | 40 => Ok(AlignmentLocation::new(30, 28)),
+        16 | 22 | 28 => Ok(AlignmentLocation::new(26, 24)),
+        18 | 31 => Ok(AlignmentLocation::new(30, 26)),
+        19 | 26 | 33 | 40 => Ok(AlignmentLocation::new(30, 28)),
+        23 | 29 | 35 => Ok(AlignmentLocation::new(30, 24)),
+        24 | 37 => Ok(AlignmentLocation::new(28, 26)),
+        30 => Ok(AlignmentLocation::new(26, 26)),
+        32 => Ok(AlignmentLocation::new(34, 26)),
         36 => Ok(AlignmentLocation::new(24, 26)),
-        _ => Err(QRError {
-            msg: format!("Unknown version {version}"),
-        }),
+        39 => Ok(AlignmentLocation::new(26, 28)),
+        _ => Err(QRError::version(format!("Unknown version {version}"))),
     }
 }
 
@@ -193,9 +259,9 @@ impl AlignmentLocation {
     }
 }
 
-struct Blocks {
-    block_info: Vec<BlockInfo>,
-    blocks: Vec<Vec<u8>>,
+struct Blocks<'a, T> {
+    block_info: &'a [BlockInfo],
+    blocks: Vec<Vec<T>>,
 
     round: usize,
     max_data_round: usize,
@@ -203,18 +269,11 @@ struct Blocks {
     data_blocks: bool,
 }
 
-impl Blocks {
-    fn new(block_info: Vec<BlockInfo>) -> Blocks {
-        let mut blocks = vec![];
-        let mut max_data_round: usize = 0;
-
-        for bi in &block_info {
-            if bi.data_per as usize > max_data_round {
-                max_data_round = bi.data_per as usize;
-            }
-
-            blocks.push(vec![]);
-        }
+impl<'a, T> Blocks<'a, T> {
+    fn new(block_info: &'a [BlockInfo]) -> Blocks<'a, T> {
+        // Every block is filled up to its size exactly, so none of them has to grow
+        let blocks = block_info.iter().map(|bi| Vec::with_capacity(bi.total_per as usize)).collect();
+        let max_data_round = block_info.iter().map(|bi| bi.data_per as usize).max().unwrap_or(0);
 
         Blocks {
             block_info,
@@ -226,14 +285,14 @@ impl Blocks {
         }
     }
 
-    fn push(&mut self, byte: u8) {
+    fn push(&mut self, codeword: T) {
         while self.data_blocks && self.round > self.block_info[self.block].data_per as usize - 1 {
             self.inc_count();
         }
 
-        trace!("PUSHING {:08b} TO BLOCK {}", byte, self.block);
+        trace!("PUSHING CODEWORD TO BLOCK {}", self.block);
 
-        self.blocks[self.block].push(byte);
+        self.blocks[self.block].push(codeword);
         self.inc_count();
     }
 
@@ -251,14 +310,14 @@ impl Blocks {
     }
 }
 
-struct Codewords {
+struct Codewords<'a> {
     current_byte: u8,
     bit_count: u8,
-    blocks: Blocks,
+    blocks: Blocks<'a, u8>,
 }
 
-impl Codewords {
-    fn new(block_info: Vec<BlockInfo>) -> Codewords {
+impl<'a> Codewords<'a> {
+    fn new(block_info: &'a [BlockInfo]) -> Codewords<'a> {
         Codewords {
             current_byte: 0,
             bit_count: 0,
@@ -287,6 +346,49 @@ impl Codewords {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_data_modules_all_versions() {
+        for version in 1..=40 {
+            let codewords: usize = block_info(version, &ECLevel::LOW)
+                .unwrap()
+                .iter()
+                .map(|bi| bi.total_per as usize)
+                .sum();
+
+            // Whatever does not fill a complete codeword is left over as remainder bits
+            let remainder = match version {
+                2..=6 => 7,
+                14..=20 | 28..=34 => 3,
+                21..=27 => 4,
+                _ => 0,
+            };
+
+            assert_eq!(codewords * 8 + remainder, data_modules(version).unwrap().len(), "version {}", version);
+        }
+    }
+
+    #[test]
+    fn test_data_modules_computed_once() {
+        let first = data_modules(20).unwrap();
+        assert!(std::ptr::eq(first, data_modules(20).unwrap()));
+
+        // Starting upwards in the two rightmost columns
+        assert_eq!(&first[..4], &[(96, 96), (95, 96), (96, 95), (95, 95)]);
+        assert!(data_modules(0).is_err());
+        assert!(data_modules(41).is_err());
+    }
+
+    #[test]
+    fn test_block_info_computed_once() {
+        let first = block_info(5, &ECLevel::QUARTILE).unwrap();
+        assert!(std::ptr::eq(first, block_info(5, &ECLevel::QUARTILE).unwrap()));
+
+        let sizes: Vec<u8> = first.iter().map(|bi| bi.total_per).collect();
+        assert_eq!(sizes, vec![33, 33, 34, 34]);
+        assert!(block_info(0, &ECLevel::LOW).is_err());
+        assert!(block_info(41, &ECLevel::HIGH).is_err());
+    }
+
     #[test]
     pub fn test_alignment_locs() {
         let al = alignment_location(36).expect("Alignment location should exist for version 36");
@@ -1,95 +1,270 @@
-use super::galois::{EXP8, GF8};
+use super::galois::{EXP8, GF8, LOG8};
 use super::BlockInfo;
 
+use crate::util::limits::{self, Limit};
+use crate::util::metrics;
 use crate::util::qr::QRError;
+use crate::util::trace::{self, TraceEvent};
 
 use std::ops::{Div, Mul, Sub};
+use std::sync::OnceLock;
+
+/// Index of a corrected codeword in its block, with the bits that were flipped
+pub type Correction = (usize, u8);
+
+/// Blocks from which on the blocks of a code are corrected side by side, with the `parallel` feature
+#[cfg(feature = "parallel")]
+const PARALLEL_BLOCKS: usize = 16;
+
+/// Correct every block with its block info, in order, failing with the error of the first block that fails
+///
+/// With the `parallel` feature, the many blocks of large versions are corrected side by side on the thread pool of
+/// rayon. The iterations they take count against the limits of this thread the same as when corrected one after the
+/// other, and so do their metrics and trace events, in the order of the blocks.
+pub fn correct_blocks<T: Send>(
+    blocks: Vec<Vec<u8>>,
+    block_info: &[BlockInfo],
+    correct: impl Fn(Vec<u8>, &BlockInfo) -> Result<T, QRError> + Sync,
+) -> Result<Vec<T>, QRError> {
+    #[cfg(feature = "parallel")]
+    if blocks.len() >= PARALLEL_BLOCKS {
+        return correct_parallel(blocks, block_info, correct);
+    }
+
+    blocks.into_iter().zip(block_info).map(|(block, bi)| correct(block, bi)).collect()
+}
+
+#[cfg(feature = "parallel")]
+fn correct_parallel<T: Send>(
+    blocks: Vec<Vec<u8>>,
+    block_info: &[BlockInfo],
+    correct: impl Fn(Vec<u8>, &BlockInfo) -> Result<T, QRError> + Sync,
+) -> Result<Vec<T>, QRError> {
+    use rayon::prelude::*;
+
+    use crate::util::limits::ResourceLimits;
+
+    // Every block may use all iterations left, the total is spent below
+    let limits = ResourceLimits {
+        max_rs_iterations: limits::left(Limit::RsIterations),
+        ..ResourceLimits::UNLIMITED
+    };
+
+    let corrected: Vec<_> = blocks
+        .into_par_iter()
+        .zip(block_info.par_iter())
+        .map(|(block, bi)| {
+            let (((corrected, exceeded), events), counted) =
+                metrics::collect(|| trace::collect(|| limits::within(limits, || correct(block, bi))));
+            (corrected, exceeded.is_some(), events, counted)
+        })
+        .collect();
+
+    let mut all_corrected = Vec::with_capacity(corrected.len());
+    let mut first_error = None;
+
+    for (corrected, exceeded, events, counted) in corrected {
+        metrics::count(|metrics| *metrics += counted);
+        for event in events {
+            trace::record(|| event);
+        }
+
+        // A block that ran out had all that was left, spending more than that sets the limit as the reason
+        let amount = if exceeded { u64::MAX } else { counted.rs_iterations };
+        if !limits::spend_many(Limit::RsIterations, amount) && first_error.is_none() {
+            first_error = Some(QRError::correction("Could not calculate SIGMA"));
+        }
+
+        match corrected {
+            Ok(corrected) => all_corrected.push(corrected),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(all_corrected),
+    }
+}
 
 pub fn correct(block: Vec<u8>, block_info: &BlockInfo) -> Result<Vec<u8>, QRError> {
     correct_with_error_count(block, block_info).map(|r| r.0)
 }
 
 pub fn correct_with_error_count(
-    mut block: Vec<u8>,
+    block: Vec<u8>,
     block_info: &BlockInfo,
 ) -> Result<(Vec<u8>, u32), QRError> {
+    let (block, corrections) = correct_with_error_locations(block, block_info)?;
+    let error_count = corrections.iter().map(|(_, distance)| distance.count_ones()).sum();
+
+    Ok((block, error_count))
+}
+
+/// Correct the block, also returning the index of every corrected codeword with the bits that were flipped
+pub fn correct_with_error_locations(
+    mut block: Vec<u8>,
+    block_info: &BlockInfo,
+) -> Result<(Vec<u8>, Vec<Correction>), QRError> {
     let (all_fine, syndromes) = calculate_syndromes(&block, block_info);
 
     if all_fine {
         // all fine, nothing to do
         debug!("ALL SYNDROMES WERE ZERO, NO CORRECTION NEEDED");
-        return Ok((block, 0));
+        trace::record(|| TraceEvent::Correction { codewords: 0, bits: 0 });
+
+        return Ok((block, vec![]));
     }
 
     let locs = find_locs(block_info, &syndromes)?;
 
     let distance = calculate_distances(&syndromes, &locs);
-    let distance = distance.ok_or(QRError {
-        msg: String::from("Could not calculate error distances"),
-    })?;
+    let distance = distance.ok_or(QRError::correction("Could not calculate error distances"))?;
 
-    let mut error_count = 0;
+    let mut corrections = vec![];
 
     for i in 0..locs.len() {
+        let index = block_info.total_per as usize - 1 - locs[i];
+
         debug!(
             "FIXING LOCATION {loc} FROM {from:08b} TO {to:08b}",
-            loc = block_info.total_per as usize - 1 - locs[i] as usize,
-            from = block[block_info.total_per as usize - 1 - locs[i] as usize],
-            to = block[block_info.total_per as usize - 1 - locs[i] as usize] ^ distance[i].0
+            loc = index,
+            from = block[index],
+            to = block[index] ^ distance[i].0
         );
 
-        error_count += distance[i].0.count_ones();
-        block[block_info.total_per as usize - 1 - locs[i] as usize] ^= distance[i].0;
+        block[index] ^= distance[i].0;
+
+        if distance[i].0 != 0 {
+            corrections.push((index, distance[i].0));
+        }
     }
 
-    if syndrome(&block, EXP8[0]) != GF8(0) {
-        return Err(QRError {
-            msg: String::from("Error correcting did not fix corrupted data"),
-        });
+    if !calculate_syndromes(&block, block_info).0 {
+        return Err(QRError::correction("Error correcting did not fix corrupted data"));
     }
 
-    Ok((block, error_count))
+    trace::record(|| TraceEvent::Correction {
+        codewords: corrections.len(),
+        bits: corrections.iter().map(|(_, distance)| distance.count_ones()).sum(),
+    });
+
+    Ok((block, corrections))
 }
 
+// Most syndromes of a block, those of version 40 at the high level
+const MAX_SYNDROMES: usize = 30;
+
+// Remainders are kept in registers this wide, so updating them works on whole vectors
+const REGISTER: usize = 32;
+
 fn calculate_syndromes(block: &[u8], block_info: &BlockInfo) -> (bool, Vec<GF8>) {
-    let mut syndromes = vec![GF8(0); (block_info.ec_cap * 2) as usize];
+    let count = (block_info.ec_cap * 2) as usize;
+    let mut syndromes = vec![GF8(0); count];
+
+    // The syndrome for alpha^i is the block evaluated at alpha^i, the same as its remainder after dividing by the
+    // generator, which has alpha^i as a root. Dividing takes one pass over the block, after which only the short
+    // remainder is evaluated at every root, and not at all when it is zero, as it is for a block without errors.
+    let rem = remainder(block, count);
+    if rem.iter().all(|coefficient| *coefficient == 0) {
+        return (true, syndromes);
+    }
 
-    let mut all_fine = true;
-    for i in 0..block_info.ec_cap * 2 {
-        syndromes[i as usize] = syndrome(block, EXP8[i as usize]);
-        if syndromes[i as usize] != GF8(0) {
-            all_fine = false;
+    for (j, coefficient) in rem[..count].iter().enumerate() {
+        if *coefficient == 0 {
+            continue;
+        }
+
+        // The coefficient of x^(count - 1 - j)
+        let (log, degree) = (usize::from(LOG8[*coefficient as usize]), count - 1 - j);
+        for (i, synd) in syndromes.iter_mut().enumerate() {
+            *synd = *synd + EXP8[(log + i * degree) % 255];
         }
     }
 
+    let all_fine = syndromes.iter().all(|synd| *synd == GF8(0));
     (all_fine, syndromes)
 }
 
-fn syndrome(block: &[u8], base: GF8) -> GF8 {
-    let mut synd = GF8(0);
-    let mut alpha = GF8(1);
+// Remainder of the block after dividing by the generator of degree `count` with roots alpha^0 up to
+// alpha^(count - 1), highest coefficient first.
+//
+// With the codewords taken from the first on, every step multiplies the remainder by x and adds the next codeword. The
+// coefficient shifted out to x^count is reduced by subtracting its multiple of the generator, which is a row of the
+// table, so the step is a shift and an XOR of the whole register.
+fn remainder(block: &[u8], count: usize) -> [u8; REGISTER] {
+    let rows = generator_rows(count);
+
+    let mut rem = [0; REGISTER];
+    for codeword in block {
+        let overflow = rem[0];
+        rem.copy_within(1.., 0);
+        rem[REGISTER - 1] = 0;
+        rem[count - 1] ^= codeword;
+
+        for (r, g) in rem.iter_mut().zip(&rows[overflow as usize]) {
+            *r ^= g;
+        }
+    }
 
-    for codeword in block.iter().rev() {
-        synd = synd + (alpha * GF8(*codeword));
+    rem
+}
 
-        alpha = alpha * base;
-    }
+// The generator with roots alpha^0 up to alpha^(count - 1), without its leading 1, multiplied by every codeword
+//
+// Computed once per number of syndromes, and shared by every block after that.
+fn generator_rows(count: usize) -> &'static [[u8; REGISTER]] {
+    static ROWS: [OnceLock<Vec<[u8; REGISTER]>>; MAX_SYNDROMES / 2 + 1] =
+        [const { OnceLock::new() }; MAX_SYNDROMES / 2 + 1];
+
+    ROWS[count / 2].get_or_init(|| {
+        // Highest coefficient first, multiplied by (x - alpha^i) for every root. Subtracting is adding in GF(2^8)
+        let mut generator = vec![GF8(1)];
+        for root in EXP8.iter().take(count) {
+            let mut product = generator.clone();
+            product.push(GF8(0));
+            for (k, coefficient) in generator.iter().enumerate() {
+                product[k + 1] = product[k + 1] + *coefficient * *root;
+            }
+            generator = product;
+        }
 
-    synd
+        (0..=255)
+            .map(|codeword| {
+                let mut row = [0; REGISTER];
+                for (r, coefficient) in row.iter_mut().zip(&generator[1..]) {
+                    *r = (GF8(codeword) * *coefficient).0;
+                }
+                row
+            })
+            .collect()
+    })
 }
 
 fn find_locs(block_info: &BlockInfo, syndromes: &[GF8]) -> Result<Vec<usize>, QRError> {
-    let z = block_info.ec_cap as usize;
-    let mut eq = vec![vec![GF8(0); z + 1]; z];
-    for i in 0..z {
-        eq[i][..=z].clone_from_slice(&syndromes[i..(z + 1 + i)]);
-    }
+    // The equations for SIGMA can only be solved for the actual number of errors, so start at the most that can be
+    // corrected and count down
+    let mut iterations = 0;
+    let sigma = (1..=block_info.ec_cap as usize).rev().find_map(|z| {
+        // Without iterations left every number of errors fails, which the decoder reports as the limit
+        if !limits::spend(Limit::RsIterations) {
+            return None;
+        }
+        iterations += 1;
+
+        let mut eq = vec![vec![GF8(0); z + 1]; z];
+        for i in 0..z {
+            eq[i][..=z].clone_from_slice(&syndromes[i..(z + 1 + i)]);
+        }
+
+        solve(eq, GF8(0))
+    });
 
-    let sigma = solve(eq, GF8(0), GF8(1), false);
+    metrics::count(|metrics| metrics.rs_iterations += iterations);
 
-    let sigma = sigma.ok_or(QRError {
-        msg: String::from("Could not calculate SIGMA"),
-    })?;
+    let sigma = sigma.ok_or(QRError::correction("Could not calculate SIGMA"))?;
 
     let mut locs = vec![];
 
@@ -110,6 +285,10 @@ fn find_locs(block_info: &BlockInfo, syndromes: &[GF8]) -> Result<Vec<usize>, QR
 
     debug!("LOCS {locs:?}");
 
+    if locs.len() != sigma.len() {
+        return Err(QRError::correction("Could not locate all errors"));
+    }
+
     Ok(locs)
 }
 
@@ -123,10 +302,11 @@ fn calculate_distances(syndromes: &[GF8], locs: &[usize]) -> Option<Vec<GF8>> {
         eq[i][locs.len()] = syndromes[i];
     }
 
-    solve(eq, GF8(0), GF8(1), false)
+    solve(eq, GF8(0))
 }
 
-fn solve<T>(mut eq: Vec<Vec<T>>, zero: T, one: T, fail_on_rank: bool) -> Option<Vec<T>>
+// Gaussian elimination, None if the equations do not have a single solution
+fn solve<T>(mut eq: Vec<Vec<T>>, zero: T) -> Option<Vec<T>>
 where
     T: Div<Output = T> + Mul<Output = T> + Sub<Output = T> + Copy + PartialEq,
 {
@@ -141,6 +321,10 @@ where
     }
 
     for i in 0..num_eq {
+        // use an equation where this coefficient is nonzero, if there is none the equations are singular
+        let pivot = (i..num_eq).find(|j| eq[*j][i] != zero)?;
+        eq.swap(i, pivot);
+
         // normalise equation
         for j in (i..num_coeff).rev() {
             // divide all coefficients by the first nonzero
@@ -155,11 +339,6 @@ where
                 eq[j][k] = eq[j][k] - (eq[j][i] * eq[i][k]);
             }
         }
-
-        // If the rank is too low, can't solve
-        if fail_on_rank && eq[i][num_coeff - 1] == one {
-            return None;
-        }
     }
 
     let mut solution = vec![zero; num_eq];
@@ -216,10 +395,73 @@ mod tests {
     #[test]
     fn test_syndrome_calculation() {
         let block = vec![1, 2, 3, 4, 5];
-        let base = GF8(1);
-        let result = syndrome(&block, base);
-        // Just verify it doesn't panic and returns a GF8 value
-        assert!(result.0 <= 255);
+        let (all_fine, syndromes) = calculate_syndromes(&block, &BlockInfo::new(1, 5, 3, 1));
+        // With base 1 every alpha is 1, so the first syndrome is the XOR of all codewords
+        assert!(!all_fine);
+        assert_eq!(syndromes[0], GF8(1));
+    }
+
+    #[test]
+    fn test_generator_rows() {
+        for count in [2, 8, 30] {
+            let rows = generator_rows(count);
+
+            // The generator, with its leading 1, is zero at every root
+            let generator: Vec<GF8> = std::iter::once(GF8(1)).chain(rows[1][..count].iter().map(|g| GF8(*g))).collect();
+            for root in EXP8.iter().take(count) {
+                let value = generator.iter().fold(GF8(0), |value, coefficient| value * *root + *coefficient);
+                assert_eq!(value, GF8(0));
+            }
+
+            assert!(rows[0].iter().all(|g| *g == 0));
+            assert!(rows[7].iter().zip(&rows[1]).all(|(g7, g1)| GF8(*g7) == GF8(7) * GF8(*g1)));
+        }
+    }
+
+    #[test]
+    fn test_syndrome_matches_direct_sum() {
+        let block: Vec<u8> = (0..70).map(|i| (i * 73 % 256) as u8).collect();
+        let (_, syndromes) = calculate_syndromes(&block, &BlockInfo::new(1, 70, 40, 15));
+
+        for (base, synd) in EXP8.iter().zip(&syndromes) {
+            let mut expected = GF8(0);
+            let mut alpha = GF8(1);
+            for codeword in block.iter().rev() {
+                expected = expected + (alpha * GF8(*codeword));
+                alpha = alpha * *base;
+            }
+
+            assert_eq!(*synd, expected);
+        }
+    }
+
+    #[test]
+    fn test_correct_fewer_errors_than_capacity() {
+        // The example from the standard, 01234567 in version 1-M, which corrects up to 4 codewords
+        let block = vec![
+            0x10, 0x20, 0x0C, 0x56, 0x61, 0x80, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xA5, 0x24,
+            0xD4, 0xC1, 0xED, 0x36, 0xC7, 0x87, 0x2C, 0x55,
+        ];
+        let block_info = BlockInfo::new(1, 26, 16, 4);
+
+        for index in 0..block.len() {
+            let mut damaged = block.clone();
+            damaged[index] ^= 0x01;
+
+            let (corrected, corrections) = correct_with_error_locations(damaged, &block_info).unwrap();
+            assert_eq!(corrected, block);
+            assert_eq!(corrections, vec![(index, 0x01)]);
+        }
+
+        let mut damaged = block.clone();
+        for index in &[0, 7, 16, 25] {
+            damaged[*index] ^= 0xFF;
+        }
+
+        assert_eq!(correct_with_error_count(damaged.clone(), &block_info).unwrap(), (block.clone(), 32));
+
+        damaged[10] ^= 0xFF;
+        assert!(correct(damaged, &block_info).is_err());
     }
 
     #[test]
@@ -250,4 +492,41 @@ mod tests {
         assert_eq!(corrected, 0b00000111);
         assert_eq!(error_pattern.count_ones(), 3);
     }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_correct_blocks() {
+        use super::super::blocks::blocks;
+        use super::super::format::read_format;
+        use super::super::{block_info, mask::StandardMask};
+        use crate::encode::QREncoder;
+        use crate::util::fault;
+        use crate::util::limits::ResourceLimits;
+        use crate::util::qr::ECLevel;
+
+        // 81 blocks, enough to be corrected side by side with the parallel feature
+        let mut data = QREncoder::new().version(40).ec_level(ECLevel::HIGH).encode("blocks").unwrap().to_qr_data();
+        fault::flip_modules(&mut data, 300, 7).unwrap();
+
+        let (level, mask) = read_format(&data).unwrap();
+        let block_info = block_info(40, &level).unwrap();
+        let blocks = blocks(&data, &level, &StandardMask::new(mask).unwrap()).unwrap();
+
+        let (sequential, sequential_counted) = metrics::collect(|| {
+            let corrected = blocks.iter().zip(block_info).map(|(block, bi)| correct(block.clone(), bi).unwrap());
+            corrected.collect::<Vec<_>>()
+        });
+        let (corrected, counted) = metrics::collect(|| correct_blocks(blocks.clone(), block_info, correct).unwrap());
+        assert_eq!(corrected, sequential);
+        assert_eq!(counted.rs_iterations, sequential_counted.rs_iterations);
+
+        // The iterations of all blocks count against the limit together
+        let limits = ResourceLimits {
+            max_rs_iterations: counted.rs_iterations - 1,
+            ..ResourceLimits::UNLIMITED
+        };
+        let (corrected, exceeded) = limits::within(limits, || correct_blocks(blocks.clone(), block_info, correct));
+        assert!(corrected.is_err());
+        assert!(exceeded.unwrap().is_resource_limit());
+    }
 }
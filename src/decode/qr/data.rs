@@ -1,75 +1,435 @@
-use crate::util::qr::QRError;
+use std::ops::Range;
+
+use crate::util::qr::{QRError, StructuredAppend};
 use crate::util::Chomp;
 
-pub fn data(input: Vec<u8>, version: u32) -> Result<String, QRError> {
-    let mut chomp = Chomp::new(input);
-    let mut result = String::new();
+/// Group separator, which separates variable length GS1 element strings
+const GS: char = '\u{1D}';
+
+/// How the bytes of eight bit segments become text
+///
+/// Eight bit data is ISO 8859-1 according to the standard, but most generators write UTF-8 without saying so, and some
+/// codes hold binary data that is neither. Set a policy on [`QRDecoder`] or [`QRDecoderWithInfo`] with
+/// `with_byte_policy`, for a result that does not depend on guessing.
+///
+/// [`QRDecoder`]: struct.QRDecoder.html
+/// [`QRDecoderWithInfo`]: struct.QRDecoderWithInfo.html
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum BytePolicy {
+    /// UTF-8 when the bytes of the segment are valid UTF-8, and ISO 8859-1 otherwise. The default
+    #[default]
+    Latin1Fallback,
+    /// UTF-8, with a single U+FFFD replacement character for every sequence of bytes that is not valid UTF-8
+    ReplaceInvalid,
+    /// UTF-8, failing the code with an [`ErrorCode::Utf8`] error for bytes that are not valid UTF-8
+    ///
+    /// [`ErrorCode::Utf8`]: ../util/qr/enum.ErrorCode.html#variant.Utf8
+    Error,
+    /// Every byte as the character with the same value, U+0000 to U+00FF, so the bytes are `c as u8` of the
+    /// characters of the segment. Nothing is interpreted as UTF-8
+    ///
+    /// For bytes that are not valid UTF-8 this is the same text as `Latin1Fallback` gives. It differs for valid
+    /// UTF-8, which stays bytes: "é" is the two characters U+00C3 U+00A9 rather than one. A
+    /// [`QRStreamDecoder`](struct.QRStreamDecoder.html) with this policy writes the bytes themselves to its sink.
+    RawBytes,
+}
+
+pub fn data(input: Vec<u8>, version: u32, policy: BytePolicy) -> Result<String, QRError> {
+    Ok(data_with_spans(input, version, policy)?.0)
+}
+
+/// A byte range of the decoded text and the range of bits of the data codewords that encode it
+pub type Span = (Range<usize>, Range<usize>);
+
+/// Same as [`data`], also with the bits every piece of the text comes from, in the order of the text
+///
+/// The pieces are as small as the encoding allows: three digits, two alphanumeric characters or a single character
+/// of eight bit data. Alphanumeric data in FNC1 mode, where `%` encodes a group separator, is a single piece, and so
+/// is a symbology identifier prepended for FNC1, which comes from the bits of the FNC1 mode.
+pub fn data_with_spans(input: Vec<u8>, version: u32, policy: BytePolicy) -> Result<(String, Vec<Span>), QRError> {
+    let (text, _) = parse(input, version, policy)?;
+    Ok((text.text, text.spans))
+}
+
+/// Same as [`data`], also with the structured append header of the code, if it has one
+pub fn data_with_append(
+    input: Vec<u8>,
+    version: u32,
+    policy: BytePolicy,
+) -> Result<(String, Option<StructuredAppend>), QRError> {
+    let (text, structured_append) = parse(input, version, policy)?;
+    Ok((text.text, structured_append))
+}
+
+/// Whether the data parses to exactly `expected`, the same text as [`data`] gives, compared while it is parsed
+///
+/// No text is built: every piece is compared with the expected text as it is read, and the comparison stops at the
+/// first piece that differs, which includes one that runs past the end of `expected`. A difference is final, even
+/// when the data after it would not parse. Data with FNC1, which puts a symbology identifier in front of the text,
+/// and eight bit data that is not valid UTF-8 with [`BytePolicy::ReplaceInvalid`] are parsed into the whole text
+/// instead, which is then compared.
+///
+/// # Errors
+///
+/// Returns `QRError` if the data does not parse, up to where it differs from `expected`
+pub fn matches(input: &[u8], version: u32, policy: BytePolicy, expected: &str) -> Result<bool, QRError> {
+    let mut chomp = Chomp::new(input.to_vec());
+    // Bytes of the expected text matched so far, always at a character boundary
+    let mut matched = 0;
+
+    while let Some(mode) = chomp.chomp(4) {
+        let same = match mode {
+            0b0001 => numeric_pieces(&mut chomp, version, |piece, _| compare(expected, &mut matched, piece))?,
+            0b0010 => alphanumeric_pieces(&mut chomp, version, |piece, _| compare(expected, &mut matched, piece))?,
+            0b0100 => match eight_bit_compared(&mut chomp, version, policy, &expected[matched..])? {
+                Compared::Same(length) => {
+                    matched += length;
+                    true
+                }
+                Compared::Differs => false,
+                Compared::Unknown => return Ok(data(input.to_vec(), version, policy)? == expected),
+            },
+            0b0101 | 0b1001 => return Ok(data(input.to_vec(), version, policy)? == expected),
+            // The structured append header is not part of the text
+            0b0011 => {
+                read_bits(&mut chomp, 4)?;
+                read_bits(&mut chomp, 4)?;
+                read_bits(&mut chomp, 8)?;
+                true
+            }
+            0b0000 => break,
+            _ => {
+                return Err(QRError::unsupported_mode(format!("Mode {mode:04b} not yet implemented.")))
+            }
+        };
+
+        if !same {
+            return Ok(false);
+        }
+    }
+
+    Ok(matched == expected.len())
+}
+
+/// Write the text of the data to `sink` one segment at a time, the same text as [`data`] gives, without building it
+///
+/// The data is parsed twice: first to check that all of it parses, so nothing is written for data that does not, and
+/// to find the symbology identifier of FNC1, which goes in front of the text. With [`BytePolicy::RawBytes`] the bytes
+/// of eight bit segments are written as they are, instead of the UTF-8 of the characters with their values. The result
+/// is the number of bytes written.
+///
+/// # Errors
+///
+/// Returns `QRError` if the data does not parse, or the first error of `sink`, after which nothing more is written
+pub fn write(
+    input: &[u8],
+    version: u32,
+    policy: BytePolicy,
+    mut sink: impl FnMut(&[u8]) -> Result<(), QRError>,
+) -> Result<usize, QRError> {
+    let identifier = segments(input, version, policy, |_| Ok(()))?;
+
+    let mut written = 0;
+    let mut write = |bytes: &[u8]| match bytes.is_empty() {
+        true => Ok(()),
+        false => {
+            written += bytes.len();
+            sink(bytes)
+        }
+    };
+
+    write(identifier.as_bytes())?;
+    segments(input, version, policy, |segment| write(&segment))?;
+
+    Ok(written)
+}
+
+// Parse the data and pass the bytes of the text of every segment to `segment` in turn, those of eight bit segments as
+// they are for `BytePolicy::RawBytes`. The result is the symbology identifier of FNC1, which is not passed on, as it
+// goes in front of the text
+fn segments(
+    input: &[u8],
+    version: u32,
+    policy: BytePolicy,
+    mut segment: impl FnMut(Vec<u8>) -> Result<(), QRError>,
+) -> Result<String, QRError> {
+    let mut chomp = Chomp::new(input.to_vec());
+    let mut identifier = String::new();
+    let joined = |pieces: Vec<Piece>| -> String { pieces.into_iter().map(|(piece, _)| piece).collect() };
 
     while let Some(mode) = chomp.chomp(4) {
         match mode {
-            0b0001 => result.push_str(numeric(&mut chomp, version)?.as_str()),
-            0b0010 => result.push_str(alphanumeric(&mut chomp, version)?.as_str()),
-            0b0100 => result.push_str(eight_bit(&mut chomp, version)?.as_str()),
+            0b0001 => segment(joined(numeric(&mut chomp, version)?).into_bytes())?,
+            0b0010 => {
+                let alphanumeric = joined(alphanumeric(&mut chomp, version)?);
+                match identifier.is_empty() {
+                    true => segment(alphanumeric.into_bytes())?,
+                    false => segment(fnc1_separators(&alphanumeric).into_bytes())?,
+                }
+            }
+            0b0100 => {
+                let eight_bit = joined(eight_bit(&mut chomp, version, policy)?.0);
+                match policy {
+                    BytePolicy::RawBytes => segment(eight_bit.chars().map(|c| c as u8).collect())?,
+                    _ => segment(eight_bit.into_bytes())?,
+                }
+            }
+            0b0101 => identifier.insert_str(0, "]Q3"),
+            0b1001 => identifier.insert_str(0, &format!("]Q5{}", application_indicator(&mut chomp)?)),
+            0b0011 => {
+                read_bits(&mut chomp, 4)?;
+                read_bits(&mut chomp, 4)?;
+                read_bits(&mut chomp, 8)?;
+            }
             0b0000 => break,
             _ => {
-                return Err(QRError {
-                    msg: format!("Mode {mode:04b} not yet implemented."),
-                })
+                return Err(QRError::unsupported_mode(format!("Mode {mode:04b} not yet implemented.")))
             }
         }
     }
 
+    Ok(identifier)
+}
+
+// Whether the piece is next in the expected text after the bytes matched so far, which then include it
+fn compare(expected: &str, matched: &mut usize, piece: &str) -> bool {
+    let same = expected[*matched..].starts_with(piece);
+    if same {
+        *matched += piece.len();
+    }
+
+    same
+}
+
+// How a segment compares with the expected text that follows what was matched before it
+enum Compared {
+    // The segment is the text, this many bytes of it
+    Same(usize),
+    Differs,
+    // Only the text of the whole segment can tell
+    Unknown,
+}
+
+fn parse(input: Vec<u8>, version: u32, policy: BytePolicy) -> Result<(Text, Option<StructuredAppend>), QRError> {
+    let mut chomp = Chomp::new(input);
+    let mut text = Text::default();
+    let mut fnc1 = false;
+    let mut structured_append = None;
+    // The parity of the data of this code, over the bytes of every character as it was encoded
+    let mut parity = 0;
+
+    loop {
+        let start = chomp.position();
+        let mode = match chomp.chomp(4) {
+            Some(mode) => mode,
+            None => break,
+        };
+
+        match mode {
+            0b0001 => {
+                let numeric = numeric(&mut chomp, version)?;
+                parity ^= text_parity(&numeric);
+                text.extend(numeric);
+            }
+            0b0010 => {
+                let alphanumeric = alphanumeric(&mut chomp, version)?;
+                parity ^= text_parity(&alphanumeric);
+                if fnc1 {
+                    text.push_joined(alphanumeric, fnc1_separators);
+                } else {
+                    text.extend(alphanumeric);
+                }
+            }
+            0b0100 => {
+                let (eight_bit, bytes_parity) = eight_bit(&mut chomp, version, policy)?;
+                parity ^= bytes_parity;
+                text.extend(eight_bit);
+            }
+            // FNC1 in first position: GS1 data. The symbology identifier is prepended, as barcode scanners do,
+            // so the content can be recognised as GS1 element strings
+            0b0101 => {
+                fnc1 = true;
+                text.prepend("]Q3", start..chomp.position());
+            }
+            // FNC1 in second position: data formatted to an industry standard, identified by an application indicator
+            0b1001 => {
+                let indicator = application_indicator(&mut chomp)?;
+
+                fnc1 = true;
+                text.prepend(&format!("]Q5{indicator}"), start..chomp.position());
+            }
+            // Structured append: the position of this code in a sequence of codes, their number and the parity of
+            // the whole message. Each code of the sequence has its own part of the message
+            0b0011 => {
+                let index = read_bits(&mut chomp, 4)?;
+                let total = read_bits(&mut chomp, 4)? + 1;
+                let parity = read_bits(&mut chomp, 8)?;
+
+                structured_append = Some(StructuredAppend {
+                    index,
+                    total,
+                    parity,
+                    data_parity: 0,
+                });
+            }
+            0b0000 => break,
+            _ => {
+                return Err(QRError::unsupported_mode(format!("Mode {mode:04b} not yet implemented.")))
+            }
+        }
+    }
+
+    if let Some(header) = &mut structured_append {
+        header.data_parity = parity;
+    }
+
+    Ok((text, structured_append))
+}
+
+// The application indicator of FNC1 in second position: two digits, or a letter encoded as its value plus 100
+fn application_indicator(chomp: &mut Chomp) -> Result<String, QRError> {
+    let indicator = read_bits(chomp, 8)?;
+
+    match indicator {
+        0..=99 => Ok(format!("{indicator:02}")),
+        165..=190 | 197..=222 => Ok(String::from(char::from(indicator - 100))),
+        _ => Err(QRError::data(format!("Invalid FNC1 application indicator {indicator}"))),
+    }
+}
+
+// Numeric and alphanumeric characters are ASCII, their parity is that of the bytes of the text
+fn text_parity(pieces: &[Piece]) -> u8 {
+    pieces.iter().flat_map(|(piece, _)| piece.bytes()).fold(0, |parity, byte| parity ^ byte)
+}
+
+// A piece of the text of a segment, with the bits it comes from
+type Piece = (String, Range<usize>);
+
+// The decoded text so far, with where every piece of it comes from
+#[derive(Default)]
+struct Text {
+    text: String,
+    spans: Vec<Span>,
+}
+
+impl Text {
+    fn extend(&mut self, pieces: Vec<Piece>) {
+        for (piece, bits) in pieces {
+            let start = self.text.len();
+            self.text.push_str(&piece);
+            self.spans.push((start..self.text.len(), bits));
+        }
+    }
+
+    // The pieces as one, changed as a whole
+    fn push_joined(&mut self, pieces: Vec<Piece>, change: impl Fn(&str) -> String) {
+        let bits = match (pieces.first(), pieces.last()) {
+            (Some(first), Some(last)) => first.1.start..last.1.end,
+            _ => return,
+        };
+        let joined: String = pieces.into_iter().map(|(piece, _)| piece).collect();
+
+        self.extend(vec![(change(&joined), bits)]);
+    }
+
+    fn prepend(&mut self, prefix: &str, bits: Range<usize>) {
+        self.text.insert_str(0, prefix);
+
+        for (text, _) in &mut self.spans {
+            *text = text.start + prefix.len()..text.end + prefix.len();
+        }
+        self.spans.insert(0, (0..prefix.len(), bits));
+    }
+}
+
+// In FNC1 mode a % in alphanumeric data encodes a group separator and %% encodes a literal %
+fn fnc1_separators(alphanumeric: &str) -> String {
+    let mut result = String::with_capacity(alphanumeric.len());
+    let mut chars = alphanumeric.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '%' if chars.peek() == Some(&'%') => {
+                chars.next();
+                result.push('%');
+            }
+            '%' => result.push(GS),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+fn numeric(chomp: &mut Chomp, version: u32) -> Result<Vec<Piece>, QRError> {
+    let mut result = vec![];
+    numeric_pieces(chomp, version, |piece, bits| {
+        result.push((String::from(piece), bits));
+        true
+    })?;
+
+    debug!("NUMERIC {result:?}");
+
     Ok(result)
 }
 
-fn numeric(chomp: &mut Chomp, version: u32) -> Result<String, QRError> {
+// Every group of digits with its bits in turn, while `piece` returns true. False if it stopped there
+fn numeric_pieces(
+    chomp: &mut Chomp,
+    version: u32,
+    mut piece: impl FnMut(&str, Range<usize>) -> bool,
+) -> Result<bool, QRError> {
     let length_bits = match version {
         1..=9 => 10,
         10..=26 => 12,
         27..=40 => 14,
         _ => {
-            return Err(QRError {
-                msg: format!("Unknown version {version}"),
-            });
+            return Err(QRError::version(format!("Unknown version {version}")));
         }
     };
 
     let mut length = chomp.chomp_or_u16(
         length_bits,
-        QRError {
-            msg: format!("Could not read {length_bits} bits for numeric length"),
-        },
+        QRError::data(format!("Could not read {length_bits} bits for numeric length")),
     )?;
 
-    let mut result = String::new();
-
     while length > 0 {
-        if length >= 3 {
-            let digits = read_bits_u16(chomp, 10)?;
-            result.push_str(&format!("{digits:03}"));
+        let start = chomp.position();
+
+        // Three digits in 10 bits, the last two in 7 and the last one in 4
+        let (bits, max, count) = match length {
+            3.. => (10, 999, 3),
+            2 => (7, 99, 2),
+            _ => (4, 9, 1),
+        };
+        let mut value = numeric_digits(chomp, bits, max)?;
+
+        let mut digits = [b'0'; 3];
+        for digit in digits[..usize::from(count)].iter_mut().rev() {
+            *digit = b'0' + (value % 10) as u8;
+            value /= 10;
+        }
 
-            length -= 3;
-            continue;
+        let digits = std::str::from_utf8(&digits[..usize::from(count)]).expect("Digits are ASCII");
+        if !piece(digits, start..chomp.position()) {
+            return Ok(false);
         }
 
-        if length == 2 {
-            let digits = read_bits_u16(chomp, 7)?;
-            result.push_str(&format!("{digits:02}"));
+        length -= count;
+    }
 
-            break;
-        }
+    Ok(true)
+}
 
-        if length == 1 {
-            let digits = read_bits_u16(chomp, 4)?;
-            result.push_str(&format!("{digits:01}"));
+// Groups of digits are read in bits that can hold larger values, which only a damaged or malicious code has
+fn numeric_digits(chomp: &mut Chomp, bits: u8, max: u16) -> Result<u16, QRError> {
+    let digits = read_bits_u16(chomp, bits)?;
 
-            break;
-        }
+    if digits > max {
+        return Err(QRError::data(format!("Invalid numeric value {digits}, more than {max}")));
     }
 
-    debug!("NUMERIC {result:?}");
-
-    Ok(result)
+    Ok(digits)
 }
 
 const ALPHANUMERIC: [char; 45] = [
@@ -78,74 +438,80 @@ const ALPHANUMERIC: [char; 45] = [
     '%', '*', '+', '-', '.', '/', ':',
 ];
 
-fn alphanumeric(chomp: &mut Chomp, version: u32) -> Result<String, QRError> {
+fn alphanumeric(chomp: &mut Chomp, version: u32) -> Result<Vec<Piece>, QRError> {
+    let mut result = vec![];
+    alphanumeric_pieces(chomp, version, |piece, bits| {
+        result.push((String::from(piece), bits));
+        true
+    })?;
+
+    debug!("ALPHANUMERIC {result:?}");
+
+    Ok(result)
+}
+
+// Every pair of characters with its bits in turn, while `piece` returns true. False if it stopped there
+fn alphanumeric_pieces(
+    chomp: &mut Chomp,
+    version: u32,
+    mut piece: impl FnMut(&str, Range<usize>) -> bool,
+) -> Result<bool, QRError> {
     let length_bits = match version {
         1..=9 => 9,
         10..=26 => 11,
         27..=40 => 13,
         _ => {
-            return Err(QRError {
-                msg: format!("Unknown version {version}"),
-            });
+            return Err(QRError::version(format!("Unknown version {version}")));
         }
     };
 
     let mut length = chomp.chomp_or_u16(
         length_bits,
-        QRError {
-            msg: format!(
-                "Could not read {length_bits} bits for alphanumeric length"
-            ),
-        },
+        QRError::data(format!("Could not read {length_bits} bits for alphanumeric length")),
     )?;
 
-    let mut result = String::new();
-
     while length > 0 {
-        if length >= 2 {
-            let chars = read_bits_u16(chomp, 11)?;
-            result.push(ALPHANUMERIC[chars as usize / 45]);
-            result.push(ALPHANUMERIC[chars as usize % 45]);
+        let start = chomp.position();
+
+        // Two characters in 11 bits, the last one in 6
+        let mut chars = [0; 2];
+        let count = match length {
+            2.. => {
+                let pair = read_bits_u16(chomp, 11)?;
+                chars = [alphanumeric_char(pair / 45)?, alphanumeric_char(pair % 45)?];
+                2
+            }
+            _ => {
+                chars[0] = alphanumeric_char(read_bits_u16(chomp, 6)?)?;
+                1
+            }
+        };
 
-            length -= 2;
-            continue;
+        let chars = std::str::from_utf8(&chars[..usize::from(count)]).expect("Alphanumeric characters are ASCII");
+        if !piece(chars, start..chomp.position()) {
+            return Ok(false);
         }
 
-        if length == 1 {
-            let chars = read_bits_u16(chomp, 6)?;
-            result.push(ALPHANUMERIC[chars as usize]);
-
-            break;
-        }
+        length -= count;
     }
 
-    debug!("ALPHANUMERIC {result:?}");
-
-    Ok(result)
+    Ok(true)
 }
 
-fn eight_bit(chomp: &mut Chomp, version: u32) -> Result<String, QRError> {
-    let length_bits = match version {
-        1..=9 => 8,
-        10..=26 => 16,
-        27..=40 => 16,
-        _ => {
-            return Err(QRError {
-                msg: format!("Unknown version {version}"),
-            });
-        }
-    };
+// 11 and 6 bits can hold values that are not characters, which only a damaged or malicious code has
+fn alphanumeric_char(value: u16) -> Result<u8, QRError> {
+    ALPHANUMERIC
+        .get(usize::from(value))
+        .map(|c| *c as u8)
+        .ok_or_else(|| QRError::data(format!("Invalid alphanumeric value {value}")))
+}
 
-    let length = chomp.chomp_or_u16(
-        length_bits,
-        QRError {
-            msg: format!(
-                "Could not read {length_bits} bits for alphanumeric length"
-            ),
-        },
-    )?;
+// The characters of the bytes, with the parity of the bytes
+fn eight_bit(chomp: &mut Chomp, version: u32, policy: BytePolicy) -> Result<(Vec<Piece>, u8), QRError> {
+    let length = eight_bit_length(chomp, version)?;
 
     let mut result = vec![];
+    let start = chomp.position();
 
     for _ in 0..length {
         result.push(read_bits(chomp, 8)?);
@@ -153,45 +519,362 @@ fn eight_bit(chomp: &mut Chomp, version: u32) -> Result<String, QRError> {
 
     debug!("EIGHT BIT RAW {result:?}");
 
-    let mut may_be_utf8 = false;
+    // The bits of the bytes from `from` up to `to`
+    let bits = |from: usize, to: usize| start + 8 * from..start + 8 * to;
+    let parity = result.iter().fold(0, |parity, byte| parity ^ byte);
+
+    let utf8 = |utf8: &str, offset: usize| -> Vec<Piece> {
+        utf8.char_indices().map(|(i, c)| (String::from(c), bits(offset + i, offset + i + c.len_utf8()))).collect()
+    };
+    let iso88591 = |bytes: &[u8]| -> Vec<Piece> {
+        bytes.iter().enumerate().map(|(i, r)| (String::from(*r as char), bits(i, i + 1))).collect()
+    };
+
+    let final_result = match (policy, std::str::from_utf8(&result)) {
+        (BytePolicy::RawBytes, _) | (BytePolicy::Latin1Fallback, Err(_)) => {
+            debug!("EIGHT BIT AS ISO 8859-1");
+            iso88591(&result)
+        }
+        (_, Ok(valid)) => {
+            debug!("EIGHT BIT AS UTF-8 {valid:?}");
+            utf8(valid, 0)
+        }
+        (BytePolicy::Error, Err(_)) => return Err(QRError::from(String::from_utf8(result).unwrap_err())),
+        (BytePolicy::ReplaceInvalid, Err(_)) => {
+            debug!("EIGHT BIT AS UTF-8 WITH REPLACEMENTS");
+            let mut pieces = vec![];
+            let mut offset = 0;
+
+            for chunk in result.utf8_chunks() {
+                pieces.extend(utf8(chunk.valid(), offset));
+                offset += chunk.valid().len();
+
+                if !chunk.invalid().is_empty() {
+                    let end = offset + chunk.invalid().len();
+                    pieces.push((String::from(char::REPLACEMENT_CHARACTER), bits(offset, end)));
+                    offset = end;
+                }
+            }
+
+            pieces
+        }
+    };
+
+    Ok((final_result, parity))
+}
+
+// Compare an eight bit segment with the start of `rest`, as the text the policy makes of its bytes
+fn eight_bit_compared(chomp: &mut Chomp, version: u32, policy: BytePolicy, rest: &str) -> Result<Compared, QRError> {
+    let length = usize::from(eight_bit_length(chomp, version)?);
+
+    // As UTF-8 the text is the bytes themselves, as ISO 8859-1 every byte is the character with its value
+    let mut as_utf8 = policy != BytePolicy::RawBytes;
+    let mut as_iso88591 = matches!(policy, BytePolicy::Latin1Fallback | BytePolicy::RawBytes);
+    let mut chars = rest.char_indices();
+    let mut iso88591_end = 0;
+
+    for i in 0..length {
+        let byte = read_bits(chomp, 8)?;
+
+        as_utf8 &= rest.as_bytes().get(i) == Some(&byte);
+        if as_iso88591 {
+            match chars.next() {
+                Some((at, c)) if u32::from(c) == u32::from(byte) => iso88591_end = at + c.len_utf8(),
+                _ => as_iso88591 = false,
+            }
+        }
 
-    for r in &result {
-        if *r == 0xC3 {
-            may_be_utf8 = true;
-            break;
+        // Invalid UTF-8 is replaced, so the bytes alone can not tell
+        if !as_utf8 && !as_iso88591 && policy != BytePolicy::ReplaceInvalid {
+            return Ok(Compared::Differs);
         }
     }
 
-    let final_result = if may_be_utf8 {
-        let utf8 = String::from_utf8(result)?;
-        debug!("EIGHT BIT AS UTF-8 {utf8:?}");
-        utf8
-    } else {
-        let mut iso88591 = String::new();
-        for r in result {
-            iso88591.push(r as char);
+    // The same bytes as the expected text, up to a character boundary of it, are valid UTF-8
+    if as_utf8 && rest.is_char_boundary(length) {
+        return Ok(Compared::Same(length));
+    }
+
+    Ok(match policy {
+        BytePolicy::RawBytes if as_iso88591 => Compared::Same(iso88591_end),
+        // The bytes are ISO 8859-1 only when they are not valid UTF-8, they are the characters matched
+        BytePolicy::Latin1Fallback if as_iso88591 => {
+            let bytes: Vec<u8> = rest[..iso88591_end].chars().map(|c| c as u8).collect();
+            match std::str::from_utf8(&bytes) {
+                Ok(_) => Compared::Differs,
+                Err(_) => Compared::Same(iso88591_end),
+            }
+        }
+        BytePolicy::ReplaceInvalid => Compared::Unknown,
+        _ => Compared::Differs,
+    })
+}
+
+fn eight_bit_length(chomp: &mut Chomp, version: u32) -> Result<u16, QRError> {
+    let length_bits = match version {
+        1..=9 => 8,
+        10..=26 => 16,
+        27..=40 => 16,
+        _ => {
+            return Err(QRError::version(format!("Unknown version {version}")));
         }
-        debug!("EIGHT BIT AS ISO 8859-1 {iso88591:?}");
-        iso88591
     };
 
-    Ok(final_result)
+    chomp.chomp_or_u16(
+        length_bits,
+        QRError::data(format!("Could not read {length_bits} bits for alphanumeric length")),
+    )
 }
 
 fn read_bits(chomp: &mut Chomp, bits: u8) -> Result<u8, QRError> {
     chomp.chomp_or(
         bits,
-        QRError {
-            msg: format!("Could not read {bits} bits"),
-        },
+        QRError::data(format!("Could not read {bits} bits")),
     )
 }
 
 fn read_bits_u16(chomp: &mut Chomp, bits: u8) -> Result<u16, QRError> {
     chomp.chomp_or_u16(
         bits,
-        QRError {
-            msg: format!("Could not read {bits} bits"),
-        },
+        QRError::data(format!("Could not read {bits} bits")),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pack (value, bit count) pairs into bytes, padding the last byte with zeroes
+    fn bits(fields: &[(u16, u8)]) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut count = 0;
+
+        for (value, nr_bits) in fields {
+            for i in (0..*nr_bits).rev() {
+                if count % 8 == 0 {
+                    bytes.push(0);
+                }
+                *bytes.last_mut().unwrap() |= (((value >> i) & 1) as u8) << (7 - count % 8);
+                count += 1;
+            }
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn test_fnc1_first_position() {
+        // FNC1, numeric "0104912345123459", alphanumeric "10ABC%21%%1"
+        let input = bits(&[
+            (0b0101, 4),
+            (0b0001, 4),
+            (16, 10),
+            (10, 10),
+            (491, 10),
+            (234, 10),
+            (512, 10),
+            (345, 10),
+            (9, 4),
+            (0b0010, 4),
+            (11, 9),
+            (45, 11),
+            (10 * 45 + 11, 11),
+            (12 * 45 + 38, 11),
+            (2 * 45 + 1, 11),
+            (38 * 45 + 38, 11),
+            (1, 6),
+            (0, 4),
+        ]);
+
+        assert_eq!(data(input, 1, BytePolicy::default()), Ok(String::from("]Q3010491234512345910ABC\u{1D}21%1")));
+    }
+
+    #[test]
+    fn test_fnc1_second_position() {
+        // FNC1 with application indicator "A", eight bit "BC%"
+        let input = bits(&[(0b1001, 4), (165, 8), (0b0100, 4), (3, 8), (66, 8), (67, 8), (37, 8), (0, 4)]);
+
+        assert_eq!(data(input.clone(), 1, BytePolicy::default()), Ok(String::from("]Q5ABC%")));
+
+        // The symbology identifier comes from the FNC1 mode and its indicator, each character from its byte
+        let (_, spans) = data_with_spans(input, 1, BytePolicy::default()).unwrap();
+        assert_eq!(spans, vec![(0..4, 0..12), (4..5, 24..32), (5..6, 32..40), (6..7, 40..48)]);
+    }
+
+    #[test]
+    fn test_structured_append() {
+        // Second of three codes, with the parity of the whole message, then eight bit "BC"
+        let input = bits(&[(0b0011, 4), (1, 4), (2, 4), (0x42, 8), (0b0100, 4), (2, 8), (66, 8), (67, 8), (0, 4)]);
+
+        assert_eq!(data(input.clone(), 1, BytePolicy::default()), Ok(String::from("BC")));
+        assert!(data(bits(&[(0b0011, 4), (1, 4), (2, 4)]), 1, BytePolicy::default()).is_err());
+
+        // The parity of the data of this code is that of its bytes, "B" ^ "C"
+        let (_, header) = data_with_append(input, 1, BytePolicy::default()).unwrap();
+        let expected = StructuredAppend {
+            index: 1,
+            total: 3,
+            parity: 0x42,
+            data_parity: 0x42 ^ 0x43,
+        };
+        assert_eq!(header, Some(expected));
+
+        // Digits and alphanumeric characters count as the bytes of the characters
+        let input = bits(&[(0b0011, 4), (0, 4), (0, 4), (0, 8), (0b0001, 4), (2, 10), (12, 7), (0, 4)]);
+        let (text, header) = data_with_append(input, 1, BytePolicy::default()).unwrap();
+        assert_eq!((text.as_str(), header.map(|h| h.data_parity)), ("12", Some(b'1' ^ b'2')));
+        assert_eq!(data_with_append(bits(&[(0b0001, 4), (1, 10), (1, 4)]), 1, BytePolicy::default()).unwrap().1, None);
+    }
+
+    #[test]
+    fn test_byte_policy() {
+        use crate::util::qr::ErrorCode;
+
+        // Eight bit "é" as UTF-8, a byte that is not UTF-8 and "a"
+        let input = bits(&[(0b0100, 4), (4, 8), (0xC3, 8), (0xA9, 8), (0xFF, 8), (0x61, 8), (0, 4)]);
+        let decode = |policy| data(input.clone(), 1, policy);
+
+        assert_eq!(decode(BytePolicy::Latin1Fallback), Ok(String::from("\u{C3}\u{A9}\u{FF}a")));
+        assert_eq!(decode(BytePolicy::ReplaceInvalid), Ok(String::from("é\u{FFFD}a")));
+        assert_eq!(decode(BytePolicy::Error).unwrap_err().code(), ErrorCode::Utf8);
+        assert_eq!(decode(BytePolicy::RawBytes), Ok(String::from("\u{C3}\u{A9}\u{FF}a")));
+
+        // The replacement character comes from the bits of the byte it replaces
+        let (_, spans) = data_with_spans(input, 1, BytePolicy::ReplaceInvalid).unwrap();
+        assert_eq!(spans, vec![(0..2, 12..28), (2..5, 28..36), (5..6, 36..44)]);
+
+        // Only raw bytes keep valid UTF-8 as it is
+        let input = bits(&[(0b0100, 4), (2, 8), (0xC3, 8), (0xA9, 8), (0, 4)]);
+        assert_eq!(data(input.clone(), 1, BytePolicy::Latin1Fallback), Ok(String::from("é")));
+        assert_eq!(data(input.clone(), 1, BytePolicy::Error), Ok(String::from("é")));
+        assert_eq!(data(input, 1, BytePolicy::RawBytes), Ok(String::from("\u{C3}\u{A9}")));
+    }
+
+    #[test]
+    fn test_default_byte_policy() {
+        let eight_bit = |bytes: &[u8]| {
+            let mut fields = vec![(0b0100, 4), (bytes.len() as u16, 8)];
+            fields.extend(bytes.iter().map(|byte| (u16::from(*byte), 8)));
+            fields.push((0, 4));
+            data(bits(&fields), 1, BytePolicy::default())
+        };
+
+        // Up to 0.3 a segment with the byte 0xC3 was read as UTF-8 and failed when it was not, any other as ISO
+        // 8859-1. Now invalid UTF-8 is ISO 8859-1 whatever its bytes, valid UTF-8 is UTF-8 without 0xC3 as well
+        assert_eq!(eight_bit(&[0xC3, 0x28]), Ok(String::from("\u{C3}(")));
+        assert_eq!(eight_bit("€".as_bytes()), Ok(String::from("€")));
+        assert_eq!(eight_bit(&[0xE9, 0x74, 0xE9]), Ok(String::from("été")));
+    }
+
+    #[test]
+    fn test_write() {
+        // The segments in turn: numeric "12", FNC1 with application indicator "A", eight bit "é" and a byte that is
+        // not UTF-8
+        let input = bits(&[
+            (0b0001, 4),
+            (2, 10),
+            (12, 7),
+            (0b1001, 4),
+            (165, 8),
+            (0b0100, 4),
+            (3, 8),
+            (0xC3, 8),
+            (0xA9, 8),
+            (0xFF, 8),
+            (0, 4),
+        ]);
+        let written = |input: &[u8], policy| {
+            let mut segments: Vec<Vec<u8>> = vec![];
+            let written = write(input, 1, policy, |segment| {
+                segments.push(segment.to_vec());
+                Ok(())
+            });
+            written.map(|written| (written, segments))
+        };
+
+        // The same text as the whole data, the symbology identifier in front of it
+        let (length, segments) = written(&input, BytePolicy::default()).unwrap();
+        let text = data(input.clone(), 1, BytePolicy::default()).unwrap();
+        assert_eq!((length, segments.concat()), (text.len(), text.into_bytes()));
+        assert_eq!(segments.len(), 3);
+
+        // Raw bytes reach the sink as they are
+        let (length, segments) = written(&input, BytePolicy::RawBytes).unwrap();
+        assert_eq!((length, segments.concat()), (9, b"]Q5A12\xC3\xA9\xFF".to_vec()));
+
+        // Nothing is written for data that does not parse, and the error of the sink ends the writing
+        let invalid = bits(&[(0b0001, 4), (2, 10), (12, 7), (0b0010, 4), (1, 9), (45, 6), (0, 4)]);
+        let error = write(&invalid, 1, BytePolicy::default(), |_| panic!("Nothing is written")).unwrap_err();
+        assert_eq!(error.msg, "Invalid alphanumeric value 45");
+
+        let mut calls = 0;
+        let failed = write(&input, 1, BytePolicy::default(), |_| {
+            calls += 1;
+            Err(QRError::new("full"))
+        });
+        assert_eq!((failed, calls), (Err(QRError::new("full")), 1));
+    }
+
+    #[test]
+    fn test_matches() {
+        // Numeric "12345", alphanumeric "AB-", eight bit "é", numeric "7"
+        let input = bits(&[
+            (0b0001, 4),
+            (5, 10),
+            (123, 10),
+            (45, 7),
+            (0b0010, 4),
+            (3, 9),
+            (10 * 45 + 11, 11),
+            (41, 6),
+            (0b0100, 4),
+            (2, 8),
+            (0xC3, 8),
+            (0xA9, 8),
+            (0b0001, 4),
+            (1, 10),
+            (7, 4),
+            (0, 4),
+        ]);
+        let matched = |input: &[u8], policy, expected: &str| matches(input, 1, policy, expected).unwrap();
+
+        assert!(matched(&input, BytePolicy::default(), "12345AB-é7"));
+        assert!(matched(&input, BytePolicy::ReplaceInvalid, "12345AB-é7"));
+        for other in ["12345AB-é", "12345AB-é78", "12345AB-e7", "12346AB-é7", "", "\u{C3}\u{A9}"] {
+            assert!(!matched(&input, BytePolicy::default(), other), "{}", other);
+        }
+        assert!(matched(&input, BytePolicy::RawBytes, "12345AB-\u{C3}\u{A9}7"));
+        assert!(!matched(&input, BytePolicy::RawBytes, "12345AB-é7"));
+
+        // Every policy matches the text it decodes to
+        let input = bits(&[(0b0100, 4), (4, 8), (0xC3, 8), (0xA9, 8), (0xFF, 8), (0x61, 8), (0, 4)]);
+        let texts = ["\u{C3}\u{A9}\u{FF}a", "é\u{FFFD}a", "é\u{FF}a", "\u{C3}\u{A9}\u{FF}"];
+        let policies = [BytePolicy::Latin1Fallback, BytePolicy::ReplaceInvalid, BytePolicy::Error, BytePolicy::RawBytes];
+        for policy in policies {
+            for text in texts {
+                let decoded = data(input.clone(), 1, policy);
+                assert_eq!(matched(&input, policy, text), decoded.as_deref() == Ok(text), "{:?} {}", policy, text);
+            }
+        }
+
+        // FNC1 compares the whole text, with its symbology identifier
+        let input = bits(&[(0b1001, 4), (165, 8), (0b0100, 4), (3, 8), (66, 8), (67, 8), (37, 8), (0, 4)]);
+        assert!(matched(&input, BytePolicy::default(), "]Q5ABC%"));
+        assert!(!matched(&input, BytePolicy::default(), "BC%"));
+
+        // Data that does not parse before it differs is an error, after it a mismatch
+        let invalid = bits(&[(0b0001, 4), (6, 10), (123, 10), (1000, 10)]);
+        assert!(matches(&invalid, 1, BytePolicy::default(), "123456").is_err());
+        assert_eq!(matches(&invalid, 1, BytePolicy::default(), "124"), Ok(false));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        // Two alphanumeric characters, the first value too large
+        assert!(data(bits(&[(0b0010, 4), (2, 9), (45 * 45, 11)]), 1, BytePolicy::default()).is_err());
+        assert!(data(bits(&[(0b0010, 4), (1, 9), (45, 6)]), 1, BytePolicy::default()).is_err());
+        assert!(data(bits(&[(0b0001, 4), (3, 10), (1000, 10)]), 1, BytePolicy::default()).is_err());
+        assert!(data(bits(&[(0b0001, 4), (2, 10), (100, 7)]), 1, BytePolicy::default()).is_err());
+        assert!(data(bits(&[(0b0001, 4), (1, 10), (10, 4)]), 1, BytePolicy::default()).is_err());
+    }
+}
@@ -1,6 +1,9 @@
 use super::super::Decode;
+use super::data::BytePolicy;
+use super::mask::StandardMask;
 
-use crate::util::qr::{QRData, QRError, QRInfo};
+use crate::util::metrics;
+use crate::util::qr::{ECLevel, QRData, QRError, QRInfo};
 
 /// Decode a QR code into a resulting String
 ///
@@ -14,12 +17,52 @@ use crate::util::qr::{QRData, QRError, QRInfo};
 /// The error correction process can be relatively expensive. This decoder has a fast detection of the existence of errors,
 /// allowing to bypass the correction altogether if none exist. Users of this library are encouraged to provide high quality fault-free images,
 /// speeding up the decoding process by not having to correct errors.
-pub struct QRDecoder {}
+pub struct QRDecoder {
+    forced_format: Option<(ECLevel, u8)>,
+    byte_policy: BytePolicy,
+}
 
 impl QRDecoder {
     /// Construct a new QRDecoder
     pub fn new() -> QRDecoder {
-        QRDecoder {}
+        QRDecoder {
+            forced_format: None,
+            byte_policy: BytePolicy::default(),
+        }
+    }
+
+    /// Construct a QRDecoder that ignores the format information and uses this error correction level and mask
+    /// pattern instead
+    ///
+    /// This recovers QR Codes whose format information is destroyed, when the level and mask are known from another
+    /// code of the same batch, or by trying all 32 combinations.
+    pub fn with_forced_format(ec_level: ECLevel, mask: u8) -> QRDecoder {
+        QRDecoder {
+            forced_format: Some((ec_level, mask)),
+            byte_policy: BytePolicy::default(),
+        }
+    }
+
+    /// Decode the bytes of eight bit segments with this policy, [`BytePolicy::Latin1Fallback`] by default
+    ///
+    /// [`BytePolicy::Latin1Fallback`]: enum.BytePolicy.html#variant.Latin1Fallback
+    pub fn with_byte_policy(mut self, policy: BytePolicy) -> QRDecoder {
+        self.byte_policy = policy;
+        self
+    }
+
+    // Write the text of the code to the sink one segment at a time, with the first format that corrects and parses,
+    // see `data::write`
+    pub(crate) fn write(
+        &self,
+        data: Result<QRData, QRError>,
+        mut sink: impl FnMut(&[u8]) -> Result<(), QRError>,
+    ) -> Result<usize, QRError> {
+        let qr_data = data?;
+
+        first_decoded(formats(&self.forced_format, &qr_data)?, |format| {
+            super::data::write(&corrected_data(&qr_data, &format)?, qr_data.version, self.byte_policy, &mut sink)
+        })
     }
 }
 
@@ -27,36 +70,108 @@ impl Decode<QRData, String, QRError> for QRDecoder {
     fn decode(&self, data: Result<QRData, QRError>) -> Result<String, QRError> {
         let qr_data = data?;
 
-        let format = super::format::format(&qr_data)?;
-        let blocks = super::blocks::blocks(&qr_data, &format.0, &format.1)?;
-        let block_info = super::block_info(qr_data.version, &format.0)?;
+        first_decoded(formats(&self.forced_format, &qr_data)?, |format| {
+            decode_format(&qr_data, format, self.byte_policy)
+        })
+    }
+
+    fn matches(&self, data: &Result<QRData, QRError>, expected: &str) -> Option<bool> {
+        Some(matches(&self.forced_format, data, self.byte_policy, expected))
+    }
+}
 
-        let mut all_blocks = vec![];
+fn decode_format(qr_data: &QRData, format: (ECLevel, u8), policy: BytePolicy) -> Result<String, QRError> {
+    let data = super::data::data(corrected_data(qr_data, &format)?, qr_data.version, policy)?;
+    Ok(data)
+}
 
-        for (block, bi) in blocks.into_iter().zip(block_info) {
-            let corrected = super::correct::correct(block, &bi)?;
+// The data codewords of every block in turn, after error correction
+fn corrected_data(qr_data: &QRData, format: &(ECLevel, u8)) -> Result<Vec<u8>, QRError> {
+    let blocks = super::blocks::blocks(qr_data, &format.0, &StandardMask::new(format.1)?)?;
+    let block_info = super::block_info(qr_data.version, &format.0)?;
 
-            for corr in corrected.iter().take(bi.data_per as usize) {
-                all_blocks.push(*corr);
-            }
-        }
+    let mut all_blocks = vec![];
 
-        debug!("TOTAL LENGTH {len}", len = all_blocks.len());
+    let corrected = super::correct::correct_blocks(blocks, block_info, super::correct::correct)?;
 
-        let data = super::data::data(all_blocks, qr_data.version)?;
-        Ok(data)
+    for (corrected, bi) in corrected.iter().zip(block_info) {
+        for corr in corrected.iter().take(bi.data_per as usize) {
+            all_blocks.push(*corr);
+        }
     }
+
+    debug!("TOTAL LENGTH {len}", len = all_blocks.len());
+
+    Ok(all_blocks)
+}
+
+// Whether the code decodes to the expected text, with the first format that corrects and parses up to where the text
+// differs, see `data::matches`. A code that does not decode does not match
+fn matches(
+    forced_format: &Option<(ECLevel, u8)>,
+    data: &Result<QRData, QRError>,
+    policy: BytePolicy,
+    expected: &str,
+) -> bool {
+    let Ok(qr_data) = data else {
+        return false;
+    };
+
+    let matched = formats(forced_format, qr_data).and_then(|formats| {
+        first_decoded(formats, |format| {
+            super::data::matches(&corrected_data(qr_data, &format)?, qr_data.version, policy, expected)
+        })
+    });
+
+    matched.unwrap_or(false)
 }
 
 /// Decode a QR code into a resulting String. It also includes some information about the decoded QR Code.
 ///
 /// Functions the same as QRDecoder, apart from also returning some information about the decoded QR Code.
-pub struct QRDecoderWithInfo {}
+pub struct QRDecoderWithInfo {
+    forced_format: Option<(ECLevel, u8)>,
+    raw_data: bool,
+    byte_policy: BytePolicy,
+}
 
 impl QRDecoderWithInfo {
     /// Construct a new QRDecoder
     pub fn new() -> QRDecoderWithInfo {
-        QRDecoderWithInfo {}
+        QRDecoderWithInfo {
+            forced_format: None,
+            raw_data: false,
+            byte_policy: BytePolicy::default(),
+        }
+    }
+
+    /// Construct a QRDecoderWithInfo that ignores the format information, see
+    /// [`QRDecoder::with_forced_format`](struct.QRDecoder.html#method.with_forced_format)
+    pub fn with_forced_format(ec_level: ECLevel, mask: u8) -> QRDecoderWithInfo {
+        QRDecoderWithInfo {
+            forced_format: Some((ec_level, mask)),
+            raw_data: false,
+            byte_policy: BytePolicy::default(),
+        }
+    }
+
+    /// Include the corrected data codewords in [`QRInfo::raw_data`], off by default
+    ///
+    /// This is meant for analysing payloads in non-standard or proprietary encodings. With raw data included, a code
+    /// whose data does not parse as any of the supported modes still decodes, to an empty string with the codewords in
+    /// the info, instead of failing. Codes whose errors cannot be corrected fail as usual.
+    ///
+    /// [`QRInfo::raw_data`]: ../../util/qr/struct.QRInfo.html#structfield.raw_data
+    pub fn with_raw_data(mut self, raw_data: bool) -> QRDecoderWithInfo {
+        self.raw_data = raw_data;
+        self
+    }
+
+    /// Decode the bytes of eight bit segments with this policy, see
+    /// [`QRDecoder::with_byte_policy`](struct.QRDecoder.html#method.with_byte_policy)
+    pub fn with_byte_policy(mut self, policy: BytePolicy) -> QRDecoderWithInfo {
+        self.byte_policy = policy;
+        self
     }
 }
 
@@ -64,43 +179,133 @@ impl Decode<QRData, (String, QRInfo), QRError> for QRDecoderWithInfo {
     fn decode(&self, data: Result<QRData, QRError>) -> Result<(String, QRInfo), QRError> {
         let qr_data = data?;
 
-        let format = super::format::format(&qr_data)?;
-        let blocks = super::blocks::blocks(&qr_data, &format.0, &format.1)?;
-        let block_info = super::block_info(qr_data.version, &format.0)?;
+        first_decoded(formats(&self.forced_format, &qr_data)?, |format| {
+            decode_format_with_info(&qr_data, format, self.raw_data, self.byte_policy)
+        })
+    }
+
+    // With raw data included, data that does not parse decodes to an empty string, so only decoding can tell
+    fn matches(&self, data: &Result<QRData, QRError>, expected: &str) -> Option<bool> {
+        match self.raw_data {
+            true => None,
+            false => Some(matches(&self.forced_format, data, self.byte_policy, expected)),
+        }
+    }
+}
 
-        let mut all_blocks = vec![];
-        let mut total_errors = 0;
+fn decode_format_with_info(
+    qr_data: &QRData,
+    format: (ECLevel, u8),
+    include_raw_data: bool,
+    policy: BytePolicy,
+) -> Result<(String, QRInfo), QRError> {
+    let blocks = super::blocks::blocks(qr_data, &format.0, &StandardMask::new(format.1)?)?;
+    let block_info = super::block_info(qr_data.version, &format.0)?;
+    let modules = super::blocks::codeword_modules(qr_data, &format.0)?;
 
-        for (block, bi) in blocks.into_iter().zip(block_info) {
-            let (corrected, error_count) = super::correct::correct_with_error_count(block, &bi)?;
+    let mut all_blocks = vec![];
+    let mut damaged_modules = vec![];
+
+    let corrected = super::correct::correct_blocks(blocks, block_info, super::correct::correct_with_error_locations)?;
+
+    for (((corrected, corrections), bi), modules) in corrected.into_iter().zip(block_info).zip(modules) {
+        for corr in corrected.iter().take(bi.data_per as usize) {
+            all_blocks.push(*corr);
+        }
+
+        damaged_modules.extend(super::flipped_modules(&corrections, &modules));
+    }
+
+    debug!("TOTAL LENGTH {len}", len = all_blocks.len());
+    let total_data = (all_blocks.len() as u32) * 8;
+
+    let ((data, structured_append), raw_data) = if include_raw_data {
+        // A payload the parser rejects is what the raw data is for
+        let data = super::data::data_with_append(all_blocks.clone(), qr_data.version, policy).unwrap_or_default();
+        (data, Some(all_blocks))
+    } else {
+        (super::data::data_with_append(all_blocks, qr_data.version, policy)?, None)
+    };
+
+    Ok((
+        data,
+        QRInfo {
+            version: qr_data.version,
+            ec_level: format.0,
+            mask: format.1,
+            total_data,
+            errors: damaged_modules.len() as u32,
+            damaged_modules,
+            print_growth: qr_data.print_growth,
+            raw_data,
+            physical_size: qr_data.physical_size(),
+            structured_append,
+        },
+    ))
+}
+
+// The formats to try in order: the forced one, or else the one read from the QR Code. With an expected error
+// correction level, also every mask pattern with that level, since damaged format information can still read as a
+// valid but wrong format
+pub(super) fn formats(forced_format: &Option<(ECLevel, u8)>, data: &QRData) -> Result<Vec<(ECLevel, u8)>, QRError> {
+    super::check_layout(data)?;
+
+    if let Some(format) = forced_format {
+        return Ok(vec![format.clone()]);
+    }
 
-            for corr in corrected.iter().take(bi.data_per as usize) {
-                all_blocks.push(*corr);
+    match (super::format::read_format(data), &data.expected_ec_level) {
+        (read, Some(expected)) => {
+            let mut formats: Vec<(ECLevel, u8)> = read.into_iter().collect();
+            let read_mask = formats.first().map(|format| format.1);
+
+            // The mask read is the most likely, when only the level bits are damaged
+            for mask in read_mask.into_iter().chain(0..8) {
+                if !formats.contains(&(expected.clone(), mask)) {
+                    formats.push((expected.clone(), mask));
+                }
             }
 
-            total_errors += error_count;
+            Ok(formats)
         }
+        (read, None) => Ok(vec![read?]),
+    }
+}
 
-        debug!("TOTAL LENGTH {len}", len = all_blocks.len());
-        let total_data = (all_blocks.len() as u32) * 8;
+// Decode with each format in turn, returning the first success or else the error of the first format
+pub(super) fn first_decoded<T>(
+    formats: Vec<(ECLevel, u8)>,
+    mut decode: impl FnMut((ECLevel, u8)) -> Result<T, QRError>,
+) -> Result<T, QRError> {
+    let mut first_error = None;
 
-        let data = super::data::data(all_blocks, qr_data.version)?;
-        Ok((
-            data,
-            QRInfo {
-                version: qr_data.version,
-                ec_level: format.0,
-                total_data,
-                errors: total_errors,
-            },
-        ))
+    for (i, format) in formats.into_iter().enumerate() {
+        if i > 0 {
+            metrics::count(|metrics| metrics.retries += 1);
+        }
+
+        match decode(format) {
+            Ok(decoded) => return Ok(decoded),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
     }
+
+    Err(first_error.expect("At least one format is tried"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::util::qr::ECLevel;
+
+    #[test]
+    fn test_invalid_data() {
+        assert!(QRDecoder::new().decode(Ok(QRData::new(vec![], 1))).is_err());
+        assert!(QRDecoder::new().decode(Ok(QRData::new(vec![0; 21 * 21], 50))).is_err());
+        assert!(QRDecoderWithInfo::new().decode(Ok(QRData::new(vec![0; 17 * 17], 0))).is_err());
+        assert!(QRDecoder::with_forced_format(ECLevel::LOW, 0).decode(Ok(QRData::new(vec![0; 20], 1))).is_err());
+    }
 
     #[test]
     fn test_qr_decoder_new() {
@@ -119,9 +324,7 @@ mod tests {
     #[test]
     fn test_decode_invalid_data_error() {
         let decoder = QRDecoder::new();
-        let error = QRError {
-            msg: "Test error".to_string(),
-        };
+        let error = QRError::new("Test error");
         let result = decoder.decode(Err(error.clone()));
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), error);
@@ -130,21 +333,152 @@ mod tests {
     #[test]
     fn test_decode_with_info_invalid_data_error() {
         let decoder = QRDecoderWithInfo::new();
-        let error = QRError {
-            msg: "Test error".to_string(),
-        };
+        let error = QRError::new("Test error");
         let result = decoder.decode(Err(error.clone()));
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), error);
     }
 
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_forced_format() {
+        use crate::encode::QREncoder;
+        use crate::util::fault;
+
+        let code = QREncoder::new().ec_level(ECLevel::QUARTILE).mask(5).encode("forced format").unwrap();
+        let (_, info) = QRDecoderWithInfo::new().decode(Ok(code.to_qr_data())).unwrap();
+        assert_eq!((info.ec_level, info.mask), (ECLevel::QUARTILE, 5));
+
+        // Cover both copies of the format information
+        let mut data = code.to_qr_data();
+        let side = data.side;
+        fault::erase_rect(&mut data, 0, 8, 9, 1, true);
+        fault::erase_rect(&mut data, 8, 0, 1, 9, true);
+        fault::erase_rect(&mut data, 8, side - 8, 1, 8, true);
+        fault::erase_rect(&mut data, side - 8, 8, 8, 1, true);
+
+        let damaged = || QRData {
+            data: data.data.clone(),
+            ..QRData::new(vec![], data.version)
+        };
+
+        assert!(QRDecoder::new().decode(Ok(damaged())).is_err());
+        assert!(QRDecoder::with_forced_format(ECLevel::QUARTILE, 4).decode(Ok(damaged())).is_err());
+        assert_eq!(
+            QRDecoder::with_forced_format(ECLevel::QUARTILE, 5).decode(Ok(damaged())),
+            Ok(String::from("forced format"))
+        );
+
+        let (content, info) = QRDecoderWithInfo::with_forced_format(ECLevel::QUARTILE, 5)
+            .decode(Ok(damaged()))
+            .unwrap();
+        assert_eq!(content, "forced format");
+        assert_eq!((info.ec_level, info.mask), (ECLevel::QUARTILE, 5));
+
+        assert!(QRDecoder::with_forced_format(ECLevel::QUARTILE, 8).decode(Ok(damaged())).is_err());
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_byte_policy() {
+        use crate::encode::{QREncoder, Segment};
+
+        let code = QREncoder::new().encode_segments(&[Segment::Bytes(vec![0x00, 0x9F, 0xFF])]).unwrap();
+
+        assert!(QRDecoder::new().with_byte_policy(BytePolicy::Error).decode(Ok(code.to_qr_data())).is_err());
+        let (content, _) =
+            QRDecoderWithInfo::new().with_byte_policy(BytePolicy::RawBytes).decode(Ok(code.to_qr_data())).unwrap();
+        assert_eq!(content.chars().map(|c| c as u8).collect::<Vec<u8>>(), vec![0x00, 0x9F, 0xFF]);
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_raw_data() {
+        use crate::encode::QREncoder;
+
+        let code = QREncoder::new().encode("raw").unwrap();
+        let (content, info) = QRDecoderWithInfo::new().decode(Ok(code.to_qr_data())).unwrap();
+        assert_eq!((content.as_str(), info.raw_data), ("raw", None));
+
+        // Byte mode, a length of 3, then "raw"
+        let decoder = QRDecoderWithInfo::new().with_raw_data(true);
+        let (content, info) = decoder.decode(Ok(code.to_qr_data())).unwrap();
+        let raw_data = info.raw_data.unwrap();
+        assert_eq!(content, "raw");
+        assert_eq!(raw_data.len(), 16);
+        assert_eq!(&raw_data[..4], &[0x40, 0x37, 0x26, 0x17]);
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_matches() {
+        use crate::encode::QREncoder;
+        use crate::util::fault;
+
+        let code = QREncoder::new().ec_level(ECLevel::QUARTILE).encode("LOT-1234").unwrap();
+        let mut data = code.to_qr_data();
+        fault::flip_modules(&mut data, 4, 7).unwrap();
+
+        for decoder in [QRDecoder::new(), QRDecoder::new().with_byte_policy(BytePolicy::RawBytes)] {
+            assert_eq!(decoder.matches(&Ok(data.clone()), "LOT-1234"), Some(true));
+            assert_eq!(decoder.matches(&Ok(data.clone()), "LOT-123"), Some(false));
+            assert_eq!(decoder.matches(&Ok(data.clone()), "LOT-12345"), Some(false));
+        }
+        assert_eq!(QRDecoderWithInfo::new().matches(&Ok(data.clone()), "LOT-1234"), Some(true));
+        assert_eq!(QRDecoderWithInfo::new().with_raw_data(true).matches(&Ok(data), "LOT-1234"), None);
+
+        // Codes that do not decode do not match
+        let error = QRError::new("Not extracted");
+        assert_eq!(QRDecoder::new().matches(&Err(error), "LOT-1234"), Some(false));
+        assert_eq!(QRDecoder::new().matches(&Ok(QRData::new(vec![0; 21 * 21], 1)), ""), Some(false));
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_expected_ec_level() {
+        use crate::encode::QREncoder;
+        use crate::util::fault;
+
+        let code = QREncoder::new().ec_level(ECLevel::HIGH).mask(6).encode("expected").unwrap();
+
+        let mut data = code.to_qr_data();
+        let side = data.side;
+        fault::erase_rect(&mut data, 0, 8, 9, 1, false);
+        fault::erase_rect(&mut data, 8, 0, 1, 9, false);
+        fault::erase_rect(&mut data, 8, side - 8, 1, 8, false);
+        fault::erase_rect(&mut data, side - 8, 8, 8, 1, false);
+
+        let damaged = |expected_ec_level: Option<ECLevel>| QRData {
+            data: data.data.clone(),
+            expected_ec_level,
+            ..QRData::new(vec![], data.version)
+        };
+
+        assert!(QRDecoder::new().decode(Ok(damaged(None))).is_err());
+        assert_eq!(QRDecoder::new().decode(Ok(damaged(Some(ECLevel::HIGH)))), Ok(String::from("expected")));
+
+        let (_, info) = QRDecoderWithInfo::new().decode(Ok(damaged(Some(ECLevel::HIGH)))).unwrap();
+        assert_eq!((info.ec_level, info.mask), (ECLevel::HIGH, 6));
+
+        // An intact format wins over a wrong expectation
+        let mut intact = code.to_qr_data();
+        intact.expected_ec_level = Some(ECLevel::LOW);
+        assert_eq!(QRDecoder::new().decode(Ok(intact)), Ok(String::from("expected")));
+    }
+
     #[test]
     fn test_qr_info_struct_fields() {
         let info = QRInfo {
             version: 7,
             ec_level: ECLevel::HIGH,
+            mask: 0,
             total_data: 1024,
             errors: 5,
+            damaged_modules: vec![],
+            print_growth: 0.0,
+            raw_data: None,
+            physical_size: None,
+            structured_append: None,
         };
         
         assert_eq!(info.version, 7);
@@ -158,15 +492,27 @@ mod tests {
         let info1 = QRInfo {
             version: 3,
             ec_level: ECLevel::MEDIUM,
+            mask: 0,
             total_data: 512,
             errors: 2,
+            damaged_modules: vec![],
+            print_growth: 0.0,
+            raw_data: None,
+            physical_size: None,
+            structured_append: None,
         };
         
         let info2 = QRInfo {
             version: 3,
             ec_level: ECLevel::MEDIUM,
+            mask: 0,
             total_data: 512,
             errors: 2,
+            damaged_modules: vec![],
+            print_growth: 0.0,
+            raw_data: None,
+            physical_size: None,
+            structured_append: None,
         };
         
         assert_eq!(info1, info2);
@@ -177,15 +523,27 @@ mod tests {
         let info1 = QRInfo {
             version: 3,
             ec_level: ECLevel::MEDIUM,
+            mask: 0,
             total_data: 512,
             errors: 2,
+            damaged_modules: vec![],
+            print_growth: 0.0,
+            raw_data: None,
+            physical_size: None,
+            structured_append: None,
         };
         
         let info2 = QRInfo {
             version: 4,
             ec_level: ECLevel::MEDIUM,
+            mask: 0,
             total_data: 512,
             errors: 2,
+            damaged_modules: vec![],
+            print_growth: 0.0,
+            raw_data: None,
+            physical_size: None,
+            structured_append: None,
         };
         
         assert_ne!(info1, info2);
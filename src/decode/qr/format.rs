@@ -1,40 +1,55 @@
-use super::galois::{EXP4, GF4, LOG4};
-use super::{ECLevel, QRMask};
+use std::convert::TryFrom;
 
+use super::mask::StandardMask;
+use super::ECLevel;
+
+use crate::util::bch::Bch;
 use crate::util::qr::{QRData, QRError};
+use crate::util::trace::{self, TraceEvent};
+
+pub fn format(data: &QRData) -> Result<(ECLevel, StandardMask), QRError> {
+    let (correction, pattern) = read_format(data)?;
 
-const MASK: [u8; 15] = [1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0];
+    Ok((correction, StandardMask::new(pattern)?))
+}
 
-pub fn format(data: &QRData) -> Result<(ECLevel, Box<QRMask>), QRError> {
+/// Error correction level and mask pattern from the format information, trying the second copy if the first is
+/// corrupted
+pub fn read_format(data: &QRData) -> Result<(ECLevel, u8), QRError> {
     let mut format = format1(data);
+    let mut copy = 1;
 
     if format.is_err() {
+        trace::record(|| TraceEvent::FormatRejected { copy: 1 });
+
         format = format2(data);
+        copy = 2;
     }
 
-    let format = format?;
+    let format = format.inspect_err(|_| trace::record(|| TraceEvent::FormatRejected { copy: 2 }))?;
+
+    let correction = ECLevel::try_from(format >> 3)?;
+    let pattern = format & 0b111;
 
-    let correction = error_correction(2 * format[0] + format[1])
-        .ok_or_else(|| QRError {
-            msg: format!("Invalid error correction level: {level:02b}", level = 2 * format[0] + format[1]),
-        })?;
-    let mask = mask(4 * format[2] + 2 * format[3] + format[4])
-        .ok_or_else(|| QRError {
-            msg: format!("Invalid mask pattern: {pattern:03b}", pattern = 4 * format[2] + 2 * format[3] + format[4]),
-        })?;
+    trace::record(|| TraceEvent::Format {
+        copy,
+        bits: Bch::FORMAT.with_mask(0).encode(u32::from(format)) as u16,
+        ec_level: correction.clone(),
+        mask: pattern,
+    });
 
-    Ok((correction, mask))
+    Ok((correction, pattern))
 }
 
-fn format1(data: &QRData) -> Result<Vec<u8>, QRError> {
-    let mut format1 = vec![];
+fn format1(data: &QRData) -> Result<u8, QRError> {
+    let mut format1 = 0;
 
     for x in 0..9 {
         if x == 6 {
             continue;
         }
 
-        format1.push(data[[x, 8]]);
+        format1 = format1 << 1 | u32::from(data[[x, 8]]);
     }
 
     for y in (0..8).rev() {
@@ -42,166 +57,69 @@ fn format1(data: &QRData) -> Result<Vec<u8>, QRError> {
             continue;
         }
 
-        format1.push(data[[8, y]]);
-    }
-
-    for i in 0..format1.len() {
-        format1[i] ^= MASK[i];
+        format1 = format1 << 1 | u32::from(data[[8, y]]);
     }
 
     correct(format1)
 }
 
-fn format2(data: &QRData) -> Result<Vec<u8>, QRError> {
-    let mut format2 = vec![];
+fn format2(data: &QRData) -> Result<u8, QRError> {
+    let mut format2 = 0;
 
     for y in (data.side - 7..data.side).rev() {
-        format2.push(data[[8, y]]);
+        format2 = format2 << 1 | u32::from(data[[8, y]]);
     }
 
     for x in data.side - 8..data.side {
-        format2.push(data[[x, 8]]);
-    }
-
-    for i in 0..format2.len() {
-        format2[i] ^= MASK[i];
+        format2 = format2 << 1 | u32::from(data[[x, 8]]);
     }
 
     correct(format2)
 }
 
-fn correct(mut format: Vec<u8>) -> Result<Vec<u8>, QRError> {
-    let mut s1 = GF4(0);
-
-    for i in 0..format.len() {
-        s1 = s1 + GF4(format[format.len() - i - 1]) * EXP4[i % 15];
-    }
-
-    if s1 == GF4(0) {
-        // syndrome == 0, no error detected
-        return Ok(format);
-    }
-
-    let s2 = s1 * s1;
-    let s4 = s2 * s2;
-
-    let mut s3 = GF4(0);
-    let mut s5 = GF4(0);
-
-    for i in 0..format.len() {
-        s3 = s3 + GF4(format[format.len() - i - 1]) * EXP4[(3 * i) % 15];
-        s5 = s5 + GF4(format[format.len() - i - 1]) * EXP4[(5 * i) % 15];
-    }
-
-    let sigma1 = s1;
-    let sigma2 = ((s5 + s4 * sigma1) - s2 * (s3 + s2 * sigma1)) / (s3 - s1 * s2);
-    let sigma3 = s3 + s2 * sigma1 + s1 * sigma2;
-
-    let mut error_pos = vec![];
-
-    for i in 0..16 {
-        let x = GF4(i);
-        if sigma3 + sigma2 * x + sigma1 * x * x + x * x * x == GF4(0) {
-            let log = LOG4[i as usize];
-            if log != 0 {
-                error_pos.push(log);
-            }
-        }
-    }
-
-    for error in error_pos {
-        let len = format.len();
-        format[len - error as usize - 1] ^= 1;
-    }
-
-    s1 = GF4(0);
-
-    for i in 0..format.len() {
-        s1 = s1 + GF4(format[format.len() - i - 1]) * EXP4[i % 15];
-    }
-
-    if s1 == GF4(0) {
-        // syndrome == 0, no error detected
-        return Ok(format);
-    }
-
-    Err(QRError {
-        msg: String::from("Format information corrupted"),
-    })
-}
-
-fn error_correction(bytes: u8) -> Option<ECLevel> {
-    match bytes {
-        0b01 => Some(ECLevel::LOW),
-        0b00 => Some(ECLevel::MEDIUM),
-        0b11 => Some(ECLevel::QUARTILE),
-        0b10 => Some(ECLevel::HIGH),
-        _ => None,
-    }
-}
-
-fn mask(bytes: u8) -> Option<Box<QRMask>> {
-    debug!("MASK {bytes:03b}");
-    match bytes {
-        0b000 => qrmask(Box::new(|j, i| (i + j) % 2 == 0)),
-        0b001 => qrmask(Box::new(|_, i| i % 2 == 0)),
-        0b010 => qrmask(Box::new(|j, _| j % 3 == 0)),
-        0b011 => qrmask(Box::new(|j, i| (i + j) % 3 == 0)),
-        0b100 => qrmask(Box::new(|j, i| (i / 2 + j / 3) % 2 == 0)),
-        0b101 => qrmask(Box::new(|j, i| (i * j) % 2 + (i * j) % 3 == 0)),
-        0b110 => qrmask(Box::new(|j, i| ((i * j) % 2 + (i * j) % 3) % 2 == 0)),
-        0b111 => qrmask(Box::new(|j, i| ((i * j) % 3 + (i + j) % 2) % 2 == 0)),
-        _ => None,
-    }
+// The 5 bits of format from the 15 bits of a copy of the format information, first bit read most significant
+fn correct(bits: u32) -> Result<u8, QRError> {
+    Bch::FORMAT
+        .decode(bits)
+        .map(|(format, _)| format as u8)
+        .ok_or_else(|| QRError::format("Format information corrupted"))
 }
 
-type Mask = dyn Fn(u32, u32) -> bool;
-
-fn qrmask(mask: Box<Mask>) -> Option<Box<QRMask>> {
-    Some(Box::new(move |q: &QRData, i: u32, j: u32| {
-        q[[i, j]] ^ (if mask(i, j) { 1 } else { 0 })
-    }))
+/// The version with the version information closest to the 18 bits of one of its blocks, and how many bits differ
+///
+/// `None` if more than 3 bits differ from every version, the most the code can correct.
+pub fn read_version(bits: u32) -> Option<(u32, u32)> {
+    Bch::VERSION.decode(bits).filter(|(version, _)| (7..=40).contains(version))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
-    const CORRECT: [u8; 15] = [0, 0, 0, 1, 1, 1, 1, 0, 1, 0, 1, 1, 0, 0, 1];
+    // Level L with mask 011, masked
+    const CORRECT: u32 = 0b000_1111_0101_1001 ^ 0x5412;
 
     #[test]
-    pub fn test_correct() {
-        let input = CORRECT.to_vec();
-        let input_to_check = input.clone();
+    pub fn test_read_version() {
+        assert_eq!(read_version(0x07C94), Some((7, 0)));
+        assert_eq!(read_version(0x28C69 ^ 0b100_0000_0100_0001), Some((40, 3)));
+        assert_eq!(read_version(0x1F250 ^ 0b1111), None);
+        assert_eq!(read_version(0), None);
+    }
 
-        let output = correct(input);
-        assert!(output.is_ok());
-        assert_eq!(input_to_check, output.expect("Format correction should succeed for correct input"));
+    #[test]
+    pub fn test_correct() {
+        assert_eq!(correct(CORRECT), Ok(0b00011));
     }
 
     #[test]
     pub fn test_fixable() {
-        let input_orig = CORRECT.to_vec();
-        let mut input_fixable = input_orig.clone();
-        input_fixable[4] ^= 1;
-        input_fixable[12] ^= 1;
-
-        let output = correct(input_fixable);
-        assert!(output.is_ok());
-        assert_eq!(input_orig, output.expect("Format correction should succeed for fixable errors"));
+        assert_eq!(correct(CORRECT ^ 1 << 10 ^ 1 << 2), Ok(0b00011));
     }
 
     #[test]
     pub fn test_corrupt() {
-        let input_orig = CORRECT.to_vec();
-        let mut input_corrupt = input_orig;
-        input_corrupt[4] ^= 1;
-        input_corrupt[5] ^= 1;
-        input_corrupt[6] ^= 1;
-        input_corrupt[12] ^= 1;
-        input_corrupt[13] ^= 1;
-
-        let output = correct(input_corrupt);
+        let output = correct(CORRECT ^ 1 << 10 ^ 1 << 9 ^ 1 << 8 ^ 1 << 2 ^ 1 << 1);
 
         println!("{:?}", output);
 
@@ -46,6 +46,10 @@ impl Div<GF8> for GF8 {
 
     #[allow(clippy::suspicious_arithmetic_impl)]
     fn div(self, other: GF8) -> GF8 {
+        if self.0 == 0 {
+            return EXP8[255];
+        }
+
         let log_self = LOG8[self.0 as usize];
         let log_other = LOG8[other.0 as usize];
         let mut diff = i16::from(log_self) - i16::from(log_other);
@@ -56,58 +60,6 @@ impl Div<GF8> for GF8 {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub struct GF4(pub u8);
-
-impl Add<GF4> for GF4 {
-    type Output = GF4;
-
-    #[allow(clippy::suspicious_arithmetic_impl)]
-    fn add(self, other: GF4) -> GF4 {
-        GF4(self.0 ^ other.0)
-    }
-}
-
-impl Sub<GF4> for GF4 {
-    type Output = GF4;
-
-    #[allow(clippy::suspicious_arithmetic_impl)]
-    fn sub(self, other: GF4) -> GF4 {
-        GF4(self.0 ^ other.0)
-    }
-}
-
-impl Mul<GF4> for GF4 {
-    type Output = GF4;
-
-    #[allow(clippy::suspicious_arithmetic_impl)]
-    fn mul(self, other: GF4) -> GF4 {
-        if self.0 == 0 || other.0 == 0 {
-            return EXP4[15];
-        }
-
-        let log_self = LOG4[self.0 as usize];
-        let log_other = LOG4[other.0 as usize];
-
-        EXP4[((u16::from(log_self) + u16::from(log_other)) % 15) as usize]
-    }
-}
-
-impl Div<GF4> for GF4 {
-    type Output = GF4;
-
-    #[allow(clippy::suspicious_arithmetic_impl)]
-    fn div(self, other: GF4) -> GF4 {
-        let log_self = LOG4[self.0 as usize];
-        let log_other = LOG4[other.0 as usize];
-        let mut diff = i16::from(log_self) - i16::from(log_other);
-
-        diff = if diff < 0 { diff + 15 } else { diff };
-
-        EXP4[diff as usize]
-    }
-}
-
 // exp and log tables with base 2 in Galois Field 2^8 under modulo 0b100011101
 // to generate:
 /*
@@ -405,29 +357,6 @@ pub const LOG8: [u8; 256] = [
     0x4F, 0xAE, 0xD5, 0xE9, 0xE6, 0xE7, 0xAD, 0xE8, 0x74, 0xD6, 0xF4, 0xEA, 0xA8, 0x50, 0x58, 0xAF,
 ];
 
-pub const EXP4: [GF4; 16] = [
-    GF4(0x01),
-    GF4(0x02),
-    GF4(0x04),
-    GF4(0x08),
-    GF4(0x03),
-    GF4(0x06),
-    GF4(0x0C),
-    GF4(0x0B),
-    GF4(0x05),
-    GF4(0x0A),
-    GF4(0x07),
-    GF4(0x0E),
-    GF4(0x0F),
-    GF4(0x0D),
-    GF4(0x09),
-    GF4(0x00),
-];
-
-pub const LOG4: [u8; 16] = [
-    0x00, 0x00, 0x01, 0x04, 0x02, 0x08, 0x05, 0x0A, 0x03, 0x0E, 0x09, 0x07, 0x06, 0x0D, 0x0B, 0x0C,
-];
-
 #[cfg(test)]
 mod test {
     use super::*;
@@ -499,67 +428,4 @@ mod test {
         assert_eq!(GF8(67) / GF8(193), GF8(40));
         assert_eq!(GF8(67) / GF8(40), GF8(193));
     }
-
-    #[test]
-    pub fn test_gf4_add() {
-        // zero
-        assert_eq!(GF4(0) + GF4(5), GF4(5));
-
-        // inverse sub
-        assert_eq!(GF4(3) + GF4(7), GF4(4));
-
-        // commutativity
-        assert_eq!(GF4(5) + GF4(9), GF4(9) + GF4(5));
-
-        // associativity
-        assert_eq!((GF4(3) + GF4(9)) + GF4(10), GF4(3) + (GF4(9) + GF4(10)));
-    }
-
-    #[test]
-    pub fn test_gf4_sub() {
-        // zero
-        assert_eq!(GF4(5) - GF4(5), GF4(0));
-
-        // inverse add
-        assert_eq!(GF4(4) - GF4(3), GF4(7));
-        assert_eq!(GF4(4) - GF4(7), GF4(3));
-    }
-
-    #[test]
-    pub fn test_gf4_mul() {
-        // zero
-        assert_eq!(GF4(4) * GF4(0), GF4(0));
-        assert_eq!(GF4(0) * GF4(4), GF4(0));
-
-        // unit
-        assert_eq!(GF4(4) * GF4(1), GF4(4));
-        assert_eq!(GF4(1) * GF4(4), GF4(4));
-
-        // inverse div
-        assert_eq!(GF4(7) * GF4(3), GF4(9));
-
-        // commutativity
-        assert_eq!(GF4(2) * GF4(9), GF4(9) * GF4(2));
-
-        // associativity
-        assert_eq!((GF4(2) * GF4(9)) * GF4(13), GF4(2) * (GF4(9) * GF4(13)));
-
-        // distributivity
-        assert_eq!(
-            GF4(2) * (GF4(5) + GF4(11)),
-            GF4(2) * GF4(5) + GF4(2) * GF4(11)
-        );
-    }
-
-    #[test]
-    pub fn test_gf4_div() {
-        // unit
-        assert_eq!(GF4(4) / GF4(4), GF4(1));
-        assert_eq!(GF4(4) / GF4(1), GF4(4));
-
-        // inverse mul
-        assert_eq!(GF4(9) / GF4(7), GF4(3));
-        assert_eq!(GF4(9) / GF4(3), GF4(7));
-    }
-
 }
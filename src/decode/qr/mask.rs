@@ -0,0 +1,143 @@
+use crate::util::qr::{QRData, QRError};
+
+/// A mask pattern, which flips modules of the data area so a code has no large areas of one color
+///
+/// The decoder undoes the mask of the format information with [`unmask`](#method.unmask) before reading the
+/// codewords. The standard patterns are [`StandardMask`]; implement this trait to read the codewords of a code that
+/// uses some other pattern, or none.
+///
+/// [`StandardMask`]: struct.StandardMask.html
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::QRMask;
+///
+/// // Leaves every module as it is
+/// struct NoMask;
+///
+/// impl QRMask for NoMask {
+///     fn is_masked(&self, _x: u32, _y: u32) -> bool {
+///         false
+///     }
+/// }
+///
+/// assert!(!NoMask.is_masked(3, 5));
+/// ```
+pub trait QRMask {
+    /// Whether the mask flips the module at column `x`, row `y`
+    fn is_masked(&self, x: u32, y: u32) -> bool;
+
+    /// Read the module at column `x`, row `y` with the mask undone
+    fn unmask(&self, data: &QRData, x: u32, y: u32) -> u8 {
+        data[[x, y]] ^ u8::from(self.is_masked(x, y))
+    }
+}
+
+/// One of the eight mask patterns of the QR Code specification, by its pattern reference
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::{QRMask, StandardMask};
+/// use bardecoder::util::qr::QRData;
+///
+/// // Pattern 0 flips every module where the row and column add up to an even number
+/// let mask = StandardMask::new(0).unwrap();
+/// assert!(mask.is_masked(0, 0) && !mask.is_masked(1, 0));
+///
+/// let light = QRData::new(vec![255; 21 * 21], 1);
+/// assert_eq!((mask.unmask(&light, 0, 0), mask.unmask(&light, 1, 0)), (1, 0));
+///
+/// assert!(StandardMask::new(8).is_err());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StandardMask {
+    pattern: u8,
+}
+
+impl StandardMask {
+    /// All eight patterns, in order of their pattern reference
+    pub const ALL: [StandardMask; 8] = [
+        StandardMask { pattern: 0 },
+        StandardMask { pattern: 1 },
+        StandardMask { pattern: 2 },
+        StandardMask { pattern: 3 },
+        StandardMask { pattern: 4 },
+        StandardMask { pattern: 5 },
+        StandardMask { pattern: 6 },
+        StandardMask { pattern: 7 },
+    ];
+
+    /// The pattern with this reference, as read from the format information
+    pub fn new(pattern: u8) -> Result<StandardMask, QRError> {
+        debug!("MASK {pattern:03b}");
+
+        match StandardMask::ALL.get(usize::from(pattern)) {
+            Some(mask) => Ok(*mask),
+            None => Err(QRError::format(format!("Invalid mask pattern: {pattern:03b}"))),
+        }
+    }
+
+    /// The pattern reference, 0 to 7
+    pub fn pattern(&self) -> u8 {
+        self.pattern
+    }
+}
+
+impl QRMask for StandardMask {
+    fn is_masked(&self, x: u32, y: u32) -> bool {
+        let (i, j) = (y, x);
+
+        match self.pattern {
+            0b000 => (i + j).is_multiple_of(2),
+            0b001 => i.is_multiple_of(2),
+            0b010 => j.is_multiple_of(3),
+            0b011 => (i + j).is_multiple_of(3),
+            0b100 => (i / 2 + j / 3).is_multiple_of(2),
+            0b101 => (i * j) % 2 + (i * j) % 3 == 0,
+            0b110 => ((i * j) % 2 + (i * j) % 3).is_multiple_of(2),
+            0b111 => ((i * j) % 3 + (i + j) % 2).is_multiple_of(2),
+            _ => unreachable!("StandardMask is only constructed with patterns 0 to 7"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns() {
+        // Flipped modules of the top left 6x6 modules of every pattern, as drawn in ISO/IEC 18004
+        let expected = [
+            ["X.X.X.", ".X.X.X", "X.X.X.", ".X.X.X", "X.X.X.", ".X.X.X"],
+            ["XXXXXX", "......", "XXXXXX", "......", "XXXXXX", "......"],
+            ["X..X..", "X..X..", "X..X..", "X..X..", "X..X..", "X..X.."],
+            ["X..X..", "..X..X", ".X..X.", "X..X..", "..X..X", ".X..X."],
+            ["XXX...", "XXX...", "...XXX", "...XXX", "XXX...", "XXX..."],
+            ["XXXXXX", "X.....", "X..X..", "X.X.X.", "X..X..", "X....."],
+            ["XXXXXX", "XXX...", "XX.XX.", "X.X.X.", "X.XX.X", "X...XX"],
+            ["X.X.X.", "...XXX", "X...XX", ".X.X.X", "XXX...", ".XXX.."],
+        ];
+
+        for (mask, rows) in StandardMask::ALL.iter().zip(&expected) {
+            for (y, row) in rows.iter().enumerate() {
+                let flipped: String = (0..6).map(|x| if mask.is_masked(x, y as u32) { 'X' } else { '.' }).collect();
+                assert_eq!(&flipped, row, "pattern {}, row {}", mask.pattern(), y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_new() {
+        for pattern in 0..8 {
+            assert_eq!(StandardMask::new(pattern).map(|mask| mask.pattern()), Ok(pattern));
+        }
+
+        assert_eq!(
+            StandardMask::new(0b1000),
+            Err(QRError::format("Invalid mask pattern: 1000"))
+        );
+    }
+}
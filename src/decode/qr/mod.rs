@@ -1,13 +1,92 @@
+use std::sync::OnceLock;
+
+use self::blocks::CodewordModules;
+use self::correct::Correction;
 use crate::util::qr::{ECLevel, QRData, QRError};
 
+pub mod append;
+pub mod assemble;
 pub mod blocks;
 pub mod correct;
 pub mod data;
 pub mod decoder;
 pub mod format;
 pub mod galois;
+pub mod mask;
+pub mod orientation;
+pub mod origin;
+pub mod stream;
+
+/// Build every table that is otherwise built the first time a version or level is decoded
+pub(crate) fn build_tables() {
+    for version in 1..=40 {
+        blocks::data_modules(version).expect("Every version has data modules");
+
+        #[cfg(feature = "encode")]
+        blocks::alignment_positions(version).expect("Every version has alignment positions");
+
+        for level in [ECLevel::LOW, ECLevel::MEDIUM, ECLevel::QUARTILE, ECLevel::HIGH].iter() {
+            block_info(version, level).expect("Every version has blocks for every level");
+        }
+    }
+}
+
+/// Check that the version, side and modules of the QR Code fit together, before indexing the modules
+///
+/// The fields of QRData are public, so a hand-built one can have a side that does not match its version, or fewer
+/// modules than its side needs. Every entry point that reads the modules checks this first.
+pub(crate) fn check_layout(data: &QRData) -> Result<(), QRError> {
+    if !(1..=40).contains(&data.version) || data.side != 4 * data.version + 17 {
+        return Err(QRError::invalid_input(format!(
+            "Invalid version {version} for a side of {side}",
+            version = data.version,
+            side = data.side
+        )));
+    }
+
+    let modules = (data.side * data.side) as usize;
+    if data.data.len() != modules {
+        return Err(QRError::invalid_input(format!(
+            "Expected {modules} modules but found {found}",
+            found = data.data.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Coordinates, in modules, of every bit that error correction flipped
+pub fn corrected_modules(data: &QRData) -> Result<Vec<(u32, u32)>, QRError> {
+    check_layout(data)?;
+
+    let (level, mask) = format::format(data)?;
+    let blocks = blocks::blocks(data, &level, &mask)?;
+    let modules = blocks::codeword_modules(data, &level)?;
+
+    let mut corrected = vec![];
+
+    for ((block, bi), modules) in blocks.into_iter().zip(block_info(data.version, &level)?).zip(modules) {
+        let (_, corrections) = correct::correct_with_error_locations(block, bi)?;
+        corrected.extend(flipped_modules(&corrections, &modules));
+    }
+
+    Ok(corrected)
+}
+
+/// Coordinates, in modules, of the bits flipped by the corrections of a block
+pub fn flipped_modules(corrections: &[Correction], modules: &[CodewordModules]) -> Vec<(u32, u32)> {
+    let mut flipped = vec![];
+
+    for (index, bits) in corrections {
+        for (bit, module) in modules[*index].iter().enumerate() {
+            if bits & (0x80 >> bit) != 0 {
+                flipped.push(*module);
+            }
+        }
+    }
 
-pub type QRMask = dyn Fn(&QRData, u32, u32) -> u8;
+    flipped
+}
 
 #[derive(Debug, Clone)]
 pub struct BlockInfo {
@@ -28,8 +107,37 @@ impl BlockInfo {
     }
 }
 
-pub fn block_info(version: u32, level: &ECLevel) -> Result<Vec<BlockInfo>, QRError> {
-    let block_info = match (version, level) {
+/// Every block of the version and level, in the order they are interleaved
+///
+/// Computed once per version and level, and shared by every decode after that.
+pub fn block_info(version: u32, level: &ECLevel) -> Result<&'static [BlockInfo], QRError> {
+    static BLOCKS: [[OnceLock<Vec<BlockInfo>>; 4]; 40] = [const { [const { OnceLock::new() }; 4] }; 40];
+
+    if !(1..=40).contains(&version) {
+        return Err(QRError::version(format!("Unknown combination of version {version} and level {level:?}")));
+    }
+
+    let index = match level {
+        ECLevel::LOW => 0,
+        ECLevel::MEDIUM => 1,
+        ECLevel::QUARTILE => 2,
+        ECLevel::HIGH => 3,
+    };
+
+    let blocks = BLOCKS[version as usize - 1][index].get_or_init(|| {
+        block_groups(version, level)
+            .expect("Every version has blocks for every level")
+            .iter()
+            .flat_map(|bi| std::iter::repeat_n(bi.clone(), usize::from(bi.block_count)))
+            .collect()
+    });
+
+    Ok(blocks)
+}
+
+/// The groups of blocks with the same size, as listed in the standard
+pub fn block_groups(version: u32, level: &ECLevel) -> Result<Vec<BlockInfo>, QRError> {
+    match (version, level) {
         // Version 1
         (1, ECLevel::LOW) => Ok(vec![BlockInfo::new(1, 26, 19, 2)]),
         (1, ECLevel::MEDIUM) => Ok(vec![BlockInfo::new(1, 26, 16, 4)]),
@@ -654,20 +762,36 @@ pub fn block_info(version: u32, level: &ECLevel) -> Result<Vec<BlockInfo>, QRErr
             BlockInfo::new(61, 46, 16, 15),
         ]),
 
-        (version, level) => Err(QRError {
-            msg: format!(
-                "Unknown combination of version {version} and level {level:?}"
-            ),
-        }),
-    }?;
+        (version, level) => Err(QRError::version(format!(
+            "Unknown combination of version {version} and level {level:?}"
+        ))),
+    }
+}
 
-    let mut bi_unwound = vec![];
+#[cfg(all(test, feature = "debug-images"))]
+mod test {
+    use super::*;
 
-    for bi in &block_info {
-        for _ in 0..bi.block_count {
-            bi_unwound.push(bi.clone());
-        }
-    }
+    use crate::detect::{Detect, LineScan, Location};
+    use crate::extract::{Extract, QRExtractor};
+    use crate::prepare::{BlockedMean, Prepare};
 
-    Ok(bi_unwound)
+    #[test]
+    fn test_corrected_modules() {
+        let source = image::open("tests/images/needs_alignment.jpg").unwrap();
+        let prepared = BlockedMean::new(5, 7).prepare(&source);
+        let Location::QR(loc) = LineScan::new().detect(&prepared).remove(0);
+        let mut data = QRExtractor::new().extract(&prepared, loc).unwrap();
+
+        // The image has three wrong bits, flipping one more adds it to the corrections
+        let corrected = corrected_modules(&data).unwrap();
+        assert_eq!(3, corrected.len());
+
+        let index = (25 * data.side + 20) as usize;
+        data.data[index] = 255 - data.data[index];
+
+        let mut flipped = corrected_modules(&data).unwrap();
+        flipped.retain(|m| !corrected.contains(m));
+        assert_eq!(vec![(20, 25)], flipped);
+    }
 }
@@ -0,0 +1,184 @@
+use super::super::Decode;
+use super::format;
+
+use crate::util::qr::{QRData, QRError};
+
+/// How a module matrix has to be turned to be the right way up, see [`decode_any_orientation`]
+///
+/// The matrix is mirrored left to right first, if at all, and then turned clockwise.
+///
+/// [`decode_any_orientation`]: fn.decode_any_orientation.html
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatrixOrientation {
+    /// Mirrored left to right, as when read through the back of a transparent label
+    pub mirrored: bool,
+    /// Quarter turns clockwise, from 0 to 3
+    pub quarter_turns: u8,
+}
+
+impl MatrixOrientation {
+    /// Every orientation, the right way up first and the mirrored ones last
+    pub const ALL: [MatrixOrientation; 8] = [
+        MatrixOrientation::new(false, 0),
+        MatrixOrientation::new(false, 1),
+        MatrixOrientation::new(false, 2),
+        MatrixOrientation::new(false, 3),
+        MatrixOrientation::new(true, 0),
+        MatrixOrientation::new(true, 1),
+        MatrixOrientation::new(true, 2),
+        MatrixOrientation::new(true, 3),
+    ];
+
+    const fn new(mirrored: bool, quarter_turns: u8) -> MatrixOrientation {
+        MatrixOrientation {
+            mirrored,
+            quarter_turns,
+        }
+    }
+
+    /// The matrix turned this way, with everything else about it kept
+    pub fn apply(&self, data: &QRData) -> QRData {
+        let last = data.side - 1;
+        let mut turned = data.clone();
+
+        for y in 0..data.side {
+            for x in 0..data.side {
+                // The module that ends up at (x, y), undoing the turns and then the mirroring
+                let (mut from_x, mut from_y) = (x, y);
+                for _ in 0..self.quarter_turns % 4 {
+                    (from_x, from_y) = (from_y, last - from_x);
+                }
+                if self.mirrored {
+                    from_x = last - from_x;
+                }
+
+                turned.data[(y * data.side + x) as usize] = data.data[(from_y * data.side + from_x) as usize];
+            }
+        }
+
+        turned
+    }
+}
+
+/// How to turn the matrix, such as `upright` or `mirrored, turned 90 degrees clockwise`
+impl std::fmt::Display for MatrixOrientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let degrees = u32::from(self.quarter_turns % 4) * 90;
+
+        match (self.mirrored, degrees) {
+            (false, 0) => write!(f, "upright"),
+            (false, degrees) => write!(f, "turned {degrees} degrees clockwise"),
+            (true, 0) => write!(f, "mirrored"),
+            (true, degrees) => write!(f, "mirrored, turned {degrees} degrees clockwise"),
+        }
+    }
+}
+
+/// Decode a module matrix that may be turned or mirrored, in the first orientation its format information reads in
+///
+/// Detectors that find a code without its finder patterns, from its outline for example, sample the modules without
+/// knowing which corner is the top left. Every [`MatrixOrientation`] is tried in turn, and the ones in which either copy
+/// of the format information is readable are decoded with the decoder, until one succeeds. The result comes with the
+/// orientation it decoded in.
+///
+/// # Errors
+///
+/// Returns the `QRError` of decoding the matrix as it is if no orientation decodes
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::{decode_any_orientation, QRDecoder};
+/// use bardecoder::util::qr::QRData;
+///
+/// let blank = QRData::new(vec![255; 21 * 21], 1);
+///
+/// assert!(decode_any_orientation(&QRDecoder::new(), &blank).is_err());
+/// ```
+///
+/// [`MatrixOrientation`]: struct.MatrixOrientation.html
+pub fn decode_any_orientation<RESULT>(
+    decoder: &impl Decode<QRData, RESULT, QRError>,
+    data: &QRData,
+) -> Result<(RESULT, MatrixOrientation), QRError> {
+    // The fields of QRData are public, leave matrices that are not side x side modules to the decoder to reject
+    if data.side == 0 || data.data.len() != (data.side * data.side) as usize {
+        return decoder.decode(Ok(data.clone())).map(|result| (result, MatrixOrientation::ALL[0]));
+    }
+
+    for orientation in MatrixOrientation::ALL.iter() {
+        let turned = orientation.apply(data);
+
+        if format::read_format(&turned).is_ok() {
+            if let Ok(result) = decoder.decode(Ok(turned)) {
+                return Ok((result, *orientation));
+            }
+        }
+    }
+
+    decoder.decode(Ok(data.clone())).map(|result| (result, MatrixOrientation::ALL[0]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        // The top left module is dark, and the one to the right of it
+        let mut data = QRData::new(vec![255; 21 * 21], 1);
+        data.data[0] = 0;
+        data.data[1] = 0;
+
+        let dark = |data: &QRData| -> Vec<(u32, u32)> {
+            (0..21 * 21).filter(|i| data.data[*i as usize] == 0).map(|i| (i % 21, i / 21)).collect()
+        };
+
+        assert_eq!(dark(&MatrixOrientation::new(false, 1).apply(&data)), vec![(20, 0), (20, 1)]);
+        assert_eq!(dark(&MatrixOrientation::new(false, 2).apply(&data)), vec![(19, 20), (20, 20)]);
+        assert_eq!(dark(&MatrixOrientation::new(true, 0).apply(&data)), vec![(19, 0), (20, 0)]);
+        assert_eq!(dark(&MatrixOrientation::new(true, 1).apply(&data)), vec![(20, 19), (20, 20)]);
+
+        // Four quarter turns are none at all, and so is mirroring twice
+        let mut turned = data.clone();
+        for _ in 0..4 {
+            turned = MatrixOrientation::new(false, 1).apply(&turned);
+        }
+        assert_eq!(turned.data, data.data);
+        let mirrored = MatrixOrientation::new(true, 0);
+        assert_eq!(mirrored.apply(&mirrored.apply(&data)).data, data.data);
+    }
+
+    #[test]
+    fn test_display() {
+        let shown: Vec<String> = MatrixOrientation::ALL.iter().step_by(3).map(MatrixOrientation::to_string).collect();
+        assert_eq!(shown, vec!["upright", "turned 270 degrees clockwise", "mirrored, turned 180 degrees clockwise"]);
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_decode_any_orientation() {
+        use super::super::decoder::QRDecoder;
+        use crate::encode::QREncoder;
+
+        let upright = QREncoder::new().version(2).encode("which way up").unwrap().to_qr_data();
+
+        for orientation in MatrixOrientation::ALL.iter() {
+            // Turned the opposite way, so this orientation turns it back. Mirroring and then turning is the same as
+            // turning the other way and then mirroring.
+            let inverse = match orientation.mirrored {
+                false => MatrixOrientation::new(false, (4 - orientation.quarter_turns) % 4),
+                true => *orientation,
+            };
+            let turned = inverse.apply(&upright);
+
+            let (text, found) = decode_any_orientation(&QRDecoder::new(), &turned).unwrap();
+            assert_eq!(text, "which way up");
+            assert_eq!(found, *orientation);
+        }
+
+        let blank = QRData::new(vec![255; 25 * 25], 2);
+        assert!(decode_any_orientation(&QRDecoder::new(), &blank).is_err());
+    }
+}
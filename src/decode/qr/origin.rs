@@ -0,0 +1,139 @@
+use std::ops::Range;
+
+use super::data::BytePolicy;
+use super::{block_info, blocks, correct, data, format};
+
+use crate::util::qr::{QRData, QRError};
+
+/// Where a piece of the decoded text of a QR Code is stored in the code, see [`text_origins`]
+///
+/// [`text_origins`]: fn.text_origins.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextOrigin {
+    /// Byte range of the piece in the decoded text
+    pub text: Range<usize>,
+
+    /// Range of bits in the data codewords of all blocks one after the other, after error correction, that encode it
+    pub bits: Range<usize>,
+
+    /// The codewords holding those bits, each as the index of its block in the order blocks are interleaved and the
+    /// index of the codeword in the block
+    pub codewords: Vec<(usize, usize)>,
+
+    /// Coordinates (x, y), in modules, of the modules holding those bits, (0, 0) being the top left module
+    pub modules: Vec<(u32, u32)>,
+}
+
+/// Decode the QR Code the same way as [`QRDecoder`], with where every piece of the text comes from in the code
+///
+/// This lets forensic tools show which area of a code produced a suspicious piece of text, for example text that was
+/// printed over the original code. Alongside the text there is a [`TextOrigin`] for every piece of it, in the order
+/// of the text. A piece is three digits, two alphanumeric characters or a single character of eight bit data; GS1
+/// data in alphanumeric mode and the prepended symbology identifier are a piece each. The bits of mode indicators and
+/// lengths are left out, except for those of FNC1, which the symbology identifier comes from.
+///
+/// # Errors
+///
+/// Returns `QRError` if the code can not be decoded
+///
+/// [`QRDecoder`]: struct.QRDecoder.html
+/// [`TextOrigin`]: struct.TextOrigin.html
+pub fn text_origins(data: &QRData) -> Result<(String, Vec<TextOrigin>), QRError> {
+    super::check_layout(data)?;
+
+    let (level, mask) = format::format(data)?;
+    let blocks = blocks::blocks(data, &level, &mask)?;
+    let modules = blocks::codeword_modules(data, &level)?;
+
+    let mut codewords = vec![];
+    let mut locations = vec![];
+
+    for (b, (block, bi)) in blocks.into_iter().zip(block_info(data.version, &level)?).enumerate() {
+        let corrected = correct::correct(block, bi)?;
+
+        for (i, codeword) in corrected.into_iter().take(usize::from(bi.data_per)).enumerate() {
+            codewords.push(codeword);
+            locations.push((b, i));
+        }
+    }
+
+    let (text, spans) = data::data_with_spans(codewords, data.version, BytePolicy::default())?;
+
+    let origins = spans
+        .into_iter()
+        .map(|(text, bits)| {
+            let codewords = match bits.is_empty() {
+                true => vec![],
+                false => locations[bits.start / 8..=(bits.end - 1) / 8].to_vec(),
+            };
+            let modules = bits
+                .clone()
+                .map(|bit| {
+                    let (block, codeword) = locations[bit / 8];
+                    modules[block][codeword][bit % 8]
+                })
+                .collect();
+
+            TextOrigin {
+                text,
+                bits,
+                codewords,
+                modules,
+            }
+        })
+        .collect();
+
+    Ok((text, origins))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_text_origins() {
+        use crate::encode::{QREncoder, Segment};
+        use crate::util::qr::ECLevel;
+
+        // Version 3 at QUARTILE has two blocks of 17 data codewords
+        let segments = [Segment::Numeric(String::from("01234567")), Segment::Bytes(b"abcdefghijklmnopqrst".to_vec())];
+        let code = QREncoder::new().version(3).ec_level(ECLevel::QUARTILE).encode_segments(&segments).unwrap();
+
+        let (text, origins) = text_origins(&code.to_qr_data()).unwrap();
+
+        assert_eq!(text, "01234567abcdefghijklmnopqrst");
+        assert_eq!(origins.len(), 3 + 20);
+        let pieces: Vec<(&str, Range<usize>)> = origins.iter().map(|o| (&text[o.text.clone()], o.bits.clone())).collect();
+        // After the mode indicator and 10 bits of length, 10 bits for every 3 digits and 7 for the last 2, then after
+        // another mode indicator and 8 bits of length, 8 bits for every byte
+        assert_eq!(&pieces[..4], &[("012", 14..24), ("345", 24..34), ("67", 34..41), ("a", 53..61)]);
+        assert_eq!(pieces[22], ("t", 205..213));
+
+        // The codewords of the second block follow those of the first
+        assert_eq!(origins[0].codewords, vec![(0, 1), (0, 2)]);
+        assert_eq!(origins[22].codewords, vec![(1, 8), (1, 9)]);
+
+        // The modules are those the codewords are read from
+        let modules = blocks::codeword_modules(&code.to_qr_data(), &ECLevel::QUARTILE).unwrap();
+        assert_eq!(origins[3].modules.len(), 8);
+        assert_eq!(origins[3].modules[0], modules[0][6][5]);
+        assert_eq!(origins[3].modules[7], modules[0][7][4]);
+    }
+
+    #[test]
+    fn test_text_origins_errors() {
+        assert!(text_origins(&QRData::new(vec![0; 21 * 21], 1)).is_err());
+
+        // A hand-built QRData whose fields do not fit together is an error, not a panic
+        let mut wrong_side = QRData::new(vec![0; 21 * 21], 1);
+        wrong_side.side = 25;
+        let error = text_origins(&wrong_side).unwrap_err();
+        assert_eq!(error.msg, "Invalid version 1 for a side of 25");
+
+        let mut short = QRData::new(vec![0; 21 * 21], 1);
+        short.data.truncate(100);
+        let error = text_origins(&short).unwrap_err();
+        assert_eq!(error.msg, "Expected 441 modules but found 100");
+    }
+}
@@ -0,0 +1,163 @@
+use std::io::Write;
+use std::sync::Mutex;
+
+use super::super::Decode;
+use super::decoder::QRDecoder;
+
+use crate::util::qr::{QRData, QRError};
+
+/// Decode QR Codes and write their messages to a sink, instead of returning them
+///
+/// Each code is decoded the same way as by [`QRDecoder`], and its message is written to the sink one segment at a
+/// time as the data is parsed, as UTF-8, so not even a single message is built in memory. With
+/// [`BytePolicy::RawBytes`] the bytes of eight bit segments reach the sink as they are. The result is the number of
+/// bytes written. Nothing is written for a code whose data does not parse, but a sink that fails part way leaves what
+/// it took of the message.
+///
+/// The messages are written in the order the codes are decoded. That is reading order for
+/// [`Decoder::decode`](../struct.Decoder.html#method.decode), unless a
+/// [`DecoderBuilder::time_budget`](../struct.DecoderBuilder.html#method.time_budget) is set, which decodes the codes
+/// most likely to decode first, and no particular order for `decode_parallel`. The parts of a structured append
+/// sequence are written in that order as well, not in the order of their headers: putting them in order would mean
+/// holding on to them. Decode the parts with `QRDecoderWithInfo` and join them with
+/// [`join_structured_append`](fn.join_structured_append.html) when they can come in any order. The sink is shared by
+/// every code, so writing from `decode_parallel` waits for the other codes.
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::QRStreamDecoder;
+/// use bardecoder::detect::LineScan;
+/// use bardecoder::extract::QRExtractor;
+/// use bardecoder::prepare::BlockedMean;
+/// use bardecoder::util::LumaImage;
+/// use bardecoder::DecoderBuilder;
+///
+/// let mut db: DecoderBuilder<LumaImage, LumaImage, usize> = DecoderBuilder::new();
+/// db.prepare(Box::new(BlockedMean::new(5, 7)));
+/// db.detect(Box::new(LineScan::new()));
+/// db.qr(Box::new(QRExtractor::new()), Box::new(QRStreamDecoder::new(std::io::stdout())));
+///
+/// let decoder = db.build().unwrap();
+/// ```
+///
+/// [`QRDecoder`]: struct.QRDecoder.html
+/// [`BytePolicy::RawBytes`]: enum.BytePolicy.html#variant.RawBytes
+pub struct QRStreamDecoder<W> {
+    decoder: QRDecoder,
+    sink: Mutex<W>,
+}
+
+impl<W: Write> QRStreamDecoder<W> {
+    /// Construct a new QRStreamDecoder, writing to the sink
+    pub fn new(sink: W) -> QRStreamDecoder<W> {
+        QRStreamDecoder::with_decoder(QRDecoder::new(), sink)
+    }
+
+    /// Construct a QRStreamDecoder that decodes with this decoder, for example one with a forced format
+    pub fn with_decoder(decoder: QRDecoder, sink: W) -> QRStreamDecoder<W> {
+        QRStreamDecoder {
+            decoder,
+            sink: Mutex::new(sink),
+        }
+    }
+
+    /// Stop decoding and return the sink
+    pub fn into_sink(self) -> W {
+        self.sink.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<W: Write + Send> Decode<QRData, usize, QRError> for QRStreamDecoder<W> {
+    fn decode(&self, data: Result<QRData, QRError>) -> Result<usize, QRError> {
+        // Locked at the first segment, so other codes do not wait for the error correction of this one
+        let mut sink = None;
+        let mut failed = None;
+
+        let written = self.decoder.write(data, |segment| {
+            // After a failed write nothing more is written, also not with another format
+            if let Some(error) = &failed {
+                return Err(QRError::clone(error));
+            }
+
+            // A panic while writing leaves the sink as it is, the next message is written after what got through
+            let sink = sink.get_or_insert_with(|| self.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+            sink.write_all(segment).map_err(|e| {
+                let error = QRError::new(format!("Unable to write decoded data: {e}"));
+                failed = Some(error.clone());
+                error
+            })
+        });
+
+        match failed {
+            Some(error) => Err(error),
+            None => written,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_stream_decoder() {
+        use crate::encode::QREncoder;
+
+        let stream = QRStreamDecoder::new(vec![]);
+
+        for part in &["first part, ", "second part"] {
+            let code = QREncoder::new().encode(part).unwrap();
+            assert_eq!(stream.decode(Ok(code.to_qr_data())), Ok(part.len()));
+        }
+
+        assert_eq!(stream.into_sink(), b"first part, second part".to_vec());
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_stream_decoder_raw_bytes() {
+        use crate::decode::BytePolicy;
+        use crate::encode::{QREncoder, Segment};
+
+        let segments = [Segment::Alphanumeric(String::from("AB")), Segment::Bytes(vec![0xC3, 0xA9, 0xFF])];
+        let data = QREncoder::new().encode_segments(&segments).unwrap().to_qr_data();
+
+        let stream = QRStreamDecoder::new(vec![]);
+        assert_eq!(stream.decode(Ok(data.clone())), Ok(8));
+        assert_eq!(stream.into_sink(), "AB\u{C3}\u{A9}\u{FF}".as_bytes().to_vec());
+
+        let raw = QRStreamDecoder::with_decoder(QRDecoder::new().with_byte_policy(BytePolicy::RawBytes), vec![]);
+        assert_eq!(raw.decode(Ok(data)), Ok(5));
+        assert_eq!(raw.into_sink(), b"AB\xC3\xA9\xFF".to_vec());
+    }
+
+    #[test]
+    fn test_stream_decoder_errors() {
+        struct Full {}
+
+        impl Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let error = QRError::extract("Unable to find alignment pattern");
+        let stream = QRStreamDecoder::new(Full {});
+        assert_eq!(stream.decode(Err(error.clone())), Err(error));
+
+        #[cfg(feature = "encode")]
+        {
+            let code = crate::encode::QREncoder::new().encode("lost").unwrap();
+            let error = stream.decode(Ok(code.to_qr_data())).unwrap_err();
+            assert_eq!(error.msg, "Unable to write decoded data: disk full");
+        }
+    }
+}
@@ -1,64 +1,1359 @@
-use image::DynamicImage;
-use image::GrayImage;
+#[cfg(feature = "image")]
+use image::codecs::gif::GifDecoder;
+#[cfg(feature = "image")]
+use image::codecs::png::PngDecoder;
+#[cfg(feature = "image")]
+use image::{AnimationDecoder, DynamicImage, Frames, GenericImageView, GrayImage, ImageFormat};
 
+#[cfg(feature = "debug-images")]
+use image::{Rgb, RgbImage};
 
-use crate::decode::{Decode, QRDecoder, QRDecoderWithInfo};
-use crate::detect::{Detect, LineScan, Location};
+#[cfg(feature = "image")]
+use std::io::Cursor;
+use std::cmp::Ordering;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::decode::{Decode, Fallback as DecodeFallback, QRDecoder, QRDecoderWithInfo};
+use crate::detect::{Detect, Fallback as DetectFallback, LineScan, Location};
 use crate::extract::{Extract, QRExtractor};
+#[cfg(feature = "image")]
+use crate::prepare::{HeifFormat, ImageLoader};
 use crate::prepare::{BlockedMean, Prepare};
 
-use crate::util::qr::{QRData, QRError, QRInfo, QRLocation};
+use crate::util::guidance::{self, ScanGuidance};
+use crate::util::limits::{self, ResourceLimits};
+use crate::util::metrics::{self, DecodeMetrics};
+use crate::util::observer::{PipelineObserver, Rejection};
+use crate::util::qr::{CodeError, DecodeHints, QRData, QRError, QRInfo, QRLocation, Stage};
+use crate::util::quality::{self, SymbolQuality, QUIET_ZONE};
+use crate::util::trace::{self, CodeTrace, DecodeTrace};
+use crate::util::{LumaImage, LumaSource};
+#[cfg(feature = "debug-images")]
+use crate::util::{draw, Point};
+
+#[cfg(feature = "tiff")]
+mod pages;
+mod rescaled;
+mod scanline;
+mod tiled;
+mod typed;
+
+#[cfg(feature = "tiff")]
+pub use self::pages::PageResult;
+pub use self::scanline::Scanlines;
+pub use self::typed::{ComponentMissing, ComponentProvided, TypedDecoderBuilder};
+
+/// Error type for `DecoderBuilder`
+#[derive(Debug, thiserror::Error)]
+pub enum BuilderError {
+    /// The prepare component is required but was not provided
+    #[error("Cannot build Decoder without Prepare component")]
+    MissingPrepare,
+    /// The detect component is required but was not provided
+    #[error("Cannot build Decoder without Detect component")]
+    MissingDetect,
+    /// The QR extract and decode components are required but were not provided
+    #[error("Cannot build Decoder without QR extract and decode components")]
+    MissingQR,
+}
+
+/// Struct to hold logic to do the entire decoding
+///
+/// A `Decoder` is `Send` and `Sync`, so a single one can be shared between threads, for example behind an `Arc`.
+/// Cloning one is cheap as well: the components are shared between the clones, only the limits are copied.
+pub struct Decoder<IMG, PREPD, RESULT> {
+    prepare: Arc<dyn Prepare<IMG, PREPD> + Send + Sync>,
+    // The detect implementation of the builder, followed by its fallbacks
+    detect: Arc<DetectFallback<PREPD>>,
+    qr: ExtractDecode<PREPD, QRLocation, QRData, RESULT, QRError>,
+    min_module_size: f64,
+    max_version: u32,
+    #[cfg(feature = "image")]
+    max_pixels: u64,
+    max_codes: usize,
+    limits: ResourceLimits,
+    time_budget: Option<Duration>,
+    min_dynamic_range: u8,
+    observer: Option<Arc<dyn PipelineObserver<PREPD, RESULT> + Send + Sync>>,
+    #[cfg(feature = "image")]
+    loaders: Arc<[Box<dyn ImageLoader + Send + Sync>]>,
+    #[cfg(feature = "parallel")]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl<IMG, PREPD, RESULT> Clone for Decoder<IMG, PREPD, RESULT> {
+    fn clone(&self) -> Self {
+        Decoder {
+            prepare: Arc::clone(&self.prepare),
+            detect: Arc::clone(&self.detect),
+            qr: self.qr.clone(),
+            min_module_size: self.min_module_size,
+            max_version: self.max_version,
+            #[cfg(feature = "image")]
+            max_pixels: self.max_pixels,
+            max_codes: self.max_codes,
+            limits: self.limits,
+            time_budget: self.time_budget,
+            min_dynamic_range: self.min_dynamic_range,
+            observer: self.observer.clone(),
+            #[cfg(feature = "image")]
+            loaders: Arc::clone(&self.loaders),
+            #[cfg(feature = "parallel")]
+            thread_pool: self.thread_pool.clone(),
+        }
+    }
+}
+
+impl<IMG, PREPD, RESULT> Decoder<IMG, PREPD, RESULT> {
+    /// Do the actual decoding
+    ///
+    /// Logic is run in the following order:
+    /// * prepare
+    /// * detect
+    /// * per detected code the associated extract and decode functions
+    ///
+    /// Results are in reading order of the codes in the image: top to bottom, and left to right for codes next to each
+    /// other, by the centers of the codes. The same holds for all other methods returning results for every code. The
+    /// order only depends on the locations of the codes, not on the order the detector found them in, so the results
+    /// are the same on every run and every thread.
+    pub fn decode(&self, source: &IMG) -> Vec<Result<RESULT, QRError>> {
+        if let Err(e) = self.check_dynamic_range(source) {
+            return vec![Err(e)];
+        }
+
+        let prepared = self.prepare_observed(source);
+        self.decode_prepared(&prepared)
+    }
+
+    /// Decode an image prepared earlier with [`prepare_only`](#method.prepare_only), skipping the prepare step
+    ///
+    /// Preparing is the expensive part for many images, so an application that runs several decoders over the same
+    /// frame, for example a strict one and a lenient one, can prepare it once and share the result between them. The
+    /// prepared image can come from any decoder with the same prepared type, including one for a different type of
+    /// source. The results are the same as those of [`decode`](#method.decode) with this decoder's own prepare step,
+    /// if it prepares the same way.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    /// // For borrowed pixels, but preparing to the same type
+    /// let with_info = bardecoder::default_luma_decoder_with_info::<&[u8]>();
+    ///
+    /// let prepared = decoder.prepare_only(&source);
+    ///
+    /// assert!(decoder.decode_prepared(&prepared).is_empty());
+    /// assert!(with_info.decode_prepared(&prepared).is_empty());
+    /// ```
+    pub fn decode_prepared(&self, prepared: &PREPD) -> Vec<Result<RESULT, QRError>> {
+        self.decode_prepared_located(prepared)
+            .into_iter()
+            .map(|decoded| decoded.map_err(|e| e.error))
+            .collect()
+    }
+
+    /// Decode the same way as [`decode`](#method.decode), with the location of the code and the failed step in every
+    /// error
+    ///
+    /// The location is the one the code was extracted from. The errors for detection reaching one of the
+    /// [`DecoderBuilder::limits`] and for codes dropped because of [`DecoderBuilder::max_codes`] are not about a single
+    /// code, they come last without a location.
+    ///
+    /// [`DecoderBuilder::limits`]: struct.DecoderBuilder.html#method.limits
+    /// [`DecoderBuilder::max_codes`]: struct.DecoderBuilder.html#method.max_codes
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// for result in decoder.decode_with_error_locations(&source) {
+    ///     if let Err(e) = result {
+    ///         if let Some(location) = &e.location {
+    ///             println!("The code at {:?} is unreadable: {}", location.center(), e.error);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn decode_with_error_locations(&self, source: &IMG) -> Vec<Result<RESULT, CodeError>> {
+        if let Err(error) = self.check_dynamic_range(source) {
+            return vec![Err(CodeError {
+                error,
+                location: None,
+                stage: Stage::Prepare,
+            })];
+        }
+
+        let prepared = self.prepare_observed(source);
+        self.decode_prepared_located(&prepared)
+    }
+
+    fn decode_prepared_located(&self, prepared: &PREPD) -> Vec<Result<RESULT, CodeError>> {
+        let (locations, exceeded) = self.locations(prepared);
+
+        let mut all_decoded = self.scheduled(
+            locations,
+            |qrloc| {
+                let extracted = self.extract_observed(prepared, qrloc.clone());
+                let stage = if extracted.is_err() { Stage::Extract } else { Stage::Decode };
+                self.decode_observed(extracted).map_err(|error| CodeError {
+                    error,
+                    location: Some(qrloc),
+                    stage,
+                })
+            },
+            |qrloc, error| {
+                Err(CodeError {
+                    error,
+                    location: Some(qrloc),
+                    stage: Stage::Extract,
+                })
+            },
+        );
+
+        all_decoded.extend(exceeded.into_iter().map(|error| {
+            Err(CodeError {
+                error,
+                location: None,
+                stage: Stage::Detect,
+            })
+        }));
+        all_decoded
+    }
+
+    /// Decode a single QR Code at a known location, skipping detection
+    ///
+    /// Detection is the most expensive step, so this makes decoding a code again nearly free when its location is
+    /// already known, for example from the previous frame of a video (see [`QRLocation::corners`]) or from another
+    /// computer vision pipeline. The image is prepared as usual.
+    ///
+    /// [`QRLocation::corners`]: util/qr/struct.QRLocation.html#method.corners
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::qr::QRLocation;
+    /// use bardecoder::util::{LumaImage, Point};
+    ///
+    /// let source = LumaImage::from_fn(80, 80, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// let location = QRLocation {
+    ///     top_left: Point { x: 15.0, y: 15.0 },
+    ///     top_right: Point { x: 65.0, y: 15.0 },
+    ///     bottom_left: Point { x: 15.0, y: 65.0 },
+    ///     module_size: 3.57,
+    ///     version: 1,
+    /// };
+    ///
+    /// // There is no QR Code in this image
+    /// assert!(decoder.decode_at(&source, location).is_err());
+    /// ```
+    pub fn decode_at(&self, source: &IMG, location: QRLocation) -> Result<RESULT, QRError> {
+        self.check_dynamic_range(source)?;
+
+        let prepared = self.prepare_observed(source);
+        let extracted = self.extract_observed(&prepared, location);
+
+        self.decode_observed(extracted)
+    }
+
+    // Decode the codes in a frame of a video, with where they are
+    //
+    // Codes move little from one frame to the next, so the locations they had in the previous frame are tried first,
+    // the way `decode_at` does, which skips detection. Only when one of them does not decode there anymore is the
+    // frame detected as a whole, the same as without any locations. Whether it was is returned with the results.
+    #[cfg(feature = "video")]
+    pub(crate) fn decode_tracked(
+        &self,
+        source: &IMG,
+        tracked: &[QRLocation],
+    ) -> (Vec<Tracked<RESULT>>, bool) {
+        if let Err(e) = self.check_dynamic_range(source) {
+            return (vec![Err(e)], false);
+        }
+
+        let prepared = self.prepare_observed(source);
+        let decode = |qrloc: QRLocation| {
+            let extracted = self.extract_observed(&prepared, qrloc.clone());
+            self.decode_observed(extracted).map(|decoded| (qrloc, decoded))
+        };
+
+        if !tracked.is_empty() {
+            let decoded: Vec<_> = tracked.iter().cloned().map(decode).collect();
+            if decoded.iter().all(Result::is_ok) {
+                return (decoded, false);
+            }
+        }
+
+        let (locations, exceeded) = self.locations(&prepared);
+
+        let mut all_decoded = self.scheduled(locations, decode, |_, error| Err(error));
+        all_decoded.extend(exceeded.into_iter().map(Err));
+        (all_decoded, true)
+    }
+
+    /// Detect the QR Codes in the image without extracting or decoding them, with where they are
+    ///
+    /// This is for applications that only need to know whether there are codes, or where: checking the layout of a
+    /// printed page, or drawing the outline of every code on every frame of a camera preview (see
+    /// [`QRLocation::corners`]) and decoding only the one the user taps with [`decode_at`](#method.decode_at). The
+    /// extract and decode steps are skipped for every code.
+    ///
+    /// The locations are in reading order, without the codes ruled out by the limits set on the builder, the same as
+    /// those [`decode`](#method.decode) would extract. Anything that looks like three finder patterns counts, so codes
+    /// too damaged to decode are found as well, and so are the occasional false positives of the detector; see
+    /// [`detect_with_format`](#method.detect_with_format) for fewer.
+    ///
+    /// [`QRLocation::corners`]: util/qr/struct.QRLocation.html#method.corners
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let frame = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// for location in decoder.detect_only(&frame) {
+    ///     println!("Code at {:?}", location.corners());
+    ///
+    ///     // When the user taps it
+    ///     let _decoded = decoder.decode_at(&frame, location);
+    /// }
+    /// ```
+    pub fn detect_only(&self, source: &IMG) -> Vec<QRLocation> {
+        if self.check_dynamic_range(source).is_err() {
+            return vec![];
+        }
+
+        let prepared = self.prepare_observed(source);
+        let (locations, _) = self.locations(&prepared);
+
+        locations
+            .into_iter()
+            .map(|location| match location {
+                Location::QR(qrloc) => qrloc,
+            })
+            .collect()
+    }
+
+    /// The locations of [`detect_only`](#method.detect_only), only those whose format information reads
+    ///
+    /// Every code is extracted to read its format information, which is protected by an error correcting code of its
+    /// own and rarely reads in places without a QR Code. The error correction and the decoding of the data are still
+    /// skipped, so codes whose data is too damaged to decode are found as well.
+    pub fn detect_with_format(&self, source: &IMG) -> Vec<QRLocation> {
+        if self.check_dynamic_range(source).is_err() {
+            return vec![];
+        }
+
+        let prepared = self.prepare_observed(source);
+        let (locations, _) = self.locations(&prepared);
+
+        let mut located = vec![];
+        for location in locations {
+            match location {
+                Location::QR(qrloc) => {
+                    let extracted = self.extract_observed(&prepared, qrloc.clone());
+                    if extracted.is_ok_and(|data| crate::decode::qr::format::read_format(&data).is_ok()) {
+                        located.push(qrloc);
+                    }
+                }
+            }
+        }
+
+        located
+    }
+
+    /// The number of QR Codes [`detect_only`](#method.detect_only) finds in the image
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// assert_eq!(decoder.count(&source), 0);
+    /// ```
+    pub fn count(&self, source: &IMG) -> usize {
+        self.detect_only(source).len()
+    }
+
+    /// Decode the same way as [`decode`](#method.decode), using what is known about the QR Codes in advance
+    ///
+    /// With an expected version, every detected code is extracted with the grid of that version, instead of the
+    /// version estimated from the distance between its finder patterns. With an expected error correction level, the
+    /// default decoders use it when the format information is unreadable or has another level, trying every mask
+    /// pattern. This helps badly damaged labels, where the areas with this information are often the first to go.
+    ///
+    /// The source of the image is passed on to the prepare and extract components, see [`Source`].
+    ///
+    /// [`Source`]: util/qr/enum.Source.html
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::qr::DecodeHints;
+    /// use bardecoder::util::LumaImage;
+    /// use bardecoder::ECLevel;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// let mut hints = DecodeHints::new();
+    /// hints.expected_version(2).expected_ec_level(ECLevel::HIGH);
+    ///
+    /// assert!(decoder.decode_with_hints(&source, &hints).is_empty());
+    /// ```
+    pub fn decode_with_hints(&self, source: &IMG, hints: &DecodeHints) -> Vec<Result<RESULT, QRError>> {
+        if let Err(e) = self.check_dynamic_range(source) {
+            return vec![Err(e)];
+        }
+
+        let prepared = self.observe_prepared(self.prepare.prepare_with_hints(source, hints));
+        let (locations, exceeded) = self.locations(&prepared);
+
+        let mut all_decoded = self.scheduled(
+            locations,
+            |mut qrloc| {
+                if let Some(version) = hints.version {
+                    qrloc.version = version;
+                }
+
+                let extracted = self.extract_limited(|| self.qr.extract.extract_with_hints(&prepared, qrloc, hints));
+                let extracted = self.observe_extracted(extracted).map(|mut data| {
+                    data.expected_ec_level = hints.ec_level.clone();
+                    data.dpi = hints.dpi;
+                    data
+                });
+
+                self.decode_observed(extracted)
+            },
+            |_, error| Err(error),
+        );
+
+        all_decoded.extend(exceeded.into_iter().map(Err));
+        all_decoded
+    }
+
+    /// Decode the same way as [`decode`](#method.decode), also returning a trace of what happened along the way
+    ///
+    /// The trace has the time spent in each step and, for the default components, the finder patterns that were
+    /// considered or rejected, the format information read, the block layout and the number of corrected errors per
+    /// block. Collecting it is slower than a normal decode, so this is meant for finding out why a code does not decode.
+    ///
+    /// Timing uses `std::time::Instant`, which is not available on `wasm32-unknown-unknown`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// let (results, trace) = decoder.decode_with_trace(&source);
+    ///
+    /// assert!(results.is_empty());
+    /// assert!(trace.codes.is_empty());
+    /// ```
+    pub fn decode_with_trace(&self, source: &IMG) -> (Vec<Result<RESULT, QRError>>, DecodeTrace) {
+        let mut trace = DecodeTrace::default();
+
+        if let Err(e) = self.check_dynamic_range(source) {
+            return (vec![Err(e)], trace);
+        }
+
+        let start = Instant::now();
+        let prepared = self.prepare_observed(source);
+        trace.prepare_time = start.elapsed();
+
+        let start = Instant::now();
+        let ((locations, exceeded), events) = trace::collect(|| self.locations(&prepared));
+        trace.detect_time = start.elapsed();
+        trace.detect_events = events;
+
+        let mut all_decoded = vec![];
+
+        for location in locations {
+            match location {
+                Location::QR(qrloc) => {
+                    let version = qrloc.version;
+                    let corners = qrloc.corners();
+                    let mut times = (Duration::default(), Duration::default());
+
+                    let (decoded, events) = trace::collect(|| {
+                        let start = Instant::now();
+                        let extracted = self.extract_observed(&prepared, qrloc);
+                        times.0 = start.elapsed();
+
+                        let start = Instant::now();
+                        let decoded = self.decode_observed(extracted);
+                        times.1 = start.elapsed();
+
+                        decoded
+                    });
+
+                    trace.codes.push(CodeTrace {
+                        version,
+                        corners,
+                        extract_time: times.0,
+                        decode_time: times.1,
+                        events,
+                        error: decoded.as_ref().err().cloned(),
+                    });
+
+                    all_decoded.push(decoded);
+                }
+            }
+        }
+
+        all_decoded.extend(exceeded.into_iter().map(Err));
+        (all_decoded, trace)
+    }
+
+    /// Decode the same way as [`decode`](#method.decode), also counting the work done in each step
+    ///
+    /// The default components count the pixels they read per step, the finder pattern candidates they examine, the
+    /// iterations of Reed-Solomon error correction and the retries after a failure, see [`DecodeMetrics`]. The counts
+    /// do not depend on the machine, so they are a steadier measure of a change to the pipeline than a benchmark.
+    /// Custom components count nothing, unless they are made up of default ones.
+    ///
+    /// [`DecodeMetrics`]: util/metrics/struct.DecodeMetrics.html
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// let (results, metrics) = decoder.decode_with_metrics(&source);
+    ///
+    /// assert!(results.is_empty());
+    /// assert_eq!(metrics.finder_candidates, 0);
+    /// assert!(metrics.detect_pixels >= 40 * 40);
+    /// ```
+    pub fn decode_with_metrics(&self, source: &IMG) -> (Vec<Result<RESULT, QRError>>, DecodeMetrics) {
+        metrics::collect(|| self.decode(source))
+    }
+
+    /// Only run the prepare step, returning the image the other steps work on
+    ///
+    /// For the default decoder this is the thresholded black and white image, which shows whether the binarization
+    /// works for a source: codes that look noisy or broken up here will not decode.
+    ///
+    /// The prepared image can be decoded with [`decode_prepared`](#method.decode_prepared), by this decoder or others.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |x, y| ((x * 7 + y * 3) % 256) as u8);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// let prepared = decoder.prepare_only(&source);
+    ///
+    /// assert!(prepared.into_raw().iter().all(|p| *p == 0 || *p == 255));
+    /// ```
+    pub fn prepare_only(&self, source: &IMG) -> PREPD {
+        self.prepare_observed(source)
+    }
+
+    /// Build the tables and start the threads that would otherwise be set up during the first decodes
+    ///
+    /// The module order and the layout of the blocks of every version and level are built the first time a code of
+    /// that version and level is decoded, and the instruction set for scanning rows is detected on the first scan. With
+    /// the `parallel` feature, the global pool of rayon starts its threads on first use as well. A service that has to
+    /// answer its first requests as fast as the ones after them can call this at startup instead. Everything is built
+    /// once for the whole process, calling it again does nothing.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let decoder = bardecoder::default_luma_decoder();
+    /// decoder.warm_up();
+    ///
+    /// assert!(decoder.decode(&LumaImage::from_fn(40, 40, |_, _| 255)).is_empty());
+    /// ```
+    pub fn warm_up(&self) {
+        crate::decode::qr::build_tables();
+        crate::util::simd::run_length(&[255], 0);
+
+        #[cfg(feature = "parallel")]
+        if self.thread_pool.is_none() {
+            rayon::broadcast(|_| ());
+        }
+    }
+
+    // With a minimum set, a source whose prepare step measures less of a dynamic range fails as a whole
+    fn check_dynamic_range(&self, source: &IMG) -> Result<(), QRError> {
+        if self.min_dynamic_range == 0 {
+            return Ok(());
+        }
+
+        match self.prepare.dynamic_range(source) {
+            Some(range) if range < self.min_dynamic_range => Err(QRError::no_contrast(format!(
+                "Not enough contrast: a dynamic range of {range}, less than the minimum of {}",
+                self.min_dynamic_range
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // Run the step, letting the observer know about its outcome
+    fn prepare_observed(&self, source: &IMG) -> PREPD {
+        self.observe_prepared(self.prepare.prepare(source))
+    }
+
+    fn observe_prepared(&self, prepared: PREPD) -> PREPD {
+        if let Some(observer) = &self.observer {
+            observer.on_prepared(&prepared);
+        }
+
+        prepared
+    }
+
+    fn extract_observed(&self, prepared: &PREPD, qrloc: QRLocation) -> Result<QRData, QRError> {
+        self.observe_extracted(self.extract_limited(|| self.qr.extract.extract(prepared, qrloc)))
+    }
+
+    // Extract with limits of its own, so a single code can not use them up for the others
+    fn extract_limited(&self, extract: impl FnOnce() -> Result<QRData, QRError>) -> Result<QRData, QRError> {
+        match limits::within(self.limits, extract) {
+            (_, Some(limit)) => Err(limit),
+            (extracted, None) => extracted,
+        }
+    }
+
+    fn observe_extracted(&self, extracted: Result<QRData, QRError>) -> Result<QRData, QRError> {
+        match (&self.observer, &extracted) {
+            (Some(observer), Ok(data)) => observer.on_extracted(data),
+            (Some(observer), Err(error)) => observer.on_error(error),
+            (None, _) => {}
+        }
+
+        extracted
+    }
+
+    fn decode_observed(&self, extracted: Result<QRData, QRError>) -> Result<RESULT, QRError> {
+        // An error from extracting is passed through, the observer was told about it already
+        let extract_failed = extracted.is_err();
+        let (decoded, exceeded) = limits::within(self.limits, || self.qr.decode.decode(extracted));
+        // Out of iterations, error correction fails in every decoder, the limit is the actual reason
+        let decoded = match exceeded {
+            Some(limit) if decoded.is_err() => Err(limit),
+            _ => decoded,
+        };
+
+        match (&self.observer, &decoded) {
+            (Some(observer), Ok(result)) => observer.on_decoded(result),
+            (Some(observer), Err(error)) if !extract_failed => observer.on_error(error),
+            _ => {}
+        }
+
+        decoded
+    }
+
+    // Run every location in reading order, or with a time budget the most likely to decode first, until the budget is
+    // used up. The locations there was no time left for get the error of the budget instead. The results are in the
+    // order of the locations either way.
+    fn scheduled<T>(
+        &self,
+        locations: Vec<Location>,
+        mut run: impl FnMut(QRLocation) -> T,
+        over_budget: impl Fn(QRLocation, QRError) -> T,
+    ) -> Vec<T> {
+        let Some(budget) = self.time_budget else {
+            return locations.into_iter().map(|Location::QR(qrloc)| run(qrloc)).collect();
+        };
+
+        let start = Instant::now();
+        let order = decode_order(&locations);
+        let mut pending: Vec<Option<QRLocation>> = locations.into_iter().map(|Location::QR(qrloc)| Some(qrloc)).collect();
+        let mut results: Vec<Option<T>> = pending.iter().map(|_| None).collect();
+
+        for index in order {
+            let qrloc = pending[index].take().expect("Every location is run once");
+
+            results[index] = Some(match start.elapsed() < budget {
+                true => run(qrloc),
+                false => over_budget(qrloc, QRError::resource_limit(&format!("time budget of {budget:?} used up"))),
+            });
+        }
+
+        results.into_iter().map(|result| result.expect("Every location is run")).collect()
+    }
+
+    // Detect the codes, without those ruled out by the limits set on the builder
+    //
+    // The errors are for the limits that were reached: detection stopping early, or codes dropped because there were
+    // more than `max_codes` or `max_extractions`
+    fn locations(&self, prepared: &PREPD) -> (Vec<Location>, Vec<QRError>) {
+        let (mut locations, exceeded) = limits::within(self.limits, || self.detect.detect(prepared));
+        let mut errors: Vec<QRError> = exceeded.into_iter().collect();
+
+        locations.retain(|location| match location {
+            Location::QR(qrloc) => {
+                let rejection = if qrloc.module_size < self.min_module_size {
+                    Some(Rejection::ModuleSize)
+                } else if qrloc.version > self.max_version {
+                    Some(Rejection::Version)
+                } else {
+                    None
+                };
+
+                if let Some(observer) = &self.observer {
+                    observer.on_candidate(qrloc);
+                    if let Some(rejection) = rejection {
+                        observer.on_rejected(qrloc, rejection);
+                    }
+                }
+
+                rejection.is_none()
+            }
+        });
+        reading_order(&mut locations, qr_location);
+
+        let max_codes = self.max_codes.min(self.limits.max_extractions);
+        if locations.len() <= max_codes {
+            return (locations, errors);
+        }
+
+        let dropped = locations.len() - max_codes;
+        for location in locations.drain(max_codes..) {
+            if let (Some(observer), Location::QR(qrloc)) = (&self.observer, location) {
+                observer.on_rejected(&qrloc, Rejection::TooManyCodes);
+            }
+        }
+
+        let limit = format!("{dropped} more codes detected than the maximum of {max_codes}");
+        errors.push(QRError::resource_limit(&limit));
+        (locations, errors)
+    }
+}
+
+#[cfg(feature = "image")]
+impl<PREPD, RESULT> Decoder<DynamicImage, PREPD, RESULT> {
+    /// Decode an encoded image, for example the contents of a PNG, JPEG or WebP file
+    ///
+    /// The image format is determined from the content of the buffer. This saves having to load the image separately
+    /// when it was received over the network, for example as an HTTP upload.
+    ///
+    /// With the `exif` feature, the EXIF orientation of the image is applied first, see [`Orientation`].
+    ///
+    /// Formats the `image` crate cannot read, such as HEIC and AVIF from phone cameras, are read by the loaders of
+    /// [`DecoderBuilder::loader`].
+    ///
+    /// [`Orientation`]: prepare/enum.Orientation.html
+    /// [`DecoderBuilder::loader`]: struct.DecoderBuilder.html#method.loader
+    ///
+    /// # Errors
+    ///
+    /// Returns `QRError` if the bytes could not be decoded into an image, or the image has more pixels than
+    /// [`DecoderBuilder::max_pixels`] allows. Errors for individual codes in the image are returned inside the
+    /// `Vec`, the same as with [`decode`](#method.decode).
+    ///
+    /// [`DecoderBuilder::max_pixels`]: struct.DecoderBuilder.html#method.max_pixels
+    pub fn decode_bytes(&self, bytes: &[u8]) -> Result<Vec<Result<RESULT, QRError>>, QRError> {
+        Ok(self.decode(&self.load_bytes(bytes)?))
+    }
+
+    /// Read an encoded image the same way as [`decode_bytes`](#method.decode_bytes), without decoding it
+    ///
+    /// For front ends that decode the image in another way, such as with [`decode_with_trace`], but should refuse the
+    /// same images with the same errors: the loaders, [`DecoderBuilder::max_pixels`] and the EXIF orientation all
+    /// apply.
+    ///
+    /// [`decode_with_trace`]: #method.decode_with_trace
+    /// [`DecoderBuilder::max_pixels`]: struct.DecoderBuilder.html#method.max_pixels
+    ///
+    /// # Errors
+    ///
+    /// Returns `QRError` if the bytes could not be decoded into an image, or the image has more pixels than
+    /// [`DecoderBuilder::max_pixels`] allows.
+    pub fn load_bytes(&self, bytes: &[u8]) -> Result<DynamicImage, QRError> {
+        if let Some(source) = self.load_with_loader(bytes)? {
+            return Ok(source);
+        }
+
+        self.check_pixels(bytes)?;
+
+        let source = image::load_from_memory(bytes)?;
+
+        #[cfg(feature = "exif")]
+        let source = match crate::prepare::Orientation::from_bytes(bytes) {
+            Some(orientation) => orientation.apply(source),
+            None => source,
+        };
+
+        Ok(source)
+    }
+
+    // The image read by the first loader that can, `None` for the image crate to read
+    fn load_with_loader(&self, bytes: &[u8]) -> Result<Option<DynamicImage>, QRError> {
+        let loader = match self.loaders.iter().find(|loader| loader.can_load(bytes)) {
+            Some(loader) => loader,
+            // Rather than the image crate failing to guess the format
+            None => match HeifFormat::from_bytes(bytes) {
+                Some(format) => {
+                    return Err(QRError::image(format!(
+                        "Unable to read image: {format:?} needs an ImageLoader, see DecoderBuilder::loader"
+                    )))
+                }
+                None => return Ok(None),
+            },
+        };
+
+        if let Some((width, height)) = loader.dimensions(bytes) {
+            self.check_dimensions(width, height)?;
+        }
+
+        let source = loader.load(bytes)?;
+        self.check_dimensions(source.width(), source.height())?;
+
+        Ok(Some(source))
+    }
+
+    // Read the dimensions from the header, which is cheap even for images that are far too large
+    fn check_pixels(&self, bytes: &[u8]) -> Result<(), QRError> {
+        if self.max_pixels == u64::MAX {
+            return Ok(());
+        }
+
+        let (width, height) = image::io::Reader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(image::ImageError::IoError)?
+            .into_dimensions()?;
+
+        self.check_dimensions(width, height)
+    }
+
+    fn check_dimensions(&self, width: u32, height: u32) -> Result<(), QRError> {
+        let pixels = u64::from(width) * u64::from(height);
+
+        if pixels > self.max_pixels {
+            let limit = format!("image of {width}x{height} pixels is larger than the maximum of {}", self.max_pixels);
+            return Err(QRError::resource_limit(&limit));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "image")]
+impl<PREPD: LumaSource, RESULT> Decoder<DynamicImage, PREPD, RESULT> {
+    /// Decode the same way as [`decode`](#method.decode), also measuring the contrast of every decoded code
+    ///
+    /// The reflectances are read from the source image, converted to grayscale, at the pixels `QRExtractor` samples.
+    /// See [`SymbolQuality`] for what is measured.
+    ///
+    /// [`SymbolQuality`]: struct.SymbolQuality.html
+    pub fn decode_with_quality(&self, source: &DynamicImage) -> Vec<Result<(RESULT, SymbolQuality), QRError>> {
+        self.decode_measured(source, &source.to_luma8())
+    }
+}
+
+impl<IMG: LumaSource, PREPD: LumaSource, RESULT> Decoder<IMG, PREPD, RESULT> {
+    /// Decode the same way as [`decode`](#method.decode), also measuring the contrast of every decoded code
+    ///
+    /// The reflectances are read from the source image at the pixels `QRExtractor` samples. See [`SymbolQuality`] for
+    /// what is measured.
+    ///
+    /// [`SymbolQuality`]: struct.SymbolQuality.html
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// assert!(decoder.decode_with_quality(&source).is_empty());
+    /// ```
+    pub fn decode_with_quality(&self, source: &IMG) -> Vec<Result<(RESULT, SymbolQuality), QRError>> {
+        self.decode_measured(source, source)
+    }
+}
+
+impl<IMG, PREPD: LumaSource, RESULT> Decoder<IMG, PREPD, RESULT> {
+    fn decode_measured<S: LumaSource>(&self, source: &IMG, gray: &S) -> Vec<Result<(RESULT, SymbolQuality), QRError>> {
+        if let Err(e) = self.check_dynamic_range(source) {
+            return vec![Err(e)];
+        }
+
+        let prepared = self.prepare_observed(source);
+
+        let (locations, exceeded) = self.locations(&prepared);
+        let mut all_decoded = vec![];
+
+        for location in locations {
+            match location {
+                Location::QR(qrloc) => {
+                    let grid = crate::extract::sample_grid(&prepared, &qrloc, QUIET_ZONE);
+                    let extracted = self.extract_observed(&prepared, qrloc);
+                    let dark = extracted.as_ref().map(decoded_colors).unwrap_or_default();
+
+                    let decoded = self.decode_observed(extracted).and_then(|decoded| {
+                        let grid = grid?;
+                        Ok((decoded, quality::measure(gray, &grid.centers, &dark, &grid.rings)))
+                    });
+
+                    all_decoded.push(decoded);
+                }
+            }
+        }
+
+        all_decoded.extend(exceeded.into_iter().map(Err));
+        all_decoded
+    }
+}
+
+#[cfg(feature = "image")]
+impl<PREPD, RESULT> Decoder<DynamicImage, PREPD, RESULT> {
+    /// Decode the same way as [`decode`](#method.decode), also measuring what a scanner can tell its user to get a
+    /// readable image
+    ///
+    /// Exposure and sharpness are measured on the source image, converted to grayscale. See [`ScanGuidance`] for what
+    /// is measured.
+    ///
+    /// [`ScanGuidance`]: util/guidance/struct.ScanGuidance.html
+    pub fn decode_with_guidance(&self, source: &DynamicImage) -> (Vec<Result<RESULT, QRError>>, ScanGuidance) {
+        self.decode_guided(source, &source.to_luma8())
+    }
+}
+
+impl<IMG: LumaSource, PREPD, RESULT> Decoder<IMG, PREPD, RESULT> {
+    /// Decode the same way as [`decode`](#method.decode), also measuring what a scanner can tell its user to get a
+    /// readable image
+    ///
+    /// The guidance is there also when no code decodes, which is when it is needed: the exposure and sharpness of the
+    /// source, and the finder patterns the detector saw, with their positions and module sizes.
+    /// [`ScanGuidance::advice`] turns these into a single instruction, such as to add light or to move closer.
+    ///
+    /// [`ScanGuidance::advice`]: util/guidance/struct.ScanGuidance.html#method.advice
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 20);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// let (results, guidance) = decoder.decode_with_guidance(&source);
+    /// if results.is_empty() {
+    ///     if let Some(advice) = guidance.advice() {
+    ///         println!("{}", advice);
+    ///     }
+    /// }
+    /// ```
+    pub fn decode_with_guidance(&self, source: &IMG) -> (Vec<Result<RESULT, QRError>>, ScanGuidance) {
+        self.decode_guided(source, source)
+    }
+}
+
+impl<IMG, PREPD, RESULT> Decoder<IMG, PREPD, RESULT> {
+    fn decode_guided<S: LumaSource>(&self, source: &IMG, gray: &S) -> (Vec<Result<RESULT, QRError>>, ScanGuidance) {
+        let (results, events) = trace::collect(|| self.decode(source));
+        (results, guidance::measure(gray, &events))
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<IMG, PREPD: Sync, RESULT: Send> Decoder<IMG, PREPD, RESULT> {
+    /// Decode the same way as [`decode`](#method.decode), extracting and decoding the detected codes in parallel
+    ///
+    /// This pays off for images with many codes, such as a sheet of labels. The codes run on the thread pool set with
+    /// [`DecoderBuilder::thread_pool`], or else on the global pool of rayon. The results are the same as those of
+    /// `decode`, in the same order, only the observer can be called from several threads at once.
+    ///
+    /// [`DecoderBuilder::thread_pool`]: struct.DecoderBuilder.html#method.thread_pool
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// assert_eq!(decoder.decode_parallel(&source), decoder.decode(&source));
+    /// ```
+    pub fn decode_parallel(&self, source: &IMG) -> Vec<Result<RESULT, QRError>> {
+        use rayon::prelude::*;
+
+        if let Err(e) = self.check_dynamic_range(source) {
+            return vec![Err(e)];
+        }
+
+        let prepared = self.prepare_observed(source);
+        let (locations, exceeded) = self.locations(&prepared);
+
+        // Collecting an indexed parallel iterator keeps the reading order
+        let decode_all = || -> Vec<Result<RESULT, QRError>> {
+            locations
+                .into_par_iter()
+                .map(|location| match location {
+                    Location::QR(qrloc) => self.decode_observed(self.extract_observed(&prepared, qrloc)),
+                })
+                .collect()
+        };
+
+        let mut all_decoded = match &self.thread_pool {
+            Some(pool) => pool.install(decode_all),
+            None => decode_all(),
+        };
+
+        all_decoded.extend(exceeded.into_iter().map(Err));
+        all_decoded
+    }
+}
+
+// A code decoded with its location, by `Decoder::decode_tracked`
+#[cfg(feature = "video")]
+type Tracked<RESULT> = Result<(QRLocation, RESULT), QRError>;
+
+// Indices of the locations, the most likely to decode first: larger modules are sampled more reliably, and regular
+// finder patterns are more likely to be those of a single code
+fn decode_order(locations: &[Location]) -> Vec<usize> {
+    let priority = |index: &usize| match &locations[*index] {
+        Location::QR(qrloc) => qrloc.module_size * qrloc.confidence(),
+    };
+
+    let mut order: Vec<usize> = (0..locations.len()).collect();
+    order.sort_by(|a, b| priority(b).total_cmp(&priority(a)));
+    order
+}
+
+// Sort top to bottom and then left to right, by the centers of the codes
+//
+// Codes whose centers are less than half a code apart vertically are in the same row, so codes printed next to each
+// other stay in order from left to right when the image is slightly rotated. Ties, such as two detections of the same
+// code, are broken by the rest of the location, so the order does not depend on the order the items come in. That
+// keeps the results the same however the detector, or the parts of the image, are split up and merged.
+fn reading_order<T>(items: &mut Vec<T>, location: fn(&T) -> &QRLocation) {
+    let center = |item: &T| location(item).center();
+    let half_height = |item: &T| half_size(location(item));
+    let rest_of = |a: &T, b: &T| location_order(location(a), location(b));
+
+    items.sort_by(|a, b| center(a).y.total_cmp(&center(b).y).then_with(|| rest_of(a, b)));
+
+    let mut rest = std::mem::take(items).into_iter().peekable();
+
+    while let Some(first) = rest.next() {
+        let bottom = center(&first).y + half_height(&first);
+        let mut row = vec![first];
+
+        while let Some(next) = rest.next_if(|next| center(next).y < bottom) {
+            row.push(next);
+        }
+
+        row.sort_by(|a, b| center(a).x.total_cmp(&center(b).x).then_with(|| rest_of(a, b)));
+        items.append(&mut row);
+    }
+}
+
+// A total order over every field of the locations
+fn location_order(a: &QRLocation, b: &QRLocation) -> Ordering {
+    let fields = |qrloc: &QRLocation| {
+        let QRLocation {
+            top_left: tl,
+            top_right: tr,
+            bottom_left: bl,
+            ..
+        } = *qrloc;
+        [tl.x, tl.y, tr.x, tr.y, bl.x, bl.y, qrloc.module_size]
+    };
+
+    fields(a)
+        .iter()
+        .zip(&fields(b))
+        .map(|(a, b)| a.total_cmp(b))
+        .fold(Ordering::Equal, Ordering::then)
+        .then(a.version.cmp(&b.version))
+}
+
+fn qr_location(location: &Location) -> &QRLocation {
+    match location {
+        Location::QR(qrloc) => qrloc,
+    }
+}
+
+fn half_size(qrloc: &QRLocation) -> f64 {
+    qrloc.module_size * f64::from(4 * qrloc.version + 17) / 2.0
+}
+
+// Detections of the same code, for example in overlapping parts of an image, have centers within half a code
+fn same_code(a: &QRLocation, b: &QRLocation) -> bool {
+    let (ca, cb) = (a.center(), b.center());
+
+    (ca.x - cb.x).hypot(ca.y - cb.y) < half_size(a)
+}
+
+// The location moved by `dx` and `dy` pixels, from a part of an image to the whole of it
+fn shifted(qrloc: &QRLocation, dx: f64, dy: f64) -> QRLocation {
+    let shift = |point: crate::util::Point| crate::util::Point {
+        x: point.x + dx,
+        y: point.y + dy,
+    };
+
+    QRLocation {
+        top_left: shift(qrloc.top_left),
+        top_right: shift(qrloc.top_right),
+        bottom_left: shift(qrloc.bottom_left),
+        module_size: qrloc.module_size,
+        version: qrloc.version,
+    }
+}
+
+// Whether every module is dark after error correction, row by row
+fn decoded_colors(data: &QRData) -> Vec<bool> {
+    let mut dark: Vec<bool> = data.data.iter().map(|pixel| *pixel == 0).collect();
+
+    for (x, y) in crate::decode::corrected_modules(data).unwrap_or_default() {
+        let index = (y * data.side + x) as usize;
+        dark[index] = !dark[index];
+    }
+
+    dark
+}
+
+#[cfg(feature = "debug-images")]
+impl<PREPD: LumaSource, RESULT> Decoder<DynamicImage, PREPD, RESULT> {
+    /// Render the source image annotated with everything the decoder found in it
+    ///
+    /// This is the first thing to look at when an image does not decode. For every detected QR Code it shows:
+    /// * the finder patterns, as yellow squares
+    /// * the grid of pixels `QRExtractor` samples, as red dots for dark and blue dots for light modules
+    /// * the modules that error correction had to fix, as magenta squares
+    /// * the outline of the code, in green if it decoded and in red if it did not
+    ///
+    /// The grid and the corrections are left out if the code could not be extracted or decoded that far.
+    pub fn debug_image(&self, source: &DynamicImage) -> RgbImage {
+        let mut img = source.to_rgb8();
+        let prepared = self.prepare_observed(source);
+
+        for location in self.locations(&prepared).0 {
+            match location {
+                Location::QR(loc) => {
+                    let half = loc.module_size / 2.0;
+
+                    for finder in &[loc.top_left, loc.top_right, loc.bottom_left] {
+                        draw::square(&mut img, *finder, 3.5 * loc.module_size, FINDER_COLOR);
+                        draw::square(&mut img, *finder, 1.5 * loc.module_size, FINDER_COLOR);
+                    }
+
+                    let grid = crate::extract::sample_grid(&prepared, &loc, 0).map(|grid| grid.centers).unwrap_or_default();
+                    let pixel = |(x, y): (u32, u32)| Point {
+                        x: f64::from(x),
+                        y: f64::from(y),
+                    };
+
+                    for (x, y) in &grid {
+                        let color = if prepared.luma(*x, *y) == 0 { DARK_COLOR } else { LIGHT_COLOR };
+                        draw::dot(&mut img, pixel((*x, *y)), (half / 3.0).min(1.5), color);
+                    }
+
+                    let corners = loc.corners();
+                    let extracted = self.extract_observed(&prepared, loc);
+
+                    if let Ok(data) = &extracted {
+                        let corrected = crate::decode::corrected_modules(data).unwrap_or_default();
+
+                        for (x, y) in corrected {
+                            if let Some(center) = grid.get((y * data.side + x) as usize) {
+                                draw::square(&mut img, pixel(*center), half, CORRECTED_COLOR);
+                            }
+                        }
+                    }
+
+                    let color = match self.decode_observed(extracted) {
+                        Ok(_) => DECODED_COLOR,
+                        Err(_) => FAILED_COLOR,
+                    };
+
+                    draw::polygon(&mut img, &corners, color);
+                }
+            }
+        }
+
+        img
+    }
+}
+
+#[cfg(feature = "debug-images")]
+const FINDER_COLOR: Rgb<u8> = Rgb([255, 200, 0]);
+#[cfg(feature = "debug-images")]
+const DARK_COLOR: Rgb<u8> = Rgb([255, 0, 0]);
+#[cfg(feature = "debug-images")]
+const LIGHT_COLOR: Rgb<u8> = Rgb([0, 128, 255]);
+#[cfg(feature = "debug-images")]
+const CORRECTED_COLOR: Rgb<u8> = Rgb([255, 0, 255]);
+#[cfg(feature = "debug-images")]
+const DECODED_COLOR: Rgb<u8> = Rgb([0, 200, 0]);
+#[cfg(feature = "debug-images")]
+const FAILED_COLOR: Rgb<u8> = Rgb([255, 0, 0]);
+
+#[cfg(feature = "image")]
+impl<PREPD, RESULT: DecodedContent> Decoder<DynamicImage, PREPD, RESULT> {
+    /// Decode every `step`th frame of an encoded animated GIF or APNG
+    ///
+    /// Codes are usually visible in many consecutive frames, so every distinct content is returned only once, together
+    /// with the index of the frame it first appeared in. Results are in order of appearance. Any other image is
+    /// treated as a single frame animation. A `step` of 0 is treated as 1.
+    ///
+    /// Codes that could not be decoded in a frame are skipped; they may well decode in one of the other frames.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QRError` if the bytes, or any of the frames, could not be decoded into an image, or the image has more
+    /// pixels than [`DecoderBuilder::max_pixels`] allows.
+    ///
+    /// [`DecoderBuilder::max_pixels`]: struct.DecoderBuilder.html#method.max_pixels
+    pub fn decode_animation_bytes(&self, bytes: &[u8], step: usize) -> Result<Vec<FrameResult<RESULT>>, QRError> {
+        let frames: Box<dyn Iterator<Item = image::ImageResult<DynamicImage>>> = match self.load_with_loader(bytes)? {
+            Some(source) => Box::new(std::iter::once(Ok(source))),
+            None => self.frames(bytes)?,
+        };
+
+        let mut found: Vec<FrameResult<RESULT>> = vec![];
+
+        for (frame, image) in frames.enumerate().step_by(step.max(1)) {
+            for result in self.decode(&image?) {
+                match result {
+                    Ok(result) => {
+                        if !found.iter().any(|f| f.result.content() == result.content()) {
+                            found.push(FrameResult { frame, result });
+                        }
+                    }
+                    Err(e) => debug!("Frame {}: {}", frame, e),
+                }
+            }
+        }
+
+        Ok(found)
+    }
+
+    // The frames of an animation, or any other image as a single frame
+    fn frames<'a>(
+        &self,
+        bytes: &'a [u8],
+    ) -> Result<Box<dyn Iterator<Item = image::ImageResult<DynamicImage>> + 'a>, QRError> {
+        self.check_pixels(bytes)?;
 
-/// Error type for `DecoderBuilder`
-#[derive(Debug, thiserror::Error)]
-pub enum BuilderError {
-    /// The prepare component is required but was not provided
-    #[error("Cannot build Decoder without Prepare component")]
-    MissingPrepare,
-    /// The detect component is required but was not provided
-    #[error("Cannot build Decoder without Detect component")]
-    MissingDetect,
-    /// The QR extract and decode components are required but were not provided
-    #[error("Cannot build Decoder without QR extract and decode components")]
-    MissingQR,
+        let frames: Box<dyn Iterator<Item = image::ImageResult<DynamicImage>>> = match image::guess_format(bytes)? {
+            ImageFormat::Gif => Box::new(to_images(GifDecoder::new(Cursor::new(bytes))?.into_frames())),
+            ImageFormat::Png => {
+                let png = PngDecoder::new(Cursor::new(bytes))?;
+                if png.is_apng() {
+                    Box::new(to_images(png.apng().into_frames()))
+                } else {
+                    Box::new(std::iter::once(DynamicImage::from_decoder(png)))
+                }
+            }
+            _ => Box::new(std::iter::once(image::load_from_memory(bytes))),
+        };
+
+        Ok(frames)
+    }
 }
 
-/// Struct to hold logic to do the entire decoding
-pub struct Decoder<IMG, PREPD, RESULT> {
-    prepare: Box<dyn Prepare<IMG, PREPD>>,
-    detect: Box<dyn Detect<PREPD>>,
-    qr: ExtractDecode<PREPD, QRLocation, QRData, RESULT, QRError>,
+#[cfg(feature = "image")]
+fn to_images(frames: Frames<'_>) -> impl Iterator<Item = image::ImageResult<DynamicImage>> + '_ {
+    frames.map(|frame| frame.map(|f| DynamicImage::ImageRgba8(f.into_buffer())))
 }
 
-impl<IMG, PREPD, RESULT> Decoder<IMG, PREPD, RESULT> {
-    /// Do the actual decoding
+impl<IMG, PREPD, RESULT: DecodedContent> Decoder<IMG, PREPD, RESULT> {
+    /// Whether any code in the image has exactly the expected content
     ///
-    /// Logic is run in the following order:
-    /// * prepare
-    /// * detect
-    /// * per detected code the associated extract and decode functions
-    pub fn decode(&self, source: &IMG) -> Vec<Result<RESULT, QRError>> {
-        let prepared = self.prepare.prepare(source);
-        let locations = self.detect.detect(&prepared);
-
-        if locations.is_empty() {
-            return vec![];
+    /// Meant for verification, for example checking every printed label against its job ticket. Codes are decoded one
+    /// by one in reading order and this returns as soon as one matches, without decoding the rest or collecting the
+    /// results. Codes that do not decode count as not matching.
+    ///
+    /// With `QRDecoder` or `QRDecoderWithInfo`, the text of a code is compared while its data is parsed, without
+    /// building it, and the comparison stops at the first piece that differs from the expected content. Other
+    /// decoders can do the same with [`Decode::matches`], or else the code is decoded and the content of the result
+    /// compared. With an observer, which is told about every decoded result, codes are always decoded in full.
+    ///
+    /// [`Decode::matches`]: decode/trait.Decode.html#method.matches
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// assert!(!decoder.contains(&source, "LOT-1234"));
+    /// ```
+    pub fn contains(&self, source: &IMG, expected: &str) -> bool {
+        if self.check_dynamic_range(source).is_err() {
+            return false;
         }
 
-        let mut all_decoded = vec![];
+        let prepared = self.prepare_observed(source);
 
-        for location in locations {
-            match location {
-                Location::QR(qrloc) => {
-                    let extracted = self.qr.extract.extract(&prepared, qrloc);
-                    let decoded = self.qr.decode.decode(extracted);
+        self.locations(&prepared).0.into_iter().any(|location| match location {
+            Location::QR(qrloc) => self.matches_observed(self.extract_observed(&prepared, qrloc), expected),
+        })
+    }
 
-                    all_decoded.push(decoded);
-                }
+    // Whether the code decodes to the expected content, compared while decoding when there is no observer to tell
+    // about the result and the decoder can
+    fn matches_observed(&self, extracted: Result<QRData, QRError>, expected: &str) -> bool {
+        if self.observer.is_none() {
+            let (matched, _) = limits::within(self.limits, || self.qr.decode.matches(&extracted, expected));
+            if let Some(matched) = matched {
+                return matched;
             }
         }
 
-        all_decoded
+        self.decode_observed(extracted).is_ok_and(|decoded| decoded.content() == expected)
+    }
+}
+
+/// A decoded code together with the index of the animation frame it first appeared in
+#[derive(Debug, PartialEq)]
+pub struct FrameResult<RESULT> {
+    /// Index of the frame, counting from 0
+    pub frame: usize,
+    /// The decoded result
+    pub result: RESULT,
+}
+
+/// Decoder results that carry the decoded content, used to recognise the same code across frames
+pub trait DecodedContent {
+    /// The decoded content
+    fn content(&self) -> &str;
+}
+
+impl DecodedContent for String {
+    fn content(&self) -> &str {
+        self
+    }
+}
+
+impl DecodedContent for (String, QRInfo) {
+    fn content(&self) -> &str {
+        &self.0
     }
 }
 
@@ -77,6 +1372,7 @@ impl<IMG, PREPD, RESULT> Decoder<IMG, PREPD, RESULT> {
 ///
 /// This function will panic if the default builder fails to build,
 /// which should never happen as all components are provided.
+#[cfg(feature = "image")]
 #[must_use]
 pub fn default_decoder() -> Decoder<DynamicImage, GrayImage, String> {
     default_builder()
@@ -99,6 +1395,7 @@ pub fn default_decoder() -> Decoder<DynamicImage, GrayImage, String> {
 ///
 /// Returns `BuilderError` if the decoder fails to build,
 /// though this should never happen as all components are provided.
+#[cfg(feature = "image")]
 pub fn try_default_decoder() -> Result<Decoder<DynamicImage, GrayImage, String>, BuilderError> {
     default_builder().build()
 }
@@ -118,6 +1415,7 @@ pub fn try_default_decoder() -> Result<Decoder<DynamicImage, GrayImage, String>,
 ///
 /// This function will panic if the default builder fails to build,
 /// which should never happen as all components are provided.
+#[cfg(feature = "image")]
 #[must_use]
 pub fn default_decoder_with_info() -> Decoder<DynamicImage, GrayImage, (String, QRInfo)> {
     default_builder_with_info()
@@ -140,6 +1438,7 @@ pub fn default_decoder_with_info() -> Decoder<DynamicImage, GrayImage, (String,
 ///
 /// Returns `BuilderError` if the decoder fails to build,
 /// though this should never happen as all components are provided.
+#[cfg(feature = "image")]
 pub fn try_default_decoder_with_info() -> Result<Decoder<DynamicImage, GrayImage, (String, QRInfo)>, BuilderError> {
     default_builder_with_info().build()
 }
@@ -152,10 +1451,29 @@ pub fn try_default_decoder_with_info() -> Result<Decoder<DynamicImage, GrayImage
 /// * Detect
 /// * Extract
 /// * Decode
+///
+/// All components must be `Send` and `Sync`, which makes the Decoder `Send` and `Sync` as well.
 pub struct DecoderBuilder<IMG, PREPD, RESULT> {
-    prepare: Option<Box<dyn Prepare<IMG, PREPD>>>,
-    detect: Option<Box<dyn Detect<PREPD>>>,
-    qr: Option<ExtractDecode<PREPD, QRLocation, QRData, RESULT, QRError>>,
+    prepare: Option<Box<dyn Prepare<IMG, PREPD> + Send + Sync>>,
+    detect: Option<Box<dyn Detect<PREPD> + Send + Sync>>,
+    fallback_detect: Vec<Box<dyn Detect<PREPD> + Send + Sync>>,
+    // Set together by `qr`
+    extract: Option<Box<dyn Extract<PREPD, QRLocation, QRData, QRError> + Send + Sync>>,
+    decode: Option<Box<dyn Decode<QRData, RESULT, QRError> + Send + Sync>>,
+    fallback_decode: Vec<Box<dyn Decode<QRData, RESULT, QRError> + Send + Sync>>,
+    min_module_size: f64,
+    max_version: u32,
+    #[cfg(feature = "image")]
+    max_pixels: u64,
+    max_codes: usize,
+    limits: ResourceLimits,
+    time_budget: Option<Duration>,
+    min_dynamic_range: u8,
+    observer: Option<Box<dyn PipelineObserver<PREPD, RESULT> + Send + Sync>>,
+    #[cfg(feature = "image")]
+    loaders: Vec<Box<dyn ImageLoader + Send + Sync>>,
+    #[cfg(feature = "parallel")]
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
 }
 
 impl<IMG, PREPD, RESULT> DecoderBuilder<IMG, PREPD, RESULT> {
@@ -164,14 +1482,30 @@ impl<IMG, PREPD, RESULT> DecoderBuilder<IMG, PREPD, RESULT> {
         DecoderBuilder {
             prepare: None,
             detect: None,
-            qr: None,
+            fallback_detect: vec![],
+            extract: None,
+            decode: None,
+            fallback_decode: vec![],
+            min_module_size: 0.0,
+            max_version: 40,
+            #[cfg(feature = "image")]
+            max_pixels: u64::MAX,
+            max_codes: usize::MAX,
+            limits: ResourceLimits::default(),
+            time_budget: None,
+            min_dynamic_range: 0,
+            observer: None,
+            #[cfg(feature = "image")]
+            loaders: vec![],
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
         }
     }
 
     /// Set the prepare implementation for this Decoder
     pub fn prepare(
         &mut self,
-        prepare: Box<dyn Prepare<IMG, PREPD>>,
+        prepare: Box<dyn Prepare<IMG, PREPD> + Send + Sync>,
     ) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
         self.prepare = Some(prepare);
         self
@@ -180,19 +1514,222 @@ impl<IMG, PREPD, RESULT> DecoderBuilder<IMG, PREPD, RESULT> {
     /// Set the detect implementation for this Decoder
     pub fn detect(
         &mut self,
-        detect: Box<dyn Detect<PREPD>>,
+        detect: Box<dyn Detect<PREPD> + Send + Sync>,
     ) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
         self.detect = Some(detect);
         self
     }
 
+    /// Add a detect implementation to run when the ones before it find nothing
+    ///
+    /// Every call adds another, after the detect implementation and the fallbacks added before, so a fast detector
+    /// can handle the common case with a slower, more robust one as backup. A fallback only runs when the ones before
+    /// it detected no codes at all, before the limits of the builder are applied. See [`Fallback`].
+    ///
+    /// [`Fallback`]: detect/struct.Fallback.html
+    pub fn fallback_detect(
+        &mut self,
+        detect: Box<dyn Detect<PREPD> + Send + Sync>,
+    ) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.fallback_detect.push(detect);
+        self
+    }
+
     /// Set the extact and decode implementations for this Decoder for QR codes
     pub fn qr(
         &mut self,
-        extract: Box<dyn Extract<PREPD, QRLocation, QRData, QRError>>,
-        decode: Box<dyn Decode<QRData, RESULT, QRError>>,
+        extract: Box<dyn Extract<PREPD, QRLocation, QRData, QRError> + Send + Sync>,
+        decode: Box<dyn Decode<QRData, RESULT, QRError> + Send + Sync>,
+    ) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.extract = Some(extract);
+        self.decode = Some(decode);
+        self
+    }
+
+    /// Add a QR decode implementation to try when the ones before it fail
+    ///
+    /// Every call adds another, after the decode implementation set with [`qr`](#method.qr) and the fallbacks added
+    /// before, for example a strict decoder first and a lenient one as backup. Each one gets the same extracted data,
+    /// until one succeeds. When they all fail, the error has the message of every attempt. See [`Fallback`].
+    ///
+    /// [`Fallback`]: decode/struct.Fallback.html
+    pub fn fallback_decode(
+        &mut self,
+        decode: Box<dyn Decode<QRData, RESULT, QRError> + Send + Sync>,
+    ) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.fallback_decode.push(decode);
+        self
+    }
+
+    /// Replace the prepare implementation, keeping the other components
+    ///
+    /// Takes and returns the builder by value, so a single stage of a default builder can be replaced in one
+    /// expression, as in `default_builder().with_prepare(Box::new(BlockedMean::new(3, 9))).build()`.
+    pub fn with_prepare(
+        mut self,
+        prepare: Box<dyn Prepare<IMG, PREPD> + Send + Sync>,
+    ) -> DecoderBuilder<IMG, PREPD, RESULT> {
+        self.prepare(prepare);
+        self
+    }
+
+    /// Replace the detect implementation, keeping the other components, see [`with_prepare`](#method.with_prepare)
+    pub fn with_detect(mut self, detect: Box<dyn Detect<PREPD> + Send + Sync>) -> DecoderBuilder<IMG, PREPD, RESULT> {
+        self.detect(detect);
+        self
+    }
+
+    /// Replace the QR extract and decode implementations, keeping the other components, see
+    /// [`with_prepare`](#method.with_prepare)
+    pub fn with_qr(
+        mut self,
+        extract: Box<dyn Extract<PREPD, QRLocation, QRData, QRError> + Send + Sync>,
+        decode: Box<dyn Decode<QRData, RESULT, QRError> + Send + Sync>,
+    ) -> DecoderBuilder<IMG, PREPD, RESULT> {
+        self.qr(extract, decode);
+        self
+    }
+
+    /// Skip detected codes with modules smaller than this, in pixels
+    ///
+    /// Codes with modules of only a pixel or two rarely decode, and noise in an image is often detected as such
+    /// small codes. They are dropped after detection, before any time is spent extracting them. Defaults to 0.0.
+    pub fn min_module_size(&mut self, min_module_size: f64) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.min_module_size = min_module_size;
+        self
+    }
+
+    /// Skip detected codes with a version larger than this
+    ///
+    /// For applications that only print small codes, so a large version can only be a wrong detection. Defaults to
+    /// 40, the largest version.
+    pub fn max_version(&mut self, max_version: u32) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.max_version = max_version;
+        self
+    }
+
+    /// Refuse encoded images with more pixels than this, before decoding their pixel data
+    ///
+    /// Applies to [`Decoder::decode_bytes`] and [`Decoder::decode_animation_bytes`], which read the dimensions from
+    /// the header of the image first, or from [`ImageLoader::dimensions`] for the images of a loader. A small file can
+    /// claim to be a huge image, so this bounds the memory of the decoded and prepared images for untrusted uploads.
+    /// Larger images are an error for which [`QRError::is_resource_limit`] is true. Defaults to no limit.
+    ///
+    /// [`Decoder::decode_bytes`]: struct.Decoder.html#method.decode_bytes
+    /// [`Decoder::decode_animation_bytes`]: struct.Decoder.html#method.decode_animation_bytes
+    /// [`ImageLoader::dimensions`]: prepare/trait.ImageLoader.html#method.dimensions
+    /// [`QRError::is_resource_limit`]: util/qr/struct.QRError.html#method.is_resource_limit
+    #[cfg(feature = "image")]
+    pub fn max_pixels(&mut self, max_pixels: u64) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.max_pixels = max_pixels;
+        self
+    }
+
+    /// Extract and decode at most this many of the detected codes per image
+    ///
+    /// Every detected code costs an extraction and error correction, and noise can be detected as many codes. The
+    /// codes that are kept are the first in reading order, top to bottom and then left to right, after those ruled out
+    /// by [`min_module_size`](#method.min_module_size) and [`max_version`](#method.max_version). Which codes those are
+    /// does not depend on the [`time_budget`](#method.time_budget), which only changes the order the kept codes are
+    /// decoded in. The rest are replaced by a single error for which [`QRError::is_resource_limit`] is true, and
+    /// [`ResourceLimits::max_extractions`] lowers the maximum the same way. The memory per code is bounded by
+    /// [`max_version`](#method.max_version). Defaults to no limit.
+    ///
+    /// [`QRError::is_resource_limit`]: util/qr/struct.QRError.html#method.is_resource_limit
+    /// [`ResourceLimits::max_extractions`]: util/limits/struct.ResourceLimits.html#structfield.max_extractions
+    pub fn max_codes(&mut self, max_codes: usize) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.max_codes = max_codes;
+        self
+    }
+
+    /// Bound the work of a decode, so images crafted to be slow to decode fail early, see [`ResourceLimits`]
+    ///
+    /// The default limits apply unless this is called, use `ResourceLimits::UNLIMITED` for trusted input that needs
+    /// more.
+    ///
+    /// [`ResourceLimits`]: util/limits/struct.ResourceLimits.html
+    pub fn limits(&mut self, limits: ResourceLimits) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.limits = limits;
+        self
+    }
+
+    /// Stop extracting and decoding codes once this much time has passed since detection finished
+    ///
+    /// With many codes in an image, there may not be time for all of them. With a time budget the codes are extracted
+    /// and decoded in order of how likely they are to decode: those with the largest modules and the most regular
+    /// finder patterns first, see [`QRLocation::confidence`], so the deadline cuts off the least likely ones. Every
+    /// code there was no time left for gets an error for which [`QRError::is_resource_limit`] is true. Results stay in
+    /// reading order. A budget applies to `decode`, `decode_prepared`, `decode_with_error_locations` and
+    /// `decode_with_hints`. Defaults to no budget, decoding every code in reading order.
+    ///
+    /// Timing uses `std::time::Instant`, which is not available on `wasm32-unknown-unknown`.
+    ///
+    /// [`QRLocation::confidence`]: util/qr/struct.QRLocation.html#method.confidence
+    /// [`QRError::is_resource_limit`]: util/qr/struct.QRError.html#method.is_resource_limit
+    pub fn time_budget(&mut self, time_budget: Duration) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    /// Fail images that are too uniform to show a code, instead of returning no results for them
+    ///
+    /// An image with the lens cap on, or of a blank sheet of paper, decodes to nothing, the same as an image of a
+    /// scene without a code in it. With a minimum set, the prepare step measures the dynamic range of the image first:
+    /// the difference between its darkest and lightest parts, from 0 to 255, see [`Prepare::dynamic_range`]. Below
+    /// the minimum, the result is a single error with [`ErrorCode::NoContrast`] and nothing is prepared or detected,
+    /// so a camera application can tell the user to point it at the code rather than keep trying. The black and white
+    /// modules of a code have a range of 100 to 200 in most photos, a minimum of 32 leaves room for dim and washed
+    /// out ones. Defaults to 0, no minimum.
+    ///
+    /// This applies to every method of the decoder that prepares a source, with prepare implementations that measure
+    /// the range, as `BlockedMean` does. Those that return results for the codes return the error instead, such as
+    /// `decode_at` and `decode_with_trace`; `detect_only`, `detect_with_format` and `count` find no codes, and
+    /// `contains` is false. The exceptions are:
+    /// * `prepare_only` and `decode_prepared`, which are for preparing once and decoding as the caller sees fit
+    /// * `debug_image`, which is for seeing what the decoder makes of an image, also one without enough contrast
+    /// * `decode_tiled` and `scanlines`, where a blank tile or strip of rows is normal for a large scan
+    ///
+    /// [`Prepare::dynamic_range`]: prepare/trait.Prepare.html#method.dynamic_range
+    /// [`ErrorCode::NoContrast`]: util/qr/enum.ErrorCode.html#variant.NoContrast
+    pub fn min_dynamic_range(&mut self, min_dynamic_range: u8) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.min_dynamic_range = min_dynamic_range;
+        self
+    }
+
+    /// Let the observer know about the outcome of every step of a decode, see [`PipelineObserver`]
+    ///
+    /// [`PipelineObserver`]: util/observer/trait.PipelineObserver.html
+    pub fn observer(
+        &mut self,
+        observer: Box<dyn PipelineObserver<PREPD, RESULT> + Send + Sync>,
     ) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
-        self.qr = Some(ExtractDecode { extract, decode });
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Read the images this loader [`can_load`] in [`Decoder::decode_bytes`], instead of with the `image` crate
+    ///
+    /// This is how formats such as HEIC and AVIF are read, see [`ImageLoader`]. It can be called more than once, the
+    /// loaders are asked in the order they were added.
+    ///
+    /// [`can_load`]: prepare/trait.ImageLoader.html#tymethod.can_load
+    /// [`Decoder::decode_bytes`]: struct.Decoder.html#method.decode_bytes
+    /// [`ImageLoader`]: prepare/trait.ImageLoader.html
+    #[cfg(feature = "image")]
+    pub fn loader(&mut self, loader: Box<dyn ImageLoader + Send + Sync>) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.loaders.push(loader);
+        self
+    }
+
+    /// Run [`Decoder::decode_parallel`] on this thread pool instead of the global one of rayon
+    ///
+    /// An application with a pool of its own can share it, so decoding does not start another set of threads
+    /// competing for the same cores. A pool of a single thread runs the codes one at a time, off the calling thread.
+    ///
+    /// [`Decoder::decode_parallel`]: struct.Decoder.html#method.decode_parallel
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool(&mut self, thread_pool: Arc<rayon::ThreadPool>) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        self.thread_pool = Some(thread_pool);
         self
     }
 
@@ -207,12 +1744,34 @@ impl<IMG, PREPD, RESULT> DecoderBuilder<IMG, PREPD, RESULT> {
     pub fn build(self) -> Result<Decoder<IMG, PREPD, RESULT>, BuilderError> {
         let prepare = self.prepare.ok_or(BuilderError::MissingPrepare)?;
         let detect = self.detect.ok_or(BuilderError::MissingDetect)?;
-        let qr = self.qr.ok_or(BuilderError::MissingQR)?;
+        let extract = self.extract.ok_or(BuilderError::MissingQR)?;
+        let decode = self.decode.ok_or(BuilderError::MissingQR)?;
+
+        let mut detectors = vec![detect];
+        detectors.extend(self.fallback_detect);
+        let mut decoders = vec![decode];
+        decoders.extend(self.fallback_decode);
 
         Ok(Decoder {
-            prepare,
-            detect,
-            qr,
+            prepare: Arc::from(prepare),
+            detect: Arc::new(DetectFallback::new(detectors)),
+            qr: ExtractDecode {
+                extract: Arc::from(extract),
+                decode: Arc::new(DecodeFallback::new(decoders)),
+            },
+            min_module_size: self.min_module_size,
+            max_version: self.max_version,
+            #[cfg(feature = "image")]
+            max_pixels: self.max_pixels,
+            max_codes: self.max_codes,
+            limits: self.limits,
+            time_budget: self.time_budget,
+            min_dynamic_range: self.min_dynamic_range,
+            observer: self.observer.map(Arc::from),
+            #[cfg(feature = "image")]
+            loaders: Arc::from(self.loaders),
+            #[cfg(feature = "parallel")]
+            thread_pool: self.thread_pool,
         })
     }
 }
@@ -227,6 +1786,7 @@ impl<IMG, PREPD, RESULT> DecoderBuilder<IMG, PREPD, RESULT> {
 /// * decode: `QRDecoder`
 ///
 /// The builder can then be customised before creating the Decoder
+#[cfg(feature = "image")]
 #[must_use]
 pub fn default_builder() -> DecoderBuilder<DynamicImage, GrayImage, String> {
     let mut db = DecoderBuilder::new();
@@ -248,6 +1808,7 @@ pub fn default_builder() -> DecoderBuilder<DynamicImage, GrayImage, String> {
 /// * decode: `QRDecoderWithInfo`
 ///
 /// The builder can then be customised before creating the Decoder
+#[cfg(feature = "image")]
 #[must_use]
 pub fn default_builder_with_info() -> DecoderBuilder<DynamicImage, GrayImage, (String, QRInfo)> {
     let mut db = DecoderBuilder::new();
@@ -262,12 +1823,60 @@ pub fn default_builder_with_info() -> DecoderBuilder<DynamicImage, GrayImage, (S
     db
 }
 
+/// Create a default Decoder for raw grayscale buffers
+///
+/// It uses the same components as [`default_decoder`](fn.default_decoder.html), but takes a [`LumaImage`] so that
+/// it is also available without the `image` feature. Any buffer type can back the image, for example a `Vec<u8>` or
+/// a `&[u8]` borrowed from a camera frame.
+///
+/// [`LumaImage`]: util/struct.LumaImage.html
+#[must_use]
+pub fn default_luma_decoder<C: Deref<Target = [u8]>>() -> Decoder<LumaImage<C>, LumaImage, String> {
+    let mut db = DecoderBuilder::new();
+
+    db.prepare(Box::new(BlockedMean::new(5, 7)));
+    db.detect(Box::new(LineScan::new()));
+    db.qr(Box::new(QRExtractor::new()), Box::new(QRDecoder::new()));
+
+    db.build()
+        .expect("Default decoder should always build successfully: all required components are provided")
+}
+
+/// Create a default Decoder for raw grayscale buffers that also returns information about the decoded QR Code
+///
+/// See [`default_luma_decoder`](fn.default_luma_decoder.html)
+#[must_use]
+pub fn default_luma_decoder_with_info<C: Deref<Target = [u8]>>() -> Decoder<LumaImage<C>, LumaImage, (String, QRInfo)> {
+    let mut db = DecoderBuilder::new();
+
+    db.prepare(Box::new(BlockedMean::new(5, 7)));
+    db.detect(Box::new(LineScan::new()));
+    db.qr(
+        Box::new(QRExtractor::new()),
+        Box::new(QRDecoderWithInfo::new()),
+    );
+
+    db.build()
+        .expect("Default decoder with info should always build successfully: all required components are provided")
+}
+
 struct ExtractDecode<PREPD, LOC, DATA, RESULT, ERROR> {
-    extract: Box<dyn Extract<PREPD, LOC, DATA, ERROR>>,
-    decode: Box<dyn Decode<DATA, RESULT, ERROR>>,
+    extract: Arc<dyn Extract<PREPD, LOC, DATA, ERROR> + Send + Sync>,
+    // The decode implementation of the builder, followed by its fallbacks
+    decode: Arc<DecodeFallback<DATA, RESULT>>,
+}
+
+// Not derived, which would require the type parameters to be `Clone` as well
+impl<PREPD, LOC, DATA, RESULT, ERROR> Clone for ExtractDecode<PREPD, LOC, DATA, RESULT, ERROR> {
+    fn clone(&self) -> Self {
+        ExtractDecode {
+            extract: Arc::clone(&self.extract),
+            decode: Arc::clone(&self.decode),
+        }
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "image"))]
 mod tests {
     use super::*;
     use image::{DynamicImage, GrayImage};
@@ -327,6 +1936,163 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_reading_order() {
+        // Version 1 codes of 21 pixels, identified by the x and y of their center
+        let code = |x: f64, y: f64| {
+            let point = |dx: f64, dy: f64| crate::util::Point { x: x + dx, y: y + dy };
+            Location::QR(QRLocation {
+                top_left: point(-7.0, -7.0),
+                top_right: point(7.0, -7.0),
+                bottom_left: point(-7.0, 7.0),
+                module_size: 1.0,
+                version: 1,
+            })
+        };
+        let centers = |locations: &[Location]| -> Vec<(f64, f64)> {
+            locations
+                .iter()
+                .map(|Location::QR(qrloc)| (qrloc.center().x, qrloc.center().y))
+                .collect()
+        };
+
+        let mut locations = vec![code(100.0, 52.0), code(50.0, 100.0), code(10.0, 45.0), code(55.0, 50.0)];
+        reading_order(&mut locations, qr_location);
+
+        assert_eq!(
+            centers(&locations),
+            vec![(10.0, 45.0), (55.0, 50.0), (100.0, 52.0), (50.0, 100.0)]
+        );
+    }
+
+    #[test]
+    fn test_decode_order() {
+        let code = |module_size: f64, skew: f64| {
+            let point = |x: f64, y: f64| crate::util::Point { x, y };
+            Location::QR(QRLocation {
+                top_left: point(10.0, 10.0),
+                top_right: point(10.0 + 14.0 * module_size, 10.0 + skew),
+                bottom_left: point(10.0, 10.0 + 14.0 * module_size),
+                module_size,
+                version: 1,
+            })
+        };
+
+        // Larger modules first, but a badly skewed code after regular ones with somewhat smaller modules
+        let locations = vec![code(1.0, 0.0), code(3.0, 20.0), code(3.0, 0.0), code(2.0, 0.0)];
+        assert_eq!(decode_order(&locations), vec![2, 3, 1, 0]);
+    }
+
+    #[test]
+    fn test_reading_order_ignores_input_order() {
+        let code = |x: f64, y: f64, module_size: f64| {
+            let point = |dx: f64, dy: f64| crate::util::Point { x: x + dx, y: y + dy };
+            QRLocation {
+                top_left: point(-7.0, -7.0),
+                top_right: point(7.0, -7.0),
+                bottom_left: point(-7.0, 7.0),
+                module_size,
+                version: 1,
+            }
+        };
+
+        // Two detections of the code at (50, 50), a code in the same row and one with the same center but mirrored
+        let mut mirrored = code(10.0, 50.0, 1.0);
+        std::mem::swap(&mut mirrored.top_right, &mut mirrored.bottom_left);
+
+        let locations = vec![
+            code(50.0, 50.0, 1.0),
+            code(50.0, 50.0, 1.1),
+            code(10.0, 50.0, 1.0),
+            mirrored,
+            code(30.0, 100.0, 1.0),
+        ];
+
+        let ordered = |mut locations: Vec<QRLocation>| {
+            reading_order(&mut locations, |qrloc| qrloc);
+            format!("{:?}", locations)
+        };
+        let expected = ordered(locations.clone());
+
+        for start in 0..locations.len() {
+            let mut rotated = locations.clone();
+            rotated.rotate_left(start);
+            assert_eq!(ordered(rotated.clone()), expected);
+
+            rotated.reverse();
+            assert_eq!(ordered(rotated), expected);
+        }
+    }
+
+    #[test]
+    fn test_decoder_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+        let decoder = Arc::new(default_decoder());
+        assert_send_sync(&decoder);
+        assert_send_sync(&default_luma_decoder::<Vec<u8>>());
+
+        let img = DynamicImage::new_luma8(40, 40);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let decoder = Arc::clone(&decoder);
+                let img = img.clone();
+                std::thread::spawn(move || decoder.decode(&img).len())
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 0);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_decode_parallel_on_thread_pool() {
+        use std::sync::Mutex;
+
+        // The name of the thread every code was decoded on
+        struct Threads(Arc<Mutex<Vec<Option<String>>>>);
+
+        impl PipelineObserver<GrayImage, String> for Threads {
+            fn on_decoded(&self, _result: &String) {
+                self.0.lock().unwrap().push(std::thread::current().name().map(String::from));
+            }
+        }
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .thread_name(|i| format!("injected-{i}"))
+            .build()
+            .unwrap();
+        let threads = Arc::new(Mutex::new(vec![]));
+
+        let mut builder = default_builder();
+        builder.thread_pool(Arc::new(pool));
+        builder.observer(Box::new(Threads(Arc::clone(&threads))));
+        let decoder = builder.build().unwrap();
+
+        let img = image::open("tests/images/multiple_codes.png").unwrap();
+        let results = decoder.decode_parallel(&img);
+        assert_eq!(results, default_decoder().decode(&img));
+
+        let threads = threads.lock().unwrap();
+        assert_eq!(threads.len(), 2);
+        assert!(threads.iter().all(|name| name.as_deref().is_some_and(|name| name.starts_with("injected-"))));
+    }
+
+    #[test]
+    fn test_decoder_clone_shares_components() {
+        let decoder = default_decoder();
+        let clone = decoder.clone();
+
+        assert!(Arc::ptr_eq(&decoder.prepare, &clone.prepare));
+        assert!(Arc::ptr_eq(&decoder.qr.decode, &clone.qr.decode));
+
+        let img = DynamicImage::new_luma8(40, 40);
+        assert_eq!(decoder.decode(&img), clone.decode(&img));
+    }
+
     #[test]
     fn test_default_decoder_builds() {
         // This should not panic
@@ -379,6 +2145,50 @@ mod tests {
         assert!(results.is_empty(), "Empty image should return no results");
     }
 
+    #[test]
+    fn test_decode_bytes_invalid_image() {
+        let decoder = default_decoder();
+        let result = decoder.decode_bytes(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        assert!(result.is_err(), "Garbage bytes should not decode into an image");
+    }
+
+    // Reads a version 1 code from bytes that start with "fake"
+    struct FakeLoader;
+
+    impl ImageLoader for FakeLoader {
+        fn can_load(&self, bytes: &[u8]) -> bool {
+            bytes.starts_with(b"fake")
+        }
+
+        fn load(&self, _bytes: &[u8]) -> Result<DynamicImage, QRError> {
+            Ok(image::open("tests/images/version1_example.jpg")?)
+        }
+    }
+
+    #[test]
+    fn test_decode_bytes_with_loader() {
+        let mut db = default_builder();
+        db.loader(Box::new(FakeLoader));
+        let decoder = db.build().unwrap();
+
+        assert_eq!(decoder.decode_bytes(b"fake image").unwrap(), vec![Ok(String::from("01234567"))]);
+        let frames = decoder.decode_animation_bytes(b"fake image", 1).unwrap();
+        assert_eq!(frames, vec![FrameResult { frame: 0, result: String::from("01234567") }]);
+
+        let mut db = default_builder();
+        db.loader(Box::new(FakeLoader)).max_pixels(1000);
+        assert!(db.build().unwrap().decode_bytes(b"fake image").unwrap_err().is_resource_limit());
+    }
+
+    #[test]
+    fn test_decode_bytes_heif_without_loader() {
+        let heic = b"\0\0\0\x18ftypheic\0\0\0\0mif1heic";
+        let error = default_decoder().decode_bytes(heic).unwrap_err();
+
+        assert!(error.msg.contains("Heic needs an ImageLoader"), "{}", error.msg);
+        assert_eq!(error.code(), crate::util::qr::ErrorCode::Image);
+    }
+
     #[test]
     fn test_try_default_decoder() {
         let result = try_default_decoder();
@@ -396,4 +2206,110 @@ mod tests {
         // Verify it returns the same type as default_decoder_with_info
         let _decoder: Decoder<DynamicImage, GrayImage, (String, QRInfo)> = result.expect("Should build decoder");
     }
+
+    #[test]
+    fn test_builder_fallback_detect() {
+        struct Nothing {}
+
+        impl Detect<GrayImage> for Nothing {
+            fn detect(&self, _prepared: &GrayImage) -> Vec<Location> {
+                vec![]
+            }
+        }
+
+        let img = image::open("tests/images/version1_example.jpg").unwrap();
+
+        let mut builder = default_builder();
+        builder.detect(Box::new(Nothing {}));
+        assert!(builder.build().unwrap().decode(&img).is_empty());
+
+        let mut builder = default_builder();
+        builder.detect(Box::new(Nothing {}));
+        builder.fallback_detect(Box::new(Nothing {}));
+        builder.fallback_detect(Box::new(LineScan::new()));
+        let (results, metrics) = builder.build().unwrap().decode_with_metrics(&img);
+        assert_eq!(results, vec![Ok(String::from("01234567"))]);
+
+        // Both fallbacks ran, only the last one read any pixels
+        assert_eq!(metrics.retries, 2);
+        assert!(metrics.detect_pixels > 0);
+    }
+
+    #[test]
+    fn test_builder_closures() {
+        let blocked_mean = BlockedMean::new(5, 7);
+        let linescan = LineScan::new();
+        let extractor = QRExtractor::new();
+        let qr = QRDecoder::new();
+
+        let mut builder: DecoderBuilder<DynamicImage, GrayImage, String> = DecoderBuilder::new();
+        builder.prepare(Box::new(move |source: &DynamicImage| blocked_mean.prepare(source)));
+        builder.detect(Box::new(move |prepared: &GrayImage| linescan.detect(prepared)));
+        builder.qr(
+            Box::new(move |prepared: &GrayImage, loc: QRLocation| extractor.extract(prepared, loc)),
+            Box::new(move |data: Result<QRData, QRError>| qr.decode(data).map(|text| format!("[{text}]"))),
+        );
+
+        let img = image::open("tests/images/version1_example.jpg").unwrap();
+        assert_eq!(builder.build().unwrap().decode(&img), vec![Ok(String::from("[01234567]"))]);
+    }
+
+    #[test]
+    fn test_decode_with_error_locations() {
+        let img = image::open("tests/images/version1_example.jpg").unwrap();
+        let refuse = |_: Result<QRData, QRError>| -> Result<String, QRError> {
+            Err(QRError::new("refused"))
+        };
+
+        let decoder = default_builder().with_qr(Box::new(QRExtractor::new()), Box::new(refuse)).build().unwrap();
+        let results = decoder.decode_with_error_locations(&img);
+        assert_eq!(results.len(), 1);
+        let error = results[0].as_ref().unwrap_err();
+        assert_eq!((error.stage, error.error.msg.as_str()), (Stage::Decode, "refused"));
+        let detected = LineScan::new().detect(&decoder.prepare_only(&img));
+        let Location::QR(expected) = &detected[0];
+        let center = error.location.as_ref().unwrap().center();
+        assert_eq!((center.x, center.y), (expected.center().x, expected.center().y));
+        assert!(error.to_string().starts_with("Decode failed for the code at ("), "{}", error);
+
+        let unsampled = |_: &GrayImage, _: QRLocation| -> Result<QRData, QRError> {
+            Err(QRError::new("unsampled"))
+        };
+        let decoder = default_builder().with_qr(Box::new(unsampled), Box::new(QRDecoder::new())).build().unwrap();
+        let results = decoder.decode_with_error_locations(&img);
+        assert_eq!(results[0].as_ref().unwrap_err().stage, Stage::Extract);
+
+        let mut db = default_builder();
+        db.max_codes(0);
+        let results = db.build().unwrap().decode_with_error_locations(&img);
+        let error = results[0].as_ref().unwrap_err();
+        assert_eq!(results.len(), 1);
+        assert_eq!((error.stage, error.location.is_none()), (Stage::Detect, true));
+        assert!(error.error.is_resource_limit());
+    }
+
+    #[test]
+    fn test_builder_fallback_decode() {
+        struct Refuse {}
+
+        impl Decode<QRData, String, QRError> for Refuse {
+            fn decode(&self, data: Result<QRData, QRError>) -> Result<String, QRError> {
+                data?;
+                Err(QRError::new("refused"))
+            }
+        }
+
+        let img = image::open("tests/images/version1_example.jpg").unwrap();
+
+        let mut builder = default_builder();
+        builder.qr(Box::new(QRExtractor::new()), Box::new(Refuse {}));
+        builder.fallback_decode(Box::new(QRDecoder::new()));
+        assert_eq!(builder.build().unwrap().decode(&img), vec![Ok(String::from("01234567"))]);
+
+        let mut builder = default_builder();
+        builder.qr(Box::new(QRExtractor::new()), Box::new(Refuse {}));
+        builder.fallback_decode(Box::new(Refuse {}));
+        let error = QRError::new("All 2 decoders failed: 1) refused; 2) refused");
+        assert_eq!(builder.build().unwrap().decode(&img), vec![Err(error)]);
+    }
 }
@@ -0,0 +1,147 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageBuffer};
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+use tiff::ColorType;
+
+use super::Decoder;
+
+use crate::util::qr::QRError;
+
+impl<PREPD, RESULT> Decoder<DynamicImage, PREPD, RESULT> {
+    /// Decode every page of an encoded TIFF, such as a scanned document with several pages
+    ///
+    /// The pages are read and decoded one after the other, so only a single page is in memory at a time. There is a
+    /// result for every page, in the order of the file, with the results of its codes in reading order the same as
+    /// for [`decode`](#method.decode). Pages without any codes have an empty `results`. A TIFF with a single page gives
+    /// a single result.
+    ///
+    /// Pages with 8 or 16 bits per sample are supported, in grayscale, RGB or RGBA, uncompressed or with LZW, PackBits
+    /// or JPEG compression. Black and white scans with CCITT compression are not.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QRError` if the bytes are not a TIFF, a page could not be read, or a page has more pixels than
+    /// [`DecoderBuilder::max_pixels`] allows. Errors for individual codes are returned inside the results of the page.
+    ///
+    /// [`DecoderBuilder::max_pixels`]: struct.DecoderBuilder.html#method.max_pixels
+    pub fn decode_tiff_pages(&self, bytes: &[u8]) -> Result<Vec<PageResult<RESULT>>, QRError> {
+        let unreadable =
+            |page: usize, e: tiff::TiffError| QRError::image(format!("Unable to read TIFF page {page}: {e}"));
+
+        let mut tiff = TiffDecoder::new(Cursor::new(bytes)).map_err(|e| unreadable(0, e))?;
+        let mut pages = vec![];
+
+        loop {
+            let page = pages.len();
+
+            let (width, height) = tiff.dimensions().map_err(|e| unreadable(page, e))?;
+            let pixels = u64::from(width) * u64::from(height);
+            if pixels > self.max_pixels {
+                let limit = format!(
+                    "TIFF page {page} of {width}x{height} pixels is larger than the maximum of {}",
+                    self.max_pixels
+                );
+                return Err(QRError::resource_limit(&limit));
+            }
+
+            let image = read_page(&mut tiff, width, height).map_err(|e| unreadable(page, e))?;
+            pages.push(PageResult {
+                page,
+                results: self.decode(&image),
+            });
+
+            if !tiff.more_images() {
+                return Ok(pages);
+            }
+            tiff.next_image().map_err(|e| unreadable(page + 1, e))?;
+        }
+    }
+}
+
+/// The results of the codes on a single page of a document
+#[derive(Debug, PartialEq)]
+pub struct PageResult<RESULT> {
+    /// Index of the page, counting from 0
+    pub page: usize,
+    /// The results of the codes on the page, in reading order
+    pub results: Vec<Result<RESULT, QRError>>,
+}
+
+fn read_page(tiff: &mut TiffDecoder<Cursor<&[u8]>>, width: u32, height: u32) -> tiff::TiffResult<DynamicImage> {
+    let color = tiff.colortype()?;
+
+    let image = match (color, tiff.read_image()?) {
+        (ColorType::Gray(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::GrayA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLumaA16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(data)) => {
+            ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgba16)
+        }
+        _ => None,
+    };
+
+    image.ok_or(tiff::TiffError::UnsupportedError(
+        tiff::TiffUnsupportedError::UnsupportedColorType(color),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    fn two_pages() -> Vec<u8> {
+        let mut bytes = Cursor::new(vec![]);
+        let mut tiff = TiffEncoder::new(&mut bytes).unwrap();
+        tiff.write_image::<colortype::Gray8>(30, 20, &[255; 600]).unwrap();
+        tiff.write_image::<colortype::RGB8>(10, 10, &[255; 300]).unwrap();
+
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_decode_tiff_pages() {
+        let pages = crate::default_decoder().decode_tiff_pages(&two_pages()).unwrap();
+
+        assert_eq!(
+            pages,
+            vec![
+                PageResult { page: 0, results: vec![] },
+                PageResult { page: 1, results: vec![] }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_tiff_pages_errors() {
+        let decoder = crate::default_decoder();
+        let error = decoder.decode_tiff_pages(b"not a tiff").unwrap_err();
+        assert!(error.msg.starts_with("Unable to read TIFF page 0: "), "{}", error.msg);
+
+        let mut db = crate::default_builder();
+        db.max_pixels(500);
+        let error = db.build().unwrap().decode_tiff_pages(&two_pages()).unwrap_err();
+        assert!(error.is_resource_limit());
+        assert!(error.msg.contains("TIFF page 0 of 30x20 pixels"));
+    }
+}
@@ -0,0 +1,188 @@
+#[cfg(feature = "image")]
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+
+use super::Decoder;
+
+use crate::util::qr::QRError;
+use crate::util::{LumaImage, LumaSource};
+
+/// Shrunk to half and enlarged to twice the size, as tried by `decode_rescaled`
+const SCALES: [f64; 2] = [0.5, 2.0];
+
+impl<IMG, PREPD, RESULT> Decoder<IMG, PREPD, RESULT> {
+    // Decode the source, and when no code decodes, the source rescaled by every scale in turn until one does
+    fn decode_rescaled_with(
+        &self,
+        source: &IMG,
+        scales: &[f64],
+        rescale: impl Fn(&IMG, f64) -> Option<IMG>,
+    ) -> Vec<Result<RESULT, QRError>> {
+        let results = self.decode(source);
+        if results.iter().any(Result::is_ok) {
+            return results;
+        }
+
+        for scale in scales {
+            if let Some(rescaled) = rescale(source, *scale) {
+                let rescaled_results = self.decode(&rescaled);
+                if rescaled_results.iter().any(Result::is_ok) {
+                    return rescaled_results;
+                }
+            }
+        }
+
+        results
+    }
+}
+
+impl<PREPD, RESULT> Decoder<LumaImage, PREPD, RESULT> {
+    /// Decode the same way as [`decode`](#method.decode), and when no code decodes, again at half and at twice the
+    /// size
+    ///
+    /// The detector finds codes best when their modules are a few pixels wide. A code with modules of a single pixel
+    /// often decodes at twice the size, and a code filling a photo with modules of dozens of pixels at half the size.
+    /// Both are tried only when the image as it is gives no result at all, so images that decode cost no more. The
+    /// results are those of the first size at which a code decodes, or else those of the original image.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// assert!(decoder.decode_rescaled(&source).is_empty());
+    /// ```
+    pub fn decode_rescaled(&self, source: &LumaImage) -> Vec<Result<RESULT, QRError>> {
+        self.decode_with_scales(source, &SCALES)
+    }
+
+    /// Decode the same way as [`decode_rescaled`](#method.decode_rescaled), trying these scales in order instead
+    ///
+    /// A scale of 0.5 is half the width and height, 2.0 twice. Scales that are not positive, or would leave an empty
+    /// image, are skipped. The limits, time budget and observer of the decoder apply to every size separately, and an
+    /// enlarged image takes memory for all of its pixels.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let source = LumaImage::from_fn(40, 40, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// assert!(decoder.decode_with_scales(&source, &[2.0, 3.0, 0.5]).is_empty());
+    /// ```
+    pub fn decode_with_scales(&self, source: &LumaImage, scales: &[f64]) -> Vec<Result<RESULT, QRError>> {
+        self.decode_rescaled_with(source, scales, rescale)
+    }
+}
+
+#[cfg(feature = "image")]
+impl<PREPD, RESULT> Decoder<DynamicImage, PREPD, RESULT> {
+    /// Decode the same way as [`decode`](#method.decode), and when no code decodes, again at half and at twice the
+    /// size
+    ///
+    /// The same as for a [`LumaImage`](util/struct.LumaImage.html) source, the image is resized with a linear
+    /// filter.
+    pub fn decode_rescaled(&self, source: &DynamicImage) -> Vec<Result<RESULT, QRError>> {
+        self.decode_with_scales(source, &SCALES)
+    }
+
+    /// Decode the same way as [`decode_rescaled`](#method.decode_rescaled), trying these scales in order instead
+    ///
+    /// Scales at which the image would have more pixels than [`DecoderBuilder::max_pixels`] allows are skipped as
+    /// well.
+    ///
+    /// [`DecoderBuilder::max_pixels`]: struct.DecoderBuilder.html#method.max_pixels
+    pub fn decode_with_scales(&self, source: &DynamicImage, scales: &[f64]) -> Vec<Result<RESULT, QRError>> {
+        self.decode_rescaled_with(source, scales, |source, scale| {
+            let (width, height) = scaled_dimensions(source.width(), source.height(), scale)?;
+            self.check_dimensions(width, height).ok()?;
+
+            Some(source.resize_exact(width, height, FilterType::Triangle))
+        })
+    }
+}
+
+// The dimensions at this scale, `None` for an empty image
+fn scaled_dimensions(width: u32, height: u32, scale: f64) -> Option<(u32, u32)> {
+    if !(scale.is_finite() && scale > 0.0) {
+        return None;
+    }
+
+    let scaled = |length: u32| (f64::from(length) * scale).round() as u32;
+    match (scaled(width), scaled(height)) {
+        (0, _) | (_, 0) => None,
+        dimensions => Some(dimensions),
+    }
+}
+
+// The average of the pixels covered when shrinking, interpolated between the nearest four when enlarging
+fn rescale(source: &LumaImage, scale: f64) -> Option<LumaImage> {
+    let (width, height) = source.luma_dimensions();
+    let (scaled_width, scaled_height) = scaled_dimensions(width, height, scale)?;
+    let (x_scale, y_scale) = (f64::from(width) / f64::from(scaled_width), f64::from(height) / f64::from(scaled_height));
+
+    if scale < 1.0 {
+        // The source pixels from the start of the scaled pixel up to the start of the next one, at least one
+        let span = |i: u32, pixel_scale: f64, length: u32| {
+            let start = ((f64::from(i) * pixel_scale) as u32).min(length - 1);
+            let end = ((f64::from(i + 1) * pixel_scale) as u32).clamp(start + 1, length);
+            start..end
+        };
+
+        return Some(LumaImage::from_fn(scaled_width, scaled_height, |x, y| {
+            let (xs, ys) = (span(x, x_scale, width), span(y, y_scale, height));
+            let count = xs.len() as u32 * ys.len() as u32;
+            let sum: u32 = ys
+                .flat_map(|sy| &source.luma_row(sy)[xs.start as usize..xs.end as usize])
+                .map(|p| u32::from(*p))
+                .sum();
+            ((sum + count / 2) / count) as u8
+        }));
+    }
+
+    // The position in the source of the center of the scaled pixel, with the nearest pixel on either side of it
+    let position = |i: u32, pixel_scale: f64, length: u32| {
+        let at = ((f64::from(i) + 0.5) * pixel_scale - 0.5).clamp(0.0, f64::from(length - 1));
+        let before = at.floor() as u32;
+        (before, (before + 1).min(length - 1), at - f64::from(before))
+    };
+
+    Some(LumaImage::from_fn(scaled_width, scaled_height, |x, y| {
+        let (x0, x1, fx) = position(x, x_scale, width);
+        let (y0, y1, fy) = position(y, y_scale, height);
+        let row = |sy: u32| {
+            let row = source.luma_row(sy);
+            f64::from(row[x0 as usize]) * (1.0 - fx) + f64::from(row[x1 as usize]) * fx
+        };
+
+        (row(y0) * (1.0 - fy) + row(y1) * fy).round() as u8
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rescale() {
+        // Columns of 0, 100, 200 and 250
+        let source = LumaImage::from_fn(4, 2, |x, _| [0, 100, 200, 250][x as usize]);
+
+        let half = rescale(&source, 0.5).unwrap();
+        assert_eq!(half.luma_dimensions(), (2, 1));
+        assert_eq!((half.luma(0, 0), half.luma(1, 0)), (50, 225));
+
+        let double = rescale(&source, 2.0).unwrap();
+        assert_eq!(double.luma_dimensions(), (8, 4));
+        let row: Vec<u8> = (0..8).map(|x| double.luma(x, 3)).collect();
+        assert_eq!(row, vec![0, 25, 75, 125, 175, 213, 238, 250]);
+
+        assert!(rescale(&source, 0.1).is_none());
+        assert!(rescale(&source, -1.0).is_none());
+        assert!(rescale(&source, f64::NAN).is_none());
+    }
+}
@@ -0,0 +1,187 @@
+use super::{same_code, shifted, Decoder};
+
+use crate::detect::Location;
+use crate::util::qr::{QRError, QRLocation};
+use crate::util::LumaImage;
+
+impl<PREPD, RESULT> Decoder<LumaImage, PREPD, RESULT> {
+    /// Decode an image that arrives row by row, for example from a line scan camera or a streaming JPEG decoder
+    ///
+    /// Rows of `width` pixels are fed to the returned [`Scanlines`] as they arrive. Detection runs again every few rows
+    /// on what was received so far, and a code is decoded as soon as all of it is in, instead of waiting for the full
+    /// frame.
+    ///
+    /// [`Scanlines`]: struct.Scanlines.html
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// let decoder = bardecoder::default_luma_decoder();
+    /// let mut scanlines = decoder.scanlines(80);
+    ///
+    /// for _ in 0..200 {
+    ///     let decoded = scanlines.push_row(&[255; 80]).unwrap();
+    ///     assert!(decoded.is_empty());
+    /// }
+    ///
+    /// assert!(scanlines.finish().is_empty());
+    /// ```
+    pub fn scanlines(&self, width: u32) -> Scanlines<'_, PREPD, RESULT> {
+        Scanlines {
+            decoder: self,
+            width,
+            rows: vec![],
+            first_row: 0,
+            since_detect: 0,
+            interval: 32,
+            window: 1024,
+            seen: vec![],
+        }
+    }
+}
+
+/// Push based decoding of an image received row by row, see [`Decoder::scanlines`]
+///
+/// Every code is reported once, from the [`push_row`] call that completed it or from [`finish`]. Only the last
+/// [`window`] rows are kept, so the stream can be endless, as it is for a line scan camera, but codes taller than that
+/// are not found.
+///
+/// The limits and the observer of the decoder apply to every detection, so the observer hears about a code that is
+/// still incomplete every time detection runs.
+///
+/// [`Decoder::scanlines`]: struct.Decoder.html#method.scanlines
+/// [`push_row`]: #method.push_row
+/// [`finish`]: #method.finish
+/// [`window`]: #method.window
+pub struct Scanlines<'a, PREPD, RESULT> {
+    decoder: &'a Decoder<LumaImage, PREPD, RESULT>,
+    width: u32,
+    rows: Vec<u8>,
+    first_row: u32,
+    since_detect: u32,
+    interval: u32,
+    window: u32,
+    // Codes already reported, with their position in the whole stream
+    seen: Vec<QRLocation>,
+}
+
+impl<'a, PREPD, RESULT> Scanlines<'a, PREPD, RESULT> {
+    /// Run detection every `interval` rows, 32 by default
+    ///
+    /// A shorter interval reports codes sooner after their last row arrives, at the cost of detecting more often.
+    pub fn interval(&mut self, interval: u32) -> &mut Scanlines<'a, PREPD, RESULT> {
+        self.interval = interval.max(1);
+        self
+    }
+
+    /// Keep the last `window` rows for detection, 1024 by default
+    ///
+    /// This bounds the memory and the time every detection takes. Codes taller than the window are not found.
+    pub fn window(&mut self, window: u32) -> &mut Scanlines<'a, PREPD, RESULT> {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Add the next row of the image, returning the codes it completed
+    ///
+    /// Only the first `width` pixels of the row are used.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QRError` if the row is shorter than the width
+    pub fn push_row(&mut self, row: &[u8]) -> Result<Vec<Result<RESULT, QRError>>, QRError> {
+        let width = self.width as usize;
+        if row.len() < width {
+            return Err(QRError::invalid_input(format!(
+                "Row of {} pixels is shorter than the width of {}",
+                row.len(),
+                width
+            )));
+        }
+
+        self.rows.extend_from_slice(&row[..width]);
+        self.since_detect += 1;
+
+        if self.since_detect < self.interval {
+            return Ok(vec![]);
+        }
+
+        let decoded = self.scan(false);
+        self.trim();
+
+        Ok(decoded)
+    }
+
+    /// End of the image, returning the codes not reported yet
+    ///
+    /// Codes touching the last row are decoded as they are.
+    pub fn finish(mut self) -> Vec<Result<RESULT, QRError>> {
+        self.scan(true)
+    }
+
+    // Rows currently kept
+    fn height(&self) -> u32 {
+        match self.width {
+            0 => 0,
+            width => (self.rows.len() / width as usize) as u32,
+        }
+    }
+
+    // Detect on the rows kept, decoding the codes that are complete and were not reported yet
+    fn scan(&mut self, last: bool) -> Vec<Result<RESULT, QRError>> {
+        self.since_detect = 0;
+
+        let height = self.height();
+        if height == 0 {
+            return vec![];
+        }
+
+        let rows = std::mem::take(&mut self.rows);
+        let source = LumaImage::new(self.width, height, rows).expect("Rows are kept in full");
+
+        let prepared = self.decoder.prepare_observed(&source);
+        let (locations, exceeded) = self.decoder.locations(&prepared);
+        let mut all_decoded = vec![];
+
+        for location in locations {
+            match location {
+                Location::QR(qrloc) => {
+                    // At least a quiet zone of 4 modules below the code, or thresholding is still off at its edge
+                    let bottom = qrloc.corners().iter().fold(0.0f64, |bottom, corner| bottom.max(corner.y));
+                    if !last && bottom + 4.0 * qrloc.module_size >= f64::from(height) {
+                        continue;
+                    }
+
+                    let global = shifted(&qrloc, 0.0, f64::from(self.first_row));
+                    if self.seen.iter().any(|seen| same_code(seen, &global)) {
+                        continue;
+                    }
+                    self.seen.push(global);
+
+                    let extracted = self.decoder.extract_observed(&prepared, qrloc);
+                    all_decoded.push(self.decoder.decode_observed(extracted));
+                }
+            }
+        }
+
+        self.rows = source.into_raw();
+
+        all_decoded.extend(exceeded.into_iter().map(Err));
+        all_decoded
+    }
+
+    // Drop the rows outside of the window, and the reported codes no longer in it
+    fn trim(&mut self) {
+        let height = self.height();
+        if height <= self.window {
+            return;
+        }
+
+        let dropped = height - self.window;
+        self.rows.drain(..dropped as usize * self.width as usize);
+        self.first_row += dropped;
+
+        let first_row = f64::from(self.first_row);
+        self.seen.retain(|seen| seen.corners().iter().any(|corner| corner.y >= first_row));
+    }
+}
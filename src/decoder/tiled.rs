@@ -0,0 +1,109 @@
+use super::{reading_order, same_code, shifted, Decoder};
+
+use crate::detect::Location;
+use crate::util::qr::{QRError, QRLocation};
+use crate::util::tile::TileSource;
+use crate::util::LumaImage;
+
+impl<PREPD, RESULT> Decoder<LumaImage, PREPD, RESULT> {
+    /// Decode an image a tile at a time, for scans of hundreds of megapixels that should not be loaded in full
+    ///
+    /// The image is read in overlapping squares of `tile` pixels, which are prepared, detected and decoded one after
+    /// the other, so only a single tile is in memory at a time. A code is decoded from the first tile that holds all of
+    /// it and its quiet zone, and reported once. For every code to be found, `overlap` needs to be at least the size of
+    /// the largest code in pixels, plus 8 modules.
+    ///
+    /// The limits and the observer of the decoder apply to every tile separately. Results are in reading order, the
+    /// same as for [`decode`](#method.decode).
+    ///
+    /// # Errors
+    ///
+    /// Returns `QRError` if a tile could not be read, or `overlap` is not smaller than `tile`. Errors for individual
+    /// codes in the image are returned inside the `Vec`.
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::LumaImage;
+    ///
+    /// let scan = LumaImage::from_fn(3000, 2000, |_, _| 255);
+    /// let decoder = bardecoder::default_luma_decoder();
+    ///
+    /// assert!(decoder.decode_tiled(&scan, 1024, 256).unwrap().is_empty());
+    /// ```
+    pub fn decode_tiled<T: TileSource + ?Sized>(
+        &self,
+        source: &T,
+        tile: u32,
+        overlap: u32,
+    ) -> Result<Vec<Result<RESULT, QRError>>, QRError> {
+        if overlap >= tile {
+            return Err(QRError::invalid_input(format!(
+                "Overlap of {overlap} pixels is not smaller than the tile size of {tile}"
+            )));
+        }
+
+        let (width, height) = source.tile_dimensions();
+        let mut seen: Vec<(QRLocation, Result<RESULT, QRError>)> = vec![];
+        let mut dropped = vec![];
+        let mut buffer = vec![];
+
+        for y in starts(height, tile, overlap) {
+            for x in starts(width, tile, overlap) {
+                let (tile_width, tile_height) = (tile.min(width - x), tile.min(height - y));
+
+                buffer.resize(tile_width as usize * tile_height as usize, 0);
+                source.read_tile(x, y, tile_width, tile_height, &mut buffer)?;
+
+                let part = LumaImage::new(tile_width, tile_height, std::mem::take(&mut buffer)).expect("Tile is sized");
+                let prepared = self.prepare_observed(&part);
+                let (locations, too_many) = self.locations(&prepared);
+
+                for location in locations {
+                    match location {
+                        Location::QR(qrloc) => {
+                            // Codes cut off by the edge of the tile are complete in the next one
+                            let margin = 4.0 * qrloc.module_size;
+                            let inside = qrloc.corners().iter().all(|corner| {
+                                (x + tile_width == width || corner.x + margin < f64::from(tile_width))
+                                    && (y + tile_height == height || corner.y + margin < f64::from(tile_height))
+                                    && (x == 0 || corner.x >= margin)
+                                    && (y == 0 || corner.y >= margin)
+                            });
+
+                            let global = shifted(&qrloc, f64::from(x), f64::from(y));
+                            if !inside || seen.iter().any(|(seen, _)| same_code(seen, &global)) {
+                                continue;
+                            }
+
+                            let extracted = self.extract_observed(&prepared, qrloc);
+                            seen.push((global, self.decode_observed(extracted)));
+                        }
+                    }
+                }
+
+                dropped.extend(too_many);
+                buffer = part.into_raw();
+            }
+        }
+
+        reading_order(&mut seen, |(qrloc, _)| qrloc);
+
+        let mut all_decoded: Vec<Result<RESULT, QRError>> = seen.into_iter().map(|(_, decoded)| decoded).collect();
+        all_decoded.extend(dropped.into_iter().map(Err));
+
+        Ok(all_decoded)
+    }
+}
+
+// Offsets of the tiles along one side, the last one ending at the edge
+fn starts(length: u32, tile: u32, overlap: u32) -> impl Iterator<Item = u32> {
+    let step = tile - overlap;
+    let last = length.saturating_sub(tile);
+
+    (0..)
+        .map(move |i| i * step)
+        .take_while(move |start| *start < last + step)
+        .map(move |start| start.min(last))
+        .take_while(move |_| length > 0)
+}
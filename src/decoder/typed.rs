@@ -0,0 +1,163 @@
+use std::marker::PhantomData;
+
+use super::{Decoder, DecoderBuilder};
+
+use crate::decode::Decode;
+use crate::detect::Detect;
+use crate::extract::Extract;
+use crate::prepare::Prepare;
+use crate::util::qr::{QRData, QRError, QRLocation};
+
+/// State of a required component of a [`TypedDecoderBuilder`] that has not been provided yet
+///
+/// [`TypedDecoderBuilder`]: struct.TypedDecoderBuilder.html
+#[derive(Debug)]
+pub struct ComponentMissing;
+
+/// State of a required component of a [`TypedDecoderBuilder`] that has been provided
+///
+/// [`TypedDecoderBuilder`]: struct.TypedDecoderBuilder.html
+#[derive(Debug)]
+pub struct ComponentProvided;
+
+/// A [`DecoderBuilder`] that checks for missing components at compile time
+///
+/// The type parameters `P`, `D` and `Q` record whether the prepare, detect and QR extract and decode components have
+/// been provided. Each of [`prepare`], [`detect`] and [`qr`] takes the builder by value and returns it with that
+/// component marked as [`ComponentProvided`], and [`build`] only exists once all three are. It returns the
+/// `Decoder` itself, as none of the errors of [`DecoderBuilder::build`] can happen.
+///
+/// Everything that is optional, such as fallbacks, limits and the observer, is set through [`options`].
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::QRDecoder;
+/// use bardecoder::detect::LineScan;
+/// use bardecoder::extract::QRExtractor;
+/// use bardecoder::prepare::BlockedMean;
+/// use bardecoder::util::LumaImage;
+/// use bardecoder::TypedDecoderBuilder;
+///
+/// let mut builder = TypedDecoderBuilder::<LumaImage, LumaImage, String>::new()
+///     .prepare(Box::new(BlockedMean::new(5, 7)))
+///     .detect(Box::new(LineScan::new()))
+///     .qr(Box::new(QRExtractor::new()), Box::new(QRDecoder::new()));
+/// builder.options().max_codes(10);
+///
+/// let decoder = builder.build();
+/// ```
+///
+/// Leaving out a component does not compile:
+/// ```compile_fail
+/// # extern crate bardecoder;
+/// # use bardecoder::detect::LineScan;
+/// # use bardecoder::util::LumaImage;
+/// # use bardecoder::TypedDecoderBuilder;
+/// let decoder = TypedDecoderBuilder::<LumaImage, LumaImage, String>::new()
+///     .detect(Box::new(LineScan::new()))
+///     .build();
+/// ```
+///
+/// [`DecoderBuilder`]: struct.DecoderBuilder.html
+/// [`DecoderBuilder::build`]: struct.DecoderBuilder.html#method.build
+/// [`ComponentProvided`]: struct.ComponentProvided.html
+/// [`prepare`]: #method.prepare
+/// [`detect`]: #method.detect
+/// [`qr`]: #method.qr
+/// [`build`]: #method.build
+/// [`options`]: #method.options
+pub struct TypedDecoderBuilder<IMG, PREPD, RESULT, P = ComponentMissing, D = ComponentMissing, Q = ComponentMissing> {
+    builder: DecoderBuilder<IMG, PREPD, RESULT>,
+    state: PhantomData<(P, D, Q)>,
+}
+
+impl<IMG, PREPD, RESULT> TypedDecoderBuilder<IMG, PREPD, RESULT> {
+    /// Constructor; no components provided yet
+    pub fn new() -> TypedDecoderBuilder<IMG, PREPD, RESULT> {
+        TypedDecoderBuilder {
+            builder: DecoderBuilder::new(),
+            state: PhantomData,
+        }
+    }
+}
+
+impl<IMG, PREPD, RESULT, P, D, Q> TypedDecoderBuilder<IMG, PREPD, RESULT, P, D, Q> {
+    /// Set the prepare implementation for this Decoder
+    pub fn prepare(
+        mut self,
+        prepare: Box<dyn Prepare<IMG, PREPD> + Send + Sync>,
+    ) -> TypedDecoderBuilder<IMG, PREPD, RESULT, ComponentProvided, D, Q> {
+        self.builder.prepare(prepare);
+        self.into_state()
+    }
+
+    /// Set the detect implementation for this Decoder
+    pub fn detect(
+        mut self,
+        detect: Box<dyn Detect<PREPD> + Send + Sync>,
+    ) -> TypedDecoderBuilder<IMG, PREPD, RESULT, P, ComponentProvided, Q> {
+        self.builder.detect(detect);
+        self.into_state()
+    }
+
+    /// Set the extract and decode implementations for QR codes for this Decoder
+    pub fn qr(
+        mut self,
+        extract: Box<dyn Extract<PREPD, QRLocation, QRData, QRError> + Send + Sync>,
+        decode: Box<dyn Decode<QRData, RESULT, QRError> + Send + Sync>,
+    ) -> TypedDecoderBuilder<IMG, PREPD, RESULT, P, D, ComponentProvided> {
+        self.builder.qr(extract, decode);
+        self.into_state()
+    }
+
+    /// The builder underneath, to set the optional parts of the Decoder
+    ///
+    /// Set the required components with the methods of this type instead, so they are recorded in its type.
+    pub fn options(&mut self) -> &mut DecoderBuilder<IMG, PREPD, RESULT> {
+        &mut self.builder
+    }
+
+    fn into_state<P2, D2, Q2>(self) -> TypedDecoderBuilder<IMG, PREPD, RESULT, P2, D2, Q2> {
+        TypedDecoderBuilder {
+            builder: self.builder,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<IMG, PREPD, RESULT>
+    TypedDecoderBuilder<IMG, PREPD, RESULT, ComponentProvided, ComponentProvided, ComponentProvided>
+{
+    /// Build actual Decoder
+    pub fn build(self) -> Decoder<IMG, PREPD, RESULT> {
+        self.builder
+            .build()
+            .expect("All required components are provided, the type says so")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::decode::QRDecoder;
+    use crate::detect::LineScan;
+    use crate::extract::QRExtractor;
+    use crate::prepare::BlockedMean;
+    use crate::util::LumaImage;
+
+    #[test]
+    fn test_typed_builder() {
+        // The components can be provided in any order
+        let mut builder = TypedDecoderBuilder::<LumaImage, LumaImage, String>::new()
+            .qr(Box::new(QRExtractor::new()), Box::new(QRDecoder::new()))
+            .detect(Box::new(LineScan::new()))
+            .prepare(Box::new(BlockedMean::new(5, 7)));
+        builder.options().max_codes(0);
+
+        let decoder = builder.build();
+        assert!(decoder.decode(&LumaImage::from_fn(40, 40, |_, _| 255)).is_empty());
+        assert_eq!(decoder.max_codes, 0);
+    }
+}
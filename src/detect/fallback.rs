@@ -0,0 +1,108 @@
+use super::{Detect, Location};
+
+use crate::util::metrics;
+
+/// Run detectors in order until one of them finds something
+///
+/// This lets a fast detector handle the common case, with a slower but more robust one to fall back on for images
+/// where it finds nothing. The locations of the first detector that finds any are returned as they are, so the later
+/// ones do not run at all for those images.
+///
+/// [`DecoderBuilder::fallback_detect`] sets one up from the detect implementations of the builder.
+///
+/// [`DecoderBuilder::fallback_detect`]: ../struct.DecoderBuilder.html#method.fallback_detect
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::detect::{Detect, Fallback, LineScan};
+/// use bardecoder::util::LumaImage;
+///
+/// let detect = Fallback::new(vec![Box::new(LineScan::new()), Box::new(LineScan::new())]);
+///
+/// let blank = LumaImage::from_fn(100, 100, |_, _| 255);
+/// assert!(detect.detect(&blank).is_empty());
+/// ```
+pub struct Fallback<PREPD> {
+    detectors: Vec<Box<dyn Detect<PREPD> + Send + Sync>>,
+}
+
+impl<PREPD> Fallback<PREPD> {
+    /// Construct a new Fallback, running the detectors in the order provided
+    pub fn new(detectors: Vec<Box<dyn Detect<PREPD> + Send + Sync>>) -> Fallback<PREPD> {
+        Fallback { detectors }
+    }
+}
+
+impl<PREPD> Detect<PREPD> for Fallback<PREPD> {
+    fn detect(&self, prepared: &PREPD) -> Vec<Location> {
+        for (i, detector) in self.detectors.iter().enumerate() {
+            if i > 0 {
+                metrics::count(|metrics| metrics.retries += 1);
+            }
+
+            let locations = detector.detect(prepared);
+
+            if !locations.is_empty() {
+                return locations;
+            }
+        }
+
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::util::qr::QRLocation;
+    use crate::util::Point;
+
+    // Finds `found` codes, counting how often it ran
+    struct Fixed {
+        found: usize,
+        runs: Arc<AtomicUsize>,
+    }
+
+    impl Detect<()> for Fixed {
+        fn detect(&self, _prepared: &()) -> Vec<Location> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+
+            let point = Point { x: 0.0, y: 0.0 };
+            (0..self.found)
+                .map(|_| {
+                    Location::QR(QRLocation {
+                        top_left: point,
+                        top_right: point,
+                        bottom_left: point,
+                        module_size: 1.0,
+                        version: 1,
+                    })
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_fallback() {
+        let runs: Vec<Arc<AtomicUsize>> = (0..3).map(|_| Arc::new(AtomicUsize::new(0))).collect();
+        let fixed = |found: usize, runs: &Arc<AtomicUsize>| -> Box<dyn Detect<()> + Send + Sync> {
+            Box::new(Fixed {
+                found,
+                runs: Arc::clone(runs),
+            })
+        };
+
+        let detect = Fallback::new(vec![fixed(0, &runs[0]), fixed(2, &runs[1]), fixed(1, &runs[2])]);
+        assert_eq!(detect.detect(&()).len(), 2);
+
+        let counts: Vec<usize> = runs.iter().map(|runs| runs.load(Ordering::SeqCst)).collect();
+        assert_eq!(counts, vec![1, 1, 0]);
+
+        assert!(Fallback::<()>::new(vec![]).detect(&()).is_empty());
+    }
+}
@@ -4,10 +4,11 @@ use std::cmp::min;
 use std::iter::repeat;
 use std::iter::Iterator;
 
+use crate::util::limits::{self, Limit};
+use crate::util::metrics::{self, Counted};
 use crate::util::qr::QRLocation;
-use crate::util::Point;
-
-use image::{GrayImage, Pixel};
+use crate::util::trace::{self, TraceEvent};
+use crate::util::{simd, LumaSource, Point};
 
 #[cfg(feature = "debug-images")]
 use image::{DynamicImage, Rgb};
@@ -21,103 +22,193 @@ use std::{env::temp_dir, fs::create_dir_all};
 /// 1. Scan line by line horizontally for possible QR Finder patterns (the three squares)
 /// 2. If a possible pattern is found, check vertically and diagonally to confirm it is indeed a pattern
 /// 3. Try to find combinations of three patterns that are perpendicular and with similar distance that form a complete QR Code
-pub struct LineScan {}
+///
+/// How strict every step is can be set with [`with_tolerances`](#method.with_tolerances).
+pub struct LineScan {
+    tolerances: LineScanTolerances,
+}
 
 impl LineScan {
     /// Constuct a new LineScan
     pub fn new() -> LineScan {
-        LineScan {}
+        LineScan::with_tolerances(LineScanTolerances::default())
     }
+
+    /// Construct a new LineScan with other tolerances than the default ones
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::detect::{LineScan, LineScanTolerances};
+    ///
+    /// // Blurry images from a cheap camera, at the cost of more false positives
+    /// let linescan = LineScan::with_tolerances(LineScanTolerances {
+    ///     run_ratio: 0.8,
+    ///     side_length: 0.1,
+    ///     ..LineScanTolerances::default()
+    /// });
+    /// ```
+    pub fn with_tolerances(tolerances: LineScanTolerances) -> LineScan {
+        LineScan { tolerances }
+    }
+}
+
+/// How far the finder patterns a [`LineScan`] detects can be off, see [`LineScan::with_tolerances`]
+///
+/// Larger values find codes in blurry, noisy or skewed images that the defaults miss, but also let more of the image
+/// pass for finder patterns, which are all grouped and extracted. Smaller values do the opposite. The defaults are
+/// what `LineScan::new` uses.
+///
+/// [`LineScan`]: struct.LineScan.html
+/// [`LineScan::with_tolerances`]: struct.LineScan.html#method.with_tolerances
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LineScanTolerances {
+    /// How far every run of dark or light pixels across a finder pattern can be off its 1-1-3-1-1 ratio, as a
+    /// fraction of the module size. Defaults to 2/3
+    pub run_ratio: f64,
+    /// Fewest pixels across the five runs of a finder pattern together, with 7 being one pixel per module. Defaults
+    /// to 7
+    pub min_run_length: u32,
+    /// How much the module size found when checking a finder pattern vertically or horizontally can differ from the
+    /// size found before, as a fraction of the larger one. Defaults to 0.2
+    pub refine_module_size: f64,
+    /// How much the module sizes of the three finder patterns of a code can differ, as a fraction of the larger one.
+    /// Defaults to 0.1
+    pub group_module_size: f64,
+    /// How much the distances from the top left finder pattern to the other two can differ, as a fraction of the
+    /// larger one. Defaults to 0.06
+    pub side_length: f64,
+    /// How far the two sides can be from perpendicular, as the difference of the sine of the angle between them from
+    /// 1. Defaults to 0.05, about 18 degrees
+    pub perpendicular: f64,
 }
 
-type Refine = dyn Fn(&LineScan, &GrayImage, &Point, f64) -> Option<QRFinderPosition>;
+impl Default for LineScanTolerances {
+    fn default() -> LineScanTolerances {
+        LineScanTolerances {
+            run_ratio: 1.0 / 1.5,
+            min_run_length: 7,
+            refine_module_size: 0.2,
+            group_module_size: 0.1,
+            side_length: 0.06,
+            perpendicular: 0.05,
+        }
+    }
+}
 
-impl Detect<GrayImage> for LineScan {
-    fn detect(&self, prepared: &GrayImage) -> Vec<Location> {
+type Refine<P> = fn(&LineScan, &P, &Point, f64) -> Option<QRFinderPosition>;
+
+impl<P: LumaSource> Detect<P> for LineScan {
+    fn detect(&self, prepared: &P) -> Vec<Location> {
+        let counted = Counted::new(prepared);
+        let (locations, candidates, groups) = self.scan(&counted);
+
+        metrics::count(|metrics| {
+            metrics.detect_pixels += counted.pixels();
+            metrics.finder_candidates += candidates;
+            metrics.finder_groups += groups;
+        });
+
+        locations
+    }
+}
+
+impl LineScan {
+    // The locations found, with the number of possible finder patterns that were refined and the number of groups of
+    // three that were checked
+    fn scan<P: LumaSource>(&self, prepared: &P) -> (Vec<Location>, u64, u64) {
         // The order of refinement is important.
         // The candidate is found in horizontal direction, so the first refinement is vertical
-        let refine_func: Vec<(Box<Refine>, f64, f64, bool)> = vec![
-            (Box::new(LineScan::refine_vertical), 0.0, 1.0, false),
-            (Box::new(LineScan::refine_horizontal), 1.0, 0.0, false),
-            (Box::new(LineScan::refine_diagonal), 1.0, 1.0, true),
+        let refine_func: Vec<(Refine<P>, f64, f64, bool, &'static str)> = vec![
+            (LineScan::refine_vertical, 0.0, 1.0, false, "vertical"),
+            (LineScan::refine_horizontal, 1.0, 0.0, false, "horizontal"),
+            (LineScan::refine_diagonal, 1.0, 1.0, true, "diagonal"),
         ];
 
+        let runs = RunCheck::new(&self.tolerances);
         let mut candidates: Vec<QRFinderPosition> = vec![];
-
-        let mut last_pixel = 127;
-        let mut pattern = QRFinderPattern::new();
-        'pixels: for (x, y, p) in prepared.enumerate_pixels() {
-            // Step 1
-            // A new line, construct a new QRFinderPattern
-            if x == 0 {
-                last_pixel = 127;
-                pattern = QRFinderPattern::new();
-            }
-
-            // A pixel of the same color, add to the count in the last position
-            if p.channels()[0] == last_pixel {
-                pattern.6 += 1;
-
-                if x != prepared.dimensions().0 - 1 {
+        let mut refined = 0;
+
+        // Step 1
+        // Scan line by line, with a new QRFinderPattern for every line
+        'rows: for y in 0..prepared.luma_dimensions().1 {
+            let row = prepared.luma_row(y);
+            let mut pattern = QRFinderPattern::new();
+
+            'pixels: for (x, same) in Runs::new(row) {
+                // Pixels of the same color are added to the count in the last position in one go.
+                // Only color switches and the end of the line need to be looked at
+                pattern.6 += same;
+
+                // A pixel color switch, but the current pattern does not look like a finder
+                // Slide the pattern and continue searching
+                if !pattern.looks_like_finder(&runs) {
+                    pattern.slide();
                     continue 'pixels;
                 }
-            }
 
-            // A pixel color switch, but the current pattern does not look like a finder
-            // Slide the pattern and continue searching
-            if !pattern.looks_like_finder() {
-                last_pixel = p.channels()[0];
-                pattern.slide();
-                continue 'pixels;
-            }
-
-            let mut module_size = pattern.est_mod_size();
+                let mut module_size = pattern.est_mod_size();
 
-            // A finder pattern is 1-1-3-1-1 modules wide, so subtract 3.5 modules to get the x coordinate in the center
-            let mut finder = Point {
-                x: f64::from(x) - module_size * 3.5,
-                y: f64::from(y),
-            };
+                // A finder pattern is 1-1-3-1-1 modules wide, so subtract 3.5 modules to get the x coordinate in the center
+                let mut finder = Point {
+                    x: f64::from(x) - module_size * 3.5,
+                    y: f64::from(y),
+                };
 
-            for candidate in &candidates {
-                if dist(&finder, &candidate.location) < 7.0 * module_size {
-                    // The candidate location we have found was already detected and stored on a previous line.
-                    last_pixel = p.channels()[0];
-                    pattern.slide();
+                for candidate in &candidates {
+                    if dist(&finder, &candidate.location) < 7.0 * module_size {
+                        // The candidate location we have found was already detected and stored on a previous line.
+                        pattern.slide();
 
-                    continue 'pixels;
+                        continue 'pixels;
+                    }
                 }
-            }
 
-            // Step 2
-            // Run the refinement functions on the candidate location
-            for (refine_func, dx, dy, is_diagonal) in &refine_func {
-                let vert = refine_func(self, prepared, &finder, module_size);
+                // Step 2
+                // Run the refinement functions on the candidate location
+                refined += 1;
+                for (refine_func, dx, dy, is_diagonal, direction) in &refine_func {
+                    let vert = refine_func(self, prepared, &finder, module_size);
+
+                    let Some(vert) = vert else {
+                        trace::record(|| TraceEvent::FinderRejected {
+                            location: finder,
+                            module_size,
+                            direction,
+                        });
+
+                        pattern.slide();
+                        continue 'pixels;
+                    };
+
+                    if !is_diagonal {
+                        // Adjust the candidate location with the refined candidate and module size,
+                        // exchept when refining the diagonal because that is unreliable on lower resolutions
+                        let half_finder = 3.5 * vert.last_module_size;
+                        finder.x = vert.location.x - dx * half_finder;
+                        finder.y = vert.location.y - dy * half_finder;
+                        module_size = vert.module_size;
+                    }
+                }
 
-                let Some(vert) = vert else {
-                    last_pixel = p.channels()[0];
-                    pattern.slide();
-                    continue 'pixels;
-                };
+                trace::record(|| TraceEvent::FinderCandidate {
+                    location: finder,
+                    module_size,
+                });
 
-                if !is_diagonal {
-                    // Adjust the candidate location with the refined candidate and module size,
-                    // exchept when refining the diagonal because that is unreliable on lower resolutions
-                    let half_finder = 3.5 * vert.last_module_size;
-                    finder.x = vert.location.x - dx * half_finder;
-                    finder.y = vert.location.y - dy * half_finder;
-                    module_size = vert.module_size;
+                // Crafted images can hold any number of finder patterns, each adding to the groups of three to check
+                if !limits::spend(Limit::Candidates) {
+                    break 'rows;
                 }
-            }
 
-            candidates.push(QRFinderPosition {
-                location: finder,
-                module_size,
-                last_module_size: 0.0,
-            });
+                candidates.push(QRFinderPosition {
+                    location: finder,
+                    module_size,
+                    last_module_size: 0.0,
+                });
 
-            last_pixel = p.channels()[0];
-            pattern.slide();
+                pattern.slide();
+            }
         }
 
         debug!("Candidate QR Locators {candidates:#?}");
@@ -125,8 +216,7 @@ impl Detect<GrayImage> for LineScan {
         // Output a debug image by drawing red squares around all candidate locations
         #[cfg(feature = "debug-images")]
         {
-            #[cfg(feature = "debug-images")]
-            let mut img = DynamicImage::ImageLuma8(prepared.clone()).to_rgb8();
+            let mut img = DynamicImage::ImageLuma8(crate::util::to_gray_image(prepared)).to_rgb8();
 
             for c in candidates.iter() {
                 let loc = c.location;
@@ -159,6 +249,7 @@ impl Detect<GrayImage> for LineScan {
         }
 
         let mut locations: Vec<Location> = vec![];
+        let mut groups = 0;
 
         let max_candidates = candidates.len();
 
@@ -173,7 +264,7 @@ impl Detect<GrayImage> for LineScan {
 
                 trace!("DIFF 1 {}", diff1);
 
-                if diff1 > 0.1 {
+                if diff1 > self.tolerances.group_module_size {
                     continue;
                 }
 
@@ -185,31 +276,39 @@ impl Detect<GrayImage> for LineScan {
 
                     trace!("DIFF 2 {}", diff2);
 
-                    if diff2 > 0.1 {
+                    if diff2 > self.tolerances.group_module_size {
                         continue;
                     }
 
+                    groups += 1;
                     if let Some(qr) = find_qr(
                         &candidates[candidate1].location,
                         &candidates[candidate2].location,
                         &candidates[candidate3].location,
                         candidates[candidate1].module_size,
+                        &self.tolerances,
                     ) {
                         locations.push(Location::QR(qr));
+                    } else {
+                        trace::record(|| TraceEvent::FindersRejected {
+                            finders: [
+                                candidates[candidate1].location,
+                                candidates[candidate2].location,
+                                candidates[candidate3].location,
+                            ],
+                        });
                     }
                 }
             }
         }
 
-        locations
+        (locations, refined, groups)
     }
-}
 
-impl LineScan {
     // Refine horizontally
-    fn refine_horizontal(
+    fn refine_horizontal<P: LumaSource>(
         &self,
-        prepared: &GrayImage,
+        prepared: &P,
         finder: &Point,
         module_size: f64,
     ) -> Option<QRFinderPosition> {
@@ -217,7 +316,7 @@ impl LineScan {
         let start_x = (finder.x - 5.0 * module_size).max(0.0_f64).round() as u32;
         let end_x = min(
             (finder.x + 5.0 * module_size).round() as u32,
-            prepared.dimensions().0,
+            prepared.luma_dimensions().0,
         );
 
         // Range in x direction, y is constant
@@ -228,9 +327,9 @@ impl LineScan {
     }
 
     // Refine vertically
-    fn refine_vertical(
+    fn refine_vertical<P: LumaSource>(
         &self,
-        prepared: &GrayImage,
+        prepared: &P,
         finder: &Point,
         module_size: f64,
     ) -> Option<QRFinderPosition> {
@@ -238,7 +337,7 @@ impl LineScan {
         let start_y = (finder.y - 5.0 * module_size).max(0.0_f64).round() as u32;
         let end_y = min(
             (finder.y + 5.0 * module_size).round() as u32,
-            prepared.dimensions().1,
+            prepared.luma_dimensions().1,
         );
 
         // X is constant, range in y direction
@@ -249,9 +348,9 @@ impl LineScan {
     }
 
     // Refine diagonally
-    fn refine_diagonal(
+    fn refine_diagonal<P: LumaSource>(
         &self,
-        prepared: &GrayImage,
+        prepared: &P,
         finder: &Point,
         module_size: f64,
     ) -> Option<QRFinderPosition> {
@@ -280,25 +379,26 @@ impl LineScan {
         let range_x = start_x.round() as u32
             ..min(
                 (finder.x + 5.0 * module_size).round() as u32,
-                prepared.dimensions().0,
+                prepared.luma_dimensions().0,
             );
         let range_y = start_y.round() as u32
             ..min(
                 (finder.y + 5.0 * module_size).round() as u32,
-                prepared.dimensions().1,
+                prepared.luma_dimensions().1,
             );
 
         self.refine(prepared, module_size, range_x, range_y, true)
     }
 
-    fn refine(
+    fn refine<P: LumaSource>(
         &self,
-        prepared: &GrayImage,
+        prepared: &P,
         module_size: f64,
         range_x: impl Iterator<Item = u32>,
         range_y: impl Iterator<Item = u32>,
         is_diagonal: bool,
     ) -> Option<QRFinderPosition> {
+        let runs = RunCheck::new(&self.tolerances);
         let mut last_pixel = 127;
         let mut pattern = QRFinderPattern::new();
         let mut last_x = 0;
@@ -306,15 +406,15 @@ impl LineScan {
 
         // Loop over provided range and basically execute the same logic as above
         for (x, y) in range_x.zip(range_y) {
-            let p = prepared.get_pixel(x, y)[0];
+            let p = prepared.luma(x, y);
             if p == last_pixel {
                 pattern.6 += 1;
             } else {
                 // The current pattern needs to look like a finder (1-1-3-1-1)
                 // Also the module size needs to be similar to the candidate we are refining,
                 // except when checking the diagonal because that is unreliable on lower resolutions
-                if pattern.looks_like_finder()
-                    && (diff(module_size, pattern.est_mod_size()) < 0.2 || is_diagonal)
+                if pattern.looks_like_finder(&runs)
+                    && (diff(module_size, pattern.est_mod_size()) < self.tolerances.refine_module_size || is_diagonal)
                 {
                     let new_est_mod_size = (module_size + pattern.est_mod_size()) / 2.0;
                     return Some(QRFinderPosition {
@@ -338,8 +438,8 @@ impl LineScan {
         // The current pattern needs to look like a finder (1-1-3-1-1)
         // Also the module size needs to be similar to the candidate we are refining,
         // except when checking the diagonal because that is unreliable on lower resolutions
-        if pattern.looks_like_finder()
-            && (diff(module_size, pattern.est_mod_size()) < 0.2 || is_diagonal)
+        if pattern.looks_like_finder(&runs)
+            && (diff(module_size, pattern.est_mod_size()) < self.tolerances.refine_module_size || is_diagonal)
         {
             let new_est_mod_size = (module_size + pattern.est_mod_size()) / 2.0;
             return Some(QRFinderPosition {
@@ -356,6 +456,47 @@ impl LineScan {
     }
 }
 
+// Iterates a line of pixels by runs of the same color, yielding every color switch and finally the end of the line
+// as (x, number of pixels of the previous color since the last switch)
+struct Runs<'a> {
+    row: &'a [u8],
+    pos: usize,
+    color: u8,
+}
+
+impl<'a> Runs<'a> {
+    fn new(row: &'a [u8]) -> Runs<'a> {
+        Runs {
+            row,
+            pos: 0,
+            color: 127,
+        }
+    }
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = (u32, u32);
+
+    fn next(&mut self) -> Option<(u32, u32)> {
+        if self.pos >= self.row.len() {
+            return None;
+        }
+
+        let start = self.pos;
+        let switch = start + simd::run_length(&self.row[start..], self.color);
+
+        if switch == self.row.len() {
+            // The end of the line, which is still the same color
+            self.pos = switch;
+            return Some(((switch - 1) as u32, (switch - start) as u32));
+        }
+
+        self.color = self.row[switch];
+        self.pos = switch + 1;
+        Some((switch as u32, (switch - start) as u32))
+    }
+}
+
 #[derive(Debug)]
 struct QRFinderPattern(u32, u32, u32, u32, u32, u32, u32);
 
@@ -365,7 +506,7 @@ impl QRFinderPattern {
     }
 
     fn slide(&mut self) {
-        if f64::from(self.6) < f64::from(self.5) / 10.0 && self.4 != 0 {
+        if 10 * u64::from(self.6) < u64::from(self.5) && self.4 != 0 {
             // we slid last time because the pixels inverted,
             // but it turned out that it was only for a few pixels
             // likely it was just some noise in the image
@@ -397,15 +538,16 @@ impl QRFinderPattern {
     }
 
     // Determine if the candidate looks like a finder, with about 1-1-3-1-1 ratios
-    fn looks_like_finder(&self) -> bool {
+    #[cfg(not(feature = "fixed-point"))]
+    fn looks_like_finder(&self, runs: &RunCheck) -> bool {
         let total_size = self.2 + self.3 + self.4 + self.5 + self.6;
 
-        if total_size < 7 {
+        if total_size < runs.min_length {
             return false;
         }
 
         let module_size: f64 = f64::from(total_size) / 7.0;
-        let max_variance = module_size / 1.5;
+        let max_variance = module_size * runs.ratio;
 
         if (module_size - f64::from(self.2)).abs() > max_variance {
             return false;
@@ -429,6 +571,52 @@ impl QRFinderPattern {
 
         true
     }
+
+    // Same check as above, multiplied by 21 to stay in integers, since it runs for every color switch in the image
+    #[cfg(feature = "fixed-point")]
+    fn looks_like_finder(&self, runs: &RunCheck) -> bool {
+        let total_size = self.2 + self.3 + self.4 + self.5 + self.6;
+
+        if total_size < runs.min_length {
+            return false;
+        }
+
+        let max_variance = runs.ratio * i64::from(total_size);
+        let module_size = 3 * RATIO_ONE * i64::from(total_size);
+
+        let off = |expected: i64, run: u32| (expected - 21 * RATIO_ONE * i64::from(run)).abs() >= max_variance;
+
+        !(off(module_size, self.2)
+            || off(module_size, self.3)
+            || off(module_size * 3, self.4)
+            || off(module_size, self.5)
+            || off(module_size, self.6))
+    }
+}
+
+// The tolerances for the runs of a finder pattern, in the form the check for every color switch needs
+struct RunCheck {
+    min_length: u32,
+    #[cfg(not(feature = "fixed-point"))]
+    ratio: f64,
+    // The ratio in 1/RATIO_ONE of 1/21 of the total size, 2 * RATIO_ONE for the default
+    #[cfg(feature = "fixed-point")]
+    ratio: i64,
+}
+
+#[cfg(feature = "fixed-point")]
+const RATIO_ONE: i64 = 1 << 10;
+
+impl RunCheck {
+    fn new(tolerances: &LineScanTolerances) -> RunCheck {
+        RunCheck {
+            min_length: tolerances.min_run_length,
+            #[cfg(not(feature = "fixed-point"))]
+            ratio: tolerances.run_ratio,
+            #[cfg(feature = "fixed-point")]
+            ratio: (tolerances.run_ratio * 3.0 * RATIO_ONE as f64).round() as i64,
+        }
+    }
 }
 
 #[inline]
@@ -447,11 +635,17 @@ fn dist(one: &Point, other: &Point) -> f64 {
 }
 
 #[inline]
-fn find_qr(one: &Point, two: &Point, three: &Point, module_size: f64) -> Option<QRLocation> {
+fn find_qr(
+    one: &Point,
+    two: &Point,
+    three: &Point,
+    module_size: f64,
+    tolerances: &LineScanTolerances,
+) -> Option<QRLocation> {
     // Try all three combinations of points to see if any of them are a QR
-    find_qr_internal(one, two, three, module_size)
-        .or_else(|| find_qr_internal(two, one, three, module_size))
-        .or_else(|| find_qr_internal(three, one, two, module_size))
+    find_qr_internal(one, two, three, module_size, tolerances)
+        .or_else(|| find_qr_internal(two, one, three, module_size, tolerances))
+        .or_else(|| find_qr_internal(three, one, two, module_size, tolerances))
 }
 
 fn find_qr_internal(
@@ -459,6 +653,7 @@ fn find_qr_internal(
     two: &Point,
     three: &Point,
     module_size: f64,
+    tolerances: &LineScanTolerances,
 ) -> Option<QRLocation> {
     let ax = two.x - one.x;
     let ay = two.y - one.y;
@@ -473,7 +668,7 @@ fn find_qr_internal(
     trace!("DIFF {}", diff(len_a, len_b));
 
     // The distance between the two finders needs to be similar
-    if diff(len_a, len_b) > 0.06 {
+    if diff(len_a, len_b) > tolerances.side_length {
         return None;
     }
 
@@ -482,7 +677,7 @@ fn find_qr_internal(
     trace!("PERPENDICULAR {}", perpendicular);
 
     // The two sides need to be perpendicular
-    if (perpendicular.abs() - 1.0).abs() > 0.05 {
+    if (perpendicular.abs() - 1.0).abs() > tolerances.perpendicular {
         return None;
     }
 
@@ -531,3 +726,55 @@ pub struct QRFinderPosition {
     pub module_size: f64,
     pub last_module_size: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs() {
+        let row = [0, 0, 255, 255, 255, 0];
+        assert_eq!(Runs::new(&row).collect::<Vec<_>>(), vec![(0, 0), (2, 1), (5, 2)]);
+
+        let row = [255, 0, 0, 0];
+        assert_eq!(Runs::new(&row).collect::<Vec<_>>(), vec![(0, 0), (1, 0), (3, 2)]);
+
+        assert_eq!(Runs::new(&[]).count(), 0);
+    }
+
+    #[test]
+    fn test_tolerances() {
+        let default = LineScanTolerances::default();
+        let strict = LineScanTolerances {
+            run_ratio: 0.2,
+            min_run_length: 14,
+            side_length: 0.01,
+            perpendicular: 0.01,
+            ..default
+        };
+        let (default_runs, strict_runs) = (RunCheck::new(&default), RunCheck::new(&strict));
+
+        // The first run is half a module too wide
+        let off = QRFinderPattern(0, 0, 3, 2, 6, 2, 2);
+        assert!(off.looks_like_finder(&default_runs));
+        assert!(!off.looks_like_finder(&strict_runs));
+        assert!(QRFinderPattern(0, 0, 2, 2, 6, 2, 2).looks_like_finder(&strict_runs));
+
+        // One pixel per module
+        let small = QRFinderPattern(0, 0, 1, 1, 3, 1, 1);
+        assert!(small.looks_like_finder(&default_runs));
+        assert!(!small.looks_like_finder(&strict_runs));
+
+        let point = |x: f64, y: f64| Point { x, y };
+
+        // Sides of 100 and 95 pixels, at right angles
+        let uneven = [point(0.0, 0.0), point(100.0, 0.0), point(0.0, 95.0)];
+        assert!(find_qr(&uneven[0], &uneven[1], &uneven[2], 4.0, &default).is_some());
+        assert!(find_qr(&uneven[0], &uneven[1], &uneven[2], 4.0, &strict).is_none());
+
+        // Sides of 100 pixels, about 17 degrees from perpendicular
+        let skewed = [point(0.0, 0.0), point(100.0, 0.0), point(30.0, 95.4)];
+        assert!(find_qr(&skewed[0], &skewed[1], &skewed[2], 4.0, &default).is_some());
+        assert!(find_qr(&skewed[0], &skewed[1], &skewed[2], 4.0, &strict).is_none());
+    }
+}
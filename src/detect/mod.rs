@@ -14,21 +14,36 @@ use crate::util::qr::QRLocation;
 /// # Example
 /// ```
 /// # extern crate bardecoder;
-/// # extern crate image;
 /// # use bardecoder::detect::Location;
-/// # use image::GrayImage;
+/// # use bardecoder::util::LumaImage;
 /// use bardecoder::detect::Detect;
 ///
 /// struct MyDetector {}
 ///
-/// impl Detect<GrayImage> for MyDetector {
-///     fn detect(&self, prepared: &GrayImage) -> Vec<Location> {
+/// impl Detect<LumaImage> for MyDetector {
+///     fn detect(&self, prepared: &LumaImage) -> Vec<Location> {
 ///         // detect codes here
 /// #       vec![]
 ///     }
 /// }
 /// ```
 ///
+/// A function or closure with the signature of [`detect`](#tymethod.detect) is a `Detect` as well, for example to
+/// filter what another detector found:
+/// ```
+/// # extern crate bardecoder;
+/// # use bardecoder::detect::Location;
+/// # use bardecoder::util::LumaImage;
+/// use bardecoder::detect::{Detect, LineScan};
+///
+/// let linescan = LineScan::new();
+/// let large_only = move |prepared: &LumaImage| -> Vec<Location> {
+///     let locations = linescan.detect(prepared);
+///     locations.into_iter().filter(|Location::QR(qrloc)| qrloc.module_size >= 4.0).collect()
+/// };
+/// let detect: Box<dyn Detect<LumaImage> + Send + Sync> = Box::new(large_only);
+/// ```
+///
 /// with the corresponding impl Detect being the Example [`here`]
 ///
 /// [`Location`]: ../detect/enum.Location.html
@@ -41,9 +56,22 @@ pub trait Detect<PREPD> {
     fn detect(&self, prepared: &PREPD) -> Vec<Location>;
 }
 
+impl<PREPD, F> Detect<PREPD> for F
+where
+    F: Fn(&PREPD) -> Vec<Location>,
+{
+    fn detect(&self, prepared: &PREPD) -> Vec<Location> {
+        self(prepared)
+    }
+}
+
+mod fallback;
 mod linescan;
+mod rotated;
 
-pub use self::linescan::LineScan;
+pub use self::fallback::Fallback;
+pub use self::linescan::{LineScan, LineScanTolerances};
+pub use self::rotated::RotatedViews;
 
 /// Location of a detected barcode
 #[derive(Debug)]
@@ -0,0 +1,233 @@
+use super::{Detect, Location};
+
+use crate::util::metrics;
+use crate::util::qr::QRLocation;
+use crate::util::{LumaImage, LumaSource, Point};
+
+/// Detect in views of the prepared image turned by 90, 180 and 270 degrees
+///
+/// A detector that scans along the rows of the image sees a code differently once it is turned, so a code it misses
+/// can be found in one of these views. The views are tried in turn clockwise and the locations of the first one in
+/// which the detector finds anything are returned, mapped back onto the prepared image itself. Extracting and decoding
+/// work on the prepared image as usual.
+///
+/// Turning the image costs a single pass over its pixels per view, little compared to detecting in it. As the
+/// unturned image is not tried, this is meant as a fallback for another detector, see
+/// [`DecoderBuilder::fallback_detect`].
+///
+/// [`DecoderBuilder::fallback_detect`]: ../struct.DecoderBuilder.html#method.fallback_detect
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::decode::QRDecoder;
+/// use bardecoder::detect::{LineScan, RotatedViews};
+/// use bardecoder::extract::QRExtractor;
+/// use bardecoder::prepare::BlockedMean;
+/// use bardecoder::util::LumaImage;
+/// use bardecoder::DecoderBuilder;
+///
+/// let mut db: DecoderBuilder<LumaImage, LumaImage, String> = DecoderBuilder::new();
+/// db.prepare(Box::new(BlockedMean::new(5, 7)));
+/// db.detect(Box::new(LineScan::new()));
+/// db.fallback_detect(Box::new(RotatedViews::new(LineScan::new())));
+/// db.qr(Box::new(QRExtractor::new()), Box::new(QRDecoder::new()));
+///
+/// let decoder = db.build().unwrap();
+/// ```
+pub struct RotatedViews<D> {
+    detector: D,
+}
+
+impl<D> RotatedViews<D> {
+    /// Construct a new RotatedViews, detecting with the provided detector in every view
+    pub fn new(detector: D) -> RotatedViews<D> {
+        RotatedViews { detector }
+    }
+}
+
+impl<P: LumaSource, D: Detect<LumaImage>> Detect<P> for RotatedViews<D> {
+    fn detect(&self, prepared: &P) -> Vec<Location> {
+        let (width, height) = prepared.luma_dimensions();
+
+        for (i, turn) in TURNS.iter().enumerate() {
+            // Turning reads every pixel, every view after the first is another try
+            metrics::count(|metrics| {
+                metrics.detect_pixels += u64::from(width) * u64::from(height);
+                if i > 0 {
+                    metrics.retries += 1;
+                }
+            });
+
+            let locations = self.detector.detect(&turn.view(prepared));
+
+            if !locations.is_empty() {
+                return locations
+                    .into_iter()
+                    .map(|Location::QR(qrloc)| Location::QR(turn.unturned(&qrloc, width, height)))
+                    .collect();
+            }
+        }
+
+        vec![]
+    }
+}
+
+const TURNS: [Turn; 3] = [Turn::Quarter, Turn::Half, Turn::ThreeQuarters];
+
+// Clockwise
+#[derive(Debug, Copy, Clone)]
+enum Turn {
+    Quarter,
+    Half,
+    ThreeQuarters,
+}
+
+impl Turn {
+    fn view<P: LumaSource>(self, prepared: &P) -> LumaImage {
+        let (width, height) = prepared.luma_dimensions();
+        let (view_width, view_height) = match self {
+            Turn::Half => (width, height),
+            Turn::Quarter | Turn::ThreeQuarters => (height, width),
+        };
+
+        LumaImage::from_fn(view_width, view_height, |x, y| {
+            let (x, y) = self.unturned_pixel(x, y, width, height);
+            prepared.luma(x, y)
+        })
+    }
+
+    // The pixel of the prepared image of `width` by `height` at this pixel of the view
+    fn unturned_pixel(self, x: u32, y: u32, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Turn::Quarter => (y, height - 1 - x),
+            Turn::Half => (width - 1 - x, height - 1 - y),
+            Turn::ThreeQuarters => (width - 1 - y, x),
+        }
+    }
+
+    // Pixel coordinates are those of the pixel centers, so points are mapped the same way as pixels
+    fn unturned(self, qrloc: &QRLocation, width: u32, height: u32) -> QRLocation {
+        let (width, height) = (f64::from(width), f64::from(height));
+        let unturn = |p: Point| match self {
+            Turn::Quarter => Point {
+                x: p.y,
+                y: height - 1.0 - p.x,
+            },
+            Turn::Half => Point {
+                x: width - 1.0 - p.x,
+                y: height - 1.0 - p.y,
+            },
+            Turn::ThreeQuarters => Point {
+                x: width - 1.0 - p.y,
+                y: p.x,
+            },
+        };
+
+        QRLocation {
+            top_left: unturn(qrloc.top_left),
+            top_right: unturn(qrloc.top_right),
+            bottom_left: unturn(qrloc.bottom_left),
+            module_size: qrloc.module_size,
+            version: qrloc.version,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    // Finds a code at a fixed location in views that are wider than they are tall, recording every view
+    struct Landscape {
+        views: Mutex<Vec<LumaImage>>,
+    }
+
+    impl Detect<LumaImage> for Landscape {
+        fn detect(&self, prepared: &LumaImage) -> Vec<Location> {
+            self.views.lock().unwrap().push(prepared.clone());
+
+            let (width, height) = prepared.luma_dimensions();
+            if width <= height {
+                return vec![];
+            }
+
+            vec![Location::QR(QRLocation {
+                top_left: Point { x: 1.0, y: 2.0 },
+                top_right: Point { x: 5.0, y: 2.0 },
+                bottom_left: Point { x: 1.0, y: 6.0 },
+                module_size: 1.0,
+                version: 1,
+            })]
+        }
+    }
+
+    #[test]
+    fn test_rotated_views() {
+        let prepared = LumaImage::from_fn(20, 30, |x, y| (x + 20 * y) as u8);
+        let detect = RotatedViews::new(Landscape {
+            views: Mutex::new(vec![]),
+        });
+
+        let locations = detect.detect(&prepared);
+
+        // The first view is turned clockwise by a quarter: its top left pixel is the bottom left one
+        let views = detect.detector.views.into_inner().unwrap();
+        assert_eq!(views.len(), 1);
+        assert_eq!(views[0].luma_dimensions(), (30, 20));
+        assert_eq!(views[0].luma(0, 0), prepared.luma(0, 29));
+        assert_eq!(views[0].luma(29, 19), prepared.luma(19, 0));
+
+        let Location::QR(qrloc) = &locations[0];
+        assert_eq!((qrloc.top_left.x, qrloc.top_left.y), (2.0, 28.0));
+        assert_eq!((qrloc.top_right.x, qrloc.top_right.y), (2.0, 24.0));
+        assert_eq!((qrloc.bottom_left.x, qrloc.bottom_left.y), (6.0, 28.0));
+    }
+
+    #[test]
+    fn test_every_view_maps_back() {
+        let prepared = LumaImage::from_fn(7, 5, |x, y| (x + 7 * y) as u8);
+
+        for turn in TURNS.iter() {
+            let view = turn.view(&prepared);
+            let (view_width, view_height) = view.luma_dimensions();
+
+            for (x, y) in (0..view_width).flat_map(|x| (0..view_height).map(move |y| (x, y))) {
+                let qrloc = QRLocation {
+                    top_left: Point {
+                        x: f64::from(x),
+                        y: f64::from(y),
+                    },
+                    top_right: Point { x: 0.0, y: 0.0 },
+                    bottom_left: Point { x: 0.0, y: 0.0 },
+                    module_size: 1.0,
+                    version: 1,
+                };
+                let unturned = turn.unturned(&qrloc, 7, 5).top_left;
+
+                assert_eq!(view.luma(x, y), prepared.luma(unturned.x as u32, unturned.y as u32), "{:?}", turn);
+            }
+        }
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_decode_in_rotated_view() {
+        use crate::decode::{Decode, QRDecoder};
+        use crate::detect::LineScan;
+        use crate::extract::{Extract, QRExtractor};
+        use crate::prepare::{BlockedMean, Prepare};
+
+        let img = image::open("tests/images/version1_example.jpg").unwrap();
+        let prepared: image::GrayImage = BlockedMean::new(5, 7).prepare(&img);
+
+        let locations = RotatedViews::new(LineScan::new()).detect(&prepared);
+        assert_eq!(locations.len(), 1);
+
+        let Location::QR(qrloc) = locations.into_iter().next().unwrap();
+        let decoded = QRDecoder::new().decode(QRExtractor::new().extract(&prepared, qrloc));
+        assert_eq!(decoded, Ok(String::from("01234567")));
+    }
+}
@@ -0,0 +1,25 @@
+//! Encode content into QR Codes
+//!
+//! [`QREncoder`] produces the module matrix of a QR Code of any version from 1 to 40, at any error correction level,
+//! using numeric, alphanumeric and byte mode. The result can be turned into an image, or straight into the
+//! [`QRData`] the decoder works on, which is what the round-trip tests of the decoder use.
+//!
+//! # Example
+//! ```
+//! # extern crate bardecoder;
+//! use bardecoder::decode::{Decode, QRDecoder};
+//! use bardecoder::encode::QREncoder;
+//! use bardecoder::ECLevel;
+//!
+//! let code = QREncoder::new().ec_level(ECLevel::QUARTILE).encode("HELLO WORLD").unwrap();
+//!
+//! assert_eq!(code.version, 1);
+//! assert_eq!(QRDecoder::new().decode(Ok(code.to_qr_data())).unwrap(), "HELLO WORLD");
+//! ```
+//!
+//! [`QREncoder`]: struct.QREncoder.html
+//! [`QRData`]: ../util/qr/struct.QRData.html
+
+mod qr;
+
+pub use self::qr::{EncodeError, QRCode, QREncoder, Segment};
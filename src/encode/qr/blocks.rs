@@ -0,0 +1,95 @@
+use crate::decode::qr::galois::{EXP8, GF8};
+use crate::decode::qr::BlockInfo;
+
+/// Split the data codewords into blocks, add their error correction codewords and interleave them
+pub fn interleave(data: &[u8], block_info: &[BlockInfo]) -> Vec<u8> {
+    let mut blocks = vec![];
+    let mut start = 0;
+
+    for bi in block_info {
+        let end = start + bi.data_per as usize;
+        let block = &data[start..end];
+
+        blocks.push((block, ec_codewords(block, (bi.total_per - bi.data_per) as usize)));
+        start = end;
+    }
+
+    let mut codewords = vec![];
+    let max_data = block_info.iter().map(|bi| bi.data_per).max().unwrap_or(0) as usize;
+    let max_ec = blocks.iter().map(|(_, ec)| ec.len()).max().unwrap_or(0);
+
+    for i in 0..max_data {
+        codewords.extend(blocks.iter().filter_map(|(data, _)| data.get(i)));
+    }
+
+    for i in 0..max_ec {
+        codewords.extend(blocks.iter().filter_map(|(_, ec)| ec.get(i)));
+    }
+
+    codewords
+}
+
+/// Remainder of the data, shifted up by the number of error correction codewords, divided by the generator
+pub fn ec_codewords(data: &[u8], count: usize) -> Vec<u8> {
+    let generator = generator(count);
+    let mut remainder = vec![GF8(0); count];
+
+    for codeword in data {
+        let factor = GF8(*codeword) + remainder[0];
+
+        remainder.remove(0);
+        remainder.push(GF8(0));
+
+        for (r, g) in remainder.iter_mut().zip(&generator) {
+            *r = *r + *g * factor;
+        }
+    }
+
+    remainder.into_iter().map(|r| r.0).collect()
+}
+
+// (x - a^0)(x - a^1)...(x - a^(count - 1)), highest power first and without its coefficient of 1
+fn generator(count: usize) -> Vec<GF8> {
+    let mut generator = vec![GF8(1)];
+
+    for root in EXP8.iter().take(count) {
+        let mut next = vec![GF8(0); generator.len() + 1];
+
+        for (i, coefficient) in generator.iter().enumerate() {
+            next[i] = next[i] + *coefficient;
+            next[i + 1] = next[i + 1] + *coefficient * *root;
+        }
+
+        generator = next;
+    }
+
+    generator.remove(0);
+    generator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ec_codewords() {
+        // The example from the standard, 01234567 in version 1-M
+        let data = [0x10, 0x20, 0x0C, 0x56, 0x61, 0x80, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11];
+
+        assert_eq!(
+            ec_codewords(&data, 10),
+            vec![0xA5, 0x24, 0xD4, 0xC1, 0xED, 0x36, 0xC7, 0x87, 0x2C, 0x55]
+        );
+    }
+
+    #[test]
+    fn test_interleave() {
+        let block_info = vec![BlockInfo::new(1, 4, 2, 1), BlockInfo::new(1, 5, 3, 1)];
+        let codewords = interleave(&[1, 2, 3, 4, 5], &block_info);
+
+        let first = ec_codewords(&[1, 2], 2);
+        let second = ec_codewords(&[3, 4, 5], 2);
+
+        assert_eq!(codewords, vec![1, 3, 2, 4, 5, first[0], second[0], first[1], second[1]]);
+    }
+}
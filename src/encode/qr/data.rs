@@ -0,0 +1,191 @@
+use super::EncodeError;
+use crate::util::capacity::Mode;
+
+const ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// A run of content encoded in a single mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// Digits only, 10 bits for every 3 digits
+    Numeric(String),
+    /// Digits, upper case letters, space and `$%*+-./:`, 11 bits for every 2 characters
+    Alphanumeric(String),
+    /// Any bytes, 8 bits each. Text is usually UTF-8
+    Bytes(Vec<u8>),
+}
+
+impl Segment {
+    /// The most compact single segment for the content
+    pub fn auto(content: &str) -> Segment {
+        if !content.is_empty() && content.bytes().all(|b| b.is_ascii_digit()) {
+            Segment::Numeric(String::from(content))
+        } else if !content.is_empty() && content.chars().all(|c| ALPHANUMERIC.contains(c)) {
+            Segment::Alphanumeric(String::from(content))
+        } else {
+            Segment::Bytes(content.as_bytes().to_vec())
+        }
+    }
+
+    fn mode(&self) -> u32 {
+        match self {
+            Segment::Numeric(_) => 0b0001,
+            Segment::Alphanumeric(_) => 0b0010,
+            Segment::Bytes(_) => 0b0100,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Segment::Numeric(digits) => digits.len(),
+            Segment::Alphanumeric(chars) => chars.len(),
+            Segment::Bytes(bytes) => bytes.len(),
+        }
+    }
+
+    fn length_bits(&self, version: u32) -> u8 {
+        let mode = match self {
+            Segment::Numeric(_) => Mode::Numeric,
+            Segment::Alphanumeric(_) => Mode::Alphanumeric,
+            Segment::Bytes(_) => Mode::Byte,
+        };
+
+        mode.length_bits(version) as u8
+    }
+
+    fn validate(&self) -> Result<(), EncodeError> {
+        let invalid = match self {
+            Segment::Numeric(digits) => digits.chars().find(|c| !c.is_ascii_digit()),
+            Segment::Alphanumeric(chars) => chars.chars().find(|c| !ALPHANUMERIC.contains(*c)),
+            Segment::Bytes(_) => None,
+        };
+
+        match invalid {
+            Some(c) => Err(EncodeError::InvalidCharacter(c)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Bits in the order they are written, most significant first
+#[derive(Debug, Default)]
+pub struct Bits(Vec<bool>);
+
+impl Bits {
+    pub fn push(&mut self, value: u32, count: u8) {
+        for bit in (0..count).rev() {
+            self.0.push(value >> bit & 1 == 1);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    // Pad with the terminator and then the alternating pad codewords, up to the capacity in codewords
+    pub fn into_codewords(mut self, capacity: usize) -> Vec<u8> {
+        let terminator = (capacity * 8 - self.len()).min(4);
+        self.push(0, terminator as u8);
+
+        while !self.len().is_multiple_of(8) {
+            self.0.push(false);
+        }
+
+        let mut codewords: Vec<u8> = self
+            .0
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, bit| (acc << 1) | u8::from(*bit)))
+            .collect();
+
+        for pad in [0xEC, 0x11].iter().cycle() {
+            if codewords.len() >= capacity {
+                break;
+            }
+
+            codewords.push(*pad);
+        }
+
+        codewords
+    }
+}
+
+/// Validate the segments, which is independent of the version
+pub fn validate(segments: &[Segment]) -> Result<(), EncodeError> {
+    segments.iter().try_for_each(Segment::validate)
+}
+
+/// Mode, length and content of every segment, `None` if a segment is too long for its length field
+pub fn bits(segments: &[Segment], version: u32) -> Option<Bits> {
+    let mut bits = Bits::default();
+
+    for segment in segments {
+        let length_bits = segment.length_bits(version);
+        if segment.len() >= 1 << length_bits {
+            return None;
+        }
+
+        bits.push(segment.mode(), 4);
+        bits.push(segment.len() as u32, length_bits);
+
+        match segment {
+            Segment::Numeric(digits) => {
+                for group in digits.as_bytes().chunks(3) {
+                    let value = group.iter().fold(0, |acc, d| acc * 10 + u32::from(d - b'0'));
+                    bits.push(value, [0, 4, 7, 10][group.len()]);
+                }
+            }
+            Segment::Alphanumeric(chars) => {
+                let values: Vec<u32> = chars
+                    .chars()
+                    .filter_map(|c| ALPHANUMERIC.find(c))
+                    .map(|v| v as u32)
+                    .collect();
+
+                for pair in values.chunks(2) {
+                    match pair {
+                        [first, second] => bits.push(first * 45 + second, 11),
+                        [single] => bits.push(*single, 6),
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            Segment::Bytes(bytes) => {
+                for byte in bytes {
+                    bits.push(u32::from(*byte), 8);
+                }
+            }
+        }
+    }
+
+    Some(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto() {
+        assert_eq!(Segment::auto("0123"), Segment::Numeric(String::from("0123")));
+        assert_eq!(Segment::auto("AB-1"), Segment::Alphanumeric(String::from("AB-1")));
+        assert_eq!(Segment::auto("ab"), Segment::Bytes(b"ab".to_vec()));
+        assert_eq!(Segment::auto(""), Segment::Bytes(vec![]));
+    }
+
+    #[test]
+    fn test_bits() {
+        // The example from the standard, 01234567 in version 1-M
+        let codewords = bits(&[Segment::auto("01234567")], 1).unwrap().into_codewords(16);
+
+        assert_eq!(
+            codewords,
+            vec![0x10, 0x20, 0x0C, 0x56, 0x61, 0x80, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11]
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(&[Segment::Numeric(String::from("12a"))]), Err(EncodeError::InvalidCharacter('a')));
+        assert_eq!(validate(&[Segment::Alphanumeric(String::from("ab"))]), Err(EncodeError::InvalidCharacter('a')));
+        assert_eq!(validate(&[Segment::Bytes(vec![0xFF])]), Ok(()));
+    }
+}
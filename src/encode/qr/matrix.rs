@@ -0,0 +1,196 @@
+use crate::decode::qr::blocks::{alignment_positions, data_modules};
+use crate::decode::{QRMask, StandardMask};
+use crate::util::bch::Bch;
+use crate::util::qr::{ECLevel, QRError};
+
+/// Modules of a QR Code under construction, row by row
+pub struct Matrix {
+    pub side: u32,
+    pub modules: Vec<bool>,
+}
+
+impl Matrix {
+    /// All function patterns of the version, with room left for the format information
+    pub fn new(version: u32) -> Result<Matrix, QRError> {
+        let side = 4 * version + 17;
+        let mut matrix = Matrix {
+            side,
+            modules: vec![false; (side * side) as usize],
+        };
+
+        for (x, y) in &[(0, 0), (side - 7, 0), (0, side - 7)] {
+            matrix.finder(*x, *y);
+        }
+
+        for i in 8..side - 8 {
+            matrix.set(i, 6, i % 2 == 0);
+            matrix.set(6, i, i % 2 == 0);
+        }
+
+        let positions = alignment_positions(version)?;
+        for x in positions {
+            for y in positions {
+                // The corners with a finder pattern have no alignment pattern
+                let last = side - 7;
+                if (*x, *y) != (6, 6) && (*x, *y) != (6, last) && (*x, *y) != (last, 6) {
+                    matrix.alignment(*x, *y);
+                }
+            }
+        }
+
+        // Always dark, next to the bottom left finder
+        matrix.set(8, side - 8, true);
+
+        if version >= 7 {
+            matrix.version(version);
+        }
+
+        Ok(matrix)
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        self.modules[(y * self.side + x) as usize]
+    }
+
+    fn set(&mut self, x: u32, y: u32, dark: bool) {
+        self.modules[(y * self.side + x) as usize] = dark;
+    }
+
+    fn finder(&mut self, left: u32, top: u32) {
+        for dy in 0..7 {
+            for dx in 0..7 {
+                let ring = (dx as i32 - 3).abs().max((dy as i32 - 3).abs());
+                self.set(left + dx, top + dy, ring != 2);
+            }
+        }
+    }
+
+    fn alignment(&mut self, x: u32, y: u32) {
+        for dy in 0..5 {
+            for dx in 0..5 {
+                let ring = (dx as i32 - 2).abs().max((dy as i32 - 2).abs());
+                self.set(x + dx - 2, y + dy - 2, ring != 1);
+            }
+        }
+    }
+
+    // 6 bits of version and 12 BCH bits, least significant bit in the top left of the top right block
+    fn version(&mut self, version: u32) {
+        let bits = Bch::VERSION.encode(version);
+
+        for bit in 0..18 {
+            let dark = bits >> bit & 1 == 1;
+            let (across, along) = (self.side - 11 + bit % 3, bit / 3);
+
+            self.set(across, along, dark);
+            self.set(along, across, dark);
+        }
+    }
+
+    /// Place the codewords in the data modules, masked with the pattern, the remainder bits stay light
+    pub fn place(&mut self, version: u32, codewords: &[u8], mask: u8) -> Result<(), QRError> {
+        let mask = StandardMask::new(mask)?;
+        let bits = codewords.iter().flat_map(|codeword| (0..8).rev().map(move |bit| codeword >> bit & 1 == 1));
+
+        for (&(x, y), dark) in data_modules(version)?.iter().zip(bits.chain(std::iter::repeat(false))) {
+            self.set(x, y, dark ^ mask.is_masked(x, y));
+        }
+
+        Ok(())
+    }
+
+    /// Write both copies of the format information, the same way the decoder reads them
+    pub fn format(&mut self, ec_level: &ECLevel, mask: u8) {
+        let bits = Bch::FORMAT.encode(u32::from(u8::from(ec_level)) << 3 | u32::from(mask));
+        let side = self.side;
+
+        let first = (0..9).filter(|x| *x != 6).map(|x| (x, 8)).chain((0..8).rev().filter(|y| *y != 6).map(|y| (8, y)));
+        let second = (side - 7..side).rev().map(|y| (8, y)).chain((side - 8..side).map(|x| (x, 8)));
+
+        for (i, (x, y)) in first.enumerate() {
+            self.set(x, y, bits >> (14 - i) & 1 == 1);
+        }
+
+        for (i, (x, y)) in second.enumerate() {
+            self.set(x, y, bits >> (14 - i) & 1 == 1);
+        }
+    }
+
+    /// Penalty score of the standard, the mask with the lowest score is used
+    pub fn penalty(&self) -> u32 {
+        let side = self.side;
+        let mut penalty = 0;
+
+        for i in 0..side {
+            penalty += line_penalty((0..side).map(|j| self.get(j, i)));
+            penalty += line_penalty((0..side).map(|j| self.get(i, j)));
+        }
+
+        for y in 0..side - 1 {
+            for x in 0..side - 1 {
+                let color = self.get(x, y);
+                if self.get(x + 1, y) == color && self.get(x, y + 1) == color && self.get(x + 1, y + 1) == color {
+                    penalty += 3;
+                }
+            }
+        }
+
+        let dark = self.modules.iter().filter(|m| **m).count();
+        let percent = dark * 100 / self.modules.len();
+        penalty += 10 * (percent.abs_diff(50) / 5) as u32;
+
+        penalty
+    }
+}
+
+// Runs of 5 or more modules of the same color, and patterns that look like a finder pattern
+fn line_penalty(line: impl Iterator<Item = bool>) -> u32 {
+    const FINDER: [bool; 7] = [true, false, true, true, true, false, true];
+
+    let line: Vec<bool> = line.collect();
+    let mut penalty = 0;
+
+    let mut run = 1;
+    for i in 1..=line.len() {
+        if i < line.len() && line[i] == line[i - 1] {
+            run += 1;
+            continue;
+        }
+
+        if run >= 5 {
+            penalty += run - 2;
+        }
+        run = 1;
+    }
+
+    for i in 0..line.len().saturating_sub(6) {
+        if line[i..i + 7] != FINDER {
+            continue;
+        }
+
+        let light_before = i >= 4 && line[i - 4..i].iter().all(|m| !m);
+        let light_after = i + 11 <= line.len() && line[i + 7..i + 11].iter().all(|m| !m);
+
+        if light_before || light_after {
+            penalty += 40;
+        }
+    }
+
+    penalty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_penalty() {
+        let line = |s: &str| s.chars().map(|c| c == '1').collect::<Vec<bool>>().into_iter();
+
+        assert_eq!(0, line_penalty(line("1010")));
+        assert_eq!(3, line_penalty(line("0111110")));
+        assert_eq!(5, line_penalty(line("1111111")));
+        assert_eq!(40, line_penalty(line("0000101110101")));
+        assert_eq!(0, line_penalty(line("0001011101010")));
+    }
+}
@@ -0,0 +1,336 @@
+mod blocks;
+mod data;
+mod matrix;
+
+pub use self::data::Segment;
+
+use self::matrix::Matrix;
+
+use crate::decode::qr::block_info;
+use crate::util::qr::{ECLevel, QRData};
+#[cfg(feature = "image")]
+use crate::util::LumaSource;
+use crate::util::LumaImage;
+
+/// Error type for `QREncoder`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EncodeError {
+    /// The content does not fit in the requested version, or in version 40
+    #[error("Content does not fit in the QR Code")]
+    DataTooLong,
+    /// Only versions 1 to 40 exist
+    #[error("Invalid version {0}, must be 1 to 40")]
+    InvalidVersion(u32),
+    /// Only mask patterns 0 to 7 exist
+    #[error("Invalid mask pattern {0}, must be 0 to 7")]
+    InvalidMask(u8),
+    /// A character that the mode of its segment cannot encode
+    #[error("Character {0:?} cannot be encoded in the mode of its segment")]
+    InvalidCharacter(char),
+}
+
+/// Encode content into a QR Code
+///
+/// By default the smallest version that fits the content is used, with error correction level MEDIUM and the mask
+/// pattern the standard's penalty rules prefer. All three can be set.
+///
+/// Kanji mode and ECI are not supported, text is encoded as UTF-8 in byte mode when it does not fit numeric or
+/// alphanumeric mode.
+pub struct QREncoder {
+    ec_level: ECLevel,
+    version: Option<u32>,
+    mask: Option<u8>,
+}
+
+impl QREncoder {
+    /// Construct a new QREncoder
+    pub fn new() -> QREncoder {
+        QREncoder {
+            ec_level: ECLevel::MEDIUM,
+            version: None,
+            mask: None,
+        }
+    }
+
+    /// Set the error correction level
+    pub fn ec_level(&mut self, ec_level: ECLevel) -> &mut QREncoder {
+        self.ec_level = ec_level;
+        self
+    }
+
+    /// Always use this version, instead of the smallest one the content fits in
+    pub fn version(&mut self, version: u32) -> &mut QREncoder {
+        self.version = Some(version);
+        self
+    }
+
+    /// Always use this mask pattern, instead of the one with the lowest penalty
+    pub fn mask(&mut self, mask: u8) -> &mut QREncoder {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Encode the content in a single segment, in the most compact mode for it
+    ///
+    /// # Errors
+    ///
+    /// Returns `EncodeError` if the content does not fit, or the version or mask pattern set are invalid
+    pub fn encode(&self, content: &str) -> Result<QRCode, EncodeError> {
+        self.encode_segments(&[Segment::auto(content)])
+    }
+
+    /// Encode the segments after each other, each in its own mode
+    ///
+    /// # Errors
+    ///
+    /// Returns `EncodeError` if a segment has characters its mode cannot encode, the segments do not fit, or the
+    /// version or mask pattern set are invalid
+    pub fn encode_segments(&self, segments: &[Segment]) -> Result<QRCode, EncodeError> {
+        data::validate(segments)?;
+
+        match self.mask {
+            Some(mask) if mask > 7 => return Err(EncodeError::InvalidMask(mask)),
+            _ => {}
+        }
+
+        let versions = match self.version {
+            Some(version) if !(1..=40).contains(&version) => return Err(EncodeError::InvalidVersion(version)),
+            Some(version) => version..=version,
+            None => 1..=40,
+        };
+
+        for version in versions {
+            let block_info = block_info(version, &self.ec_level).map_err(|_| EncodeError::InvalidVersion(version))?;
+            let capacity: usize = block_info.iter().map(|bi| bi.data_per as usize).sum();
+
+            let bits = match data::bits(segments, version) {
+                Some(bits) if bits.len() <= capacity * 8 => bits,
+                _ => continue,
+            };
+
+            let codewords = blocks::interleave(&bits.into_codewords(capacity), block_info);
+            return self.matrix(version, &codewords).map_err(|_| EncodeError::InvalidVersion(version));
+        }
+
+        Err(EncodeError::DataTooLong)
+    }
+
+    fn matrix(&self, version: u32, codewords: &[u8]) -> Result<QRCode, crate::util::qr::QRError> {
+        let masks = match self.mask {
+            Some(mask) => mask..=mask,
+            None => 0..=7,
+        };
+
+        let mut best: Option<(u32, u8, Matrix)> = None;
+
+        for mask in masks {
+            let mut matrix = Matrix::new(version)?;
+            matrix.place(version, codewords, mask)?;
+            matrix.format(&self.ec_level, mask);
+
+            let penalty = matrix.penalty();
+            if best.as_ref().is_none_or(|(lowest, _, _)| penalty < *lowest) {
+                best = Some((penalty, mask, matrix));
+            }
+        }
+
+        let (_, mask, matrix) = best.expect("At least one mask is tried");
+
+        Ok(QRCode {
+            version,
+            ec_level: self.ec_level.clone(),
+            mask,
+            side: matrix.side,
+            modules: matrix.modules,
+        })
+    }
+}
+
+/// An encoded QR Code
+#[derive(Debug, Clone, PartialEq)]
+pub struct QRCode {
+    /// Version of the QR Code, 1 being the smallest, 40 the largest
+    pub version: u32,
+
+    /// Error correction level of the QR Code
+    pub ec_level: ECLevel,
+
+    /// Mask pattern applied to the data, from 0 to 7
+    pub mask: u8,
+
+    /// Side of the QR Code, in modules
+    pub side: u32,
+
+    modules: Vec<bool>,
+}
+
+impl QRCode {
+    /// Whether the module at column x and row y is dark, (0, 0) being the top left module
+    pub fn is_dark(&self, x: u32, y: u32) -> bool {
+        self.modules[(y * self.side + x) as usize]
+    }
+
+    /// All modules row by row, `true` for dark
+    pub fn modules(&self) -> &[bool] {
+        &self.modules
+    }
+
+    /// The modules as the decoder would extract them from an image, to decode them directly
+    pub fn to_qr_data(&self) -> QRData {
+        let data = self.modules.iter().map(|dark| if *dark { 0 } else { 255 }).collect();
+
+        QRData::new(data, self.version)
+    }
+
+    /// Render black on white, with every module `module_size` pixels and a light border of `quiet_zone` modules
+    ///
+    /// The standard asks for a quiet zone of at least 4 modules.
+    pub fn to_luma_image(&self, module_size: u32, quiet_zone: u32) -> LumaImage {
+        let size = (self.side + 2 * quiet_zone) * module_size;
+
+        LumaImage::from_fn(size, size, |x, y| {
+            let (x, y) = (x / module_size, y / module_size);
+            let inside = (quiet_zone..quiet_zone + self.side).contains(&x)
+                && (quiet_zone..quiet_zone + self.side).contains(&y);
+
+            if inside && self.is_dark(x - quiet_zone, y - quiet_zone) {
+                0
+            } else {
+                255
+            }
+        })
+    }
+
+    /// Render black on white the same as [`to_luma_image`](#method.to_luma_image), as an image of the `image` crate
+    #[cfg(feature = "image")]
+    pub fn to_image(&self, module_size: u32, quiet_zone: u32) -> image::GrayImage {
+        let luma = self.to_luma_image(module_size, quiet_zone);
+        let (width, height) = luma.luma_dimensions();
+
+        image::GrayImage::from_raw(width, height, luma.into_raw()).expect("Buffer has the size of the image")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::{Decode, QRDecoder, QRDecoderWithInfo};
+    use crate::util::LumaSource;
+
+    fn decode(code: &QRCode) -> String {
+        QRDecoder::new().decode(Ok(code.to_qr_data())).unwrap()
+    }
+
+    #[test]
+    fn test_version1_example() {
+        // The example from the standard
+        let code = QREncoder::new().encode("01234567").unwrap();
+
+        assert_eq!((code.version, code.side), (1, 21));
+        assert_eq!(decode(&code), "01234567");
+    }
+
+    #[test]
+    fn test_round_trip_all_versions() {
+        let levels = [ECLevel::LOW, ECLevel::MEDIUM, ECLevel::QUARTILE, ECLevel::HIGH];
+
+        for version in 1..=40 {
+            for level in &levels {
+                let content = format!("V{}{:?}", version, level).chars().take(7).collect::<String>();
+                let code = QREncoder::new()
+                    .version(version)
+                    .ec_level(level.clone())
+                    .mask((version % 8) as u8)
+                    .encode(&content)
+                    .unwrap();
+
+                assert_eq!(code.version, version);
+                assert_eq!(decode(&code), content, "version {} at {:?}", version, level);
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_masks_and_modes() {
+        let segments = [
+            Segment::Alphanumeric(String::from("ORDER-")),
+            Segment::Numeric(String::from("0123456789")),
+            Segment::Bytes(b"/abc".to_vec()),
+        ];
+
+        for mask in 0..8 {
+            let code = QREncoder::new().mask(mask).version(2).encode_segments(&segments).unwrap();
+
+            assert_eq!(code.mask, mask);
+            assert_eq!(decode(&code), "ORDER-0123456789/abc");
+        }
+    }
+
+    #[test]
+    fn test_capacity() {
+        // The largest numeric content that fits in version 40-L, from the standard
+        let digits = "1".repeat(7089);
+        let code = QREncoder::new().ec_level(ECLevel::LOW).encode(&digits).unwrap();
+        assert_eq!(code.version, 40);
+        assert_eq!(decode(&code), digits);
+
+        let too_long = "1".repeat(7090);
+        assert_eq!(QREncoder::new().ec_level(ECLevel::LOW).encode(&too_long), Err(EncodeError::DataTooLong));
+        assert!(QREncoder::new().version(1).encode(&"1".repeat(34)).is_ok());
+        assert_eq!(QREncoder::new().version(1).encode(&"1".repeat(35)), Err(EncodeError::DataTooLong));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(QREncoder::new().version(41).encode("1"), Err(EncodeError::InvalidVersion(41)));
+        assert_eq!(QREncoder::new().mask(8).encode("1"), Err(EncodeError::InvalidMask(8)));
+    }
+
+    #[test]
+    fn test_corrected_errors() {
+        let code = QREncoder::new().ec_level(ECLevel::HIGH).encode("error correction").unwrap();
+        let mut data = code.to_qr_data();
+
+        for x in 10..14 {
+            let index = (12 * data.side + x) as usize;
+            data.data[index] = 255 - data.data[index];
+        }
+
+        let (content, info) = QRDecoderWithInfo::new().decode(Ok(data)).unwrap();
+        assert_eq!(content, "error correction");
+        assert_eq!(info.errors, 4);
+    }
+
+    #[test]
+    fn test_decode_rendered() {
+        let code = QREncoder::new().encode("https://example.com/").unwrap();
+        let results = crate::default_luma_decoder().decode(&code.to_luma_image(4, 4));
+
+        assert_eq!(results, vec![Ok(String::from("https://example.com/"))]);
+    }
+
+    #[test]
+    fn test_to_luma_image() {
+        let code = QREncoder::new().encode("1").unwrap();
+        let image = code.to_luma_image(2, 4);
+
+        assert_eq!(image.luma_dimensions(), (58, 58));
+        assert_eq!(image.luma(7, 7), 255);
+        assert_eq!(image.luma(8, 8), 0);
+    }
+
+    #[test]
+    fn test_raw_data_of_unsupported_mode() {
+        // Kanji mode, which the decoder does not parse
+        let mut data = vec![0x80, 0x10, 0x00];
+        data.extend([0xec, 0x11].iter().cycle().take(13));
+        let block_info = block_info(1, &ECLevel::MEDIUM).unwrap();
+        let mut encoder = QREncoder::new();
+        encoder.mask(0);
+        let code = encoder.matrix(1, &blocks::interleave(&data, block_info)).unwrap();
+
+        assert!(QRDecoderWithInfo::new().decode(Ok(code.to_qr_data())).is_err());
+        let (content, info) = QRDecoderWithInfo::new().with_raw_data(true).decode(Ok(code.to_qr_data())).unwrap();
+        assert_eq!((content.as_str(), info.raw_data), ("", Some(data)));
+    }
+}
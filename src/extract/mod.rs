@@ -6,6 +6,10 @@ mod qr;
 
 pub use self::qr::QRExtractor;
 
+pub(crate) use self::qr::{sample_grid, Ring};
+
+use crate::util::qr::DecodeHints;
+
 /// Extract data from a prepared image, given the location as determined by the [`Detect`] step
 ///
 /// PREPD type should be the type if the image returned from the [`Prepare`] implementation
@@ -18,21 +22,23 @@ pub use self::qr::QRExtractor;
 /// # Example
 /// ```
 /// # extern crate bardecoder;
-/// # extern crate image;
 /// # use bardecoder::util::qr::{QRLocation, QRData, QRError};
-/// # use image::GrayImage;
+/// # use bardecoder::util::LumaImage;
 /// use bardecoder::extract::Extract;
 ///
 /// struct MyExtractor {}
 ///
-/// impl Extract<GrayImage, QRLocation, QRData, QRError> for MyExtractor {
-///     fn extract(&self, prepared: &GrayImage, loc: QRLocation) -> Result<QRData, QRError> {
+/// impl Extract<LumaImage, QRLocation, QRData, QRError> for MyExtractor {
+///     fn extract(&self, prepared: &LumaImage, loc: QRLocation) -> Result<QRData, QRError> {
 ///         // extract data here
 /// #        Ok(QRData::new(vec![], 0))
 ///     }
 /// }
 /// ```
 ///
+/// A function or closure with the signature of [`extract`](#tymethod.extract) is an `Extract` as well, which saves
+/// declaring a type to try out an idea.
+///
 /// with the corresponding impl Decode being the Example [`here`]
 ///
 /// [`Location`]: ../detect/enum.Location.html
@@ -47,4 +53,23 @@ where
 {
     /// Does the actual extracting
     fn extract(&self, prepared: &PREPD, loc: LOC) -> Result<DATA, ERROR>;
+
+    /// Extract the way that suits what is known about the image, used by [`Decoder::decode_with_hints`]
+    ///
+    /// The default ignores the hints and calls [`extract`](#tymethod.extract).
+    ///
+    /// [`Decoder::decode_with_hints`]: ../struct.Decoder.html#method.decode_with_hints
+    fn extract_with_hints(&self, prepared: &PREPD, loc: LOC, _hints: &DecodeHints) -> Result<DATA, ERROR> {
+        self.extract(prepared, loc)
+    }
+}
+
+impl<PREPD, LOC, DATA, ERROR, F> Extract<PREPD, LOC, DATA, ERROR> for F
+where
+    ERROR: Error,
+    F: Fn(&PREPD, LOC) -> Result<DATA, ERROR>,
+{
+    fn extract(&self, prepared: &PREPD, loc: LOC) -> Result<DATA, ERROR> {
+        self(prepared, loc)
+    }
 }
@@ -0,0 +1,125 @@
+//! 16.16 fixed point positions, to walk the module grid without floating point operations
+
+use std::ops::Add;
+
+const SHIFT: u32 = 16;
+const HALF: i64 = 1 << (SHIFT - 1);
+
+const W_SHIFT: u32 = 30;
+
+fn to_fixed(value: f64) -> i64 {
+    (value * f64::from(1 << SHIFT)).round() as i64
+}
+
+// Round to the nearest pixel, coordinates out of range saturate like a float to u32 cast does
+fn to_pixel(value: i64) -> u32 {
+    (value.saturating_add(HALF) >> SHIFT).clamp(0, i64::from(u32::MAX)) as u32
+}
+
+/// A position along a row of the module grid in projective coordinates: the numerators of x and y in 16.16 fixed
+/// point and their denominator in 2.30, which stays close to 1
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct FixedProjective {
+    x: i64,
+    y: i64,
+    w: i64,
+}
+
+impl FixedProjective {
+    /// The pixel nearest to this position, (0, 0) for positions behind the camera
+    ///
+    /// Positions too far out for the arithmetic saturate, far outside any image.
+    pub(super) fn pixel(self) -> (u32, u32) {
+        if self.w <= 0 {
+            return (0, 0);
+        }
+
+        let coordinate = |numerator: i64| match numerator.checked_mul(1 << W_SHIFT) {
+            Some(scaled) => to_pixel(scaled / self.w),
+            None if numerator < 0 => 0,
+            None => u32::MAX,
+        };
+
+        (coordinate(self.x), coordinate(self.y))
+    }
+}
+
+/// From the x and y numerators and the denominator, such as those of a row of the module grid
+impl From<[f64; 3]> for FixedProjective {
+    fn from([x, y, w]: [f64; 3]) -> FixedProjective {
+        FixedProjective {
+            x: to_fixed(x),
+            y: to_fixed(y),
+            w: (w * f64::from(1 << W_SHIFT)).round() as i64,
+        }
+    }
+}
+
+impl Add<FixedProjective> for FixedProjective {
+    type Output = FixedProjective;
+
+    fn add(self, other: FixedProjective) -> FixedProjective {
+        FixedProjective {
+            x: self.x.saturating_add(other.x),
+            y: self.y.saturating_add(other.y),
+            w: self.w.saturating_add(other.w),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::extract::qr::homography::Homography;
+    use crate::extract::qr::Perspective;
+    use crate::util::{Delta, Point};
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn test_pixel_rounding() {
+        let at = |x: f64, y: f64, w: f64| FixedProjective::from([x, y, w]);
+
+        assert_eq!(at(10.4, 10.5, 1.0).pixel(), (10, 11));
+        assert_eq!(at(20.8, 21.0, 2.0).pixel(), (10, 11));
+        assert_eq!(at(-3.2, 0.49, 1.0).pixel(), (0, 0));
+        assert_eq!(at(10.0, 10.0, 0.0).pixel(), (0, 0));
+        assert_eq!(at(1e12, -1e12, 1.0).pixel(), (u32::MAX, 0));
+        assert_eq!((at(1e300, 0.0, 1.0) + at(1e300, 0.0, 0.0)).pixel(), (u32::MAX, 0));
+    }
+
+    #[test]
+    fn test_stepping_stays_close_to_float() {
+        let grid = [point(3.0, 3.0), point(173.0, 3.0), point(170.0, 170.0), point(3.0, 173.0)];
+        let image = [point(40.0, 50.0), point(900.0, 80.0), point(850.0, 760.0), point(60.0, 830.0)];
+        let (dx, dy) = (Delta { dx: 5.0, dy: 0.2 }, Delta { dx: -0.1, dy: 4.6 });
+        let projective = Perspective {
+            dx,
+            dy,
+            grid: Homography::from_points(grid, image).unwrap(),
+            ddx: Delta { dx: 0.0, dy: 0.0 },
+        };
+        let bent = Perspective {
+            dx,
+            dy,
+            grid: Homography::affine(point(40.0, 50.0), dx, dy),
+            ddx: Delta { dx: -0.0003, dy: 0.0002 },
+        };
+
+        for p in &[projective, bent] {
+            for row in 0..177 {
+                let (start, step) = p.row(row);
+                let (mut fixed, step) = (FixedProjective::from(start), FixedProjective::from(step));
+                for x in 0..177 {
+                    let float = p.map(f64::from(x), f64::from(row)).unwrap();
+                    let (px, py) = fixed.pixel();
+                    assert!((f64::from(px) - float.x).abs() <= 0.51 && (f64::from(py) - float.y).abs() <= 0.51);
+                    fixed = fixed + step;
+                }
+            }
+        }
+    }
+}
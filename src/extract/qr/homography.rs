@@ -0,0 +1,152 @@
+//! Projective mapping from the module grid to the image, through four control points
+
+use crate::util::{Delta, Point};
+
+/// Maps the center of module (x, y) of the grid to its position in the image
+///
+/// A projective mapping keeps straight lines straight, so unlike an affine or bilinear one it follows a code that is
+/// photographed at an angle, where the modules get smaller towards the far side.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(super) struct Homography {
+    m: [[f64; 3]; 3],
+}
+
+impl Homography {
+    /// The affine mapping with module (0, 0) at `origin`, every column `dx` and every row `dy` further
+    pub(super) fn affine(origin: Point, dx: Delta, dy: Delta) -> Homography {
+        Homography {
+            m: [[dx.dx, dy.dx, origin.x], [dx.dy, dy.dy, origin.y], [0.0, 0.0, 1.0]],
+        }
+    }
+
+    /// The mapping that takes each of the four grid positions to the image position at the same index
+    ///
+    /// `None` if either set of points is degenerate, for example with all of them on one line.
+    pub(super) fn from_points(grid: [Point; 4], image: [Point; 4]) -> Option<Homography> {
+        let to_grid = Homography::from_unit_square(grid)?.inverse()?;
+        let to_image = Homography::from_unit_square(image)?;
+        let m = to_image.after(&to_grid).m;
+
+        // Scaled so the denominator is 1 at module (0, 0), which keeps it close to 1 over the whole grid
+        if m[2][2].abs() <= f64::EPSILON {
+            return None;
+        }
+
+        Some(Homography {
+            m: m.map(|row| row.map(|value| value / m[2][2])),
+        })
+    }
+
+    /// Position of module (x, y) in the image, `None` if it is behind the camera
+    pub(super) fn map(&self, x: f64, y: f64) -> Option<Point> {
+        let m = &self.m;
+        let w = m[2][0] * x + m[2][1] * y + m[2][2];
+        if w <= f64::EPSILON {
+            return None;
+        }
+
+        Some(Point {
+            x: (m[0][0] * x + m[0][1] * y + m[0][2]) / w,
+            y: (m[1][0] * x + m[1][1] * y + m[1][2]) / w,
+        })
+    }
+
+    /// Rows of the matrix: the x numerator, the y numerator and the denominator, each as factors of x, y and 1
+    pub(super) fn rows(&self) -> [[f64; 3]; 3] {
+        self.m
+    }
+
+    // Corners of the unit square (0, 0), (1, 0), (1, 1) and (0, 1) to the four points in that order, see Heckbert,
+    // Fundamentals of Texture Mapping and Image Warping, 1989
+    fn from_unit_square(quad: [Point; 4]) -> Option<Homography> {
+        let [p0, p1, p2, p3] = quad;
+        let (sx, sy) = (p0.x - p1.x + p2.x - p3.x, p0.y - p1.y + p2.y - p3.y);
+        let (d1, d2) = (p1 - p2, p3 - p2);
+
+        let denominator = d1.dx * d2.dy - d2.dx * d1.dy;
+        if denominator.abs() <= f64::EPSILON {
+            return None;
+        }
+
+        let g = (sx * d2.dy - d2.dx * sy) / denominator;
+        let h = (d1.dx * sy - sx * d1.dy) / denominator;
+
+        Some(Homography {
+            m: [
+                [p1.x - p0.x + g * p1.x, p3.x - p0.x + h * p3.x, p0.x],
+                [p1.y - p0.y + g * p1.y, p3.y - p0.y + h * p3.y, p0.y],
+                [g, h, 1.0],
+            ],
+        })
+    }
+
+    fn inverse(&self) -> Option<Homography> {
+        let [[a, b, c], [d, e, f], [g, h, i]] = self.m;
+        let determinant = a * (e * i - f * h) - b * (d * i - f * g) + c * (d * h - e * g);
+        if determinant.abs() <= f64::EPSILON {
+            return None;
+        }
+
+        let adjugate = [
+            [e * i - f * h, c * h - b * i, b * f - c * e],
+            [f * g - d * i, a * i - c * g, c * d - a * f],
+            [d * h - e * g, b * g - a * h, a * e - b * d],
+        ];
+
+        Some(Homography {
+            m: adjugate.map(|row| row.map(|value| value / determinant)),
+        })
+    }
+
+    // This mapping applied to the result of `first`
+    fn after(&self, first: &Homography) -> Homography {
+        let mut m = [[0.0; 3]; 3];
+        for (row, out) in m.iter_mut().enumerate() {
+            for (column, value) in out.iter_mut().enumerate() {
+                *value = (0..3).map(|k| self.m[row][k] * first.m[k][column]).sum();
+            }
+        }
+
+        Homography { m }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn assert_close(a: Point, b: Point) {
+        assert!((a.x - b.x).abs() < 1e-6 && (a.y - b.y).abs() < 1e-6, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_from_points() {
+        let grid = [point(3.0, 3.0), point(30.0, 3.0), point(27.0, 27.0), point(3.0, 30.0)];
+        let image = [point(40.0, 50.0), point(300.0, 80.0), point(270.0, 260.0), point(60.0, 330.0)];
+        let homography = Homography::from_points(grid, image).unwrap();
+
+        for (from, to) in grid.iter().zip(&image) {
+            assert_close(homography.map(from.x, from.y).unwrap(), *to);
+        }
+
+        // Straight lines stay straight: the middle of the top edge is on the line between its ends
+        let middle = homography.map(16.5, 3.0).unwrap();
+        let (start, end) = (image[0], image[1]);
+        let cross = (end.x - start.x) * (middle.y - start.y) - (end.y - start.y) * (middle.x - start.x);
+        assert!(cross.abs() < 1e-6);
+
+        assert_eq!(Homography::from_points(grid, [point(5.0, 5.0); 4]), None);
+    }
+
+    #[test]
+    fn test_affine() {
+        let homography = Homography::affine(point(10.0, 20.0), Delta { dx: 4.0, dy: 1.0 }, Delta { dx: -1.0, dy: 4.0 });
+
+        assert_close(homography.map(0.0, 0.0).unwrap(), point(10.0, 20.0));
+        assert_close(homography.map(2.0, 3.0).unwrap(), point(15.0, 34.0));
+    }
+}
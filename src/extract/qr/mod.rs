@@ -1,9 +1,20 @@
 use super::Extract;
 
-use crate::util::qr::{QRData, QRError, QRLocation};
-use crate::util::{Delta, Point};
+#[cfg(feature = "fixed-point")]
+mod fixed;
+mod homography;
 
-use image::GrayImage;
+#[cfg(feature = "fixed-point")]
+use self::fixed::FixedProjective;
+use self::homography::Homography;
+
+use crate::decode::qr::format::read_version;
+use crate::util::limits::Limited;
+use crate::util::metrics::{self, Counted};
+use crate::util::qr::{DecodeHints, QRData, QRError, QRLocation, Source};
+use crate::util::{Delta, LumaSource, Point};
+
+use std::cmp::Reverse;
 
 #[cfg(feature = "debug-images")]
 use image::{DynamicImage, Rgb};
@@ -17,90 +28,675 @@ use std::{
 
 /// Extract QR Data from a preprocessed image
 ///
-/// If the version of the QR is higher than 1, this extractor will first try to find the bottom left-most
-/// alignment pattern and adjust for any perspective skewing.
+/// The version estimated from the distance between the finder patterns is often off by one or more when the module size
+/// is slightly off, so it is cross-checked with the number of modules along both timing patterns and, from version 7,
+/// both blocks of version information. The version most of them agree on is used, unless the hints have one.
+///
+/// If the version of the QR is higher than 1, this extractor will first try to find the bottom right-most
+/// alignment pattern, searching further around its expected location the larger the code is. Together with the three
+/// finder patterns it gives four control points for a projective grid, which follows a code photographed at an angle.
+/// When the pattern is within a module of where the finder patterns put it, the offset can be as much measuring error
+/// as perspective, and the even grid is only bent through it.
 ///
 /// Data is extracted by sampling the center pixel of the estimated module locations.
-/// These are determined by dividing each row and column into equal parts.
-pub struct QRExtractor {}
+/// Without an alignment pattern, these are determined by dividing each row and column into equal parts.
+/// [`with_timing_refinement`] moves every column and row to where the timing patterns have them, for labels that are
+/// stretched unevenly.
+///
+/// The print growth is estimated from the timing patterns. If the dark modules are clearly printed too large or too
+/// small, four more pixels around each center are sampled and a module needs more, or fewer, dark samples to be dark.
+///
+/// With the [`Source::Screen`] hint, runs of pixels too short to be a module, from anti-aliasing, are ignored in the
+/// timing patterns.
+///
+/// Any location can be passed in, for example from a custom detector. One that can not be a code, with a version
+/// outside 1 to 40, coordinates that are not finite numbers or modules that are not larger than zero, is an error. So is
+/// a grid with modules outside the image; pixels are only read inside it.
+///
+/// Before the search for the alignment pattern and the sampling of the grid, a detected location is checked for being
+/// a code at all, see [`with_candidate_checks`], so false positives fail early.
+///
+/// [`with_timing_refinement`]: #method.with_timing_refinement
+/// [`with_candidate_checks`]: #method.with_candidate_checks
+/// [`Source::Screen`]: ../util/qr/enum.Source.html#variant.Screen
+pub struct QRExtractor {
+    timing_refinement: bool,
+    candidate_checks: bool,
+}
 
 impl QRExtractor {
     /// Construct a new QRExtractor
     pub fn new() -> QRExtractor {
-        QRExtractor {}
+        QRExtractor {
+            timing_refinement: false,
+            candidate_checks: true,
+        }
+    }
+
+    /// Move the columns and rows of the grid to where the timing patterns have them, off by default
+    ///
+    /// A label on a curved or crumpled surface is stretched unevenly, so its modules are not where a grid through the
+    /// finder and alignment patterns puts them. The edges between the modules of the timing patterns show where every
+    /// column and row really is, and every column is shifted along the top timing pattern, every row along the left
+    /// one, to match. Codes that are not stretched decode the same either way, only a little slower.
+    pub fn with_timing_refinement(mut self, timing_refinement: bool) -> QRExtractor {
+        self.timing_refinement = timing_refinement;
+        self
+    }
+
+    /// Reject locations that can not be a code before extracting them, on by default
+    ///
+    /// Three checks that read a few dozen pixels come before the expensive steps: the distances between the finder
+    /// patterns have to be about the number of modules of the version, one of the timing patterns has to have at
+    /// least a third of its runs of dark and light, and a sample of the modules has to have both dark and light ones.
+    /// An image with many finder-like shapes, such as text or a pattern, then costs little more than its detection.
+    /// Codes damaged beyond these checks would not decode anyway, but a custom detector with locations that are only
+    /// roughly right can turn them off.
+    pub fn with_candidate_checks(mut self, candidate_checks: bool) -> QRExtractor {
+        self.candidate_checks = candidate_checks;
+        self
     }
 }
 
-impl Extract<GrayImage, QRLocation, QRData, QRError> for QRExtractor {
-    fn extract(&self, prepared: &GrayImage, loc: QRLocation) -> Result<QRData, QRError> {
-        let size = 17 + loc.version * 4;
-        let p = determine_perspective(prepared, loc.version, size, &loc)?;
+impl<P: LumaSource> Extract<P, QRLocation, QRData, QRError> for QRExtractor {
+    fn extract(&self, prepared: &P, loc: QRLocation) -> Result<QRData, QRError> {
+        self.extract_with_hints(prepared, loc, &DecodeHints::new())
+    }
 
-        debug!("PERSPECTIVE {:?}", p);
+    fn extract_with_hints(&self, prepared: &P, loc: QRLocation, hints: &DecodeHints) -> Result<QRData, QRError> {
+        let counted = Counted::new(prepared);
+        let limited = Limited::new(&counted);
+        let extracted = extract(&limited, loc, hints, self.timing_refinement, self.candidate_checks);
 
-        let mut start = loc.top_left - 3.0 * p.dy - 3.0 * p.ddy;
+        metrics::count(|metrics| metrics.extract_pixels += counted.pixels());
 
-        debug!("START {:?}", start);
+        // Past the limit the pixels were all light, what was extracted from them is not the code
+        match limited.spend() {
+            true => extracted,
+            false => Err(QRError::extract("Out of pixels to read")),
+        }
+    }
+}
 
-        let mut data = vec![];
+fn extract<P: LumaSource>(
+    prepared: &P,
+    loc: QRLocation,
+    hints: &DecodeHints,
+    timing_refinement: bool,
+    candidate_checks: bool,
+) -> Result<QRData, QRError> {
+    let source = hints.source;
+    check_location(&loc)?;
+
+    // An expected version is used as it is
+    let mut loc = loc;
+    if hints.version.is_none() {
+        loc.version = determine_version(prepared, &loc, source);
+    }
 
-        #[cfg(feature = "debug-images")]
-        let mut img = DynamicImage::ImageLuma8(prepared.clone()).to_rgb8();
+    let size = 17 + loc.version * 4;
+    if candidate_checks {
+        check_candidate(prepared, &loc, size, source)?;
+    }
 
-        let mut dy = p.dy - 3.0 * p.ddy;
-        let mut dx = p.dx - 3.0 * p.ddx;
-        for _ in 0..size {
-            let mut line = start - 3.0 * dx;
-
-            for _ in 0..size {
-                let x = line.x.round() as u32;
-                let y = line.y.round() as u32;
-                let pixel = prepared.get_pixel(x, y)[0];
-
-                #[cfg(feature = "debug-images")]
-                {
-                    if pixel == 0 {
-                        for i in max(0, x.saturating_sub(2))..min(img.dimensions().0, x + 2) {
-                            for j in max(0, y.saturating_sub(2))..min(img.dimensions().0, y + 2) {
-                                img.put_pixel(i, j, Rgb([255, 0, 0]));
-                            }
-                        }
+    let p = determine_perspective(prepared, loc.version, size, &loc)?;
+
+    debug!("PERSPECTIVE {:?}", p);
+
+    let mut centers = module_centers(&p, size);
+    check_inside(prepared, &centers)?;
+
+    if timing_refinement {
+        refine_with_timing(prepared, &mut centers, size, source);
+    }
+
+    let growth = print_growth(prepared, &centers, size, source);
+    let module_size = module_size(&centers, size);
+
+    debug!("PRINT GROWTH {:?}", growth);
+
+    let mut data = vec![];
+
+    #[cfg(feature = "debug-images")]
+    let mut img = DynamicImage::ImageLuma8(crate::util::to_gray_image(prepared)).to_rgb8();
+
+    for (x, y) in centers {
+        let pixel = if growth.abs() > GROWTH_BIAS_FROM {
+            biased_sample(prepared, x, y, &p, growth)
+        } else {
+            prepared.luma(x, y)
+        };
+
+        #[cfg(feature = "debug-images")]
+        {
+            if pixel == 0 {
+                for i in max(0, x.saturating_sub(2))..min(img.dimensions().0, x + 2) {
+                    for j in max(0, y.saturating_sub(2))..min(img.dimensions().0, y + 2) {
+                        img.put_pixel(i, j, Rgb([255, 0, 0]));
                     }
                 }
+            }
+        }
+
+        data.push(pixel);
+    }
+
+    #[cfg(feature = "debug-images")]
+    {
+        let mut tmp = temp_dir();
+        tmp.push("bardecoder-debug-images");
+        tmp.push("extract");
 
-                data.push(pixel);
-                line = line + dx;
+        if create_dir_all(tmp.clone()).is_ok() {
+            tmp.push(format!(
+                "extract_start_{start_x}_{start_y}_dx_{dx_x}_{dx_y}_dy_{dy_x}_{dy_y}.png",
+                start_x = loc.top_left.x, start_y = loc.top_left.y, dx_x = p.dx.dx, dx_y = p.dx.dy, dy_x = p.dy.dx, dy_y = p.dy.dy
+            ));
+
+            if DynamicImage::ImageRgb8(img).save(tmp.clone()).is_ok() {
+                debug!("Debug image with data pixels saved to {tmp:?}");
             }
-            dx = dx + p.ddx;
+        }
+    }
+
+    let mut data = QRData::new(data, loc.version);
+    data.print_growth = growth;
+    data.module_size = module_size;
+
+    Ok(data)
+}
 
-            start = start + dy;
-            dy = dy + p.ddy;
+// Average distance between the centers of neighbouring modules along the top and left side
+fn module_size(centers: &[(u32, u32)], size: u32) -> f64 {
+    let center = |x: u32, y: u32| {
+        let (x, y) = centers[(y * size + x) as usize];
+        (f64::from(x), f64::from(y))
+    };
+    let distance = |(from_x, from_y): (f64, f64), (to_x, to_y): (f64, f64)| (to_x - from_x).hypot(to_y - from_y);
+    let (top_left, top_right, bottom_left) = (center(0, 0), center(size - 1, 0), center(0, size - 1));
+
+    (distance(top_left, top_right) + distance(top_left, bottom_left)) / f64::from(2 * (size - 1))
+}
+
+// Smaller growth is normal for any print, and not worth the extra samples
+const GROWTH_BIAS_FROM: f64 = 0.1;
+
+// Estimate the print growth from the run lengths along both timing patterns, which alternate dark and light modules
+//
+// With growth g, dark runs are (1 + g) modules long and light runs (1 - g). A timing pattern that does not have exactly
+// one run per module is probably damaged and skipped.
+fn print_growth<P: LumaSource>(prepared: &P, centers: &[(u32, u32)], size: u32, source: Source) -> f64 {
+    let module = |x: u32, y: u32| centers[(y * size + x) as usize];
+    let (mut dark, mut dark_runs, mut light, mut light_runs) = (0, 0, 0, 0);
+
+    for (from, to) in [(module(8, 6), module(size - 9, 6)), (module(6, 8), module(6, size - 9))] {
+        let runs = timing_runs(prepared, from, to, size - 17, source);
+
+        if runs.len() != (size - 16) as usize {
+            continue;
         }
 
-        #[cfg(feature = "debug-images")]
-        {
-            let mut tmp = temp_dir();
-            tmp.push("bardecoder-debug-images");
-            tmp.push("extract");
-
-            if create_dir_all(tmp.clone()).is_ok() {
-                tmp.push(format!(
-                    "extract_start_{start_x}_{start_y}_dx_{dx_x}_{dx_y}_dy_{dy_x}_{dy_y}.png",
-                    start_x = start.x, start_y = start.y, dx_x = dx.dx, dx_y = dx.dy, dy_x = dy.dx, dy_y = dy.dy
-                ));
-
-                if DynamicImage::ImageRgb8(img).save(tmp.clone()).is_ok() {
-                    debug!("Debug image with data pixels saved to {tmp:?}");
+        // The first and last run start and end at the center of a module
+        for (is_dark, length) in &runs[1..runs.len() - 1] {
+            if *is_dark {
+                dark += length;
+                dark_runs += 1;
+            } else {
+                light += length;
+                light_runs += 1;
+            }
+        }
+    }
+
+    if dark_runs == 0 || light_runs == 0 {
+        return 0.0;
+    }
+
+    let dark = f64::from(dark) / f64::from(dark_runs);
+    let light = f64::from(light) / f64::from(light_runs);
+
+    (dark - light) / (dark + light)
+}
+
+// Ties between estimates of the version go to the one with the lowest rank: version information, with the number of
+// bits corrected as its rank, then the timing patterns and then the distance between the finder patterns
+const TIMING_RANK: u32 = 4;
+const FINDER_RANK: u32 = 5;
+
+// The version most estimates agree on
+//
+// The detector estimates the version from the distance between the finder patterns, which is off by one or more when
+// the module size is slightly off, as it is for codes that are tilted, blurred or scaled by a fraction. The number of
+// modules along both timing patterns and, from version 7, both blocks of version information give independent
+// estimates. These are only counted within 2 versions of the estimate of the detector: a damaged timing pattern can
+// have any number of runs, and below version 7 the version information modules are data, which is often close enough
+// to some version information by chance.
+fn determine_version<P: LumaSource>(prepared: &P, loc: &QRLocation, source: Source) -> u32 {
+    let size = 17 + loc.version * 4;
+    let dx = (loc.top_right - loc.top_left) / f64::from(size - 7);
+    let dy = (loc.bottom_left - loc.top_left) / f64::from(size - 7);
+
+    let pixel = |p: Point| pixel(prepared, p);
+
+    // Modules are stepped by the size of the finder modules, which does not depend on the version being right
+    let module = |d: Delta| d / (d.dx.hypot(d.dy) / loc.module_size);
+    let (mx, my) = (module(dx), module(dy));
+
+    let near = |version: u32| (i64::from(version) - i64::from(loc.version)).abs() <= 2;
+    let mut votes = vec![(loc.version, FINDER_RANK)];
+
+    // Along row and column 6 between the centers of the finders, these are the edges of both finders, a light separator
+    // module next to each and every module of a timing pattern: 12 runs fewer than modules in a row
+    let timing = [(loc.top_right, my), (loc.bottom_left, mx)];
+    for (finder, down) in timing.iter() {
+        if let (Some(from), Some(to)) = (pixel(loc.top_left + 3.0 * *down), pixel(*finder + 3.0 * *down)) {
+            let modules = timing_runs(prepared, from, to, size - 7, source).len() as u32 + 12;
+
+            if modules >= 21 && (modules - 17).is_multiple_of(4) && near((modules - 17) / 4) {
+                votes.push(((modules - 17) / 4, TIMING_RANK));
+            }
+        }
+    }
+
+    // Bit i of the version information is in module (i % 3) of row (i / 3) of the block above the bottom left finder,
+    // and mirrored along the diagonal in the block left of the top right finder. Relative to the centers of the
+    // finders, that is 7 to 5 modules away from them and from 3 modules before to 2 after them
+    for (finder, across, along) in [(loc.top_right, mx, my), (loc.bottom_left, my, mx)].iter() {
+        let bits = (0..18).try_fold(0, |bits, bit| {
+            let center = *finder + (f64::from(bit % 3) - 7.0) * *across + (f64::from(bit / 3) - 3.0) * *along;
+            let (x, y) = pixel(center)?;
+
+            Some(if prepared.luma(x, y) == 0 { bits | 1 << bit } else { bits })
+        });
+
+        // With at most one bit wrong, a block is very unlikely to be anything but version information, so it counts
+        // twice
+        if let Some((version, distance)) = bits.and_then(read_version) {
+            if near(version) {
+                let weight = if distance <= 1 { 2 } else { 1 };
+                votes.extend(std::iter::repeat_n((version, distance), weight));
+            }
+        }
+    }
+
+    debug!("VERSION VOTES {votes:?}");
+
+    let support = |version: u32| {
+        let ranks = votes.iter().filter(|(v, _)| *v == version).map(|(_, rank)| *rank);
+        (ranks.clone().count(), Reverse(ranks.min()))
+    };
+
+    // The first estimate wins a tie, for repeatable results
+    votes.iter().rev().map(|(version, _)| *version).max_by_key(|version| support(*version)).unwrap_or(loc.version)
+}
+
+// Shift every column of the grid to where the top timing pattern has its center, and every row to where the left one has
+//
+// The runs are measured between the centers of the finders, which the grid goes through, along row and column 6.
+// Patterns that do not have one run for every module between them are left alone.
+fn refine_with_timing<P: LumaSource>(prepared: &P, centers: &mut [(u32, u32)], size: u32, source: Source) {
+    let columns = timing_shifts(prepared, centers, size, source, |i| (i, 6));
+    let rows = timing_shifts(prepared, centers, size, source, |i| (6, i));
+    let (width, height) = prepared.luma_dimensions();
+
+    for (i, center) in centers.iter_mut().enumerate() {
+        let shift = columns[i % size as usize] + rows[i / size as usize];
+        let x = (f64::from(center.0) + shift.dx).round().clamp(0.0, f64::from(width.saturating_sub(1)));
+        let y = (f64::from(center.1) + shift.dy).round().clamp(0.0, f64::from(height.saturating_sub(1)));
+
+        *center = (x as u32, y as u32);
+    }
+}
+
+// How far the center of every module between two finders is from where the grid has it, along the line through them,
+// by the index of the module along it; 0 for the finders themselves
+//
+// `at` gives the column and row of module `i` along the line. From the center of one finder to the other, there is a
+// run for the edge of each finder, the light separator next to each and every module of the timing pattern. The center
+// of a module is the middle of its run, which print growth does not move; the runs say nothing about how far the
+// modules are off the line, only along it.
+fn timing_shifts<P: LumaSource>(
+    prepared: &P,
+    centers: &[(u32, u32)],
+    size: u32,
+    source: Source,
+    at: impl Fn(u32) -> (u32, u32),
+) -> Vec<Delta> {
+    let center = |i: u32| {
+        let (x, y) = at(i);
+        centers[(y * size + x) as usize]
+    };
+    let point = |(x, y): (u32, u32)| Point {
+        x: f64::from(x),
+        y: f64::from(y),
+    };
+
+    let mut shifts = vec![Delta { dx: 0.0, dy: 0.0 }; size as usize];
+
+    let (from, to) = (center(3), center(size - 4));
+    let runs = timing_runs(prepared, from, to, size - 7, source);
+    let line = point(to) - point(from);
+    let length = line.dx.hypot(line.dy);
+
+    if runs.len() != (size - 12) as usize || length < 1.0 {
+        return shifts;
+    }
+
+    let direction = line / length;
+    let steps = f64::from(line_steps(from, to));
+    let mut start = runs[0].1;
+
+    // Every run but the finders at both ends, which start and end at their centers
+    for (i, (_, run)) in runs.iter().enumerate().take(runs.len() - 1).skip(1) {
+        let middle = f64::from(start) + f64::from(run - 1) / 2.0;
+        start += run;
+
+        let module = i as u32 + 6;
+        let offset = point(from) + middle / steps * line - point(center(module));
+
+        shifts[module as usize] = (offset.dx * direction.dx + offset.dy * direction.dy) * direction;
+    }
+
+    shifts
+}
+
+// The runs along a timing pattern, from the center of one module to the center of another `modules` away
+//
+// On screens, anti-aliasing leaves runs of a pixel or two at the edges of modules. Those are too short to be a module,
+// so they are merged into the run before them.
+fn timing_runs<P: LumaSource>(
+    prepared: &P,
+    from: (u32, u32),
+    to: (u32, u32),
+    modules: u32,
+    source: Source,
+) -> Vec<(bool, u32)> {
+    let runs = pixel_runs(prepared, from, to);
+
+    if source == Source::Print {
+        return runs;
+    }
+
+    let shortest = line_steps(from, to) / (3 * modules);
+    let mut merged: Vec<(bool, u32)> = vec![];
+
+    for (is_dark, length) in runs {
+        match merged.last_mut() {
+            Some((last, last_length)) if *last == is_dark || length < shortest => *last_length += length,
+            _ => merged.push((is_dark, length)),
+        }
+    }
+
+    merged
+}
+
+// Pixels sampled on the line between two pixels, one more than the steps
+fn line_steps(from: (u32, u32), to: (u32, u32)) -> u32 {
+    let (dx, dy) = (f64::from(to.0) - f64::from(from.0), f64::from(to.1) - f64::from(from.1));
+
+    dx.abs().max(dy.abs()).max(1.0) as u32
+}
+
+// Lengths of the runs of dark and light pixels on the line between two pixels
+fn pixel_runs<P: LumaSource>(prepared: &P, from: (u32, u32), to: (u32, u32)) -> Vec<(bool, u32)> {
+    let (width, height) = prepared.luma_dimensions();
+    let (dx, dy) = (f64::from(to.0) - f64::from(from.0), f64::from(to.1) - f64::from(from.1));
+    let steps = line_steps(from, to);
+
+    let mut runs: Vec<(bool, u32)> = vec![];
+
+    for i in 0..=steps {
+        let t = f64::from(i) / f64::from(steps);
+        let x = (f64::from(from.0) + t * dx).round() as u32;
+        let y = (f64::from(from.1) + t * dy).round() as u32;
+
+        if x >= width || y >= height {
+            return vec![];
+        }
+
+        let is_dark = prepared.luma(x, y) == 0;
+        match runs.last_mut() {
+            Some((last, length)) if *last == is_dark => *length += 1,
+            _ => runs.push((is_dark, 1)),
+        }
+    }
+
+    runs
+}
+
+// Sample the center and four points a quarter module around it. Dark modules spread into their light neighbours
+// when over-inked, so more dark samples are needed to read a module as dark, and fewer when under-inked.
+fn biased_sample<P: LumaSource>(prepared: &P, x: u32, y: u32, p: &Perspective, growth: f64) -> u8 {
+    let (width, height) = prepared.luma_dimensions();
+    let center = Point {
+        x: f64::from(x),
+        y: f64::from(y),
+    };
+
+    let mut dark = 0;
+    for offset in &[p.dx * 0.0, p.dx * 0.25, p.dx * -0.25, p.dy * 0.25, p.dy * -0.25] {
+        let sample = center + *offset;
+        let (sx, sy) = (sample.x.round(), sample.y.round());
+
+        let luma = if sx < 0.0 || sy < 0.0 || sx >= f64::from(width) || sy >= f64::from(height) {
+            prepared.luma(x, y)
+        } else {
+            prepared.luma(sx as u32, sy as u32)
+        };
+
+        if luma == 0 {
+            dark += 1;
+        }
+    }
+
+    let required = (3.0 + 2.5 * growth).round().clamp(1.0, 5.0);
+    if f64::from(dark) >= required {
+        0
+    } else {
+        255
+    }
+}
+
+/// Pixel locations of the modules of a ring around the code, `None` for those outside the image
+pub(crate) type Ring = Vec<Option<(u32, u32)>>;
+
+/// Pixel locations of the modules of a code and of the rings around it
+pub(crate) struct SampledGrid {
+    /// Every module, row by row
+    pub(crate) centers: Vec<(u32, u32)>,
+    /// The rings from the inside out
+    pub(crate) rings: Vec<Ring>,
+}
+
+/// Pixel locations `QRExtractor` samples for every module, row by row, and those of the modules in `rings` rings of
+/// modules around the code, from the inside out
+///
+/// Each ring goes round the code once, starting at its top left corner.
+pub(crate) fn sample_grid<P: LumaSource>(
+    prepared: &P,
+    loc: &QRLocation,
+    rings: u32,
+) -> Result<SampledGrid, QRError> {
+    check_location(loc)?;
+
+    let size = 17 + loc.version * 4;
+    let p = determine_perspective(prepared, loc.version, size, loc)?;
+
+    let centers = module_centers(&p, size);
+    check_inside(prepared, &centers)?;
+
+    let module = |x: i64, y: i64| pixel(prepared, p.map(x as f64, y as f64)?);
+
+    let rings = (1..=i64::from(rings))
+        .map(|ring| {
+            let (first, last) = (-ring, i64::from(size) - 1 + ring);
+            let top = (first..last).map(|x| (x, first));
+            let right = (first..last).map(|y| (last, y));
+            let bottom = (first + 1..=last).rev().map(|x| (x, last));
+            let left = (first + 1..=last).rev().map(|y| (first, y));
+
+            top.chain(right).chain(bottom).chain(left).map(|(x, y)| module(x, y)).collect()
+        })
+        .collect();
+
+    Ok(SampledGrid { centers, rings })
+}
+
+// A location from a custom detector or the caller of `Decoder::decode_at` can be anything, so it is checked before
+// any arithmetic on it
+fn check_location(loc: &QRLocation) -> Result<(), QRError> {
+    if !(1..=40).contains(&loc.version) {
+        return Err(QRError::invalid_input(format!("Invalid version {}, expected 1 to 40", loc.version)));
+    }
+
+    let finite = [loc.top_left, loc.top_right, loc.bottom_left].iter().all(|p| p.x.is_finite() && p.y.is_finite());
+    if !finite || !loc.module_size.is_finite() || loc.module_size <= 0.0 {
+        let finders = (loc.top_left, loc.top_right, loc.bottom_left);
+        return Err(QRError::invalid_input(format!(
+            "Invalid location: finder patterns {finders:?} with modules of {}",
+            loc.module_size
+        )));
+    }
+
+    Ok(())
+}
+
+// Modules across the code the distances between the finders may be off by, as a factor, before a location is rejected.
+// At a steep angle one side of a code is much shorter than the other.
+const CANDIDATE_MODULES_OFF: f64 = 2.0;
+
+// Modules sampled along each side for the contrast check, and the least part of them that has to be dark and light
+const CANDIDATE_SAMPLES: u32 = 8;
+const CANDIDATE_CONTRAST: f64 = 0.1;
+
+// Cheap checks that the location is a code of this size, before the alignment search, sampling and error correction
+//
+// A location from the detector has the module size and version estimated from the same finders, so mostly the timing
+// patterns and samples throw out false positives: three finder-like shapes in text or a solid area between them.
+fn check_candidate<P: LumaSource>(prepared: &P, loc: &QRLocation, size: u32, source: Source) -> Result<(), QRError> {
+    let reject = |reason: &str| {
+        Err(QRError::new(format!("Not a QR Code, {reason}")))
+    };
+
+    let dx = (loc.top_right - loc.top_left) / f64::from(size - 7);
+    let dy = (loc.bottom_left - loc.top_left) / f64::from(size - 7);
+
+    let expected = f64::from(size - 7);
+    let consistent = [dx, dy].iter().all(|d| {
+        let modules = expected * d.dx.hypot(d.dy) / loc.module_size;
+        modules * CANDIDATE_MODULES_OFF >= expected && modules <= expected * CANDIDATE_MODULES_OFF
+    });
+    if !consistent {
+        return reject("the finder patterns are not as far apart as the modules of the version");
+    }
+
+    let pixel = |p: Point| pixel(prepared, p);
+
+    // Along row and column 6 between the centers of the finders, as for the version, but only a part of the runs has to
+    // be there for a damaged or blurred timing pattern. Three runs are what solid light between two finders has
+    let least_runs = ((size - 12) / 3).max(5) as usize;
+    let timing = [(loc.top_right, dy), (loc.bottom_left, dx)].iter().any(|(finder, down)| {
+        match (pixel(loc.top_left + 3.0 * *down), pixel(*finder + 3.0 * *down)) {
+            (Some(from), Some(to)) => timing_runs(prepared, from, to, size - 7, source).len() >= least_runs,
+            _ => false,
+        }
+    });
+    if !timing {
+        return reject("there are no timing patterns between the finder patterns");
+    }
+
+    // Modules spread evenly over the code, from the top left one, 3 modules before the center of its finder
+    let (mut dark, mut sampled) = (0, 0);
+    for j in 0..CANDIDATE_SAMPLES {
+        for i in 0..CANDIDATE_SAMPLES {
+            let module = |k: u32| f64::from(k * (size - 1) / (CANDIDATE_SAMPLES - 1)) - 3.0;
+
+            if let Some((x, y)) = pixel(loc.top_left + module(i) * dx + module(j) * dy) {
+                sampled += 1;
+                if prepared.luma(x, y) == 0 {
+                    dark += 1;
                 }
             }
         }
+    }
+
+    let least = (f64::from(sampled) * CANDIDATE_CONTRAST).ceil() as u32;
+    if sampled == 0 || dark < least || sampled - dark < least {
+        return reject("its modules are all dark or all light");
+    }
+
+    Ok(())
+}
+
+// The pixel nearest to the point, `None` outside the image
+fn pixel<P: LumaSource>(prepared: &P, p: Point) -> Option<(u32, u32)> {
+    let (width, height) = prepared.luma_dimensions();
+    let (x, y) = (p.x.round(), p.y.round());
+
+    match x >= 0.0 && y >= 0.0 && x < f64::from(width) && y < f64::from(height) {
+        true => Some((x as u32, y as u32)),
+        false => None,
+    }
+}
+
+// A grid through an alignment pattern in the wrong place, or of a code cut off by the edge of the image, can have
+// modules outside of it
+fn check_inside<P: LumaSource>(prepared: &P, centers: &[(u32, u32)]) -> Result<(), QRError> {
+    let (width, height) = prepared.luma_dimensions();
+
+    if centers.iter().any(|&(x, y)| x >= width || y >= height) {
+        return Err(QRError::extract("Module grid extends outside the image"));
+    }
+
+    Ok(())
+}
+
+// Pixel locations of the center of every module, row by row
+#[cfg(not(feature = "fixed-point"))]
+fn module_centers(p: &Perspective, size: u32) -> Vec<(u32, u32)> {
+    let mut centers = Vec::with_capacity((size * size) as usize);
+
+    for row in 0..size {
+        let ([mut line_x, mut line_y, mut line_w], [x, y, w]) = p.row(row);
+
+        for _ in 0..size {
+            centers.push(match line_w > 0.0 {
+                true => ((line_x / line_w).round() as u32, (line_y / line_w).round() as u32),
+                false => (0, 0),
+            });
+
+            line_x += x;
+            line_y += y;
+            line_w += w;
+        }
+    }
 
-        Ok(QRData::new(data, loc.version))
+    centers
+}
+
+// Same as above, but stepping through the grid in fixed point, since this runs for every module
+#[cfg(feature = "fixed-point")]
+fn module_centers(p: &Perspective, size: u32) -> Vec<(u32, u32)> {
+    let mut centers = Vec::with_capacity((size * size) as usize);
+
+    for row in 0..size {
+        let (start, step) = p.row(row);
+        let (mut line, step) = (FixedProjective::from(start), FixedProjective::from(step));
+
+        for _ in 0..size {
+            centers.push(line.pixel());
+            line = line + step;
+        }
     }
+
+    centers
 }
 
-fn determine_perspective(
-    prepared: &GrayImage,
+// Modules along a row or column the alignment pattern may be off its estimate for the grid to stay affine
+const MAX_BENT_OFFSET: f64 = 1.0;
+
+fn determine_perspective<S: LumaSource>(
+    prepared: &S,
     version: u32,
     size: u32,
     loc: &QRLocation,
@@ -111,13 +707,18 @@ fn determine_perspective(
     let mut dy = loc.bottom_left - loc.top_left;
     dy = dy / f64::from(size - 7);
 
+    // Without an alignment pattern, the three finders span a parallelogram
+    let affine = Homography::affine(loc.top_left - 3.0 * dx - 3.0 * dy, dx, dy);
+
+    let no_bend = Delta { dx: 0.0, dy: 0.0 };
+
     if version == 1 {
-        return Ok(Perspective::new(
+        return Ok(Perspective {
             dx,
-            Delta { dx: 0.0, dy: 0.0 },
             dy,
-            Delta { dx: 0.0, dy: 0.0 },
-        ));
+            grid: affine,
+            ddx: no_bend,
+        });
     }
 
     let mut est_alignment = Point {
@@ -127,7 +728,11 @@ fn determine_perspective(
 
     let mut found = false;
 
-    'distance: for i in 0..4 {
+    // The estimate assumes a parallelogram, so on a tilted code the pattern is further off the larger the code is.
+    // Search rings of half a module around it, out to an eighth of the code
+    let radius = (size / 8).max(4) as i32;
+
+    'distance: for i in 0..radius {
         'scale: for j in &[0, 1, -1, 2, -2, 3] {
             let scale = 1.0 + (f64::from(*j) / 10.0);
 
@@ -174,25 +779,24 @@ fn determine_perspective(
         }
     }
 
+    let lost = || QRError::extract("Unable to find alignment pattern");
+
     if !found {
-        return Err(QRError {
-            msg: String::from("Unable to find alignment pattern"),
-        });
+        return Err(lost());
     }
 
-    let al_x = est_alignment.x.round() as u32;
-    let al_y = est_alignment.y.round() as u32;
+    let (al_x, al_y) = pixel(prepared, est_alignment).ok_or_else(lost)?;
     let mut left_x = 0;
-    let mut right_x = prepared.dimensions().0;
+    let mut right_x = prepared.luma_dimensions().0;
     for x in (0..al_x).rev() {
-        if prepared.get_pixel(x, al_y)[0] == 255 {
+        if prepared.luma(x, al_y) == 255 {
             left_x = x;
             break;
         }
     }
 
-    for x in al_x..prepared.dimensions().0 {
-        if prepared.get_pixel(x, al_y)[0] == 255 {
+    for x in al_x..prepared.luma_dimensions().0 {
+        if prepared.luma(x, al_y) == 255 {
             right_x = x;
             break;
         }
@@ -201,20 +805,20 @@ fn determine_perspective(
     debug!("LEFT X {left_x} RIGHT X {right_x}");
     est_alignment.x = (f64::from(left_x) + f64::from(right_x)) / 2.0;
 
-    let al_x = est_alignment.x.round() as u32;
-    let al_y = est_alignment.y.round() as u32;
+    // Without a light pixel to the right, the middle can be the pixel just past the edge
+    let (al_x, al_y) = pixel(prepared, est_alignment).ok_or_else(lost)?;
     let mut top_y = 0;
-    let mut bottom_y = prepared.dimensions().1;
+    let mut bottom_y = prepared.luma_dimensions().1;
 
     for y in (0..al_y).rev() {
-        if prepared.get_pixel(al_x, y)[0] == 255 {
+        if prepared.luma(al_x, y) == 255 {
             top_y = y;
             break;
         }
     }
 
-    for y in al_y..prepared.dimensions().1 {
-        if prepared.get_pixel(al_x, y)[0] == 255 {
+    for y in al_y..prepared.luma_dimensions().1 {
+        if prepared.luma(al_x, y) == 255 {
             bottom_y = y;
             break;
         }
@@ -225,7 +829,7 @@ fn determine_perspective(
 
     #[cfg(feature = "debug-images")]
     {
-        let mut img = DynamicImage::ImageLuma8(prepared.clone()).to_rgb8();
+        let mut img = DynamicImage::ImageLuma8(crate::util::to_gray_image(prepared)).to_rgb8();
 
         let x_start = max(0, (est_alignment.x - 2.5 * loc.module_size) as u32);
         let x_end = min(
@@ -267,16 +871,47 @@ fn determine_perspective(
 
     debug!("ORIG EST {orig_estimate:?}, NEW EST {est_alignment:?}");
 
-    let mut delta = est_alignment - orig_estimate;
+    let delta = est_alignment - orig_estimate;
 
     debug!("DELTA {delta:?}");
 
-    delta = delta / f64::from((size - 10) * (size - 10));
+    // Within a module of the estimate, the offset is as much the error of measuring the centers of the patterns as
+    // perspective. A projective grid would make more of that error towards the far corner, so the affine grid is only
+    // bent through the alignment pattern, the step along each row growing by the same amount every row
+    let modules = |step: Delta| (delta.dx * step.dx + delta.dy * step.dy) / (step.dx * step.dx + step.dy * step.dy);
+    if modules(dx).abs() <= MAX_BENT_OFFSET && modules(dy).abs() <= MAX_BENT_OFFSET {
+        return Ok(Perspective {
+            dx,
+            dy,
+            grid: affine,
+            ddx: delta / f64::from((size - 10) * (size - 10)),
+        });
+    }
 
-    Ok(Perspective::new(dx, delta, dy, Delta { dx: 0.0, dy: 0.0 }))
+    // Further off the code is clearly photographed at an angle. The alignment pattern is the fourth control point, at
+    // module (size - 7, size - 7), so the grid follows the perspective, which extrapolating from the three finders
+    // alone gets wrong
+    let module = |x: u32, y: u32| Point {
+        x: f64::from(x),
+        y: f64::from(y),
+    };
+    let grid = [module(3, 3), module(size - 4, 3), module(size - 7, size - 7), module(3, size - 4)];
+    let image = [loc.top_left, loc.top_right, est_alignment, loc.bottom_left];
+
+    let last = f64::from(size - 1);
+    let grid = Homography::from_points(grid, image)
+        .filter(|h| [(0.0, 0.0), (last, 0.0), (0.0, last), (last, last)].iter().all(|(x, y)| h.map(*x, *y).is_some()))
+        .unwrap_or(affine);
+
+    Ok(Perspective {
+        dx,
+        dy,
+        grid,
+        ddx: no_bend,
+    })
 }
 
-fn is_alignment(prepared: &GrayImage, p: Point, dx: Delta, dy: Delta, scale: f64) -> bool {
+fn is_alignment<S: LumaSource>(prepared: &S, p: Point, dx: Delta, dy: Delta, scale: f64) -> bool {
     if p.x < 0.0 || p.y < 0.0 {
         return false;
     }
@@ -286,7 +921,7 @@ fn is_alignment(prepared: &GrayImage, p: Point, dx: Delta, dy: Delta, scale: f64
 
     #[cfg(feature = "debug-images")]
     {
-        let mut img = DynamicImage::ImageLuma8(prepared.clone()).to_rgb8();
+        let mut img = DynamicImage::ImageLuma8(crate::util::to_gray_image(prepared)).to_rgb8();
 
         for i in -2..3 {
             for j in -2..3 {
@@ -315,75 +950,220 @@ fn is_alignment(prepared: &GrayImage, p: Point, dx: Delta, dy: Delta, scale: f64
         }
     }
 
-    let top_left = p - 2.0 * dx - 2.0 * dy;
-    if top_left.x < 0.0 || top_left.y < 0.0 {
-        return false;
-    }
-
-    let bottom_right = p + 2.0 * dx + 2.0 * dy;
-    let dims = prepared.dimensions();
-    if bottom_right.x > f64::from(dims.0) || bottom_right.y > f64::from(dims.1) {
+    // Every sample below is between these corners, so with the corners inside the image all of them are, also when
+    // the code is at an angle
+    let corners = [p - 2.0 * dx - 2.0 * dy, p + 2.0 * dx - 2.0 * dy, p - 2.0 * dx + 2.0 * dy, p + 2.0 * dx + 2.0 * dy];
+    if corners.iter().any(|corner| pixel(prepared, *corner).is_none()) {
         return false;
     }
 
     for x in -2..2 {
         let twice_up = p - f64::from(x) * dx - 2.0 * dy;
-        if prepared.get_pixel(twice_up.x.round() as u32, twice_up.y.round() as u32)[0] == 255 {
+        if prepared.luma(twice_up.x.round() as u32, twice_up.y.round() as u32) == 255 {
             return false;
         }
 
         let twice_down = p - f64::from(x) * dx + 2.0 * dy;
-        if prepared.get_pixel(twice_down.x.round() as u32, twice_down.y.round() as u32)[0] == 255 {
+        if prepared.luma(twice_down.x.round() as u32, twice_down.y.round() as u32) == 255 {
             return false;
         }
     }
 
     for y in -1..1 {
         let twice_left = p - 2.0 * dx - f64::from(y) * dy;
-        if prepared.get_pixel(twice_left.x.round() as u32, twice_left.y.round() as u32)[0] == 255 {
+        if prepared.luma(twice_left.x.round() as u32, twice_left.y.round() as u32) == 255 {
             return false;
         }
 
         let twice_right = p + 2.0 * dx - f64::from(y) * dy;
-        if prepared.get_pixel(twice_right.x.round() as u32, twice_right.y.round() as u32)[0] == 255
+        if prepared.luma(twice_right.x.round() as u32, twice_right.y.round() as u32) == 255
         {
             return false;
         }
 
         let left = p - dx - f64::from(y) * dy;
-        if prepared.get_pixel(left.x.round() as u32, left.y.round() as u32)[0] == 0 {
+        if prepared.luma(left.x.round() as u32, left.y.round() as u32) == 0 {
             return false;
         }
 
         let right = p - dx - f64::from(y) * dy;
-        if prepared.get_pixel(right.x.round() as u32, right.y.round() as u32)[0] == 0 {
+        if prepared.luma(right.x.round() as u32, right.y.round() as u32) == 0 {
             return false;
         }
     }
 
     let up = p - dy;
-    if prepared.get_pixel(up.x.round() as u32, up.y.round() as u32)[0] == 0 {
+    if prepared.luma(up.x.round() as u32, up.y.round() as u32) == 0 {
         return false;
     }
 
     let down = p + dy;
-    if prepared.get_pixel(down.x.round() as u32, down.y.round() as u32)[0] == 0 {
+    if prepared.luma(down.x.round() as u32, down.y.round() as u32) == 0 {
         return false;
     }
 
-    prepared.get_pixel(p.x.round() as u32, p.y.round() as u32)[0] == 0
+    prepared.luma(p.x.round() as u32, p.y.round() as u32) == 0
 }
 
+// Where the modules are in the image
 #[derive(Debug)]
 struct Perspective {
+    // Average size and direction of a module, from the finders
     dx: Delta,
-    ddx: Delta,
     dy: Delta,
-    ddy: Delta,
+    grid: Homography,
+    // How much the step along a row grows every row below the top left finder, only for an affine grid
+    ddx: Delta,
 }
 
 impl Perspective {
-    fn new(dx: Delta, ddx: Delta, dy: Delta, ddy: Delta) -> Perspective {
-        Perspective { dx, ddx, dy, ddy }
+    // Center of module (x, y) in the image, `None` if it is behind the camera
+    fn map(&self, x: f64, y: f64) -> Option<Point> {
+        Some(self.grid.map(x, y)? + (x - 3.0) * (y - 3.0) * self.ddx)
+    }
+
+    // The first module of a row in projective coordinates, the x and y numerators and the denominator, and the step to
+    // the next module along it
+    fn row(&self, row: u32) -> ([f64; 3], [f64; 3]) {
+        let [x, y, w] = self.grid.rows();
+        let row = f64::from(row);
+        let bend = (row - 3.0) * self.ddx;
+
+        (
+            [x[1] * row + x[2] - 3.0 * bend.dx, y[1] * row + y[2] - 3.0 * bend.dy, w[1] * row + w[2]],
+            [x[0] + bend.dx, y[0] + bend.dy, w[0]],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::LumaImage;
+
+    #[test]
+    fn test_print_growth() {
+        // Version 1 with modules of 10 pixels, only the timing patterns, with dark modules 2 pixels too wide on every side
+        let centers: Vec<(u32, u32)> = (0..21 * 21).map(|i| (i % 21 * 10 + 5, i / 21 * 10 + 5)).collect();
+        let timing = |along: u32, across: u32| {
+            across / 10 == 6 && (80..130).contains(&along) && (along + 2) % 20 < 14
+        };
+        let prepared = LumaImage::from_fn(210, 210, |x, y| if timing(x, y) || timing(y, x) { 0 } else { 255 });
+
+        let growth = print_growth(&prepared, &centers, 21, Source::Print);
+        assert!((growth - 0.4).abs() < 0.01, "growth {}", growth);
+
+        let blank = LumaImage::from_fn(210, 210, |_, _| 255);
+        assert_eq!(0.0, print_growth(&blank, &centers, 21, Source::Print));
+    }
+
+    #[test]
+    fn test_refine_with_timing() {
+        // Version 1 with modules of 10 pixels and only row 6, in which the edges from column 9 to 12 are 3 pixels right
+        let mut centers: Vec<(u32, u32)> = (0..21 * 21).map(|i| (i % 21 * 10 + 5, i / 21 * 10 + 5)).collect();
+        let edge = |k: u32| if (9..=12).contains(&k) { k * 10 + 3 } else { k * 10 };
+        let column = |x: u32| (0..21).find(|k| x < edge(k + 1)).unwrap();
+        let dark = |k: u32| !(7..=13).contains(&k) || k.is_multiple_of(2);
+        let prepared = LumaImage::from_fn(210, 210, |x, y| if y / 10 == 6 && dark(column(x)) { 0 } else { 255 });
+
+        refine_with_timing(&prepared, &mut centers, 21, Source::Print);
+
+        for (i, center) in centers.iter().enumerate() {
+            let (x, y) = (i as u32 % 21, i as u32 / 21);
+            let shift = match x {
+                9..=11 => 3,
+                8 | 12 => 1,
+                _ => 0,
+            };
+
+            assert_eq!(*center, (x * 10 + 5 + shift, y * 10 + 5), "module {} {}", x, y);
+        }
+    }
+
+    #[test]
+    fn test_degenerate_locations() {
+        // Stripes, so there are dark and light pixels everywhere to find alignment patterns and timing patterns in
+        let prepared = LumaImage::from_fn(60, 60, |x, y| if (x / 3 + y / 5) % 2 == 0 { 0 } else { 255 });
+        let point = |x: f64, y: f64| Point { x, y };
+        let loc = |top_left: Point, top_right: Point, bottom_left: Point, module_size: f64, version: u32| QRLocation {
+            top_left,
+            top_right,
+            bottom_left,
+            module_size,
+            version,
+        };
+        let error = |loc: QRLocation| QRExtractor::new().extract(&prepared, loc).unwrap_err().msg;
+
+        let (a, b, c) = (point(10.0, 10.0), point(50.0, 10.0), point(10.0, 50.0));
+        assert!(error(loc(a, b, c, 2.0, 0)).starts_with("Invalid version 0"));
+        assert!(error(loc(a, b, c, 2.0, u32::MAX)).starts_with("Invalid version"));
+        assert!(error(loc(point(f64::NAN, 10.0), b, c, 2.0, 2)).starts_with("Invalid location"));
+        assert!(error(loc(a, point(f64::INFINITY, 10.0), c, 2.0, 2)).starts_with("Invalid location"));
+        assert!(error(loc(a, b, c, 0.0, 2)).starts_with("Invalid location"));
+        assert!(sample_grid(&prepared, &loc(a, b, c, -1.0, 2), 1).is_err());
+
+        // Anything else is extracted or an error, but never a panic
+        let corners = [0.4, 59.6, -0.6, 60.0, -1e300, 1e300];
+        for (i, x) in corners.iter().enumerate() {
+            for (j, y) in corners.iter().enumerate() {
+                let extractor = QRExtractor::new().with_timing_refinement((i + j) % 2 == 0);
+                let (far, near) = (point(*x, *y), point(corners[(i + 1) % corners.len()], 30.0));
+
+                for version in [1, 2] {
+                    let _ = extractor.extract(&prepared, loc(far, near, c, 1.5, version));
+                    let _ = extractor.extract(&prepared, loc(a, far, near, 1e-9, version));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_candidate() {
+        let point = |x: f64, y: f64| Point { x, y };
+        // Version 1 with modules of 10 pixels and the finders centered at 35 and 175, as in `test_print_growth`
+        let loc = QRLocation {
+            top_left: point(35.0, 35.0),
+            top_right: point(175.0, 35.0),
+            bottom_left: point(35.0, 175.0),
+            module_size: 10.0,
+            version: 1,
+        };
+        let check = |prepared: &LumaImage, loc: &QRLocation| check_candidate(prepared, loc, 21, Source::Print);
+
+        // The finders and both timing patterns
+        let finder = |x: u32, y: u32| {
+            let (x, y) = (x % 140 / 10, y % 140 / 10);
+            x < 7 && y < 7 && x.abs_diff(3).max(y.abs_diff(3)) != 2
+        };
+        let timing = |along: u32, across: u32| {
+            across / 10 == 6 && (80..130).contains(&along) && (along / 10).is_multiple_of(2)
+        };
+        let finders = |x: u32, y: u32| (x < 140 || y < 140) && finder(x, y);
+        let code = |x: u32, y: u32| finders(x, y) || timing(x, y) || timing(y, x);
+        let prepared = LumaImage::from_fn(210, 210, |x, y| if code(x, y) { 0 } else { 255 });
+        assert_eq!(check(&prepared, &loc), Ok(()));
+
+        let far = QRLocation {
+            module_size: 3.0,
+            ..loc.clone()
+        };
+        assert!(check(&prepared, &far).unwrap_err().msg.contains("not as far apart"));
+
+        let finders_only = LumaImage::from_fn(210, 210, |x, y| if finders(x, y) { 0 } else { 255 });
+        assert!(check(&finders_only, &loc).unwrap_err().msg.contains("no timing patterns"));
+
+        // Stripes where the timing patterns are, and light everywhere else
+        let stripes = |x: u32, y: u32| (60..70).contains(&y) && x % 4 < 2 || (60..70).contains(&x) && y % 4 < 2;
+        let timing_only = LumaImage::from_fn(210, 210, |x, y| if stripes(x, y) { 0 } else { 255 });
+        assert!(check(&timing_only, &loc).unwrap_err().msg.contains("all dark or all light"));
+    }
+
+    #[test]
+    fn test_check_inside() {
+        let prepared = LumaImage::from_fn(20, 10, |_, _| 255);
+
+        assert!(check_inside(&prepared, &[(0, 0), (19, 9)]).is_ok());
+        assert!(check_inside(&prepared, &[(0, 0), (20, 9)]).is_err());
+        assert!(check_inside(&prepared, &[(5, 10)]).is_err());
     }
 }
@@ -15,14 +15,32 @@ extern crate newtype_derive;
 
 mod decoder;
 
+#[cfg(feature = "camera")]
+pub mod camera;
+#[cfg(feature = "content")]
+pub mod content;
 pub mod decode;
 pub mod detect;
+#[cfg(feature = "encode")]
+pub mod encode;
 pub mod extract;
 pub mod prepare;
 pub mod util;
+#[cfg(feature = "video")]
+pub mod video;
 
+#[cfg(feature = "image")]
 pub use crate::decoder::{
     default_builder, default_builder_with_info, default_decoder, default_decoder_with_info,
+    try_default_decoder, try_default_decoder_with_info,
 };
-pub use crate::decoder::{BuilderError, Decoder, DecoderBuilder};
-pub use crate::util::qr::{ECLevel, QRError, QRInfo};
+pub use crate::decoder::{default_luma_decoder, default_luma_decoder_with_info};
+pub use crate::decoder::{BuilderError, DecodedContent, Decoder, DecoderBuilder, FrameResult, Scanlines};
+pub use crate::decoder::{ComponentMissing, ComponentProvided, TypedDecoderBuilder};
+#[cfg(feature = "tiff")]
+pub use crate::decoder::PageResult;
+pub use crate::util::qr::{CodeError, DecodeHints, ECLevel, ErrorCode, PhysicalSize, QRError, QRInfo, Source, Stage};
+pub use crate::util::limits::ResourceLimits;
+pub use crate::util::quality::SymbolQuality;
+pub use crate::util::session::ScanSession;
+pub use crate::util::trace::DecodeTrace;
@@ -0,0 +1,106 @@
+// BlockedMean on the GPU, see blockedmean.rs for the CPU version this has to match exactly
+//
+// Pixels are packed 4 to a u32, with every row padded to a multiple of 4 pixels.
+// Instead of comparing against the floating point mean, the comparisons are done on the totals and
+// counts directly: `pixel > total / count` is the same as `pixel * count > total`.
+
+struct Params {
+    width: u32,
+    height: u32,
+    // Row length in u32 words
+    stride: u32,
+    block_size: u32,
+    // Number of blocks on either side of a block to take the mean over
+    block_stride: u32,
+    blocks_x: u32,
+    blocks_y: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> pixels: array<u32>;
+@group(0) @binding(2) var<storage, read_write> blocks: array<vec2<u32>>;
+@group(0) @binding(3) var<storage, read_write> means: array<vec2<u32>>;
+@group(0) @binding(4) var<storage, read_write> output: array<u32>;
+
+fn pixel(x: u32, y: u32) -> u32 {
+    return (pixels[y * params.stride + x / 4u] >> ((x % 4u) * 8u)) & 0xFFu;
+}
+
+// Total and count of the pixels in every block
+@compute @workgroup_size(8, 8)
+fn block_sums(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.blocks_x || id.y >= params.blocks_y) {
+        return;
+    }
+
+    let x_start = id.x * params.block_size;
+    let y_start = id.y * params.block_size;
+    let x_end = min(x_start + params.block_size, params.width);
+    let y_end = min(y_start + params.block_size, params.height);
+
+    var total = 0u;
+    var count = 0u;
+    for (var y = y_start; y < y_end; y++) {
+        for (var x = x_start; x < x_end; x++) {
+            total += pixel(x, y);
+            count += 1u;
+        }
+    }
+
+    blocks[id.y * params.blocks_x + id.x] = vec2<u32>(total, count);
+}
+
+// Total and count of the surrounding block of blocks, for every block
+@compute @workgroup_size(8, 8)
+fn block_means(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.blocks_x || id.y >= params.blocks_y) {
+        return;
+    }
+
+    let s = params.block_stride;
+    let x_start = select(0u, id.x - s, id.x >= s);
+    let y_start = select(0u, id.y - s, id.y >= s);
+    let x_end = min(params.blocks_x - 1u, id.x + s);
+    let y_end = min(params.blocks_y - 1u, id.y + s);
+
+    var sum = vec2<u32>(0u, 0u);
+    for (var x = x_start; x < x_end; x++) {
+        for (var y = y_start; y < y_end; y++) {
+            sum += blocks[y * params.blocks_x + x];
+        }
+    }
+
+    means[id.y * params.blocks_x + id.x] = sum;
+}
+
+// Threshold 4 pixels at a time, so every invocation writes its own u32
+@compute @workgroup_size(8, 8)
+fn threshold(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.stride || id.y >= params.height) {
+        return;
+    }
+
+    var word = 0u;
+    for (var i = 0u; i < 4u; i++) {
+        let x = id.x * 4u + i;
+        if (x >= params.width) {
+            break;
+        }
+
+        let mean = means[(id.y / params.block_size) * params.blocks_x + x / params.block_size];
+        let p = pixel(x, id.y);
+
+        var value = 0u;
+        if (mean.x > 250u * mean.y) {
+            value = 255u;
+        } else if (mean.x < 5u * mean.y) {
+            value = 0u;
+        } else if (p * mean.y > mean.x) {
+            value = 255u;
+        }
+
+        word |= value << (i * 8u);
+    }
+
+    output[id.y * params.stride + id.x] = word;
+}
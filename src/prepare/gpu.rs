@@ -0,0 +1,411 @@
+use super::{BlockedMean, Prepare};
+
+use image::{DynamicImage, GrayImage};
+
+use std::sync::{mpsc, Mutex};
+
+/// [`BlockedMean`] running as wgpu compute shaders, for high resolution images where preparing dominates the
+/// decoding time
+///
+/// The output is exactly the same as that of the CPU implementation with the same parameters, so it can replace
+/// [`BlockedMean`] in any [`Decoder`]. The result is a thresholded `GrayImage`, which is what the detect and extract
+/// stages work on.
+///
+/// When no GPU adapter is available, or a GPU operation fails, preparing automatically falls back to the CPU.
+/// Use [`uses_gpu`] to find out whether a GPU was found.
+///
+/// # Example
+/// ```ignore
+/// use bardecoder::prepare::GpuBlockedMean;
+///
+/// let mut builder = bardecoder::default_builder();
+/// builder.prepare(Box::new(GpuBlockedMean::new(5, 7)));
+/// let decoder = builder.build().unwrap();
+/// ```
+///
+/// [`BlockedMean`]: struct.BlockedMean.html
+/// [`Decoder`]: ../struct.Decoder.html
+/// [`uses_gpu`]: #method.uses_gpu
+pub struct GpuBlockedMean {
+    block_size: u32,
+    block_mean_size: u32,
+    cpu: BlockedMean,
+    gpu: Option<Gpu>,
+}
+
+impl GpuBlockedMean {
+    /// Construct a new GpuBlockedMean, setting up the GPU if one is available
+    ///
+    /// # Arguments
+    ///
+    /// * `block_size`: width in pixels of each block
+    /// * `block_mean_size`: width in blocks of each block of blocks
+    pub fn new(block_size: u32, block_mean_size: u32) -> GpuBlockedMean {
+        let gpu = match Gpu::new() {
+            Ok(gpu) => Some(gpu),
+            Err(e) => {
+                warn!("No GPU available for GpuBlockedMean, using the CPU instead: {}", e);
+                None
+            }
+        };
+
+        GpuBlockedMean {
+            block_size,
+            block_mean_size,
+            cpu: BlockedMean::new(block_size, block_mean_size),
+            gpu,
+        }
+    }
+
+    /// Set the grayscale value transparent pixels are composited over, see [`BlockedMean::with_background`]
+    ///
+    /// [`BlockedMean::with_background`]: struct.BlockedMean.html#method.with_background
+    pub fn with_background(mut self, background: u8) -> GpuBlockedMean {
+        self.cpu = self.cpu.with_background(background);
+        self
+    }
+
+    /// Whether a GPU was found. If not, all images are prepared on the CPU
+    pub fn uses_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    fn threshold(&self, grayscale: GrayImage) -> GrayImage {
+        if let Some(gpu) = &self.gpu {
+            match gpu.threshold(&grayscale, self.block_size, self.block_mean_size) {
+                Ok(thresholded) => return thresholded,
+                Err(e) => warn!("GPU thresholding failed, falling back to the CPU: {}", e),
+            }
+        }
+
+        self.cpu.threshold(grayscale)
+    }
+}
+
+impl Prepare<DynamicImage, GrayImage> for GpuBlockedMean {
+    fn prepare(&self, input: &DynamicImage) -> GrayImage {
+        self.threshold(self.cpu.to_grayscale(input))
+    }
+
+    fn dynamic_range(&self, input: &DynamicImage) -> Option<u8> {
+        self.cpu.dynamic_range(input)
+    }
+}
+
+impl Prepare<GrayImage, GrayImage> for GpuBlockedMean {
+    fn prepare(&self, input: &GrayImage) -> GrayImage {
+        self.threshold(input.clone())
+    }
+
+    fn dynamic_range(&self, input: &GrayImage) -> Option<u8> {
+        self.cpu.dynamic_range(input)
+    }
+}
+
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    block_sums: wgpu::ComputePipeline,
+    block_means: wgpu::ComputePipeline,
+    threshold: wgpu::ComputePipeline,
+    // Buffers for the last image size, so a stream of same sized frames does not allocate every time
+    buffers: Mutex<Option<Buffers>>,
+}
+
+struct Buffers {
+    width: u32,
+    height: u32,
+    params: wgpu::Buffer,
+    pixels: wgpu::Buffer,
+    output: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+// Matches the Params struct in the shader; the padding brings it to the 16 byte alignment of uniforms
+#[derive(Copy, Clone)]
+struct Params {
+    width: u32,
+    height: u32,
+    stride: u32,
+    block_size: u32,
+    block_stride: u32,
+    blocks_x: u32,
+    blocks_y: u32,
+}
+
+impl Params {
+    fn to_bytes(self) -> Vec<u8> {
+        [
+            self.width,
+            self.height,
+            self.stride,
+            self.block_size,
+            self.block_stride,
+            self.blocks_x,
+            self.blocks_y,
+            0,
+        ]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+    }
+}
+
+impl Gpu {
+    fn new() -> Result<Gpu, String> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|e| e.to_string())?;
+
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("bardecoder"),
+            ..Default::default()
+        }))
+        .map_err(|e| e.to_string())?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("blockedmean"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("blockedmean.wgsl").into()),
+        });
+
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty,
+            count: None,
+        };
+        let buffer = |ty| wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        };
+        let storage = |read_only| buffer(wgpu::BufferBindingType::Storage { read_only });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blockedmean"),
+            entries: &[
+                entry(0, buffer(wgpu::BufferBindingType::Uniform)),
+                entry(1, storage(true)),
+                entry(2, storage(false)),
+                entry(3, storage(false)),
+                entry(4, storage(false)),
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("blockedmean"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+
+        let block_sums = pipeline("block_sums");
+        let block_means = pipeline("block_means");
+        let threshold = pipeline("threshold");
+
+        Ok(Gpu {
+            device,
+            queue,
+            layout,
+            block_sums,
+            block_means,
+            threshold,
+            buffers: Mutex::new(None),
+        })
+    }
+
+    fn threshold(&self, grayscale: &GrayImage, block_size: u32, block_mean_size: u32) -> Result<GrayImage, String> {
+        let (width, height) = grayscale.dimensions();
+        if width == 0 || height == 0 || block_size == 0 {
+            return Err(String::from("Nothing to threshold"));
+        }
+
+        let stride = width.div_ceil(4);
+        let params = Params {
+            width,
+            height,
+            stride,
+            block_size,
+            block_stride: block_mean_size.saturating_sub(1) / 2,
+            blocks_x: width / block_size + 1,
+            blocks_y: height / block_size + 1,
+        };
+
+        let mut buffers = self.buffers.lock().map_err(|e| e.to_string())?;
+        let buffers = match buffers.take() {
+            Some(b) if b.width == width && b.height == height => buffers.insert(b),
+            _ => buffers.insert(self.buffers(params)),
+        };
+
+        self.queue.write_buffer(&buffers.params, 0, &params.to_bytes());
+
+        // Pad every row to a multiple of 4 pixels, so every row starts at a u32 in the shader
+        let padded_width = (stride * 4) as usize;
+        if padded_width == width as usize {
+            self.queue.write_buffer(&buffers.pixels, 0, grayscale.as_raw());
+        } else {
+            let mut padded = vec![0; padded_width * height as usize];
+            for (to, from) in padded
+                .chunks_exact_mut(padded_width)
+                .zip(grayscale.as_raw().chunks_exact(width as usize))
+            {
+                to[..from.len()].copy_from_slice(from);
+            }
+            self.queue.write_buffer(&buffers.pixels, 0, &padded);
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("blockedmean") });
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("blockedmean"),
+                timestamp_writes: None,
+            });
+            pass.set_bind_group(0, &buffers.bind_group, &[]);
+
+            let block_groups = (workgroups(params.blocks_x), workgroups(params.blocks_y));
+
+            pass.set_pipeline(&self.block_sums);
+            pass.dispatch_workgroups(block_groups.0, block_groups.1, 1);
+            pass.set_pipeline(&self.block_means);
+            pass.dispatch_workgroups(block_groups.0, block_groups.1, 1);
+            pass.set_pipeline(&self.threshold);
+            pass.dispatch_workgroups(workgroups(stride), workgroups(height), 1);
+        }
+
+        encoder.copy_buffer_to_buffer(&buffers.output, 0, &buffers.readback, 0, buffers.output.size());
+        self.queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        let slice = buffers.readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| e.to_string())?;
+        receiver
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+
+        let thresholded = {
+            let mapped = slice.get_mapped_range();
+            let mut raw = Vec::with_capacity((width * height) as usize);
+            for row in mapped.chunks_exact(padded_width) {
+                raw.extend_from_slice(&row[..width as usize]);
+            }
+            raw
+        };
+        buffers.readback.unmap();
+
+        GrayImage::from_raw(width, height, thresholded).ok_or_else(|| String::from("Unexpected output size"))
+    }
+
+    fn buffers(&self, params: Params) -> Buffers {
+        let buffer = |label, size, usage| {
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+
+        let storage = wgpu::BufferUsages::STORAGE;
+        let image_size = u64::from(params.stride) * 4 * u64::from(params.height);
+        let block_count = u64::from(params.blocks_x) * u64::from(params.blocks_y);
+
+        let uniform = buffer("params", 32, wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST);
+        let pixels = buffer("pixels", image_size, storage | wgpu::BufferUsages::COPY_DST);
+        let blocks = buffer("blocks", block_count * 8, storage);
+        let means = buffer("means", block_count * 8, storage);
+        let output = buffer("output", image_size, storage | wgpu::BufferUsages::COPY_SRC);
+        let readback = buffer(
+            "readback",
+            image_size,
+            wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        );
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("blockedmean"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: pixels.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: blocks.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: means.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        Buffers {
+            width: params.width,
+            height: params.height,
+            params: uniform,
+            pixels,
+            output,
+            readback,
+            bind_group,
+        }
+    }
+}
+
+// The shaders use 8x8 workgroups
+fn workgroups(invocations: u32) -> u32 {
+    invocations.div_ceil(8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::Luma;
+
+    #[test]
+    fn test_matches_cpu() {
+        // Odd sizes, so neither the rows nor the blocks line up
+        let gradient = GrayImage::from_fn(203, 77, |x, y| Luma([((x * 7 + y * 13) % 256) as u8]));
+        let gpu = GpuBlockedMean::new(5, 7);
+        let cpu = BlockedMean::new(5, 7);
+
+        assert_eq!(gpu.prepare(&gradient), cpu.prepare(&DynamicImage::ImageLuma8(gradient.clone())));
+
+        let img = image::open("tests/images/version3_example.jpg").unwrap();
+        assert_eq!(gpu.prepare(&img), cpu.prepare(&img));
+    }
+}
@@ -0,0 +1,125 @@
+use image::DynamicImage;
+
+use crate::util::qr::QRError;
+
+/// Reads encoded images in a format the `image` crate does not, for [`Decoder::decode_bytes`]
+///
+/// Phone cameras save photos as HEIC or AVIF, which need a decoder for HEVC or AV1 and are best read with a library
+/// such as libheif. A loader wraps such a library; [`DecoderBuilder::loader`] adds it to a decoder. The bytes go to
+/// the first loader that [`can_load`] them, and to the `image` crate when none can.
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// # extern crate image;
+/// use bardecoder::prepare::{HeifFormat, ImageLoader};
+/// use bardecoder::QRError;
+/// use image::DynamicImage;
+///
+/// struct HeicLoader {}
+///
+/// impl ImageLoader for HeicLoader {
+///     fn can_load(&self, bytes: &[u8]) -> bool {
+///         HeifFormat::from_bytes(bytes) == Some(HeifFormat::Heic)
+///     }
+///
+///     fn load(&self, bytes: &[u8]) -> Result<DynamicImage, QRError> {
+///         // decode with a HEIF library here
+/// #       Ok(DynamicImage::new_luma8(40, 40))
+///     }
+/// }
+///
+/// let mut db = bardecoder::default_builder();
+/// db.loader(Box::new(HeicLoader {}));
+/// let decoder = db.build().unwrap();
+/// ```
+///
+/// [`Decoder::decode_bytes`]: ../struct.Decoder.html#method.decode_bytes
+/// [`DecoderBuilder::loader`]: ../struct.DecoderBuilder.html#method.loader
+/// [`can_load`]: #tymethod.can_load
+pub trait ImageLoader {
+    /// Whether these bytes are in a format this loader reads, usually judged by the signature at their start
+    fn can_load(&self, bytes: &[u8]) -> bool;
+
+    /// The width and height of the image, if the loader can read them without decoding the pixels
+    ///
+    /// With [`DecoderBuilder::max_pixels`], these let too large an image be refused before it is decoded. Without
+    /// them the limit is checked on the decoded image instead.
+    ///
+    /// [`DecoderBuilder::max_pixels`]: ../struct.DecoderBuilder.html#method.max_pixels
+    fn dimensions(&self, _bytes: &[u8]) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// Decode the image, turned the way it is displayed
+    ///
+    /// The EXIF orientation is not applied to images of a loader, as HEIF records rotation and mirroring in the
+    /// container and its decoders apply it already.
+    fn load(&self, bytes: &[u8]) -> Result<DynamicImage, QRError>;
+}
+
+/// The formats of the HEIF family that phone cameras save photos in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeifFormat {
+    /// HEIF with HEVC compressed images, as saved by iPhones and many Android phones
+    Heic,
+    /// HEIF with AV1 compressed images
+    Avif,
+}
+
+impl HeifFormat {
+    /// Recognise the format from the brands in the `ftyp` box at the start of an encoded image
+    ///
+    /// Returns `None` for any other image, including HEIF with a compression that is neither HEVC nor AV1
+    pub fn from_bytes(bytes: &[u8]) -> Option<HeifFormat> {
+        if bytes.len() < 16 || &bytes[4..8] != b"ftyp" {
+            return None;
+        }
+
+        let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        let ftyp = bytes.get(8..size.clamp(16, bytes.len()))?;
+
+        // The major brand, then the minor version, then the compatible brands
+        let brands = std::iter::once(&ftyp[0..4]).chain(ftyp[8..].chunks_exact(4));
+        let mut format = None;
+        for brand in brands {
+            match brand {
+                b"avif" | b"avis" => return Some(HeifFormat::Avif),
+                b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx" => format = Some(HeifFormat::Heic),
+                _ => {}
+            }
+        }
+
+        format
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let size = 16 + 4 * compatible.len() as u32;
+        let mut bytes = size.to_be_bytes().to_vec();
+        bytes.extend_from_slice(b"ftyp");
+        bytes.extend_from_slice(major);
+        bytes.extend_from_slice(&[0; 4]);
+        for brand in compatible {
+            bytes.extend_from_slice(*brand);
+        }
+        bytes.extend_from_slice(b"\0\0\0\x08meta");
+        bytes
+    }
+
+    #[test]
+    fn test_heif_format() {
+        assert_eq!(HeifFormat::from_bytes(&ftyp(b"heic", &[b"mif1", b"heic"])), Some(HeifFormat::Heic));
+        assert_eq!(HeifFormat::from_bytes(&ftyp(b"avif", &[b"mif1", b"miaf"])), Some(HeifFormat::Avif));
+        assert_eq!(HeifFormat::from_bytes(&ftyp(b"mif1", &[b"heic"])), Some(HeifFormat::Heic));
+        assert_eq!(HeifFormat::from_bytes(&ftyp(b"mif1", &[b"avif", b"heic"])), Some(HeifFormat::Avif));
+        assert_eq!(HeifFormat::from_bytes(&ftyp(b"mif1", &[b"jpeg"])), None);
+        assert_eq!(HeifFormat::from_bytes(&ftyp(b"isom", &[b"mp41"])), None);
+        assert_eq!(HeifFormat::from_bytes(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), None);
+        assert_eq!(HeifFormat::from_bytes(b"ftyp"), None);
+    }
+}
@@ -1,8 +1,28 @@
 //! Prepare an image for data extraction
 
 mod blockedmean;
+#[cfg(feature = "wgpu")]
+mod gpu;
+#[cfg(feature = "image")]
+mod loader;
+#[cfg(feature = "opencv")]
+mod opencv;
+#[cfg(feature = "image")]
+mod orientation;
+mod thresholdmap;
+mod undistort;
 
 pub use self::blockedmean::BlockedMean;
+#[cfg(feature = "wgpu")]
+pub use self::gpu::GpuBlockedMean;
+#[cfg(feature = "image")]
+pub use self::loader::{HeifFormat, ImageLoader};
+#[cfg(feature = "image")]
+pub use self::orientation::Orientation;
+pub use self::thresholdmap::ThresholdMap;
+pub use self::undistort::{LensDistortion, Undistort};
+
+use crate::util::qr::DecodeHints;
 
 /// Prepare the source image for data extraction, for example by converting it to black/white
 ///
@@ -15,20 +35,34 @@ pub use self::blockedmean::BlockedMean;
 /// # Example
 /// ```
 /// # extern crate bardecoder;
-/// # extern crate image;
-/// # use image::{DynamicImage, GrayImage};
+/// # use bardecoder::util::LumaImage;
 /// use bardecoder::prepare::Prepare;
 ///
 /// struct MyPreparator {}
 ///
-/// impl Prepare<DynamicImage, GrayImage> for MyPreparator {
-///     fn prepare(&self, input: &DynamicImage) -> GrayImage {
+/// impl Prepare<LumaImage, LumaImage> for MyPreparator {
+///     fn prepare(&self, input: &LumaImage) -> LumaImage {
 ///         // prepare image here
-/// #       input.to_luma8()
+/// #       input.clone()
 ///     }
 /// }
 /// ```
 ///
+/// A function or closure with the signature of [`prepare`](#tymethod.prepare) is a `Prepare` as well, which saves
+/// declaring a type to try out an idea:
+/// ```
+/// # extern crate bardecoder;
+/// # use bardecoder::util::LumaImage;
+/// use bardecoder::prepare::Prepare;
+/// use bardecoder::util::LumaSource;
+///
+/// let invert = |input: &LumaImage| {
+///     let (width, height) = input.luma_dimensions();
+///     LumaImage::from_fn(width, height, |x, y| 255 - input.luma(x, y))
+/// };
+/// let prepare: Box<dyn Prepare<LumaImage, LumaImage> + Send + Sync> = Box::new(invert);
+/// ```
+///
 /// with the corresponding impl Prepare being the Example [`here`]
 ///
 /// [`Decoder`]: ../struct.Decoder.html
@@ -37,4 +71,32 @@ pub use self::blockedmean::BlockedMean;
 pub trait Prepare<IMG, PREPD> {
     /// Does the actual preparing
     fn prepare(&self, source: &IMG) -> PREPD;
+
+    /// Prepare the way that suits what is known about the image, used by [`Decoder::decode_with_hints`]
+    ///
+    /// The default ignores the hints and calls [`prepare`](#tymethod.prepare).
+    ///
+    /// [`Decoder::decode_with_hints`]: ../struct.Decoder.html#method.decode_with_hints
+    fn prepare_with_hints(&self, source: &IMG, _hints: &DecodeHints) -> PREPD {
+        self.prepare(source)
+    }
+
+    /// Difference between the darkest and the lightest parts of the source, from 0 for a uniform image to 255, used
+    /// by [`DecoderBuilder::min_dynamic_range`]
+    ///
+    /// The default does not measure it and returns `None`, which never fails a decode.
+    ///
+    /// [`DecoderBuilder::min_dynamic_range`]: ../struct.DecoderBuilder.html#method.min_dynamic_range
+    fn dynamic_range(&self, _source: &IMG) -> Option<u8> {
+        None
+    }
+}
+
+impl<IMG, PREPD, F> Prepare<IMG, PREPD> for F
+where
+    F: Fn(&IMG) -> PREPD,
+{
+    fn prepare(&self, source: &IMG) -> PREPD {
+        self(source)
+    }
 }
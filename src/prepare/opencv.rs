@@ -0,0 +1,101 @@
+use super::{BlockedMean, Prepare};
+
+use ::opencv::core::{Mat, Vec3b, CV_8UC1, CV_8UC3};
+use ::opencv::prelude::*;
+
+use image::GrayImage;
+
+/// Prepare an OpenCV [`Mat`] directly, without converting it to an [`image`] type first
+///
+/// Supported are `CV_8UC1` (grayscale) and `CV_8UC3` (BGR, as produced by `VideoCapture`) matrices. Rows are read
+/// in place, so matrices with padding or ROI views into a larger matrix work as well.
+///
+/// Any other matrix type is logged and results in an empty image, in which nothing will be detected.
+///
+/// # Example
+/// ```ignore
+/// use bardecoder::prepare::BlockedMean;
+/// use bardecoder::DecoderBuilder;
+/// use image::GrayImage;
+/// use opencv::core::Mat;
+///
+/// let mut builder = DecoderBuilder::<Mat, GrayImage, String>::new();
+/// builder.prepare(Box::new(BlockedMean::new(5, 7)));
+/// // set detect and qr as in bardecoder::default_builder()
+/// ```
+///
+/// [`Mat`]: ../../opencv/core/struct.Mat.html
+/// [`image`]: ../../image/index.html
+impl Prepare<Mat, GrayImage> for BlockedMean {
+    fn prepare(&self, input: &Mat) -> GrayImage {
+        match to_grayscale(input) {
+            Ok(Some(grayscale)) => self.threshold(grayscale),
+            Ok(None) => {
+                warn!("Unsupported OpenCV Mat type {}, only CV_8UC1 and CV_8UC3 can be prepared", input.typ());
+                GrayImage::new(0, 0)
+            }
+            Err(e) => {
+                warn!("Unable to read OpenCV Mat: {}", e);
+                GrayImage::new(0, 0)
+            }
+        }
+    }
+}
+
+fn to_grayscale(input: &Mat) -> ::opencv::Result<Option<GrayImage>> {
+    let width = input.cols().max(0) as u32;
+    let height = input.rows().max(0) as u32;
+
+    let mut grayscale = GrayImage::new(width, height);
+    if width == 0 || height == 0 {
+        return Ok(Some(grayscale));
+    }
+
+    let typ = input.typ();
+    for (y, out) in grayscale.chunks_exact_mut(width as usize).enumerate() {
+        if typ == CV_8UC1 {
+            out.copy_from_slice(input.at_row::<u8>(y as i32)?);
+        } else if typ == CV_8UC3 {
+            for (o, bgr) in out.iter_mut().zip(input.at_row::<Vec3b>(y as i32)?) {
+                *o = bgr_to_luma(bgr.0);
+            }
+        } else {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(grayscale))
+}
+
+// Same Rec. 709 weights the image crate uses for its own RGB to luma conversion
+fn bgr_to_luma([b, g, r]: [u8; 3]) -> u8 {
+    ((2126 * u32::from(r) + 7152 * u32::from(g) + 722 * u32::from(b)) / 10000) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ::opencv::core::{Scalar, CV_16UC1};
+
+    #[test]
+    fn test_bgr_to_luma() {
+        assert_eq!(bgr_to_luma([0, 0, 0]), 0);
+        assert_eq!(bgr_to_luma([255, 255, 255]), 255);
+        assert_eq!(bgr_to_luma([0, 0, 255]), 54);
+    }
+
+    #[test]
+    fn test_prepare_gray_mat() {
+        let mat = Mat::new_rows_cols_with_default(20, 30, CV_8UC1, Scalar::all(200.0)).unwrap();
+        let prepared = BlockedMean::new(5, 7).prepare(&mat);
+        assert_eq!(prepared.dimensions(), (30, 20));
+    }
+
+    #[test]
+    fn test_prepare_unsupported_mat() {
+        let mat = Mat::new_rows_cols_with_default(20, 30, CV_16UC1, Scalar::all(200.0)).unwrap();
+        let prepared = BlockedMean::new(5, 7).prepare(&mat);
+        assert_eq!(prepared.dimensions(), (0, 0));
+    }
+}
@@ -0,0 +1,105 @@
+use image::DynamicImage;
+
+/// How the pixels of an image have to be transformed to display it the right way up
+///
+/// Cameras usually store photos the way the sensor read them out and record the rotation in the EXIF `Orientation`
+/// tag instead. QR codes can be decoded at any rotation, but mirrored ones cannot, and a sideways photo makes the
+/// decoder work harder than necessary. [`apply`] turns the image the way a photo viewer would.
+///
+/// With the `exif` feature, [`from_bytes`] reads the orientation from JPEG, PNG, TIFF, HEIF or WebP files and
+/// [`Decoder::decode_bytes`] applies it automatically.
+///
+/// [`apply`]: #method.apply
+/// [`from_bytes`]: #method.from_bytes
+/// [`Decoder::decode_bytes`]: ../struct.Decoder.html#method.decode_bytes
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Orientation {
+    /// Already the right way up
+    Normal,
+    /// Mirrored left to right
+    FlipHorizontal,
+    /// Upside down
+    Rotate180,
+    /// Mirrored top to bottom
+    FlipVertical,
+    /// Mirrored along the diagonal from the top left corner
+    Transpose,
+    /// Needs to be rotated 90 degrees clockwise
+    Rotate90,
+    /// Mirrored along the diagonal from the top right corner
+    Transverse,
+    /// Needs to be rotated 270 degrees clockwise
+    Rotate270,
+}
+
+impl Orientation {
+    /// The orientation for the value (1 to 8) of the EXIF `Orientation` tag
+    pub fn from_exif(value: u32) -> Option<Orientation> {
+        match value {
+            1 => Some(Orientation::Normal),
+            2 => Some(Orientation::FlipHorizontal),
+            3 => Some(Orientation::Rotate180),
+            4 => Some(Orientation::FlipVertical),
+            5 => Some(Orientation::Transpose),
+            6 => Some(Orientation::Rotate90),
+            7 => Some(Orientation::Transverse),
+            8 => Some(Orientation::Rotate270),
+            _ => None,
+        }
+    }
+
+    /// Read the orientation from the EXIF data of an encoded image
+    ///
+    /// Returns `None` if the image has no EXIF data or no valid `Orientation` tag
+    #[cfg(feature = "exif")]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Orientation> {
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::Cursor::new(bytes))
+            .ok()?;
+        let field = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?;
+
+        Orientation::from_exif(field.value.get_uint(0)?)
+    }
+
+    /// Transform the image so it is the right way up
+    pub fn apply(self, img: DynamicImage) -> DynamicImage {
+        match self {
+            Orientation::Normal => img,
+            Orientation::FlipHorizontal => img.fliph(),
+            Orientation::Rotate180 => img.rotate180(),
+            Orientation::FlipVertical => img.flipv(),
+            Orientation::Transpose => img.rotate90().fliph(),
+            Orientation::Rotate90 => img.rotate90(),
+            Orientation::Transverse => img.rotate270().fliph(),
+            Orientation::Rotate270 => img.rotate270(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::{GenericImageView, GrayImage, Luma};
+
+    #[test]
+    fn test_apply() {
+        // 3x2 image with distinct pixels, stored as the sensor saw it
+        let img = DynamicImage::ImageLuma8(GrayImage::from_fn(3, 2, |x, y| Luma([(y * 3 + x) as u8])));
+        let pixel = |img: &DynamicImage, x, y| img.get_pixel(x, y)[0];
+
+        let transposed = Orientation::Transpose.apply(img.clone());
+        assert_eq!(transposed.dimensions(), (2, 3));
+        assert_eq!(pixel(&transposed, 1, 2), pixel(&img, 2, 1));
+
+        let transversed = Orientation::Transverse.apply(img.clone());
+        assert_eq!(transversed.dimensions(), (2, 3));
+        assert_eq!(pixel(&transversed, 0, 0), pixel(&img, 2, 1));
+
+        let rotated = Orientation::Rotate90.apply(img.clone());
+        assert_eq!(pixel(&rotated, 1, 0), pixel(&img, 0, 0));
+
+        assert_eq!(Orientation::Normal.apply(img.clone()), img);
+        assert_eq!(Orientation::from_exif(9), None);
+    }
+}
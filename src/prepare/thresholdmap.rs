@@ -0,0 +1,147 @@
+use super::Prepare;
+
+use crate::util::metrics;
+use crate::util::{LumaImage, LumaSource};
+
+#[cfg(feature = "image")]
+use image::{DynamicImage, GrayImage};
+
+use std::ops::Deref;
+
+/// Reduce the image to black/white with thresholds computed by the caller
+///
+/// A pixel is white when its grayscale value is above the threshold at the same position in the map, and black
+/// otherwise, the same as for the local means of [`BlockedMean`]. This gives exact control over binarization to
+/// imaging hardware that corrects for its own illumination, or produces thresholded data already: a map of 127
+/// everywhere keeps black and white input as it is.
+///
+/// The map usually has the dimensions of the image. When it does not, every pixel is compared with the threshold at
+/// the corresponding position of the map, so a coarse map, say one threshold per 16 by 16 pixels, is stretched over
+/// the image.
+///
+/// When the thresholds differ for every image, prepare it with a `ThresholdMap` of its own and pass the result to
+/// [`Decoder::decode_prepared`].
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::prepare::{Prepare, ThresholdMap};
+/// use bardecoder::util::{LumaImage, LumaSource};
+///
+/// // Brighter to the right, as with light from the side
+/// let source = LumaImage::from_fn(40, 40, |x, y| (x * 4 + y % 2 * 40) as u8);
+/// let thresholds = LumaImage::from_fn(40, 40, |x, _| (x * 4 + 20) as u8);
+///
+/// let prepared = ThresholdMap::new(thresholds).prepare(&source);
+///
+/// assert_eq!((prepared.luma(30, 0), prepared.luma(30, 1)), (0, 255));
+/// ```
+///
+/// [`BlockedMean`]: struct.BlockedMean.html
+/// [`Decoder::decode_prepared`]: ../struct.Decoder.html#method.decode_prepared
+pub struct ThresholdMap {
+    thresholds: LumaImage,
+}
+
+impl ThresholdMap {
+    /// Construct a new ThresholdMap
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map has no pixels
+    pub fn new(thresholds: LumaImage) -> ThresholdMap {
+        let (width, height) = thresholds.luma_dimensions();
+        assert!(width > 0 && height > 0, "Threshold map of {}x{} has no thresholds", width, height);
+
+        ThresholdMap { thresholds }
+    }
+
+    // The thresholded pixels, row by row without padding
+    fn threshold_raw<S: LumaSource>(&self, source: &S) -> Vec<u8> {
+        let (width, height) = source.luma_dimensions();
+        let (map_width, map_height) = self.thresholds.luma_dimensions();
+
+        metrics::count(|metrics| metrics.prepare_pixels += u64::from(width) * u64::from(height));
+
+        let map_columns: Vec<usize> = (0..width).map(|x| scaled(x, width, map_width) as usize).collect();
+        let mut thresholded = Vec::with_capacity(width as usize * height as usize);
+
+        for y in 0..height {
+            let thresholds = self.thresholds.luma_row(scaled(y, height, map_height));
+
+            for (p, column) in source.luma_row(y)[..width as usize].iter().zip(&map_columns) {
+                thresholded.push(if *p > thresholds[*column] { 255 } else { 0 });
+            }
+        }
+
+        thresholded
+    }
+}
+
+// The position in the map for a position in the image
+fn scaled(position: u32, size: u32, map_size: u32) -> u32 {
+    (u64::from(position) * u64::from(map_size) / u64::from(size)) as u32
+}
+
+impl<C: Deref<Target = [u8]>> Prepare<LumaImage<C>, LumaImage> for ThresholdMap {
+    fn prepare(&self, input: &LumaImage<C>) -> LumaImage {
+        let (width, height) = input.luma_dimensions();
+
+        LumaImage::new(width, height, self.threshold_raw(input)).expect("Thresholded size should match")
+    }
+}
+
+#[cfg(feature = "image")]
+impl Prepare<DynamicImage, GrayImage> for ThresholdMap {
+    fn prepare(&self, input: &DynamicImage) -> GrayImage {
+        let grayscale = input.to_luma8();
+        let (width, height) = grayscale.dimensions();
+
+        GrayImage::from_raw(width, height, self.threshold_raw(&grayscale)).expect("Thresholded size should match")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_map() {
+        let source = LumaImage::from_fn(3, 2, |x, y| (x * 10 + y * 100) as u8);
+        let thresholds = LumaImage::from_fn(3, 2, |x, _| (x * 10 + 50) as u8);
+
+        let prepared = ThresholdMap::new(thresholds).prepare(&source);
+
+        assert_eq!(prepared.into_raw(), vec![0, 0, 0, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_coarse_threshold_map() {
+        let source = LumaImage::from_fn(4, 4, |_, _| 100);
+        // One threshold for every 2 by 2 pixels, only the bottom right one is above the pixels
+        let thresholds = LumaImage::from_fn(2, 2, |x, y| if x == 1 && y == 1 { 150 } else { 50 });
+
+        let prepared = ThresholdMap::new(thresholds).prepare(&source);
+
+        let rows: Vec<Vec<u8>> = prepared.into_raw().chunks(4).map(|row| row.to_vec()).collect();
+        assert_eq!(rows, vec![[255, 255, 255, 255], [255, 255, 255, 255], [255, 255, 0, 0], [255, 255, 0, 0]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "has no thresholds")]
+    fn test_empty_threshold_map() {
+        ThresholdMap::new(LumaImage::from_fn(0, 10, |_, _| 0));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_decode_with_threshold_map() {
+        let img = image::open("tests/images/version1_example.jpg").unwrap();
+        let (width, height) = img.to_luma8().dimensions();
+
+        let mut db = crate::default_builder();
+        db.prepare(Box::new(ThresholdMap::new(LumaImage::from_fn(width, height, |_, _| 128))));
+
+        assert_eq!(db.build().unwrap().decode(&img), vec![Ok(String::from("01234567"))]);
+    }
+}
@@ -0,0 +1,271 @@
+use super::Prepare;
+
+use crate::util::qr::DecodeHints;
+use crate::util::{LumaImage, LumaSource, Point};
+
+#[cfg(feature = "image")]
+use image::{DynamicImage, GrayImage};
+
+use std::ops::Deref;
+
+/// The lens of a camera, as found by camera calibration: its camera matrix and its radial and tangential distortion
+///
+/// These are the parameters of the usual (Brown-Conrady) model, the same as the camera matrix and the coefficients
+/// `k1, k2, p1, p2, k3` of OpenCV, so the result of `calibrateCamera` can be used as it is with [`from_opencv`]. Wide
+/// angle lenses bend straight lines outwards (barrel distortion), which bends the edges and timing patterns of QR Codes
+/// far from the center of the image as well.
+///
+/// [`from_opencv`]: #method.from_opencv
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LensDistortion {
+    /// Focal length along x, in pixels
+    pub fx: f64,
+    /// Focal length along y, in pixels
+    pub fy: f64,
+    /// X coordinate of the principal point, the pixel the optical axis goes through
+    pub cx: f64,
+    /// Y coordinate of the principal point
+    pub cy: f64,
+    /// Radial distortion coefficients of r^2, r^4 and r^6
+    pub radial: [f64; 3],
+    /// Tangential distortion coefficients p1 and p2
+    pub tangential: [f64; 2],
+}
+
+impl LensDistortion {
+    /// Construct a LensDistortion from an OpenCV camera matrix and distortion coefficients
+    ///
+    /// The coefficients are in the OpenCV order `k1, k2, p1, p2, k3`. Missing ones are 0.0, and any after `k3`, of the
+    /// rational and thin prism models, are ignored.
+    pub fn from_opencv(camera_matrix: [[f64; 3]; 3], coefficients: &[f64]) -> LensDistortion {
+        let coefficient = |i: usize| coefficients.get(i).copied().unwrap_or(0.0);
+
+        LensDistortion {
+            fx: camera_matrix[0][0],
+            fy: camera_matrix[1][1],
+            cx: camera_matrix[0][2],
+            cy: camera_matrix[1][2],
+            radial: [coefficient(0), coefficient(1), coefficient(4)],
+            tangential: [coefficient(2), coefficient(3)],
+        }
+    }
+
+    /// Where the lens images a point of the undistorted image, in the pixels of the camera
+    pub fn distort(&self, point: Point) -> Point {
+        let (x, y) = ((point.x - self.cx) / self.fx, (point.y - self.cy) / self.fy);
+        let [k1, k2, k3] = self.radial;
+        let [p1, p2] = self.tangential;
+
+        let r2 = x * x + y * y;
+        let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+        let distorted_x = x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x);
+        let distorted_y = y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y;
+
+        Point {
+            x: distorted_x * self.fx + self.cx,
+            y: distorted_y * self.fy + self.cy,
+        }
+    }
+
+    /// The image as it would have been taken without distortion, the same size and with the same camera matrix
+    ///
+    /// Every pixel is interpolated from the four pixels around where the lens imaged it. Pixels the camera did not see,
+    /// which happens near the corners for barrel distortion, are white.
+    pub fn undistort<S: LumaSource>(&self, source: &S) -> LumaImage {
+        let (width, height) = source.luma_dimensions();
+
+        LumaImage::from_fn(width, height, |x, y| {
+            let distorted = self.distort(Point {
+                x: f64::from(x),
+                y: f64::from(y),
+            });
+
+            bilinear(source, distorted.x, distorted.y)
+        })
+    }
+}
+
+// Interpolate between the four pixels around the position, white outside of the image
+fn bilinear<S: LumaSource>(source: &S, x: f64, y: f64) -> u8 {
+    let (width, height) = source.luma_dimensions();
+    if !(x >= 0.0 && y >= 0.0 && x <= f64::from(width) - 1.0 && y <= f64::from(height) - 1.0) {
+        return 255;
+    }
+
+    let (left, top) = (x.floor() as u32, y.floor() as u32);
+    let (right, bottom) = ((left + 1).min(width - 1), (top + 1).min(height - 1));
+    let (fx, fy) = (x - f64::from(left), y - f64::from(top));
+
+    let row = |y: u32| {
+        let row = source.luma_row(y);
+        f64::from(row[left as usize]) * (1.0 - fx) + f64::from(row[right as usize]) * fx
+    };
+
+    (row(top) * (1.0 - fy) + row(bottom) * fy).round() as u8
+}
+
+/// Correct the lens distortion of an image before preparing it with another [`Prepare`]
+///
+/// Detection and extraction expect straight lines to stay straight, so QR Codes seen through a wide angle lens, away
+/// from the center of the image, may not decode. `Undistort` resamples every image through the [`LensDistortion`] of
+/// the camera first, without a round trip through OpenCV, and hands the corrected image to the `Prepare` it wraps.
+///
+/// Color images are undistorted in grayscale, so the wrapped `Prepare` gets a grayscale `DynamicImage`. The
+/// undistortion evaluates the lens model for every pixel, which takes about as long as thresholding.
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::prepare::{BlockedMean, LensDistortion, Undistort};
+/// use bardecoder::util::LumaImage;
+/// use bardecoder::DecoderBuilder;
+///
+/// let camera_matrix = [[800.0, 0.0, 640.0], [0.0, 800.0, 360.0], [0.0, 0.0, 1.0]];
+/// let lens = LensDistortion::from_opencv(camera_matrix, &[-0.28, 0.07, 0.0, 0.0, 0.0]);
+///
+/// let mut db: DecoderBuilder<LumaImage, LumaImage, String> = DecoderBuilder::new();
+/// db.prepare(Box::new(Undistort::new(lens, BlockedMean::new(5, 7))));
+/// ```
+///
+/// [`Prepare`]: trait.Prepare.html
+/// [`LensDistortion`]: struct.LensDistortion.html
+pub struct Undistort<P> {
+    lens: LensDistortion,
+    prepare: P,
+}
+
+impl<P> Undistort<P> {
+    /// Construct a new Undistort, preparing the corrected images with `prepare`
+    pub fn new(lens: LensDistortion, prepare: P) -> Undistort<P> {
+        Undistort { lens, prepare }
+    }
+}
+
+impl<C, PREPD, P> Prepare<LumaImage<C>, PREPD> for Undistort<P>
+where
+    C: Deref<Target = [u8]>,
+    P: Prepare<LumaImage, PREPD>,
+{
+    fn prepare(&self, input: &LumaImage<C>) -> PREPD {
+        self.prepare.prepare(&self.lens.undistort(input))
+    }
+
+    fn prepare_with_hints(&self, input: &LumaImage<C>, hints: &DecodeHints) -> PREPD {
+        self.prepare.prepare_with_hints(&self.lens.undistort(input), hints)
+    }
+
+    fn dynamic_range(&self, input: &LumaImage<C>) -> Option<u8> {
+        self.prepare.dynamic_range(&self.lens.undistort(input))
+    }
+}
+
+#[cfg(feature = "image")]
+impl<PREPD, P> Prepare<DynamicImage, PREPD> for Undistort<P>
+where
+    P: Prepare<DynamicImage, PREPD>,
+{
+    fn prepare(&self, input: &DynamicImage) -> PREPD {
+        self.prepare.prepare(&self.undistort_image(input))
+    }
+
+    fn prepare_with_hints(&self, input: &DynamicImage, hints: &DecodeHints) -> PREPD {
+        self.prepare.prepare_with_hints(&self.undistort_image(input), hints)
+    }
+
+    fn dynamic_range(&self, input: &DynamicImage) -> Option<u8> {
+        self.prepare.dynamic_range(&self.undistort_image(input))
+    }
+}
+
+#[cfg(feature = "image")]
+impl<P> Undistort<P> {
+    fn undistort_image(&self, input: &DynamicImage) -> DynamicImage {
+        let undistorted = self.lens.undistort(&input.to_luma8());
+        let (width, height) = undistorted.luma_dimensions();
+
+        DynamicImage::ImageLuma8(
+            GrayImage::from_raw(width, height, undistorted.into_raw()).expect("Undistorted size should match"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn radial(k1: f64) -> LensDistortion {
+        LensDistortion::from_opencv([[200.0, 0.0, 100.0], [0.0, 200.0, 80.0], [0.0, 0.0, 1.0]], &[k1])
+    }
+
+    #[test]
+    fn test_distort() {
+        let lens = LensDistortion::from_opencv(
+            [[200.0, 0.0, 100.0], [0.0, 250.0, 80.0], [0.0, 0.0, 1.0]],
+            &[-0.2, 0.05, 0.001, 0.002, 0.01, 0.5],
+        );
+        assert_eq!((lens.fx, lens.fy, lens.cx, lens.cy), (200.0, 250.0, 100.0, 80.0));
+        assert_eq!((lens.radial, lens.tangential), ([-0.2, 0.05, 0.01], [0.001, 0.002]));
+
+        // The principal point stays, without distortion every point does
+        let center = lens.distort(Point { x: 100.0, y: 80.0 });
+        assert!((center.x - 100.0).abs() < 1e-9 && (center.y - 80.0).abs() < 1e-9);
+        let point = radial(0.0).distort(Point { x: 13.0, y: 170.0 });
+        assert!((point.x - 13.0).abs() < 1e-9 && (point.y - 170.0).abs() < 1e-9);
+
+        // Barrel distortion images points closer to the center, a tenth of the focal length out by 1% less
+        let point = radial(-1.0).distort(Point { x: 120.0, y: 80.0 });
+        assert!((point.x - 119.8).abs() < 1e-9, "{:?}", point);
+    }
+
+    #[test]
+    fn test_undistort() {
+        let source = LumaImage::from_fn(200, 160, |x, y| ((x + y) % 256) as u8);
+
+        assert_eq!(radial(0.0).undistort(&source).into_raw(), source.clone().into_raw());
+
+        // Under strong pincushion distortion the corners were not seen
+        let undistorted = radial(1.0).undistort(&source);
+        assert_eq!(undistorted.luma(0, 0), 255);
+        assert_eq!(undistorted.luma(100, 80), source.luma(100, 80));
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_decode_undistorted() {
+        use crate::decode::QRDecoder;
+        use crate::detect::LineScan;
+        use crate::encode::QREncoder;
+        use crate::extract::QRExtractor;
+        use crate::prepare::BlockedMean;
+        use crate::DecoderBuilder;
+
+        let code = QREncoder::new().version(3).encode("https://example.com/lens").unwrap().to_luma_image(6, 4);
+        let (width, height) = code.luma_dimensions();
+        let (fx, cx, cy) = (f64::from(width), f64::from(width) / 2.0, f64::from(height) / 2.0);
+        let lens = LensDistortion::from_opencv([[fx, 0.0, cx], [0.0, fx, cy], [0.0, 0.0, 1.0]], &[-0.5]);
+
+        // What the camera sees: every pixel is where the lens images it, found by inverting the distortion
+        let distorted = LumaImage::from_fn(width, height, |x, y| {
+            let target = Point { x: f64::from(x), y: f64::from(y) };
+            let mut point = target;
+            for _ in 0..50 {
+                point = point + (target - lens.distort(point));
+            }
+            bilinear(&code, point.x, point.y)
+        });
+
+        let decoder = |prepare: Box<dyn Prepare<LumaImage, LumaImage> + Send + Sync>| {
+            let mut db: DecoderBuilder<LumaImage, LumaImage, String> = DecoderBuilder::new();
+            db.prepare(prepare);
+            db.detect(Box::new(LineScan::new()));
+            db.qr(Box::new(QRExtractor::new()), Box::new(QRDecoder::new()));
+            db.build().unwrap()
+        };
+
+        let plain = decoder(Box::new(BlockedMean::new(5, 7))).decode(&distorted);
+        assert!(plain.iter().all(Result::is_err), "{:?}", plain);
+
+        let undistorted = decoder(Box::new(Undistort::new(lens, BlockedMean::new(5, 7)))).decode(&distorted);
+        assert_eq!(undistorted, vec![Ok(String::from("https://example.com/lens"))]);
+    }
+}
@@ -0,0 +1,125 @@
+//! BCH codes protecting the format and version information of QR Codes, see [`Bch`]
+//!
+//! [`Bch`]: struct.Bch.html
+
+/// A BCH code: a few bits of data followed by the remainder of dividing them by a generator polynomial over GF(2)
+///
+/// QR Codes protect their format information with a (15, 5) code and their version information with an (18, 6) code,
+/// [`Bch::FORMAT`] and [`Bch::VERSION`]. Other symbologies use the same codes with a different mask, Micro QR Codes
+/// for example mask their format information with `0x4445`, which is `Bch::FORMAT.with_mask(0x4445)`.
+///
+/// The data is short enough to decode by comparing with every codeword, so the decoded data is always the closest
+/// codeword, or nothing when that is more errors away than the code can correct.
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::util::bch::Bch;
+///
+/// // Format information for level M with mask 101, with two bits flipped
+/// let bits = Bch::FORMAT.encode(0b00101) ^ 0b100_0000_0001;
+///
+/// assert_eq!(Bch::FORMAT.decode(bits), Some((0b00101, 2)));
+/// ```
+///
+/// [`Bch::FORMAT`]: #associatedconstant.FORMAT
+/// [`Bch::VERSION`]: #associatedconstant.VERSION
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Bch {
+    generator: u32,
+    data_bits: u32,
+    max_errors: u32,
+    mask: u32,
+}
+
+impl Bch {
+    /// The (15, 5) code of the format information of QR Codes: 2 bits of error correction level and 3 of mask
+    /// pattern, masked with `0x5412`
+    pub const FORMAT: Bch = Bch::new(0x537, 5, 3).with_mask(0x5412);
+
+    /// The (18, 6) code of the version information of QR Codes: the version, for versions 7 to 40
+    pub const VERSION: Bch = Bch::new(0x1F25, 6, 3);
+
+    /// Construct a code for `data_bits` of data with this generator polynomial, correcting up to `max_errors` bits
+    ///
+    /// The generator has a bit set for every term of the polynomial, so `0x537` is x^10 + x^8 + x^5 + x^4 + x^2 + x + 1,
+    /// and its degree is the number of bits appended to the data.
+    pub const fn new(generator: u32, data_bits: u32, max_errors: u32) -> Bch {
+        Bch {
+            generator,
+            data_bits,
+            max_errors,
+            mask: 0,
+        }
+    }
+
+    /// The same code, with the codewords XORed with this mask
+    pub const fn with_mask(self, mask: u32) -> Bch {
+        Bch { mask, ..self }
+    }
+
+    /// Total number of bits of a codeword
+    pub fn bits(&self) -> u32 {
+        self.data_bits + self.check_bits()
+    }
+
+    /// The codeword of the data, masked, with the data in the most significant bits
+    pub fn encode(&self, data: u32) -> u32 {
+        let check_bits = self.check_bits();
+        let data = data & ((1 << self.data_bits) - 1);
+        let generator_bits = check_bits + 1;
+        let mut remainder = data << check_bits;
+
+        while 32 - remainder.leading_zeros() >= generator_bits {
+            remainder ^= self.generator << (32 - remainder.leading_zeros() - generator_bits);
+        }
+
+        (data << check_bits | remainder) ^ self.mask
+    }
+
+    /// The data of the codeword closest to these bits, and how many bits differ from it
+    ///
+    /// `None` if more bits differ from every codeword than the code can correct.
+    pub fn decode(&self, bits: u32) -> Option<(u32, u32)> {
+        (0..1 << self.data_bits)
+            .map(|data| (data, (self.encode(data) ^ bits).count_ones()))
+            .min_by_key(|(_, distance)| *distance)
+            .filter(|(_, distance)| *distance <= self.max_errors)
+    }
+
+    fn check_bits(&self) -> u32 {
+        31 - self.generator.leading_zeros()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        // Format information for M with mask 101, and version information of versions 7 and 40, from the standard
+        assert_eq!(Bch::FORMAT.encode(0b00101), 0b100000011001110);
+        assert_eq!(Bch::VERSION.encode(7), 0x07C94);
+        assert_eq!(Bch::VERSION.encode(40), 0x28C69);
+        assert_eq!((Bch::FORMAT.bits(), Bch::VERSION.bits()), (15, 18));
+
+        // Micro QR Codes use the same code with another mask, the format information of M1 with mask 00
+        assert_eq!(Bch::FORMAT.with_mask(0x4445).encode(0), 0x4445);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(Bch::FORMAT.decode(0b100000011001110), Some((0b00101, 0)));
+        assert_eq!(Bch::FORMAT.decode(0b100000011001110 ^ 0b1_0000_0010_0001), Some((0b00101, 3)));
+        assert_eq!(Bch::VERSION.decode(0x28C69 ^ 0b100_0000_0100_0001), Some((40, 3)));
+        assert_eq!(Bch::VERSION.decode(0x1F250 ^ 0b1111), None);
+
+        // Every pair of codewords differs in at least 7 bits, so 3 errors are always corrected
+        for data in 0..32 {
+            for other in 0..data {
+                assert!((Bch::FORMAT.encode(data) ^ Bch::FORMAT.encode(other)).count_ones() >= 7);
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Capacity of QR Codes for every version and error correction level, from the tables of the standard
+//!
+//! Use these to check whether a payload fits before printing it, or to show how much space a version has left.
+//!
+//! # Example
+//! ```
+//! # extern crate bardecoder;
+//! use bardecoder::util::capacity::{self, Mode};
+//! use bardecoder::ECLevel;
+//!
+//! assert_eq!(capacity::data_codewords(1, &ECLevel::MEDIUM).unwrap(), 16);
+//! assert_eq!(capacity::max_capacity(1, &ECLevel::MEDIUM, Mode::Numeric).unwrap(), 34);
+//! assert_eq!(capacity::max_capacity(40, &ECLevel::LOW, Mode::Byte).unwrap(), 2953);
+//! assert_eq!(capacity::smallest_version_for(100, &ECLevel::MEDIUM, Mode::Byte), Some(6));
+//!
+//! let groups = capacity::block_groups(5, &ECLevel::QUARTILE).unwrap();
+//! assert_eq!(groups.len(), 2);
+//! assert_eq!((groups[0].blocks, groups[0].codewords, groups[0].data_codewords), (2, 33, 15));
+//! ```
+
+use crate::decode::qr;
+use crate::util::qr::{ECLevel, QRError};
+
+/// Encoding mode of the content, which determines how many characters fit in a number of bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Digits only, 10 bits for every 3 digits
+    Numeric,
+    /// Digits, upper case letters, space and `$%*+-./:`, 11 bits for every 2 characters
+    Alphanumeric,
+    /// Any bytes, 8 bits each
+    Byte,
+}
+
+impl Mode {
+    /// Number of bits of the length field of a segment in this mode, which depends on the version
+    pub fn length_bits(self, version: u32) -> u32 {
+        let index = match version {
+            1..=9 => 0,
+            10..=26 => 1,
+            _ => 2,
+        };
+
+        match self {
+            Mode::Numeric => [10, 12, 14][index],
+            Mode::Alphanumeric => [9, 11, 13][index],
+            Mode::Byte => [8, 16, 16][index],
+        }
+    }
+
+    // Characters that fit in the bits, after the mode indicator and length field
+    fn characters(self, bits: u32) -> u32 {
+        match self {
+            Mode::Numeric => 3 * (bits / 10) + [0, 0, 0, 0, 1, 1, 1, 2, 2, 2][(bits % 10) as usize],
+            Mode::Alphanumeric => 2 * (bits / 11) + u32::from(bits % 11 >= 6),
+            Mode::Byte => bits / 8,
+        }
+    }
+}
+
+/// A group of blocks of the same size
+///
+/// The codewords of a QR Code are split into blocks that each have their own error correction codewords. Larger
+/// versions have two groups, the blocks of the second group having one more data codeword.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockGroup {
+    /// Number of blocks in the group
+    pub blocks: u32,
+
+    /// Codewords per block, data and error correction
+    pub codewords: u32,
+
+    /// Data codewords per block
+    pub data_codewords: u32,
+
+    /// Number of codewords with errors error correction can fix per block
+    pub correctable: u32,
+}
+
+impl BlockGroup {
+    /// Error correction codewords per block
+    pub fn ec_codewords(&self) -> u32 {
+        self.codewords - self.data_codewords
+    }
+}
+
+/// The block groups of the version and level, in the order they are interleaved
+///
+/// # Errors
+///
+/// Returns `QRError` if the version is not 1 to 40
+pub fn block_groups(version: u32, ec_level: &ECLevel) -> Result<Vec<BlockGroup>, QRError> {
+    Ok(qr::block_groups(version, ec_level)?
+        .into_iter()
+        .map(|bi| BlockGroup {
+            blocks: u32::from(bi.block_count),
+            codewords: u32::from(bi.total_per),
+            data_codewords: u32::from(bi.data_per),
+            correctable: u32::from(bi.ec_cap),
+        })
+        .collect())
+}
+
+/// All codewords of the version, data and error correction, which does not depend on the level
+///
+/// # Errors
+///
+/// Returns `QRError` if the version is not 1 to 40
+pub fn total_codewords(version: u32) -> Result<u32, QRError> {
+    Ok(sum(&block_groups(version, &ECLevel::LOW)?, |g| g.codewords))
+}
+
+/// Data codewords of the version and level
+///
+/// # Errors
+///
+/// Returns `QRError` if the version is not 1 to 40
+pub fn data_codewords(version: u32, ec_level: &ECLevel) -> Result<u32, QRError> {
+    Ok(sum(&block_groups(version, ec_level)?, |g| g.data_codewords))
+}
+
+/// Error correction codewords of the version and level
+///
+/// # Errors
+///
+/// Returns `QRError` if the version is not 1 to 40
+pub fn ec_codewords(version: u32, ec_level: &ECLevel) -> Result<u32, QRError> {
+    Ok(sum(&block_groups(version, ec_level)?, BlockGroup::ec_codewords))
+}
+
+/// The most characters that fit in the version and level, in a single segment of the mode
+///
+/// For byte mode this is the number of bytes, so fewer characters fit for text outside of ASCII.
+///
+/// # Errors
+///
+/// Returns `QRError` if the version is not 1 to 40
+pub fn max_capacity(version: u32, ec_level: &ECLevel, mode: Mode) -> Result<u32, QRError> {
+    let bits = 8 * data_codewords(version, ec_level)?;
+    let length_bits = mode.length_bits(version);
+    let characters = mode.characters(bits.saturating_sub(4 + length_bits));
+
+    // The length field limits the length of a segment as well
+    Ok(characters.min((1 << length_bits) - 1))
+}
+
+/// The smallest version in which `len` characters of the mode fit at the level, in a single segment
+///
+/// Returns `None` when they do not fit in any version, not even 40.
+pub fn smallest_version_for(len: usize, ec_level: &ECLevel, mode: Mode) -> Option<u32> {
+    (1..=40).find(|version| match max_capacity(*version, ec_level, mode) {
+        Ok(capacity) => len <= capacity as usize,
+        Err(_) => false,
+    })
+}
+
+fn sum(groups: &[BlockGroup], per_block: impl Fn(&BlockGroup) -> u32) -> u32 {
+    groups.iter().map(|g| g.blocks * per_block(g)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codewords() {
+        assert_eq!(total_codewords(1).unwrap(), 26);
+        assert_eq!(total_codewords(40).unwrap(), 3706);
+        assert_eq!(data_codewords(40, &ECLevel::HIGH).unwrap(), 1276);
+        assert_eq!(ec_codewords(40, &ECLevel::HIGH).unwrap(), 2430);
+
+        for version in 1..=40 {
+            for level in &[ECLevel::LOW, ECLevel::MEDIUM, ECLevel::QUARTILE, ECLevel::HIGH] {
+                let total = data_codewords(version, level).unwrap() + ec_codewords(version, level).unwrap();
+                assert_eq!(total, total_codewords(version).unwrap());
+            }
+        }
+
+        assert!(total_codewords(0).is_err());
+        assert!(data_codewords(41, &ECLevel::LOW).is_err());
+    }
+
+    #[test]
+    fn test_max_capacity() {
+        // From the capacity table of the standard
+        let expected = [
+            (1, ECLevel::LOW, [41, 25, 17]),
+            (1, ECLevel::HIGH, [17, 10, 7]),
+            (10, ECLevel::MEDIUM, [513, 311, 213]),
+            (27, ECLevel::QUARTILE, [1933, 1172, 805]),
+            (40, ECLevel::LOW, [7089, 4296, 2953]),
+            (40, ECLevel::HIGH, [3057, 1852, 1273]),
+        ];
+
+        for (version, level, capacity) in &expected {
+            for (mode, capacity) in [Mode::Numeric, Mode::Alphanumeric, Mode::Byte].iter().zip(capacity) {
+                assert_eq!(max_capacity(*version, level, *mode).unwrap(), *capacity, "{} {:?} {:?}", version, level, mode);
+            }
+        }
+    }
+
+    #[test]
+    fn test_smallest_version_for() {
+        assert_eq!(smallest_version_for(0, &ECLevel::HIGH, Mode::Byte), Some(1));
+        assert_eq!(smallest_version_for(34, &ECLevel::MEDIUM, Mode::Numeric), Some(1));
+        assert_eq!(smallest_version_for(35, &ECLevel::MEDIUM, Mode::Numeric), Some(2));
+        assert_eq!(smallest_version_for(2953, &ECLevel::LOW, Mode::Byte), Some(40));
+        assert_eq!(smallest_version_for(2954, &ECLevel::LOW, Mode::Byte), None);
+
+        for version in 1..=40 {
+            let capacity = max_capacity(version, &ECLevel::QUARTILE, Mode::Alphanumeric).unwrap() as usize;
+            assert!(smallest_version_for(capacity, &ECLevel::QUARTILE, Mode::Alphanumeric).unwrap() <= version);
+        }
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_max_capacity_encodes() {
+        use crate::encode::QREncoder;
+
+        for version in 1..=40 {
+            for level in &[ECLevel::LOW, ECLevel::MEDIUM, ECLevel::QUARTILE, ECLevel::HIGH] {
+                let digits = max_capacity(version, level, Mode::Numeric).unwrap() as usize;
+                let mut encoder = QREncoder::new();
+                encoder.version(version).ec_level(level.clone()).mask(0);
+
+                assert!(encoder.encode(&"1".repeat(digits)).is_ok());
+                assert!(encoder.encode(&"1".repeat(digits + 1)).is_err());
+            }
+        }
+    }
+}
@@ -11,8 +11,8 @@ use std::vec::IntoIter;
 ///
 /// let mut chomp = Chomp::new(vec![0b11101011, 0b01101101, 0b10101110, 0b00011001]);
 /// assert_eq!(chomp.chomp(3).unwrap(), 0b111);
-/// assert_eq!(chomp.chomp_or(8, QRError{msg:String::from("Error")}).unwrap(), 0b01011011);
-/// assert_eq!(chomp.chomp_or_u16(12, QRError{msg:String::from("Error")}).unwrap(), 0b011011010111);
+/// assert_eq!(chomp.chomp_or(8, QRError::new("Error")).unwrap(), 0b01011011);
+/// assert_eq!(chomp.chomp_or_u16(12, QRError::new("Error")).unwrap(), 0b011011010111);
 /// assert_eq!(chomp.chomp(4).unwrap(), 0b0000);
 /// assert!(chomp.chomp(8).is_none()); // only 5 bits left
 /// assert_eq!(chomp.chomp(5).unwrap(), 0b11001);
@@ -22,6 +22,7 @@ pub struct Chomp {
     bits_left: BitCount,
     current_byte: Option<u8>,
     bits_left_in_byte: BitCount,
+    position: usize,
 }
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
@@ -47,9 +48,15 @@ impl Chomp {
             bits_left,
             current_byte,
             bits_left_in_byte,
+            position: 0,
         }
     }
 
+    /// Number of bits chomped so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
     /// Try to chomp `nr_bits` bits. If not enough bits are left, or requesting more than 8 bits the provided `err` will be returned
     /// If requesting fewer than 8 bits, the result will be in the least significant bits of the u8
     pub fn chomp_or<E>(&mut self, nr_bits: u8, err: E) -> Result<u8, E> {
@@ -75,6 +82,13 @@ impl Chomp {
     /// Try to chomp `nr_bits` bits. If not enough bits are left, or requesting more than 8 bits [`None`] will be returned
     /// If requesting fewer than 8 bits, the result will be in the least significant bits of the u8
     pub fn chomp(&mut self, nr_bits: u8) -> Option<u8> {
+        let chomped = self.chomp_bits(nr_bits)?;
+        self.position += usize::from(nr_bits);
+
+        Some(chomped)
+    }
+
+    fn chomp_bits(&mut self, nr_bits: u8) -> Option<u8> {
         let bit_count = BitCount(nr_bits as usize);
         if !(1..=8).contains(&nr_bits) || bit_count > self.bits_left {
             return None;
@@ -181,6 +195,16 @@ mod test {
         assert_eq!(None, chomp.chomp(4));
     }
 
+    #[test]
+    pub fn position() {
+        let mut chomp = Chomp::new(vec![0b11000100, 0b10101010]);
+
+        assert_eq!(Some(0b110), chomp.chomp(3));
+        assert_eq!(Ok(0b00100101), chomp.chomp_or(8, ()));
+        assert_eq!(None, chomp.chomp(6));
+        assert_eq!(11, chomp.position());
+    }
+
     #[test]
     pub fn chomp_u16() {
         let mut chomp = Chomp::new(vec![0b11000100, 0b10101010]);
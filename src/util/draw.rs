@@ -0,0 +1,57 @@
+//! Just enough drawing to annotate debug images
+
+use image::{Rgb, RgbImage};
+
+use crate::util::Point;
+
+/// Straight line between two points
+pub(crate) fn line(img: &mut RgbImage, from: Point, to: Point, color: Rgb<u8>) {
+    let steps = (to.x - from.x).abs().max((to.y - from.y).abs()).ceil().max(1.0);
+
+    for i in 0..=steps as u32 {
+        let t = f64::from(i) / steps;
+        put(img, from.x + t * (to.x - from.x), from.y + t * (to.y - from.y), color);
+    }
+}
+
+/// Closed outline through all points
+pub(crate) fn polygon(img: &mut RgbImage, points: &[Point], color: Rgb<u8>) {
+    for (i, from) in points.iter().enumerate() {
+        line(img, *from, points[(i + 1) % points.len()], color);
+    }
+}
+
+/// Outline of an axis aligned square around the center
+pub(crate) fn square(img: &mut RgbImage, center: Point, half: f64, color: Rgb<u8>) {
+    let corner = |dx: f64, dy: f64| Point {
+        x: center.x + dx * half,
+        y: center.y + dy * half,
+    };
+
+    polygon(img, &[corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)], color);
+}
+
+/// Filled axis aligned square around the center
+pub(crate) fn dot(img: &mut RgbImage, center: Point, half: f64, color: Rgb<u8>) {
+    let half = half.max(0.5);
+    let mut y = center.y - half;
+
+    while y <= center.y + half {
+        let mut x = center.x - half;
+
+        while x <= center.x + half {
+            put(img, x, y, color);
+            x += 1.0;
+        }
+
+        y += 1.0;
+    }
+}
+
+fn put(img: &mut RgbImage, x: f64, y: f64, color: Rgb<u8>) {
+    let (x, y) = (x.round(), y.round());
+
+    if x >= 0.0 && y >= 0.0 && x < f64::from(img.width()) && y < f64::from(img.height()) {
+        img.put_pixel(x as u32, y as u32, color);
+    }
+}
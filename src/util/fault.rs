@@ -0,0 +1,194 @@
+//! Deterministic damage to extracted QR Codes, to measure how much the error correction recovers
+//!
+//! All functions work on the modules of a [`QRData`](../qr/struct.QRData.html), after extraction and before
+//! decoding, so the damage is exact and does not depend on the quality of an image. The same seed always damages the
+//! same modules.
+//!
+//! # Example
+//! ```
+//! # extern crate bardecoder;
+//! use bardecoder::util::fault;
+//! use bardecoder::util::qr::QRData;
+//!
+//! // A version 1 QR Code, all modules light
+//! let mut data = QRData::new(vec![255; 21 * 21], 1);
+//!
+//! let flipped = fault::flip_modules(&mut data, 5, 42).unwrap();
+//! assert_eq!(flipped.len(), 5);
+//! assert!(flipped.iter().all(|(x, y)| data[[*x, *y]] == 1));
+//!
+//! assert_eq!(fault::erase_rect(&mut data, 0, 0, 7, 7, true), 49);
+//! ```
+
+use crate::decode::qr::blocks::data_modules;
+use crate::util::qr::{QRData, QRError};
+
+/// Flip `count` distinct data modules, chosen by `seed`, and return their coordinates
+///
+/// Only modules holding data or error correction bits are picked, so the finder patterns and format information stay
+/// intact and the damage lands on the codewords. All data modules are flipped if there are fewer than `count`.
+///
+/// # Errors
+///
+/// Returns `QRError` if the version of the QR Code is not valid
+pub fn flip_modules(data: &mut QRData, count: usize, seed: u64) -> Result<Vec<(u32, u32)>, QRError> {
+    let mut candidates = data_modules(data.version)?.to_vec();
+    let mut rng = SplitMix(seed);
+    let count = count.min(candidates.len());
+
+    // Partial Fisher-Yates, the first `count` candidates end up a random selection
+    for i in 0..count {
+        let pick = i + (rng.next() % (candidates.len() - i) as u64) as usize;
+        candidates.swap(i, pick);
+    }
+
+    candidates.truncate(count);
+
+    for (x, y) in &candidates {
+        flip(data, *x, *y);
+    }
+
+    Ok(candidates)
+}
+
+/// Set all modules in the rectangle to dark or light and return how many changed
+///
+/// The rectangle starts at column `x` and row `y`, in modules, and is clipped to the QR Code.
+pub fn erase_rect(data: &mut QRData, x: u32, y: u32, width: u32, height: u32, dark: bool) -> usize {
+    let side = data.side;
+    let value = if dark { 0 } else { 255 };
+    let mut changed = 0;
+
+    for j in y.min(side)..y.saturating_add(height).min(side) {
+        for i in x.min(side)..x.saturating_add(width).min(side) {
+            let index = (j * side + i) as usize;
+            if (data.data[index] == 0) != dark {
+                changed += 1;
+            }
+
+            data.data[index] = value;
+        }
+    }
+
+    changed
+}
+
+/// Cover the center of the QR Code with a light square, the way a logo is placed, and return how many modules changed
+///
+/// `fraction` is the part of the area of the QR Code the square covers, a logo usually covers less than 0.1 with
+/// error correction level HIGH. The square has an odd side, so it is centered on the center module.
+pub fn overlay_logo(data: &mut QRData, fraction: f64) -> usize {
+    let side = data.side;
+    let logo = ((f64::from(side * side) * fraction.clamp(0.0, 1.0)).sqrt() as u32) | 1;
+    let logo = logo.min(side);
+    let start = (side - logo) / 2;
+
+    erase_rect(data, start, start, logo, logo, false)
+}
+
+fn flip(data: &mut QRData, x: u32, y: u32) {
+    let index = (y * data.side + x) as usize;
+    data.data[index] = if data.data[index] == 0 { 255 } else { 0 };
+}
+
+// SplitMix64, small and good enough to pick modules, and the same on every platform
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light(version: u32) -> QRData {
+        let side = 4 * version + 17;
+        QRData::new(vec![255; (side * side) as usize], version)
+    }
+
+    #[test]
+    fn test_flip_modules_deterministic() {
+        let mut first = light(2);
+        let mut second = light(2);
+
+        let flipped = flip_modules(&mut first, 20, 7).unwrap();
+        assert_eq!(flipped, flip_modules(&mut second, 20, 7).unwrap());
+        assert_eq!(first.data, second.data);
+
+        let mut distinct = flipped.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), 20);
+
+        let mut other = light(2);
+        assert_ne!(flipped, flip_modules(&mut other, 20, 8).unwrap());
+    }
+
+    #[test]
+    fn test_flip_modules_data_only() {
+        let mut data = light(1);
+        let modules = data_modules(data.version).unwrap();
+
+        let flipped = flip_modules(&mut data, 1000, 1).unwrap();
+        assert_eq!(flipped.len(), modules.len());
+        assert_eq!(data.data.iter().filter(|m| **m == 0).count(), modules.len());
+
+        assert!(flip_modules(&mut QRData::new(vec![], 41), 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_erase_rect() {
+        let mut data = light(1);
+
+        assert_eq!(erase_rect(&mut data, 18, 18, 10, 10, true), 9);
+        assert_eq!(erase_rect(&mut data, 17, 17, 2, 2, true), 3);
+        assert_eq!(erase_rect(&mut data, 0, 0, 21, 21, false), 12);
+        assert_eq!(erase_rect(&mut data, 30, 30, 5, 5, true), 0);
+    }
+
+    #[test]
+    fn test_overlay_logo() {
+        let mut data = QRData::new(vec![0; 25 * 25], 2);
+
+        // 0.08 of 625 modules is a square of 7, rounded down to odd
+        assert_eq!(overlay_logo(&mut data, 0.08), 49);
+        assert_eq!(data[[8, 8]], 1);
+        assert_eq!(data[[9, 9]], 0);
+        assert_eq!(data[[15, 15]], 0);
+        assert_eq!(data[[16, 16]], 1);
+
+        assert_eq!(overlay_logo(&mut QRData::new(vec![0; 25 * 25], 2), 2.0), 625);
+    }
+
+    #[cfg(feature = "encode")]
+    #[test]
+    fn test_error_correction_recovers() {
+        use crate::decode::{Decode, QRDecoderWithInfo};
+        use crate::encode::QREncoder;
+        use crate::util::qr::ECLevel;
+
+        let code = QREncoder::new().version(7).ec_level(ECLevel::HIGH).encode("fault injection").unwrap();
+
+        // Version 7 has no remainder bits, so every flipped module is in a codeword and gets corrected
+        for seed in 0..10 {
+            let mut data = code.to_qr_data();
+            let flipped = flip_modules(&mut data, 30, seed).unwrap();
+
+            let (content, info) = QRDecoderWithInfo::new().decode(Ok(data)).unwrap();
+            assert_eq!(content, "fault injection");
+            assert_eq!(info.errors as usize, flipped.len());
+        }
+
+        let mut data = code.to_qr_data();
+        assert!(overlay_logo(&mut data, 0.08) > 0);
+        assert_eq!(QRDecoderWithInfo::new().decode(Ok(data)).unwrap().0, "fault injection");
+    }
+}
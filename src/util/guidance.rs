@@ -0,0 +1,273 @@
+//! Signals for guiding the user of a camera towards a readable image, see [`Decoder::decode_with_guidance`]
+//!
+//! These are measured on every image, also when no code decodes, so a scanner UI can tell its user why and what to do
+//! about it: add light, hold still or move closer. They are rough on purpose, cheap enough to measure for every frame
+//! of a video.
+//!
+//! [`Decoder::decode_with_guidance`]: ../../struct.Decoder.html#method.decode_with_guidance
+
+use crate::util::trace::TraceEvent;
+use crate::util::{LumaSource, Point};
+
+/// Rows and columns measured at most, spread evenly over the image
+const LINES: u32 = 128;
+
+/// Pixels this dark or darker are underexposed, this light or lighter overexposed
+const CLIPPED: u8 = 5;
+
+/// Least difference in brightness over [`EDGE_SPAN`] pixels for an edge to be measured
+const EDGE_CONTRAST: u8 = 48;
+
+/// Pixels over which an edge is measured, the widest edge that can be told apart
+const EDGE_SPAN: usize = 4;
+
+/// Below this mean brightness the image is too dark
+const MIN_EXPOSURE: f64 = 0.2;
+
+/// Above this mean brightness the image is too light
+const MAX_EXPOSURE: f64 = 0.9;
+
+/// Below this sharpness the image is too blurred, edges are more than about three pixels wide
+const MIN_SHARPNESS: f64 = 0.35;
+
+/// Below this module size, in pixels, finder patterns are too small to be read reliably
+const MIN_MODULE_SIZE: f64 = 2.0;
+
+/// A finder pattern the detector saw, whether or not it became part of a decoded code
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FinderHint {
+    /// Center of the finder pattern, in pixels
+    pub location: Point,
+    /// Estimated module size, in pixels
+    pub module_size: f64,
+}
+
+/// What a scanner can tell its user to do to get a readable image, see [`ScanGuidance::advice`]
+///
+/// [`ScanGuidance::advice`]: struct.ScanGuidance.html#method.advice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScanAdvice {
+    /// The image is too dark
+    AddLight,
+    /// The image is too light, such as from glare or a flash too close by
+    ReduceLight,
+    /// The image is blurred, by movement or by being out of focus
+    HoldStill,
+    /// Finder patterns are seen, but too small to read the code
+    MoveCloser,
+}
+
+/// The advice as an instruction, such as `Hold still`
+impl std::fmt::Display for ScanAdvice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ScanAdvice::AddLight => "Add light",
+            ScanAdvice::ReduceLight => "Reduce light",
+            ScanAdvice::HoldStill => "Hold still",
+            ScanAdvice::MoveCloser => "Move closer",
+        })
+    }
+}
+
+/// Exposure, sharpness and finder patterns of an image, brightness is relative to white, from 0.0 to 1.0
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanGuidance {
+    /// Mean brightness of the image
+    pub exposure: f64,
+
+    /// Part of the pixels that are black, so darker details are lost
+    pub underexposed: f64,
+
+    /// Part of the pixels that are white, so lighter details are lost
+    pub overexposed: f64,
+
+    /// Steepness of the edges in the image, about one over their width in pixels: 1.0 for edges from one pixel to the
+    /// next, down to 0.25 for edges wider than four pixels. `None` when the image has no edges at all.
+    pub sharpness: Option<f64>,
+
+    /// Finder patterns seen by the detector, also those that are not part of a decoded code, such as when the other
+    /// two are outside of the image or too damaged to find
+    pub finders: Vec<FinderHint>,
+}
+
+impl ScanGuidance {
+    /// What the user should do first for a better image, `None` when nothing stands out
+    ///
+    /// Exposure comes first, as it also hides edges and finder patterns, then sharpness, then the size of the finder
+    /// patterns. The advice is meant for when no code decodes; a code that decodes can still get some.
+    pub fn advice(&self) -> Option<ScanAdvice> {
+        if self.exposure < MIN_EXPOSURE {
+            Some(ScanAdvice::AddLight)
+        } else if self.exposure > MAX_EXPOSURE {
+            Some(ScanAdvice::ReduceLight)
+        } else if self.sharpness.is_some_and(|sharpness| sharpness < MIN_SHARPNESS) {
+            Some(ScanAdvice::HoldStill)
+        } else if !self.finders.is_empty() && self.finders.iter().all(|finder| finder.module_size < MIN_MODULE_SIZE) {
+            Some(ScanAdvice::MoveCloser)
+        } else {
+            None
+        }
+    }
+}
+
+/// Measure the exposure and sharpness of the source, with the finder patterns of the trace events of its detection
+pub(crate) fn measure<S: LumaSource>(source: &S, events: &[TraceEvent]) -> ScanGuidance {
+    let (width, height) = source.luma_dimensions();
+
+    let mut pixels = 0_u64;
+    let (mut sum, mut dark, mut light) = (0_u64, 0_u64, 0_u64);
+    let mut edges = Edges::default();
+
+    let mut line = vec![];
+    for y in spread(height) {
+        let row = source.luma_row(y);
+        for pixel in row {
+            sum += u64::from(*pixel);
+            dark += u64::from(*pixel <= CLIPPED);
+            light += u64::from(*pixel >= 255 - CLIPPED);
+        }
+        pixels += row.len() as u64;
+        edges.add(row);
+    }
+
+    for x in spread(width) {
+        line.clear();
+        line.extend((0..height).map(|y| source.luma(x, y)));
+        edges.add(&line);
+    }
+
+    let part = |count: u64| match pixels {
+        0 => 0.0,
+        _ => count as f64 / pixels as f64,
+    };
+
+    ScanGuidance {
+        exposure: part(sum) / 255.0,
+        underexposed: part(dark),
+        overexposed: part(light),
+        sharpness: edges.sharpness(),
+        finders: finders(events),
+    }
+}
+
+// Up to `LINES` positions spread evenly over the length
+fn spread(length: u32) -> impl Iterator<Item = u32> {
+    let step = length.div_ceil(LINES).max(1);
+    (0..length).step_by(step as usize)
+}
+
+// Sum of the steepness of every edge measured
+#[derive(Default)]
+struct Edges {
+    steepness: f64,
+    count: u64,
+}
+
+impl Edges {
+    // Where the brightness changes by at least `EDGE_CONTRAST` over `EDGE_SPAN` pixels, the largest change from one
+    // pixel to the next relative to the whole change: 1.0 for a sharp edge, 1 / `EDGE_SPAN` for a ramp at least that
+    // wide
+    fn add(&mut self, line: &[u8]) {
+        for window in line.windows(EDGE_SPAN + 1) {
+            let change = window[0].abs_diff(window[EDGE_SPAN]);
+            if change < EDGE_CONTRAST {
+                continue;
+            }
+
+            let steepest = window.windows(2).map(|pair| pair[0].abs_diff(pair[1])).max().unwrap_or(0);
+            self.steepness += (f64::from(steepest) / f64::from(change)).min(1.0);
+            self.count += 1;
+        }
+    }
+
+    fn sharpness(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            count => Some(self.steepness / count as f64),
+        }
+    }
+}
+
+// The finder patterns of the events, once each when several detectors or views saw the same one
+fn finders(events: &[TraceEvent]) -> Vec<FinderHint> {
+    let mut finders: Vec<FinderHint> = vec![];
+
+    for event in events {
+        if let TraceEvent::FinderCandidate { location, module_size } = event {
+            let seen = finders.iter().any(|finder| {
+                let (dx, dy) = (finder.location.x - location.x, finder.location.y - location.y);
+                (dx * dx + dy * dy).sqrt() < 3.5 * finder.module_size.max(*module_size)
+            });
+
+            if !seen {
+                finders.push(FinderHint {
+                    location: *location,
+                    module_size: *module_size,
+                });
+            }
+        }
+    }
+
+    finders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::LumaImage;
+
+    // Stripes of 8 pixels, with edges `blur` pixels wide
+    fn stripes(blur: u32, dark: u8, light: u8) -> LumaImage {
+        LumaImage::from_fn(64, 64, |x, _| {
+            let phase = x % 16;
+            let step = |at: u32| (f64::from(phase.saturating_sub(at)) / f64::from(blur)).min(1.0);
+            let lightness = step(0) - step(8);
+            (f64::from(dark) + f64::from(light - dark) * lightness).round() as u8
+        })
+    }
+
+    #[test]
+    fn test_measure() {
+        let sharp = measure(&stripes(1, 0, 255), &[]);
+        assert!((sharp.exposure - 0.5).abs() < 0.05);
+        assert!((sharp.underexposed - 0.5).abs() < 0.05);
+        assert!((sharp.overexposed - 0.5).abs() < 0.05);
+        assert_eq!(sharp.sharpness, Some(1.0));
+        assert_eq!(sharp.advice(), None);
+
+        let blurred = measure(&stripes(6, 0, 255), &[]);
+        assert!(blurred.sharpness.unwrap() < MIN_SHARPNESS);
+        assert_eq!(blurred.advice(), Some(ScanAdvice::HoldStill));
+
+        let dark = measure(&stripes(1, 0, 80), &[]);
+        assert!(dark.exposure < MIN_EXPOSURE);
+        assert_eq!(dark.advice(), Some(ScanAdvice::AddLight));
+        assert_eq!(ScanAdvice::AddLight.to_string(), "Add light");
+
+        let flat = measure(&LumaImage::from_fn(64, 64, |_, _| 128), &[]);
+        assert_eq!(flat.sharpness, None);
+        assert_eq!(flat.advice(), None);
+    }
+
+    #[test]
+    fn test_finders() {
+        let candidate = |x: f64, module_size: f64| TraceEvent::FinderCandidate {
+            location: Point { x, y: 10.0 },
+            module_size,
+        };
+
+        // The same finder pattern seen twice counts once
+        let events = [candidate(10.0, 1.5), TraceEvent::FormatRejected { copy: 1 }, candidate(12.0, 1.5)];
+        let mut guidance = measure(&stripes(1, 0, 255), &events);
+        assert_eq!(guidance.finders.len(), 1);
+        assert_eq!(guidance.advice(), Some(ScanAdvice::MoveCloser));
+
+        guidance.finders = finders(&[candidate(10.0, 1.5), candidate(40.0, 3.0)]);
+        assert_eq!(guidance.finders.len(), 2);
+        assert_eq!(guidance.advice(), None);
+    }
+}
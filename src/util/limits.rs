@@ -0,0 +1,275 @@
+//! Hard bounds on the work of a decode, see [`ResourceLimits`]
+//!
+//! [`ResourceLimits`]: struct.ResourceLimits.html
+
+use std::cell::Cell;
+
+use crate::util::qr::QRError;
+use crate::util::LumaSource;
+
+/// Bounds on the work of a decode, so crafted images can not make it take unbounded time
+///
+/// Every group of three finder patterns of similar size is checked for being a QR Code, and every code found is
+/// extracted and error corrected, with every format when its format information is unreadable. An image full of fake
+/// finder patterns can therefore make a decode take far longer than any real image would. Reaching a limit stops that
+/// part of the decode, with an error for which [`QRError::is_resource_limit`] is true in the results. The limits per
+/// code are counted for every code on its own, so a code that reaches one fails while the others still decode. The
+/// defaults are well above what real images need, set them on the [`DecoderBuilder`] with
+/// [`limits`](../../struct.DecoderBuilder.html#method.limits).
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::util::limits::ResourceLimits;
+/// use bardecoder::util::LumaImage;
+/// use bardecoder::DecoderBuilder;
+///
+/// let mut db: DecoderBuilder<LumaImage, LumaImage, String> = DecoderBuilder::new();
+/// db.limits(ResourceLimits {
+///     max_extractions: 4,
+///     ..ResourceLimits::default()
+/// });
+/// ```
+///
+/// [`QRError::is_resource_limit`]: ../qr/struct.QRError.html#method.is_resource_limit
+/// [`DecoderBuilder`]: ../../struct.DecoderBuilder.html
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ResourceLimits {
+    /// Finder patterns a detection keeps for grouping, over all detectors and views that run. Detection stops at the
+    /// limit, grouping the finder patterns found so far. Defaults to 256
+    pub max_candidates: u64,
+    /// Codes extracted per detection. The codes after the limit are dropped the same way as for
+    /// [`DecoderBuilder::max_codes`](../../struct.DecoderBuilder.html#method.max_codes), which is for the number of
+    /// codes an application expects rather than a bound on the work. Defaults to 256
+    pub max_extractions: usize,
+    /// Reed-Solomon iterations per code, over every decoder and format tried, counted the same way as
+    /// [`DecodeMetrics::rs_iterations`](../metrics/struct.DecodeMetrics.html#structfield.rs_iterations). Defaults to
+    /// 10000, several times what a damaged version 40 code takes
+    pub max_rs_iterations: u64,
+    /// Pixels `QRExtractor` reads per code, counted the same way as
+    /// [`DecodeMetrics::extract_pixels`](../metrics/struct.DecodeMetrics.html#structfield.extract_pixels). A false
+    /// positive spanning a large part of the image reads long lines of pixels looking for edges that are not there;
+    /// past the limit every pixel reads as light, which ends that quickly, and the code fails with the limit.
+    /// Defaults to 1000000, about 30 times what a version 40 code takes
+    pub max_extract_pixels: u64,
+}
+
+impl ResourceLimits {
+    /// No limits at all, for trusted input
+    pub const UNLIMITED: ResourceLimits = ResourceLimits {
+        max_candidates: u64::MAX,
+        max_extractions: usize::MAX,
+        max_rs_iterations: u64::MAX,
+        max_extract_pixels: u64::MAX,
+    };
+}
+
+impl Default for ResourceLimits {
+    fn default() -> ResourceLimits {
+        ResourceLimits {
+            max_candidates: 256,
+            max_extractions: 256,
+            max_rs_iterations: 10_000,
+            max_extract_pixels: 1_000_000,
+        }
+    }
+}
+
+/// A limit spent by the components while a budget is set, see [`within`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Limit {
+    Candidates,
+    RsIterations,
+    ExtractPixels,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Budget {
+    limits: ResourceLimits,
+    candidates: u64,
+    rs_iterations: u64,
+    extract_pixels: u64,
+    exceeded: Option<Limit>,
+}
+
+thread_local! {
+    static BUDGET: Cell<Option<Budget>> = const { Cell::new(None) };
+}
+
+/// Spend one of the limit, false when there is none left. Without a budget on this thread nothing is limited
+pub(crate) fn spend(limit: Limit) -> bool {
+    spend_many(limit, 1)
+}
+
+/// Spend `amount` of the limit at once, false when there is not that much left
+pub(crate) fn spend_many(limit: Limit, amount: u64) -> bool {
+    BUDGET.with(|budget| {
+        let Some(mut current) = budget.get() else {
+            return true;
+        };
+
+        let (spent, max) = spent(&mut current, limit);
+
+        let left = amount <= max - *spent;
+        if left {
+            *spent += amount;
+        } else {
+            *spent = max;
+            current.exceeded.get_or_insert(limit);
+        }
+
+        budget.set(Some(current));
+        left
+    })
+}
+
+/// What is left of the limit, `u64::MAX` without a budget on this thread
+pub(crate) fn left(limit: Limit) -> u64 {
+    BUDGET.with(|budget| match budget.get() {
+        Some(mut current) => {
+            let (spent, max) = spent(&mut current, limit);
+            max - *spent
+        }
+        None => u64::MAX,
+    })
+}
+
+fn spent(budget: &mut Budget, limit: Limit) -> (&mut u64, u64) {
+    match limit {
+        Limit::Candidates => (&mut budget.candidates, budget.limits.max_candidates),
+        Limit::RsIterations => (&mut budget.rs_iterations, budget.limits.max_rs_iterations),
+        Limit::ExtractPixels => (&mut budget.extract_pixels, budget.limits.max_extract_pixels),
+    }
+}
+
+/// A source that reads as light once the pixels left of [`Limit::ExtractPixels`] are used up
+///
+/// Only single pixels are counted, the extractor reads rows for the debug images only. The pixels read are spent with
+/// [`spend`](#method.spend).
+pub(crate) struct Limited<'a, P> {
+    source: &'a P,
+    left: u64,
+    read: Cell<u64>,
+}
+
+impl<'a, P: LumaSource> Limited<'a, P> {
+    pub(crate) fn new(source: &'a P) -> Limited<'a, P> {
+        Limited {
+            source,
+            left: left(Limit::ExtractPixels),
+            read: Cell::new(0),
+        }
+    }
+
+    /// Spend the pixels read, false when there were more than there was left
+    pub(crate) fn spend(&self) -> bool {
+        spend_many(Limit::ExtractPixels, self.read.get())
+    }
+}
+
+impl<P: LumaSource> LumaSource for Limited<'_, P> {
+    fn luma_dimensions(&self) -> (u32, u32) {
+        self.source.luma_dimensions()
+    }
+
+    fn luma_row(&self, y: u32) -> &[u8] {
+        self.source.luma_row(y)
+    }
+
+    fn luma(&self, x: u32, y: u32) -> u8 {
+        let read = self.read.get();
+        self.read.set(read.saturating_add(1));
+
+        match read < self.left {
+            true => self.source.luma(x, y),
+            false => 255,
+        }
+    }
+}
+
+/// Run the function with a fresh budget on this thread, with the error for the first limit that ran out
+pub(crate) fn within<T>(limits: ResourceLimits, f: impl FnOnce() -> T) -> (T, Option<QRError>) {
+    let budget = Budget {
+        limits,
+        candidates: 0,
+        rs_iterations: 0,
+        extract_pixels: 0,
+        exceeded: None,
+    };
+
+    let outer = BUDGET.with(|current| current.replace(Some(budget)));
+    let result = f();
+    let spent = BUDGET.with(|current| current.replace(outer)).expect("Budget is set");
+
+    let error = spent.exceeded.map(|limit| {
+        let (max, what) = match limit {
+            Limit::Candidates => (limits.max_candidates, "finder patterns"),
+            Limit::RsIterations => (limits.max_rs_iterations, "Reed-Solomon iterations"),
+            Limit::ExtractPixels => (limits.max_extract_pixels, "pixels read extracting the code"),
+        };
+        QRError::resource_limit(&format!("more than the maximum of {max} {what}"))
+    });
+
+    (result, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_within() {
+        assert!(spend(Limit::Candidates));
+
+        let limits = ResourceLimits {
+            max_candidates: 2,
+            ..ResourceLimits::UNLIMITED
+        };
+        let (spent, error) = within(limits, || {
+            let spent: Vec<bool> = (0..3).map(|_| spend(Limit::Candidates)).collect();
+
+            // A budget inside another has limits of its own
+            let ((), inner) = within(ResourceLimits::UNLIMITED, || assert!(spend(Limit::Candidates)));
+            assert_eq!(inner, None);
+
+            spent
+        });
+
+        assert_eq!(spent, vec![true, true, false]);
+        let error = error.unwrap();
+        assert!(error.is_resource_limit());
+        assert!(error.msg.ends_with("more than the maximum of 2 finder patterns"));
+
+        let ((), error) = within(ResourceLimits::default(), || assert!(spend(Limit::RsIterations)));
+        assert_eq!(error, None);
+    }
+
+    #[test]
+    fn test_limited() {
+        use crate::util::LumaImage;
+
+        let image = LumaImage::from_fn(4, 4, |_, _| 0);
+        assert_eq!(Limited::new(&image).luma(0, 0), 0);
+
+        let limits = ResourceLimits {
+            max_extract_pixels: 10,
+            ..ResourceLimits::UNLIMITED
+        };
+        let (spent, error) = within(limits, || {
+            assert!(spend_many(Limit::ExtractPixels, 4));
+            assert_eq!(left(Limit::ExtractPixels), 6);
+
+            // Past what is left, every pixel is light
+            let limited = Limited::new(&image);
+            let pixels: Vec<u8> = (0..8).map(|x| limited.luma(x % 4, 0)).collect();
+            assert_eq!(pixels, vec![0, 0, 0, 0, 0, 0, 255, 255]);
+
+            let spent = limited.spend();
+            assert_eq!(left(Limit::ExtractPixels), 0);
+            spent
+        });
+
+        assert!(!spent);
+        assert!(error.unwrap().msg.ends_with("more than the maximum of 10 pixels read extracting the code"));
+    }
+}
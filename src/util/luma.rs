@@ -0,0 +1,158 @@
+use std::ops::Deref;
+
+/// 8 bit grayscale pixels that can be read row by row
+///
+/// This is all the default [`Prepare`], [`Detect`] and [`Extract`] implementations need from an image, so they work
+/// with any buffer implementing this trait, not just the types of the `image` crate. It is implemented for
+/// [`LumaImage`] and, with the `image` feature, for `GrayImage`.
+///
+/// [`Prepare`]: ../prepare/trait.Prepare.html
+/// [`Detect`]: ../detect/trait.Detect.html
+/// [`Extract`]: ../extract/trait.Extract.html
+/// [`LumaImage`]: struct.LumaImage.html
+pub trait LumaSource {
+    /// Width and height in pixels
+    fn luma_dimensions(&self) -> (u32, u32);
+
+    /// The pixels of row `y`, which must be at least as long as the width
+    fn luma_row(&self, y: u32) -> &[u8];
+
+    /// The pixel at (`x`, `y`)
+    fn luma(&self, x: u32, y: u32) -> u8 {
+        self.luma_row(y)[x as usize]
+    }
+}
+
+/// Grayscale image on top of a plain byte buffer, one byte per pixel
+///
+/// The buffer can be owned, like the default `Vec<u8>`, or borrowed, for example a `&[u8]` pointing into a camera
+/// frame. Rows may be padded, see [`with_stride`].
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::util::{LumaImage, LumaSource};
+///
+/// let frame = vec![255; 640 * 480];
+/// let img = LumaImage::new(640, 480, &frame[..]).unwrap();
+/// assert_eq!(img.luma(10, 10), 255);
+/// ```
+///
+/// [`with_stride`]: #method.with_stride
+#[derive(Debug, Clone, PartialEq)]
+pub struct LumaImage<C = Vec<u8>> {
+    width: u32,
+    height: u32,
+    stride: usize,
+    data: C,
+}
+
+impl<C: Deref<Target = [u8]>> LumaImage<C> {
+    /// Wrap a buffer with rows of exactly `width` pixels
+    ///
+    /// Returns `None` if the buffer is too small for the provided dimensions
+    pub fn new(width: u32, height: u32, data: C) -> Option<LumaImage<C>> {
+        LumaImage::with_stride(width, height, width as usize, data)
+    }
+
+    /// Wrap a buffer where every row starts `stride` bytes after the previous one
+    ///
+    /// Returns `None` if the stride is smaller than the width, or the buffer is too small for the provided dimensions
+    pub fn with_stride(width: u32, height: u32, stride: usize, data: C) -> Option<LumaImage<C>> {
+        let needed = match height {
+            0 => 0,
+            _ => stride * (height as usize - 1) + width as usize,
+        };
+
+        if stride < width as usize || data.len() < needed {
+            return None;
+        }
+
+        Some(LumaImage {
+            width,
+            height,
+            stride,
+            data,
+        })
+    }
+
+    /// Return the underlying buffer
+    pub fn into_raw(self) -> C {
+        self.data
+    }
+}
+
+impl LumaImage<Vec<u8>> {
+    /// Create an image of the provided size, with every pixel set by `f`
+    pub fn from_fn(width: u32, height: u32, mut f: impl FnMut(u32, u32) -> u8) -> LumaImage<Vec<u8>> {
+        let mut data = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                data.push(f(x, y));
+            }
+        }
+
+        LumaImage {
+            width,
+            height,
+            stride: width as usize,
+            data,
+        }
+    }
+}
+
+impl<C: Deref<Target = [u8]>> LumaSource for LumaImage<C> {
+    fn luma_dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn luma_row(&self, y: u32) -> &[u8] {
+        let start = y as usize * self.stride;
+        &self.data[start..start + self.width as usize]
+    }
+}
+
+#[cfg(feature = "image")]
+impl<C: Deref<Target = [u8]>> LumaSource for image::ImageBuffer<image::Luma<u8>, C> {
+    fn luma_dimensions(&self) -> (u32, u32) {
+        self.dimensions()
+    }
+
+    fn luma_row(&self, y: u32) -> &[u8] {
+        let raw: &[u8] = self;
+        let width = self.width() as usize;
+        let start = y as usize * width;
+        &raw[start..start + width]
+    }
+}
+
+/// Copy any source into a `GrayImage`, to draw the debug images on
+///
+/// Row by row, so the pixels do not count against the limit of the pixels an extraction reads
+#[cfg(feature = "debug-images")]
+pub(crate) fn to_gray_image<S: LumaSource>(source: &S) -> image::GrayImage {
+    let (width, height) = source.luma_dimensions();
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        pixels.extend_from_slice(&source.luma_row(y)[..width as usize]);
+    }
+
+    image::GrayImage::from_raw(width, height, pixels).expect("Every row is as long as the width")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stride() {
+        let data = [1, 2, 3, 0, 4, 5, 6];
+        let img = LumaImage::with_stride(3, 2, 4, &data[..]).unwrap();
+
+        assert_eq!(img.luma_row(1), &[4, 5, 6]);
+        assert_eq!(img.luma(2, 0), 3);
+
+        assert!(LumaImage::with_stride(3, 2, 2, &data[..]).is_none());
+        assert!(LumaImage::new(3, 3, &data[..]).is_none());
+    }
+}
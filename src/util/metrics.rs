@@ -0,0 +1,139 @@
+//! Counters of the work done during a decode, see [`Decoder::decode_with_metrics`]
+//!
+//! The default components count the pixels they read, the candidates they examine and the iterations of error
+//! correction. Counting is cheap, it is only collected while [`Decoder::decode_with_metrics`] runs, per thread like
+//! a [trace](../trace/index.html). Unlike timings, the counts are the same on every run and every machine, so they
+//! show whether a change to the pipeline does less work even when the difference is lost in the noise of a benchmark.
+//!
+//! [`Decoder::decode_with_metrics`]: ../../struct.Decoder.html#method.decode_with_metrics
+
+use std::cell::Cell;
+use std::ops::AddAssign;
+
+use crate::util::LumaSource;
+
+/// Work done while decoding, added up over every code in the image
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DecodeMetrics {
+    /// Pixels read while preparing the image, once for every pass over it to threshold or smooth it
+    pub prepare_pixels: u64,
+    /// Pixels read while detecting codes, including the scan of every row
+    pub detect_pixels: u64,
+    /// Pixels read while extracting the modules of the detected codes
+    pub extract_pixels: u64,
+    /// Possible finder patterns found on a row and checked in the other directions
+    pub finder_candidates: u64,
+    /// Groups of three finder patterns of similar size checked for being the corners of a QR Code
+    pub finder_groups: u64,
+    /// Reed-Solomon iterations: the number of errors tried while locating them in a damaged block, counting down from
+    /// the most that can be corrected. Blocks without errors take none
+    pub rs_iterations: u64,
+    /// Steps run again after a failure: every fallback detector or decoder that ran, every format tried after the
+    /// first, and every turned view of `RotatedViews` after the first
+    pub retries: u64,
+}
+
+impl AddAssign for DecodeMetrics {
+    fn add_assign(&mut self, other: DecodeMetrics) {
+        self.prepare_pixels += other.prepare_pixels;
+        self.detect_pixels += other.detect_pixels;
+        self.extract_pixels += other.extract_pixels;
+        self.finder_candidates += other.finder_candidates;
+        self.finder_groups += other.finder_groups;
+        self.rs_iterations += other.rs_iterations;
+        self.retries += other.retries;
+    }
+}
+
+thread_local! {
+    static METRICS: Cell<Option<DecodeMetrics>> = const { Cell::new(None) };
+}
+
+/// Update the counters if metrics are being collected on this thread
+pub(crate) fn count(update: impl FnOnce(&mut DecodeMetrics)) {
+    METRICS.with(|metrics| {
+        if let Some(mut counted) = metrics.get() {
+            update(&mut counted);
+            metrics.set(Some(counted));
+        }
+    });
+}
+
+/// Run the function while collecting metrics, which are also added to those of an outer collection
+pub(crate) fn collect<T>(f: impl FnOnce() -> T) -> (T, DecodeMetrics) {
+    let outer = METRICS.with(|metrics| metrics.replace(Some(DecodeMetrics::default())));
+    let result = f();
+    let counted = METRICS.with(|metrics| metrics.replace(outer)).unwrap_or_default();
+
+    count(|metrics| *metrics += counted);
+
+    (result, counted)
+}
+
+/// A source that counts the pixels read from it, a whole row for every row
+pub(crate) struct Counted<'a, P> {
+    source: &'a P,
+    pixels: Cell<u64>,
+}
+
+impl<'a, P: LumaSource> Counted<'a, P> {
+    pub(crate) fn new(source: &'a P) -> Counted<'a, P> {
+        Counted {
+            source,
+            pixels: Cell::new(0),
+        }
+    }
+
+    pub(crate) fn pixels(&self) -> u64 {
+        self.pixels.get()
+    }
+}
+
+impl<P: LumaSource> LumaSource for Counted<'_, P> {
+    fn luma_dimensions(&self) -> (u32, u32) {
+        self.source.luma_dimensions()
+    }
+
+    fn luma_row(&self, y: u32) -> &[u8] {
+        self.pixels.set(self.pixels.get() + u64::from(self.luma_dimensions().0));
+        self.source.luma_row(y)
+    }
+
+    fn luma(&self, x: u32, y: u32) -> u8 {
+        self.pixels.set(self.pixels.get() + 1);
+        self.source.luma(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::LumaImage;
+
+    #[test]
+    fn test_collect() {
+        count(|_| panic!("Nothing is collected"));
+
+        let ((), outer) = collect(|| {
+            count(|metrics| metrics.retries += 1);
+
+            let ((), inner) = collect(|| count(|metrics| metrics.rs_iterations += 2));
+            assert_eq!(inner.rs_iterations, 2);
+            assert_eq!(inner.retries, 0);
+        });
+
+        assert_eq!((outer.retries, outer.rs_iterations), (1, 2));
+    }
+
+    #[test]
+    fn test_counted() {
+        let image = LumaImage::from_fn(10, 4, |x, y| (x + y) as u8);
+        let counted = Counted::new(&image);
+
+        assert_eq!(counted.luma(3, 2), 5);
+        assert_eq!(counted.luma_row(1)[4], 5);
+        assert_eq!(counted.luma_dimensions(), (10, 4));
+        assert_eq!(counted.pixels(), 11);
+    }
+}
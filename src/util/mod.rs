@@ -3,9 +3,29 @@
 #[allow(clippy::unreadable_literal)]
 mod chomp;
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod fault;
+#[cfg(feature = "debug-images")]
+pub(crate) mod draw;
+mod luma;
 mod point;
 
+pub(crate) mod simd;
+
+pub mod bch;
+pub mod capacity;
+pub mod guidance;
+pub mod limits;
+pub mod metrics;
+pub mod observer;
 pub mod qr;
+pub mod quality;
+pub mod session;
+pub mod tile;
+pub mod trace;
 
 pub use self::chomp::Chomp;
+pub use self::luma::{LumaImage, LumaSource};
+#[cfg(feature = "debug-images")]
+pub(crate) use self::luma::to_gray_image;
 pub use self::point::{Delta, Point};
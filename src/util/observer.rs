@@ -0,0 +1,64 @@
+//! Hooks into every step of a decode, see [`DecoderBuilder::observer`]
+//!
+//! An observer is told about the outcome of every step while a [`Decoder`] runs, for all of its methods. Use it for
+//! metrics, logging or feedback in a user interface, without wrapping the components or parsing `log` output.
+//!
+//! [`DecoderBuilder::observer`]: ../../struct.DecoderBuilder.html#method.observer
+//! [`Decoder`]: ../../struct.Decoder.html
+//!
+//! # Example
+//! ```
+//! # extern crate bardecoder;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! use bardecoder::util::observer::PipelineObserver;
+//! use bardecoder::util::qr::QRLocation;
+//!
+//! #[derive(Default)]
+//! struct Counter {
+//!     candidates: AtomicUsize,
+//! }
+//!
+//! impl<PREPD, RESULT> PipelineObserver<PREPD, RESULT> for Counter {
+//!     fn on_candidate(&self, _location: &QRLocation) {
+//!         self.candidates.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! }
+//! ```
+
+use crate::util::qr::{QRData, QRError, QRLocation};
+
+/// Why a detected code was not extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// The modules are smaller than [`DecoderBuilder::min_module_size`](../../struct.DecoderBuilder.html#method.min_module_size)
+    ModuleSize,
+    /// The version is larger than [`DecoderBuilder::max_version`](../../struct.DecoderBuilder.html#method.max_version)
+    Version,
+    /// More codes were detected than [`DecoderBuilder::max_codes`](../../struct.DecoderBuilder.html#method.max_codes)
+    TooManyCodes,
+}
+
+/// Receives the outcome of every step of a decode
+///
+/// All methods do nothing by default, so an observer only implements the ones it needs. They are called on the
+/// thread that decodes, in the order the steps run, so they should return quickly.
+pub trait PipelineObserver<PREPD, RESULT> {
+    /// The source image was prepared
+    fn on_prepared(&self, _prepared: &PREPD) {}
+
+    /// A code was detected, before the limits of the builder are applied
+    fn on_candidate(&self, _location: &QRLocation) {}
+
+    /// A detected code is skipped because of a limit set on the builder
+    fn on_rejected(&self, _location: &QRLocation, _reason: Rejection) {}
+
+    /// The modules of a code were extracted
+    fn on_extracted(&self, _data: &QRData) {}
+
+    /// A code was decoded
+    fn on_decoded(&self, _result: &RESULT) {}
+
+    /// Extracting or decoding a code failed
+    fn on_error(&self, _error: &QRError) {}
+}
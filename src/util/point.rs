@@ -2,6 +2,7 @@ use std::ops::{Add, Div, Mul, Sub};
 
 /// Representation of a location in the source image, in pixels
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     /// X Coordinate, in pixels
     pub x: f64,
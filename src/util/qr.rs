@@ -1,27 +1,220 @@
 //! Utility structs for decoding QR Codes
 
+use std::convert::TryFrom;
+use std::fmt;
 use std::ops::Index;
 
 use std::string::FromUtf8Error;
 
+#[cfg(feature = "image")]
+use image::ImageError;
+
 use crate::util::Point;
 
 /// Generic QR Error message
+///
+/// Errors of custom components are made with [`QRError::new`](#method.new). The errors of this crate also carry the
+/// [`ErrorCode`](enum.ErrorCode.html) of their kind, which is set when they are made, see
+/// [`code`](#method.code).
 #[derive(thiserror::Error, Debug, Clone, PartialEq)]
 #[error("Error decoding QR Code: {msg}")]
 pub struct QRError {
     /// Detail message
     pub msg: String,
+
+    code: ErrorCode,
+}
+
+impl QRError {
+    /// Error with the message, of [`ErrorCode::Other`](enum.ErrorCode.html#variant.Other), such as for a custom
+    /// component
+    pub fn new(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::Other, msg)
+    }
+
+    /// Error for input that exceeds one of the limits set on the `DecoderBuilder`, see
+    /// [`is_resource_limit`](#method.is_resource_limit)
+    pub fn resource_limit(what: &str) -> QRError {
+        QRError::with_code(ErrorCode::ResourceLimit, format!("{RESOURCE_LIMIT}{what}"))
+    }
+
+    pub(crate) fn image(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::Image, msg)
+    }
+
+    pub(crate) fn invalid_input(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::InvalidInput, msg)
+    }
+
+    pub(crate) fn no_contrast(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::NoContrast, msg)
+    }
+
+    pub(crate) fn extract(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::Extract, msg)
+    }
+
+    pub(crate) fn format(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::Format, msg)
+    }
+
+    pub(crate) fn version(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::Version, msg)
+    }
+
+    pub(crate) fn correction(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::Correction, msg)
+    }
+
+    pub(crate) fn data(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::Data, msg)
+    }
+
+    pub(crate) fn unsupported_mode(msg: impl Into<String>) -> QRError {
+        QRError::with_code(ErrorCode::UnsupportedMode, msg)
+    }
+
+    // For errors that stand for another one, such as that of a `Fallback` for its first attempt
+    pub(crate) fn with_code(code: ErrorCode, msg: impl Into<String>) -> QRError {
+        QRError { msg: msg.into(), code }
+    }
+
+    /// Whether decoding stopped early because of a limit set on the `DecoderBuilder`, rather than because the input
+    /// could not be decoded
+    pub fn is_resource_limit(&self) -> bool {
+        self.code == ErrorCode::ResourceLimit
+    }
+
+    /// What kind of failure this is, with a numeric value that bindings and log aggregation can rely on
+    ///
+    /// The code is set where the error is made, so unlike the message, which is meant for people and can be reworded,
+    /// it does not change, see [`ErrorCode`]. Errors of custom components are [`ErrorCode::Other`], whatever their
+    /// message. When every decoder of a [`Fallback`](../../decode/struct.Fallback.html) failed, the code is that of
+    /// the first one.
+    ///
+    /// [`ErrorCode`]: enum.ErrorCode.html
+    /// [`ErrorCode::Other`]: enum.ErrorCode.html#variant.Other
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::util::qr::{ErrorCode, QRError};
+    ///
+    /// let error = QRError::resource_limit("too many codes");
+    /// assert_eq!(error.code(), ErrorCode::ResourceLimit);
+    /// assert_eq!(error.code().value(), 100);
+    ///
+    /// assert_eq!(QRError::new("Expected a label").code(), ErrorCode::Other);
+    /// ```
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+}
+
+const RESOURCE_LIMIT: &str = "Resource limit exceeded: ";
+
+/// Kind of a [`QRError`], see [`QRError::code`]
+///
+/// # Compatibility
+///
+/// The numeric value of every code is part of the public API: it never changes and is never reused for another code,
+/// so it can be stored, compared across versions of this crate and mapped to error codes of C, Python or Node bindings.
+/// New codes can be added in any release, which is why the enum is non-exhaustive; treat unknown values like
+/// [`Other`](#variant.Other). An error that is classified more precisely in a later release can move from `Other` to
+/// a new code, but never from one existing code to another.
+///
+/// The hundreds follow the steps of decoding: 1xx for the input, 2xx for extracting, 3xx for the format and version,
+/// 4xx for error correction and 5xx for the data.
+///
+/// [`QRError`]: struct.QRError.html
+/// [`QRError::code`]: struct.QRError.html#method.code
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+#[repr(u32)]
+pub enum ErrorCode {
+    /// Any other error, such as one of a custom component
+    Other = 1,
+    /// Input over a limit set on the `DecoderBuilder`, see [`QRError::is_resource_limit`]
+    ///
+    /// [`QRError::is_resource_limit`]: struct.QRError.html#method.is_resource_limit
+    ResourceLimit = 100,
+    /// The image could not be read or decoded from its file format
+    Image = 101,
+    /// Input that does not fit together, such as a `QRData` with the wrong number of modules for its version
+    InvalidInput = 102,
+    /// The image is too uniform to show a code, see
+    /// [`DecoderBuilder::min_dynamic_range`](../../struct.DecoderBuilder.html#method.min_dynamic_range)
+    NoContrast = 103,
+    /// The modules could not be sampled from the image
+    Extract = 200,
+    /// The format information is unreadable or invalid
+    Format = 300,
+    /// The version is not one of the 40 of QR Codes
+    Version = 301,
+    /// There were more errors than error correction can fix
+    Correction = 400,
+    /// The corrected data does not hold a valid message
+    Data = 500,
+    /// The data uses a mode that is not supported
+    UnsupportedMode = 501,
+    /// The message is not valid UTF-8
+    Utf8 = 502,
+}
+
+impl ErrorCode {
+    /// Every code, in order of their values
+    pub const ALL: [ErrorCode; 12] = [
+        ErrorCode::Other,
+        ErrorCode::ResourceLimit,
+        ErrorCode::Image,
+        ErrorCode::InvalidInput,
+        ErrorCode::NoContrast,
+        ErrorCode::Extract,
+        ErrorCode::Format,
+        ErrorCode::Version,
+        ErrorCode::Correction,
+        ErrorCode::Data,
+        ErrorCode::UnsupportedMode,
+        ErrorCode::Utf8,
+    ];
+
+    /// The stable numeric value of the code
+    pub fn value(self) -> u32 {
+        self as u32
+    }
+
+    /// The code with this value, `None` for values this version of the crate does not know
+    pub fn from_value(value: u32) -> Option<ErrorCode> {
+        ErrorCode::ALL.iter().copied().find(|code| code.value() == value)
+    }
+}
+
+impl From<ErrorCode> for u32 {
+    fn from(code: ErrorCode) -> u32 {
+        code.value()
+    }
 }
 
 impl From<FromUtf8Error> for QRError {
     fn from(error: FromUtf8Error) -> Self {
-        QRError {
-            msg: format!(
-                "Unable to convert result to UTF-8, raw bytes: {bytes:?}",
-                bytes = error.into_bytes()
-            ),
-        }
+        QRError::with_code(
+            ErrorCode::Utf8,
+            format!("Unable to convert result to UTF-8, raw bytes: {bytes:?}", bytes = error.into_bytes()),
+        )
+    }
+}
+
+impl From<std::io::Error> for QRError {
+    fn from(error: std::io::Error) -> Self {
+        QRError::image(format!("Unable to read image: {error}"))
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<ImageError> for QRError {
+    fn from(error: ImageError) -> Self {
+        QRError::image(format!("Unable to load image: {error}"))
     }
 }
 
@@ -29,6 +222,9 @@ impl From<FromUtf8Error> for QRError {
 ///
 /// While the data is still pixels of value 0/255, using the index will convert it into 1's (pixel 0) and 0's (pixel 255)
 ///
+/// More fields can be added in later versions, so outside of this crate it is made with [`QRData::new`](#method.new),
+/// and the fields an extractor estimates, such as `module_size`, are set on it after that.
+///
 /// # Example
 /// ```
 /// # extern crate bardecoder;
@@ -43,7 +239,8 @@ impl From<FromUtf8Error> for QRError {
 /// assert_eq!(qr_data[[0, 0]], 1);
 /// assert_eq!(qr_data[[20, 20]], 0);
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct QRData {
     /// QR Pixel Data in side x side pixels, stored in row major order. Using the provided index will convert into 1's and 0's.
     pub data: Vec<u8>,
@@ -53,19 +250,193 @@ pub struct QRData {
 
     /// Side in pixels of the QR square
     pub side: u32,
+
+    /// How much larger than a module the dark modules were printed, as a fraction of the module size. Negative if they
+    /// were printed smaller. Left at 0.0 by extractors that do not estimate it.
+    pub print_growth: f64,
+
+    /// Error correction level the QR Code is expected to have, from [`DecodeHints`]. The default decoders use it
+    /// instead of the format information when that is unreadable or does not agree, trying every mask pattern.
+    ///
+    /// [`DecodeHints`]: struct.DecodeHints.html
+    pub expected_ec_level: Option<ECLevel>,
+
+    /// Size of a module in pixels of the image, the average along the top and left side of the QR Code. Left at 0.0
+    /// by extractors that do not estimate it.
+    pub module_size: f64,
+
+    /// Resolution of the image in dots per inch, from [`DecodeHints`], to report the physical size of the QR Code
+    ///
+    /// [`DecodeHints`]: struct.DecodeHints.html
+    pub dpi: Option<f64>,
 }
 
 impl QRData {
-    /// Create a new QRData object with the provided data and version. `side` will be calculated automatically.
+    /// Create a new QRData object with the provided data and version. `side` will be calculated automatically, the
+    /// estimates are left at 0.0 and the hints at `None`.
     pub fn new(data: Vec<u8>, version: u32) -> QRData {
         QRData {
             data,
             version,
             side: 4 * version + 17,
+            print_growth: 0.0,
+            expected_ec_level: None,
+            module_size: 0.0,
+            dpi: None,
+        }
+    }
+
+    /// The physical size of the QR Code, from the module size and the resolution of the image
+    ///
+    /// `None` without either of them.
+    pub fn physical_size(&self) -> Option<PhysicalSize> {
+        let dpi = self.dpi.filter(|dpi| dpi.is_finite() && *dpi > 0.0)?;
+        if !(self.module_size.is_finite() && self.module_size > 0.0) {
+            return None;
         }
+
+        let module_mm = self.module_size * MM_PER_INCH / dpi;
+
+        Some(PhysicalSize {
+            module_mm,
+            symbol_mm: module_mm * f64::from(self.side),
+        })
+    }
+}
+
+const MM_PER_INCH: f64 = 25.4;
+
+/// How large a QR Code is printed, see [`QRInfo::physical_size`](struct.QRInfo.html#structfield.physical_size)
+///
+/// Printing specifications give the module size, also called the X-dimension, as a minimum or a range. The size of
+/// the symbol is without its quiet zone, add `2 * quiet_zone * module_mm` for the area it needs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicalSize {
+    /// Side of a module in millimeters
+    pub module_mm: f64,
+
+    /// Side of the QR Code in millimeters, without the quiet zone
+    pub symbol_mm: f64,
+}
+
+/// Both sizes to a hundredth of a millimeter, such as `0.50 mm modules, 12.50 mm wide`
+impl fmt::Display for PhysicalSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2} mm modules, {:.2} mm wide", self.module_mm, self.symbol_mm)
     }
 }
 
+/// The place of a QR Code in a structured append sequence, see
+/// [`QRInfo::structured_append`](struct.QRInfo.html#structfield.structured_append)
+///
+/// A message too long for a single code can be split over up to 16 codes, each holding a part of it. Join the parts
+/// with [`join_structured_append`](../../decode/fn.join_structured_append.html), which checks they belong together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructuredAppend {
+    /// Position of this code in the sequence, from 0
+    pub index: u8,
+
+    /// Number of codes in the sequence, from 1 to 16
+    pub total: u8,
+
+    /// Parity of the whole message, the same in every code of the sequence
+    pub parity: u8,
+
+    /// Parity of the part of the message in this code: the bytes of its data XORed together
+    pub data_parity: u8,
+}
+
+/// What is known about the QR Codes in advance, to decode labels whose version or format information is damaged
+///
+/// Pass them to [`Decoder::decode_with_hints`](../../struct.Decoder.html#method.decode_with_hints).
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use bardecoder::util::qr::DecodeHints;
+/// use bardecoder::ECLevel;
+///
+/// let mut hints = DecodeHints::new();
+/// hints.expected_version(3).expected_ec_level(ECLevel::HIGH);
+///
+/// assert_eq!(hints.version, Some(3));
+/// assert_eq!(hints.ec_level, Some(ECLevel::HIGH));
+/// ```
+///
+/// The [`Source`](enum.Source.html) of the image picks how the default components prepare it and extract the codes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DecodeHints {
+    /// Version of the QR Codes, used instead of the version estimated from the distance between the finder patterns
+    pub version: Option<u32>,
+
+    /// Error correction level of the QR Codes, used when the format information is unreadable or does not agree
+    pub ec_level: Option<ECLevel>,
+
+    /// Where the image comes from, [`Source::Print`](enum.Source.html#variant.Print) by default
+    pub source: Source,
+
+    /// Resolution of the image in dots per inch, to report the physical size of the QR Codes in
+    /// [`QRInfo::physical_size`](struct.QRInfo.html#structfield.physical_size)
+    pub dpi: Option<f64>,
+}
+
+impl DecodeHints {
+    /// No hints, decoding the same as without them
+    pub fn new() -> DecodeHints {
+        DecodeHints::default()
+    }
+
+    /// Extract the QR Codes with the grid of this version
+    pub fn expected_version(&mut self, version: u32) -> &mut DecodeHints {
+        self.version = Some(version);
+        self
+    }
+
+    /// Decode the QR Codes with this error correction level if their format information is damaged
+    pub fn expected_ec_level(&mut self, ec_level: ECLevel) -> &mut DecodeHints {
+        self.ec_level = Some(ec_level);
+        self
+    }
+
+    /// Prepare and extract the way that suits images from this source
+    pub fn source(&mut self, source: Source) -> &mut DecodeHints {
+        self.source = source;
+        self
+    }
+
+    /// The image was scanned or photographed at this resolution, in dots per inch
+    pub fn dpi(&mut self, dpi: f64) -> &mut DecodeHints {
+        self.dpi = Some(dpi);
+        self
+    }
+
+    /// The pixels of the image are this far apart on the label, in millimeters, the same as a resolution of
+    /// `25.4 / pixel_pitch` dots per inch
+    pub fn pixel_pitch(&mut self, pixel_pitch: f64) -> &mut DecodeHints {
+        self.dpi(MM_PER_INCH / pixel_pitch)
+    }
+}
+
+/// Where an image of QR Codes comes from, see [`DecodeHints::source`](struct.DecodeHints.html#method.source)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Source {
+    /// Printed codes, photographed or scanned
+    #[default]
+    Print,
+
+    /// Codes captured from a screen, in a screenshot or a photo of a display
+    ///
+    /// Sub-pixel rendering, fractional scaling and the moire of photographed pixels break the edges of the modules up
+    /// and make them uneven in size. [`BlockedMean`] smooths the image lightly before thresholding, and [`QRExtractor`]
+    /// ignores runs too short to be a module when counting the modules of the timing patterns.
+    ///
+    /// [`BlockedMean`]: ../../prepare/struct.BlockedMean.html
+    /// [`QRExtractor`]: ../../extract/struct.QRExtractor.html
+    Screen,
+}
+
 impl Index<[u32; 2]> for QRData {
     type Output = u8;
 
@@ -80,7 +451,8 @@ impl Index<[u32; 2]> for QRData {
 }
 
 /// Location of the QR Code in the source image, in pixels
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QRLocation {
     /// Center of the top left finder pattern, in pixels, relative to the QR Code
     pub top_left: Point,
@@ -98,8 +470,131 @@ pub struct QRLocation {
     pub version: u32,
 }
 
+impl QRLocation {
+    /// Estimate the four outer corners of the QR Code, in pixels
+    ///
+    /// The finder pattern centers are 3.5 modules away from the edges of the code, so the corners are found by extending
+    /// the lines between the finders. The fourth corner assumes there is no perspective skew.
+    ///
+    /// Corners are returned in the order top left, top right, bottom right, bottom left.
+    pub fn corners(&self) -> [Point; 4] {
+        let modules = f64::from(4 * self.version + 10);
+        let dx = (self.top_right - self.top_left) / modules;
+        let dy = (self.bottom_left - self.top_left) / modules;
+
+        let top_left = self.top_left - 3.5 * dx - 3.5 * dy;
+        let top_right = self.top_right + 3.5 * dx - 3.5 * dy;
+        let bottom_left = self.bottom_left - 3.5 * dx + 3.5 * dy;
+        let bottom_right = top_right + (bottom_left - top_left);
+
+        [top_left, top_right, bottom_right, bottom_left]
+    }
+
+    /// Center of the QR Code, in pixels, halfway between the top right and bottom left finder patterns
+    pub fn center(&self) -> Point {
+        self.top_right + (self.bottom_left - self.top_right) / 2.0
+    }
+
+    /// How much the three finder patterns look like those of a single QR Code, from 0.0 to 1.0
+    ///
+    /// A code seen straight on has its finder patterns at the corners of a right angle with sides of the same length,
+    /// as many modules long as the version says. This is 1.0 for such a location and falls with how far the sides differ
+    /// in length, the angle from 90 degrees and the distance between the finder patterns from the module size. Fake
+    /// groups of finder patterns of different codes, or of patterns in the background, usually score low.
+    pub fn confidence(&self) -> f64 {
+        let (across, down) = (self.top_right - self.top_left, self.bottom_left - self.top_left);
+        let (width, height) = (across.dx.hypot(across.dy), down.dx.hypot(down.dy));
+        let expected = f64::from(4 * self.version + 10) * self.module_size;
+
+        let similar = |a: f64, b: f64| a.min(b) / a.max(b);
+        let cos = (across.dx * down.dx + across.dy * down.dy) / (width * height);
+        let confidence = similar(width, height) * (1.0 - cos.abs()) * similar((width + height) / 2.0, expected);
+
+        match confidence.is_finite() {
+            true => confidence.clamp(0.0, 1.0),
+            false => 0.0,
+        }
+    }
+}
+
+/// The version, center and module size, such as `Version 2 code at (120, 80), 4.0 pixels per module`
+impl fmt::Display for QRLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let center = self.center();
+        write!(
+            f,
+            "Version {} code at ({:.0}, {:.0}), {:.1} pixels per module",
+            self.version, center.x, center.y, self.module_size
+        )
+    }
+}
+
+/// The step in which decoding a single detected QR Code failed, see [`CodeError`]
+///
+/// The steps are ordered the way they run.
+///
+/// [`CodeError`]: struct.CodeError.html
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stage {
+    /// Preparing the image, which was too uniform to show a code; the error is not about any one code
+    Prepare,
+    /// Detecting found more codes than the decoder allows; the error is not about any one of them
+    Detect,
+    /// Sampling the modules of the code from the prepared image
+    Extract,
+    /// Decoding the sampled modules into the result
+    Decode,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// The error of a single detected QR Code, with where the code is and which step failed
+///
+/// An application that decodes several codes at once can tell which of them is unreadable, for example to highlight
+/// it or to ask for the code at the top right to be rescanned. Returned by
+/// [`Decoder::decode_with_error_locations`](../../struct.Decoder.html#method.decode_with_error_locations).
+#[derive(Debug, Clone)]
+pub struct CodeError {
+    /// The error itself
+    pub error: QRError,
+
+    /// Where the code is in the image, `None` if the error is not about a single code
+    pub location: Option<QRLocation>,
+
+    /// The step that failed
+    pub stage: Stage,
+}
+
+impl fmt::Display for CodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => {
+                let center = location.center();
+                write!(f, "{} failed for the code at ({:.0}, {:.0}): {}", self.stage, center.x, center.y, self.error)
+            }
+            None => write!(f, "{} failed: {}", self.stage, self.error),
+        }
+    }
+}
+
+impl std::error::Error for CodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
 /// Information about the decoded QR Code
-#[derive(Debug, PartialEq)]
+///
+/// With the `serde` feature it can be serialized, for example to JSON for a log or a web service. More fields can be
+/// added in later versions, so outside of this crate it is made with [`QRInfo::new`](#method.new).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct QRInfo {
     /// Version of the QR Code, 1 being the smallest, 40 the largest
     pub version: u32,
@@ -107,15 +602,121 @@ pub struct QRInfo {
     /// Error correction level of the QR Code
     pub ec_level: ECLevel,
 
+    /// Mask pattern applied to the data, from 0 to 7
+    pub mask: u8,
+
     /// Number of bits of information in the QR Code. This includes both data codewords and error correction codewords.
     pub total_data: u32,
 
     /// Number of bits of information that were incorrect. This can be both in data codewords or error correction codewords since the algorithm doesn't distinguish between the two.
     pub errors: u32,
+
+    /// Coordinates (x, y), in modules, of every module that error correction had to flip, (0, 0) being the top left module.
+    /// There is one for each of the `errors`.
+    pub damaged_modules: Vec<(u32, u32)>,
+
+    /// How much larger than a module the dark modules were printed, as a fraction of the module size, estimated from
+    /// the timing patterns. Over-inked labels have a positive growth, 0.2 meaning dark modules are 20% too large.
+    pub print_growth: f64,
+
+    /// The data codewords after unmasking, deinterleaving and error correction, before they are parsed into segments.
+    /// Only included when asked for, see
+    /// [`QRDecoderWithInfo::with_raw_data`](../../decode/struct.QRDecoderWithInfo.html#method.with_raw_data).
+    pub raw_data: Option<Vec<u8>>,
+
+    /// The physical size of the module and of the QR Code, when decoded with a resolution in the
+    /// [`DecodeHints`](struct.DecodeHints.html#structfield.dpi) and an extractor that estimates the module size
+    pub physical_size: Option<PhysicalSize>,
+
+    /// The place of the QR Code in a structured append sequence, for codes that are part of one
+    pub structured_append: Option<StructuredAppend>,
+}
+
+impl QRInfo {
+    /// Information about a QR Code without damaged modules, print growth, raw data, physical size or structured append
+    ///
+    /// The other fields are public, to be filled in after.
+    pub fn new(version: u32, ec_level: ECLevel, mask: u8, total_data: u32, errors: u32) -> QRInfo {
+        QRInfo {
+            version,
+            ec_level,
+            mask,
+            total_data,
+            errors,
+            damaged_modules: vec![],
+            print_growth: 0.0,
+            raw_data: None,
+            physical_size: None,
+            structured_append: None,
+        }
+    }
+
+    /// Side of the QR Code, in modules
+    pub fn side(&self) -> u32 {
+        4 * self.version + 17
+    }
+
+    /// The damaged modules as a side x side mask, stored in row major order
+    ///
+    /// # Example
+    /// ```
+    /// # extern crate bardecoder;
+    /// use bardecoder::{ECLevel, QRInfo};
+    ///
+    /// let mut info = QRInfo::new(1, ECLevel::LOW, 0, 208, 1);
+    /// info.damaged_modules = vec![(20, 3)];
+    ///
+    /// let mask = info.damage_mask();
+    ///
+    /// assert_eq!(21 * 21, mask.len());
+    /// assert!(mask[3 * 21 + 20]);
+    /// assert_eq!(1, mask.iter().filter(|damaged| **damaged).count());
+    /// ```
+    pub fn damage_mask(&self) -> Vec<bool> {
+        let side = self.side();
+        let mut mask = vec![false; (side * side) as usize];
+
+        for (x, y) in &self.damaged_modules {
+            mask[(y * side + x) as usize] = true;
+        }
+
+        mask
+    }
+}
+
+/// A summary of the code and its damage, such as `Version 3-M, mask 5, 4 errors corrected`
+///
+/// The physical size follows when it is known.
+impl fmt::Display for QRInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Version {}-{}, mask {}, {} errors corrected", self.version, self.ec_level, self.mask, self.errors)?;
+
+        match &self.physical_size {
+            Some(size) => write!(f, ", {size}"),
+            None => Ok(()),
+        }
+    }
 }
 
 /// Error Correction level of the QR Code
-#[derive(Debug, PartialEq)]
+///
+/// The levels are ordered by how much damage they recover from, `LOW` < `MEDIUM` < `QUARTILE` < `HIGH`. They convert
+/// from and to the two bits of the format information, and are displayed as the letter of the level, such as `M`.
+///
+/// # Example
+/// ```
+/// # extern crate bardecoder;
+/// use std::convert::TryFrom;
+///
+/// use bardecoder::ECLevel;
+///
+/// assert_eq!(ECLevel::try_from(0b11), Ok(ECLevel::QUARTILE));
+/// assert_eq!(u8::from(&ECLevel::LOW), 0b01);
+/// assert_eq!(ECLevel::MEDIUM.to_string(), "M");
+/// assert!(ECLevel::HIGH > ECLevel::QUARTILE);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)]
 pub enum ECLevel {
     LOW,
@@ -123,3 +724,211 @@ pub enum ECLevel {
     QUARTILE,
     HIGH,
 }
+
+/// The level from the two bits of the format information, before they are masked
+impl TryFrom<u8> for ECLevel {
+    type Error = QRError;
+
+    fn try_from(bits: u8) -> Result<ECLevel, QRError> {
+        match bits {
+            0b01 => Ok(ECLevel::LOW),
+            0b00 => Ok(ECLevel::MEDIUM),
+            0b11 => Ok(ECLevel::QUARTILE),
+            0b10 => Ok(ECLevel::HIGH),
+            _ => Err(QRError::format(format!("Invalid error correction level: {bits:02b}"))),
+        }
+    }
+}
+
+/// The two bits of the format information for the level, before they are masked
+impl From<&ECLevel> for u8 {
+    fn from(level: &ECLevel) -> u8 {
+        match level {
+            ECLevel::LOW => 0b01,
+            ECLevel::MEDIUM => 0b00,
+            ECLevel::QUARTILE => 0b11,
+            ECLevel::HIGH => 0b10,
+        }
+    }
+}
+
+impl From<ECLevel> for u8 {
+    fn from(level: ECLevel) -> u8 {
+        u8::from(&level)
+    }
+}
+
+impl fmt::Display for ECLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letter = match self {
+            ECLevel::LOW => "L",
+            ECLevel::MEDIUM => "M",
+            ECLevel::QUARTILE => "Q",
+            ECLevel::HIGH => "H",
+        };
+
+        f.pad(letter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_code_values() {
+        // These values are a compatibility guarantee, they must never change
+        let values: Vec<u32> = ErrorCode::ALL.iter().map(|code| code.value()).collect();
+        assert_eq!(values, vec![1, 100, 101, 102, 103, 200, 300, 301, 400, 500, 501, 502]);
+
+        for code in ErrorCode::ALL.iter() {
+            assert_eq!(ErrorCode::from_value(code.value()), Some(*code));
+        }
+        assert_eq!(ErrorCode::from_value(0), None);
+    }
+
+    #[test]
+    fn test_error_code() {
+        assert_eq!(QRError::format("Format information corrupted").code(), ErrorCode::Format);
+        assert_eq!(QRError::image("Unable to read TIFF page 2: end of file").code(), ErrorCode::Image);
+        assert_eq!(QRError::resource_limit("image too large").code(), ErrorCode::ResourceLimit);
+        assert_eq!(QRError::from(String::from_utf8(vec![0xff]).unwrap_err()).code(), ErrorCode::Utf8);
+        assert_eq!(QRError::from(std::io::Error::other("gone")).code(), ErrorCode::Image);
+
+        // The message does not matter, a custom component that words its error like one of this crate gets Other
+        assert_eq!(QRError::new("Expected 441 modules but found 440").code(), ErrorCode::Other);
+        assert_eq!(QRError::new("Mode 1110 not yet implemented.").code(), ErrorCode::Other);
+        assert_ne!(QRError::new("Unknown version 41"), QRError::version("Unknown version 41"));
+
+        // Only the errors of the limits are resource limits, not those worded like them
+        assert!(QRError::resource_limit("image too large").is_resource_limit());
+        assert!(!QRError::new("Resource limit exceeded: image too large").is_resource_limit());
+    }
+
+    #[test]
+    fn test_ec_level() {
+        let levels = [ECLevel::LOW, ECLevel::MEDIUM, ECLevel::QUARTILE, ECLevel::HIGH];
+
+        for level in levels.iter() {
+            assert_eq!(ECLevel::try_from(u8::from(level)).as_ref(), Ok(level));
+        }
+        assert_eq!(ECLevel::try_from(4).unwrap_err().code(), ErrorCode::Format);
+
+        let letters: Vec<String> = levels.iter().map(ECLevel::to_string).collect();
+        assert_eq!(letters, vec!["L", "M", "Q", "H"]);
+        assert_eq!(format!("[{:>3}]", ECLevel::HIGH), "[  H]");
+
+        let mut sorted = vec![ECLevel::HIGH, ECLevel::LOW, ECLevel::QUARTILE, ECLevel::MEDIUM];
+        sorted.sort();
+        assert_eq!(sorted, levels.to_vec());
+    }
+
+    fn info() -> QRInfo {
+        QRInfo {
+            version: 3,
+            ec_level: ECLevel::MEDIUM,
+            mask: 5,
+            total_data: 560,
+            errors: 4,
+            damaged_modules: vec![(1, 2)],
+            print_growth: 0.1,
+            raw_data: None,
+            physical_size: None,
+            structured_append: None,
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let mut info = info();
+        assert_eq!(info.to_string(), "Version 3-M, mask 5, 4 errors corrected");
+
+        info.physical_size = Some(PhysicalSize {
+            module_mm: 0.5,
+            symbol_mm: 14.5,
+        });
+        assert_eq!(info.to_string(), "Version 3-M, mask 5, 4 errors corrected, 0.50 mm modules, 14.50 mm wide");
+
+        let loc = QRLocation {
+            top_left: Point { x: 13.5, y: 13.5 },
+            top_right: Point { x: 27.5, y: 13.5 },
+            bottom_left: Point { x: 13.5, y: 27.5 },
+            module_size: 1.0,
+            version: 1,
+        };
+        assert_eq!(loc.to_string(), "Version 1 code at (20, 20), 1.0 pixels per module");
+
+        assert_eq!(Stage::Extract.to_string(), "Extract");
+        assert!(Stage::Detect < Stage::Decode);
+        assert_eq!(u32::from(ErrorCode::Correction), 400);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let json = serde_json::to_string(&info()).unwrap();
+
+        assert!(json.starts_with(r#"{"version":3,"ec_level":"MEDIUM","mask":5,"#), "{}", json);
+        assert_eq!(serde_json::from_str::<QRInfo>(&json).unwrap(), info());
+        assert_eq!(serde_json::to_string(&ErrorCode::Format).unwrap(), r#""Format""#);
+    }
+
+    #[test]
+    fn test_confidence() {
+        let loc = |top_right: Point, module_size: f64| QRLocation {
+            top_left: Point { x: 13.5, y: 13.5 },
+            top_right,
+            bottom_left: Point { x: 13.5, y: 27.5 },
+            module_size,
+            version: 1,
+        };
+
+        assert_eq!(loc(Point { x: 27.5, y: 13.5 }, 1.0).confidence(), 1.0);
+        assert_eq!(loc(Point { x: 27.5, y: 13.5 }, 2.0).confidence(), 0.5);
+
+        let skewed = loc(Point { x: 27.5, y: 20.5 }, 1.0).confidence();
+        assert!(skewed > 0.3 && skewed < 0.8, "{}", skewed);
+
+        assert_eq!(loc(Point { x: 13.5, y: 13.5 }, 1.0).confidence(), 0.0);
+    }
+
+    #[test]
+    fn test_corners() {
+        // Version 1 is 21 modules wide, finder centers are 14 modules apart
+        let loc = QRLocation {
+            top_left: Point { x: 13.5, y: 13.5 },
+            top_right: Point { x: 27.5, y: 13.5 },
+            bottom_left: Point { x: 13.5, y: 27.5 },
+            module_size: 1.0,
+            version: 1,
+        };
+
+        let corners = loc.corners();
+        let expected = [(10.0, 10.0), (31.0, 10.0), (31.0, 31.0), (10.0, 31.0)];
+
+        for (corner, (x, y)) in corners.iter().zip(expected.iter()) {
+            assert!((corner.x - x).abs() < 1e-9, "{:?} != {:?}", corner, (x, y));
+            assert!((corner.y - y).abs() < 1e-9, "{:?} != {:?}", corner, (x, y));
+        }
+    }
+
+    #[test]
+    fn test_physical_size() {
+        let mut data = QRData::new(vec![255; 25 * 25], 2);
+        data.module_size = 5.0;
+        assert_eq!(data.physical_size(), None);
+
+        // 5 pixels at 254 DPI are half a millimeter
+        let mut hints = DecodeHints::new();
+        data.dpi = hints.dpi(254.0).dpi;
+        let size = data.physical_size().unwrap();
+        assert!((size.module_mm - 0.5).abs() < 1e-9);
+        assert!((size.symbol_mm - 12.5).abs() < 1e-9);
+
+        data.dpi = hints.pixel_pitch(0.02).dpi;
+        assert!((data.physical_size().unwrap().module_mm - 0.1).abs() < 1e-9);
+
+        data.module_size = 0.0;
+        assert_eq!(data.physical_size(), None);
+    }
+}
@@ -0,0 +1,185 @@
+//! Basic print quality metrics of a decoded code, see [`Decoder::decode_with_quality`]
+//!
+//! These follow the ideas of ISO/IEC 15415 but are measured on a single pixel per module, at the centers
+//! `QRExtractor` samples. That makes them cheap enough to log for every scan, but they are no replacement for a
+//! verifier when a proper grade is needed.
+//!
+//! [`Decoder::decode_with_quality`]: ../../struct.Decoder.html#method.decode_with_quality
+
+use crate::extract::Ring;
+use crate::util::LumaSource;
+
+/// Contrast metrics of a decoded code, reflectances are relative to white, from 0.0 to 1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SymbolQuality {
+    /// Difference between the highest and the lowest reflectance of all modules
+    pub symbol_contrast: f64,
+
+    /// Reflectance halfway between the highest and the lowest, separating dark from light modules
+    pub global_threshold: f64,
+
+    /// Average distance of a module from the global threshold, relative to half the symbol contrast. 1.0 means every
+    /// module is as dark or as light as the darkest or lightest one.
+    pub modulation: f64,
+
+    /// Distance of the worst module from the global threshold on the side of its decoded color, relative to half the
+    /// symbol contrast. This is negative when a module has the wrong color and had to be fixed by error correction.
+    pub reflectance_margin: f64,
+
+    /// Modules of clear space around the code, counted up to [`QUIET_ZONE`]: the number of rings of modules around it
+    /// that are all lighter than the global threshold. Space outside of the image is not counted, as a scanner cannot
+    /// see it either.
+    ///
+    /// [`QUIET_ZONE`]: constant.QUIET_ZONE.html
+    pub quiet_zone: u32,
+}
+
+/// Modules of clear space the standard requires around a QR Code
+pub const QUIET_ZONE: u32 = 4;
+
+/// The metrics to two decimals, such as `contrast 0.82, modulation 0.71, margin 0.40, quiet zone 4`
+impl std::fmt::Display for SymbolQuality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "contrast {:.2}, modulation {:.2}, margin {:.2}, quiet zone {}",
+            self.symbol_contrast, self.modulation, self.reflectance_margin, self.quiet_zone
+        )
+    }
+}
+
+impl SymbolQuality {
+    /// Whether the code has less clear space around it than the standard requires
+    ///
+    /// This crate finds codes without a full quiet zone, but many other scanners do not, so a code that decodes here
+    /// can still fail elsewhere. Dark marks close to the code, such as text or a frame, are the usual cause.
+    pub fn quiet_zone_violated(&self) -> bool {
+        self.quiet_zone < QUIET_ZONE
+    }
+}
+
+/// Measure the source at the module centers, `dark` tells the decoded color of every module
+///
+/// `rings` are the module centers of the rings of modules around the code, from the inside out, `None` when outside
+/// of the image
+pub(crate) fn measure<S: LumaSource>(
+    source: &S,
+    centers: &[(u32, u32)],
+    dark: &[bool],
+    rings: &[Ring],
+) -> SymbolQuality {
+    let (width, height) = source.luma_dimensions();
+    let reflectance = |(x, y): (u32, u32)| match x < width && y < height {
+        true => Some(f64::from(source.luma(x, y)) / 255.0),
+        false => None,
+    };
+    let samples: Vec<(f64, bool)> = centers
+        .iter()
+        .zip(dark)
+        .filter_map(|(center, dark)| Some((reflectance(*center)?, *dark)))
+        .collect();
+
+    let max = samples.iter().map(|s| s.0).fold(0.0, f64::max);
+    let min = samples.iter().map(|s| s.0).fold(1.0, f64::min);
+
+    if samples.is_empty() || max <= min {
+        return SymbolQuality {
+            symbol_contrast: 0.0,
+            global_threshold: max,
+            modulation: 0.0,
+            reflectance_margin: 0.0,
+            quiet_zone: 0,
+        };
+    }
+
+    let threshold = (max + min) / 2.0;
+    let half = (max - min) / 2.0;
+
+    let modulation = samples.iter().map(|s| (s.0 - threshold).abs() / half).sum::<f64>() / samples.len() as f64;
+    let margin = samples
+        .iter()
+        .map(|(reflectance, dark)| {
+            let margin = (reflectance - threshold) / half;
+            if *dark {
+                -margin
+            } else {
+                margin
+            }
+        })
+        .fold(1.0, f64::min);
+
+    let clear = |ring: &Ring| {
+        ring.iter()
+            .all(|center| center.and_then(reflectance).is_some_and(|reflectance| reflectance > threshold))
+    };
+    let quiet_zone = rings.iter().take(QUIET_ZONE as usize).take_while(|ring| clear(ring)).count() as u32;
+
+    SymbolQuality {
+        symbol_contrast: max - min,
+        global_threshold: threshold,
+        modulation,
+        reflectance_margin: margin,
+        quiet_zone,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::LumaImage;
+
+    fn assert_near(expected: f64, actual: f64) {
+        assert!((expected - actual).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn test_measure() {
+        // Four modules in a row: dark, dark, light and light, the second one right at the threshold
+        let source = LumaImage::from_fn(4, 1, |x, _| [51, 153, 204, 255][x as usize]);
+        let centers = vec![(0, 0), (1, 0), (2, 0), (3, 0)];
+
+        let quality = measure(&source, &centers, &[true, true, false, false], &[]);
+
+        assert_near(0.8, quality.symbol_contrast);
+        assert_near(0.6, quality.global_threshold);
+        assert_near((1.0 + 0.0 + 0.5 + 1.0) / 4.0, quality.modulation);
+        assert_near(0.0, quality.reflectance_margin);
+
+        let quality = measure(&source, &centers, &[true, false, false, false], &[]);
+        assert_near(0.0, quality.reflectance_margin);
+
+        let quality = measure(&source, &centers, &[false, true, false, false], &[]);
+        assert_near(-1.0, quality.reflectance_margin);
+    }
+
+    #[test]
+    fn test_measure_flat() {
+        let source = LumaImage::from_fn(2, 2, |_, _| 128);
+        let quality = measure(&source, &[(0, 0), (1, 1), (5, 5)], &[true, false, true], &[]);
+
+        assert_eq!(0.0, quality.symbol_contrast);
+        assert_eq!(0.0, quality.modulation);
+    }
+
+    #[test]
+    fn test_quiet_zone() {
+        // A dark and a light module, with rings of single pixels around them taken from the same row
+        let source = LumaImage::from_fn(6, 1, |x, _| [255, 255, 0, 255, 250, 255][x as usize]);
+        let centers = vec![(2, 0), (0, 0)];
+        let ring = |x| vec![Some((x, 0))];
+
+        let quality = measure(&source, &centers, &[true, false], &[ring(0), ring(4), ring(2), ring(5)]);
+        assert_eq!(2, quality.quiet_zone);
+        assert!(quality.quiet_zone_violated());
+
+        let rings = [ring(0), ring(1), ring(4), ring(5), ring(2)];
+        let quality = measure(&source, &centers, &[true, false], &rings);
+        assert_eq!(QUIET_ZONE, quality.quiet_zone);
+        assert!(!quality.quiet_zone_violated());
+
+        // Outside of the image
+        let quality = measure(&source, &centers, &[true, false], &[ring(0), vec![Some((0, 0)), None]]);
+        assert_eq!(1, quality.quiet_zone);
+    }
+}
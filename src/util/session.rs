@@ -0,0 +1,301 @@
+//! Statistics over many decodes, see [`ScanSession`]
+//!
+//! A scanner service decodes one image after the other. A [`ScanSession`] adds up the outcome of every decode: how
+//! many scans found a code, how many codes failed and why, the errors corrected and how long the scans took. A
+//! [`SessionSnapshot`] of it can be taken at any time, for a dashboard or a health check.
+//!
+//! [`ScanSession`]: struct.ScanSession.html
+//! [`SessionSnapshot`]: struct.SessionSnapshot.html
+//!
+//! # Example
+//! ```
+//! # extern crate bardecoder;
+//! use std::time::Instant;
+//!
+//! use bardecoder::util::session::ScanSession;
+//! use bardecoder::util::LumaImage;
+//!
+//! let decoder = bardecoder::default_luma_decoder();
+//! let session = ScanSession::new();
+//!
+//! for _ in 0..3 {
+//!     let source = LumaImage::from_fn(40, 40, |_, _| 255);
+//!     let start = Instant::now();
+//!     let results = decoder.decode(&source);
+//!     session.record(&results, start.elapsed());
+//! }
+//!
+//! let snapshot = session.snapshot();
+//! assert_eq!(snapshot.scans, 3);
+//! assert_eq!(snapshot.scan_success_rate(), 0.0);
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::util::qr::{ErrorCode, QRError, QRInfo};
+
+/// Scan times kept for the percentiles by default
+const WINDOW: usize = 1000;
+
+/// Adds up the results of many decodes, see the [module documentation](index.html)
+///
+/// Recording takes `&self`, so a session can be shared between the threads of a service, for example in an `Arc`.
+/// The counts cover every decode since the session was created. The percentiles of the scan time are over the most
+/// recent scans only, so they follow changes in load and memory use stays bounded.
+#[derive(Debug)]
+pub struct ScanSession {
+    state: Mutex<State>,
+    window: usize,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    scans: u64,
+    scans_with_codes: u64,
+    decoded: u64,
+    failed: u64,
+    failures: HashMap<ErrorCode, u64>,
+    errors_corrected: u64,
+    codes_with_info: u64,
+    times: VecDeque<Duration>,
+}
+
+impl ScanSession {
+    /// Construct a new ScanSession, keeping the times of the last 1000 scans for the percentiles
+    pub fn new() -> ScanSession {
+        ScanSession::with_window(WINDOW)
+    }
+
+    /// Construct a new ScanSession, keeping the times of the last `window` scans for the percentiles
+    pub fn with_window(window: usize) -> ScanSession {
+        ScanSession {
+            state: Mutex::new(State::default()),
+            window,
+        }
+    }
+
+    /// Record the results of a single decode, and how long it took
+    pub fn record<RESULT>(&self, results: &[Result<RESULT, QRError>], elapsed: Duration) {
+        let mut state = self.state();
+
+        state.scans += 1;
+        if results.iter().any(Result::is_ok) {
+            state.scans_with_codes += 1;
+        }
+
+        for result in results {
+            match result {
+                Ok(_) => state.decoded += 1,
+                Err(e) => {
+                    state.failed += 1;
+                    *state.failures.entry(e.code()).or_default() += 1;
+                }
+            }
+        }
+
+        if self.window > 0 {
+            if state.times.len() == self.window {
+                state.times.pop_front();
+            }
+            state.times.push_back(elapsed);
+        }
+    }
+
+    /// Record the results of a single decode with [`QRInfo`], to also add up the errors corrected
+    ///
+    /// [`QRInfo`]: ../qr/struct.QRInfo.html
+    pub fn record_with_info(&self, results: &[Result<(String, QRInfo), QRError>], elapsed: Duration) {
+        self.record(results, elapsed);
+
+        let mut state = self.state();
+        for (_, info) in results.iter().flatten() {
+            state.errors_corrected += u64::from(info.errors);
+            state.codes_with_info += 1;
+        }
+    }
+
+    /// The statistics of the session so far
+    pub fn snapshot(&self) -> SessionSnapshot {
+        let state = self.state();
+
+        let mut failures: Vec<(ErrorCode, u64)> = state.failures.iter().map(|(code, count)| (*code, *count)).collect();
+        failures.sort_by_key(|(code, _)| code.value());
+
+        let mut times: Vec<Duration> = state.times.iter().copied().collect();
+        times.sort();
+        let percentile = |p: f64| match times.len() {
+            0 => Duration::default(),
+            len => times[((p * len as f64).ceil() as usize).clamp(1, len) - 1],
+        };
+
+        SessionSnapshot {
+            scans: state.scans,
+            scans_with_codes: state.scans_with_codes,
+            decoded: state.decoded,
+            failed: state.failed,
+            failures,
+            errors_corrected: state.errors_corrected,
+            codes_with_info: state.codes_with_info,
+            median_time: percentile(0.5),
+            p90_time: percentile(0.9),
+            p99_time: percentile(0.99),
+            max_time: times.last().copied().unwrap_or_default(),
+        }
+    }
+
+    /// Start over, forgetting everything recorded so far
+    pub fn reset(&self) {
+        *self.state() = State::default();
+    }
+
+    // A panic in another thread while recording leaves counts that are at most one scan off, keep them
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for ScanSession {
+    fn default() -> ScanSession {
+        ScanSession::new()
+    }
+}
+
+/// The statistics of a [`ScanSession`] at one point in time
+///
+/// [`ScanSession`]: struct.ScanSession.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSnapshot {
+    /// Decodes recorded
+    pub scans: u64,
+    /// Decodes in which at least one code was decoded
+    pub scans_with_codes: u64,
+    /// Codes decoded
+    pub decoded: u64,
+    /// Codes that were detected but failed, and codes dropped because of a limit
+    pub failed: u64,
+    /// The failures by [`ErrorCode`], in order of the code values, without codes that did not occur
+    ///
+    /// Only QR Codes are decoded, so this is also how the failures of QR Codes break down.
+    ///
+    /// [`ErrorCode`]: ../qr/enum.ErrorCode.html
+    pub failures: Vec<(ErrorCode, u64)>,
+    /// Errors corrected in all codes recorded with [`QRInfo`]
+    ///
+    /// [`QRInfo`]: ../qr/struct.QRInfo.html
+    pub errors_corrected: u64,
+    /// Codes recorded with [`QRInfo`]
+    ///
+    /// [`QRInfo`]: ../qr/struct.QRInfo.html
+    pub codes_with_info: u64,
+    /// Median time of the recent scans, zero without any
+    pub median_time: Duration,
+    /// Time 90% of the recent scans took at most
+    pub p90_time: Duration,
+    /// Time 99% of the recent scans took at most
+    pub p99_time: Duration,
+    /// Longest time of the recent scans
+    pub max_time: Duration,
+}
+
+impl SessionSnapshot {
+    /// Fraction of the scans in which at least one code was decoded, 0.0 without any scans
+    pub fn scan_success_rate(&self) -> f64 {
+        ratio(self.scans_with_codes, self.scans)
+    }
+
+    /// Fraction of the detected codes that were decoded, 0.0 without any codes
+    pub fn code_success_rate(&self) -> f64 {
+        ratio(self.decoded, self.decoded + self.failed)
+    }
+
+    /// Average number of errors corrected per code recorded with `QRInfo`, `None` without any
+    pub fn average_errors_corrected(&self) -> Option<f64> {
+        match self.codes_with_info {
+            0 => None,
+            codes => Some(self.errors_corrected as f64 / codes as f64),
+        }
+    }
+}
+
+fn ratio(part: u64, whole: u64) -> f64 {
+    match whole {
+        0 => 0.0,
+        whole => part as f64 / whole as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::util::qr::ECLevel;
+
+    fn error(error: QRError) -> Result<String, QRError> {
+        Err(error)
+    }
+
+    fn info(errors: u32) -> Result<(String, QRInfo), QRError> {
+        Ok((
+            String::from("code"),
+            QRInfo {
+                version: 1,
+                ec_level: ECLevel::LOW,
+                mask: 0,
+                total_data: 208,
+                errors,
+                damaged_modules: vec![],
+                print_growth: 0.0,
+                raw_data: None,
+                physical_size: None,
+                structured_append: None,
+            },
+        ))
+    }
+
+    #[test]
+    fn test_scan_session() {
+        let session = ScanSession::new();
+        assert_eq!(session.snapshot().scan_success_rate(), 0.0);
+        assert_eq!(session.snapshot().average_errors_corrected(), None);
+
+        let ms = Duration::from_millis;
+        session.record(&[Ok(String::from("a")), error(QRError::format("Format information corrupted"))], ms(30));
+        let uncorrected = QRError::correction("Error correcting did not fix");
+        session.record(&[error(uncorrected), error(QRError::format("Invalid mask pattern 9"))], ms(10));
+        session.record::<String>(&[], ms(20));
+        session.record_with_info(&[info(3), info(0)], ms(40));
+
+        let snapshot = session.snapshot();
+        assert_eq!((snapshot.scans, snapshot.scans_with_codes), (4, 2));
+        assert_eq!((snapshot.decoded, snapshot.failed), (3, 3));
+        assert_eq!(snapshot.failures, vec![(ErrorCode::Format, 2), (ErrorCode::Correction, 1)]);
+        assert_eq!(snapshot.scan_success_rate(), 0.5);
+        assert_eq!(snapshot.code_success_rate(), 0.5);
+        assert_eq!(snapshot.average_errors_corrected(), Some(1.5));
+        assert_eq!((snapshot.median_time, snapshot.p90_time, snapshot.max_time), (ms(20), ms(40), ms(40)));
+
+        session.reset();
+        assert_eq!(session.snapshot().scans, 0);
+    }
+
+    #[test]
+    fn test_scan_session_window() {
+        let session = ScanSession::with_window(10);
+
+        for ms in 1..=100 {
+            session.record::<String>(&[], Duration::from_millis(ms));
+        }
+
+        // Only the last 10 scans count for the times
+        let snapshot = session.snapshot();
+        assert_eq!(snapshot.scans, 100);
+        assert_eq!(snapshot.median_time, Duration::from_millis(95));
+        assert_eq!(snapshot.p99_time, Duration::from_millis(100));
+
+        let session = ScanSession::with_window(0);
+        session.record::<String>(&[], Duration::from_millis(5));
+        assert_eq!(session.snapshot().max_time, Duration::default());
+    }
+}
@@ -0,0 +1,117 @@
+//! SIMD versions of the innermost loops
+//!
+//! On x86_64 the best available instruction set is detected at runtime, aarch64 always has NEON.
+//! Other targets use the scalar versions, which are also the reference for the tests.
+
+/// Number of leading bytes in `data` that are equal to `value`
+#[cfg(target_arch = "x86_64")]
+pub(crate) fn run_length(data: &[u8], value: u8) -> usize {
+    if is_x86_feature_detected!("avx2") {
+        // Safety: AVX2 support was checked above
+        unsafe { x86::run_length_avx2(data, value) }
+    } else {
+        // Safety: SSE2 is part of the x86_64 baseline
+        unsafe { x86::run_length_sse2(data, value) }
+    }
+}
+
+/// Number of leading bytes in `data` that are equal to `value`
+#[cfg(target_arch = "aarch64")]
+pub(crate) fn run_length(data: &[u8], value: u8) -> usize {
+    // Safety: NEON is part of the aarch64 baseline
+    unsafe { neon::run_length(data, value) }
+}
+
+/// Number of leading bytes in `data` that are equal to `value`
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub(crate) fn run_length(data: &[u8], value: u8) -> usize {
+    run_length_scalar(data, value)
+}
+
+fn run_length_scalar(data: &[u8], value: u8) -> usize {
+    data.iter().take_while(|d| **d == value).count()
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn run_length_sse2(data: &[u8], value: u8) -> usize {
+        let needle = _mm_set1_epi8(value as i8);
+
+        let mut i = 0;
+        while i + 16 <= data.len() {
+            let chunk = _mm_loadu_si128(data.as_ptr().add(i) as *const __m128i);
+            let equal = _mm_movemask_epi8(_mm_cmpeq_epi8(chunk, needle)) as u32;
+            if equal != 0xFFFF {
+                return i + (!equal).trailing_zeros() as usize;
+            }
+
+            i += 16;
+        }
+
+        i + super::run_length_scalar(&data[i..], value)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn run_length_avx2(data: &[u8], value: u8) -> usize {
+        let needle = _mm256_set1_epi8(value as i8);
+
+        let mut i = 0;
+        while i + 32 <= data.len() {
+            let chunk = _mm256_loadu_si256(data.as_ptr().add(i) as *const __m256i);
+            let equal = _mm256_movemask_epi8(_mm256_cmpeq_epi8(chunk, needle)) as u32;
+            if equal != u32::MAX {
+                return i + (!equal).trailing_zeros() as usize;
+            }
+
+            i += 32;
+        }
+
+        i + run_length_sse2(&data[i..], value)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn run_length(data: &[u8], value: u8) -> usize {
+        let needle = vdupq_n_u8(value);
+
+        let mut i = 0;
+        while i + 16 <= data.len() {
+            let equal = vceqq_u8(vld1q_u8(data.as_ptr().add(i)), needle);
+            if vminvq_u8(equal) != 0xFF {
+                break;
+            }
+
+            i += 16;
+        }
+
+        i + super::run_length_scalar(&data[i..], value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_length() {
+        let mut data = vec![255; 100];
+        assert_eq!(run_length(&data, 255), 100);
+        assert_eq!(run_length(&data, 0), 0);
+        assert_eq!(run_length(&[], 0), 0);
+
+        for switch in &[1, 15, 16, 17, 31, 32, 33, 64, 99] {
+            data[*switch] = 0;
+            assert_eq!(run_length(&data, 255), *switch);
+            assert_eq!(run_length(&data, 255), run_length_scalar(&data, 255));
+            data[*switch] = 255;
+        }
+    }
+
+}
@@ -0,0 +1,147 @@
+//! Images read a part at a time, for scans too large to keep in memory, see [`Decoder::decode_tiled`]
+//!
+//! A [`TileSource`] hands out rectangles of 8 bit grayscale pixels on request. Every [`LumaSource`] is one, including a
+//! [`LumaImage`] on top of a memory mapped file, where the operating system only loads the pages that are read.
+//! [`RawLumaFile`] reads the rectangles straight from an uncompressed grayscale file instead.
+//!
+//! [`Decoder::decode_tiled`]: ../../struct.Decoder.html#method.decode_tiled
+//! [`TileSource`]: trait.TileSource.html
+//! [`LumaSource`]: ../trait.LumaSource.html
+//! [`LumaImage`]: ../struct.LumaImage.html
+//! [`RawLumaFile`]: struct.RawLumaFile.html
+//!
+//! # Example
+//! ```
+//! # extern crate bardecoder;
+//! use bardecoder::util::tile::TileSource;
+//! use bardecoder::util::LumaImage;
+//!
+//! let img = LumaImage::from_fn(100, 100, |x, y| (x + y) as u8);
+//!
+//! let mut tile = vec![0; 4];
+//! img.read_tile(10, 20, 2, 2, &mut tile).unwrap();
+//! assert_eq!(tile, vec![30, 31, 31, 32]);
+//! ```
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::util::LumaSource;
+
+/// Grayscale image that is read a rectangle at a time
+pub trait TileSource {
+    /// Width and height of the whole image in pixels
+    fn tile_dimensions(&self) -> (u32, u32);
+
+    /// Read the `width` by `height` pixels at (`x`, `y`) into `tile`, row by row without padding
+    ///
+    /// The rectangle is always inside the image and `tile` is exactly large enough for it.
+    fn read_tile(&self, x: u32, y: u32, width: u32, height: u32, tile: &mut [u8]) -> io::Result<()>;
+}
+
+impl<T: LumaSource> TileSource for T {
+    fn tile_dimensions(&self) -> (u32, u32) {
+        self.luma_dimensions()
+    }
+
+    fn read_tile(&self, x: u32, y: u32, width: u32, height: u32, tile: &mut [u8]) -> io::Result<()> {
+        let (x, width) = (x as usize, width as usize);
+
+        for (row, out) in (y..y + height).zip(tile.chunks_mut(width)) {
+            out.copy_from_slice(&self.luma_row(row)[x..x + width]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Uncompressed 8 bit grayscale image in a file, one byte per pixel row by row, like a PGM without its header
+///
+/// Only the rows of the requested rectangles are read, so the file can be far larger than the available memory.
+#[derive(Debug)]
+pub struct RawLumaFile {
+    file: File,
+    width: u32,
+    height: u32,
+    offset: u64,
+}
+
+impl RawLumaFile {
+    /// Open a file of `width` by `height` pixels
+    ///
+    /// # Errors
+    ///
+    /// Returns `io::Error` if the file cannot be opened, or is too small for the provided dimensions
+    pub fn open<P: AsRef<Path>>(path: P, width: u32, height: u32) -> io::Result<RawLumaFile> {
+        RawLumaFile::with_offset(path, width, height, 0)
+    }
+
+    /// Open a file of `width` by `height` pixels that start after a header of `offset` bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns `io::Error` if the file cannot be opened, or is too small for the provided dimensions
+    pub fn with_offset<P: AsRef<Path>>(path: P, width: u32, height: u32, offset: u64) -> io::Result<RawLumaFile> {
+        let file = File::open(path)?;
+
+        let needed = offset + u64::from(width) * u64::from(height);
+        if file.metadata()?.len() < needed {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("File is too small for an image of {width}x{height} pixels"),
+            ));
+        }
+
+        Ok(RawLumaFile {
+            file,
+            width,
+            height,
+            offset,
+        })
+    }
+}
+
+impl TileSource for RawLumaFile {
+    fn tile_dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn read_tile(&self, x: u32, y: u32, width: u32, height: u32, tile: &mut [u8]) -> io::Result<()> {
+        let mut file = &self.file;
+
+        for (row, out) in (y..y + height).zip(tile.chunks_mut(width as usize)) {
+            let start = self.offset + u64::from(row) * u64::from(self.width) + u64::from(x);
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::LumaImage;
+
+    #[test]
+    fn test_raw_luma_file() {
+        let img = LumaImage::from_fn(30, 20, |x, y| (x * 7 + y * 3) as u8);
+        let path = std::env::temp_dir().join(format!("bardecoder_tile_{}.raw", std::process::id()));
+        let mut data = vec![1, 2, 3];
+        data.extend_from_slice(&img.clone().into_raw());
+        std::fs::write(&path, data).unwrap();
+
+        let file = RawLumaFile::with_offset(&path, 30, 20, 3).unwrap();
+        assert_eq!(file.tile_dimensions(), (30, 20));
+
+        let (mut expected, mut tile) = (vec![0; 12], vec![0; 12]);
+        img.read_tile(25, 16, 3, 4, &mut expected).unwrap();
+        file.read_tile(25, 16, 3, 4, &mut tile).unwrap();
+        assert_eq!(tile, expected);
+
+        assert!(RawLumaFile::open(&path, 30, 21).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
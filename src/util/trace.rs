@@ -0,0 +1,149 @@
+//! Structured trace of a decode, see [`Decoder::decode_with_trace`]
+//!
+//! The default components record what they consider and reject while a trace is being collected. Collecting is
+//! per thread, so decoding on other threads at the same time does not mix up the traces. Custom components can
+//! leave this alone; their stages are still timed, they just don't add any events.
+//!
+//! [`Decoder::decode_with_trace`]: ../../struct.Decoder.html#method.decode_with_trace
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::util::qr::{ECLevel, QRError};
+use crate::util::Point;
+
+/// Everything that happened while decoding a single image
+#[derive(Debug, Clone, Default)]
+pub struct DecodeTrace {
+    /// Time spent preparing the image
+    pub prepare_time: Duration,
+    /// Time spent detecting codes
+    pub detect_time: Duration,
+    /// Events recorded by the detector, in order
+    pub detect_events: Vec<TraceEvent>,
+    /// Every detected code, in the same order as the results
+    pub codes: Vec<CodeTrace>,
+}
+
+/// Everything that happened while extracting and decoding a single detected code
+#[derive(Debug, Clone)]
+pub struct CodeTrace {
+    /// Version estimated by the detector
+    pub version: u32,
+    /// Estimated corners of the code in the image, see [`QRLocation::corners`]
+    ///
+    /// [`QRLocation::corners`]: ../qr/struct.QRLocation.html#method.corners
+    pub corners: [Point; 4],
+    /// Time spent extracting the modules
+    pub extract_time: Duration,
+    /// Time spent decoding, including error correction
+    pub decode_time: Duration,
+    /// Events recorded by the extractor and the decoder, in order
+    pub events: Vec<TraceEvent>,
+    /// Why the code failed to decode, if it did
+    pub error: Option<QRError>,
+}
+
+/// Something a component considered, found or rejected
+#[derive(Debug, Clone)]
+pub enum TraceEvent {
+    /// A possible finder pattern that looked like one in every direction
+    FinderCandidate {
+        /// Center of the finder pattern, in pixels
+        location: Point,
+        /// Estimated module size, in pixels
+        module_size: f64,
+    },
+    /// A possible finder pattern that turned out not to look like one when checked in another direction
+    FinderRejected {
+        /// Center of the finder pattern, in pixels
+        location: Point,
+        /// Estimated module size, in pixels
+        module_size: f64,
+        /// The direction that did not match: `vertical`, `horizontal` or `diagonal`
+        direction: &'static str,
+    },
+    /// Three finder patterns of similar size that are not the corners of a QR Code
+    FindersRejected {
+        /// Centers of the finder patterns, in pixels
+        finders: [Point; 3],
+    },
+    /// Format information read from one of its two copies
+    Format {
+        /// Copy 1 is around the top left finder, copy 2 is split between the other two
+        copy: u8,
+        /// The 15 format bits after error correction, still masked
+        bits: u16,
+        /// Error correction level
+        ec_level: ECLevel,
+        /// Mask pattern, from 0 to 7
+        mask: u8,
+    },
+    /// A copy of the format information that was too damaged to read
+    FormatRejected {
+        /// Copy 1 is around the top left finder, copy 2 is split between the other two
+        copy: u8,
+    },
+    /// Layout of the codewords, for every block the total number of codewords and the number of data codewords
+    Blocks {
+        /// Total and data codewords, per block
+        sizes: Vec<(u8, u8)>,
+    },
+    /// Reed-Solomon error correction of a block, recorded for every block in order
+    Correction {
+        /// Number of corrected codewords, 0 if the block had no errors
+        codewords: usize,
+        /// Number of corrected bits
+        bits: u32,
+    },
+}
+
+thread_local! {
+    static EVENTS: RefCell<Option<Vec<TraceEvent>>> = const { RefCell::new(None) };
+}
+
+/// Record an event if a trace is being collected on this thread, only then is the event created
+pub(crate) fn record(event: impl FnOnce() -> TraceEvent) {
+    EVENTS.with(|events| {
+        if let Some(events) = events.borrow_mut().as_mut() {
+            events.push(event());
+        }
+    });
+}
+
+/// Run the function while collecting events
+pub(crate) fn collect<T>(f: impl FnOnce() -> T) -> (T, Vec<TraceEvent>) {
+    let outer = EVENTS.with(|events| events.replace(Some(vec![])));
+    let result = f();
+    let events = EVENTS.with(|events| events.replace(outer));
+
+    (result, events.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect() {
+        record(|| panic!("Nothing is collected"));
+
+        let ((), outer) = collect(|| {
+            record(|| TraceEvent::FormatRejected { copy: 1 });
+
+            let ((), inner) = collect(|| record(|| TraceEvent::FormatRejected { copy: 2 }));
+            assert_eq!(1, inner.len());
+
+            record(|| TraceEvent::FormatRejected { copy: 3 });
+        });
+
+        let copies: Vec<u8> = outer
+            .iter()
+            .map(|e| match e {
+                TraceEvent::FormatRejected { copy } => *copy,
+                other => panic!("Unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(vec![1, 3], copies);
+    }
+}
@@ -0,0 +1,103 @@
+use super::{VideoDecoder, VideoError, VideoResult};
+
+use std::path::Path;
+use std::time::Duration;
+
+use ffmpeg_next as ffmpeg;
+use ffmpeg::format::Pixel;
+use ffmpeg::media::Type;
+use ffmpeg::software::scaling::{Context as Scaler, Flags};
+use ffmpeg::util::frame::Video;
+use ffmpeg::Rational;
+
+use image::{DynamicImage, GrayImage};
+
+/// Decode all frames of a video file with FFmpeg, returning the deduplicated codes in order of appearance
+///
+/// Only the best video stream of the file is decoded. Frames are converted to grayscale by FFmpeg, so no color
+/// conversion is needed afterwards.
+///
+/// # Example
+/// ```ignore
+/// use bardecoder::video::{decode_file, VideoDecoder};
+///
+/// let mut decoder = VideoDecoder::new();
+/// for result in decode_file("recording.mp4", &mut decoder).unwrap() {
+///     println!("{:?} {}", result.timestamp, result.data);
+/// }
+/// ```
+pub fn decode_file<P: AsRef<Path>>(path: P, decoder: &mut VideoDecoder) -> Result<Vec<VideoResult>, VideoError> {
+    ffmpeg::init()?;
+
+    let mut input = ffmpeg::format::input(&path)?;
+    let stream = input.streams().best(Type::Video).ok_or(ffmpeg::Error::StreamNotFound)?;
+    let stream_index = stream.index();
+    let time_base = stream.time_base();
+
+    let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+    let mut video = context.decoder().video()?;
+
+    let mut scaler = Scaler::get(
+        video.format(),
+        video.width(),
+        video.height(),
+        Pixel::GRAY8,
+        video.width(),
+        video.height(),
+        Flags::BILINEAR,
+    )?;
+
+    let mut results = vec![];
+
+    for (stream, packet) in input.packets() {
+        if stream.index() == stream_index {
+            video.send_packet(&packet)?;
+            receive_frames(&mut video, &mut scaler, time_base, decoder, &mut results)?;
+        }
+    }
+
+    video.send_eof()?;
+    receive_frames(&mut video, &mut scaler, time_base, decoder, &mut results)?;
+
+    Ok(results)
+}
+
+fn receive_frames(
+    video: &mut ffmpeg::decoder::Video,
+    scaler: &mut Scaler,
+    time_base: Rational,
+    decoder: &mut VideoDecoder,
+    results: &mut Vec<VideoResult>,
+) -> Result<(), VideoError> {
+    let mut decoded = Video::empty();
+
+    while video.receive_frame(&mut decoded).is_ok() {
+        let mut gray = Video::empty();
+        scaler.run(&decoded, &mut gray)?;
+
+        let timestamp = decoded.timestamp().map_or(Duration::from_secs(0), |ts| to_duration(ts, time_base));
+        results.extend(decoder.push(timestamp, &DynamicImage::ImageLuma8(to_image(&gray))));
+    }
+
+    Ok(())
+}
+
+// FFmpeg rows may be padded, so copy row by row using the stride
+fn to_image(frame: &Video) -> GrayImage {
+    let (width, height) = (frame.width(), frame.height());
+    let stride = frame.stride(0);
+    let data = frame.data(0);
+
+    GrayImage::from_fn(width, height, |x, y| image::Luma([data[y as usize * stride + x as usize]]))
+}
+
+fn to_duration(timestamp: i64, time_base: Rational) -> Duration {
+    let seconds = timestamp.max(0) as f64 * f64::from(time_base.numerator()) / f64::from(time_base.denominator());
+    Duration::from_secs_f64(seconds)
+}
+
+impl From<ffmpeg::Error> for VideoError {
+    fn from(error: ffmpeg::Error) -> Self {
+        VideoError::Video(error.to_string())
+    }
+}
@@ -0,0 +1,400 @@
+//! Decode QR codes in recorded video
+//!
+//! [`VideoDecoder`] decodes a sequence of timestamped frames and reports every code once, in the frame it first
+//! appears in, instead of once for every frame it is visible in. Frames can come from any source; with the `ffmpeg`
+//! feature enabled, [`decode_file`] reads them straight from a video file.
+//!
+//! # Example
+//! ```
+//! # extern crate bardecoder;
+//! # extern crate image;
+//! use bardecoder::video::VideoDecoder;
+//! use std::time::Duration;
+//!
+//! let frame = image::open("tests/images/version1_example.jpg").unwrap();
+//! let frames = (0..3).map(|i| (Duration::from_millis(40 * i), frame.clone()));
+//!
+//! let results = VideoDecoder::new().decode_frames(frames);
+//! assert_eq!(results.len(), 1);
+//! assert_eq!(results[0].data, "01234567");
+//! ```
+//!
+//! [`VideoDecoder`]: struct.VideoDecoder.html
+//! [`decode_file`]: fn.decode_file.html
+
+#[cfg(feature = "ffmpeg")]
+mod ffmpeg;
+
+#[cfg(feature = "ffmpeg")]
+pub use self::ffmpeg::decode_file;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use image::{DynamicImage, GenericImageView, GrayImage, Pixel};
+
+use crate::decoder::{default_decoder_with_info, Decoder};
+use crate::util::qr::{QRError, QRInfo, QRLocation};
+
+/// Error type for reading video
+#[derive(Debug, thiserror::Error)]
+pub enum VideoError {
+    /// The video could not be opened or read
+    #[error("Video error: {0}")]
+    Video(String),
+}
+
+/// A code found in a video
+#[derive(Debug, PartialEq)]
+pub struct VideoResult {
+    /// The decoded content
+    pub data: String,
+    /// Information about the decoded code
+    pub info: QRInfo,
+    /// Index of the frame the code was found in, counting from 0
+    pub frame: usize,
+    /// Timestamp of the frame the code was found in
+    pub timestamp: Duration,
+}
+
+/// Samples along each side of a frame compared by [`VideoDecoder::skip_unchanged`]
+const DIFFERENCE_SAMPLES: u32 = 64;
+
+/// Decode a sequence of frames, deduplicating codes that are visible in more than one frame
+///
+/// By default every distinct content is reported only once. Use [`repeat_after`] to report a code again when it
+/// reappears after being out of view for a while.
+///
+/// [`repeat_after`]: #method.repeat_after
+pub struct VideoDecoder {
+    decoder: Decoder<DynamicImage, GrayImage, (String, QRInfo)>,
+    repeat_after: Option<Duration>,
+    max_difference: Option<f64>,
+    detect_every: Option<usize>,
+    last_seen: HashMap<String, Duration>,
+    // The last frame that was decoded, as samples, with the codes decoded in it
+    reference: Option<(Samples, Vec<(String, QRInfo)>)>,
+    // The locations of the codes decoded in the last decoded frame, with the frames decoded since the last detection
+    tracked: Vec<QRLocation>,
+    since_detect: usize,
+    frames: usize,
+    skipped: usize,
+}
+
+impl VideoDecoder {
+    /// Create a video decoder using the default decoder
+    pub fn new() -> VideoDecoder {
+        VideoDecoder::with_decoder(default_decoder_with_info())
+    }
+
+    /// Create a video decoder using a custom decoder
+    pub fn with_decoder(decoder: Decoder<DynamicImage, GrayImage, (String, QRInfo)>) -> VideoDecoder {
+        VideoDecoder {
+            decoder,
+            repeat_after: None,
+            max_difference: None,
+            detect_every: None,
+            last_seen: HashMap::new(),
+            reference: None,
+            tracked: vec![],
+            since_detect: 0,
+            frames: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Report a code again when it has not been seen for at least `gap`
+    pub fn repeat_after(&mut self, gap: Duration) -> &mut VideoDecoder {
+        self.repeat_after = Some(gap);
+        self
+    }
+
+    /// Skip decoding frames that hardly differ from the last frame decoded, reusing the codes found in it
+    ///
+    /// Static scenes, from a camera on a tripod or a paused stream, need not be decoded again and again. A grid of
+    /// 64 by 64 pixels of every frame is compared with the same pixels of the last frame that was decoded. When they
+    /// differ by at most `max_difference` on average, in levels of gray from 0 to 255, the frame is taken to show the
+    /// same codes, without running detection at all. Comparing with the last decoded frame rather than the previous
+    /// one means a slow change, such as a pan, still gets decoded once it adds up. Sensor noise is usually well below
+    /// 2.0 on average, a code moving into view well above it.
+    pub fn skip_unchanged(&mut self, max_difference: f64) -> &mut VideoDecoder {
+        self.max_difference = Some(max_difference);
+        self
+    }
+
+    /// Decode the codes of the last decoded frame at their locations in it first, without running detection
+    ///
+    /// Codes in a video move little from one frame to the next, so they mostly still decode at the locations they had
+    /// in the last frame, the way [`Decoder::decode_at`] decodes a code. Only when one of them does not is the frame
+    /// detected as a whole. A code that comes into view while the others still decode is found at the latest in the
+    /// next full detection, which runs at least every `detect_every` frames. A value of 0 is treated as 1.
+    ///
+    /// [`Decoder::decode_at`]: ../struct.Decoder.html#method.decode_at
+    pub fn track(&mut self, detect_every: usize) -> &mut VideoDecoder {
+        self.detect_every = Some(detect_every.max(1));
+        self
+    }
+
+    /// Decode the next frame, returning the codes that were not reported before
+    ///
+    /// Frames are expected in presentation order
+    pub fn push(&mut self, timestamp: Duration, frame: &DynamicImage) -> Vec<VideoResult> {
+        let index = self.frames;
+        self.frames += 1;
+
+        let samples = self.max_difference.map(|_| Samples::of(frame));
+        let unchanged = match (&self.reference, &samples, self.max_difference) {
+            (Some((reference, _)), Some(samples), Some(max_difference)) => {
+                reference.difference(samples).is_some_and(|difference| difference <= max_difference)
+            }
+            _ => false,
+        };
+
+        let decoded = match (unchanged, &self.reference) {
+            (true, Some((_, decoded))) => {
+                self.skipped += 1;
+                decoded.clone()
+            }
+            _ => {
+                let decoded = self.decode(index, frame);
+
+                if let Some(samples) = samples {
+                    self.reference = Some((samples, decoded.clone()));
+                }
+                decoded
+            }
+        };
+
+        let mut results = vec![];
+
+        for (data, info) in decoded {
+            let report = match self.last_seen.get(&data) {
+                None => true,
+                Some(last) => self
+                    .repeat_after
+                    .is_some_and(|gap| timestamp.saturating_sub(*last) >= gap),
+            };
+
+            self.last_seen.insert(data.clone(), timestamp);
+
+            if report {
+                results.push(VideoResult {
+                    data,
+                    info,
+                    frame: index,
+                    timestamp,
+                });
+            }
+        }
+
+        results
+    }
+
+    // Decode a frame in full, or with tracking at the locations of the codes of the last decoded frame
+    fn decode(&mut self, index: usize, frame: &DynamicImage) -> Vec<(String, QRInfo)> {
+        let Some(detect_every) = self.detect_every else {
+            return self.decoder.decode(frame).into_iter().filter_map(|result| logged(index, result)).collect();
+        };
+
+        // Detecting now and then finds the codes that came into view while the tracked ones still decode
+        self.since_detect += 1;
+        if self.since_detect >= detect_every {
+            self.tracked.clear();
+        }
+
+        let (decoded, detected) = self.decoder.decode_tracked(frame, &self.tracked);
+        if detected {
+            self.since_detect = 0;
+        }
+
+        let (tracked, decoded) = decoded.into_iter().filter_map(|result| logged(index, result)).unzip();
+        self.tracked = tracked;
+        decoded
+    }
+
+    /// Number of frames skipped by [`skip_unchanged`](#method.skip_unchanged) since the start or the last reset
+    pub fn skipped_frames(&self) -> usize {
+        self.skipped
+    }
+
+    /// Decode all provided frames, returning the deduplicated codes in order of appearance
+    pub fn decode_frames<I>(&mut self, frames: I) -> Vec<VideoResult>
+    where
+        I: IntoIterator<Item = (Duration, DynamicImage)>,
+    {
+        frames
+            .into_iter()
+            .flat_map(|(timestamp, frame)| self.push(timestamp, &frame))
+            .collect()
+    }
+
+    /// Forget all codes seen so far and restart frame counting, to decode another video
+    pub fn reset(&mut self) {
+        self.last_seen.clear();
+        self.reference = None;
+        self.tracked.clear();
+        self.since_detect = 0;
+        self.frames = 0;
+        self.skipped = 0;
+    }
+}
+
+// The decoded code, with the error logged instead
+fn logged<T>(index: usize, result: Result<T, QRError>) -> Option<T> {
+    result.map_err(|e| debug!("Frame {}: {}", index, e)).ok()
+}
+
+// Pixels of a frame on an evenly spaced grid, for comparing frames without looking at every pixel
+struct Samples {
+    dimensions: (u32, u32),
+    luma: Vec<u8>,
+}
+
+impl Samples {
+    fn of(frame: &DynamicImage) -> Samples {
+        let (width, height) = frame.dimensions();
+        let at = |i: u32, length: u32| (u64::from(i) * u64::from(length) / u64::from(DIFFERENCE_SAMPLES)) as u32;
+
+        let mut luma = vec![];
+        if width > 0 && height > 0 {
+            for y in 0..DIFFERENCE_SAMPLES {
+                for x in 0..DIFFERENCE_SAMPLES {
+                    luma.push(frame.get_pixel(at(x, width), at(y, height)).to_luma()[0]);
+                }
+            }
+        }
+
+        Samples {
+            dimensions: (width, height),
+            luma,
+        }
+    }
+
+    // Mean absolute difference of the samples, `None` for frames of another size
+    fn difference(&self, other: &Samples) -> Option<f64> {
+        if self.dimensions != other.dimensions {
+            return None;
+        }
+        if self.luma.is_empty() {
+            return Some(0.0);
+        }
+
+        let total: u64 = self.luma.iter().zip(&other.luma).map(|(a, b)| u64::from(a.abs_diff(*b))).sum();
+        Some(total as f64 / self.luma.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames() -> Vec<(Duration, DynamicImage)> {
+        let version1 = image::open("tests/images/version1_example.jpg").unwrap();
+        let version3 = image::open("tests/images/version3_example.jpg").unwrap();
+        let blank = DynamicImage::new_luma8(100, 100);
+
+        vec![
+            (Duration::from_millis(0), version1.clone()),
+            (Duration::from_millis(40), version1.clone()),
+            (Duration::from_millis(80), blank),
+            (Duration::from_millis(120), version3),
+            (Duration::from_millis(2000), version1),
+        ]
+    }
+
+    fn found(results: &[VideoResult]) -> Vec<(usize, &str)> {
+        results.iter().map(|r| (r.frame, r.data.as_str())).collect()
+    }
+
+    #[test]
+    fn test_deduplicates() {
+        let results = VideoDecoder::new().decode_frames(frames());
+
+        assert_eq!(
+            found(&results),
+            vec![(0, "01234567"), (3, "https://payapp.weixin.qq.com/olspree?code_type=2")]
+        );
+        assert_eq!(results[1].timestamp, Duration::from_millis(120));
+    }
+
+    #[test]
+    fn test_repeat_after() {
+        let mut decoder = VideoDecoder::new();
+        decoder.repeat_after(Duration::from_secs(1));
+        let results = decoder.decode_frames(frames());
+
+        assert_eq!(
+            found(&results),
+            vec![
+                (0, "01234567"),
+                (3, "https://payapp.weixin.qq.com/olspree?code_type=2"),
+                (4, "01234567")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skip_unchanged() {
+        let mut decoder = VideoDecoder::new();
+        decoder.skip_unchanged(2.0).repeat_after(Duration::from_secs(1));
+
+        let mut frames = frames();
+        // The same frame with a little noise, and the last frame once more
+        let mut noisy = frames[0].1.to_luma8();
+        noisy.put_pixel(10, 10, image::Luma([128]));
+        frames[1].1 = DynamicImage::ImageLuma8(noisy);
+        frames.push((Duration::from_millis(2500), frames[4].1.clone()));
+
+        let results = decoder.decode_frames(frames);
+
+        assert_eq!(
+            found(&results),
+            vec![
+                (0, "01234567"),
+                (3, "https://payapp.weixin.qq.com/olspree?code_type=2"),
+                (4, "01234567")
+            ]
+        );
+        assert_eq!(decoder.skipped_frames(), 2);
+
+        // A code still in view is reported again after the gap, from the results of the frame it was decoded in
+        let version1 = image::open("tests/images/version1_example.jpg").unwrap();
+        let skipped = decoder.push(Duration::from_millis(4500), &version1);
+        assert_eq!((found(&skipped), decoder.skipped_frames()), (vec![(6, "01234567")], 3));
+    }
+
+    #[test]
+    fn test_track() {
+        use crate::util::metrics;
+
+        let mut decoder = VideoDecoder::new();
+        decoder.track(3);
+        let results = decoder.decode_frames(frames());
+
+        assert_eq!(found(&results), found(&VideoDecoder::new().decode_frames(frames())));
+
+        // The code of the last frame decodes where it was, until the next full detection
+        let version1 = image::open("tests/images/version1_example.jpg").unwrap();
+        let detected: Vec<u64> = (0..3)
+            .map(|i| metrics::collect(|| decoder.push(Duration::from_millis(2040 + 40 * i), &version1)).1)
+            .map(|metrics| metrics.finder_candidates)
+            .collect();
+
+        assert_eq!(detected[0], 0);
+        assert_eq!(detected[1], 0);
+        assert!(detected[2] > 0);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut decoder = VideoDecoder::new();
+        let frame = image::open("tests/images/version1_example.jpg").unwrap();
+
+        assert_eq!(decoder.push(Duration::from_millis(0), &frame).len(), 1);
+        assert_eq!(decoder.push(Duration::from_millis(40), &frame).len(), 0);
+
+        decoder.reset();
+        let results = decoder.push(Duration::from_millis(0), &frame);
+        assert_eq!(found(&results), vec![(0, "01234567")]);
+    }
+}
@@ -1,6 +1,4 @@
-use bardecoder;
-use bardecoder::util::qr::{ECLevel, QRInfo};
-use image;
+use bardecoder::util::qr::ECLevel;
 
 #[test]
 fn test_decode_version1_with_info() {
@@ -15,12 +15,7 @@ pub fn test_version1_example_with_info() {
         "tests/images/version1_example.jpg",
         vec![Ok((
             String::from("01234567"),
-            QRInfo {
-                version: 1,
-                ec_level: ECLevel::MEDIUM,
-                total_data: 128,
-                errors: 0,
-            },
+            info(1, ECLevel::MEDIUM, 3, 128, 0, vec![], -0.064),
         ))],
     );
 }
@@ -99,12 +94,7 @@ pub fn test_needs_alignment_with_info() {
         "tests/images/needs_alignment.jpg",
         vec![Ok((
             String::from("http://cblink.je/app-install-display-nl"),
-            QRInfo {
-                version: 3,
-                ec_level: ECLevel::LOW,
-                total_data: 440,
-                errors: 3,
-            },
+            info(3, ECLevel::LOW, 2, 440, 3, vec![(2, 20), (3, 20), (22, 28)], -0.055),
         ))],
     );
 }
@@ -114,34 +104,44 @@ pub fn test_multiple_codes() {
     test_image(
         "tests/images/multiple_codes.png",
         vec![
-            Ok(String::from("http://www.prolinepetfood.com/1/")),
             Ok(String::from("Ver1")),
+            Ok(String::from("http://www.prolinepetfood.com/1/")),
         ],
     );
 }
 
+#[test]
+pub fn test_multiple_codes_on_every_thread() {
+    use std::sync::Arc;
+
+    let img = Arc::new(image::open("tests/images/multiple_codes.png").expect("Failed to open test image"));
+    let decoder = Arc::new(bardecoder::default_decoder_with_info());
+    let expected = decoder.decode(&img);
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let (decoder, img) = (Arc::clone(&decoder), Arc::clone(&img));
+            std::thread::spawn(move || decoder.decode(&img))
+        })
+        .collect();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), expected);
+    }
+}
+
 #[test]
 pub fn test_multiple_codes_with_info() {
     test_image_with_info(
         "tests/images/multiple_codes.png",
         vec![
             Ok((
-                String::from("http://www.prolinepetfood.com/1/"),
-                QRInfo {
-                    version: 3,
-                    ec_level: ECLevel::MEDIUM,
-                    total_data: 352,
-                    errors: 0,
-                },
+                String::from("Ver1"),
+                QRInfo::new(1, ECLevel::HIGH, 1, 72, 0),
             )),
             Ok((
-                String::from("Ver1"),
-                QRInfo {
-                    version: 1,
-                    ec_level: ECLevel::HIGH,
-                    total_data: 72,
-                    errors: 0,
-                },
+                String::from("http://www.prolinepetfood.com/1/"),
+                info(3, ECLevel::MEDIUM, 7, 352, 0, vec![], -0.167),
             )),
         ],
     );
@@ -189,8 +189,55 @@ pub fn test_wikipedia_examples() {
     );
 }
 
+#[test]
+pub fn test_decode_bytes() {
+    let bytes = std::fs::read("tests/images/version1_example.jpg").expect("Failed to read test image");
+
+    let decoder = bardecoder::default_decoder();
+    let result = decoder.decode_bytes(&bytes).expect("Test image should load from bytes");
+
+    assert_eq!(vec![Ok(String::from("01234567"))], result);
+}
+
+#[test]
+pub fn test_rgb_image_buffer() {
+    use bardecoder::decode::QRDecoder;
+    use bardecoder::detect::LineScan;
+    use bardecoder::extract::QRExtractor;
+    use bardecoder::prepare::BlockedMean;
+    use bardecoder::DecoderBuilder;
+    use image::{GrayImage, RgbImage};
+
+    let img = image::open("tests/images/version1_example.jpg")
+        .expect("Failed to open test image")
+        .to_rgb8();
+
+    let mut db: DecoderBuilder<RgbImage, GrayImage, String> = DecoderBuilder::new();
+    db.prepare(Box::new(BlockedMean::new(5, 7)));
+    db.detect(Box::new(LineScan::new()));
+    db.qr(Box::new(QRExtractor::new()), Box::new(QRDecoder::new()));
+    let decoder = db.build().expect("Decoder should build");
+
+    assert_eq!(vec![Ok(String::from("01234567"))], decoder.decode(&img));
+}
+
+#[test]
+pub fn test_luma_decoder() {
+    use bardecoder::util::LumaImage;
+
+    let img = image::open("tests/images/version1_example.jpg")
+        .expect("Failed to open test image")
+        .to_luma8();
+    let (width, height) = img.dimensions();
+    let luma = LumaImage::new(width, height, img.as_raw().as_slice()).expect("Buffer size should match");
+
+    let decoder = bardecoder::default_luma_decoder();
+
+    assert_eq!(vec![Ok(String::from("01234567"))], decoder.decode(&luma));
+}
+
 pub fn test_image(file: &str, expected: Vec<Result<String, QRError>>) {
-    let img = image::open(file).expect(&format!("Failed to open test image: {}", file));
+    let img = image::open(file).unwrap_or_else(|_| panic!("Failed to open test image: {}", file));
 
     let decoder = bardecoder::default_decoder();
     let result = decoder.decode(&img);
@@ -204,8 +251,24 @@ pub fn test_image(file: &str, expected: Vec<Result<String, QRError>>) {
     }
 }
 
+// The info expected of a test image, which has no raw data, physical size or structured append
+fn info(
+    version: u32,
+    ec_level: ECLevel,
+    mask: u8,
+    total_data: u32,
+    errors: u32,
+    damaged_modules: Vec<(u32, u32)>,
+    print_growth: f64,
+) -> QRInfo {
+    let mut info = QRInfo::new(version, ec_level, mask, total_data, errors);
+    info.damaged_modules = damaged_modules;
+    info.print_growth = print_growth;
+    info
+}
+
 pub fn test_image_with_info(file: &str, expected: Vec<Result<(String, QRInfo), QRError>>) {
-    let img = image::open(file).expect(&format!("Failed to open test image: {}", file));
+    let img = image::open(file).unwrap_or_else(|_| panic!("Failed to open test image: {}", file));
 
     let decoder = bardecoder::default_decoder_with_info();
     let result = decoder.decode(&img);
@@ -215,6 +278,1003 @@ pub fn test_image_with_info(file: &str, expected: Vec<Result<(String, QRInfo), Q
     for (expected, result) in expected.into_iter().zip(result) {
         assert!(expected.is_ok());
         assert!(result.is_ok());
-        assert_eq!(expected.expect("Expected result should be Ok"), result.expect("Decoded result should be Ok"));
+
+        let expected = expected.expect("Expected result should be Ok");
+        let mut result = result.expect("Decoded result should be Ok");
+
+        // The growth is estimated from pixel runs, so only compare it roughly
+        assert!((expected.1.print_growth - result.1.print_growth).abs() < 0.01);
+        result.1.print_growth = expected.1.print_growth;
+
+        assert_eq!(expected, result);
+    }
+}
+
+// A code in a square image of `size` pixels, anti-aliased from `samples` x `samples` per pixel. `module` maps the
+// position of a sample, in pixels, to where it falls on the code, in modules; outside of the code is light
+#[cfg(feature = "encode")]
+pub fn render(
+    code: &bardecoder::encode::QRCode,
+    size: u32,
+    samples: u32,
+    module: impl Fn(f64, f64) -> (f64, f64),
+) -> bardecoder::util::LumaImage {
+    let side = f64::from(code.side);
+    let count = samples * samples;
+
+    bardecoder::util::LumaImage::from_fn(size, size, |x, y| {
+        let mut dark = 0;
+        for sample in 0..count {
+            let (mx, my) = module(
+                f64::from(x) + (f64::from(sample % samples) + 0.5) / f64::from(samples),
+                f64::from(y) + (f64::from(sample / samples) + 0.5) / f64::from(samples),
+            );
+            if mx >= 0.0 && my >= 0.0 && mx < side && my < side && code.is_dark(mx as u32, my as u32) {
+                dark += 1;
+            }
+        }
+        (255 - dark * 255 / count) as u8
+    })
+}
+
+#[test]
+pub fn test_decode_animation_bytes() {
+    use image::codecs::gif::GifEncoder;
+    use image::{imageops, Frame, GenericImageView, Rgba, RgbaImage};
+
+    let version1 = image::open("tests/images/version1_example.jpg").expect("Failed to open test image");
+    let version3 = image::open("tests/images/version3_example.jpg").expect("Failed to open test image");
+
+    let width = version1.width().max(version3.width());
+    let height = version1.height().max(version3.height());
+    let frame = |img: Option<&image::DynamicImage>| {
+        let mut canvas = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+        if let Some(img) = img {
+            imageops::overlay(&mut canvas, &img.to_rgba8(), 0, 0);
+        }
+        Frame::new(canvas)
+    };
+
+    let mut bytes = vec![];
+    GifEncoder::new_with_speed(&mut bytes, 30)
+        .encode_frames(vec![
+            frame(None),
+            frame(Some(&version1)),
+            frame(Some(&version1)),
+            frame(Some(&version3)),
+        ])
+        .expect("Failed to encode animation");
+
+    let decoder = bardecoder::default_decoder();
+
+    let all = decoder.decode_animation_bytes(&bytes, 1).expect("Animation should load from bytes");
+    let found: Vec<(usize, &str)> = all.iter().map(|f| (f.frame, f.result.as_str())).collect();
+    assert_eq!(
+        vec![(1, "01234567"), (3, "https://payapp.weixin.qq.com/olspree?code_type=2")],
+        found
+    );
+
+    let every_other = decoder.decode_animation_bytes(&bytes, 2).expect("Animation should load from bytes");
+    assert_eq!(2, every_other[0].frame);
+    assert_eq!(1, every_other.len());
+
+    let single = std::fs::read("tests/images/version1_example.jpg").expect("Failed to read test image");
+    let single = decoder.decode_animation_bytes(&single, 1).expect("Test image should load from bytes");
+    assert_eq!(vec![(0, "01234567")], single.iter().map(|f| (f.frame, f.result.as_str())).collect::<Vec<_>>());
+}
+
+#[cfg(feature = "tiff")]
+#[test]
+pub fn test_decode_tiff_pages() {
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    let version1 = image::open("tests/images/version1_example.jpg").expect("Failed to open test image").to_luma8();
+    let version3 = image::open("tests/images/version3_example.jpg").expect("Failed to open test image").to_rgb8();
+
+    let mut bytes = std::io::Cursor::new(vec![]);
+    let mut tiff = TiffEncoder::new(&mut bytes).expect("Failed to encode TIFF");
+    tiff.write_image::<colortype::Gray8>(version1.width(), version1.height(), &version1)
+        .expect("Failed to encode TIFF page");
+    tiff.write_image::<colortype::Gray8>(40, 40, &[255; 1600]).expect("Failed to encode TIFF page");
+    tiff.write_image::<colortype::RGB8>(version3.width(), version3.height(), &version3)
+        .expect("Failed to encode TIFF page");
+
+    let pages = bardecoder::default_decoder()
+        .decode_tiff_pages(bytes.get_ref())
+        .expect("TIFF should load from bytes");
+
+    let found: Vec<(usize, Vec<String>)> = pages
+        .into_iter()
+        .map(|p| (p.page, p.results.into_iter().map(|r| r.expect("Code should decode")).collect()))
+        .collect();
+    assert_eq!(
+        vec![
+            (0, vec![String::from("01234567")]),
+            (1, vec![]),
+            (2, vec![String::from("https://payapp.weixin.qq.com/olspree?code_type=2")])
+        ],
+        found
+    );
+}
+
+#[cfg(feature = "exif")]
+#[test]
+pub fn test_decode_bytes_applies_exif_orientation() {
+    use bardecoder::prepare::Orientation;
+    use image::ImageOutputFormat;
+
+    // A mirrored photo, with the EXIF orientation saying so
+    let mirrored = image::open("tests/images/version1_example.jpg")
+        .expect("Failed to open test image")
+        .fliph();
+    let mut jpeg = vec![];
+    mirrored
+        .write_to(&mut jpeg, ImageOutputFormat::Jpeg(90))
+        .expect("Test image should encode");
+
+    // APP1 segment with a big endian TIFF header and a single IFD entry: Orientation (0x0112), SHORT, 1 value of 2
+    let app1: &[u8] = &[
+        0xFF, 0xE1, 0x00, 0x22, b'E', b'x', b'i', b'f', 0, 0, b'M', b'M', 0x00, 0x2A, 0x00, 0x00, 0x00, 0x08, 0x00,
+        0x01, 0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let bytes: Vec<u8> = jpeg[..2].iter().chain(app1).chain(&jpeg[2..]).copied().collect();
+
+    assert_eq!(Some(Orientation::FlipHorizontal), Orientation::from_bytes(&bytes));
+
+    let decoder = bardecoder::default_decoder();
+    let result = decoder.decode_bytes(&bytes).expect("Test image should load from bytes");
+
+    assert_eq!(vec![Ok(String::from("01234567"))], result);
+}
+
+#[cfg(feature = "debug-images")]
+#[test]
+pub fn test_debug_image() {
+    use image::{GenericImageView, Rgb};
+
+    let count = |img: &image::RgbImage, color: Rgb<u8>| img.pixels().filter(|p| **p == color).count();
+    let decoder = bardecoder::default_decoder();
+
+    // The three errors in this image are marked as corrected
+    let source = image::open("tests/images/needs_alignment.jpg").expect("Failed to open test image");
+    let annotated = decoder.debug_image(&source);
+
+    assert_eq!(source.width(), annotated.width());
+    assert_eq!(source.height(), annotated.height());
+    assert!(count(&annotated, Rgb([0, 200, 0])) > 0, "Outline of the decoded code");
+    assert!(count(&annotated, Rgb([255, 0, 255])) > 0, "Corrected modules");
+
+    let source = image::open("tests/images/version1_example.jpg").expect("Failed to open test image");
+    let annotated = decoder.debug_image(&source);
+
+    assert!(count(&annotated, Rgb([255, 200, 0])) > 0, "Finder patterns");
+    assert_eq!(0, count(&annotated, Rgb([255, 0, 255])), "Nothing to correct");
+}
+
+#[test]
+pub fn test_prepare_only() {
+    use bardecoder::detect::{Detect, LineScan};
+    use image::GenericImageView;
+
+    let img = image::open("tests/images/version1_example.jpg").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+
+    let prepared = decoder.prepare_only(&img);
+
+    assert_eq!(img.dimensions(), prepared.dimensions());
+    assert!(prepared.pixels().all(|p| p[0] == 0 || p[0] == 255));
+    assert_eq!(1, LineScan::new().detect(&prepared).len());
+}
+
+#[test]
+pub fn test_decode_prepared_with_several_decoders() {
+    let img = image::open("tests/images/version3_example.jpg").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+    let with_info = bardecoder::default_decoder_with_info();
+
+    let prepared = decoder.prepare_only(&img);
+
+    assert_eq!(decoder.decode(&img), decoder.decode_prepared(&prepared));
+    let decoded: Vec<String> = with_info
+        .decode_prepared(&prepared)
+        .into_iter()
+        .map(|result| result.expect("Code should decode").0)
+        .collect();
+    assert_eq!(vec![String::from("https://payapp.weixin.qq.com/olspree?code_type=2")], decoded);
+}
+
+#[test]
+pub fn test_decode_with_trace() {
+    use bardecoder::util::trace::TraceEvent;
+
+    let img = image::open("tests/images/needs_alignment.jpg").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+
+    let (results, trace) = decoder.decode_with_trace(&img);
+
+    assert_eq!(1, results.len());
+    assert!(results[0].is_ok());
+    assert_eq!(1, trace.codes.len());
+    assert!(trace.codes[0].error.is_none());
+    assert!(trace
+        .detect_events
+        .iter()
+        .any(|e| matches!(e, TraceEvent::FinderCandidate { .. })));
+
+    let events = &trace.codes[0].events;
+    assert!(events.iter().any(|e| matches!(e, TraceEvent::Format { copy: 1, .. })));
+    assert!(events.iter().any(|e| matches!(e, TraceEvent::Blocks { sizes } if !sizes.is_empty())));
+
+    let bits: u32 = events
+        .iter()
+        .filter_map(|e| match e {
+            TraceEvent::Correction { bits, .. } => Some(*bits),
+            _ => None,
+        })
+        .sum();
+    assert_eq!(3, bits);
+
+    // Events do not carry over between traces
+    let (_, trace) = decoder.decode_with_trace(&img);
+    assert_eq!(events.len(), trace.codes[0].events.len());
+}
+
+#[test]
+pub fn test_decode_with_metrics() {
+    let img = image::open("tests/images/needs_alignment.jpg").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+
+    let (results, metrics) = decoder.decode_with_metrics(&img);
+
+    assert_eq!(1, results.len());
+    assert!(results[0].is_ok());
+
+    let (width, height) = img.to_luma8().dimensions();
+    let pixels = u64::from(width) * u64::from(height);
+    assert!(metrics.prepare_pixels >= 2 * pixels);
+    assert!(metrics.detect_pixels >= pixels);
+    assert!(metrics.extract_pixels > 0);
+    assert!(metrics.finder_candidates >= 3);
+    assert!(metrics.finder_groups >= 1);
+
+    // The code has errors, but the format read is the right one
+    assert!(metrics.rs_iterations > 0);
+    assert_eq!(metrics.retries, 0);
+
+    // The counts are the same on every run, and a plain decode does not add to them
+    decoder.decode(&img);
+    assert_eq!(metrics, decoder.decode_with_metrics(&img).1);
+}
+
+#[test]
+pub fn test_decode_with_quality() {
+    let img = image::open("tests/images/version1_example.jpg").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+
+    let results = decoder.decode_with_quality(&img);
+
+    assert_eq!(1, results.len());
+    let (content, quality) = results.into_iter().next().unwrap().expect("Code should decode");
+
+    assert_eq!("01234567", content);
+    assert!(quality.symbol_contrast > 0.5);
+    assert!(quality.modulation > 0.5 && quality.modulation <= 1.0);
+    assert!(quality.reflectance_margin > 0.0);
+
+    let img = image::open("tests/images/needs_alignment.jpg").expect("Failed to open test image");
+    let (_, quality) = decoder.decode_with_quality(&img).remove(0).expect("Code should decode");
+
+    // Some of the modules fixed by error correction are on the wrong side of the threshold
+    assert!(quality.reflectance_margin < 0.0);
+}
+
+#[cfg(feature = "encode")]
+#[test]
+pub fn test_decode_with_quality_quiet_zone() {
+    use bardecoder::encode::QREncoder;
+    use bardecoder::util::{LumaImage, LumaSource};
+
+    let code = QREncoder::new().encode("quiet zone").unwrap();
+    let decoder = bardecoder::default_luma_decoder();
+    let quiet_zone = |img: &LumaImage| decoder.decode_with_quality(img).remove(0).expect("Code should decode").1;
+
+    let quality = quiet_zone(&code.to_luma_image(5, 6));
+    assert_eq!((quality.quiet_zone, quality.quiet_zone_violated()), (4, false));
+
+    // Cut off by the edge of the image
+    assert_eq!(2, quiet_zone(&code.to_luma_image(5, 2)).quiet_zone);
+
+    // A line 3 modules to the left of the code, as of a frame printed around it
+    let framed = code.to_luma_image(5, 6);
+    let (width, height) = framed.luma_dimensions();
+    let framed = LumaImage::from_fn(width, height, |x, y| if x / 5 == 3 { 0 } else { framed.luma(x, y) });
+    let quality = quiet_zone(&framed);
+    assert_eq!((quality.quiet_zone, quality.quiet_zone_violated()), (2, true));
+}
+
+#[cfg(feature = "encode")]
+#[test]
+pub fn test_decode_with_dpi() {
+    use bardecoder::encode::QREncoder;
+    use bardecoder::DecodeHints;
+
+    // Modules of 6 pixels at 600 DPI are 0.254 mm, a version 1 code is 21 of them
+    let img = QREncoder::new().version(1).encode("label").unwrap().to_luma_image(6, 4);
+    let decoder = bardecoder::default_luma_decoder_with_info();
+
+    assert_eq!(decoder.decode(&img).remove(0).unwrap().1.physical_size, None);
+
+    let mut hints = DecodeHints::new();
+    hints.dpi(600.0);
+    let size = decoder.decode_with_hints(&img, &hints).remove(0).unwrap().1.physical_size.unwrap();
+    assert!((size.module_mm - 0.254).abs() < 0.005, "{:?}", size);
+    assert!((size.symbol_mm - 21.0 * 0.254).abs() < 0.1, "{:?}", size);
+}
+
+#[test]
+pub fn test_decode_at() {
+    use bardecoder::detect::{Detect, LineScan, Location};
+
+    let img = image::open("tests/images/multiple_codes.png").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+
+    let locations = LineScan::new().detect(&decoder.prepare_only(&img));
+    assert_eq!(2, locations.len());
+
+    // Detection order is not the order of the results, which are sorted by position
+    let expected = decoder.decode(&img);
+    for location in locations {
+        let Location::QR(location) = location;
+        assert!(expected.contains(&decoder.decode_at(&img, location)));
+    }
+}
+
+#[test]
+pub fn test_decode_with_hints() {
+    use bardecoder::DecodeHints;
+
+    let img = image::open("tests/images/version3_example.jpg").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+
+    let mut hints = DecodeHints::new();
+    hints.expected_version(3).expected_ec_level(ECLevel::HIGH);
+    assert_eq!(decoder.decode(&img), decoder.decode_with_hints(&img, &hints));
+
+    // The grid of another version does not fit
+    hints.expected_version(4);
+    assert!(decoder.decode_with_hints(&img, &hints)[0].is_err());
+}
+
+#[test]
+pub fn test_detect_only() {
+    let img = image::open("tests/images/multiple_codes.png").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+
+    assert_eq!(decoder.count(&img), 2);
+    let centers = |locations: Vec<bardecoder::util::qr::QRLocation>| -> Vec<(f64, f64)> {
+        locations.iter().map(|qrloc| qrloc.center()).map(|c| (c.x, c.y)).collect()
+    };
+    assert_eq!(centers(decoder.detect_only(&img)), centers(decoder.detect_with_format(&img)));
+
+    // Without the middle of the code its data can not be corrected, it is still there
+    let mut img = image::open("tests/images/version3_example.jpg").expect("Failed to open test image").to_luma8();
+    let location = decoder.detect_only(&image::DynamicImage::ImageLuma8(img.clone())).remove(0);
+    let center = location.center();
+    let half = 6.0 * location.module_size;
+    for y in (center.y - half) as u32..(center.y + half) as u32 {
+        for x in (center.x - half) as u32..(center.x + half) as u32 {
+            img.put_pixel(x, y, image::Luma([255]));
+        }
+    }
+    let img = image::DynamicImage::ImageLuma8(img);
+
+    assert!(decoder.decode(&img)[0].is_err());
+    assert_eq!(decoder.count(&img), 1);
+    assert_eq!(decoder.detect_with_format(&img).len(), 1);
+
+    let blank = image::DynamicImage::ImageLuma8(image::GrayImage::from_pixel(100, 100, image::Luma([255])));
+    assert_eq!(decoder.count(&blank), 0);
+}
+
+#[test]
+pub fn test_builder_limits() {
+    let img = image::open("tests/images/version3_example.jpg").expect("Failed to open test image");
+    let decode = |min_module_size: f64, max_version: u32| {
+        let mut builder = bardecoder::default_builder();
+        builder.min_module_size(min_module_size).max_version(max_version);
+        builder.build().expect("Default builder should build").decode(&img)
+    };
+
+    assert_eq!(1, decode(2.0, 3).len());
+    assert!(decode(2.0, 2).is_empty());
+    assert!(decode(100.0, 40).is_empty());
+}
+
+#[test]
+pub fn test_resource_limits() {
+    let bytes = std::fs::read("tests/images/multiple_codes.png").expect("Failed to read test image");
+    let img = image::load_from_memory(&bytes).expect("Failed to load test image");
+    let (width, height) = img.to_luma8().dimensions();
+    let pixels = u64::from(width) * u64::from(height);
+
+    let mut builder = bardecoder::default_builder();
+    builder.max_pixels(pixels - 1);
+    let error = builder.build().unwrap().decode_bytes(&bytes).unwrap_err();
+    assert!(error.is_resource_limit());
+
+    let mut builder = bardecoder::default_builder();
+    builder.max_pixels(pixels).max_codes(1);
+    let results = builder.build().unwrap().decode_bytes(&bytes).unwrap();
+
+    assert_eq!(2, results.len());
+    assert!(results[0].is_ok());
+    assert!(results[1].as_ref().unwrap_err().is_resource_limit());
+    assert!(!QRError::new("other").is_resource_limit());
+}
+
+#[test]
+pub fn test_decode_with_time_budget() {
+    use std::time::Duration;
+
+    let img = image::open("tests/images/multiple_codes.png").expect("Failed to open test image");
+    let decode = |budget: Duration| {
+        let mut builder = bardecoder::default_builder();
+        builder.time_budget(budget);
+        builder.build().unwrap().decode(&img)
+    };
+
+    // With time for every code the results are the same as without a budget
+    assert_eq!(decode(Duration::from_secs(600)), bardecoder::default_decoder().decode(&img));
+
+    let results = decode(Duration::ZERO);
+    assert_eq!(results.len(), 2);
+    for result in results {
+        let error = result.unwrap_err();
+        assert!(error.is_resource_limit(), "{}", error);
+        assert!(error.msg.ends_with("time budget of 0ns used up"), "{}", error);
+    }
+}
+
+#[test]
+pub fn test_min_dynamic_range() {
+    use bardecoder::util::qr::ErrorCode;
+    use bardecoder::Stage;
+    use image::{DynamicImage, GrayImage, Luma};
+
+    let mut builder = bardecoder::default_builder();
+    builder.min_dynamic_range(32);
+    let decoder = builder.build().unwrap();
+
+    let img = image::open("tests/images/version1_example.jpg").unwrap();
+    assert_eq!(decoder.decode(&img), vec![Ok(String::from("01234567"))]);
+
+    // A blank sheet of paper, with a little noise
+    let blank = DynamicImage::ImageLuma8(GrayImage::from_fn(200, 150, |x, y| Luma([200 + ((x ^ y) % 7) as u8])));
+    let results = decoder.decode(&blank);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].as_ref().unwrap_err().code(), ErrorCode::NoContrast);
+
+    let located = decoder.decode_with_error_locations(&blank);
+    assert_eq!(located[0].as_ref().err().map(|e| (e.stage, e.location.is_none())), Some((Stage::Prepare, true)));
+
+    // Without a minimum it decodes to nothing, as before
+    assert!(bardecoder::default_decoder().decode(&blank).is_empty());
+}
+
+// The decoder with a minimum dynamic range of 32, and a blank sheet of paper below it
+fn low_contrast() -> (bardecoder::Decoder<image::DynamicImage, image::GrayImage, String>, image::DynamicImage) {
+    use image::{DynamicImage, GrayImage, Luma};
+
+    let mut builder = bardecoder::default_builder();
+    builder.min_dynamic_range(32);
+
+    let blank = DynamicImage::ImageLuma8(GrayImage::from_fn(200, 150, |x, y| Luma([200 + ((x ^ y) % 7) as u8])));
+    (builder.build().unwrap(), blank)
+}
+
+fn no_contrast<T>(results: &[Result<T, QRError>]) -> bool {
+    use bardecoder::util::qr::ErrorCode;
+
+    matches!(results, [Err(e)] if e.code() == ErrorCode::NoContrast)
+}
+
+#[test]
+pub fn test_min_dynamic_range_decode_at() {
+    use bardecoder::util::qr::{ErrorCode, QRLocation};
+    use bardecoder::util::Point;
+
+    let (decoder, blank) = low_contrast();
+    let location = QRLocation {
+        top_left: Point { x: 50.0, y: 30.0 },
+        top_right: Point { x: 140.0, y: 30.0 },
+        bottom_left: Point { x: 50.0, y: 120.0 },
+        module_size: 6.0,
+        version: 1,
+    };
+
+    let error = decoder.decode_at(&blank, location).unwrap_err();
+    assert_eq!(error.code(), ErrorCode::NoContrast);
+}
+
+#[test]
+pub fn test_min_dynamic_range_decode_with_trace() {
+    let (decoder, blank) = low_contrast();
+
+    let (results, trace) = decoder.decode_with_trace(&blank);
+    assert!(no_contrast(&results));
+    assert!(trace.codes.is_empty());
+}
+
+#[test]
+pub fn test_min_dynamic_range_detect_only() {
+    let (decoder, blank) = low_contrast();
+    assert!(decoder.detect_only(&blank).is_empty());
+    assert!(decoder.detect_with_format(&blank).is_empty());
+    assert_eq!(decoder.count(&blank), 0);
+
+    // Images with contrast are detected as usual
+    let img = image::open("tests/images/version1_example.jpg").unwrap();
+    assert_eq!(decoder.count(&img), 1);
+    assert_eq!(decoder.detect_with_format(&img).len(), 1);
+}
+
+#[test]
+pub fn test_min_dynamic_range_contains() {
+    let (decoder, blank) = low_contrast();
+    assert!(!decoder.contains(&blank, "01234567"));
+
+    let img = image::open("tests/images/version1_example.jpg").unwrap();
+    assert!(decoder.contains(&img, "01234567"));
+}
+
+#[test]
+pub fn test_min_dynamic_range_decode_with_quality() {
+    let (decoder, blank) = low_contrast();
+    assert!(no_contrast(&decoder.decode_with_quality(&blank)));
+}
+
+#[test]
+pub fn test_min_dynamic_range_decode_with_guidance() {
+    let (decoder, blank) = low_contrast();
+
+    // The guidance is measured all the same, it is what tells the user to point the camera at the code
+    let (results, guidance) = decoder.decode_with_guidance(&blank);
+    assert!(no_contrast(&results));
+    assert!(guidance.exposure > 0.5);
+    assert!(guidance.finders.is_empty());
+}
+
+#[test]
+pub fn test_decode_with_resource_limits() {
+    use bardecoder::util::limits::ResourceLimits;
+
+    let decode = |limits: ResourceLimits, path: &str| {
+        let mut builder = bardecoder::default_builder();
+        builder.limits(limits);
+        builder.build().unwrap().decode(&image::open(path).unwrap())
+    };
+    let limit = |results: &[Result<String, QRError>], i: usize| -> String {
+        let error = results[i].as_ref().unwrap_err();
+        assert!(error.is_resource_limit(), "{}", error);
+        error.msg.clone()
+    };
+
+    // A single finder pattern is not a code, detection stops before finding the other two
+    let candidates = ResourceLimits {
+        max_candidates: 1,
+        ..ResourceLimits::default()
+    };
+    let results = decode(candidates, "tests/images/version1_example.jpg");
+    assert_eq!(results.len(), 1);
+    assert!(limit(&results, 0).ends_with("more than the maximum of 1 finder patterns"));
+
+    let extractions = ResourceLimits {
+        max_extractions: 1,
+        ..ResourceLimits::default()
+    };
+    let results = decode(extractions, "tests/images/multiple_codes.png");
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    assert!(limit(&results, 1).ends_with("1 more codes detected than the maximum of 1"));
+
+    // This code has errors to correct
+    let iterations = ResourceLimits {
+        max_rs_iterations: 1,
+        ..ResourceLimits::default()
+    };
+    let results = decode(iterations, "tests/images/needs_alignment.jpg");
+    assert_eq!(results.len(), 1);
+    assert!(limit(&results, 0).ends_with("more than the maximum of 1 Reed-Solomon iterations"));
+
+    let results = decode(ResourceLimits::UNLIMITED, "tests/images/needs_alignment.jpg");
+    assert!(results[0].is_ok());
+
+    // Every code has pixels of its own to read, the first code reads about 900 and the second about 5100
+    let pixels = ResourceLimits {
+        max_extract_pixels: 6000,
+        ..ResourceLimits::default()
+    };
+    let results = decode(pixels, "tests/images/multiple_codes.png");
+    assert!(results.iter().all(Result::is_ok));
+
+    // Running out fails only the code that did
+    let pixels = ResourceLimits {
+        max_extract_pixels: 3000,
+        ..ResourceLimits::default()
+    };
+    let results = decode(pixels, "tests/images/multiple_codes.png");
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    assert!(limit(&results, 1).ends_with("more than the maximum of 3000 pixels read extracting the code"));
+
+    // 400 finder patterns of 3 pixels per module in rows and columns, every group of three at right angles is a code
+    let finders = image::GrayImage::from_fn(600, 600, |x, y| {
+        let (mx, my) = ((x / 3 % 10) as i32, (y / 3 % 10) as i32);
+        let ring = (mx - 3).abs().max((my - 3).abs());
+        image::Luma([if mx < 7 && my < 7 && ring != 2 { 0 } else { 255 }])
+    });
+    let mut builder = bardecoder::default_builder();
+    builder.limits(ResourceLimits {
+        max_extractions: 0,
+        ..ResourceLimits::default()
+    });
+    let results = builder.build().unwrap().decode(&image::DynamicImage::ImageLuma8(finders));
+    assert_eq!(results.len(), 2);
+    assert!(limit(&results, 0).ends_with("more than the maximum of 256 finder patterns"));
+    assert!(limit(&results, 1).ends_with("more codes detected than the maximum of 0"));
+}
+
+#[test]
+pub fn test_builder_with_stage() {
+    use bardecoder::detect::LineScan;
+    use bardecoder::prepare::BlockedMean;
+
+    let img = image::open("tests/images/version3_example.jpg").expect("Failed to open test image");
+
+    let decoder = bardecoder::default_builder()
+        .with_prepare(Box::new(BlockedMean::new(7, 9)))
+        .with_detect(Box::new(LineScan::new()))
+        .build()
+        .expect("Default builder with replaced stages should build");
+
+    let result = decoder.decode(&img);
+    assert_eq!(1, result.len());
+    assert_eq!("https://payapp.weixin.qq.com/olspree?code_type=2", result[0].as_ref().unwrap());
+}
+
+#[test]
+pub fn test_contains() {
+    let img = image::open("tests/images/multiple_codes.png").expect("Failed to open test image");
+    let decoder = bardecoder::default_decoder();
+
+    assert!(decoder.contains(&img, "Ver1"));
+    assert!(decoder.contains(&img, "http://www.prolinepetfood.com/1/"));
+    assert!(!decoder.contains(&img, "Ver2"));
+    assert!(!decoder.contains(&img, "Ver"));
+}
+
+#[test]
+pub fn test_observer() {
+    use std::sync::{Arc, Mutex};
+
+    use bardecoder::util::observer::{PipelineObserver, Rejection};
+    use bardecoder::util::qr::{QRData, QRLocation};
+
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl Recorder {
+        fn record(&self, event: String) {
+            self.0.lock().unwrap().push(event);
+        }
     }
+
+    impl PipelineObserver<image::GrayImage, String> for Recorder {
+        fn on_prepared(&self, prepared: &image::GrayImage) {
+            self.record(format!("prepared {}x{}", prepared.width(), prepared.height()));
+        }
+
+        fn on_candidate(&self, location: &QRLocation) {
+            self.record(format!("candidate {}", location.version));
+        }
+
+        fn on_rejected(&self, location: &QRLocation, reason: Rejection) {
+            self.record(format!("rejected {} {:?}", location.version, reason));
+        }
+
+        fn on_extracted(&self, data: &QRData) {
+            self.record(format!("extracted {}", data.version));
+        }
+
+        fn on_decoded(&self, result: &String) {
+            self.record(format!("decoded {result}"));
+        }
+    }
+
+    let img = image::open("tests/images/multiple_codes.png").expect("Failed to open test image");
+    let events = Arc::new(Mutex::new(vec![]));
+
+    let mut builder = bardecoder::default_builder();
+    builder.max_codes(1).observer(Box::new(Recorder(Arc::clone(&events))));
+    let decoder = builder.build().unwrap();
+
+    assert_eq!(2, decoder.decode(&img).len());
+
+    let events = events.lock().unwrap();
+    let gray = img.to_luma8();
+    assert_eq!(events[0], format!("prepared {}x{}", gray.width(), gray.height()));
+    assert_eq!(
+        events[1..].to_vec(),
+        vec!["candidate 3", "candidate 1", "rejected 3 TooManyCodes", "extracted 1", "decoded Ver1"]
+    );
+}
+
+#[test]
+pub fn test_scanlines() {
+    // Two codes above each other, the top one should be decoded long before the last row arrives
+    let top = image::open("tests/images/version1_example.jpg").expect("Failed to open test image").to_luma8();
+    let bottom = image::open("tests/images/version3_example2.jpg").expect("Failed to open test image").to_luma8();
+    let width = top.width().max(bottom.width());
+    let rows = top.rows().chain(bottom.rows()).map(|row| {
+        let mut row: Vec<u8> = row.map(|p| p[0]).collect();
+        row.resize(width as usize, 255);
+        row
+    });
+
+    let decoder = bardecoder::default_luma_decoder();
+    let mut scanlines = decoder.scanlines(width);
+    scanlines.window(400);
+    let mut decoded = vec![];
+
+    for (y, row) in rows.enumerate() {
+        for result in scanlines.push_row(&row).unwrap() {
+            decoded.push((y as u32, result.unwrap()));
+        }
+    }
+    assert!(scanlines.push_row(&[255; 10]).is_err());
+    let rest: Vec<String> = scanlines.finish().into_iter().map(Result::unwrap).collect();
+
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].1, "01234567");
+    assert!(decoded[0].0 < top.height());
+    assert_eq!(rest, vec!["http://www.prolinepetfood.com/1/"]);
+}
+
+#[test]
+pub fn test_decode_tiled() {
+    use bardecoder::util::LumaImage;
+
+    // Codes spread over a large scan, one of them across the boundary between the first tiles
+    let codes = [
+        ("tests/images/version1_example.jpg", 100, 100),
+        ("tests/images/version3_example2.jpg", 850, 150),
+        ("tests/images/multiple_codes.png", 400, 1200),
+    ];
+    let mut scan = image::GrayImage::from_pixel(2000, 1800, image::Luma([255]));
+    for (path, x, y) in &codes {
+        let code = image::open(path).expect("Failed to open test image").to_luma8();
+        image::imageops::replace(&mut scan, &code, *x, *y);
+    }
+    let scan = LumaImage::new(scan.width(), scan.height(), scan.into_raw()).unwrap();
+
+    let decoder = bardecoder::default_luma_decoder();
+    let tiled: Vec<String> = decoder.decode_tiled(&scan, 1024, 500).unwrap().into_iter().map(Result::unwrap).collect();
+
+    assert_eq!(
+        tiled,
+        vec!["01234567", "http://www.prolinepetfood.com/1/", "Ver1", "http://www.prolinepetfood.com/1/"]
+    );
+    assert!(decoder.decode_tiled(&scan, 500, 500).is_err());
+}
+
+#[test]
+pub fn test_decode_rescaled() {
+    use bardecoder::util::LumaImage;
+    use image::imageops::FilterType;
+    use image::GenericImageView;
+
+    let img = image::open("tests/images/version3_example.jpg").expect("Failed to open test image");
+    let (width, height) = (img.width(), img.height());
+    let decoder = bardecoder::default_decoder();
+
+    // Modules of 18 pixels are too large for the detector, at half the size they are not
+    let large = img.resize_exact(width * 2, height * 2, FilterType::Triangle);
+    assert!(decoder.decode(&large).is_empty());
+    let expected = vec![Ok(String::from("https://payapp.weixin.qq.com/olspree?code_type=2"))];
+    assert_eq!(decoder.decode_rescaled(&large), expected);
+
+    // Modules of less than two pixels take more than twice the size
+    let small = img.resize_exact(width / 5, height / 5, FilterType::Triangle).to_luma8();
+    let small = LumaImage::new(small.width(), small.height(), small.into_raw()).unwrap();
+    let decoder = bardecoder::default_luma_decoder();
+    assert!(decoder.decode_rescaled(&small).iter().all(Result::is_err));
+    assert_eq!(decoder.decode_with_scales(&small, &[2.0, 3.0]), expected);
+
+    // Without any scale that decodes, the results are those of the image as it is
+    assert_eq!(decoder.decode_with_scales(&small, &[0.0, 2.0]), decoder.decode(&small));
+}
+
+#[cfg(feature = "encode")]
+#[test]
+pub fn test_decode_screenshot() {
+    use bardecoder::encode::QREncoder;
+    use bardecoder::util::{LumaImage, LumaSource};
+    use bardecoder::{DecodeHints, Source};
+
+    // A code scaled by a fraction and anti-aliased from 4x4 samples per pixel, with moire on top
+    let screenshot = |version: u32, module: f64, moire: f64, period: f64| {
+        let code = QREncoder::new().version(version).encode("https://example.com/screen").unwrap();
+        let size = ((f64::from(code.side) + 8.0) * module).ceil() as u32;
+        let scaled = render(&code, size, 4, |px, py| (px / module - 4.0, py / module - 4.0));
+
+        LumaImage::from_fn(size, size, |x, y| {
+            let pattern = moire * (2.0 * std::f64::consts::PI * (f64::from(x) + 0.7 * f64::from(y)) / period).sin();
+            (f64::from(scaled.luma(x, y)) + pattern).clamp(0.0, 255.0) as u8
+        })
+    };
+
+    let decoder = bardecoder::default_luma_decoder();
+    let mut hints = DecodeHints::new();
+    hints.source(Source::Screen);
+
+    assert!(decoder.decode(&screenshot(4, 2.9, 90.0, 2.6)).iter().all(Result::is_err));
+
+    for img in [screenshot(4, 2.9, 90.0, 2.6), screenshot(7, 3.4, 0.0, 2.0)] {
+        assert_eq!(
+            vec![String::from("https://example.com/screen")],
+            decoder.decode_with_hints(&img, &hints).into_iter().map(Result::unwrap).collect::<Vec<_>>()
+        );
+    }
+}
+
+#[cfg(feature = "encode")]
+#[test]
+pub fn test_decode_color_gradient() {
+    use bardecoder::encode::QREncoder;
+    use bardecoder::prepare::BlockedMean;
+    use image::{DynamicImage, Rgb, RgbImage};
+
+    // Orange to red modules on teal to light blue from the center out, about as bright as each other, with some noise
+    let code = QREncoder::new().version(2).encode("HELLO GRADIENT").unwrap();
+    let size = (code.side + 8) * 4;
+    let img = RgbImage::from_fn(size, size, |x, y| {
+        let (fx, fy) = (f64::from(x) / f64::from(size) - 0.5, f64::from(y) / f64::from(size) - 0.5);
+        let t = ((fx * fx + fy * fy).sqrt() * 1.41).min(1.0);
+        let (mx, my) = ((x / 4).wrapping_sub(4), (y / 4).wrapping_sub(4));
+        let (from, to) = match mx < code.side && my < code.side && code.is_dark(mx, my) {
+            true => ([255.0, 120.0, 0.0], [220.0, 20.0, 60.0]),
+            false => ([0.0, 190.0, 170.0], [90.0, 210.0, 230.0]),
+        };
+
+        Rgb([0, 1, 2].map(|c| {
+            let noise = (x.wrapping_mul(73_856_093) ^ y.wrapping_mul(19_349_663) ^ (c as u32 * 83_492_791)) % 41;
+            (from[c] + (to[c] - from[c]) * t + f64::from(noise) - 20.0).clamp(0.0, 255.0) as u8
+        }))
+    });
+    let img = DynamicImage::ImageRgb8(img);
+
+    assert!(bardecoder::default_decoder().decode(&img).iter().all(Result::is_err));
+
+    let mut builder = bardecoder::default_builder();
+    builder.prepare(Box::new(BlockedMean::new(5, 7).with_channel_selection(true)));
+    let decoder = builder.build().unwrap();
+
+    assert_eq!(vec![Ok(String::from("HELLO GRADIENT"))], decoder.decode(&img));
+}
+
+#[cfg(feature = "encode")]
+#[test]
+pub fn test_decode_perspective() {
+    use bardecoder::encode::QREncoder;
+
+    // A code photographed at an angle, with its right edge `tilt` shorter than its left and further away, so the
+    // modules get smaller towards it. Anti-aliased from 3x3 samples per pixel
+    let tilted = |version: u32, tilt: f64| {
+        let code = QREncoder::new().version(version).encode("PERSPECTIVE").unwrap();
+        let side = f64::from(code.side) + 8.0;
+        let (size, g) = (side * 4.0, tilt / (1.0 - tilt));
+
+        render(&code, size as u32, 3, |px, py| {
+            let (a, b) = (px / size, py / size - 0.5);
+            let (u, w) = (a / (1.0 - g * a), 1.0 / (1.0 - g * a));
+            (u * side - 4.0, (0.5 + b * w) * side - 4.0)
+        })
+    };
+
+    let decoder = bardecoder::default_luma_decoder();
+    for (version, tilt) in [(8, 0.07), (10, 0.06), (15, 0.06), (25, 0.03)] {
+        let img = tilted(version, tilt);
+        assert_eq!(vec![Ok(String::from("PERSPECTIVE"))], decoder.decode(&img), "version {version}, tilt {tilt}");
+    }
+}
+
+#[cfg(feature = "encode")]
+#[test]
+pub fn test_decode_fractional_scale() {
+    use bardecoder::encode::QREncoder;
+
+    // A code scaled by a fraction and anti-aliased from 4x4 samples per pixel, so the distance between the finder
+    // patterns gives the wrong version
+    let scaled = |version: u32, module: f64| {
+        let code = QREncoder::new().version(version).encode("VERSION CHECK").unwrap();
+        let size = ((f64::from(code.side) + 8.0) * module).ceil() as u32;
+
+        render(&code, size, 4, |px, py| (px / module - 4.0, py / module - 4.0))
+    };
+
+    let decoder = bardecoder::default_luma_decoder();
+    for (version, module) in [(6, 3.7), (7, 2.6), (9, 3.4), (12, 2.9), (16, 3.7), (20, 2.6)] {
+        let img = scaled(version, module);
+        assert_eq!(
+            vec![Ok(String::from("VERSION CHECK"))],
+            decoder.decode(&img),
+            "version {}, module {}",
+            version,
+            module
+        );
+    }
+}
+
+#[cfg(feature = "encode")]
+#[test]
+pub fn test_decode_stretched() {
+    use bardecoder::decode::QRDecoder;
+    use bardecoder::encode::QREncoder;
+    use bardecoder::extract::QRExtractor;
+    use image::{DynamicImage, GrayImage};
+
+    // A code on warped paper, stretched unevenly but the same way in every row and column, by up to `amount` modules
+    // in the middle of the label. Anti-aliased from 3x3 samples per pixel
+    let stretched = |version: u32, amount: f64| {
+        let code = QREncoder::new().version(version).encode("STRETCHED").unwrap();
+        let side = f64::from(code.side) + 8.0;
+        let size = side * 4.0;
+        let module = |pixel: f64| pixel / size * side + amount * (2.0 * std::f64::consts::PI * pixel / size).sin() - 4.0;
+
+        let img = render(&code, size as u32, 3, |px, py| (module(px), module(py)));
+        DynamicImage::ImageLuma8(GrayImage::from_raw(size as u32, size as u32, img.into_raw()).unwrap())
+    };
+
+    let mut builder = bardecoder::default_builder();
+    builder.qr(Box::new(QRExtractor::new().with_timing_refinement(true)), Box::new(QRDecoder::new()));
+    let refined = builder.build().unwrap();
+
+    for (version, amount) in [(8, 1.0), (12, 1.0), (20, 1.5)] {
+        let img = stretched(version, amount);
+        assert!(bardecoder::default_decoder().decode(&img).iter().all(Result::is_err));
+        assert_eq!(vec![Ok(String::from("STRETCHED"))], refined.decode(&img), "version {}, amount {}", version, amount);
+    }
+}
+
+#[test]
+pub fn test_decode_with_guidance() {
+    use bardecoder::util::guidance::ScanAdvice;
+    use image::imageops::{colorops, FilterType};
+    use image::{DynamicImage, GenericImageView};
+
+    let decoder = bardecoder::default_decoder();
+
+    let img = image::open("tests/images/version1_example.jpg").unwrap();
+    let (results, guidance) = decoder.decode_with_guidance(&img);
+    assert_eq!(results, vec![Ok(String::from("01234567"))]);
+    assert!(guidance.exposure > 0.5 && guidance.exposure < 0.7, "{:?}", guidance);
+    assert!(guidance.sharpness.unwrap() > 0.7, "{:?}", guidance);
+    assert!(guidance.finders.iter().filter(|finder| finder.module_size > 6.0).count() >= 3);
+    assert_eq!(guidance.advice(), None);
+
+    // Out of focus
+    let (results, guidance) = decoder.decode_with_guidance(&img.blur(5.0));
+    assert!(results.is_empty());
+    assert_eq!(guidance.advice(), Some(ScanAdvice::HoldStill));
+
+    // Underexposed, the advice is there whether or not the code still decodes
+    let dark = DynamicImage::ImageLuma8(colorops::brighten(&img.to_luma8(), -150));
+    let (_, guidance) = decoder.decode_with_guidance(&dark);
+    assert_eq!(guidance.advice(), Some(ScanAdvice::AddLight));
+
+    // Too far away, some of the finder patterns are still seen
+    let far = img.resize(img.width() / 4, img.height() / 4, FilterType::Triangle);
+    let (results, guidance) = decoder.decode_with_guidance(&far);
+    assert!(results.is_empty());
+    assert!(!guidance.finders.is_empty());
+    assert_eq!(guidance.advice(), Some(ScanAdvice::MoveCloser));
 }
@@ -4,12 +4,7 @@ use bardecoder::util::qr::{ECLevel, QRInfo};
 fn test_qr_version_bounds() {
     // Property: QR version must be between 1 and 40
     for version in 1..=40 {
-        let info = QRInfo {
-            version,
-            ec_level: ECLevel::MEDIUM,
-            total_data: 100,
-            errors: 0,
-        };
+        let info = QRInfo::new(version, ECLevel::MEDIUM, 0, 100, 0);
         assert!(info.version >= 1 && info.version <= 40);
     }
 }
@@ -45,12 +40,7 @@ fn test_error_count_never_exceeds_total_data() {
     // Property: Error count should never exceed total data bits
     for errors in 0..1000 {
         for total_data in errors..errors + 1000 {
-            let info = QRInfo {
-                version: 1,
-                ec_level: ECLevel::MEDIUM,
-                total_data,
-                errors,
-            };
+            let info = QRInfo::new(1, ECLevel::MEDIUM, 0, total_data, errors);
             
             assert!(
                 info.errors <= info.total_data,
@@ -73,12 +63,7 @@ fn test_ec_level_all_variants() {
     ];
     
     for ec_level in ec_levels {
-        let info = QRInfo {
-            version: 1,
-            ec_level,
-            total_data: 100,
-            errors: 0,
-        };
+        let info = QRInfo::new(1, ec_level, 0, 100, 0);
         
         // Just verify construction doesn't panic
         match info.ec_level {
@@ -109,12 +94,7 @@ fn test_total_data_bits_by_version() {
     
     for (version, max_bits) in version_max_bits {
         for total_data in 1..=max_bits * 2 {
-            let info = QRInfo {
-                version,
-                ec_level: ECLevel::LOW,
-                total_data,
-                errors: 0,
-            };
+            let info = QRInfo::new(version, ECLevel::LOW, 0, total_data, 0);
             
             // Total data includes both data and EC codewords
             // So it can be up to ~2x the data capacity
@@ -131,26 +111,11 @@ fn test_total_data_bits_by_version() {
 fn test_qr_info_equality_properties() {
     // Property: Equality should be reflexive, symmetric, and transitive
     
-    let info1 = QRInfo {
-        version: 5,
-        ec_level: ECLevel::HIGH,
-        total_data: 1000,
-        errors: 10,
-    };
+    let info1 = QRInfo::new(5, ECLevel::HIGH, 0, 1000, 10);
     
-    let info2 = QRInfo {
-        version: 5,
-        ec_level: ECLevel::HIGH,
-        total_data: 1000,
-        errors: 10,
-    };
+    let info2 = QRInfo::new(5, ECLevel::HIGH, 0, 1000, 10);
     
-    let info3 = QRInfo {
-        version: 5,
-        ec_level: ECLevel::HIGH,
-        total_data: 1000,
-        errors: 10,
-    };
+    let info3 = QRInfo::new(5, ECLevel::HIGH, 0, 1000, 10);
     
     // Reflexive: a == a
     assert_eq!(info1, info1);
@@ -169,47 +134,22 @@ fn test_qr_info_equality_properties() {
 fn test_qr_info_inequality_on_different_fields() {
     // Property: Changing any field should make QRInfo unequal
     
-    let base = QRInfo {
-        version: 5,
-        ec_level: ECLevel::MEDIUM,
-        total_data: 1000,
-        errors: 10,
-    };
+    let base = QRInfo::new(5, ECLevel::MEDIUM, 0, 1000, 10);
     
     // Different version
-    let diff_version = QRInfo {
-        version: 6,
-        ec_level: ECLevel::MEDIUM,
-        total_data: 1000,
-        errors: 10,
-    };
+    let diff_version = QRInfo::new(6, ECLevel::MEDIUM, 0, 1000, 10);
     assert_ne!(base, diff_version);
     
     // Different EC level
-    let diff_ec = QRInfo {
-        version: 5,
-        ec_level: ECLevel::HIGH,
-        total_data: 1000,
-        errors: 10,
-    };
+    let diff_ec = QRInfo::new(5, ECLevel::HIGH, 0, 1000, 10);
     assert_ne!(base, diff_ec);
     
     // Different total_data
-    let diff_data = QRInfo {
-        version: 5,
-        ec_level: ECLevel::MEDIUM,
-        total_data: 1001,
-        errors: 10,
-    };
+    let diff_data = QRInfo::new(5, ECLevel::MEDIUM, 0, 1001, 10);
     assert_ne!(base, diff_data);
     
     // Different errors
-    let diff_errors = QRInfo {
-        version: 5,
-        ec_level: ECLevel::MEDIUM,
-        total_data: 1000,
-        errors: 11,
-    };
+    let diff_errors = QRInfo::new(5, ECLevel::MEDIUM, 0, 1000, 11);
     assert_ne!(base, diff_errors);
 }
 
@@ -227,12 +167,7 @@ fn test_error_correction_capability_by_ec_level() {
     ];
     
     for (ec_level, _capability_percent) in ec_capabilities {
-        let info = QRInfo {
-            version: 10,
-            ec_level,
-            total_data: 1000,
-            errors: 50,
-        };
+        let info = QRInfo::new(10, ec_level, 0, 1000, 50);
         
         // Just verify we can create QRInfo with different EC levels
         match info.ec_level {
@@ -249,12 +184,7 @@ fn test_version_to_size_mapping() {
     for version in 1..=40 {
         let expected_size = 4 * version + 17;
         
-        let info = QRInfo {
-            version,
-            ec_level: ECLevel::MEDIUM,
-            total_data: 100,
-            errors: 0,
-        };
+        let info = QRInfo::new(version, ECLevel::MEDIUM, 0, 100, 0);
         
         // Verify the version is stored correctly
         assert_eq!(info.version, version);
@@ -268,12 +198,7 @@ fn test_version_to_size_mapping() {
 #[test]
 fn test_debug_trait_implementation() {
     // Property: QRInfo should have a Debug implementation for diagnostics
-    let info = QRInfo {
-        version: 7,
-        ec_level: ECLevel::QUARTILE,
-        total_data: 512,
-        errors: 3,
-    };
+    let info = QRInfo::new(7, ECLevel::QUARTILE, 0, 512, 3);
     
     let debug_str = format!("{:?}", info);
     assert!(debug_str.contains("version"));